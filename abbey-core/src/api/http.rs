@@ -0,0 +1,245 @@
+//! Just enough HTTP/1.1 for the API: one request per connection, bodies sized
+//! by `Content-Length`, and responses that close the connection
+
+use serde::Serialize;
+use std::io::{self, BufRead, Read, Write};
+
+/// Largest request body accepted, comfortably above any composition
+pub const MAX_BODY_BYTES: usize = 8 * 1024 * 1024;
+
+/// Largest request line plus headers accepted
+const MAX_HEAD_BYTES: usize = 64 * 1024;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Request {
+    pub method: String,
+    /// The path without its query string, e.g. `/api/compositions`
+    pub path: String,
+    /// Decoded query parameters in the order given
+    pub query: Vec<(String, String)>,
+    /// Header names are lowercased
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    /// A request for `target`, a path with an optional query string
+    pub fn new(method: &str, target: &str) -> Self {
+        let (path, query) = match target.split_once('?') {
+            Some((path, query)) => (path, parse_query(query)),
+            None => (target, Vec::new()),
+        };
+
+        Self {
+            method: method.to_uppercase(),
+            path: path.to_string(),
+            query,
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_lowercase(), value.to_string()));
+        self
+    }
+
+    pub fn with_body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn query(&self, name: &str) -> Option<&str> {
+        self.query.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+
+    /// The path split on `/`, ignoring leading, trailing and doubled slashes
+    pub fn segments(&self) -> Vec<&str> {
+        self.path.split('/').filter(|s| !s.is_empty()).collect()
+    }
+
+    /// Read a request from a connection. A malformed request comes back as the
+    /// error response to send.
+    pub fn read_from(reader: &mut impl BufRead) -> Result<Self, Response> {
+        let mut head_budget = MAX_HEAD_BYTES;
+        let request_line = read_head_line(reader, &mut head_budget)?;
+        let mut parts = request_line.split(' ');
+        let (method, target) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(method), Some(target), Some(version)) if version.starts_with("HTTP/1.") => (method, target),
+            _ => return Err(Response::error(400, "malformed request line")),
+        };
+        let mut request = Self::new(method, target);
+
+        loop {
+            let line = read_head_line(reader, &mut head_budget)?;
+            if line.is_empty() {
+                break;
+            }
+            match line.split_once(':') {
+                Some((name, value)) => request.headers.push((name.trim().to_lowercase(), value.trim().to_string())),
+                None => return Err(Response::error(400, "malformed header")),
+            }
+        }
+
+        if request.header("transfer-encoding").is_some() {
+            return Err(Response::error(411, "send the body with a Content-Length"));
+        }
+
+        let length = match request.header("content-length") {
+            Some(length) => length
+                .parse::<usize>()
+                .map_err(|_| Response::error(400, "malformed Content-Length"))?,
+            None => 0,
+        };
+        if length > MAX_BODY_BYTES {
+            return Err(Response::error(413, "the request body is too large"));
+        }
+
+        let mut body = vec![0; length];
+        reader
+            .read_exact(&mut body)
+            .map_err(|_| Response::error(400, "the request body ended early"))?;
+        request.body = body;
+        Ok(request)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    /// Extra headers besides the content type and length
+    pub headers: Vec<(String, String)>,
+    pub content_type: String,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn new(status: u16, content_type: &str, body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            content_type: content_type.to_string(),
+            body: body.into(),
+        }
+    }
+
+    pub fn json<T: Serialize + ?Sized>(status: u16, value: &T) -> Self {
+        match serde_json::to_vec_pretty(value) {
+            Ok(body) => Self::new(status, "application/json", body),
+            Err(e) => Self::error(500, &format!("could not encode the response: {}", e)),
+        }
+    }
+
+    /// An error as `{"error": message}`
+    pub fn error(status: u16, message: &str) -> Self {
+        Self::json(status, &serde_json::json!({ "error": message }))
+    }
+
+    pub fn no_content() -> Self {
+        Self::new(204, "", Vec::new())
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn write_to(&self, out: &mut impl Write) -> io::Result<()> {
+        let mut head = format!("HTTP/1.1 {} {}\r\n", self.status, reason(self.status));
+        if !self.content_type.is_empty() {
+            head.push_str(&format!("Content-Type: {}\r\n", self.content_type));
+        }
+        head.push_str(&format!("Content-Length: {}\r\n", self.body.len()));
+        for (name, value) in &self.headers {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        head.push_str("Connection: close\r\n\r\n");
+
+        out.write_all(head.as_bytes())?;
+        out.write_all(&self.body)?;
+        out.flush()
+    }
+}
+
+pub fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        411 => "Length Required",
+        413 => "Payload Too Large",
+        431 => "Request Header Fields Too Large",
+        503 => "Service Unavailable",
+        _ if status >= 500 => "Internal Server Error",
+        _ => "",
+    }
+}
+
+/// One line of the request head, counting it against what's left of the budget
+fn read_head_line(reader: &mut impl BufRead, budget: &mut usize) -> Result<String, Response> {
+    let mut line = String::new();
+    let read = reader
+        .take(*budget as u64)
+        .read_line(&mut line)
+        .map_err(|_| Response::error(400, "the request could not be read"))?;
+    *budget -= read;
+
+    if !line.ends_with('\n') {
+        return Err(if *budget == 0 {
+            Response::error(431, "the request headers are too large")
+        } else {
+            Response::error(400, "the request ended early")
+        });
+    }
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+fn parse_query(query: &str) -> Vec<(String, String)> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(name), percent_decode(value))
+        })
+        .collect()
+}
+
+/// Decode `%XX` escapes and `+` as a space, keeping malformed escapes as they are
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+                match hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                    Some(byte) => {
+                        decoded.push(byte);
+                        i += 2;
+                    }
+                    None => decoded.push(b'%'),
+                }
+            }
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
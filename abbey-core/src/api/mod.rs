@@ -0,0 +1,63 @@
+//! An opt-in HTTP API on localhost, so editor plugins and scripts can work with
+//! the library while Abbey (or `abbey serve`) is running
+
+mod http;
+mod routes;
+mod server;
+
+pub use http::{Request, Response, MAX_BODY_BYTES};
+pub use routes::{handle, Reply};
+pub use server::{EventHub, Server, EVENTS_PATH};
+
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    Composition,
+    Note,
+    Folder,
+    Project,
+    Flow,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    Created,
+    Updated,
+    Deleted,
+}
+
+/// Something in the library that changed, as sent on the event stream
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Change {
+    pub kind: Kind,
+    pub action: Action,
+    pub id: String,
+    /// The composition a note belongs to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub composition_id: Option<String>,
+}
+
+impl Change {
+    pub fn new(kind: Kind, action: Action, id: &str) -> Self {
+        Self {
+            kind,
+            action,
+            id: id.to_string(),
+            composition_id: None,
+        }
+    }
+
+    pub fn in_composition(mut self, composition_id: &str) -> Self {
+        self.composition_id = Some(composition_id.to_string());
+        self
+    }
+
+    /// The change as a server-sent event named `change`
+    pub fn to_event(&self) -> String {
+        let data = serde_json::to_string(self).unwrap_or_default();
+        format!("event: change\ndata: {}\n\n", data)
+    }
+}
@@ -0,0 +1,600 @@
+//! The API's endpoints, answered straight from a `Storage`
+
+use super::{Action, Change, Kind, Request, Response};
+use crate::cli;
+use crate::data::{Composition, Folder, Note, Project, Storage, StorageError};
+use crate::export::{self, ExportFormat};
+use crate::links;
+use crate::stats::{self, LibraryStats};
+use chrono::{DateTime, Local, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize};
+
+/// A response and the library changes it made
+pub type Reply = (Response, Vec<Change>);
+
+/// Answer an API request. Every change written to the library is returned
+/// alongside the response so it can be announced to event stream listeners.
+pub fn handle(storage: &Storage, request: &Request) -> Reply {
+    route(storage, request).unwrap_or_else(|e| (Response::error(e.status, &e.message), Vec::new()))
+}
+
+struct ApiError {
+    status: u16,
+    message: String,
+}
+
+impl ApiError {
+    fn new(status: u16, message: impl Into<String>) -> Self {
+        Self { status, message: message.into() }
+    }
+
+    fn not_found(kind: &str, id: &str) -> Self {
+        Self::new(404, format!("no {} with id \"{}\"", kind, id))
+    }
+}
+
+impl From<StorageError> for ApiError {
+    fn from(e: StorageError) -> Self {
+        let status = match e {
+            StorageError::ReadOnly | StorageError::Locked(_) => 409,
+            _ => 500,
+        };
+        Self::new(status, e.to_string())
+    }
+}
+
+type Result<T> = std::result::Result<T, ApiError>;
+
+fn route(storage: &Storage, request: &Request) -> Result<Reply> {
+    let segments = request.segments();
+    let method = request.method.as_str();
+
+    match (method, segments.as_slice()) {
+        ("GET", ["api"]) => index(storage),
+        ("GET", ["api", "stats"]) => library_stats(storage),
+
+        ("GET", ["api", "compositions"]) => list_compositions(storage, request),
+        ("POST", ["api", "compositions"]) => create_composition(storage, request),
+        ("GET", ["api", "compositions", id]) => {
+            let compositions = storage.load_compositions()?;
+            ok(Response::json(200, &*find_composition(&compositions, id)?.without_suggestions()))
+        }
+        ("PATCH", ["api", "compositions", id]) => update_composition(storage, id, request),
+        ("DELETE", ["api", "compositions", id]) => delete_composition(storage, id, request),
+        ("GET", ["api", "compositions", id, "export"]) => {
+            let compositions = storage.load_compositions()?;
+            let composition = find_composition(&compositions, id)?;
+            let format = export_format(request)?;
+            ok(export_response(format, export::export_composition(composition, format)))
+        }
+
+        ("GET", ["api", "compositions", id, "notes"]) => {
+            let compositions = storage.load_compositions()?;
+            ok(Response::json(200, &find_composition(&compositions, id)?.notes))
+        }
+        ("POST", ["api", "compositions", id, "notes"]) => create_note(storage, id, request),
+        ("PATCH", ["api", "compositions", id, "notes", note_id]) => update_note(storage, id, note_id, request),
+        ("DELETE", ["api", "compositions", id, "notes", note_id]) => delete_note(storage, id, note_id),
+
+        ("GET", ["api", "folders"]) => ok(Response::json(200, &storage.load_folders()?)),
+        ("POST", ["api", "folders"]) => create_folder(storage, request),
+        ("GET", ["api", "folders", id]) => {
+            let folders = storage.load_folders()?;
+            let folder = folders.iter().find(|f| f.id == *id).ok_or_else(|| ApiError::not_found("folder", id))?;
+            ok(Response::json(200, folder))
+        }
+        ("PATCH", ["api", "folders", id]) => update_folder(storage, id, request),
+        ("DELETE", ["api", "folders", id]) => delete_folder(storage, id),
+
+        ("GET", ["api", "projects"]) => ok(Response::json(200, &storage.load_projects()?)),
+        ("POST", ["api", "projects"]) => create_project(storage, request),
+        ("GET", ["api", "projects", id]) => {
+            let projects = storage.load_projects()?;
+            ok(Response::json(200, find_project(&projects, id)?))
+        }
+        ("PATCH", ["api", "projects", id]) => update_project(storage, id, request),
+        ("DELETE", ["api", "projects", id]) => delete_project(storage, id),
+        ("GET", ["api", "projects", id, "export"]) => {
+            let projects = storage.load_projects()?;
+            let project = find_project(&projects, id)?;
+            let format = export_format(request)?;
+            let document = export::export_project(project, &storage.load_compositions()?, format);
+            ok(export_response(format, document))
+        }
+
+        ("GET", ["api", "flows"]) => list_flows(storage, request),
+        ("GET", ["api", "flows", id]) => {
+            let flows = storage.load_flows()?;
+            let flow = flows.iter().find(|f| f.id == *id).ok_or_else(|| ApiError::not_found("flow", id))?;
+            ok(Response::json(200, flow))
+        }
+
+        (_, ["api", ..]) => Err(ApiError::new(404, format!("no endpoint for {} {}", method, request.path))),
+        _ => Err(ApiError::new(404, "the API lives under /api")),
+    }
+}
+
+fn ok(response: Response) -> Result<Reply> {
+    Ok((response, Vec::new()))
+}
+
+fn parse_body<T: DeserializeOwned>(request: &Request) -> Result<T> {
+    serde_json::from_slice(&request.body).map_err(|e| ApiError::new(400, format!("invalid request body: {}", e)))
+}
+
+/// Tells a field set to `null` (`Some(None)`) from one left out (`None`)
+fn present<'de, D: Deserializer<'de>, T: Deserialize<'de>>(deserializer: D) -> std::result::Result<Option<Option<T>>, D::Error> {
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+fn find_composition<'a>(compositions: &'a [Composition], id: &str) -> Result<&'a Composition> {
+    compositions.iter().find(|c| c.id == id).ok_or_else(|| ApiError::not_found("composition", id))
+}
+
+fn find_project<'a>(projects: &'a [Project], id: &str) -> Result<&'a Project> {
+    projects.iter().find(|p| p.id == id).ok_or_else(|| ApiError::not_found("project", id))
+}
+
+fn require_folder(storage: &Storage, folder_id: &Option<String>) -> Result<()> {
+    match folder_id {
+        Some(id) if !storage.load_folders()?.iter().any(|f| f.id == *id) => {
+            Err(ApiError::new(400, format!("no folder with id \"{}\"", id)))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn non_empty(field: &str, value: &str) -> Result<()> {
+    if value.trim().is_empty() {
+        return Err(ApiError::new(400, format!("{} can't be empty", field)));
+    }
+    Ok(())
+}
+
+// ========== Library ==========
+
+#[derive(Serialize)]
+struct Index<'a> {
+    name: &'a str,
+    version: &'a str,
+    library: String,
+    read_only: bool,
+}
+
+fn index(storage: &Storage) -> Result<Reply> {
+    ok(Response::json(200, &Index {
+        name: "abbey",
+        version: env!("CARGO_PKG_VERSION"),
+        library: storage.base_dir().display().to_string(),
+        read_only: storage.is_read_only(),
+    }))
+}
+
+fn library_stats(storage: &Storage) -> Result<Reply> {
+    let stats = LibraryStats::new(
+        &storage.load_compositions()?,
+        &storage.load_flows()?,
+        &storage.load_projects()?,
+    );
+    ok(Response::json(200, &stats))
+}
+
+fn export_format(request: &Request) -> Result<ExportFormat> {
+    match request.query("format") {
+        None => Ok(ExportFormat::Markdown),
+        Some(name) => ExportFormat::from_name(name)
+            .ok_or_else(|| ApiError::new(400, format!("unknown export format \"{}\" (use md, html or odt)", name))),
+    }
+}
+
+fn export_response(format: ExportFormat, document: String) -> Response {
+    let content_type = match format {
+        ExportFormat::Markdown => "text/markdown; charset=utf-8",
+        ExportFormat::Html => "text/html; charset=utf-8",
+        ExportFormat::Odt => "application/vnd.oasis.opendocument.text-flat-xml",
+    };
+    Response::new(200, content_type, document)
+}
+
+// ========== Compositions ==========
+
+/// What listing shows of each composition; fetch one for its content and notes
+#[derive(Serialize)]
+struct CompositionSummary<'a> {
+    id: &'a str,
+    title: &'a str,
+    words: usize,
+    archived: bool,
+    folder_id: Option<&'a str>,
+    tags: &'a [String],
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+fn list_compositions(storage: &Storage, request: &Request) -> Result<Reply> {
+    let compositions = storage.load_compositions()?;
+    let shown = |c: &&Composition| match request.query("archived") {
+        Some("all") => true,
+        Some("true") => c.archived,
+        _ => !c.archived,
+    };
+
+    let summaries: Vec<_> = compositions
+        .iter()
+        .filter(shown)
+        .filter(|c| request.query("folder").is_none_or(|id| c.folder_id.as_deref() == Some(id)))
+        .map(|c| CompositionSummary {
+            id: &c.id,
+            title: &c.title,
+            words: c.word_count,
+            archived: c.archived,
+            folder_id: c.folder_id.as_deref(),
+            tags: &c.tags,
+            created_at: c.created_at,
+            updated_at: c.updated_at,
+        })
+        .collect();
+    ok(Response::json(200, &summaries))
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct NewComposition {
+    title: Option<String>,
+    #[serde(default)]
+    content: String,
+    #[serde(default)]
+    tags: Vec<String>,
+    folder_id: Option<String>,
+}
+
+fn create_composition(storage: &Storage, request: &Request) -> Result<Reply> {
+    let new: NewComposition = parse_body(request)?;
+    require_folder(storage, &new.folder_id)?;
+
+    let mut comp = Composition::new();
+    if let Some(title) = new.title.filter(|t| !t.trim().is_empty()) {
+        comp.title = title;
+    }
+    comp.content = new.content;
+    comp.tags = new.tags;
+    comp.folder_id = new.folder_id;
+    comp.update_word_count();
+
+    storage.save_composition(&comp)?;
+    Ok((
+        Response::json(201, &comp),
+        vec![Change::new(Kind::Composition, Action::Created, &comp.id)],
+    ))
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CompositionUpdate {
+    title: Option<String>,
+    content: Option<String>,
+    tags: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "present")]
+    folder_id: Option<Option<String>>,
+    archived: Option<bool>,
+}
+
+fn update_composition(storage: &Storage, id: &str, request: &Request) -> Result<Reply> {
+    let update: CompositionUpdate = parse_body(request)?;
+    let compositions = storage.load_compositions()?;
+    let mut comp = find_composition(&compositions, id)?.clone();
+
+    let mut old_title = None;
+    if let Some(title) = update.title {
+        non_empty("title", &title)?;
+        old_title = Some(std::mem::replace(&mut comp.title, title));
+    }
+    if let Some(content) = update.content {
+        let old = std::mem::replace(&mut comp.content, content);
+        comp.follow_edit(&old);
+        comp.update_word_count();
+    }
+    if let Some(tags) = update.tags {
+        comp.tags = tags;
+    }
+    if let Some(folder_id) = update.folder_id {
+        require_folder(storage, &folder_id)?;
+        comp.folder_id = folder_id;
+    }
+    if let Some(archived) = update.archived {
+        comp.archived = archived;
+    }
+    comp.updated_at = Utc::now();
+
+    storage.save_composition(&comp)?;
+    let mut changes = vec![Change::new(Kind::Composition, Action::Updated, &comp.id)];
+
+    // Links to the old title follow the composition to its new one
+    if let Some(old_title) = old_title {
+        let mut compositions = storage.load_compositions()?;
+        for linking in links::retitle(&mut compositions, &comp.id, &old_title) {
+            let linking = compositions.iter().find(|c| c.id == linking).unwrap();
+            storage.save_composition(linking)?;
+            if linking.id == comp.id {
+                comp = linking.clone();
+            } else {
+                changes.push(Change::new(Kind::Composition, Action::Updated, &linking.id));
+            }
+        }
+    }
+    Ok((Response::json(200, &*comp.without_suggestions()), changes))
+}
+
+/// Archives the composition, as deleting does in the app, unless `permanent=true`
+/// asks for it to be removed from the library and its projects altogether
+fn delete_composition(storage: &Storage, id: &str, request: &Request) -> Result<Reply> {
+    let mut compositions = storage.load_compositions()?;
+    find_composition(&compositions, id)?;
+
+    if request.query("permanent") != Some("true") {
+        let comp = compositions.iter_mut().find(|c| c.id == id).unwrap();
+        comp.archived = true;
+        comp.updated_at = Utc::now();
+        storage.save_compositions(&compositions)?;
+        return Ok((Response::no_content(), vec![Change::new(Kind::Composition, Action::Updated, id)]));
+    }
+
+    compositions.retain(|c| c.id != id);
+    storage.save_compositions(&compositions)?;
+    let mut changes = vec![Change::new(Kind::Composition, Action::Deleted, id)];
+
+    let mut projects = storage.load_projects()?;
+    let mut projects_changed = false;
+    for project in projects.iter_mut().filter(|p| p.composition_ids.iter().any(|c| c == id)) {
+        project.remove_composition(id);
+        changes.push(Change::new(Kind::Project, Action::Updated, &project.id));
+        projects_changed = true;
+    }
+    if projects_changed {
+        storage.save_projects(&projects)?;
+    }
+
+    Ok((Response::no_content(), changes))
+}
+
+// ========== Notes ==========
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct NoteBody {
+    content: String,
+}
+
+/// Load the compositions, apply `f` to the one with `id` and save them all back
+fn edit_composition<T>(storage: &Storage, id: &str, f: impl FnOnce(&mut Composition) -> Result<T>) -> Result<T> {
+    let mut compositions = storage.load_compositions()?;
+    let comp = compositions
+        .iter_mut()
+        .find(|c| c.id == id)
+        .ok_or_else(|| ApiError::not_found("composition", id))?;
+
+    let result = f(comp)?;
+    comp.updated_at = Utc::now();
+    let comp = comp.clone();
+    storage.save_composition(&comp)?;
+    Ok(result)
+}
+
+fn create_note(storage: &Storage, comp_id: &str, request: &Request) -> Result<Reply> {
+    let body: NoteBody = parse_body(request)?;
+    non_empty("content", &body.content)?;
+
+    let note = edit_composition(storage, comp_id, |comp| {
+        let note = Note::new(body.content);
+        comp.notes.push(note.clone());
+        Ok(note)
+    })?;
+    Ok((
+        Response::json(201, &note),
+        vec![Change::new(Kind::Note, Action::Created, &note.id).in_composition(comp_id)],
+    ))
+}
+
+fn update_note(storage: &Storage, comp_id: &str, note_id: &str, request: &Request) -> Result<Reply> {
+    let body: NoteBody = parse_body(request)?;
+    non_empty("content", &body.content)?;
+
+    let note = edit_composition(storage, comp_id, |comp| {
+        let note = comp
+            .notes
+            .iter_mut()
+            .find(|n| n.id == note_id)
+            .ok_or_else(|| ApiError::not_found("note", note_id))?;
+        note.content = body.content;
+        Ok(note.clone())
+    })?;
+    Ok((
+        Response::json(200, &note),
+        vec![Change::new(Kind::Note, Action::Updated, note_id).in_composition(comp_id)],
+    ))
+}
+
+fn delete_note(storage: &Storage, comp_id: &str, note_id: &str) -> Result<Reply> {
+    edit_composition(storage, comp_id, |comp| {
+        let before = comp.notes.len();
+        comp.notes.retain(|n| n.id != note_id);
+        if comp.notes.len() == before {
+            return Err(ApiError::not_found("note", note_id));
+        }
+        Ok(())
+    })?;
+    Ok((
+        Response::no_content(),
+        vec![Change::new(Kind::Note, Action::Deleted, note_id).in_composition(comp_id)],
+    ))
+}
+
+// ========== Folders ==========
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FolderBody {
+    name: Option<String>,
+    expanded: Option<bool>,
+}
+
+fn create_folder(storage: &Storage, request: &Request) -> Result<Reply> {
+    let body: FolderBody = parse_body(request)?;
+    let name = body.name.unwrap_or_default();
+    non_empty("name", &name)?;
+
+    let mut folder = Folder::new(name.trim().to_string());
+    if let Some(expanded) = body.expanded {
+        folder.expanded = expanded;
+    }
+
+    let mut folders = storage.load_folders()?;
+    folders.push(folder.clone());
+    storage.save_folders(&folders)?;
+    Ok((
+        Response::json(201, &folder),
+        vec![Change::new(Kind::Folder, Action::Created, &folder.id)],
+    ))
+}
+
+fn update_folder(storage: &Storage, id: &str, request: &Request) -> Result<Reply> {
+    let body: FolderBody = parse_body(request)?;
+    let mut folders = storage.load_folders()?;
+    let folder = folders
+        .iter_mut()
+        .find(|f| f.id == id)
+        .ok_or_else(|| ApiError::not_found("folder", id))?;
+
+    if let Some(name) = body.name {
+        non_empty("name", &name)?;
+        folder.name = name.trim().to_string();
+    }
+    if let Some(expanded) = body.expanded {
+        folder.expanded = expanded;
+    }
+
+    let folder = folder.clone();
+    storage.save_folders(&folders)?;
+    Ok((
+        Response::json(200, &folder),
+        vec![Change::new(Kind::Folder, Action::Updated, id)],
+    ))
+}
+
+/// Compositions in a deleted folder move back to the top level, as in the app
+fn delete_folder(storage: &Storage, id: &str) -> Result<Reply> {
+    let mut folders = storage.load_folders()?;
+    if !folders.iter().any(|f| f.id == id) {
+        return Err(ApiError::not_found("folder", id));
+    }
+
+    let mut compositions = storage.load_compositions()?;
+    let mut changes = Vec::new();
+    for comp in compositions.iter_mut().filter(|c| c.folder_id.as_deref() == Some(id)) {
+        comp.folder_id = None;
+        changes.push(Change::new(Kind::Composition, Action::Updated, &comp.id));
+    }
+    if !changes.is_empty() {
+        storage.save_compositions(&compositions)?;
+    }
+
+    folders.retain(|f| f.id != id);
+    storage.save_folders(&folders)?;
+    changes.push(Change::new(Kind::Folder, Action::Deleted, id));
+    Ok((Response::no_content(), changes))
+}
+
+// ========== Projects ==========
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProjectBody {
+    title: Option<String>,
+    description: Option<String>,
+    composition_ids: Option<Vec<String>>,
+}
+
+fn require_compositions(storage: &Storage, ids: &[String]) -> Result<()> {
+    let compositions = storage.load_compositions()?;
+    match ids.iter().find(|id| !compositions.iter().any(|c| c.id == **id)) {
+        Some(id) => Err(ApiError::new(400, format!("no composition with id \"{}\"", id))),
+        None => Ok(()),
+    }
+}
+
+fn create_project(storage: &Storage, request: &Request) -> Result<Reply> {
+    let body: ProjectBody = parse_body(request)?;
+    let title = body.title.unwrap_or_default();
+    non_empty("title", &title)?;
+
+    let mut project = Project::new(title);
+    project.description = body.description.unwrap_or_default();
+    if let Some(ids) = body.composition_ids {
+        require_compositions(storage, &ids)?;
+        for id in ids {
+            project.add_composition(id);
+        }
+    }
+
+    storage.save_project(&project)?;
+    Ok((
+        Response::json(201, &project),
+        vec![Change::new(Kind::Project, Action::Created, &project.id)],
+    ))
+}
+
+fn update_project(storage: &Storage, id: &str, request: &Request) -> Result<Reply> {
+    let body: ProjectBody = parse_body(request)?;
+    let projects = storage.load_projects()?;
+    let mut project = find_project(&projects, id)?.clone();
+
+    if let Some(title) = body.title {
+        non_empty("title", &title)?;
+        project.title = title;
+    }
+    if let Some(description) = body.description {
+        project.description = description;
+    }
+    if let Some(ids) = body.composition_ids {
+        require_compositions(storage, &ids)?;
+        let mut unique = Vec::new();
+        for id in ids {
+            if !unique.contains(&id) {
+                unique.push(id);
+            }
+        }
+        project.reorder_compositions(unique);
+    }
+    project.updated_at = Utc::now();
+
+    storage.save_project(&project)?;
+    Ok((
+        Response::json(200, &project),
+        vec![Change::new(Kind::Project, Action::Updated, id)],
+    ))
+}
+
+fn delete_project(storage: &Storage, id: &str) -> Result<Reply> {
+    let mut projects = storage.load_projects()?;
+    find_project(&projects, id)?;
+
+    projects.retain(|p| p.id != id);
+    storage.save_projects(&projects)?;
+    Ok((Response::no_content(), vec![Change::new(Kind::Project, Action::Deleted, id)]))
+}
+
+// ========== Flows ==========
+
+/// Flows newest first, optionally from `since`, which takes the same values as
+/// `abbey flows --since`
+fn list_flows(storage: &Storage, request: &Request) -> Result<Reply> {
+    let since = match request.query("since") {
+        Some(value) => cli::parse_since(value, Local::now())
+            .ok_or_else(|| ApiError::new(400, format!("can't understand since \"{}\"", value)))?,
+        None => DateTime::<Utc>::MIN_UTC,
+    };
+
+    let flows = stats::flows_since(&storage.load_flows()?, since);
+    ok(Response::json(200, &flows))
+}
@@ -0,0 +1,233 @@
+//! Serving the API on localhost, one thread per connection
+
+use super::{Change, Request, Response};
+use std::io::{self, BufReader, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// Path of the server-sent event stream of library changes
+pub const EVENTS_PATH: &str = "/api/events";
+
+/// How long a client gets to send its request
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often an idle event stream gets a comment, so dead clients are noticed
+const HEARTBEAT: Duration = Duration::from_secs(15);
+
+/// Connections served at once before new ones are turned away
+const MAX_CONNECTIONS: usize = 32;
+
+/// Hands library changes to every open event stream
+#[derive(Clone, Default)]
+pub struct EventHub {
+    subscribers: Arc<Mutex<Vec<mpsc::Sender<Change>>>>,
+}
+
+impl EventHub {
+    pub fn publish(&self, change: Change) {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        subscribers.retain(|subscriber| subscriber.send(change.clone()).is_ok());
+    }
+
+    pub fn subscribe(&self) -> mpsc::Receiver<Change> {
+        let (sender, receiver) = mpsc::channel();
+        self.subscribers.lock().unwrap().push(sender);
+        receiver
+    }
+
+    /// Drop every subscriber, ending their streams
+    fn close(&self) {
+        self.subscribers.lock().unwrap().clear();
+    }
+}
+
+type Handler = dyn Fn(&Request) -> super::Reply + Send + Sync;
+
+/// Shared by the accepting thread and each connection's thread
+struct Shared {
+    token: String,
+    handler: Box<Handler>,
+    events: EventHub,
+    stopping: AtomicBool,
+    connections: AtomicUsize,
+}
+
+/// The API listening on 127.0.0.1. Stops when dropped.
+pub struct Server {
+    addr: SocketAddr,
+    shared: Arc<Shared>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Server {
+    /// Listen on `port` (0 picks a free one) and answer authorized requests
+    /// with `handler`. Changes it returns go out on the event stream.
+    pub fn start<H>(port: u16, token: &str, handler: H) -> io::Result<Self>
+    where
+        H: Fn(&Request) -> super::Reply + Send + Sync + 'static,
+    {
+        if token.trim().is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "the API needs a token"));
+        }
+
+        // Never reachable from other machines
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+        let addr = listener.local_addr()?;
+        let shared = Arc::new(Shared {
+            token: token.to_string(),
+            handler: Box::new(handler),
+            events: EventHub::default(),
+            stopping: AtomicBool::new(false),
+            connections: AtomicUsize::new(0),
+        });
+
+        let accepting = shared.clone();
+        let thread = thread::Builder::new()
+            .name("abbey-api".to_string())
+            .spawn(move || accept(listener, accepting))?;
+
+        Ok(Self {
+            addr,
+            shared,
+            thread: Some(thread),
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Announce a change made outside the API, such as an edit in the app
+    pub fn events(&self) -> &EventHub {
+        &self.shared.events
+    }
+
+    /// Block until the server stops, which it only does on a listener error
+    pub fn wait(mut self) {
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.shared.stopping.store(true, Ordering::SeqCst);
+        self.shared.events.close();
+
+        // Wake the accepting thread so it sees the flag
+        let _ = TcpStream::connect(self.addr);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn accept(listener: TcpListener, shared: Arc<Shared>) {
+    for stream in listener.incoming() {
+        if shared.stopping.load(Ordering::SeqCst) {
+            break;
+        }
+
+        let mut stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                log::warn!("API connection failed: {}", e);
+                continue;
+            }
+        };
+
+        if shared.connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+            shared.connections.fetch_sub(1, Ordering::SeqCst);
+            let _ = Response::error(503, "too many connections").write_to(&mut stream);
+            continue;
+        }
+
+        let connection = shared.clone();
+        let spawned = thread::Builder::new().name("abbey-api-connection".to_string()).spawn(move || {
+            serve(stream, &connection);
+            connection.connections.fetch_sub(1, Ordering::SeqCst);
+        });
+        if let Err(e) = spawned {
+            shared.connections.fetch_sub(1, Ordering::SeqCst);
+            log::error!("Failed to start an API connection thread: {}", e);
+        }
+    }
+}
+
+fn serve(mut stream: TcpStream, shared: &Shared) {
+    if let Err(e) = stream.set_read_timeout(Some(READ_TIMEOUT)) {
+        log::warn!("Failed to set API read timeout: {}", e);
+    }
+
+    let request = match Request::read_from(&mut BufReader::new(&stream)) {
+        Ok(request) => request,
+        Err(response) => {
+            let _ = response.write_to(&mut stream);
+            return;
+        }
+    };
+
+    if !authorized(&request, &shared.token) {
+        let response = Response::error(401, "send the token from Abbey's settings as a bearer token")
+            .with_header("WWW-Authenticate", "Bearer");
+        let _ = response.write_to(&mut stream);
+        return;
+    }
+
+    if request.path.trim_end_matches('/') == EVENTS_PATH {
+        if request.method != "GET" {
+            let _ = Response::error(405, "the event stream only answers GET").write_to(&mut stream);
+            return;
+        }
+        stream_events(stream, shared);
+        return;
+    }
+
+    let (response, changes) = (shared.handler)(&request);
+    for change in changes {
+        shared.events.publish(change);
+    }
+    if let Err(e) = response.write_to(&mut stream) {
+        log::debug!("Failed to send API response: {}", e);
+    }
+}
+
+/// Compare the bearer token without leaking how much of it matched
+fn authorized(request: &Request, token: &str) -> bool {
+    let given = match request.header("authorization").and_then(|h| h.strip_prefix("Bearer ")) {
+        Some(given) => given.trim().as_bytes(),
+        None => return false,
+    };
+    let expected = token.as_bytes();
+
+    given.len() == expected.len() && given.iter().zip(expected).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// Send changes as server-sent events until the client goes away or the server stops
+fn stream_events(mut stream: TcpStream, shared: &Shared) {
+    let changes = shared.events.subscribe();
+    let head = "HTTP/1.1 200 OK\r\n\
+                Content-Type: text/event-stream\r\n\
+                Cache-Control: no-cache\r\n\
+                Connection: keep-alive\r\n\r\n\
+                : connected\n\n";
+    if stream.write_all(head.as_bytes()).is_err() {
+        return;
+    }
+
+    loop {
+        let message = match changes.recv_timeout(HEARTBEAT) {
+            Ok(change) => change.to_event(),
+            Err(RecvTimeoutError::Timeout) if !shared.stopping.load(Ordering::SeqCst) => ": heartbeat\n\n".to_string(),
+            Err(_) => return,
+        };
+        if stream.write_all(message.as_bytes()).and_then(|_| stream.flush()).is_err() {
+            return;
+        }
+    }
+}
@@ -0,0 +1,130 @@
+//! Consistency checks over a library on disk
+
+use crate::data::{lock_holder, Storage};
+use crate::utils::text_stats;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs;
+
+/// How serious a problem is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Something is damaged or unreadable
+    Error,
+    /// Worth a look, but nothing is lost
+    Warning,
+}
+
+/// A problem found in a library
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Issue {
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Issue {
+    fn error(message: String) -> Self {
+        Self { severity: Severity::Error, message }
+    }
+
+    fn warning(message: String) -> Self {
+        Self { severity: Severity::Warning, message }
+    }
+}
+
+/// Check that a library's files are readable and refer to each other consistently
+pub fn check_library(storage: &Storage) -> Vec<Issue> {
+    let mut issues = Vec::new();
+
+    if !storage.base_dir().is_dir() {
+        issues.push(Issue::error(format!("{} is not a library directory", storage.base_dir().display())));
+        return issues;
+    }
+
+    let compositions = storage.load_compositions().unwrap_or_else(|e| {
+        issues.push(Issue::error(e.to_string()));
+        Vec::new()
+    });
+    let folders = storage.load_folders().unwrap_or_else(|e| {
+        issues.push(Issue::error(e.to_string()));
+        Vec::new()
+    });
+    let projects = storage.load_projects().unwrap_or_else(|e| {
+        issues.push(Issue::error(e.to_string()));
+        Vec::new()
+    });
+    if let Err(e) = storage.load_flows() {
+        issues.push(Issue::error(e.to_string()));
+    }
+    if let Err(e) = storage.load_settings() {
+        issues.push(Issue::error(e.to_string()));
+    }
+
+    let mut seen = HashSet::new();
+    for comp in &compositions {
+        if !seen.insert(comp.id.as_str()) {
+            issues.push(Issue::error(format!("Composition id {} is used more than once", comp.id)));
+        }
+
+        if let Some(ref folder_id) = comp.folder_id {
+            if !folders.iter().any(|f| f.id == *folder_id) {
+                issues.push(Issue::warning(format!(
+                    "\"{}\" is in a folder that no longer exists",
+                    comp.title
+                )));
+            }
+        }
+
+        let words = text_stats::word_count(&comp.content_without_suggestions());
+        if comp.word_count != words {
+            issues.push(Issue::warning(format!(
+                "\"{}\" has a stored word count of {} but contains {} words",
+                comp.title, comp.word_count, words
+            )));
+        }
+    }
+
+    for project in &projects {
+        let missing = project
+            .composition_ids
+            .iter()
+            .filter(|id| !compositions.iter().any(|c| c.id == **id))
+            .count();
+        if missing > 0 {
+            issues.push(Issue::warning(format!(
+                "Project \"{}\" refers to {} missing composition(s)",
+                project.title, missing
+            )));
+        }
+    }
+
+    match storage.load_recovery() {
+        Ok(recovered) if !recovered.is_empty() => issues.push(Issue::warning(format!(
+            "{} composition(s) have unsaved edits from an unclean exit; open Abbey to recover them",
+            recovered.len()
+        ))),
+        Ok(_) => {}
+        Err(e) => issues.push(Issue::error(e.to_string())),
+    }
+
+    if let Ok(entries) = fs::read_dir(storage.base_dir()) {
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name.ends_with(".tmp") {
+                issues.push(Issue::warning(format!("{} was left behind by an interrupted write", name)));
+            }
+        }
+    }
+
+    match lock_holder(storage.base_dir()) {
+        Ok(Some(holder)) if holder.is_stale() => issues.push(Issue::warning(format!(
+            "A stale lock was left by {}; it will be replaced when Abbey next opens the library",
+            holder
+        ))),
+        Ok(_) => {}
+        Err(e) => issues.push(Issue::error(e.to_string())),
+    }
+
+    issues
+}
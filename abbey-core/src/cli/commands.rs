@@ -0,0 +1,408 @@
+use super::{CliError, Command, Options, USAGE};
+use crate::api;
+use crate::check::{self, Severity};
+use crate::data::{self, ApiSettings, Composition, Project, Storage, StorageError};
+use crate::export::{self, ExportFormat};
+use crate::search;
+use crate::stats::{self, LibraryStats};
+use crate::utils::{self, text_stats};
+use chrono::{DateTime, Local, Utc};
+use serde::Serialize;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// Run a parsed command, returning the exit code
+pub fn execute(
+    command: Command,
+    options: &Options,
+    stdin: Option<&mut dyn Read>,
+    out: &mut dyn Write,
+) -> Result<i32, CliError> {
+    match command {
+        Command::Help => {
+            write!(out, "{}", USAGE)?;
+            Ok(0)
+        }
+        Command::List { archived } => list(&open_for_reading(options)?, archived, options.json, out),
+        Command::Show { query } => show(&open_for_reading(options)?, &query, options.json, out),
+        Command::New { title, text } => {
+            let text = input_text(text, stdin)?;
+            new(&open_for_writing(options)?, title, text, options.json, out)
+        }
+        Command::Append { query, text } => {
+            let text = input_text(text, stdin)?;
+            if text.trim().is_empty() {
+                return Err(CliError::Usage("nothing to append".to_string()));
+            }
+            append(&open_for_writing(options)?, &query, &text, options.json, out)
+        }
+        Command::ExportProject { name, format, output, smart } => {
+            export_project(&open_for_reading(options)?, &name, format, output, smart, out)
+        }
+        Command::Flows { since } => flows(&open_for_reading(options)?, since, options.json, out),
+        Command::Stats => stats(&open_for_reading(options)?, options.json, out),
+        Command::Check => check(&Storage::open_read_only(library_dir(options)?), options.json, out),
+        Command::Serve { port } => serve(open_for_writing(options)?, port, out),
+    }
+}
+
+// ========== Library access ==========
+
+fn library_dir(options: &Options) -> Result<PathBuf, CliError> {
+    if let Some(ref library) = options.library {
+        return Ok(library.clone());
+    }
+
+    match std::env::var_os("ABBEY_LIBRARY") {
+        Some(dir) if !dir.is_empty() => Ok(PathBuf::from(dir)),
+        _ => Ok(Storage::default_base_dir()?),
+    }
+}
+
+/// Reading never takes the library lock, so it works while the app is open
+fn open_for_reading(options: &Options) -> Result<Storage, CliError> {
+    let dir = library_dir(options)?;
+    if !dir.is_dir() {
+        return Err(CliError::NoLibrary(dir));
+    }
+    Ok(Storage::open_read_only(dir))
+}
+
+/// Writing takes the lock, and so fails while the app has the library open
+/// rather than having the app overwrite the change later
+fn open_for_writing(options: &Options) -> Result<Storage, CliError> {
+    Ok(Storage::open(library_dir(options)?)?)
+}
+
+/// Text from the command line, or from piped input if none was given
+fn input_text(words: Vec<String>, stdin: Option<&mut dyn Read>) -> Result<String, CliError> {
+    if !words.is_empty() {
+        return Ok(words.join(" "));
+    }
+
+    let mut text = String::new();
+    if let Some(stdin) = stdin {
+        stdin.read_to_string(&mut text)?;
+    }
+    Ok(text.trim_end().to_string())
+}
+
+fn find_composition<'a>(compositions: &'a [Composition], query: &str) -> Result<&'a Composition, CliError> {
+    find_by_id_or_title(compositions, query, |c| &c.id, |c| &c.title)?
+        .ok_or_else(|| CliError::NotFound(format!("no composition matches \"{}\"", query)))
+}
+
+fn find_project<'a>(projects: &'a [Project], query: &str) -> Result<&'a Project, CliError> {
+    find_by_id_or_title(projects, query, |p| &p.id, |p| &p.title)?
+        .ok_or_else(|| CliError::NotFound(format!("no project matches \"{}\"", query)))
+}
+
+fn find_by_id_or_title<'a, T>(
+    items: &'a [T],
+    query: &str,
+    id: impl Fn(&T) -> &String,
+    title: impl Fn(&T) -> &String,
+) -> Result<Option<&'a T>, CliError> {
+    search::find_by_id_or_title(items, query, &id, &title).map_err(|matches| CliError::Ambiguous {
+        query: query.to_string(),
+        matches: matches
+            .iter()
+            .map(|item| format!("{}  {}", short_id(id(item)), title(item)))
+            .collect(),
+    })
+}
+
+// ========== Commands ==========
+
+#[derive(Serialize)]
+struct CompositionSummary<'a> {
+    id: &'a str,
+    title: &'a str,
+    words: usize,
+    archived: bool,
+    folder: Option<&'a str>,
+    tags: &'a [String],
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+fn list(storage: &Storage, archived: bool, json: bool, out: &mut dyn Write) -> Result<i32, CliError> {
+    let compositions = storage.load_compositions()?;
+    let folders = storage.load_folders()?;
+    let shown: Vec<_> = compositions.iter().filter(|c| c.archived == archived).collect();
+
+    if json {
+        let summaries: Vec<_> = shown
+            .iter()
+            .map(|c| CompositionSummary {
+                id: &c.id,
+                title: &c.title,
+                words: text_stats::word_count(&c.content_without_suggestions()),
+                archived: c.archived,
+                folder: c
+                    .folder_id
+                    .as_ref()
+                    .and_then(|id| folders.iter().find(|f| f.id == *id))
+                    .map(|f| f.name.as_str()),
+                tags: &c.tags,
+                created_at: c.created_at,
+                updated_at: c.updated_at,
+            })
+            .collect();
+        print_json(out, &summaries)?;
+        return Ok(0);
+    }
+
+    if shown.is_empty() {
+        writeln!(out, "No {}compositions", if archived { "archived " } else { "" })?;
+        return Ok(0);
+    }
+
+    writeln!(out, "{:<8}  {:>6}  {:<16}  TITLE", "ID", "WORDS", "UPDATED")?;
+    for comp in shown {
+        writeln!(
+            out,
+            "{:<8}  {:>6}  {:<16}  {}",
+            short_id(&comp.id),
+            text_stats::word_count(&comp.content_without_suggestions()),
+            local_time(&comp.updated_at),
+            comp.title
+        )?;
+    }
+    Ok(0)
+}
+
+fn show(storage: &Storage, query: &str, json: bool, out: &mut dyn Write) -> Result<i32, CliError> {
+    let compositions = storage.load_compositions()?;
+    let comp = find_composition(&compositions, query)?;
+
+    if json {
+        print_json(out, &*comp.without_suggestions())?;
+    } else {
+        writeln!(out, "{}", export::composition_to_markdown(comp).trim_end())?;
+    }
+    Ok(0)
+}
+
+fn new(storage: &Storage, title: Option<String>, text: String, json: bool, out: &mut dyn Write) -> Result<i32, CliError> {
+    let mut comp = Composition::new();
+    if let Some(title) = title {
+        comp.title = title;
+    }
+    comp.content = text;
+    comp.update_word_count();
+
+    storage.save_composition(&comp)?;
+
+    if json {
+        print_json(out, &comp)?;
+    } else {
+        writeln!(out, "Created \"{}\" ({})", comp.title, comp.id)?;
+    }
+    Ok(0)
+}
+
+fn append(storage: &Storage, query: &str, text: &str, json: bool, out: &mut dyn Write) -> Result<i32, CliError> {
+    let compositions = storage.load_compositions()?;
+    let mut comp = find_composition(&compositions, query)?.clone();
+
+    comp.append_paragraph(text);
+
+    storage.save_composition(&comp)?;
+
+    if json {
+        print_json(out, &comp)?;
+    } else {
+        writeln!(
+            out,
+            "Appended {} words to \"{}\"",
+            text_stats::word_count(text),
+            comp.title
+        )?;
+    }
+    Ok(0)
+}
+
+fn export_project(
+    storage: &Storage,
+    name: &str,
+    format: ExportFormat,
+    output: Option<PathBuf>,
+    smart: bool,
+    out: &mut dyn Write,
+) -> Result<i32, CliError> {
+    let projects = storage.load_projects()?;
+    let project = find_project(&projects, name)?;
+    let compositions = storage.load_compositions()?;
+
+    let document = if smart {
+        let options = storage.load_settings()?.typography.options();
+        export::export_project_smartened(project, &compositions, format, &options)
+    } else {
+        export::export_project(project, &compositions, format)
+    };
+
+    match output {
+        Some(path) => {
+            fs::write(&path, document)?;
+            writeln!(out, "Exported \"{}\" to {}", project.title, path.display())?;
+        }
+        None => write!(out, "{}", document)?,
+    }
+    Ok(0)
+}
+
+fn flows(storage: &Storage, since: Option<DateTime<Utc>>, json: bool, out: &mut dyn Write) -> Result<i32, CliError> {
+    let flows = storage.load_flows()?;
+    let flows = stats::flows_since(&flows, since.unwrap_or(DateTime::<Utc>::MIN_UTC));
+
+    if json {
+        print_json(out, &flows)?;
+        return Ok(0);
+    }
+
+    if flows.is_empty() {
+        writeln!(out, "No flow sessions")?;
+        return Ok(0);
+    }
+
+    for flow in &flows {
+        writeln!(
+            out,
+            "{}  {:>3} min  {:>5} words  {}",
+            local_time(&flow.created_at),
+            flow.duration_minutes,
+            flow.word_count(),
+            utils::excerpt(flow.content.lines().next().unwrap_or(""), 50)
+        )?;
+    }
+
+    let words: usize = flows.iter().map(|f| f.word_count()).sum();
+    let seconds: u64 = flows.iter().map(|f| f.actual_duration_seconds).sum();
+    writeln!(out, "\n{} sessions, {} words, {}", flows.len(), words, format_duration(seconds))?;
+    Ok(0)
+}
+
+fn stats(storage: &Storage, json: bool, out: &mut dyn Write) -> Result<i32, CliError> {
+    let stats = LibraryStats::new(
+        &storage.load_compositions()?,
+        &storage.load_flows()?,
+        &storage.load_projects()?,
+    );
+
+    if json {
+        print_json(out, &stats)?;
+        return Ok(0);
+    }
+
+    writeln!(out, "Compositions  {} ({} archived)", stats.compositions, stats.archived)?;
+    writeln!(out, "Words         {}", stats.composition_words)?;
+    writeln!(out, "Notes         {}", stats.notes)?;
+    writeln!(out, "Projects      {}", stats.projects)?;
+    writeln!(
+        out,
+        "Flows         {} ({} words, {})",
+        stats.flows,
+        stats.flow_words,
+        format_duration(stats.flow_seconds)
+    )?;
+    writeln!(
+        out,
+        "Flow streak   {} day{}",
+        stats.flow_streak_days,
+        if stats.flow_streak_days == 1 { "" } else { "s" }
+    )?;
+    Ok(0)
+}
+
+#[derive(Serialize)]
+struct CheckReport<'a> {
+    library: String,
+    issues: &'a [check::Issue],
+}
+
+fn check(storage: &Storage, json: bool, out: &mut dyn Write) -> Result<i32, CliError> {
+    let issues = check::check_library(storage);
+
+    if json {
+        print_json(out, &CheckReport {
+            library: storage.base_dir().display().to_string(),
+            issues: &issues,
+        })?;
+    } else if issues.is_empty() {
+        writeln!(out, "No problems found in {}", storage.base_dir().display())?;
+    } else {
+        for issue in &issues {
+            let label = match issue.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+            };
+            writeln!(out, "{}: {}", label, issue.message)?;
+        }
+    }
+
+    // Warnings alone don't fail the check
+    Ok(if issues.iter().any(|i| i.severity == Severity::Error) { 1 } else { 0 })
+}
+
+/// Serve the API until the process is stopped. The library stays locked the
+/// whole time, so the app can't start and overwrite changes made through it.
+fn serve(storage: Storage, port: Option<u16>, out: &mut dyn Write) -> Result<i32, CliError> {
+    let mut settings = storage.load_settings()?;
+    if settings.api.token.is_empty() {
+        settings.api.token = ApiSettings::generate_token();
+        storage.save_settings(&settings)?;
+        writeln!(out, "Generated an API token: {}", settings.api.token)?;
+    }
+
+    let library = storage.base_dir().display().to_string();
+    let storage = Arc::new(Mutex::new(storage));
+    let serving = storage.clone();
+    let server = api::Server::start(port.unwrap_or(settings.api.port), &settings.api.token, move |request| {
+        api::handle(&serving.lock().unwrap(), request)
+    })?;
+
+    writeln!(out, "Serving {} at http://{}/api", library, server.local_addr())?;
+    writeln!(out, "Press Ctrl+C to stop.")?;
+    out.flush()?;
+
+    loop {
+        thread::sleep(Duration::from_secs(data::LOCK_HEARTBEAT_SECONDS.into()));
+        match storage.lock().unwrap().refresh_lock() {
+            Ok(()) => {}
+            Err(e @ StorageError::Locked(_)) => return Err(e.into()),
+            Err(e) => log::warn!("Failed to refresh library lock: {}", e),
+        }
+    }
+}
+
+// ========== Output ==========
+
+fn print_json<T: Serialize + ?Sized>(out: &mut dyn Write, value: &T) -> Result<(), CliError> {
+    let json = serde_json::to_string_pretty(value).map_err(|e| CliError::Io(e.into()))?;
+    writeln!(out, "{}", json)?;
+    Ok(())
+}
+
+fn short_id(id: &str) -> &str {
+    id.get(..8).unwrap_or(id)
+}
+
+fn local_time(time: &DateTime<Utc>) -> String {
+    time.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string()
+}
+
+fn format_duration(seconds: u64) -> String {
+    let hours = seconds / 3600;
+    let minutes = (seconds % 3600) / 60;
+
+    if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else {
+        format!("{}m", minutes)
+    }
+}
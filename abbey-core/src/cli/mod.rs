@@ -0,0 +1,275 @@
+//! Headless command-line access to a library, for scripts and cron jobs
+
+mod commands;
+
+use crate::data::StorageError;
+use crate::export::ExportFormat;
+use chrono::{DateTime, Duration, Local, NaiveDate, TimeZone, Utc};
+use std::fmt;
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+Usage: abbey <command> [options]
+
+Commands:
+  list [--archived]                      List compositions
+  show <id|title>                        Print a composition
+  new [--title <title>] [text...]        Create a composition (text from stdin if not given)
+  append <id|title> [text...]            Append text to a composition (text from stdin if not given)
+  export-project <name> [--format md|html|odt] [--output <file>] [--smart]
+                                         Export a project, --smart with curly quotes and dashes
+  flows [--since <date|Nd|Nw|today|yesterday>]
+                                         List flow sessions
+  stats                                  Show library statistics
+  check                                  Check the library for problems
+  serve [--port <port>]                  Serve the HTTP API on localhost until stopped
+  help                                   Show this help
+
+Options:
+  --json                                 Print machine-readable JSON
+  --library <dir>                        Use the library in <dir> (default: $ABBEY_LIBRARY or ~/Documents/Abbey)
+
+Without a command, abbey starts the desktop app.
+";
+
+const COMMANDS: [&str; 10] = [
+    "list", "show", "new", "append", "export-project", "flows", "stats", "check", "serve", "help",
+];
+
+/// Whether `arg` names a command-line subcommand rather than an argument for
+/// the desktop app
+pub fn is_command(arg: &str) -> bool {
+    COMMANDS.contains(&arg)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    List { archived: bool },
+    Show { query: String },
+    New { title: Option<String>, text: Vec<String> },
+    Append { query: String, text: Vec<String> },
+    ExportProject { name: String, format: ExportFormat, output: Option<PathBuf>, smart: bool },
+    Flows { since: Option<DateTime<Utc>> },
+    Stats,
+    Check,
+    Serve { port: Option<u16> },
+    Help,
+}
+
+/// Options shared by every command
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Options {
+    pub library: Option<PathBuf>,
+    pub json: bool,
+}
+
+#[derive(Debug)]
+pub enum CliError {
+    /// The command line couldn't be understood
+    Usage(String),
+    NoLibrary(PathBuf),
+    NotFound(String),
+    /// A name matched more than one item
+    Ambiguous { query: String, matches: Vec<String> },
+    Storage(StorageError),
+    Io(io::Error),
+}
+
+impl CliError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::Usage(_) => 2,
+            _ => 1,
+        }
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Usage(message) => write!(f, "{}", message),
+            Self::NoLibrary(path) => write!(f, "no library found at {}", path.display()),
+            Self::NotFound(message) => write!(f, "{}", message),
+            Self::Ambiguous { query, matches } => {
+                write!(f, "\"{}\" matches more than one item:", query)?;
+                for name in matches {
+                    write!(f, "\n  {}", name)?;
+                }
+                Ok(())
+            }
+            Self::Storage(e) => write!(f, "{}", e),
+            Self::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<StorageError> for CliError {
+    fn from(e: StorageError) -> Self {
+        Self::Storage(e)
+    }
+}
+
+impl From<io::Error> for CliError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+/// Run a command line (without the program name) against the real terminal,
+/// returning the process exit code
+pub fn run(args: &[String]) -> i32 {
+    let stdin = io::stdin();
+    let mut input = if stdin.is_terminal() { None } else { Some(stdin.lock()) };
+    run_with(
+        args,
+        input.as_mut().map(|i| i as &mut dyn Read),
+        &mut io::stdout().lock(),
+        &mut io::stderr().lock(),
+    )
+}
+
+/// Run a command line with explicit streams. `stdin` is `None` when there is
+/// no piped input to read text from.
+pub fn run_with(args: &[String], stdin: Option<&mut dyn Read>, out: &mut dyn Write, err: &mut dyn Write) -> i32 {
+    let result = parse(args).and_then(|(command, options)| commands::execute(command, &options, stdin, out));
+
+    match result {
+        Ok(code) => code,
+        Err(e) => {
+            let _ = writeln!(err, "abbey: {}", e);
+            if let CliError::Usage(_) = e {
+                let _ = writeln!(err, "Run `abbey help` for usage.");
+            }
+            e.exit_code()
+        }
+    }
+}
+
+/// Parse a command line (without the program name)
+pub fn parse(args: &[String]) -> Result<(Command, Options), CliError> {
+    let (name, rest) = match args.split_first() {
+        Some((name, rest)) => (name.as_str(), rest),
+        None => return Ok((Command::Help, Options::default())),
+    };
+
+    let mut options = Options::default();
+    let mut positional = Vec::new();
+    let mut archived = false;
+    let mut title = None;
+    let mut format = None;
+    let mut output = None;
+    let mut smart = false;
+    let mut since = None;
+    let mut port = None;
+
+    let mut iter = rest.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = |flag: &str| {
+            iter.next()
+                .cloned()
+                .ok_or_else(|| CliError::Usage(format!("{} needs a value", flag)))
+        };
+
+        match arg.as_str() {
+            "--json" => options.json = true,
+            "--library" => options.library = Some(PathBuf::from(value(arg)?)),
+            "--archived" if name == "list" => archived = true,
+            "--title" if name == "new" => title = Some(value(arg)?),
+            "--format" if name == "export-project" => {
+                let requested = value(arg)?;
+                format = Some(ExportFormat::from_name(&requested).ok_or_else(|| {
+                    CliError::Usage(format!("unknown export format \"{}\" (use md, html or odt)", requested))
+                })?);
+            }
+            "--output" | "-o" if name == "export-project" => output = Some(PathBuf::from(value(arg)?)),
+            "--smart" if name == "export-project" => smart = true,
+            "--since" if name == "flows" => {
+                let requested = value(arg)?;
+                since = Some(parse_since(&requested, Local::now()).ok_or_else(|| {
+                    CliError::Usage(format!("can't understand --since \"{}\"", requested))
+                })?);
+            }
+            "--port" if name == "serve" => {
+                let requested = value(arg)?;
+                port = Some(requested.parse::<u16>().map_err(|_| {
+                    CliError::Usage(format!("--port needs a port number, not \"{}\"", requested))
+                })?);
+            }
+            "--" => positional.extend(iter.by_ref().cloned()),
+            flag if flag.starts_with("--") => {
+                return Err(CliError::Usage(format!("unknown option {} for {}", flag, name)));
+            }
+            _ => positional.push(arg.clone()),
+        }
+    }
+
+    if matches!(name, "list" | "flows" | "stats" | "check" | "serve") && !positional.is_empty() {
+        return Err(CliError::Usage(format!("{} takes no arguments", name)));
+    }
+
+    let command = match name {
+        "list" => Command::List { archived },
+        "show" => Command::Show { query: single(name, positional)? },
+        "new" => Command::New { title, text: positional },
+        "append" => {
+            if positional.is_empty() {
+                return Err(CliError::Usage("append needs a composition id or title".to_string()));
+            }
+            let query = positional.remove(0);
+            Command::Append { query, text: positional }
+        }
+        "export-project" => Command::ExportProject {
+            name: single(name, positional)?,
+            format: format.unwrap_or(ExportFormat::Markdown),
+            output,
+            smart,
+        },
+        "flows" => Command::Flows { since },
+        "stats" => Command::Stats,
+        "check" => Command::Check,
+        "serve" => Command::Serve { port },
+        "help" | "--help" | "-h" => Command::Help,
+        other => return Err(CliError::Usage(format!("unknown command \"{}\"", other))),
+    };
+
+    Ok((command, options))
+}
+
+fn single(name: &str, mut positional: Vec<String>) -> Result<String, CliError> {
+    match positional.len() {
+        1 => Ok(positional.remove(0)),
+        0 => Err(CliError::Usage(format!("{} needs an id or title", name))),
+        _ => Err(CliError::Usage(format!("{} takes one id or title; quote titles with spaces", name))),
+    }
+}
+
+/// Parse a `--since` value: a date, `today`, `yesterday`, or a number of days
+/// or weeks back such as `7d` or `2w`. Dates are taken as local midnight.
+/// `None` for anything else, including offsets reaching back past the
+/// earliest date there is.
+pub fn parse_since(value: &str, now: DateTime<Local>) -> Option<DateTime<Utc>> {
+    let today = now.date_naive();
+    let value = value.trim().to_lowercase();
+
+    let day = match value.as_str() {
+        "today" => today,
+        "yesterday" => today - Duration::days(1),
+        _ => {
+            if let Ok(date) = NaiveDate::parse_from_str(&value, "%Y-%m-%d") {
+                date
+            } else if let Ok(time) = DateTime::parse_from_rfc3339(&value) {
+                return Some(time.with_timezone(&Utc));
+            } else if let Some(days) = value.strip_suffix('d') {
+                today.checked_sub_signed(Duration::try_days(days.parse::<u32>().ok()?.into())?)?
+            } else if let Some(weeks) = value.strip_suffix('w') {
+                today.checked_sub_signed(Duration::try_weeks(weeks.parse::<u32>().ok()?.into())?)?
+            } else {
+                return None;
+            }
+        }
+    };
+
+    let midnight = day.and_hms_opt(0, 0, 0)?;
+    Local.from_local_datetime(&midnight).earliest().map(|t| t.with_timezone(&Utc))
+}
@@ -0,0 +1,78 @@
+use crate::data::LockInfo;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Errors raised by the storage layer
+#[derive(Debug)]
+pub enum StorageError {
+    /// The user's Documents directory could not be located
+    NoDocumentsDir,
+    /// Reading or writing a library file failed
+    Io { path: PathBuf, source: io::Error },
+    /// A library file exists but could not be parsed
+    Corrupt { path: PathBuf, source: serde_json::Error },
+    /// Data could not be encoded for writing
+    Serialize(serde_json::Error),
+    /// The library was opened read-only and refuses writes
+    ReadOnly,
+    /// Another running instance holds the library's lock
+    Locked(LockInfo),
+}
+
+pub type Result<T> = std::result::Result<T, StorageError>;
+
+impl StorageError {
+    pub fn io(path: &Path, source: io::Error) -> Self {
+        Self::Io { path: path.to_path_buf(), source }
+    }
+
+    /// Whether the failure means the library can't be written at all, as opposed
+    /// to a one-off problem worth retrying
+    pub fn prevents_writing(&self) -> bool {
+        match self {
+            Self::NoDocumentsDir | Self::Corrupt { .. } | Self::ReadOnly | Self::Locked(_) => true,
+            Self::Io { source, .. } => matches!(
+                source.kind(),
+                io::ErrorKind::PermissionDenied | io::ErrorKind::ReadOnlyFilesystem
+            ),
+            Self::Serialize(_) => false,
+        }
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        matches!(self, Self::ReadOnly)
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoDocumentsDir => write!(f, "could not find your Documents folder"),
+            Self::Io { path, source } => write!(f, "{}: {}", file_name(path), source),
+            Self::Corrupt { path, source } => {
+                write!(f, "{} is damaged and could not be read ({})", file_name(path), source)
+            }
+            Self::Serialize(e) => write!(f, "could not encode library data: {}", e),
+            Self::ReadOnly => write!(f, "the library is open read-only"),
+            Self::Locked(holder) => write!(f, "the library is already open in {}", holder),
+        }
+    }
+}
+
+impl std::error::Error for StorageError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            Self::Corrupt { source, .. } => Some(source),
+            Self::Serialize(e) => Some(e),
+            Self::NoDocumentsDir | Self::ReadOnly | Self::Locked(_) => None,
+        }
+    }
+}
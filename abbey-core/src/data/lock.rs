@@ -0,0 +1,244 @@
+use crate::data::{Result, StorageError};
+use chrono::{DateTime, Duration, Local, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use uuid::Uuid;
+
+/// Name of the lock file inside the library directory
+pub const LOCK_FILE_NAME: &str = ".abbey.lock";
+
+/// How often a running instance refreshes its lock
+pub const LOCK_HEARTBEAT_SECONDS: u32 = 60;
+
+/// A lock that hasn't been refreshed for this long belongs to a process that
+/// crashed, hung or lost its connection to a shared drive
+const STALE_AFTER_MINUTES: i64 = 10;
+
+/// Locks held by this process, so they can be released from a panic hook or
+/// signal handler where `Drop` never runs
+static HELD_LOCKS: Mutex<Vec<(PathBuf, String)>> = Mutex::new(Vec::new());
+
+/// Who holds a library, as recorded in its lock file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LockInfo {
+    pub pid: u32,
+    pub host: String,
+    pub acquired_at: DateTime<Utc>,
+    pub heartbeat_at: DateTime<Utc>,
+    /// Distinguishes successive locks taken by the same process
+    token: String,
+}
+
+impl LockInfo {
+    fn new() -> Self {
+        let now = Utc::now();
+        Self {
+            pid: std::process::id(),
+            host: host_name(),
+            acquired_at: now,
+            heartbeat_at: now,
+            token: Uuid::new_v4().to_string(),
+        }
+    }
+
+    fn is_this_process(&self) -> bool {
+        self.pid == std::process::id() && self.host == host_name()
+    }
+
+    /// Whether the holder has evidently gone away without releasing the lock
+    pub fn is_stale(&self) -> bool {
+        if Utc::now() - self.heartbeat_at > Duration::minutes(STALE_AFTER_MINUTES) {
+            return true;
+        }
+        
+        // Processes on other machines can only be judged by their heartbeat
+        self.host == host_name() && !process_exists(self.pid)
+    }
+}
+
+impl fmt::Display for LockInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let since = self.acquired_at.with_timezone(&Local).format("%H:%M on %b %d");
+        if self.host == host_name() {
+            write!(f, "another Abbey window (process {}) since {}", self.pid, since)
+        } else {
+            write!(f, "Abbey on {} (process {}) since {}", self.host, self.pid, since)
+        }
+    }
+}
+
+/// Advisory lock on a library directory, held for as long as this value lives.
+/// It stops a second instance (or a second machine sharing the folder) from
+/// overwriting the first one's files with full rewrites.
+#[derive(Debug)]
+pub struct LibraryLock {
+    path: PathBuf,
+    info: LockInfo,
+}
+
+impl LibraryLock {
+    /// Take the lock on `dir`, replacing a stale one or one left by this process.
+    /// Fails with `StorageError::Locked` if another live instance holds it.
+    pub fn acquire(dir: &Path) -> Result<Self> {
+        let path = dir.join(LOCK_FILE_NAME);
+        
+        // A second attempt covers a stale lock removed on the first
+        for _ in 0..2 {
+            let info = LockInfo::new();
+            match fs::OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    write_info(&mut file, &info).map_err(|e| StorageError::io(&path, e))?;
+                    register(&path, &info.token);
+                    return Ok(Self { path, info });
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                    match read_holder(&path)? {
+                        Some(holder) if !holder.is_this_process() && !holder.is_stale() => {
+                            return Err(StorageError::Locked(holder));
+                        }
+                        holder => {
+                            if let Some(holder) = holder {
+                                log::warn!("Replacing stale library lock held by {}", holder);
+                            }
+                            remove_lock_file(&path)?;
+                        }
+                    }
+                }
+                Err(e) => return Err(StorageError::io(&path, e)),
+            }
+        }
+        
+        match read_holder(&path)? {
+            Some(holder) => Err(StorageError::Locked(holder)),
+            None => Err(StorageError::io(&path, io::ErrorKind::AlreadyExists.into())),
+        }
+    }
+
+    /// Record that the holder is still alive. Fails with `StorageError::Locked`
+    /// if another instance has taken the lock over in the meantime.
+    pub fn refresh(&mut self) -> Result<()> {
+        match read_holder(&self.path)? {
+            Some(holder) if holder.token != self.info.token => Err(StorageError::Locked(holder)),
+            _ => {
+                self.info.heartbeat_at = Utc::now();
+                
+                // Write through a temporary file so readers never see a half-written lock
+                let tmp_path = self.path.with_extension("lock.tmp");
+                let write = || -> io::Result<()> {
+                    let mut file = fs::File::create(&tmp_path)?;
+                    write_info(&mut file, &self.info)?;
+                    fs::rename(&tmp_path, &self.path)
+                };
+                write().map_err(|e| StorageError::io(&self.path, e))
+            }
+        }
+    }
+}
+
+impl Drop for LibraryLock {
+    fn drop(&mut self) {
+        release(&self.path, &self.info.token);
+        if let Ok(mut held) = HELD_LOCKS.lock() {
+            held.retain(|(_, token)| *token != self.info.token);
+        }
+    }
+}
+
+/// The instance currently holding the lock on `dir`, if any
+pub fn lock_holder(dir: &Path) -> Result<Option<LockInfo>> {
+    read_holder(&dir.join(LOCK_FILE_NAME))
+}
+
+/// Release every lock this process holds. For exit paths that skip destructors,
+/// such as panics and termination signals.
+pub fn release_held_locks() {
+    if let Ok(mut held) = HELD_LOCKS.lock() {
+        for (path, token) in held.drain(..) {
+            release(&path, &token);
+        }
+    }
+}
+
+fn register(path: &Path, token: &str) {
+    if let Ok(mut held) = HELD_LOCKS.lock() {
+        held.push((path.to_path_buf(), token.to_string()));
+    }
+}
+
+/// Remove the lock file, but only if it is still ours: a newer lock taken by
+/// this process or by another instance must survive
+fn release(path: &Path, token: &str) {
+    match read_holder(path) {
+        Ok(Some(holder)) if holder.token == token => {
+            if let Err(e) = fs::remove_file(path) {
+                log::error!("Failed to release library lock: {}", e);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn write_info(file: &mut fs::File, info: &LockInfo) -> io::Result<()> {
+    let json = serde_json::to_string_pretty(info).map_err(io::Error::other)?;
+    file.write_all(json.as_bytes())?;
+    file.sync_all()
+}
+
+/// Read the current holder. An unreadable lock is treated as held by an unknown
+/// instance while it is fresh (it may still be being written), and as stale after.
+fn read_holder(path: &Path) -> Result<Option<LockInfo>> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(StorageError::io(path, e)),
+    };
+
+    if let Ok(info) = serde_json::from_str::<LockInfo>(&contents) {
+        return Ok(Some(info));
+    }
+
+    let modified = fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .map(DateTime::<Utc>::from)
+        .unwrap_or_else(|_| Utc::now());
+    if Utc::now() - modified > Duration::minutes(STALE_AFTER_MINUTES) {
+        return Ok(None);
+    }
+
+    Ok(Some(LockInfo {
+        pid: 0,
+        host: "an unknown computer".to_string(),
+        acquired_at: modified,
+        heartbeat_at: modified,
+        token: String::new(),
+    }))
+}
+
+fn remove_lock_file(path: &Path) -> Result<()> {
+    match fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(StorageError::io(path, e)),
+    }
+}
+
+fn host_name() -> String {
+    ["/proc/sys/kernel/hostname", "/etc/hostname"]
+        .iter()
+        .filter_map(|path| fs::read_to_string(path).ok())
+        .map(|name| name.trim().to_string())
+        .find(|name| !name.is_empty())
+        .or_else(|| std::env::var("HOSTNAME").ok())
+        .unwrap_or_else(|| "localhost".to_string())
+}
+
+/// Whether a process with this id is running on this machine. Without `/proc`
+/// there's no cheap way to tell, so assume it is and rely on the heartbeat.
+fn process_exists(pid: u32) -> bool {
+    let proc_dir = Path::new("/proc");
+    !proc_dir.is_dir() || proc_dir.join(pid.to_string()).exists()
+}
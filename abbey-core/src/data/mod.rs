@@ -0,0 +1,9 @@
+mod error;
+mod lock;
+mod models;
+mod storage;
+
+pub use error::{Result, StorageError};
+pub use lock::{lock_holder, release_held_locks, LibraryLock, LockInfo, LOCK_HEARTBEAT_SECONDS};
+pub use models::*;
+pub use storage::Storage;
@@ -0,0 +1,669 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+use crate::utils::{anchors, text_stats, typography};
+
+/// A single flow session - timed free-writing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Flow {
+    pub id: String,
+    pub content: String,
+    pub duration_minutes: u32,
+    pub actual_duration_seconds: u64,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Flow {
+    pub fn new(duration_minutes: u32) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            content: String::new(),
+            duration_minutes,
+            actual_duration_seconds: 0,
+            created_at: Utc::now(),
+        }
+    }
+
+    pub fn word_count(&self) -> usize {
+        text_stats::word_count(&self.content)
+    }
+}
+
+/// A composition - essay, story, or other written piece
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Composition {
+    pub id: String,
+    pub title: String,
+    pub content: String,
+    pub notes: Vec<Note>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub archived: bool,
+    pub word_count: usize,
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub folder_id: Option<String>,
+    /// A markdown file outside the library that this composition edits in place
+    #[serde(default)]
+    pub external_path: Option<PathBuf>,
+    /// Spell checking language, like `en_GB`; `None` uses the library's
+    #[serde(default)]
+    pub language: Option<String>,
+    /// Style findings the writer dismissed, by `Issue::key`
+    #[serde(default)]
+    pub lint_ignores: Vec<String>,
+    /// Comments on stretches of `content`, unlike notes which are on the whole
+    #[serde(default)]
+    pub comments: Vec<Comment>,
+    /// Changes suggested to `content`, waiting to be accepted or rejected
+    #[serde(default)]
+    pub suggestions: Vec<Suggestion>,
+    /// Whether edits are recorded as suggestions instead of being made
+    #[serde(default)]
+    pub suggesting: bool,
+}
+
+impl Composition {
+    pub fn new() -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4().to_string(),
+            title: now.format("%Y-%m-%d %H:%M").to_string(),
+            content: String::new(),
+            notes: Vec::new(),
+            created_at: now,
+            updated_at: now,
+            archived: false,
+            word_count: 0,
+            tags: Vec::new(),
+            folder_id: None,
+            external_path: None,
+            language: None,
+            lint_ignores: Vec::new(),
+            comments: Vec::new(),
+            suggestions: Vec::new(),
+            suggesting: false,
+        }
+    }
+
+    pub fn update_word_count(&mut self) {
+        self.word_count = text_stats::word_count(&self.content_without_suggestions());
+    }
+
+    /// The text as it reads before any suggested change, with suggested
+    /// insertions left out and suggested deletions kept. Everything but the
+    /// editor reads this rather than `content`.
+    pub fn content_without_suggestions(&self) -> Cow<'_, str> {
+        let insertions: Vec<(usize, usize)> = self
+            .suggestions
+            .iter()
+            .filter(|suggestion| suggestion.kind == SuggestionKind::Insertion)
+            .map(|suggestion| (suggestion.start, suggestion.end))
+            .collect();
+        if insertions.is_empty() {
+            return Cow::Borrowed(&self.content);
+        }
+        Cow::Owned(
+            self.content
+                .chars()
+                .enumerate()
+                .filter(|(i, _)| !insertions.iter().any(|&(start, end)| start <= *i && *i < end))
+                .map(|(_, c)| c)
+                .collect(),
+        )
+    }
+
+    /// The composition as it was before any suggested change, every
+    /// suggestion rejected and its comments moved to match, for exports and
+    /// anything else that shows it outside the editor
+    pub fn without_suggestions(&self) -> Cow<'_, Composition> {
+        if self.suggestions.is_empty() {
+            return Cow::Borrowed(self);
+        }
+        let mut composition = self.clone();
+        composition.resolve_all_suggestions(false);
+        composition.updated_at = self.updated_at;
+        Cow::Owned(composition)
+    }
+
+    /// Change the text as it reads without suggestions to `text`, making the
+    /// same change to `content`, so suggestions it doesn't reach stay pending
+    /// and move along with comments
+    pub fn set_content_without_suggestions(&mut self, text: &str) {
+        let old: Vec<char> = self.content_without_suggestions().chars().collect();
+        let new: Vec<char> = text.chars().collect();
+        let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+        let suffix = old[prefix..]
+            .iter()
+            .rev()
+            .zip(new[prefix..].iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+        if prefix == old.len() && prefix == new.len() {
+            return;
+        }
+
+        // Where each char of the text without suggestions is in `content`
+        let inserted = |i: usize| {
+            self.suggestions
+                .iter()
+                .any(|suggestion| suggestion.kind == SuggestionKind::Insertion && suggestion.start <= i && i < suggestion.end)
+        };
+        let length = self.content.chars().count();
+        let kept: Vec<usize> = (0..length).filter(|&i| !inserted(i)).collect();
+        let start = kept.get(prefix).copied().unwrap_or(length);
+        let end = match old.len() - suffix {
+            changed_end if changed_end == prefix => start,
+            changed_end => kept[changed_end - 1] + 1,
+        };
+
+        let before = self.content.clone();
+        let byte = |chars: usize| before.char_indices().nth(chars).map_or(before.len(), |(i, _)| i);
+        let replacement: String = new[prefix..new.len() - suffix].iter().collect();
+        self.content = format!("{}{}{}", &before[..byte(start)], replacement, &before[byte(end)..]);
+        self.follow_edit(&before);
+    }
+
+    /// Add `text` as a new paragraph at the end
+    pub fn append_paragraph(&mut self, text: &str) {
+        let content = self.content.trim_end();
+        self.content = if content.is_empty() {
+            text.to_string()
+        } else {
+            format!("{}\n\n{}", content, text)
+        };
+        self.updated_at = Utc::now();
+        self.update_word_count();
+    }
+
+    /// Move comments and suggestions along with a change to `content` from
+    /// `old_content`, and back onto their text where the change reached it.
+    /// Passing the current content finds any whose anchors no longer match it.
+    /// A suggestion whose text is gone is dropped.
+    pub fn follow_edit(&mut self, old_content: &str) {
+        for comment in self.comments.iter_mut() {
+            let range = anchors::follow(comment.start..comment.end, &comment.quote, old_content, &self.content);
+            if !range.is_empty() {
+                comment.quote = self.content.chars().skip(range.start).take(range.len()).collect();
+            }
+            comment.start = range.start;
+            comment.end = range.end;
+        }
+
+        let content = &self.content;
+        self.suggestions.retain_mut(|suggestion| {
+            let range = anchors::follow(suggestion.start..suggestion.end, &suggestion.text, old_content, content);
+            suggestion.text = content.chars().skip(range.start).take(range.len()).collect();
+            suggestion.start = range.start;
+            suggestion.end = range.end;
+            !range.is_empty()
+        });
+    }
+
+    /// Accept or reject a suggested change, making it part of the text or
+    /// taking it out. Returns false if there's no such suggestion.
+    pub fn resolve_suggestion(&mut self, id: &str, accept: bool) -> bool {
+        let index = match self.suggestions.iter().position(|suggestion| suggestion.id == id) {
+            Some(index) => index,
+            None => return false,
+        };
+        let suggestion = self.suggestions.remove(index);
+        // Accepting a deletion and rejecting an insertion both remove the text
+        if accept == (suggestion.kind == SuggestionKind::Deletion) {
+            let byte = |chars: usize| self.content.char_indices().nth(chars).map_or(self.content.len(), |(i, _)| i);
+            let range = byte(suggestion.start)..byte(suggestion.end);
+            let old = self.content.clone();
+            self.content.replace_range(range, "");
+            self.follow_edit(&old);
+            self.update_word_count();
+        }
+        self.updated_at = Utc::now();
+        true
+    }
+
+    /// Accept or reject every suggested change
+    pub fn resolve_all_suggestions(&mut self, accept: bool) {
+        while let Some(id) = self.suggestions.last().map(|suggestion| suggestion.id.clone()) {
+            self.resolve_suggestion(&id, accept);
+        }
+    }
+}
+
+impl Default for Composition {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A note attached to a composition
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Note {
+    pub id: String,
+    pub content: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Note {
+    pub fn new(content: String) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            content,
+            created_at: Utc::now(),
+        }
+    }
+}
+
+/// A comment on a stretch of a composition's text
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Comment {
+    pub id: String,
+    /// Where the comment is anchored in the composition's content, in chars
+    pub start: usize,
+    pub end: usize,
+    /// The text between `start` and `end` when they were last set, kept when
+    /// that text is deleted so the comment can say what it was on
+    pub quote: String,
+    pub content: String,
+    pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub resolved: bool,
+    #[serde(default)]
+    pub replies: Vec<CommentReply>,
+}
+
+impl Comment {
+    pub fn new(start: usize, end: usize, quote: String, content: String) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            start,
+            end,
+            quote,
+            content,
+            created_at: Utc::now(),
+            resolved: false,
+            replies: Vec::new(),
+        }
+    }
+
+    /// Whether the text the comment was on has been deleted
+    pub fn is_detached(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+/// An answer to a comment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommentReply {
+    pub id: String,
+    pub content: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl CommentReply {
+    pub fn new(content: String) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            content,
+            created_at: Utc::now(),
+        }
+    }
+}
+
+/// A change suggested to a composition's text. Suggested insertions and
+/// deletions alike stay in the content, marked, until they're resolved;
+/// `Composition::content_without_suggestions` reads it without them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Suggestion {
+    pub id: String,
+    pub kind: SuggestionKind,
+    /// The text suggested in or out, in chars of the composition's content
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+    pub author: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Suggestion {
+    pub fn new(kind: SuggestionKind, start: usize, end: usize, text: String, author: String) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            kind,
+            start,
+            end,
+            text,
+            author,
+            created_at: Utc::now(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SuggestionKind {
+    Insertion,
+    Deletion,
+}
+
+/// A folder for organizing compositions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Folder {
+    pub id: String,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub expanded: bool,
+}
+
+impl Folder {
+    pub fn new(name: String) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            name,
+            created_at: Utc::now(),
+            expanded: true,
+        }
+    }
+}
+
+/// A project - collection of compositions forming a book or anthology
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Project {
+    pub id: String,
+    pub title: String,
+    pub description: String,
+    pub composition_ids: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Project {
+    pub fn new(title: String) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4().to_string(),
+            title,
+            description: String::new(),
+            composition_ids: Vec::new(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    pub fn add_composition(&mut self, composition_id: String) {
+        if !self.composition_ids.contains(&composition_id) {
+            self.composition_ids.push(composition_id);
+            self.updated_at = Utc::now();
+        }
+    }
+
+    pub fn remove_composition(&mut self, composition_id: &str) {
+        self.composition_ids.retain(|id| id != composition_id);
+        self.updated_at = Utc::now();
+    }
+
+    pub fn reorder_compositions(&mut self, new_order: Vec<String>) {
+        self.composition_ids = new_order;
+        self.updated_at = Utc::now();
+    }
+}
+
+/// Microblog settings for publishing
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct MicroblogSettings {
+    pub endpoint: String,
+    pub api_key: String,
+    pub blog_id: Option<String>,
+}
+
+/// Window and editor state restored on startup
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionState {
+    pub cursor_offset: i32,
+    pub scroll_position: f64,
+    pub sidebar_visible: bool,
+    pub notes_visible: bool,
+    pub preview_active: bool,
+    pub split_active: bool,
+    pub nav_section: String,
+    pub window_width: i32,
+    pub window_height: i32,
+    pub maximized: bool,
+}
+
+impl Default for SessionState {
+    fn default() -> Self {
+        Self {
+            cursor_offset: 0,
+            scroll_position: 0.0,
+            sidebar_visible: true,
+            notes_visible: false,
+            preview_active: false,
+            split_active: false,
+            nav_section: "writing".to_string(),
+            window_width: 1200,
+            window_height: 800,
+            maximized: false,
+        }
+    }
+}
+
+/// Application settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Settings {
+    pub theme: String,
+    pub font_size: i32,
+    pub line_height: f64,
+    pub microblog: MicroblogSettings,
+    pub last_opened_composition: Option<String>,
+    /// Session state for this library (settings.json lives in the library directory)
+    #[serde(default)]
+    pub session: SessionState,
+    #[serde(default)]
+    pub api: ApiSettings,
+    #[serde(default)]
+    pub spelling: SpellingSettings,
+    #[serde(default)]
+    pub lint: LintSettings,
+    #[serde(default)]
+    pub focus: FocusSettings,
+    #[serde(default)]
+    pub typography: TypographySettings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            theme: "system-light".to_string(),
+            font_size: 18,
+            line_height: 1.8,
+            microblog: MicroblogSettings::default(),
+            last_opened_composition: None,
+            session: SessionState::default(),
+            api: ApiSettings::default(),
+            spelling: SpellingSettings::default(),
+            lint: LintSettings::default(),
+            focus: FocusSettings::default(),
+            typography: TypographySettings::default(),
+        }
+    }
+}
+
+/// Spell checking as you type
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpellingSettings {
+    pub enabled: bool,
+    /// Language for compositions that don't pick their own; `None` follows the locale
+    pub language: Option<String>,
+}
+
+impl Default for SpellingSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            language: None,
+        }
+    }
+}
+
+/// Style checking in the editor
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LintSettings {
+    /// Underline findings as you type
+    pub enabled: bool,
+    /// Rules turned off, by id
+    pub disabled_rules: Vec<String>,
+}
+
+impl Default for LintSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            disabled_rules: Vec::new(),
+        }
+    }
+}
+
+/// Distraction-free writing in the composition editor
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FocusSettings {
+    /// Whether focus mode is on, so it comes back after a restart
+    pub enabled: bool,
+    /// How much of the text around the cursor stays undimmed
+    pub scope: FocusScope,
+    /// Keep the cursor line in the middle of the window
+    pub typewriter: bool,
+    /// Width of the text column, in average characters
+    pub column_width: u32,
+}
+
+impl Default for FocusSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            scope: FocusScope::Sentence,
+            typewriter: true,
+            column_width: 72,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FocusScope {
+    Sentence,
+    Paragraph,
+}
+
+/// Typographic punctuation in place of what's on the keyboard
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TypographySettings {
+    /// Substitute curly quotes, dashes and ellipses as you type
+    pub smart_punctuation: bool,
+    /// Non-breaking spaces around French punctuation
+    pub french_spacing: bool,
+}
+
+impl TypographySettings {
+    pub fn options(&self) -> typography::Options {
+        typography::Options {
+            french_spacing: self.french_spacing,
+        }
+    }
+}
+
+/// The local HTTP API, off until the user turns it on
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ApiSettings {
+    pub enabled: bool,
+    pub port: u16,
+    /// Clients send this as `Authorization: Bearer <token>`
+    pub token: String,
+}
+
+impl ApiSettings {
+    pub const DEFAULT_PORT: u16 = 7717;
+
+    /// A fresh random token, replacing any clients' access
+    pub fn generate_token() -> String {
+        Uuid::new_v4().simple().to_string()
+    }
+}
+
+impl Default for ApiSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: Self::DEFAULT_PORT,
+            token: String::new(),
+        }
+    }
+}
+
+/// All flows combined into one document for viewing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlowDocument {
+    pub flows: Vec<Flow>,
+    pub total_word_count: usize,
+    pub total_time_seconds: u64,
+}
+
+impl FlowDocument {
+    pub fn new(flows: Vec<Flow>) -> Self {
+        let total_word_count = flows.iter().map(|f| f.word_count()).sum();
+        let total_time_seconds = flows.iter().map(|f| f.actual_duration_seconds).sum();
+        
+        Self {
+            flows,
+            total_word_count,
+            total_time_seconds,
+        }
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut md = String::from("# Flow Journal\n\n");
+        md.push_str(&format!(
+            "*Total sessions: {} | Total words: {} | Total time: {}*\n\n",
+            self.flows.len(),
+            self.total_word_count,
+            format_duration(self.total_time_seconds)
+        ));
+        md.push_str("---\n\n");
+
+        for flow in &self.flows {
+            md.push_str(&format!(
+                "## {}\n\n",
+                flow.created_at.format("%B %d, %Y at %H:%M")
+            ));
+            md.push_str(&format!(
+                "*{} minutes | {} words*\n\n",
+                flow.duration_minutes,
+                flow.word_count()
+            ));
+            md.push_str(&flow.content);
+            md.push_str("\n\n---\n\n");
+        }
+
+        md
+    }
+}
+
+fn format_duration(seconds: u64) -> String {
+    let hours = seconds / 3600;
+    let minutes = (seconds % 3600) / 60;
+    
+    if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else {
+        format!("{}m", minutes)
+    }
+}
@@ -0,0 +1,459 @@
+use crate::data::{Composition, Flow, Folder, LibraryLock, Project, Result, Settings, StorageError};
+use crate::export;
+use directories::UserDirs;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::cell::{Cell, RefCell};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+pub struct Storage {
+    base_dir: PathBuf,
+    compositions_dir: PathBuf,
+    flows_dir: PathBuf,
+    projects_dir: PathBuf,
+    recovery_dir: PathBuf,
+    read_only: Cell<bool>,
+    lock: RefCell<Option<LibraryLock>>,
+}
+
+impl Storage {
+    /// Open the default library in `~/Documents/Abbey` for writing
+    pub fn new() -> Result<Self> {
+        Self::open(Self::default_base_dir()?)
+    }
+        
+    /// Location of the default library
+    pub fn default_base_dir() -> Result<PathBuf> {
+        let user_dirs = UserDirs::new().ok_or(StorageError::NoDocumentsDir)?;
+        let documents_dir = user_dirs.document_dir().ok_or(StorageError::NoDocumentsDir)?;
+        Ok(documents_dir.join("Abbey"))
+    }
+        
+    /// Open a library for writing, creating its directory structure and taking its
+    /// lock. Fails if the directory can't be created or written to, or if another
+    /// instance already has it open.
+    pub fn open(base_dir: PathBuf) -> Result<Self> {
+        let storage = Self::with_base_dir(base_dir, false);
+        
+        // Create directory structure
+        for dir in [
+            &storage.base_dir,
+            &storage.compositions_dir,
+            &storage.flows_dir,
+            &storage.projects_dir,
+            &storage.recovery_dir,
+        ] {
+            fs::create_dir_all(dir).map_err(|e| StorageError::io(dir, e))?;
+        }
+        
+        storage.lock.replace(Some(LibraryLock::acquire(&storage.base_dir)?));
+        storage.check_writable()?;
+        Ok(storage)
+    }
+
+    /// Open a library without touching the disk. Loads work as long as the files
+    /// are readable; every write returns `StorageError::ReadOnly`.
+    pub fn open_read_only(base_dir: PathBuf) -> Self {
+        Self::with_base_dir(base_dir, true)
+    }
+
+    fn with_base_dir(base_dir: PathBuf, read_only: bool) -> Self {
+        Self {
+            compositions_dir: base_dir.join("compositions"),
+            flows_dir: base_dir.join("flows"),
+            projects_dir: base_dir.join("projects"),
+            recovery_dir: base_dir.join(".recovery"),
+            base_dir,
+            read_only: Cell::new(read_only),
+            lock: RefCell::new(None),
+        }
+    }
+    
+    /// Get the base Abbey directory path
+    pub fn base_dir(&self) -> &PathBuf {
+        &self.base_dir
+    }
+    
+    /// Get the compositions directory path
+    pub fn compositions_dir(&self) -> &PathBuf {
+        &self.compositions_dir
+    }
+    
+    /// Get the flows directory path  
+    pub fn flows_dir(&self) -> &PathBuf {
+        &self.flows_dir
+    }
+    
+    /// Get the projects directory path
+    pub fn projects_dir(&self) -> &PathBuf {
+        &self.projects_dir
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only.get()
+    }
+
+    /// Stop (or resume) writing to the library, e.g. after finding a damaged file
+    /// that a full rewrite would otherwise replace
+    pub fn set_read_only(&self, read_only: bool) {
+        self.read_only.set(read_only);
+    }
+
+    /// Keep the library lock fresh so other instances don't take it for stale.
+    /// If another instance has taken it over anyway, stop writing.
+    pub fn refresh_lock(&self) -> Result<()> {
+        let result = match *self.lock.borrow_mut() {
+            Some(ref mut held) => held.refresh(),
+            None => return Ok(()),
+        };
+        
+        if let Err(StorageError::Locked(_)) = result {
+            self.lock.replace(None);
+            self.read_only.set(true);
+        }
+        result
+    }
+
+    /// Probe the library by writing and removing a small file
+    fn check_writable(&self) -> Result<()> {
+        let probe = self.base_dir.join(".write-test");
+        self.write_file(&probe, b"abbey")?;
+        fs::remove_file(&probe).map_err(|e| StorageError::io(&probe, e))
+    }
+
+    // ========== Compositions ==========
+
+    pub fn save_compositions(&self, compositions: &[Composition]) -> Result<()> {
+        self.write_json(&self.base_dir.join("compositions.json"), compositions)
+    }
+
+    pub fn load_compositions(&self) -> Result<Vec<Composition>> {
+        Ok(self.read_json(&self.base_dir.join("compositions.json"))?.unwrap_or_default())
+    }
+
+    pub fn save_composition(&self, composition: &Composition) -> Result<()> {
+        // Save to the index
+        let mut compositions = self.load_compositions()?;
+        
+        if let Some(pos) = compositions.iter().position(|c| c.id == composition.id) {
+            compositions[pos] = composition.clone();
+        } else {
+            compositions.insert(0, composition.clone());
+        }
+        
+        self.save_compositions(&compositions)?;
+        
+        // Also save as individual markdown file, or back to the file it edits
+        if composition.external_path.is_some() {
+            self.write_external(composition)?;
+        } else {
+            self.save_composition_as_markdown(composition)?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Save composition as a readable markdown file
+    pub fn save_composition_as_markdown(&self, composition: &Composition) -> Result<()> {
+        let filename = self.sanitize_filename(&composition.title);
+        let path = self.compositions_dir.join(format!("{}.md", filename));
+        self.write_file(&path, export::composition_to_markdown(composition).as_bytes())
+    }
+        
+    /// Write a linked composition's content back to its external file
+    pub fn write_external(&self, composition: &Composition) -> Result<()> {
+        let path = match composition.external_path {
+            Some(ref path) => path,
+            None => return Ok(()),
+        };
+        
+        // The file holds the text without pending suggestions, which only
+        // the library keeps. Leave it (and its modification time) alone if
+        // nothing changed.
+        let content = composition.content_without_suggestions();
+        if fs::read_to_string(path).ok().as_deref() == Some(&*content) {
+            return Ok(());
+        }
+        self.write_file(path, content.as_bytes())
+    }
+
+    /// Pick up edits made to a linked composition's file outside Abbey.
+    /// Returns whether the content changed.
+    pub fn read_external(&self, composition: &mut Composition) -> Result<bool> {
+        let path = match composition.external_path {
+            Some(ref path) => path,
+            None => return Ok(false),
+        };
+        
+        let content = fs::read_to_string(path).map_err(|e| StorageError::io(path, e))?;
+        if content == composition.content_without_suggestions() {
+            return Ok(false);
+        }
+        
+        let old = std::mem::replace(&mut composition.content, content);
+        composition.follow_edit(&old);
+        composition.update_word_count();
+        composition.updated_at = chrono::Utc::now();
+        Ok(true)
+    }
+
+    // ========== Flows ==========
+
+    pub fn save_flows(&self, flows: &[Flow]) -> Result<()> {
+        self.write_json(&self.base_dir.join("flows.json"), flows)
+    }
+
+    pub fn load_flows(&self) -> Result<Vec<Flow>> {
+        Ok(self.read_json(&self.base_dir.join("flows.json"))?.unwrap_or_default())
+    }
+
+    pub fn append_flow(&self, flow: &Flow) -> Result<()> {
+        let mut flows = self.load_flows()?;
+        flows.insert(0, flow.clone());
+        self.save_flows(&flows)?;
+        
+        // Also append to the main flow document
+        self.append_flow_to_document(flow)?;
+        
+        Ok(())
+    }
+    
+    /// Append a flow session to the main flow document markdown file
+    pub fn append_flow_to_document(&self, flow: &Flow) -> Result<()> {
+        let path = self.flows_dir.join("Flow Journal.md");
+        
+        let timestamp = flow.created_at.format("%Y-%m-%d %H:%M");
+        let duration = flow.duration_minutes;
+        let words = flow.word_count();
+        
+        let entry = format!(
+            "\n\n---\n\n## {} ({} min, {} words)\n\n{}\n",
+            timestamp,
+            duration,
+            words,
+            flow.content
+        );
+        
+        // Read existing content or create header
+        let mut content = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                "# Flow Journal\n\nA collection of free-writing sessions.\n".to_string()
+            }
+            Err(e) => return Err(StorageError::io(&path, e)),
+        };
+        
+        content.push_str(&entry);
+        
+        self.write_file(&path, content.as_bytes())
+    }
+
+    // ========== Projects ==========
+
+    pub fn save_projects(&self, projects: &[Project]) -> Result<()> {
+        self.write_json(&self.base_dir.join("projects.json"), projects)
+    }
+
+    pub fn load_projects(&self) -> Result<Vec<Project>> {
+        Ok(self.read_json(&self.base_dir.join("projects.json"))?.unwrap_or_default())
+    }
+
+    pub fn save_project(&self, project: &Project) -> Result<()> {
+        let mut projects = self.load_projects()?;
+        
+        if let Some(pos) = projects.iter().position(|p| p.id == project.id) {
+            projects[pos] = project.clone();
+        } else {
+            projects.insert(0, project.clone());
+        }
+        
+        self.save_projects(&projects)?;
+        
+        // Create project folder if it has compositions
+        if !project.composition_ids.is_empty() {
+            let project_folder = self.projects_dir.join(self.sanitize_filename(&project.title));
+            fs::create_dir_all(&project_folder).map_err(|e| StorageError::io(&project_folder, e))?;
+        }
+        
+        Ok(())
+    }
+
+    // ========== Folders ==========
+
+    pub fn save_folders(&self, folders: &[Folder]) -> Result<()> {
+        self.write_json(&self.base_dir.join("folders.json"), folders)
+    }
+
+    pub fn load_folders(&self) -> Result<Vec<Folder>> {
+        Ok(self.read_json(&self.base_dir.join("folders.json"))?.unwrap_or_default())
+    }
+
+    // ========== Settings ==========
+
+    pub fn save_settings(&self, settings: &Settings) -> Result<()> {
+        self.write_json(&self.base_dir.join("settings.json"), settings)
+    }
+
+    pub fn load_settings(&self) -> Result<Settings> {
+        Ok(self.read_json(&self.base_dir.join("settings.json"))?.unwrap_or_default())
+    }
+        
+    // ========== Spelling ==========
+
+    /// Words added to the spell checker in this library, one per line in `dictionary.txt`
+    pub fn load_personal_dictionary(&self) -> Result<Vec<String>> {
+        let path = self.base_dir.join("dictionary.txt");
+        match fs::read_to_string(&path) {
+            Ok(contents) => Ok(contents
+                .lines()
+                .map(str::trim)
+                .filter(|word| !word.is_empty())
+                .map(String::from)
+                .collect()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(StorageError::io(&path, e)),
+        }
+    }
+
+    pub fn save_personal_dictionary(&self, words: &[String]) -> Result<()> {
+        let mut contents = words.join("\n");
+        contents.push('\n');
+        self.write_file(&self.base_dir.join("dictionary.txt"), contents.as_bytes())
+    }
+
+    // ========== Recovery ==========
+
+    /// Journal an unsaved composition so its edits survive a crash before autosave
+    pub fn write_recovery(&self, composition: &Composition) -> Result<()> {
+        let path = self.recovery_dir.join(format!("{}.json", composition.id));
+        let json = serde_json::to_string(composition).map_err(StorageError::Serialize)?;
+        self.write_file(&path, json.as_bytes())
+    }
+
+    /// Load every journaled composition left behind by an unclean exit
+    pub fn load_recovery(&self) -> Result<Vec<Composition>> {
+        let mut recovered = Vec::new();
+        
+        let entries = match fs::read_dir(&self.recovery_dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(recovered),
+            Err(e) => return Err(StorageError::io(&self.recovery_dir, e)),
+        };
+        
+        for entry in entries {
+            let path = entry.map_err(|e| StorageError::io(&self.recovery_dir, e))?.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            
+            let parsed = fs::read_to_string(&path)
+                .ok()
+                .and_then(|contents| serde_json::from_str::<Composition>(&contents).ok());
+            match parsed {
+                Some(composition) => recovered.push(composition),
+                None => log::warn!("Skipping unreadable recovery file {}", path.display()),
+            }
+        }
+        
+        Ok(recovered)
+    }
+        
+    /// Drop the recovery journal once its contents are safely saved
+    pub fn clear_recovery(&self) -> Result<()> {
+        self.ensure_writable()?;
+        
+        let entries = match fs::read_dir(&self.recovery_dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(StorageError::io(&self.recovery_dir, e)),
+        };
+        
+        for entry in entries {
+            let path = entry.map_err(|e| StorageError::io(&self.recovery_dir, e))?.path();
+            if path.is_file() {
+                fs::remove_file(&path).map_err(|e| StorageError::io(&path, e))?;
+            }
+        }
+        Ok(())
+    }
+    
+    // ========== Utilities ==========
+    
+    /// Sanitize a string for use as a filename
+    fn sanitize_filename(&self, name: &str) -> String {
+        name.chars()
+            .map(|c| match c {
+                '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+                _ => c,
+            })
+            .collect::<String>()
+            .trim()
+            .to_string()
+    }
+
+    fn ensure_writable(&self) -> Result<()> {
+        if self.read_only.get() {
+            Err(StorageError::ReadOnly)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Read and parse a JSON file, returning `None` if it doesn't exist yet
+    fn read_json<T: DeserializeOwned>(&self, path: &Path) -> Result<Option<T>> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(StorageError::io(path, e)),
+        };
+        
+        serde_json::from_str(&contents)
+            .map(Some)
+            .map_err(|source| StorageError::Corrupt { path: path.to_path_buf(), source })
+    }
+
+    fn write_json<T: Serialize + ?Sized>(&self, path: &Path, value: &T) -> Result<()> {
+        let json = serde_json::to_string_pretty(value).map_err(StorageError::Serialize)?;
+        self.write_file(path, json.as_bytes())
+    }
+
+    /// Write a file via a temporary sibling and rename, so a full disk or a crash
+    /// mid-write leaves the previous version intact instead of a truncated file
+    fn write_file(&self, path: &Path, contents: &[u8]) -> Result<()> {
+        self.ensure_writable()?;
+        
+        let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+        tmp_name.push(".tmp");
+        let tmp_path = path.with_file_name(tmp_name);
+        
+        let write = || -> io::Result<()> {
+            let mut file = fs::File::create(&tmp_path)?;
+            file.write_all(contents)?;
+            file.sync_all()?;
+            fs::rename(&tmp_path, path)
+        };
+        
+        write().map_err(|e| {
+            let _ = fs::remove_file(&tmp_path);
+            StorageError::io(path, e)
+        })
+    }
+
+    // ========== Export ==========
+
+    /// Export a project to markdown and keep a copy in its folder under `projects/`
+    pub fn export_project_to_markdown(&self, project: &Project, compositions: &[Composition]) -> Result<String> {
+        let md = export::project_to_markdown(project, compositions);
+        
+        let project_folder = self.projects_dir.join(self.sanitize_filename(&project.title));
+        self.ensure_writable()?;
+        fs::create_dir_all(&project_folder).map_err(|e| StorageError::io(&project_folder, e))?;
+        
+        let path = project_folder.join(format!("{}.md", self.sanitize_filename(&project.title)));
+        self.write_file(&path, md.as_bytes())?;
+        
+        Ok(md)
+    }
+}
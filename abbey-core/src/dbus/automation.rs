@@ -0,0 +1,216 @@
+//! `app.abbey.Abbey.Automation`, for sending text into Abbey from scripts,
+//! hotkey daemons and other tools
+
+use crate::data::StorageError;
+use crate::stats::LibraryStats;
+use gio::glib;
+use gio::prelude::*;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+pub const AUTOMATION_INTERFACE: &str = "app.abbey.Abbey.Automation";
+
+const INTERFACE_XML: &str = r#"
+<node>
+  <interface name="app.abbey.Abbey.Automation">
+    <method name="CreateComposition">
+      <arg type="s" name="title" direction="in"/>
+      <arg type="s" name="text" direction="in"/>
+      <arg type="s" name="id" direction="out"/>
+    </method>
+    <method name="AppendToComposition">
+      <arg type="s" name="composition" direction="in"/>
+      <arg type="s" name="text" direction="in"/>
+      <arg type="s" name="id" direction="out"/>
+    </method>
+    <method name="AddNote">
+      <arg type="s" name="composition" direction="in"/>
+      <arg type="s" name="text" direction="in"/>
+      <arg type="s" name="note_id" direction="out"/>
+    </method>
+    <method name="StartFlow">
+      <arg type="u" name="minutes" direction="in"/>
+    </method>
+    <method name="GetStats">
+      <arg type="a{sv}" name="stats" direction="out"/>
+    </method>
+    <method name="ShowQuickCapture">
+      <arg type="u" name="timestamp" direction="in"/>
+    </method>
+  </interface>
+</node>
+"#;
+
+/// Longest flow that can be started remotely, in minutes
+pub const MAX_FLOW_MINUTES: u32 = 24 * 60;
+
+/// Why an automation call failed, sent back as a D-Bus error
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AutomationError {
+    InvalidArgs(String),
+    NotFound(String),
+    /// A title or id prefix matched more than one composition
+    Ambiguous { query: String, matches: Vec<String> },
+    ReadOnly,
+    /// Something already in progress, such as a running flow
+    Busy(String),
+    Failed(String),
+}
+
+impl AutomationError {
+    /// The D-Bus error name callers can match on
+    pub fn dbus_name(&self) -> &'static str {
+        match self {
+            Self::InvalidArgs(_) => "org.freedesktop.DBus.Error.InvalidArgs",
+            Self::NotFound(_) => "app.abbey.Abbey.Error.NotFound",
+            Self::Ambiguous { .. } => "app.abbey.Abbey.Error.Ambiguous",
+            Self::ReadOnly => "app.abbey.Abbey.Error.ReadOnly",
+            Self::Busy(_) => "app.abbey.Abbey.Error.Busy",
+            Self::Failed(_) => "app.abbey.Abbey.Error.Failed",
+        }
+    }
+}
+
+impl fmt::Display for AutomationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidArgs(message) | Self::NotFound(message) | Self::Busy(message) | Self::Failed(message) => {
+                write!(f, "{}", message)
+            }
+            Self::Ambiguous { query, matches } => {
+                write!(f, "\"{}\" matches more than one composition: {}", query, matches.join(", "))
+            }
+            Self::ReadOnly => write!(f, "the library is read-only"),
+        }
+    }
+}
+
+impl From<StorageError> for AutomationError {
+    fn from(e: StorageError) -> Self {
+        if e.is_read_only() {
+            Self::ReadOnly
+        } else {
+            Self::Failed(e.to_string())
+        }
+    }
+}
+
+/// What the app does for each call. Compositions are named by id, title or
+/// unique id prefix, as on the command line.
+pub trait AutomationHandler {
+    /// Create a composition, returning its id. Without a title the usual
+    /// date-and-time title is used.
+    fn create_composition(&self, title: Option<String>, text: String) -> Result<String, AutomationError>;
+
+    /// Add `text` as a new paragraph, returning the composition's id
+    fn append_to_composition(&self, composition: &str, text: &str) -> Result<String, AutomationError>;
+
+    /// Attach a note, returning the note's id
+    fn add_note(&self, composition: &str, text: &str) -> Result<String, AutomationError>;
+
+    fn start_flow(&self, minutes: u32) -> Result<(), AutomationError>;
+
+    fn stats(&self) -> Result<LibraryStats, AutomationError>;
+
+    fn show_quick_capture(&self, timestamp: u32);
+}
+
+/// Export the automation interface at `object_path` on `connection`
+pub fn register_automation(
+    connection: &gio::DBusConnection,
+    object_path: &str,
+    handler: Rc<dyn AutomationHandler>,
+) -> Result<gio::RegistrationId, glib::Error> {
+    let node = gio::DBusNodeInfo::for_xml(INTERFACE_XML)?;
+    let interface = node
+        .lookup_interface(AUTOMATION_INTERFACE)
+        .expect("automation interface is defined in INTERFACE_XML");
+
+    connection
+        .register_object(object_path, &interface)
+        .method_call(move |_, _, _, _, method, parameters, invocation| {
+            match handle_call(handler.as_ref(), method, &parameters) {
+                Ok(reply) => invocation.return_value(reply.as_ref()),
+                Err(e) => invocation.return_dbus_error(e.dbus_name(), &e.to_string()),
+            }
+        })
+        .build()
+}
+
+fn handle_call(
+    handler: &dyn AutomationHandler,
+    method: &str,
+    parameters: &glib::Variant,
+) -> Result<Option<glib::Variant>, AutomationError> {
+    let invalid = || AutomationError::InvalidArgs(format!("unexpected arguments {} for {}", parameters.type_(), method));
+
+    match method {
+        "CreateComposition" => {
+            let (title, text) = parameters.get::<(String, String)>().ok_or_else(invalid)?;
+            let title = Some(title.trim().to_string()).filter(|t| !t.is_empty());
+            let id = handler.create_composition(title, text)?;
+            Ok(Some((id,).to_variant()))
+        }
+        "AppendToComposition" => {
+            let (composition, text) = parameters.get::<(String, String)>().ok_or_else(invalid)?;
+            let id = handler.append_to_composition(&composition, non_empty(&text)?)?;
+            Ok(Some((id,).to_variant()))
+        }
+        "AddNote" => {
+            let (composition, text) = parameters.get::<(String, String)>().ok_or_else(invalid)?;
+            let note_id = handler.add_note(&composition, non_empty(&text)?)?;
+            Ok(Some((note_id,).to_variant()))
+        }
+        "StartFlow" => {
+            let (minutes,) = parameters.get::<(u32,)>().ok_or_else(invalid)?;
+            if minutes == 0 || minutes > MAX_FLOW_MINUTES {
+                return Err(AutomationError::InvalidArgs(format!(
+                    "a flow lasts between 1 and {} minutes",
+                    MAX_FLOW_MINUTES
+                )));
+            }
+            handler.start_flow(minutes)?;
+            Ok(None)
+        }
+        "GetStats" => {
+            let stats = handler.stats()?;
+            Ok(Some((stats_dict(&stats),).to_variant()))
+        }
+        "ShowQuickCapture" => {
+            let (timestamp,) = parameters.get::<(u32,)>().ok_or_else(invalid)?;
+            handler.show_quick_capture(timestamp);
+            Ok(None)
+        }
+        _ => Err(AutomationError::InvalidArgs(format!("unknown method {}", method))),
+    }
+}
+
+fn non_empty(text: &str) -> Result<&str, AutomationError> {
+    let text = text.trim_end();
+    if text.trim().is_empty() {
+        Err(AutomationError::InvalidArgs("the text is empty".to_string()))
+    } else {
+        Ok(text)
+    }
+}
+
+/// Library statistics keyed as in `abbey stats --json`
+fn stats_dict(stats: &LibraryStats) -> HashMap<String, glib::Variant> {
+    let counts = [
+        ("compositions", stats.compositions as u64),
+        ("archived", stats.archived as u64),
+        ("composition_words", stats.composition_words as u64),
+        ("notes", stats.notes as u64),
+        ("projects", stats.projects as u64),
+        ("flows", stats.flows as u64),
+        ("flow_words", stats.flow_words as u64),
+        ("flow_seconds", stats.flow_seconds),
+        ("flow_streak_days", u64::from(stats.flow_streak_days)),
+    ];
+
+    counts
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value.to_variant()))
+        .collect()
+}
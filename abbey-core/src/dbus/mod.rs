@@ -0,0 +1,13 @@
+//! Abbey's interfaces on the session bus, registered by the app alongside its
+//! `org.gtk.Application` object
+
+mod automation;
+mod search_provider;
+
+pub use automation::{register_automation, AutomationError, AutomationHandler, AUTOMATION_INTERFACE, MAX_FLOW_MINUTES};
+pub use search_provider::{Activation, Corpus, SearchProvider, SEARCH_PROVIDER_INTERFACE};
+
+/// Where an interface lives below the application's object path
+pub fn object_path(app_path: &str, name: &str) -> String {
+    format!("{}/{}", app_path.trim_end_matches('/'), name)
+}
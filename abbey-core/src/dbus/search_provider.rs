@@ -0,0 +1,157 @@
+//! `org.gnome.Shell.SearchProvider2`, so compositions and flows show up in the
+//! GNOME Shell overview
+
+use crate::data::{Composition, Flow};
+use crate::search::{self, Target};
+use gio::glib;
+use gio::prelude::*;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+pub const SEARCH_PROVIDER_INTERFACE: &str = "org.gnome.Shell.SearchProvider2";
+
+const INTERFACE_XML: &str = r#"
+<node>
+  <interface name="org.gnome.Shell.SearchProvider2">
+    <method name="GetInitialResultSet">
+      <arg type="as" name="terms" direction="in"/>
+      <arg type="as" name="results" direction="out"/>
+    </method>
+    <method name="GetSubsearchResultSet">
+      <arg type="as" name="previous_results" direction="in"/>
+      <arg type="as" name="terms" direction="in"/>
+      <arg type="as" name="results" direction="out"/>
+    </method>
+    <method name="GetResultMetas">
+      <arg type="as" name="identifiers" direction="in"/>
+      <arg type="aa{sv}" name="metas" direction="out"/>
+    </method>
+    <method name="ActivateResult">
+      <arg type="s" name="identifier" direction="in"/>
+      <arg type="as" name="terms" direction="in"/>
+      <arg type="u" name="timestamp" direction="in"/>
+    </method>
+    <method name="LaunchSearch">
+      <arg type="as" name="terms" direction="in"/>
+      <arg type="u" name="timestamp" direction="in"/>
+    </method>
+  </interface>
+</node>
+"#;
+
+/// What there is to search, taken fresh for every query so unsaved edits in
+/// the app are found too
+#[derive(Debug, Clone, Default)]
+pub struct Corpus {
+    pub compositions: Vec<Composition>,
+    pub flows: Vec<Flow>,
+}
+
+/// What the user picked in the shell
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Activation {
+    /// Open a single result
+    Open { target: Target, timestamp: u32 },
+    /// Show the app itself with the search terms
+    Launch { terms: Vec<String>, timestamp: u32 },
+}
+
+struct Inner {
+    corpus: Box<dyn Fn() -> Corpus>,
+    activate: Box<dyn Fn(Activation)>,
+    /// The shell asks for result metas without the terms, so remember them to
+    /// build excerpts around the match
+    last_terms: RefCell<Vec<String>>,
+}
+
+#[derive(Clone)]
+pub struct SearchProvider {
+    inner: Rc<Inner>,
+}
+
+impl SearchProvider {
+    pub fn new(corpus: impl Fn() -> Corpus + 'static, activate: impl Fn(Activation) + 'static) -> Self {
+        Self {
+            inner: Rc::new(Inner {
+                corpus: Box::new(corpus),
+                activate: Box::new(activate),
+                last_terms: RefCell::new(Vec::new()),
+            }),
+        }
+    }
+
+    /// Export the provider at `object_path` on `connection`. Method calls are
+    /// dispatched on the thread-default main context at the time of the call.
+    pub fn register(
+        &self,
+        connection: &gio::DBusConnection,
+        object_path: &str,
+    ) -> Result<gio::RegistrationId, glib::Error> {
+        let node = gio::DBusNodeInfo::for_xml(INTERFACE_XML)?;
+        let interface = node
+            .lookup_interface(SEARCH_PROVIDER_INTERFACE)
+            .expect("search provider interface is defined in INTERFACE_XML");
+        
+        let provider = self.clone();
+        connection
+            .register_object(object_path, &interface)
+            .method_call(move |_, _, _, _, method, parameters, invocation| {
+                match provider.handle_call(method, &parameters) {
+                    Ok(reply) => invocation.return_value(reply.as_ref()),
+                    Err(message) => invocation.return_dbus_error("org.freedesktop.DBus.Error.InvalidArgs", &message),
+                }
+            })
+            .build()
+    }
+
+    fn handle_call(&self, method: &str, parameters: &glib::Variant) -> Result<Option<glib::Variant>, String> {
+        let invalid = || format!("unexpected arguments {} for {}", parameters.type_(), method);
+        
+        match method {
+            "GetInitialResultSet" => {
+                let (terms,) = parameters.get::<(Vec<String>,)>().ok_or_else(invalid)?;
+                let corpus = (self.inner.corpus)();
+                let results = search::search(&corpus.compositions, &corpus.flows, &terms);
+                self.inner.last_terms.replace(terms);
+                Ok(Some((results,).to_variant()))
+            }
+            "GetSubsearchResultSet" => {
+                let (previous, terms) = parameters.get::<(Vec<String>, Vec<String>)>().ok_or_else(invalid)?;
+                let corpus = (self.inner.corpus)();
+                let results = search::subsearch(&corpus.compositions, &corpus.flows, &previous, &terms);
+                self.inner.last_terms.replace(terms);
+                Ok(Some((results,).to_variant()))
+            }
+            "GetResultMetas" => {
+                let (ids,) = parameters.get::<(Vec<String>,)>().ok_or_else(invalid)?;
+                let corpus = (self.inner.corpus)();
+                let terms = self.inner.last_terms.borrow();
+                let metas: Vec<HashMap<String, glib::Variant>> =
+                    search::result_metas(&corpus.compositions, &corpus.flows, &ids, &terms)
+                        .into_iter()
+                        .map(|meta| {
+                            HashMap::from([
+                                ("id".to_string(), meta.id.to_variant()),
+                                ("name".to_string(), meta.name.to_variant()),
+                                ("description".to_string(), meta.description.to_variant()),
+                            ])
+                        })
+                        .collect();
+                Ok(Some((metas,).to_variant()))
+            }
+            "ActivateResult" => {
+                let (id, _terms, timestamp) = parameters.get::<(String, Vec<String>, u32)>().ok_or_else(invalid)?;
+                let target = Target::parse(&id).ok_or_else(|| format!("unknown result \"{}\"", id))?;
+                (self.inner.activate)(Activation::Open { target, timestamp });
+                Ok(None)
+            }
+            "LaunchSearch" => {
+                let (terms, timestamp) = parameters.get::<(Vec<String>, u32)>().ok_or_else(invalid)?;
+                (self.inner.activate)(Activation::Launch { terms, timestamp });
+                Ok(None)
+            }
+            _ => Err(format!("unknown method {}", method)),
+        }
+    }
+}
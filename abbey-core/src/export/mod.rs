@@ -0,0 +1,376 @@
+//! Turning compositions and projects into standalone documents. Open
+//! comments go along as footnotes in markdown and HTML, and as the word
+//! processor's own comments in OpenDocument; resolved ones are left out.
+//! Wiki links go to their composition's section when it's in the same
+//! project, and otherwise read as plain text. Pending suggestions are left
+//! out, so documents read as the text did before them.
+
+mod odt;
+
+use std::collections::HashSet;
+use std::ops::Range;
+
+use crate::data::{Comment, Composition, Project};
+use crate::links;
+use crate::utils::markdown::{anchored_parser_options, parser_options};
+use crate::utils::typography;
+
+use odt::Annotation;
+
+/// Formats a project can be exported to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Markdown,
+    Html,
+    /// Flat OpenDocument text, a single XML file word processors open
+    Odt,
+}
+
+impl ExportFormat {
+    /// Parse a format name as typed by a user, e.g. `md` or `html`
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "md" | "markdown" => Some(Self::Markdown),
+            "html" | "htm" => Some(Self::Html),
+            "odt" | "fodt" => Some(Self::Odt),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Markdown => "md",
+            Self::Html => "html",
+            Self::Odt => "fodt",
+        }
+    }
+}
+
+/// How a document carries its compositions' comments
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Comments {
+    /// As markdown footnotes, numbered through the document
+    Footnotes,
+    /// Left out of the text and returned with where they go
+    Anchored,
+}
+
+/// A composition as a readable markdown file, with its comments as footnotes
+/// and its notes at the end
+pub fn composition_to_markdown(composition: &Composition) -> String {
+    composition_document(&composition.without_suggestions(), Comments::Footnotes).0
+}
+
+fn composition_document(composition: &Composition, comments: Comments) -> (String, Vec<Annotation<'_>>) {
+    let mut content = format!("# {}\n\n", composition.title);
+    let mut annotations = Vec::new();
+    push_content(&mut content, composition, comments, &mut 0, &mut annotations);
+
+    if !composition.notes.is_empty() {
+        content.push_str("\n\n---\n\n## Notes\n\n");
+        for note in &composition.notes {
+            content.push_str(&format!("- {}\n", note.content));
+        }
+    }
+
+    (content, annotations)
+}
+
+/// Combine a project's compositions, in project order, into one markdown document.
+/// Ids without a matching composition are skipped.
+pub fn project_to_markdown(project: &Project, compositions: &[Composition]) -> String {
+    let (compositions, anchored) = project_compositions(project, compositions);
+    project_document(project, &compositions, &anchored, Comments::Footnotes).0
+}
+
+/// A project's compositions in project order with their links written out,
+/// and the ids of those linked to from the others
+fn project_compositions(project: &Project, library: &[Composition]) -> (Vec<Composition>, HashSet<String>) {
+    let included: Vec<&Composition> = project
+        .composition_ids
+        .iter()
+        .filter_map(|id| library.iter().find(|c| c.id == *id))
+        .collect();
+    let sections: Vec<&str> = included.iter().map(|c| c.id.as_str()).collect();
+    let anchored = included
+        .iter()
+        .flat_map(|comp| links::find(&comp.content_without_suggestions()))
+        .filter_map(|link| links::resolve(&link.target, library))
+        .filter(|target| sections.contains(&target.id.as_str()))
+        .map(|target| target.id.clone())
+        .collect();
+    let compositions = included.into_iter().map(|comp| unlinked(comp, library, &sections)).collect();
+    (compositions, anchored)
+}
+
+/// `compositions` one after another under their titles, each with an anchor
+/// if its id is in `anchored`
+fn project_document<'a>(
+    project: &Project,
+    compositions: &'a [Composition],
+    anchored: &HashSet<String>,
+    comments: Comments,
+) -> (String, Vec<Annotation<'a>>) {
+    let mut md = format!("# {}\n\n", project.title);
+    let mut annotations = Vec::new();
+    let mut footnotes = 0;
+
+    if !project.description.is_empty() {
+        md.push_str(&format!("*{}*\n\n", project.description));
+    }
+
+    md.push_str("---\n\n");
+
+    for comp in compositions {
+        let title = if comp.title.is_empty() { "Untitled" } else { &comp.title };
+        if anchored.contains(&comp.id) {
+            md.push_str(&format!("## {} {{#{}}}\n\n", title, anchor(comp)));
+        } else {
+            md.push_str(&format!("## {}\n\n", title));
+        }
+        push_content(&mut md, comp, comments, &mut footnotes, &mut annotations);
+        md.push_str("\n\n---\n\n");
+    }
+
+    (md, annotations)
+}
+
+/// The id of a composition's heading in a project document
+fn anchor(composition: &Composition) -> String {
+    format!("composition-{}", composition.id)
+}
+
+/// A composition without its pending suggestions and with its wiki links
+/// written out, as links to the sections of the compositions in `sections`
+/// and as plain text otherwise
+fn unlinked(composition: &Composition, library: &[Composition], sections: &[&str]) -> Composition {
+    let composition = &*composition.without_suggestions();
+    let mut unlinked = composition.clone();
+    unlinked.content = links::replace_links(&composition.content, |link| {
+        let text = link.text(library);
+        match links::resolve(&link.target, library) {
+            Some(target) if sections.contains(&target.id.as_str()) => format!("[{}](#{})", text, anchor(target)),
+            _ => text,
+        }
+    });
+    if unlinked.content != composition.content {
+        unlinked.follow_edit(&composition.content);
+    }
+    unlinked
+}
+
+/// Add a composition's text to `md` with its open comments as footnotes,
+/// numbered on from `footnotes`, or noting where in `md` they're anchored
+fn push_content<'a>(
+    md: &mut String,
+    composition: &'a Composition,
+    comments: Comments,
+    footnotes: &mut usize,
+    annotations: &mut Vec<Annotation<'a>>,
+) {
+    let content = &composition.content;
+    let mut open: Vec<(Range<usize>, &Comment)> = composition
+        .comments
+        .iter()
+        .filter(|comment| !comment.resolved)
+        .map(|comment| (byte_range(content, comment), comment))
+        .collect();
+    open.sort_by_key(|(range, _)| (range.end, range.start));
+
+    if comments == Comments::Anchored {
+        let offset = md.len();
+        md.push_str(content);
+        annotations.extend(open.into_iter().map(|(range, comment)| Annotation {
+            range: range.start + offset..range.end + offset,
+            comment,
+        }));
+        return;
+    }
+
+    let mut definitions = String::new();
+    let mut written = 0;
+    for (range, comment) in open {
+        *footnotes += 1;
+        let label = format!("[^comment-{}]", footnotes);
+        let at = footnote_position(content, range.end).max(written);
+        md.push_str(&content[written..at]);
+        // After a block the document ends without a newline, on a line of its own
+        if at != range.end && !content[..at].ends_with('\n') {
+            md.push('\n');
+        }
+        md.push_str(&label);
+        written = at;
+        definitions.push_str(&format!("\n\n{}: {}", label, indented(&comment.content)));
+        for reply in &comment.replies {
+            definitions.push_str(&format!("\n\n    Reply: {}", indented(&reply.content)));
+        }
+    }
+    md.push_str(&content[written..]);
+    md.push_str(&definitions);
+}
+
+/// A comment's anchor in bytes of `content`, leaving out trailing whitespace
+fn byte_range(content: &str, comment: &Comment) -> Range<usize> {
+    let byte = |chars: usize| content.char_indices().nth(chars).map_or(content.len(), |(i, _)| i);
+    let end = byte(comment.end).min(content.trim_end().len());
+    byte(comment.start).min(end)..end
+}
+
+/// Where a footnote for text ending at byte `end` can go: right there in
+/// prose, else after the code or HTML block it's in
+fn footnote_position(content: &str, end: usize) -> usize {
+    if typography::in_prose(content, content[..end].chars().count()) {
+        return end;
+    }
+    let mut depth = 0;
+    for (event, range) in pulldown_cmark::Parser::new_ext(content, parser_options()).into_offset_iter() {
+        match event {
+            pulldown_cmark::Event::Start(_) => depth += 1,
+            pulldown_cmark::Event::End(_) => depth -= 1,
+            _ => {}
+        }
+        if depth == 0 && range.start <= end && end < range.end {
+            return range.end;
+        }
+    }
+    end
+}
+
+/// Later lines of a footnote indented so they stay in it
+fn indented(text: &str) -> String {
+    text.trim().replace('\n', "\n    ")
+}
+
+/// Export a single composition in the given format, its wiki links as plain
+/// text
+pub fn export_composition(composition: &Composition, format: ExportFormat) -> String {
+    let composition = &unlinked(composition, &[], &[]);
+    if format == ExportFormat::Odt {
+        let (markdown, annotations) = composition_document(composition, Comments::Anchored);
+        return odt::document(&composition.title, &markdown, &annotations, parser_options());
+    }
+    let markdown = composition_to_markdown(composition);
+    match format {
+        ExportFormat::Html => html_document(&composition.title, &markdown),
+        _ => markdown,
+    }
+}
+
+/// Export a project in the given format
+pub fn export_project(project: &Project, compositions: &[Composition], format: ExportFormat) -> String {
+    if format == ExportFormat::Odt {
+        let (compositions, anchored) = project_compositions(project, compositions);
+        let (markdown, annotations) = project_document(project, &compositions, &anchored, Comments::Anchored);
+        return odt::document(&project.title, &markdown, &annotations, anchored_parser_options());
+    }
+    let markdown = project_to_markdown(project, compositions);
+    match format {
+        ExportFormat::Html => project_html_document(&project.title, &markdown),
+        _ => markdown,
+    }
+}
+
+/// Export a project with typographic punctuation throughout, leaving its
+/// compositions as they are
+pub fn export_project_smartened(
+    project: &Project,
+    compositions: &[Composition],
+    format: ExportFormat,
+    options: &typography::Options,
+) -> String {
+    if format == ExportFormat::Odt {
+        // Smartened one by one so their comments can follow the changes
+        let mut project = project.clone();
+        project.title = typography::smarten(&project.title, options);
+        project.description = typography::smarten(&project.description, options);
+        let compositions: Vec<Composition> = compositions.iter().map(|comp| smartened(comp, options)).collect();
+        return export_project(&project, &compositions, format);
+    }
+    let markdown = typography::smarten(&project_to_markdown(project, compositions), options);
+    match format {
+        ExportFormat::Html => project_html_document(&typography::smarten(&project.title, options), &markdown),
+        _ => markdown,
+    }
+}
+
+/// A composition with typographic punctuation, its comments still on the
+/// text they were on
+fn smartened(composition: &Composition, options: &typography::Options) -> Composition {
+    let composition = &*composition.without_suggestions();
+    let edits = typography::smarten_edits(&composition.content, options);
+    // Where a char offset ends up once every edit before it is made
+    let moved = |offset: usize| -> usize {
+        edits
+            .iter()
+            .take_while(|edit| edit.range.end <= offset)
+            .fold(offset, |offset, edit| offset + edit.text.chars().count() - edit.range.len())
+    };
+
+    let mut composition = composition.clone();
+    composition.content = typography::smarten(&composition.content, options);
+    composition.title = typography::smarten(&composition.title, options);
+    for comment in composition.comments.iter_mut() {
+        comment.start = moved(comment.start);
+        comment.end = moved(comment.end);
+    }
+    composition
+}
+
+/// Render markdown to an HTML fragment
+pub fn markdown_to_html(markdown: &str) -> String {
+    render_html(markdown, parser_options())
+}
+
+fn render_html(markdown: &str, options: pulldown_cmark::Options) -> String {
+    let parser = pulldown_cmark::Parser::new_ext(markdown, options);
+    let mut html = String::new();
+    pulldown_cmark::html::push_html(&mut html, parser);
+    html
+}
+
+/// Render markdown to a complete, styled HTML page
+pub fn html_document(title: &str, markdown: &str) -> String {
+    html_page(title, &markdown_to_html(markdown))
+}
+
+/// Render a project's markdown from [`project_to_markdown`] to a complete,
+/// styled HTML page, with its compositions' headings anchored for the links
+/// between them
+pub fn project_html_document(title: &str, markdown: &str) -> String {
+    html_page(title, &render_html(markdown, anchored_parser_options()))
+}
+
+fn html_page(title: &str, body: &str) -> String {
+    format!(r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>{}</title>
+    <style>
+        body {{
+            font-family: 'Georgia', serif;
+            max-width: 800px;
+            margin: 40px auto;
+            padding: 20px;
+            line-height: 1.8;
+            color: #333;
+        }}
+        h1 {{ font-size: 2.5em; margin-bottom: 0.5em; }}
+        h2 {{ font-size: 1.8em; margin-top: 2em; border-bottom: 1px solid #ddd; padding-bottom: 0.3em; }}
+        hr {{ border: none; border-top: 1px solid #ddd; margin: 2em 0; }}
+        p {{ margin: 1em 0; }}
+    </style>
+</head>
+<body>
+{}
+</body>
+</html>"#, escape_html(title), body)
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
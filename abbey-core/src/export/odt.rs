@@ -0,0 +1,476 @@
+//! Markdown as flat OpenDocument text (`.fodt`), a single XML file LibreOffice
+//! and other word processors open directly. Comments become the word
+//! processor's own comments on the text they were made on.
+
+use pulldown_cmark::{Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+use std::ops::Range;
+
+use crate::data::Comment;
+
+/// A comment and where it's anchored in the markdown, in bytes
+pub(super) struct Annotation<'a> {
+    pub range: Range<usize>,
+    pub comment: &'a Comment,
+}
+
+/// Where an annotation starts or ends in the markdown
+struct Mark {
+    byte: usize,
+    annotation: usize,
+    end: bool,
+}
+
+/// The markdown `markdown`, titled `title`, with `annotations` on its text,
+/// parsed with `options`
+pub(super) fn document(title: &str, markdown: &str, annotations: &[Annotation], options: Options) -> String {
+    let mut writer = Writer::new(markdown, annotations);
+    for (event, range) in Parser::new_ext(markdown, options).into_offset_iter() {
+        writer.event(event, range);
+    }
+    writer.finish();
+
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<office:document xmlns:office="urn:oasis:names:tc:opendocument:xmlns:office:1.0" xmlns:style="urn:oasis:names:tc:opendocument:xmlns:style:1.0" xmlns:text="urn:oasis:names:tc:opendocument:xmlns:text:1.0" xmlns:table="urn:oasis:names:tc:opendocument:xmlns:table:1.0" xmlns:fo="urn:oasis:names:tc:opendocument:xmlns:xsl-fo-compatible:1.0" xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:meta="urn:oasis:names:tc:opendocument:xmlns:meta:1.0" xmlns:xlink="http://www.w3.org/1999/xlink" office:version="1.3" office:mimetype="application/vnd.oasis.opendocument.text">
+<office:meta><dc:title>{}</dc:title><meta:generator>Abbey</meta:generator></office:meta>
+<office:styles>
+{}</office:styles>
+<office:body><office:text>
+{}</office:text></office:body>
+</office:document>
+"#,
+        escape(title),
+        styles(),
+        writer.body
+    )
+}
+
+struct Writer<'a> {
+    markdown: &'a str,
+    annotations: &'a [Annotation<'a>],
+    /// Annotation starts and ends in document order, and how many are written
+    marks: Vec<Mark>,
+    written: usize,
+    started: Vec<bool>,
+    body: String,
+    /// Whether a `<text:p>` or `<text:h>` is open, and how to close it
+    block: Option<&'static str>,
+    /// Whether the open paragraph was opened for text in a tight list item
+    implicit: bool,
+    quotes: usize,
+    in_code: bool,
+    /// Line breaks in a code block not yet written, as the block's last
+    /// doesn't start a line
+    code_breaks: usize,
+    /// Whether the last thing written in code was a space, so the next is kept
+    code_space: bool,
+    in_table_head: bool,
+    tables: usize,
+    in_footnote: bool,
+    /// A footnote's label, to start its first paragraph with
+    footnote_label: Option<String>,
+}
+
+impl<'a> Writer<'a> {
+    fn new(markdown: &'a str, annotations: &'a [Annotation<'a>]) -> Self {
+        let mut marks = Vec::new();
+        for (annotation, a) in annotations.iter().enumerate() {
+            marks.push(Mark { byte: a.range.start, annotation, end: false });
+            if !a.range.is_empty() {
+                marks.push(Mark { byte: a.range.end, annotation, end: true });
+            }
+        }
+        // Ends before starts at the same place, so ranges that touch don't overlap
+        marks.sort_by_key(|mark| (mark.byte, !mark.end));
+
+        Self {
+            markdown,
+            annotations,
+            marks,
+            written: 0,
+            started: vec![false; annotations.len()],
+            body: String::new(),
+            block: None,
+            implicit: false,
+            quotes: 0,
+            in_code: false,
+            code_breaks: 0,
+            code_space: false,
+            in_table_head: false,
+            tables: 0,
+            in_footnote: false,
+            footnote_label: None,
+        }
+    }
+
+    fn event(&mut self, event: Event, range: Range<usize>) {
+        match event {
+            Event::Start(tag) => self.start(tag, range),
+            Event::End(tag) => self.end(tag, range),
+            Event::Text(text) => {
+                if self.in_code {
+                    self.code(&text, range);
+                } else {
+                    self.text(&text, range);
+                }
+            }
+            Event::Code(code) => {
+                self.inline(range.start);
+                self.body.push_str(&format!(r#"<text:span text:style-name="Source_20_Text">{}</text:span>"#, escape(&code)));
+            }
+            Event::SoftBreak => self.text(" ", range.start..range.start),
+            Event::HardBreak => {
+                self.inline(range.start);
+                self.body.push_str("<text:line-break/>");
+            }
+            Event::Rule => {
+                self.close_implicit();
+                self.body.push_str("<text:p text:style-name=\"Horizontal_20_Line\"/>\n");
+            }
+            Event::FootnoteReference(label) => {
+                self.inline(range.start);
+                self.body.push_str(&format!(r#"<text:span text:style-name="Footnote_20_Symbol">{}</text:span>"#, escape(&label)));
+            }
+            Event::TaskListMarker(done) => self.text(if done { "☑ " } else { "☐ " }, range.start..range.start),
+            _ => {}
+        }
+    }
+
+    fn start(&mut self, tag: Tag, range: Range<usize>) {
+        match tag {
+            Tag::Paragraph => {
+                let style = if self.quotes > 0 {
+                    "Quotations"
+                } else if self.in_footnote {
+                    "Footnote"
+                } else {
+                    "Text_20_body"
+                };
+                self.open("text:p", &format!(r#"text:style-name="{}""#, style));
+                if let Some(label) = self.footnote_label.take() {
+                    self.body.push_str(&format!(r#"<text:span text:style-name="Footnote_20_Symbol">{}</text:span> "#, escape(&label)));
+                }
+            }
+            Tag::Heading { level, id, .. } => {
+                let level = heading_level(level);
+                self.open("text:h", &format!(r#"text:style-name="Heading_20_{0}" text:outline-level="{0}""#, level));
+                // Where links to `#id` go
+                if let Some(id) = id {
+                    self.body.push_str(&format!(r#"<text:bookmark text:name="{}"/>"#, escape(&id)));
+                }
+            }
+            Tag::BlockQuote(_) => {
+                self.close_implicit();
+                self.quotes += 1;
+            }
+            Tag::CodeBlock(_) => {
+                self.open("text:p", r#"text:style-name="Preformatted_20_Text""#);
+                self.in_code = true;
+                self.code_breaks = 0;
+                self.code_space = true;
+            }
+            Tag::List(first) => {
+                self.close_implicit();
+                let style = if first.is_some() { "Numbers" } else { "Bullets" };
+                self.body.push_str(&format!("<text:list text:style-name=\"{}\">\n", style));
+            }
+            Tag::Item => self.body.push_str("<text:list-item>"),
+            Tag::FootnoteDefinition(label) => {
+                self.close_implicit();
+                self.in_footnote = true;
+                self.footnote_label = Some(label.to_string());
+            }
+            Tag::Table(alignments) => {
+                self.close_implicit();
+                self.tables += 1;
+                self.body.push_str(&format!(
+                    "<table:table table:name=\"Table{}\"><table:table-column table:number-columns-repeated=\"{}\"/>\n",
+                    self.tables,
+                    alignments.len().max(1)
+                ));
+            }
+            Tag::TableHead => {
+                self.in_table_head = true;
+                self.body.push_str("<table:table-header-rows><table:table-row>");
+            }
+            Tag::TableRow => self.body.push_str("<table:table-row>"),
+            Tag::TableCell => {
+                self.body.push_str("<table:table-cell office:value-type=\"string\">");
+                let style = if self.in_table_head { "Table_20_Heading" } else { "Table_20_Contents" };
+                self.open("text:p", &format!(r#"text:style-name="{}""#, style));
+            }
+            Tag::Emphasis => self.span(range.start, "Emphasis"),
+            Tag::Strong => self.span(range.start, "Strong_20_Emphasis"),
+            Tag::Strikethrough => self.span(range.start, "Strikethrough"),
+            Tag::Link { dest_url, .. } => {
+                self.inline(range.start);
+                self.body.push_str(&format!(r#"<text:a xlink:type="simple" xlink:href="{}">"#, escape(&dest_url)));
+            }
+            _ => {}
+        }
+    }
+
+    fn end(&mut self, tag: TagEnd, range: Range<usize>) {
+        match tag {
+            TagEnd::Paragraph | TagEnd::Heading(_) => self.close(Some(range)),
+            TagEnd::CodeBlock => {
+                self.in_code = false;
+                self.close(Some(range));
+            }
+            TagEnd::BlockQuote(_) => {
+                self.close_implicit();
+                self.quotes = self.quotes.saturating_sub(1);
+            }
+            TagEnd::List(_) => {
+                self.close_implicit();
+                self.body.push_str("</text:list>\n");
+            }
+            TagEnd::Item => {
+                if self.implicit {
+                    self.close(Some(range));
+                }
+                self.body.push_str("</text:list-item>\n");
+            }
+            TagEnd::FootnoteDefinition => {
+                self.close_implicit();
+                self.in_footnote = false;
+                self.footnote_label = None;
+            }
+            TagEnd::Table => self.body.push_str("</table:table>\n"),
+            TagEnd::TableHead => {
+                self.in_table_head = false;
+                self.body.push_str("</table:table-row></table:table-header-rows>\n");
+            }
+            TagEnd::TableRow => self.body.push_str("</table:table-row>\n"),
+            TagEnd::TableCell => {
+                self.close(Some(range));
+                self.body.push_str("</table:table-cell>");
+            }
+            TagEnd::Emphasis | TagEnd::Strong | TagEnd::Strikethrough if self.block.is_some() => {
+                self.body.push_str("</text:span>");
+            }
+            TagEnd::Link if self.block.is_some() => self.body.push_str("</text:a>"),
+            _ => {}
+        }
+    }
+
+    fn open(&mut self, element: &'static str, attributes: &str) {
+        self.close_implicit();
+        self.body.push_str(&format!("<{} {}>", element, attributes));
+        self.block = Some(element);
+    }
+
+    /// Close the open paragraph, with what's anchored up to the end of the
+    /// text of the block `range` inside it
+    fn close(&mut self, range: Option<Range<usize>>) {
+        if let Some(element) = self.block.take() {
+            if let Some(range) = range {
+                let end = range.start + self.markdown[range].trim_end().len();
+                self.marks_before(end);
+            }
+            self.body.push_str(&format!("</{}>\n", element));
+        }
+        self.implicit = false;
+    }
+
+    /// Close a tight list item's paragraph as something else starts in it
+    fn close_implicit(&mut self) {
+        if self.implicit {
+            self.close(None);
+        }
+    }
+
+    /// Make sure there's a paragraph for inline content, as a tight list's
+    /// items have none
+    fn inline(&mut self, byte: usize) {
+        if self.block.is_none() {
+            self.body.push_str("<text:p text:style-name=\"Text_20_body\">");
+            self.block = Some("text:p");
+            self.implicit = true;
+        }
+        self.marks_before(byte);
+    }
+
+    fn span(&mut self, byte: usize, style: &str) {
+        self.inline(byte);
+        self.body.push_str(&format!(r#"<text:span text:style-name="{}">"#, style));
+    }
+
+    /// Text, with annotations starting or ending inside it where they do when
+    /// it's the markdown as written
+    fn text(&mut self, text: &str, range: Range<usize>) {
+        self.inline(range.start);
+        if self.markdown.get(range.clone()) != Some(text) {
+            self.body.push_str(&escape(text));
+            return;
+        }
+        let mut at = range.start;
+        while let Some(byte) = self.marks.get(self.written).map(|mark| mark.byte).filter(|&byte| byte < range.end) {
+            self.body.push_str(&escape(&self.markdown[at..byte]));
+            at = byte;
+            self.marks_before(byte);
+        }
+        self.body.push_str(&escape(&self.markdown[at..range.end]));
+    }
+
+    /// Code keeps its line breaks and spaces
+    fn code(&mut self, text: &str, range: Range<usize>) {
+        let verbatim = self.markdown.get(range.clone()) == Some(text);
+        if !verbatim {
+            self.marks_before(range.start);
+        }
+        for (i, c) in text.char_indices() {
+            if verbatim {
+                self.marks_before(range.start + i);
+            }
+            match c {
+                '\n' => self.code_breaks += 1,
+                _ => {
+                    for _ in 0..std::mem::take(&mut self.code_breaks) {
+                        self.body.push_str("</text:p>\n<text:p text:style-name=\"Preformatted_20_Text\">");
+                        self.code_space = true;
+                    }
+                    match c {
+                        ' ' if self.code_space => self.body.push_str("<text:s/>"),
+                        '\t' => self.body.push_str("<text:tab/>"),
+                        c => self.body.push_str(&escape(c.encode_utf8(&mut [0; 4]))),
+                    }
+                    self.code_space = c == ' ';
+                }
+            }
+        }
+    }
+
+    /// Write the annotation starts and ends up to `byte`
+    fn marks_before(&mut self, byte: usize) {
+        while let Some(mark) = self.marks.get(self.written) {
+            if mark.byte > byte {
+                break;
+            }
+            let (annotation, end) = (mark.annotation, mark.end);
+            self.written += 1;
+            if end {
+                // An end whose start fell where nothing was written starts there too
+                if !self.started[annotation] {
+                    self.start_annotation(annotation);
+                }
+                self.body.push_str(&format!(r#"<office:annotation-end office:name="comment-{}"/>"#, annotation + 1));
+            } else {
+                self.start_annotation(annotation);
+            }
+        }
+    }
+
+    fn start_annotation(&mut self, index: usize) {
+        self.started[index] = true;
+        let annotation = &self.annotations[index];
+        let comment = annotation.comment;
+        let name = if annotation.range.is_empty() {
+            String::new()
+        } else {
+            format!(r#" office:name="comment-{}""#, index + 1)
+        };
+        self.body.push_str(&format!(
+            "<office:annotation{}><dc:date>{}</dc:date>",
+            name,
+            comment.created_at.format("%Y-%m-%dT%H:%M:%S")
+        ));
+        for line in comment.content.lines() {
+            self.body.push_str(&format!("<text:p>{}</text:p>", escape(line)));
+        }
+        for reply in &comment.replies {
+            for (i, line) in reply.content.lines().enumerate() {
+                let prefix = if i == 0 { "Reply: " } else { "" };
+                self.body.push_str(&format!("<text:p>{}{}</text:p>", prefix, escape(line)));
+            }
+        }
+        self.body.push_str("</office:annotation>");
+    }
+
+    /// Anything anchored where no text was written goes in a last paragraph
+    fn finish(&mut self) {
+        if self.block.is_some() {
+            self.close(Some(0..self.markdown.len()));
+        }
+        if self.written < self.marks.len() {
+            self.body.push_str("<text:p text:style-name=\"Text_20_body\">");
+            self.marks_before(usize::MAX);
+            self.body.push_str("</text:p>\n");
+        }
+    }
+}
+
+fn heading_level(level: HeadingLevel) -> u8 {
+    match level {
+        HeadingLevel::H1 => 1,
+        HeadingLevel::H2 => 2,
+        HeadingLevel::H3 => 3,
+        HeadingLevel::H4 => 4,
+        HeadingLevel::H5 => 5,
+        HeadingLevel::H6 => 6,
+    }
+}
+
+/// The styles the body uses, named as LibreOffice names its own so documents
+/// restyle the same way
+fn styles() -> String {
+    let mut styles = String::from(
+        r#"<style:style style:name="Standard" style:family="paragraph"/>
+<style:style style:name="Text_20_body" style:display-name="Text body" style:family="paragraph" style:parent-style-name="Standard"><style:paragraph-properties fo:margin-top="0in" fo:margin-bottom="0.1in"/></style:style>
+<style:style style:name="Heading" style:family="paragraph" style:parent-style-name="Standard" style:next-style-name="Text_20_body"><style:paragraph-properties fo:margin-top="0.17in" fo:margin-bottom="0.08in" fo:keep-with-next="always"/><style:text-properties fo:font-weight="bold"/></style:style>
+<style:style style:name="Quotations" style:family="paragraph" style:parent-style-name="Standard"><style:paragraph-properties fo:margin-left="0.4in" fo:margin-right="0.4in" fo:margin-bottom="0.1in"/></style:style>
+<style:style style:name="Preformatted_20_Text" style:display-name="Preformatted Text" style:family="paragraph" style:parent-style-name="Standard"><style:text-properties fo:font-family="monospace" fo:font-size="10pt"/></style:style>
+<style:style style:name="Horizontal_20_Line" style:display-name="Horizontal Line" style:family="paragraph" style:parent-style-name="Standard"><style:paragraph-properties fo:margin-bottom="0.2in" fo:border-bottom="0.5pt solid #808080"/></style:style>
+<style:style style:name="Footnote" style:family="paragraph" style:parent-style-name="Standard"><style:text-properties fo:font-size="85%"/></style:style>
+<style:style style:name="Table_20_Contents" style:display-name="Table Contents" style:family="paragraph" style:parent-style-name="Standard"/>
+<style:style style:name="Table_20_Heading" style:display-name="Table Heading" style:family="paragraph" style:parent-style-name="Table_20_Contents"><style:text-properties fo:font-weight="bold"/></style:style>
+<style:style style:name="Emphasis" style:family="text"><style:text-properties fo:font-style="italic"/></style:style>
+<style:style style:name="Strong_20_Emphasis" style:display-name="Strong Emphasis" style:family="text"><style:text-properties fo:font-weight="bold"/></style:style>
+<style:style style:name="Source_20_Text" style:display-name="Source Text" style:family="text"><style:text-properties fo:font-family="monospace"/></style:style>
+<style:style style:name="Strikethrough" style:family="text"><style:text-properties style:text-line-through-style="solid"/></style:style>
+<style:style style:name="Footnote_20_Symbol" style:display-name="Footnote Symbol" style:family="text"><style:text-properties style:text-position="super 58%"/></style:style>
+"#,
+    );
+    for (level, size) in [(1, "130%"), (2, "115%"), (3, "101%"), (4, "95%"), (5, "85%"), (6, "85%")] {
+        styles.push_str(&format!(
+            "<style:style style:name=\"Heading_20_{0}\" style:display-name=\"Heading {0}\" style:family=\"paragraph\" style:parent-style-name=\"Heading\" style:default-outline-level=\"{0}\"><style:text-properties fo:font-size=\"{1}\"/></style:style>\n",
+            level, size
+        ));
+    }
+    for (name, numbered) in [("Bullets", false), ("Numbers", true)] {
+        styles.push_str(&format!("<text:list-style style:name=\"{}\">", name));
+        for level in 1..=6 {
+            let (element, attributes) = if numbered {
+                ("text:list-level-style-number", r#"style:num-suffix="." style:num-format="1""#)
+            } else {
+                ("text:list-level-style-bullet", r#"text:bullet-char="•""#)
+            };
+            styles.push_str(&format!(
+                r#"<{0} text:level="{1}" {2}><style:list-level-properties text:list-level-position-and-space-mode="label-alignment"><style:list-level-label-alignment text:label-followed-by="listtab" fo:text-indent="-0.25in" fo:margin-left="{3}in"/></style:list-level-properties></{0}>"#,
+                element,
+                level,
+                attributes,
+                0.25 * (level + 1) as f64
+            ));
+        }
+        styles.push_str("</text:list-style>\n");
+    }
+    styles
+}
+
+/// Text for XML, leaving out the control characters XML can't hold
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c < ' ' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,69 @@
+//! Bringing markdown files from outside the library into it
+
+use crate::data::Composition;
+use std::path::Path;
+
+/// File extensions Abbey can open as compositions
+pub const OPENABLE_EXTENSIONS: &[&str] = &["md", "markdown", "mdown", "txt", "text"];
+
+/// Whether a file looks like something Abbey can open
+pub fn is_openable(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| OPENABLE_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+/// A new composition holding a copy of a markdown file. A leading `# Heading`
+/// becomes the title (as written by Abbey's own markdown export); otherwise the
+/// file name is used.
+pub fn composition_from_markdown(text: &str, path: &Path) -> Composition {
+    let mut composition = Composition::new();
+
+    let trimmed = text.trim_start();
+    let heading = trimmed
+        .lines()
+        .next()
+        .and_then(|line| line.strip_prefix("# "))
+        .map(|title| title.trim().to_string())
+        .filter(|title| !title.is_empty());
+
+    match heading {
+        Some(title) => {
+            composition.title = title;
+            let first_line_end = trimmed.find('\n').unwrap_or(trimmed.len());
+            composition.content = trimmed[first_line_end..].trim_start_matches(['\r', '\n']).to_string();
+        }
+        None => {
+            composition.title = file_title(path);
+            composition.content = text.to_string();
+        }
+    }
+
+    composition.update_word_count();
+    composition
+}
+
+/// A composition that edits a file where it is. The content is kept exactly as
+/// in the file, so saving writes it back unchanged apart from the edits.
+pub fn linked_composition(text: &str, path: &Path) -> Composition {
+    let mut composition = Composition::new();
+    composition.title = file_title(path);
+    composition.content = text.to_string();
+    composition.external_path = Some(path.to_path_buf());
+    composition.update_word_count();
+    composition
+}
+
+/// Find the composition already linked to `path`, if any
+pub fn find_linked<'a>(compositions: &'a [Composition], path: &Path) -> Option<&'a Composition> {
+    compositions
+        .iter()
+        .find(|c| c.external_path.as_deref() == Some(path))
+}
+
+fn file_title(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .filter(|stem| !stem.is_empty())
+        .unwrap_or_else(|| "Untitled".to_string())
+}
@@ -0,0 +1,16 @@
+//! Abbey's library model, storage, export and statistics, free of any GTK
+//! dependency so they can be shared by the app, command-line tools and tests.
+
+pub mod api;
+pub mod check;
+pub mod cli;
+pub mod data;
+#[cfg(feature = "dbus")]
+pub mod dbus;
+pub mod export;
+pub mod import;
+pub mod links;
+pub mod replace;
+pub mod search;
+pub mod stats;
+pub mod utils;
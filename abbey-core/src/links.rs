@@ -0,0 +1,294 @@
+//! Wiki links between compositions, written `[[Title]]` or `[[id|label]]`.
+//! A link names its composition by id or by title, ids first; a title link
+//! follows the composition when it's renamed, as renaming rewrites it. Links
+//! inside code are only code.
+
+use std::ops::Range;
+
+use pulldown_cmark::{Event, Parser, Tag};
+
+use crate::data::{Composition, Flow};
+use crate::utils::markdown::parser_options;
+
+/// How much of a line to show around a link that references a composition
+const CONTEXT_CHARS: usize = 60;
+
+/// A `[[target]]` or `[[target|label]]` link in a text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Link {
+    /// The whole link in bytes, brackets included
+    pub range: Range<usize>,
+    /// The id or title of the composition it's to
+    pub target: String,
+    pub label: Option<String>,
+}
+
+impl Link {
+    /// What the link reads as: its label, or the title of what it's to
+    pub fn text(&self, compositions: &[Composition]) -> String {
+        if let Some(ref label) = self.label {
+            return label.clone();
+        }
+        match compositions.iter().find(|c| c.id == self.target) {
+            Some(comp) if !comp.title.is_empty() => comp.title.clone(),
+            _ => self.target.clone(),
+        }
+    }
+
+    /// The link written out again to `target`, with the same label
+    fn to(&self, target: &str) -> String {
+        match self.label {
+            Some(ref label) => format!("[[{}|{}]]", target, label),
+            None => format!("[[{}]]", target),
+        }
+    }
+}
+
+/// Where a link references a composition from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    Composition(String),
+    /// A composition's id and one of its notes' id
+    Note(String, String),
+    Flow(String),
+}
+
+/// A composition, note or flow that links to a composition
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Backlink {
+    pub source: Source,
+    /// What the source is called
+    pub name: String,
+    /// The line the first link is on, shortened around it
+    pub context: String,
+}
+
+/// Every link in a markdown text, in order
+pub fn find(text: &str) -> Vec<Link> {
+    let code = code_ranges(text);
+    let mut links = Vec::new();
+    let mut from = 0;
+    while let Some(found) = text[from..].find("[[") {
+        let start = from + found;
+        from = start + 2;
+        let line_end = text[from..].find('\n').map_or(text.len(), |i| from + i);
+        let end = match text[from..line_end].find("]]") {
+            Some(i) => from + i,
+            None => continue,
+        };
+        let inner = &text[from..end];
+        if inner.contains('[') || code.iter().any(|range| range.start <= start && start < range.end) {
+            continue;
+        }
+        let (target, label) = match inner.split_once('|') {
+            Some((target, label)) => (target.trim(), Some(label.trim()).filter(|label| !label.is_empty())),
+            None => (inner.trim(), None),
+        };
+        if target.is_empty() {
+            continue;
+        }
+        links.push(Link {
+            range: start..end + 2,
+            target: target.to_string(),
+            label: label.map(str::to_string),
+        });
+        from = end + 2;
+    }
+    links
+}
+
+/// The link at byte `at` of `text`, if there's one there
+pub fn link_at(text: &str, at: usize) -> Option<Link> {
+    find(text).into_iter().find(|link| link.range.start <= at && at < link.range.end)
+}
+
+/// The title being typed into a link left open before byte `at`, as the
+/// bytes it covers so far
+pub fn partial_at(text: &str, at: usize) -> Option<Range<usize>> {
+    let line_start = text[..at].rfind('\n').map_or(0, |i| i + 1);
+    let start = text[line_start..at].rfind("[[")? + line_start + 2;
+    let typed = &text[start..at];
+    if typed.contains([']', '[', '|']) {
+        return None;
+    }
+    Some(start..at)
+}
+
+/// Titles that complete `partial`: those starting with it first, then those
+/// with it anywhere, each in the order given
+pub fn complete<'a>(partial: &str, titles: impl IntoIterator<Item = &'a str>) -> Vec<&'a str> {
+    let partial = partial.trim().to_lowercase();
+    let (mut starting, mut containing): (Vec<&str>, Vec<&str>) = titles
+        .into_iter()
+        .filter(|title| !title.is_empty() && title.to_lowercase().contains(&partial))
+        .partition(|title| title.to_lowercase().starts_with(&partial));
+    starting.append(&mut containing);
+    starting
+}
+
+/// The composition a link's target names: the one with that id, else the
+/// one with that title, preferring one that isn't archived
+pub fn resolve<'a>(target: &str, compositions: &'a [Composition]) -> Option<&'a Composition> {
+    compositions
+        .iter()
+        .find(|c| c.id == target)
+        .or_else(|| titled(target, compositions, None))
+}
+
+/// The composition called `title`, or that was before `renamed`, as its id
+/// and old title, was renamed
+fn titled<'a>(
+    title: &str,
+    compositions: &'a [Composition],
+    renamed: Option<(&str, &str)>,
+) -> Option<&'a Composition> {
+    let title = title.trim().to_lowercase();
+    let title_of = |c: &Composition| match renamed {
+        Some((id, old_title)) if c.id == id => old_title.trim().to_lowercase(),
+        _ => c.title.trim().to_lowercase(),
+    };
+    let mut named = compositions.iter().filter(|c| title_of(c) == title);
+    let first = named.next()?;
+    if !first.archived {
+        return Some(first);
+    }
+    named.find(|c| !c.archived).or(Some(first))
+}
+
+/// Text with each link replaced by what `write` makes of it
+pub fn replace_links(text: &str, mut write: impl FnMut(&Link) -> String) -> String {
+    let mut replaced = String::with_capacity(text.len());
+    let mut written = 0;
+    for link in find(text) {
+        replaced.push_str(&text[written..link.range.start]);
+        replaced.push_str(&write(&link));
+        written = link.range.end;
+    }
+    replaced.push_str(&text[written..]);
+    replaced
+}
+
+/// After composition `id` is renamed from `old_title`, point the links that
+/// named it by that title at its new one. Returns the ids of the compositions
+/// whose text or notes changed.
+pub fn retitle(compositions: &mut [Composition], id: &str, old_title: &str) -> Vec<String> {
+    let new_title = match compositions.iter().find(|c| c.id == id) {
+        Some(comp) => comp.title.clone(),
+        None => return Vec::new(),
+    };
+    // A title a link can't hold leaves the links as they were
+    if new_title.trim() == old_title.trim() || new_title.trim().is_empty() || new_title.contains(['[', ']', '|', '\n']) {
+        return Vec::new();
+    }
+
+    // Links to the old title that went somewhere else still do
+    let ours = |link: &Link| {
+        compositions.iter().all(|c| c.id != link.target)
+            && titled(&link.target, compositions, Some((id, old_title))).is_some_and(|c| c.id == id)
+    };
+    let rewrite = |text: &str| {
+        replace_links(text, |link| if ours(link) { link.to(&new_title) } else { text[link.range.clone()].to_string() })
+    };
+    let updated: Vec<(usize, String, Vec<String>)> = compositions
+        .iter()
+        .enumerate()
+        .filter_map(|(index, comp)| {
+            let content = rewrite(&comp.content);
+            let notes: Vec<String> = comp.notes.iter().map(|note| rewrite(&note.content)).collect();
+            let changed = content != comp.content || notes.iter().zip(&comp.notes).any(|(new, note)| *new != note.content);
+            changed.then_some((index, content, notes))
+        })
+        .collect();
+
+    let now = chrono::Utc::now();
+    let mut changed = Vec::new();
+    for (index, content, notes) in updated {
+        let comp = &mut compositions[index];
+        let old_content = std::mem::replace(&mut comp.content, content);
+        comp.follow_edit(&old_content);
+        comp.update_word_count();
+        for (note, content) in comp.notes.iter_mut().zip(notes) {
+            note.content = content;
+        }
+        comp.updated_at = now;
+        changed.push(comp.id.clone());
+    }
+    changed
+}
+
+/// The compositions, notes and flows with a link to `target`, compositions
+/// first in library order, then flows newest first
+pub fn backlinks(target: &Composition, compositions: &[Composition], flows: &[Flow]) -> Vec<Backlink> {
+    let context = |text: &str| {
+        find(text)
+            .into_iter()
+            .find(|link| resolve(&link.target, compositions).is_some_and(|c| c.id == target.id))
+            .map(|link| context(text, &link.range))
+    };
+
+    let mut backlinks = Vec::new();
+    for comp in compositions.iter().filter(|c| c.id != target.id) {
+        let name = if comp.title.is_empty() { "Untitled".to_string() } else { comp.title.clone() };
+        if let Some(context) = context(&comp.content) {
+            backlinks.push(Backlink { source: Source::Composition(comp.id.clone()), name: name.clone(), context });
+        }
+        for note in &comp.notes {
+            if let Some(context) = context(&note.content) {
+                backlinks.push(Backlink {
+                    source: Source::Note(comp.id.clone(), note.id.clone()),
+                    name: format!("{} · note", name),
+                    context,
+                });
+            }
+        }
+    }
+
+    let mut flows: Vec<&Flow> = flows.iter().collect();
+    flows.sort_by_key(|flow| std::cmp::Reverse(flow.created_at));
+    for flow in flows {
+        if let Some(context) = context(&flow.content) {
+            backlinks.push(Backlink {
+                source: Source::Flow(flow.id.clone()),
+                name: format!("Flow, {}", flow.created_at.format("%B %d, %Y")),
+                context,
+            });
+        }
+    }
+    backlinks
+}
+
+/// The line around bytes `range` of `text`, cut down to about
+/// `CONTEXT_CHARS` either side of it
+fn context(text: &str, range: &Range<usize>) -> String {
+    let line_start = text[..range.start].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = text[range.end..].find('\n').map_or(text.len(), |i| range.end + i);
+    let before = &text[line_start..range.start];
+    let after = &text[range.end..line_end];
+
+    let mut context = String::new();
+    match before.char_indices().rev().nth(CONTEXT_CHARS) {
+        Some((cut, _)) => {
+            context.push('…');
+            context.push_str(before[cut..].trim_start());
+        }
+        None => context.push_str(before.trim_start()),
+    }
+    context.push_str(&text[range.clone()]);
+    match after.char_indices().nth(CONTEXT_CHARS) {
+        Some((cut, _)) => {
+            context.push_str(after[..cut].trim_end());
+            context.push('…');
+        }
+        None => context.push_str(after.trim_end()),
+    }
+    context
+}
+
+/// Bytes of `text` in code spans and blocks
+fn code_ranges(text: &str) -> Vec<Range<usize>> {
+    Parser::new_ext(text, parser_options())
+        .into_offset_iter()
+        .filter(|(event, _)| matches!(event, Event::Start(Tag::CodeBlock(_)) | Event::Code(_)))
+        .map(|(_, range)| range)
+        .collect()
+}
@@ -0,0 +1,300 @@
+//! Finding and replacing text across the library, in compositions' titles,
+//! text and notes and in projects' titles and descriptions. Every match is
+//! listed with its surroundings before and after the change, so the writer can
+//! pick which to make.
+
+use chrono::Utc;
+use regex::{Captures, Regex, RegexBuilder};
+use std::collections::HashSet;
+use std::ops::Range;
+
+use crate::data::{Composition, Project};
+use crate::links;
+
+/// Longest line of context shown around a match, in characters
+pub const CONTEXT_CHARS: usize = 80;
+
+/// What to look for
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Query {
+    pub pattern: String,
+    pub case_sensitive: bool,
+    pub whole_word: bool,
+    /// Take the pattern as a regular expression, whose groups the replacement
+    /// can use as `$1` or `${name}`
+    pub regex: bool,
+}
+
+/// A query ready to run
+#[derive(Debug, Clone)]
+pub struct Matcher {
+    regex: Regex,
+    /// Whether replacements expand `$` groups
+    expand: bool,
+}
+
+impl Matcher {
+    /// Fails when a regular expression doesn't parse
+    pub fn new(query: &Query) -> Result<Self, regex::Error> {
+        let pattern = if query.regex {
+            query.pattern.clone()
+        } else {
+            regex::escape(&query.pattern)
+        };
+        let pattern = if query.whole_word {
+            format!(r"\b(?:{})\b", pattern)
+        } else {
+            pattern
+        };
+        let regex = RegexBuilder::new(&pattern)
+            .case_insensitive(!query.case_sensitive)
+            .multi_line(true)
+            .build()?;
+        Ok(Self {
+            regex,
+            expand: query.regex,
+        })
+    }
+
+    /// Every match in `text`, in bytes. Empty matches don't count, so an empty
+    /// pattern finds nothing.
+    pub fn find(&self, text: &str) -> Vec<Range<usize>> {
+        self.regex
+            .find_iter(text)
+            .filter(|found| !found.is_empty())
+            .map(|found| found.range())
+            .collect()
+    }
+
+    /// `text` with the matches whose positions in [`find`](Self::find) are
+    /// picked replaced, or all of them with `None`
+    pub fn replace(&self, text: &str, replacement: &str, picked: Option<&HashSet<usize>>) -> String {
+        let mut result = String::with_capacity(text.len());
+        let mut last = 0;
+        let matches = self.regex.captures_iter(text).filter(|caps| !caps[0].is_empty());
+        for (index, caps) in matches.enumerate() {
+            if picked.is_some_and(|picked| !picked.contains(&index)) {
+                continue;
+            }
+            let found = caps.get(0).map_or(0..0, |m| m.range());
+            result.push_str(&text[last..found.start]);
+            result.push_str(&self.expand(&caps, replacement));
+            last = found.end;
+        }
+        result.push_str(&text[last..]);
+        result
+    }
+
+    fn expand(&self, caps: &Captures, replacement: &str) -> String {
+        if self.expand {
+            let mut expanded = String::new();
+            caps.expand(replacement, &mut expanded);
+            expanded
+        } else {
+            replacement.to_string()
+        }
+    }
+}
+
+/// Which text in the library a match is in
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Location {
+    CompositionTitle(String),
+    CompositionContent(String),
+    /// A composition's note, by the composition's id and then the note's
+    Note(String, String),
+    ProjectTitle(String),
+    ProjectDescription(String),
+}
+
+/// One match and what replacing it would make of its line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hit {
+    pub location: Location,
+    /// Which match this is in its text
+    pub index: usize,
+    /// The title of the composition or project it's in
+    pub name: String,
+    /// The line around the match as it is, and where the match is, in chars
+    pub before: String,
+    pub matched: Range<usize>,
+    /// The line with this match replaced, and where the replacement is
+    pub after: String,
+    pub replaced: Range<usize>,
+}
+
+/// What [`replace_in_library`] changed, by id
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Replaced {
+    pub compositions: Vec<String>,
+    pub projects: Vec<String>,
+    /// How many matches were replaced
+    pub count: usize,
+}
+
+/// Every match in the library, composition by composition and then project by
+/// project, each with a preview of its replacement. Archived compositions are
+/// left out.
+pub fn find_in_library(
+    matcher: &Matcher,
+    replacement: &str,
+    compositions: &[Composition],
+    projects: &[Project],
+) -> Vec<Hit> {
+    let mut hits = Vec::new();
+    for (location, name, text) in library_texts(compositions, projects) {
+        for (index, caps) in matcher
+            .regex
+            .captures_iter(text)
+            .filter(|caps| !caps[0].is_empty())
+            .enumerate()
+        {
+            let found = caps.get(0).map_or(0..0, |m| m.range());
+            let replaced = matcher.expand(&caps, replacement);
+            hits.push(preview(location.clone(), index, name, text, found, &replaced));
+        }
+    }
+    hits
+}
+
+/// Make the replacements for the picked hits, found by [`find_in_library`] in
+/// the same library. A composition whose title changes is renamed as in the
+/// editor, so links to it follow it to its new title.
+pub fn replace_in_library(
+    matcher: &Matcher,
+    replacement: &str,
+    compositions: &mut [Composition],
+    projects: &mut [Project],
+    hits: &[Hit],
+) -> Replaced {
+    let picked = |location: &Location| -> HashSet<usize> {
+        hits.iter()
+            .filter(|hit| &hit.location == location)
+            .map(|hit| hit.index)
+            .collect()
+    };
+    let mut replaced = Replaced::default();
+    let now = Utc::now();
+    let mut renamed = Vec::new();
+
+    for comp in compositions.iter_mut().filter(|c| !c.archived) {
+        let old_content = comp.content.clone();
+        let old_title = comp.title.clone();
+        let mut changed = false;
+        let mut fields: Vec<(Location, &mut String)> = vec![
+            (Location::CompositionTitle(comp.id.clone()), &mut comp.title),
+            (Location::CompositionContent(comp.id.clone()), &mut comp.content),
+        ];
+        for note in comp.notes.iter_mut() {
+            fields.push((Location::Note(comp.id.clone(), note.id.clone()), &mut note.content));
+        }
+        for (location, text) in fields {
+            let indexes = picked(&location);
+            if !indexes.is_empty() {
+                *text = matcher.replace(text, replacement, Some(&indexes));
+                replaced.count += indexes.len();
+                changed = true;
+            }
+        }
+        if changed {
+            comp.follow_edit(&old_content);
+            comp.updated_at = now;
+            comp.update_word_count();
+            replaced.compositions.push(comp.id.clone());
+        }
+        if comp.title != old_title {
+            renamed.push((comp.id.clone(), old_title));
+        }
+    }
+    for (id, old_title) in renamed {
+        for linking in links::retitle(compositions, &id, &old_title) {
+            if !replaced.compositions.contains(&linking) {
+                replaced.compositions.push(linking);
+            }
+        }
+    }
+
+    for project in projects.iter_mut() {
+        let mut changed = false;
+        let fields = [
+            (Location::ProjectTitle(project.id.clone()), &mut project.title),
+            (Location::ProjectDescription(project.id.clone()), &mut project.description),
+        ];
+        for (location, text) in fields {
+            let indexes = picked(&location);
+            if !indexes.is_empty() {
+                *text = matcher.replace(text, replacement, Some(&indexes));
+                replaced.count += indexes.len();
+                changed = true;
+            }
+        }
+        if changed {
+            project.updated_at = now;
+            replaced.projects.push(project.id.clone());
+        }
+    }
+
+    replaced
+}
+
+/// Each searchable text in the library, where it is and what it belongs to
+fn library_texts<'a>(
+    compositions: &'a [Composition],
+    projects: &'a [Project],
+) -> Vec<(Location, &'a str, &'a str)> {
+    let mut texts = Vec::new();
+    for comp in compositions.iter().filter(|c| !c.archived) {
+        let name = if comp.title.trim().is_empty() { "Untitled" } else { comp.title.as_str() };
+        texts.push((Location::CompositionTitle(comp.id.clone()), name, comp.title.as_str()));
+        texts.push((Location::CompositionContent(comp.id.clone()), name, comp.content.as_str()));
+        for note in &comp.notes {
+            texts.push((Location::Note(comp.id.clone(), note.id.clone()), name, note.content.as_str()));
+        }
+    }
+    for project in projects {
+        texts.push((Location::ProjectTitle(project.id.clone()), project.title.as_str(), project.title.as_str()));
+        texts.push((
+            Location::ProjectDescription(project.id.clone()),
+            project.title.as_str(),
+            project.description.as_str(),
+        ));
+    }
+    texts
+}
+
+/// The line around a match, before and after replacing it, cut down to
+/// [`CONTEXT_CHARS`] around the match
+fn preview(location: Location, index: usize, name: &str, text: &str, found: Range<usize>, replaced: &str) -> Hit {
+    let line_start = text[..found.start].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = text[found.end..].find('\n').map_or(text.len(), |i| found.end + i);
+    let lead = &text[line_start..found.start];
+    let matched = &text[found.clone()];
+    let tail = &text[found.end..line_end];
+
+    // Keep some of the line before the match, and as much after as fits
+    let room = CONTEXT_CHARS.saturating_sub(matched.chars().count().max(replaced.chars().count()));
+    let lead_chars = lead.chars().count();
+    let keep = lead_chars.min(room / 3).max(room.saturating_sub(tail.chars().count())).min(lead_chars);
+    let lead: String = if keep < lead_chars {
+        format!("…{}", lead.chars().skip(lead_chars - keep).collect::<String>())
+    } else {
+        lead.to_string()
+    };
+    let tail_room = room.saturating_sub(keep);
+    let tail: String = if tail.chars().count() > tail_room {
+        format!("{}…", tail.chars().take(tail_room).collect::<String>())
+    } else {
+        tail.to_string()
+    };
+
+    let start = lead.chars().count();
+    Hit {
+        location,
+        index,
+        name: name.to_string(),
+        before: format!("{}{}{}", lead, matched, tail),
+        matched: start..start + matched.chars().count(),
+        after: format!("{}{}{}", lead, replaced, tail),
+        replaced: start..start + replaced.chars().count(),
+    }
+}
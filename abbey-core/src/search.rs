@@ -0,0 +1,262 @@
+//! Finding compositions and flows by their text, as the desktop search
+//! provider does
+
+use crate::data::{Composition, Flow};
+use std::cmp::Reverse;
+
+const COMPOSITION_PREFIX: &str = "composition:";
+const FLOW_PREFIX: &str = "flow:";
+
+/// Longest excerpt shown under a result, in characters
+pub const EXCERPT_CHARS: usize = 80;
+
+/// What a search result refers to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Target {
+    Composition(String),
+    Flow(String),
+}
+
+impl Target {
+    /// The identifier handed to the desktop shell, such as `composition:<id>`
+    pub fn result_id(&self) -> String {
+        match self {
+            Self::Composition(id) => format!("{}{}", COMPOSITION_PREFIX, id),
+            Self::Flow(id) => format!("{}{}", FLOW_PREFIX, id),
+        }
+    }
+
+    pub fn parse(result_id: &str) -> Option<Self> {
+        if let Some(id) = result_id.strip_prefix(COMPOSITION_PREFIX) {
+            Some(Self::Composition(id.to_string()))
+        } else {
+            result_id.strip_prefix(FLOW_PREFIX).map(|id| Self::Flow(id.to_string()))
+        }
+    }
+}
+
+/// How a result is shown: a name and an excerpt around the match
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResultMeta {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+}
+
+/// Result ids for everything matching all of `terms`, best first. Archived
+/// compositions are left out.
+pub fn search(compositions: &[Composition], flows: &[Flow], terms: &[String]) -> Vec<String> {
+    let terms = normalize_terms(terms);
+    if terms.is_empty() {
+        return Vec::new();
+    }
+
+    let mut scored = Vec::new();
+    for comp in compositions.iter().filter(|c| !c.archived) {
+        if let Some(score) = composition_score(comp, &terms) {
+            scored.push((score, comp.updated_at, Target::Composition(comp.id.clone())));
+        }
+    }
+    for flow in flows {
+        if let Some(score) = text_score(&flow.content, &terms) {
+            scored.push((score, flow.created_at, Target::Flow(flow.id.clone())));
+        }
+    }
+
+    // Stronger matches first, then the most recent
+    scored.sort_by_key(|(score, time, _)| (Reverse(*score), Reverse(*time)));
+    scored.into_iter().map(|(_, _, target)| target.result_id()).collect()
+}
+
+/// Narrow an earlier result set as the user keeps typing, keeping its order
+pub fn subsearch(compositions: &[Composition], flows: &[Flow], previous: &[String], terms: &[String]) -> Vec<String> {
+    let terms = normalize_terms(terms);
+    if terms.is_empty() {
+        return Vec::new();
+    }
+
+    previous
+        .iter()
+        .filter(|result_id| match Target::parse(result_id) {
+            Some(Target::Composition(id)) => compositions
+                .iter()
+                .find(|c| c.id == id)
+                .is_some_and(|c| composition_score(c, &terms).is_some()),
+            Some(Target::Flow(id)) => flows
+                .iter()
+                .find(|f| f.id == id)
+                .is_some_and(|f| text_score(&f.content, &terms).is_some()),
+            None => false,
+        })
+        .cloned()
+        .collect()
+}
+
+/// Names and excerpts for result ids; ids that no longer exist are skipped
+pub fn result_metas(
+    compositions: &[Composition],
+    flows: &[Flow],
+    result_ids: &[String],
+    terms: &[String],
+) -> Vec<ResultMeta> {
+    let terms = normalize_terms(terms);
+
+    result_ids
+        .iter()
+        .filter_map(|result_id| match Target::parse(result_id)? {
+            Target::Composition(id) => {
+                let comp = compositions.iter().find(|c| c.id == id)?;
+                let name = if comp.title.trim().is_empty() {
+                    "Untitled".to_string()
+                } else {
+                    comp.title.clone()
+                };
+                Some(ResultMeta {
+                    id: result_id.clone(),
+                    name,
+                    description: excerpt_around(&comp.content_without_suggestions(), &terms, EXCERPT_CHARS),
+                })
+            }
+            Target::Flow(id) => {
+                let flow = flows.iter().find(|f| f.id == id)?;
+                Some(ResultMeta {
+                    id: result_id.clone(),
+                    name: format!(
+                        "Flow on {}",
+                        flow.created_at.with_timezone(&chrono::Local).format("%B %-d, %Y")
+                    ),
+                    description: excerpt_around(&flow.content, &terms, EXCERPT_CHARS),
+                })
+            }
+        })
+        .collect()
+}
+
+/// Match an exact id, then an exact title ignoring case, then an id prefix, as
+/// the command line and automation interfaces accept. `Err` holds every item
+/// that matched when more than one did.
+pub fn find_by_id_or_title<'a, T>(
+    items: &'a [T],
+    query: &str,
+    id: impl Fn(&T) -> &String,
+    title: impl Fn(&T) -> &String,
+) -> Result<Option<&'a T>, Vec<&'a T>> {
+    if let Some(item) = items.iter().find(|item| id(item) == query) {
+        return Ok(Some(item));
+    }
+
+    let lowered = query.to_lowercase();
+    let by_title: Vec<_> = items.iter().filter(|item| title(item).to_lowercase() == lowered).collect();
+    let mut candidates = if by_title.is_empty() {
+        items.iter().filter(|item| id(item).starts_with(query)).collect()
+    } else {
+        by_title
+    };
+
+    match candidates.len() {
+        0 => Ok(None),
+        1 => Ok(candidates.pop()),
+        _ => Err(candidates),
+    }
+}
+
+/// Up to `max_chars` of `text` on one line, starting a little before the first
+/// word containing a term, or at the beginning if none does
+pub fn excerpt_around(text: &str, terms: &[String], max_chars: usize) -> String {
+    let terms = normalize_terms(terms);
+    let words: Vec<&str> = text
+        .split_whitespace()
+        .filter(|word| !word.chars().all(|c| matches!(c, '#' | '*' | '-' | '>' | '+')))
+        .collect();
+
+    let hit = words
+        .iter()
+        .position(|word| {
+            let word = word.to_lowercase();
+            terms.iter().any(|term| word.contains(term.as_str()))
+        })
+        .unwrap_or(0);
+
+    // Lead in with a few words so the match has some context
+    let mut start = hit.saturating_sub(3);
+    while start < hit && char_len(&words[start..=hit]) > max_chars {
+        start += 1;
+    }
+
+    let mut excerpt = String::new();
+    let mut end = start;
+    for word in &words[start..] {
+        let needed = word.chars().count() + usize::from(!excerpt.is_empty());
+        if excerpt.chars().count() + needed > max_chars {
+            break;
+        }
+        if !excerpt.is_empty() {
+            excerpt.push(' ');
+        }
+        excerpt.push_str(word);
+        end += 1;
+    }
+
+    // A single word too long to fit is cut rather than dropped
+    if excerpt.is_empty() && start < words.len() {
+        excerpt = words[start].chars().take(max_chars).collect();
+        end = start + 1;
+    }
+
+    if start > 0 {
+        excerpt.insert(0, '…');
+    }
+    if end < words.len() {
+        excerpt.push('…');
+    }
+    excerpt
+}
+
+fn normalize_terms(terms: &[String]) -> Vec<String> {
+    terms
+        .iter()
+        .map(|term| term.trim().to_lowercase())
+        .filter(|term| !term.is_empty())
+        .collect()
+}
+
+fn char_len(words: &[&str]) -> usize {
+    words.iter().map(|w| w.chars().count()).sum::<usize>() + words.len().saturating_sub(1)
+}
+
+/// Every term has to match somewhere; title matches count most, then tags,
+/// then the text and notes
+fn composition_score(comp: &Composition, terms: &[String]) -> Option<u32> {
+    let title = comp.title.to_lowercase();
+    let tags: Vec<String> = comp.tags.iter().map(|t| t.to_lowercase()).collect();
+    let content = comp.content_without_suggestions().to_lowercase();
+    let notes: Vec<String> = comp.notes.iter().map(|n| n.content.to_lowercase()).collect();
+
+    let mut score = 0;
+    for term in terms {
+        score += if title.contains(term.as_str()) {
+            if title.starts_with(term.as_str()) { 5 } else { 4 }
+        } else if tags.iter().any(|t| t.contains(term.as_str())) {
+            3
+        } else if content.contains(term.as_str()) {
+            2
+        } else if notes.iter().any(|n| n.contains(term.as_str())) {
+            1
+        } else {
+            return None;
+        };
+    }
+    Some(score)
+}
+
+fn text_score(text: &str, terms: &[String]) -> Option<u32> {
+    let text = text.to_lowercase();
+    let mut score = 0;
+    for term in terms {
+        if !text.contains(term.as_str()) {
+            return None;
+        }
+        score += 2;
+    }
+    Some(score)
+}
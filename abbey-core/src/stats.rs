@@ -0,0 +1,87 @@
+//! Statistics over a library's compositions, flows and projects
+
+use crate::data::{Composition, Flow, Project};
+use crate::utils::text_stats;
+use chrono::{DateTime, Duration, Local, NaiveDate, Utc};
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+/// Totals for a whole library
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct LibraryStats {
+    /// Compositions that aren't archived
+    pub compositions: usize,
+    pub archived: usize,
+    /// Words across all compositions that aren't archived
+    pub composition_words: usize,
+    pub notes: usize,
+    pub projects: usize,
+    pub flows: usize,
+    pub flow_words: usize,
+    pub flow_seconds: u64,
+    /// Consecutive days with at least one flow, up to today
+    pub flow_streak_days: u32,
+}
+
+impl LibraryStats {
+    pub fn new(compositions: &[Composition], flows: &[Flow], projects: &[Project]) -> Self {
+        Self::on_day(compositions, flows, projects, Local::now().date_naive())
+    }
+
+    /// Statistics as they'd be on `today`, which only affects the streak
+    pub fn on_day(compositions: &[Composition], flows: &[Flow], projects: &[Project], today: NaiveDate) -> Self {
+        let active: Vec<_> = compositions.iter().filter(|c| !c.archived).collect();
+
+        Self {
+            compositions: active.len(),
+            archived: compositions.len() - active.len(),
+            composition_words: active.iter().map(|c| text_stats::word_count(&c.content_without_suggestions())).sum(),
+            notes: active.iter().map(|c| c.notes.len()).sum(),
+            projects: projects.len(),
+            flows: flows.len(),
+            flow_words: flows.iter().map(|f| f.word_count()).sum(),
+            flow_seconds: flows.iter().map(|f| f.actual_duration_seconds).sum(),
+            flow_streak_days: flow_streak(flows, today),
+        }
+    }
+}
+
+/// The local calendar day something happened on
+pub fn local_day(time: &DateTime<Utc>) -> NaiveDate {
+    time.with_timezone(&Local).date_naive()
+}
+
+/// Words written in flows, per local day
+pub fn flow_words_by_day(flows: &[Flow]) -> BTreeMap<NaiveDate, usize> {
+    let mut days = BTreeMap::new();
+    for flow in flows {
+        *days.entry(local_day(&flow.created_at)).or_insert(0) += flow.word_count();
+    }
+    days
+}
+
+/// Number of consecutive days with at least one flow, ending today. A streak
+/// that ended yesterday still counts, since today's flow may not have happened yet.
+pub fn flow_streak(flows: &[Flow], today: NaiveDate) -> u32 {
+    let days = flow_words_by_day(flows);
+
+    let mut day = today;
+    if !days.contains_key(&day) {
+        day -= Duration::days(1);
+    }
+
+    let mut streak = 0;
+    while days.contains_key(&day) {
+        streak += 1;
+        day -= Duration::days(1);
+    }
+    streak
+}
+
+/// Flows started at or after `since`, newest first
+pub fn flows_since(flows: &[Flow], since: DateTime<Utc>) -> Vec<Flow> {
+    let mut recent: Vec<_> = flows.iter().filter(|f| f.created_at >= since).cloned().collect();
+    recent.sort_by_key(|f| Reverse(f.created_at));
+    recent
+}
@@ -0,0 +1,75 @@
+//! Keeping a comment on the stretch of text it was made on while the text
+//! around it changes. An anchor is a char range together with its quote, the
+//! text the range covered: edits away from the quote move the range along,
+//! and when an edit reaches it the quote is looked for again nearby.
+
+use std::ops::Range;
+
+/// Where `range`, which covered `quote` in `old`, is once `old` became `new`
+///
+/// An anchor whose quote can't be found any more ends up empty, where the
+/// quote was.
+pub fn follow(range: Range<usize>, quote: &str, old: &str, new: &str) -> Range<usize> {
+    let old: Vec<char> = old.chars().collect();
+    let new: Vec<char> = new.chars().collect();
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let changed = prefix..old.len() - suffix;
+
+    // Outside the change an offset moves by what the change added or removed,
+    // and text typed right at either end of the anchor stays outside it
+    let inside = |offset: usize| prefix.max(offset.min(new.len() - suffix));
+    let shifted = |offset: usize| offset + new.len() - old.len();
+    let start = range.start.min(old.len());
+    let end = range.end.clamp(start, old.len());
+    let moved_start = if start >= changed.end {
+        shifted(start)
+    } else if start < changed.start {
+        start
+    } else {
+        inside(start)
+    };
+    let moved_end = if end <= changed.start {
+        end
+    } else if end >= changed.end {
+        shifted(end)
+    } else {
+        inside(end)
+    };
+    let range = start..end;
+    let moved = moved_start..moved_end.max(moved_start);
+
+    let text = |chars: &[char], range: &Range<usize>| chars[range.clone()].iter().collect::<String>();
+    let untouched = range.end <= changed.start || range.start >= changed.end;
+    if text(&new, &moved) == quote || (untouched && text(&old, &range) == quote) {
+        return moved;
+    }
+
+    let new: String = new.iter().collect();
+    nearest(&new, quote, moved.start).unwrap_or(moved.start..moved.start)
+}
+
+/// Where `quote` is in `text`, nearest to char `near`
+pub fn nearest(text: &str, quote: &str, near: usize) -> Option<Range<usize>> {
+    if quote.is_empty() {
+        return None;
+    }
+    let quote_chars = quote.chars().count();
+    let mut best: Option<Range<usize>> = None;
+    let mut chars = 0;
+    let mut last = 0;
+    for (byte, _) in text.match_indices(quote) {
+        chars += text[last..byte].chars().count();
+        last = byte;
+        let found = chars..chars + quote_chars;
+        if best.as_ref().is_none_or(|best| chars.abs_diff(near) < best.start.abs_diff(near)) {
+            best = Some(found);
+        }
+    }
+    best
+}
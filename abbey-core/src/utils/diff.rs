@@ -0,0 +1,238 @@
+//! Word-level comparison of two texts. Lines are compared first, so long
+//! documents with a few edits compare quickly, and then the words of the
+//! lines that changed. The result is the list of changes, or hunks; whatever
+//! lies between them is the same in both texts.
+
+use std::ops::Range;
+
+/// How many edits a comparison goes looking for before it calls a stretch of
+/// text rewritten outright
+const MAX_EDITS: isize = 2000;
+
+/// One change between two texts: `old[old]` became `new[new]`, in bytes.
+/// Either side may be empty, for text that was only added or removed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hunk {
+    pub old: Range<usize>,
+    pub new: Range<usize>,
+}
+
+impl Hunk {
+    /// `new` with this change taken back, as it is in `old`
+    pub fn copy_to_new(&self, old: &str, new: &str) -> String {
+        format!("{}{}{}", &new[..self.new.start], &old[self.old.clone()], &new[self.new.end..])
+    }
+
+    /// `old` with this change made, as it is in `new`
+    pub fn copy_to_old(&self, old: &str, new: &str) -> String {
+        format!("{}{}{}", &old[..self.old.start], &new[self.new.clone()], &old[self.old.end..])
+    }
+}
+
+/// The changes that turn `old` into `new`, word by word, in order
+pub fn compare(old: &str, new: &str) -> Vec<Hunk> {
+    let old_lines: Vec<&str> = old.split_inclusive('\n').collect();
+    let new_lines: Vec<&str> = new.split_inclusive('\n').collect();
+
+    // Runs of lines that changed, compared again word by word
+    let mut hunks = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    let (mut old_at, mut new_at) = (0, 0);
+    let mut changed_from = (0, 0);
+    for step in steps(&old_lines, &new_lines).into_iter().chain([Step::End]) {
+        if matches!(step, Step::Same | Step::End) && changed_from != (old_at, new_at) {
+            compare_words(old, new, changed_from.0..old_at, changed_from.1..new_at, &mut hunks);
+        }
+        match step {
+            Step::Same => {
+                old_at += old_lines[i].len();
+                new_at += new_lines[j].len();
+                i += 1;
+                j += 1;
+                changed_from = (old_at, new_at);
+            }
+            Step::Removed => {
+                old_at += old_lines[i].len();
+                i += 1;
+            }
+            Step::Added => {
+                new_at += new_lines[j].len();
+                j += 1;
+            }
+            Step::End => {}
+        }
+    }
+    merge(old, hunks)
+}
+
+/// Compare `old[old_range]` with `new[new_range]` word by word
+fn compare_words(old: &str, new: &str, old_range: Range<usize>, new_range: Range<usize>, hunks: &mut Vec<Hunk>) {
+    let old_tokens = tokens(&old[old_range.clone()], old_range.start);
+    let new_tokens = tokens(&new[new_range.clone()], new_range.start);
+    let old_words: Vec<&str> = old_tokens.iter().map(|token| &old[token.clone()]).collect();
+    let new_words: Vec<&str> = new_tokens.iter().map(|token| &new[token.clone()]).collect();
+
+    let (mut i, mut j) = (0, 0);
+    let mut change: Option<(usize, usize)> = None;
+    for step in steps(&old_words, &new_words).into_iter().chain([Step::End]) {
+        if matches!(step, Step::Same | Step::End) {
+            if let Some((from_i, from_j)) = change.take() {
+                hunks.push(Hunk {
+                    old: span(&old_tokens, from_i..i, old_range.start),
+                    new: span(&new_tokens, from_j..j, new_range.start),
+                });
+            }
+        } else if change.is_none() {
+            change = Some((i, j));
+        }
+        match step {
+            Step::Same => {
+                i += 1;
+                j += 1;
+            }
+            Step::Removed => i += 1,
+            Step::Added => j += 1,
+            Step::End => {}
+        }
+    }
+}
+
+/// The bytes tokens `range` cover, or where they'd go if there are none
+fn span(tokens: &[Range<usize>], range: Range<usize>, start: usize) -> Range<usize> {
+    let at = |index: usize| match tokens.get(index) {
+        Some(token) => token.start,
+        None => tokens.last().map_or(start, |token| token.end),
+    };
+    at(range.start)..at(range.end)
+}
+
+/// Words, runs of whitespace and single other chars, as byte ranges offset
+/// by `offset`
+fn tokens(text: &str, offset: usize) -> Vec<Range<usize>> {
+    let mut tokens: Vec<Range<usize>> = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        let mut end = start + c.len_utf8();
+        let same_kind = |next: char, prev: char| {
+            if c.is_alphanumeric() {
+                next.is_alphanumeric() || (is_apostrophe(next) && prev.is_alphanumeric())
+            } else {
+                c.is_whitespace() && next.is_whitespace()
+            }
+        };
+        let mut prev = c;
+        while let Some(&(at, next)) = chars.peek() {
+            // An apostrophe stays in a word only if a letter follows it
+            if is_apostrophe(next) && !text[at + next.len_utf8()..].starts_with(char::is_alphanumeric) {
+                break;
+            }
+            if !same_kind(next, prev) {
+                break;
+            }
+            end = at + next.len_utf8();
+            prev = next;
+            chars.next();
+        }
+        tokens.push(start + offset..end + offset);
+    }
+    tokens
+}
+
+fn is_apostrophe(c: char) -> bool {
+    c == '\'' || c == '’'
+}
+
+/// Join changes with only a space or line break between them, which read as
+/// one edit
+fn merge(old: &str, hunks: Vec<Hunk>) -> Vec<Hunk> {
+    let mut merged: Vec<Hunk> = Vec::new();
+    for hunk in hunks {
+        if let Some(last) = merged.last_mut() {
+            let gap = &old[last.old.end..hunk.old.start];
+            if gap.chars().all(char::is_whitespace) && gap.matches('\n').count() < 2 {
+                last.old.end = hunk.old.end;
+                last.new.end = hunk.new.end;
+                continue;
+            }
+        }
+        merged.push(hunk);
+    }
+    merged
+}
+
+/// One step through two sequences being compared
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Step {
+    Same,
+    Removed,
+    Added,
+    /// Past the end of both, to finish what's pending
+    End,
+}
+
+/// The fewest steps from `a` to `b` (Myers' algorithm), or everything removed
+/// and then everything added when they differ too much to be worth it
+fn steps<T: PartialEq>(a: &[T], b: &[T]) -> Vec<Step> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let max = n + m;
+    let index = |k: isize| (k + max) as usize;
+
+    // The furthest x reached on each diagonal k = x - y, after each number of
+    // edits, for finding the way back
+    let mut v = vec![0isize; 2 * max as usize + 2];
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+    let mut edits = None;
+    for d in 0..=max.min(MAX_EDITS) {
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && v[index(k - 1)] < v[index(k + 1)]) {
+                v[index(k + 1)]
+            } else {
+                v[index(k - 1)] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[index(k)] = x;
+            if x >= n && y >= m {
+                edits = Some(d);
+                break;
+            }
+        }
+        trace.push((-d..=d).map(|k| v[index(k)]).collect());
+        if edits.is_some() {
+            break;
+        }
+    }
+    let edits = match edits {
+        Some(edits) => edits,
+        None => {
+            let mut steps = vec![Step::Removed; a.len()];
+            steps.extend(vec![Step::Added; b.len()]);
+            return steps;
+        }
+    };
+
+    let mut steps = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for d in (1..=edits).rev() {
+        let previous = &trace[d as usize - 1];
+        let reached = |k: isize| previous[(k + d - 1) as usize];
+        let k = x - y;
+        let previous_k = if k == -d || (k != d && reached(k - 1) < reached(k + 1)) { k + 1 } else { k - 1 };
+        let previous_x = reached(previous_k);
+        let previous_y = previous_x - previous_k;
+        while x > previous_x && y > previous_y {
+            steps.push(Step::Same);
+            x -= 1;
+            y -= 1;
+        }
+        steps.push(if x == previous_x { Step::Added } else { Step::Removed });
+        x = previous_x;
+        y = previous_y;
+    }
+    steps.extend((0..x).map(|_| Step::Same));
+    steps.reverse();
+    steps
+}
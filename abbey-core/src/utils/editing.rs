@@ -0,0 +1,206 @@
+//! Small markdown editing decisions made as the user types: auto-pairing of
+//! brackets and emphasis markers, and continuing lists on Enter, which only
+//! look at the text of the current line around the cursor, and the sentence
+//! or paragraph focus mode keeps lit.
+
+use std::ops::Range;
+
+use super::text_stats;
+
+/// What pressing Enter should do at the end of a list item or quote line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ListContinuation {
+    /// Start the next line with this prefix, e.g. `"- "` or `"> 3. "`
+    Continue(String),
+    /// The item was left empty, so end the list by removing its marker
+    End,
+}
+
+/// What typing a character should do
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Typed {
+    /// Insert it as usual
+    Insert,
+    /// Insert it with the given closing character after the cursor
+    Pair(char),
+    /// Step over the same character already after the cursor
+    Skip,
+}
+
+/// The character that closes `open`, for the pairs the editor completes
+pub fn closing_pair(open: char) -> Option<char> {
+    match open {
+        '(' => Some(')'),
+        '[' => Some(']'),
+        '{' => Some('}'),
+        '*' | '_' | '`' => Some(open),
+        _ => None,
+    }
+}
+
+/// Decide what typing `c` does, given the line text `before` and `after` the cursor
+pub fn on_typed(c: char, before: &str, after: &str) -> Typed {
+    let next = after.chars().next();
+
+    match c {
+        ')' | ']' | '}' => {
+            if next == Some(c) {
+                Typed::Skip
+            } else {
+                Typed::Insert
+            }
+        }
+        '(' | '[' | '{' => {
+            if next.is_none_or(|n| n.is_whitespace() || is_closing_punctuation(n)) {
+                Typed::Pair(closing_pair(c).unwrap_or(c))
+            } else {
+                Typed::Insert
+            }
+        }
+        '*' | '_' | '`' => on_marker(c, before, next),
+        _ => Typed::Insert,
+    }
+}
+
+/// Emphasis and code markers open at the start of a word and close at its end,
+/// so `snake_case` and `2*3` are left alone
+fn on_marker(c: char, before: &str, next: Option<char>) -> Typed {
+    // A `*` at the start of a line is a list bullet and backticks there a fence
+    if (c == '*' || c == '`') && before.trim_end_matches(c).trim().is_empty() {
+        return Typed::Insert;
+    }
+
+    // Look past a run of the same marker, so `**` pairs like `*`
+    let opens = before
+        .trim_end_matches(c)
+        .chars()
+        .next_back()
+        .is_none_or(|p| p.is_whitespace() || matches!(p, '(' | '[' | '{' | '"' | '\'' | '“' | '‘' | '«'));
+
+    if opens {
+        if next.is_none_or(|n| n == c || n.is_whitespace() || is_closing_punctuation(n)) {
+            Typed::Pair(c)
+        } else {
+            Typed::Insert
+        }
+    } else if next == Some(c) {
+        Typed::Skip
+    } else {
+        Typed::Insert
+    }
+}
+
+fn is_closing_punctuation(c: char) -> bool {
+    matches!(c, ')' | ']' | '}' | '.' | ',' | ';' | ':' | '!' | '?')
+}
+
+/// Whether Backspace between `before` and `after` should delete both, as they
+/// are an empty pair
+pub fn deletes_pair(before: char, after: char) -> bool {
+    closing_pair(before) == Some(after)
+}
+
+/// Decide what Enter does on a list item or quote line, given the line text
+/// `before` and `after` the cursor. `None` means it's an ordinary line.
+pub fn continue_list(before: &str, after: &str) -> Option<ListContinuation> {
+    let quote = quote_prefix(before);
+    let body = &before[quote.len()..];
+    let content = body.trim_start_matches([' ', '\t']);
+    let indent = &body[..body.len() - content.len()];
+
+    let (marker, content) = match list_marker(content) {
+        Some(found) => found,
+        None if !quote.is_empty() => (String::new(), body),
+        None => return None,
+    };
+
+    if content.trim().is_empty() && after.trim().is_empty() {
+        return Some(ListContinuation::End);
+    }
+
+    if marker.is_empty() {
+        Some(ListContinuation::Continue(quote.to_string()))
+    } else {
+        Some(ListContinuation::Continue(format!("{}{}{} ", quote, indent, marker)))
+    }
+}
+
+/// The leading `>` markers of a quote line, with the spaces around them
+fn quote_prefix(line: &str) -> &str {
+    let mut end = 0;
+    let mut rest = line;
+
+    loop {
+        let trimmed = rest.trim_start_matches(' ');
+        match trimmed.strip_prefix('>') {
+            Some(after) => {
+                rest = after.strip_prefix(' ').unwrap_or(after);
+                end = line.len() - rest.len();
+            }
+            None => return &line[..end],
+        }
+    }
+}
+
+/// The marker the next item should get, and the text after this item's marker
+fn list_marker(line: &str) -> Option<(String, &str)> {
+    if let Some(bullet) = line.chars().next().filter(|c| matches!(c, '-' | '*' | '+')) {
+        let rest = line[1..].strip_prefix([' ', '\t'])?;
+        // Checked or not, the next task starts unchecked
+        for task in ["[ ] ", "[x] ", "[X] "] {
+            if let Some(content) = rest.strip_prefix(task) {
+                return Some((format!("{} [ ]", bullet), content));
+            }
+        }
+        return Some((bullet.to_string(), rest));
+    }
+
+    let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    if digits == 0 || digits > 9 {
+        return None;
+    }
+    let delimiter = line[digits..].chars().next().filter(|c| matches!(c, '.' | ')'))?;
+    let rest = line[digits + 1..].strip_prefix([' ', '\t'])?;
+    let number: u64 = line[..digits].parse().ok()?;
+    Some((format!("{}{}", number + 1, delimiter), rest))
+}
+
+// ========== Focus ==========
+
+/// The paragraph around a char offset: the lines around it up to the nearest
+/// blank ones, without the final line break. Empty on a blank line.
+pub fn paragraph_at(text: &str, offset: usize) -> Range<usize> {
+    let mut start = 0;
+    let mut paragraph: Option<Range<usize>> = None;
+    for line in text.split('\n') {
+        let end = start + line.chars().count();
+        if line.trim().is_empty() {
+            match paragraph.take() {
+                Some(found) if found.start <= offset && offset <= found.end => return found,
+                _ => {}
+            }
+        } else {
+            paragraph = Some(paragraph.map_or(start, |found| found.start)..end);
+        }
+        start = end + 1;
+    }
+    match paragraph {
+        Some(found) if found.start <= offset && offset <= found.end => found,
+        _ => offset..offset,
+    }
+}
+
+/// The sentence around a char offset, within its paragraph; between two
+/// sentences it's the one before. The whole paragraph if it has no sentences.
+pub fn sentence_at(text: &str, offset: usize) -> Range<usize> {
+    let paragraph = paragraph_at(text, offset);
+    let body: String = text.chars().skip(paragraph.start).take(paragraph.len()).collect();
+    let spans = text_stats::sentence_spans(&body);
+    let within = offset - paragraph.start;
+    spans
+        .iter()
+        .rev()
+        .find(|span| span.start <= within)
+        .or(spans.first())
+        .map_or(paragraph.clone(), |span| paragraph.start + span.start..paragraph.start + span.end)
+}
@@ -0,0 +1,333 @@
+//! Which words and phrases a piece of writing leans on: the most frequent
+//! words and phrases once stopwords are left out, words used far more often
+//! than in the rest of the library, and stretches where the same word keeps
+//! coming back.
+//!
+//! Works over the words [`text_stats::source_words`] finds, so every result
+//! can be found again in the markdown it came from.
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+use super::text_stats::{self, SourceWord};
+
+/// How many words and phrases the lists keep
+const TOP_TERMS: usize = 25;
+
+/// A word has to turn up this often before it can count as overused
+const MIN_OVERUSED_COUNT: usize = 4;
+
+/// How many times more often than in the library a word has to turn up to be
+/// overused
+const OVERUSED_RATIO: f64 = 3.0;
+
+/// Too small a library says nothing about which words are unusual
+const MIN_BASELINE_WORDS: usize = 1000;
+
+/// A word repeated with no more than this many words between makes a cluster
+const CLUSTER_GAP: usize = 40;
+
+/// How many close repeats make a cluster
+const CLUSTER_SIZE: usize = 3;
+
+/// Common English words that say nothing about the writing
+const STOPWORDS: &[&str] = &[
+    "a", "about", "above", "after", "again", "against", "all", "also", "am", "an", "and", "any", "are", "aren't",
+    "as", "at", "be", "because", "been", "before", "being", "below", "between", "both", "but", "by", "can",
+    "can't", "could", "couldn't", "did", "didn't", "do", "does", "doesn't", "doing", "don't", "down", "during",
+    "each", "even", "ever", "few", "for", "from", "further", "get", "got", "had", "hadn't", "has", "hasn't",
+    "have", "haven't", "having", "he", "he'd", "he'll", "he's", "her", "here", "hers", "herself", "him",
+    "himself", "his", "how", "i", "i'd", "i'll", "i'm", "i've", "if", "in", "into", "is", "isn't", "it", "it's",
+    "its", "itself", "just", "let's", "like", "me", "might", "more", "most", "much", "must", "my", "myself",
+    "no", "nor", "not", "now", "of", "off", "on", "once", "one", "only", "or", "other", "our", "ours",
+    "ourselves", "out", "over", "own", "really", "said", "same", "she", "she'd", "she'll", "she's", "should",
+    "so", "some", "still", "such", "than", "that", "that's", "the", "their", "theirs", "them", "themselves",
+    "then", "there", "there's", "these", "they", "they'd", "they'll", "they're", "they've", "this", "those",
+    "through", "to", "too", "under", "until", "up", "upon", "us", "very", "was", "wasn't", "we", "we'd",
+    "we'll", "we're", "we've", "were", "weren't", "what", "what's", "when", "where", "which", "while", "who",
+    "whom", "why", "will", "with", "won't", "would", "wouldn't", "yet", "you", "you'd", "you'll", "you're",
+    "you've", "your", "yours", "yourself", "yourselves",
+];
+
+/// Whether a word is too common to be worth reporting
+pub fn is_stopword(word: &str) -> bool {
+    STOPWORDS.contains(&normalize(word).as_str())
+}
+
+/// Lower case, with typographic apostrophes made straight
+fn normalize(word: &str) -> String {
+    word.to_lowercase().replace('\u{2019}', "'")
+}
+
+/// A word or phrase and how often it's used
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Term {
+    /// In lower case; phrases have single spaces between words
+    pub text: String,
+    pub count: usize,
+}
+
+/// A word used far more often than in the rest of the library
+#[derive(Debug, Clone, PartialEq)]
+pub struct Overused {
+    pub word: String,
+    pub count: usize,
+    /// Uses per thousand words here
+    pub rate: f64,
+    /// Uses per thousand words in the rest of the library
+    pub library_rate: f64,
+}
+
+/// The same word repeated close together
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cluster {
+    /// Which of the analyzed documents it's in
+    pub document: usize,
+    pub word: String,
+    /// Each use, in chars into that document
+    pub occurrences: Vec<Range<usize>>,
+}
+
+impl Cluster {
+    /// From the first use to the last
+    pub fn range(&self) -> Range<usize> {
+        let start = self.occurrences.first().map_or(0, |r| r.start);
+        let end = self.occurrences.last().map_or(0, |r| r.end);
+        start..end
+    }
+}
+
+/// What [`analyze`] found
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Analysis {
+    pub words: usize,
+    /// The most used words, leaving out stopwords
+    pub top_words: Vec<Term>,
+    /// The most used two- and three-word phrases that neither start nor end
+    /// with a stopword
+    pub top_phrases: Vec<Term>,
+    /// Most overused first
+    pub overused: Vec<Overused>,
+    /// In document order
+    pub clusters: Vec<Cluster>,
+}
+
+/// How often words turn up across the rest of the library, to compare with
+#[derive(Debug, Clone, Default)]
+pub struct Baseline {
+    counts: HashMap<String, usize>,
+    total: usize,
+}
+
+impl Baseline {
+    pub fn new<'a>(documents: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut baseline = Self::default();
+        for document in documents {
+            for token in tokens(document) {
+                *baseline.counts.entry(token.key).or_insert(0) += 1;
+                baseline.total += 1;
+            }
+        }
+        baseline
+    }
+
+    /// Whether there's enough writing to tell which words are unusual
+    pub fn is_useful(&self) -> bool {
+        self.total >= MIN_BASELINE_WORDS
+    }
+
+    /// Uses of a word per thousand words, smoothed so unseen words aren't
+    /// infinitely rare
+    fn rate(&self, word: &str) -> f64 {
+        (self.counts.get(word).copied().unwrap_or(0) as f64 + 1.0) * 1000.0 / (self.total as f64 + 1.0)
+    }
+}
+
+/// A word of a document, ready to compare
+struct Token {
+    key: String,
+    range: Range<usize>,
+    /// Whether a new sentence or block starts here, which phrases don't cross
+    starts_sentence: bool,
+}
+
+fn tokens(markdown: &str) -> Vec<Token> {
+    let chars: Vec<char> = markdown.chars().collect();
+    let mut tokens = Vec::new();
+    for block in text_stats::source_words(markdown) {
+        let mut previous: Option<&SourceWord> = None;
+        for word in &block {
+            let starts_sentence = match previous {
+                None => true,
+                Some(previous) => chars
+                    .get(previous.range.end..word.range.start)
+                    .is_some_and(|gap| gap.iter().any(|c| matches!(c, '.' | '!' | '?' | '…' | ';' | ':' | '。' | '！' | '？'))),
+            };
+            tokens.push(Token {
+                key: normalize(&word.text),
+                range: word.range.clone(),
+                starts_sentence,
+            });
+            previous = Some(word);
+        }
+    }
+    tokens
+}
+
+/// Whether a word is worth counting on its own: not a stopword or a number
+fn is_content_word(key: &str) -> bool {
+    key.chars().any(char::is_alphabetic) && !STOPWORDS.contains(&key)
+}
+
+/// Analyze one document, or several as one, such as a project's compositions,
+/// against the rest of the library
+pub fn analyze(documents: &[&str], baseline: &Baseline) -> Analysis {
+    let documents: Vec<Vec<Token>> = documents.iter().map(|document| tokens(document)).collect();
+    let words: usize = documents.iter().map(Vec::len).sum();
+
+    let mut word_counts: HashMap<&str, usize> = HashMap::new();
+    let mut phrase_counts: HashMap<String, usize> = HashMap::new();
+    for tokens in &documents {
+        for (i, token) in tokens.iter().enumerate() {
+            if is_content_word(&token.key) {
+                *word_counts.entry(&token.key).or_insert(0) += 1;
+            }
+            for len in 2..=3 {
+                if let Some(phrase) = phrase_at(tokens, i, len) {
+                    *phrase_counts.entry(phrase).or_insert(0) += 1;
+                }
+            }
+        }
+    }
+
+    let top_words = top(word_counts.iter().map(|(word, count)| (word.to_string(), *count)), 1);
+
+    // A pair that only ever turns up inside a longer phrase is that phrase
+    let longer: Vec<(&String, usize)> = phrase_counts
+        .iter()
+        .filter(|(phrase, _)| phrase.matches(' ').count() == 2)
+        .map(|(phrase, count)| (phrase, *count))
+        .collect();
+    let phrases = phrase_counts.iter().filter(|(phrase, count)| {
+        phrase.matches(' ').count() == 2
+            || !longer
+                .iter()
+                .any(|(long, long_count)| long_count == *count && long.contains(phrase.as_str()))
+    });
+    let top_phrases = top(phrases.map(|(phrase, count)| (phrase.clone(), *count)), 2);
+
+    let mut overused = Vec::new();
+    if baseline.is_useful() && words > 0 {
+        for (word, count) in &word_counts {
+            let rate = *count as f64 * 1000.0 / words as f64;
+            let library_rate = baseline.rate(word);
+            if *count >= MIN_OVERUSED_COUNT && rate >= library_rate * OVERUSED_RATIO {
+                overused.push(Overused {
+                    word: word.to_string(),
+                    count: *count,
+                    rate,
+                    library_rate,
+                });
+            }
+        }
+    }
+    overused.sort_by(|a, b| {
+        (b.rate / b.library_rate)
+            .total_cmp(&(a.rate / a.library_rate))
+            .then_with(|| a.word.cmp(&b.word))
+    });
+    overused.truncate(TOP_TERMS);
+
+    let mut clusters = Vec::new();
+    for (document, tokens) in documents.iter().enumerate() {
+        clusters.extend(find_clusters(document, tokens));
+    }
+    clusters.sort_by_key(|cluster| (cluster.document, cluster.range().start));
+
+    Analysis {
+        words,
+        top_words,
+        top_phrases,
+        overused,
+        clusters,
+    }
+}
+
+/// The phrase of `len` words starting at `start`, if it stays in one sentence
+/// and neither starts nor ends with a stopword
+fn phrase_at(tokens: &[Token], start: usize, len: usize) -> Option<String> {
+    let words = tokens.get(start..start + len)?;
+    if words[1..].iter().any(|token| token.starts_sentence) {
+        return None;
+    }
+    if !is_content_word(&words[0].key) || !is_content_word(&words[len - 1].key) {
+        return None;
+    }
+    if words.iter().any(|token| !token.key.chars().any(char::is_alphabetic)) {
+        return None;
+    }
+    Some(words.iter().map(|token| token.key.as_str()).collect::<Vec<_>>().join(" "))
+}
+
+/// The most used terms seen at least `min` times, most used first
+fn top(counts: impl Iterator<Item = (String, usize)>, min: usize) -> Vec<Term> {
+    let mut terms: Vec<Term> = counts
+        .filter(|(_, count)| *count >= min)
+        .map(|(text, count)| Term { text, count })
+        .collect();
+    terms.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.text.cmp(&b.text)));
+    terms.truncate(TOP_TERMS);
+    terms
+}
+
+fn find_clusters(document: usize, tokens: &[Token]) -> Vec<Cluster> {
+    let mut positions: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (i, token) in tokens.iter().enumerate() {
+        if is_content_word(&token.key) {
+            positions.entry(&token.key).or_default().push(i);
+        }
+    }
+
+    let mut clusters = Vec::new();
+    for (word, positions) in positions {
+        let mut run: Vec<usize> = Vec::new();
+        for position in positions.into_iter().chain([usize::MAX]) {
+            let close = run.last().is_some_and(|last| position - last <= CLUSTER_GAP + 1);
+            if !close {
+                if run.len() >= CLUSTER_SIZE {
+                    clusters.push(Cluster {
+                        document,
+                        word: word.to_string(),
+                        occurrences: run.iter().map(|&i| tokens[i].range.clone()).collect(),
+                    });
+                }
+                run.clear();
+            }
+            run.push(position);
+        }
+    }
+    clusters
+}
+
+/// Where a word or phrase is used in markdown, in chars, ignoring case
+pub fn occurrences(markdown: &str, term: &str) -> Vec<Range<usize>> {
+    let words: Vec<String> = term.split_whitespace().map(normalize).collect();
+    if words.is_empty() {
+        return Vec::new();
+    }
+
+    let tokens = tokens(markdown);
+    let mut found = Vec::new();
+    for start in 0..tokens.len() {
+        let candidate = match tokens.get(start..start + words.len()) {
+            Some(candidate) => candidate,
+            None => break,
+        };
+        let matches = candidate.iter().zip(&words).all(|(token, word)| token.key == *word)
+            && !candidate[1..].iter().any(|token| token.starts_sentence);
+        if matches {
+            found.push(candidate[0].range.start..candidate[words.len() - 1].range.end);
+        }
+    }
+    found
+}
@@ -0,0 +1,580 @@
+//! Offline style checks for English prose: passive voice, adverb-heavy
+//! paragraphs, weasel words, clichés, doubled words, long sentences and words
+//! spelled two ways in one document.
+//!
+//! Each check is a [`Rule`]; a [`Linter`] runs a set of them over the prose in
+//! a markdown document, which skips code, HTML and URLs the same way spell
+//! checking does. Rules are heuristics, so the writer can turn any of them off
+//! or dismiss a single finding.
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+use super::spelling::{blocks, Word};
+
+/// Paragraphs with more adverbs than this per hundred words get them flagged
+const ADVERBS_PER_HUNDRED_WORDS: usize = 4;
+
+/// Sentences longer than this many words are flagged
+const LONG_SENTENCE_WORDS: usize = 30;
+
+// ========== Prose ==========
+
+/// The prose of a markdown document, as words grouped into paragraphs and
+/// sentences
+pub struct Prose {
+    chars: Vec<char>,
+    pub paragraphs: Vec<Paragraph>,
+}
+
+/// A paragraph, heading, list item or table cell
+pub struct Paragraph {
+    pub words: Vec<Word>,
+    /// Each sentence, as a range of `words`
+    pub sentences: Vec<Range<usize>>,
+}
+
+impl Prose {
+    pub fn new(markdown: &str) -> Self {
+        let chars: Vec<char> = markdown.chars().collect();
+        let paragraphs = blocks(markdown)
+            .into_iter()
+            .map(|words| {
+                let mut sentences = Vec::new();
+                let mut start = 0;
+                for i in 1..words.len() {
+                    if ends_sentence(&words[i - 1], gap(&chars, &words[i - 1], &words[i])) {
+                        sentences.push(start..i);
+                        start = i;
+                    }
+                }
+                sentences.push(start..words.len());
+                Paragraph { words, sentences }
+            })
+            .collect();
+        Self { chars, paragraphs }
+    }
+
+    /// The document's text over a range of chars
+    pub fn text(&self, range: Range<usize>) -> String {
+        self.chars.get(range).map(|chars| chars.iter().collect()).unwrap_or_default()
+    }
+
+    /// What comes between two words, such as spaces, punctuation or markup
+    pub fn between(&self, first: &Word, second: &Word) -> &[char] {
+        gap(&self.chars, first, second)
+    }
+
+    /// A finding covering `range`, quoting the text there
+    pub fn issue(&self, rule: &'static str, range: Range<usize>, message: String) -> Issue {
+        Issue {
+            rule,
+            text: self.text(range.clone()),
+            range,
+            message,
+        }
+    }
+}
+
+impl Paragraph {
+    pub fn sentences(&self) -> impl Iterator<Item = &[Word]> {
+        self.sentences.iter().map(|range| &self.words[range.clone()])
+    }
+}
+
+fn gap<'a>(chars: &'a [char], first: &Word, second: &Word) -> &'a [char] {
+    chars.get(first.range.end..second.range.start).unwrap_or(&[])
+}
+
+/// Whether a sentence ends after `word`, given what follows it: a full stop,
+/// question or exclamation mark followed by a space, not one after an
+/// abbreviation or initial
+fn ends_sentence(word: &Word, gap: &[char]) -> bool {
+    let mut chars = gap.iter().peekable();
+    while let Some(c) = chars.next() {
+        if !matches!(c, '.' | '!' | '?' | '…') {
+            continue;
+        }
+        while chars.next_if(|c| is_closing(**c)).is_some() {}
+        if chars.peek().is_none_or(|c| c.is_whitespace()) {
+            return !(*c == '.' && is_abbreviation(&word.text));
+        }
+    }
+    false
+}
+
+fn is_closing(c: char) -> bool {
+    matches!(c, '.' | '!' | '?' | '…' | '"' | '\'' | '”' | '’' | ')' | ']' | '*' | '_')
+}
+
+fn is_abbreviation(word: &str) -> bool {
+    const ABBREVIATIONS: &[&str] = &[
+        "mr", "mrs", "ms", "dr", "prof", "st", "jr", "sr", "vs", "etc", "cf", "fig", "no", "vol", "approx",
+    ];
+    word.chars().count() == 1 || ABBREVIATIONS.contains(&lower(word).as_str())
+}
+
+/// Lower case, with typographic apostrophes made straight
+fn lower(word: &str) -> String {
+    word.to_lowercase().replace('\u{2019}', "'")
+}
+
+// ========== Rules ==========
+
+/// Something a rule found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Issue {
+    /// The [`Rule::id`] that found it
+    pub rule: &'static str,
+    /// Where it is, in chars
+    pub range: Range<usize>,
+    /// The text it covers
+    pub text: String,
+    pub message: String,
+}
+
+impl Issue {
+    /// How a composition remembers that the writer dismissed this finding;
+    /// it stays dismissed wherever the same text turns up again
+    pub fn key(&self) -> String {
+        format!("{}:{}", self.rule, lower(&self.text))
+    }
+}
+
+/// One kind of style check
+pub trait Rule {
+    /// Stable identifier, kept in settings and ignore lists
+    fn id(&self) -> &'static str;
+
+    /// Name to show the writer
+    fn name(&self) -> &'static str;
+
+    fn check(&self, prose: &Prose) -> Vec<Issue>;
+}
+
+/// Runs a set of rules over markdown
+pub struct Linter {
+    rules: Vec<Box<dyn Rule>>,
+}
+
+impl Linter {
+    pub fn new(rules: Vec<Box<dyn Rule>>) -> Self {
+        Self { rules }
+    }
+
+    /// Every rule Abbey comes with
+    pub fn builtin() -> Self {
+        Self::new(vec![
+            Box::new(PassiveVoice),
+            Box::new(Adverbs {
+                per_hundred_words: ADVERBS_PER_HUNDRED_WORDS,
+            }),
+            Box::new(WeaselWords),
+            Box::new(Cliches),
+            Box::new(RepeatedWords),
+            Box::new(LongSentences {
+                max_words: LONG_SENTENCE_WORDS,
+            }),
+            Box::new(InconsistentSpellings),
+        ])
+    }
+
+    /// Add a rule of your own
+    pub fn with_rule(mut self, rule: impl Rule + 'static) -> Self {
+        self.rules.push(Box::new(rule));
+        self
+    }
+
+    /// Drop the rules the writer turned off
+    pub fn without(mut self, disabled: &[String]) -> Self {
+        self.rules.retain(|rule| !disabled.iter().any(|id| id == rule.id()));
+        self
+    }
+
+    pub fn rules(&self) -> impl Iterator<Item = &dyn Rule> {
+        self.rules.iter().map(|rule| rule.as_ref())
+    }
+
+    /// Every finding in `markdown`, in document order, apart from those whose
+    /// [`Issue::key`] is in `ignored`
+    pub fn check(&self, markdown: &str, ignored: &[String]) -> Vec<Issue> {
+        let prose = Prose::new(markdown);
+        let mut issues: Vec<Issue> = self
+            .rules
+            .iter()
+            .flat_map(|rule| rule.check(&prose))
+            .filter(|issue| !ignored.contains(&issue.key()))
+            .collect();
+        issues.sort_by_key(|issue| (issue.range.start, issue.range.end));
+        issues
+    }
+}
+
+/// A form of "to be" followed by a past participle, as in "was written"
+pub struct PassiveVoice;
+
+impl Rule for PassiveVoice {
+    fn id(&self) -> &'static str {
+        "passive-voice"
+    }
+
+    fn name(&self) -> &'static str {
+        "Passive voice"
+    }
+
+    fn check(&self, prose: &Prose) -> Vec<Issue> {
+        const TO_BE: &[&str] = &["am", "is", "are", "was", "were", "be", "been", "being", "isn't", "aren't", "wasn't", "weren't"];
+
+        let mut issues = Vec::new();
+        for sentence in prose.paragraphs.iter().flat_map(Paragraph::sentences) {
+            for (i, word) in sentence.iter().enumerate() {
+                if !TO_BE.contains(&lower(&word.text).as_str()) {
+                    continue;
+                }
+                // "was quickly written" as well as "was written"
+                let mut next = i + 1;
+                if sentence.get(next).is_some_and(|w| is_adverb(&w.text)) {
+                    next += 1;
+                }
+                if let Some(participle) = sentence.get(next).filter(|w| is_participle(&w.text)) {
+                    let range = word.range.start..participle.range.end;
+                    let text = prose.text(range.clone());
+                    issues.push(prose.issue(self.id(), range, format!("“{}” is passive; who does it?", text)));
+                }
+            }
+        }
+        issues
+    }
+}
+
+fn is_participle(word: &str) -> bool {
+    const IRREGULAR: &[&str] = &[
+        "awoken", "been", "begun", "bent", "bitten", "blown", "born", "borne", "bought", "bound", "broken",
+        "brought", "built", "burnt", "caught", "chosen", "come", "dealt", "done", "drawn", "driven", "drunk",
+        "eaten", "fallen", "felt", "fought", "found", "forbidden", "forgiven", "forgotten", "frozen", "given",
+        "gone", "grown", "heard", "held", "hidden", "hit", "hung", "hurt", "kept", "known", "laid", "led",
+        "left", "lent", "lost", "made", "meant", "met", "paid", "put", "read", "ridden", "rung", "said",
+        "seen", "sent", "set", "shaken", "shot", "shown", "shut", "sold", "sought", "spent", "spoken",
+        "spun", "stolen", "struck", "sung", "sunk", "swept", "sworn", "taken", "taught", "thought", "thrown",
+        "told", "torn", "understood", "upset", "woken", "won", "worn", "written",
+    ];
+    const NOT_PARTICIPLES: &[&str] = &["bed", "bred", "embed", "exceed", "feed", "indeed", "need", "proceed", "red", "seed", "shed", "sped", "speed", "succeed", "wed"];
+
+    let word = lower(word);
+    IRREGULAR.contains(&word.as_str())
+        || (word.ends_with("ed") && word.chars().count() > 3 && !NOT_PARTICIPLES.contains(&word.as_str()))
+}
+
+/// Paragraphs leaning on "-ly" adverbs
+pub struct Adverbs {
+    pub per_hundred_words: usize,
+}
+
+impl Rule for Adverbs {
+    fn id(&self) -> &'static str {
+        "adverbs"
+    }
+
+    fn name(&self) -> &'static str {
+        "Adverbs"
+    }
+
+    fn check(&self, prose: &Prose) -> Vec<Issue> {
+        let mut issues = Vec::new();
+        for paragraph in &prose.paragraphs {
+            let adverbs: Vec<&Word> = paragraph.words.iter().filter(|w| is_adverb(&w.text)).collect();
+            if adverbs.len() * 100 <= self.per_hundred_words * paragraph.words.len() {
+                continue;
+            }
+            let message = format!(
+                "{} adverbs in {} words; a stronger verb may do without",
+                adverbs.len(),
+                paragraph.words.len()
+            );
+            for adverb in adverbs {
+                issues.push(prose.issue(self.id(), adverb.range.clone(), message.clone()));
+            }
+        }
+        issues
+    }
+}
+
+fn is_adverb(word: &str) -> bool {
+    const NOT_ADVERBS: &[&str] = &[
+        "ally", "apply", "belly", "bully", "butterfly", "comply", "curly", "dragonfly", "early", "elderly",
+        "family", "friendly", "holy", "homily", "imply", "italy", "jelly", "july", "lily", "lonely", "lovely",
+        "melancholy", "monopoly", "only", "rally", "rely", "reply", "silly", "supply", "ugly", "underbelly",
+        "unlikely", "likely", "ghostly", "costly", "lively", "deadly", "kindly", "orderly", "timely", "chilly",
+        "hilly", "jolly", "assembly", "anomaly", "wily", "oily", "sly",
+    ];
+    let word = lower(word);
+    word.ends_with("ly") && word.chars().count() > 4 && !NOT_ADVERBS.contains(&word.as_str())
+}
+
+/// Vague qualifiers and unattributed claims
+pub struct WeaselWords;
+
+impl Rule for WeaselWords {
+    fn id(&self) -> &'static str {
+        "weasel-words"
+    }
+
+    fn name(&self) -> &'static str {
+        "Weasel words"
+    }
+
+    fn check(&self, prose: &Prose) -> Vec<Issue> {
+        const WEASELS: &[&str] = &[
+            "arguably", "basically", "clearly", "completely", "exceedingly", "extremely", "fairly", "huge",
+            "interestingly", "largely", "literally", "mostly", "quite", "really", "relatively", "remarkably",
+            "significantly", "somewhat", "substantially", "surprisingly", "tiny", "various", "vast", "very",
+            "virtually", "experts agree", "it is believed", "it is said", "many people", "research shows",
+            "some say", "some people", "studies show", "it has been said",
+        ];
+        find_phrases(prose, self.id(), WEASELS, |text| format!("“{}” is vague; say how much, or who", text))
+    }
+}
+
+/// Worn-out phrases
+pub struct Cliches;
+
+impl Rule for Cliches {
+    fn id(&self) -> &'static str {
+        "cliches"
+    }
+
+    fn name(&self) -> &'static str {
+        "Clichés"
+    }
+
+    fn check(&self, prose: &Prose) -> Vec<Issue> {
+        const CLICHES: &[&str] = &[
+            "a blessing in disguise", "a piece of cake", "against all odds", "all walks of life",
+            "at the end of the day", "at this point in time", "avoid like the plague", "better late than never",
+            "beyond a shadow of a doubt", "bite the bullet", "calm before the storm", "crystal clear",
+            "dead as a doornail", "easier said than done", "every cloud has a silver lining", "few and far between",
+            "fit as a fiddle", "hit the ground running", "in the nick of time", "in this day and age",
+            "it goes without saying", "last but not least", "low-hanging fruit", "low hanging fruit",
+            "needle in a haystack", "only time will tell", "par for the course", "read between the lines",
+            "the bottom line", "think outside the box", "tip of the iceberg", "when all is said and done",
+            "without further ado", "writing on the wall",
+        ];
+        find_phrases(prose, self.id(), CLICHES, |text| format!("“{}” is a cliché", text))
+    }
+}
+
+/// Find any of `phrases` within a sentence, ignoring case and punctuation
+/// between words
+fn find_phrases(prose: &Prose, rule: &'static str, phrases: &[&str], message: impl Fn(&str) -> String) -> Vec<Issue> {
+    // Hyphens split words, so "low-hanging" matches as two
+    let phrases: Vec<Vec<String>> = phrases
+        .iter()
+        .map(|phrase| phrase.split([' ', '-']).map(str::to_string).collect())
+        .collect();
+
+    let mut issues = Vec::new();
+    for sentence in prose.paragraphs.iter().flat_map(Paragraph::sentences) {
+        let words: Vec<String> = sentence.iter().map(|w| lower(&w.text)).collect();
+        let mut i = 0;
+        while i < words.len() {
+            let found = phrases
+                .iter()
+                .filter(|phrase| words[i..].starts_with(phrase))
+                .map(Vec::len)
+                .max();
+            match found {
+                Some(len) => {
+                    let range = sentence[i].range.start..sentence[i + len - 1].range.end;
+                    let message = message(&prose.text(range.clone()));
+                    issues.push(prose.issue(rule, range, message));
+                    i += len;
+                }
+                None => i += 1,
+            }
+        }
+    }
+    issues
+}
+
+/// The same word twice in a row, as in "the the"
+pub struct RepeatedWords;
+
+impl Rule for RepeatedWords {
+    fn id(&self) -> &'static str {
+        "repeated-words"
+    }
+
+    fn name(&self) -> &'static str {
+        "Repeated words"
+    }
+
+    fn check(&self, prose: &Prose) -> Vec<Issue> {
+        // Doubled on purpose often enough to leave alone
+        const ALLOWED: &[&str] = &["had", "that", "is", "bye", "no", "yes", "very", "so"];
+
+        let mut issues = Vec::new();
+        for paragraph in &prose.paragraphs {
+            for pair in paragraph.words.windows(2) {
+                let word = lower(&pair[0].text);
+                if word != lower(&pair[1].text) || ALLOWED.contains(&word.as_str()) {
+                    continue;
+                }
+                // "Well, well" has punctuation between, so is meant
+                if !prose.between(&pair[0], &pair[1]).iter().all(|c| c.is_whitespace()) {
+                    continue;
+                }
+                let range = pair[0].range.start..pair[1].range.end;
+                issues.push(prose.issue(self.id(), range, format!("“{}” twice in a row", pair[0].text)));
+            }
+        }
+        issues
+    }
+}
+
+/// Sentences too long to follow easily
+pub struct LongSentences {
+    pub max_words: usize,
+}
+
+impl Rule for LongSentences {
+    fn id(&self) -> &'static str {
+        "long-sentences"
+    }
+
+    fn name(&self) -> &'static str {
+        "Long sentences"
+    }
+
+    fn check(&self, prose: &Prose) -> Vec<Issue> {
+        prose
+            .paragraphs
+            .iter()
+            .flat_map(Paragraph::sentences)
+            .filter(|sentence| sentence.len() > self.max_words)
+            .map(|sentence| {
+                let range = sentence[0].range.start..sentence[sentence.len() - 1].range.end;
+                prose.issue(self.id(), range, format!("{} words in one sentence; try splitting it", sentence.len()))
+            })
+            .collect()
+    }
+}
+
+/// A word spelled more than one way, such as "colour" and "color" or "e-mail"
+/// and "email"; the less common spellings are flagged
+pub struct InconsistentSpellings;
+
+impl Rule for InconsistentSpellings {
+    fn id(&self) -> &'static str {
+        "inconsistent-spellings"
+    }
+
+    fn name(&self) -> &'static str {
+        "Inconsistent spellings"
+    }
+
+    fn check(&self, prose: &Prose) -> Vec<Issue> {
+        // Each word, with hyphenated words joined back up
+        let mut tokens: Vec<(Range<usize>, String)> = Vec::new();
+        for paragraph in &prose.paragraphs {
+            let mut previous: Option<&Word> = None;
+            for word in &paragraph.words {
+                let joined = previous.is_some_and(|p| prose.between(p, word) == ['-']);
+                match tokens.last_mut() {
+                    Some((range, text)) if joined => {
+                        range.end = word.range.end;
+                        text.push('-');
+                        text.push_str(&lower(&word.text));
+                    }
+                    _ => tokens.push((word.range.clone(), lower(&word.text))),
+                }
+                previous = Some(word);
+            }
+        }
+
+        // How often each spelling of each word turns up, and where it first does
+        let mut spellings: HashMap<String, Vec<(String, usize, usize)>> = HashMap::new();
+        for (index, (_, text)) in tokens.iter().enumerate() {
+            let variants = spellings.entry(spelling_key(text)).or_default();
+            match variants.iter_mut().find(|(spelling, _, _)| spelling == text) {
+                Some((_, count, _)) => *count += 1,
+                None => variants.push((text.clone(), 1, index)),
+            }
+        }
+
+        let mut issues = Vec::new();
+        for (range, text) in &tokens {
+            let variants = &spellings[&spelling_key(text)];
+            if variants.len() < 2 {
+                continue;
+            }
+            // The most used spelling wins, or the first one used on a tie
+            let preferred = variants
+                .iter()
+                .max_by_key(|(_, count, first)| (*count, std::cmp::Reverse(*first)))
+                .expect("at least two spellings");
+            if &preferred.0 == text {
+                continue;
+            }
+            let times = if preferred.1 == 1 { "once".to_string() } else { format!("{} times", preferred.1) };
+            let message = format!("Spelled “{}” {} elsewhere", preferred.0, times);
+            issues.push(prose.issue(self.id(), range.clone(), message));
+        }
+        issues
+    }
+}
+
+/// What spellings of the same word have in common: no hyphens, and American
+/// endings in place of British ones
+fn spelling_key(word: &str) -> String {
+    // British ending, American ending, and the shortest word to try it on
+    const ENDINGS: &[(&str, &str, usize)] = &[
+        ("isations", "izations", 9),
+        ("isation", "ization", 8),
+        ("ising", "izing", 6),
+        ("ysing", "yzing", 6),
+        ("elling", "eling", 7),
+        ("ouring", "oring", 7),
+        ("ised", "ized", 6),
+        ("ises", "izes", 6),
+        ("ysed", "yzed", 6),
+        ("elled", "eled", 6),
+        ("eller", "eler", 6),
+        ("oured", "ored", 7),
+        ("ogues", "ogs", 7),
+        ("ogue", "og", 6),
+        ("ours", "ors", 7),
+        ("tres", "ters", 6),
+        ("ise", "ize", 6),
+        ("yse", "yze", 6),
+        ("our", "or", 6),
+        ("tre", "ter", 5),
+        ("bre", "ber", 5),
+    ];
+    const WORDS: &[(&str, &str)] = &[
+        ("grey", "gray"),
+        ("aluminium", "aluminum"),
+        ("defence", "defense"),
+        ("offence", "offense"),
+        ("licence", "license"),
+        ("pretence", "pretense"),
+        ("programme", "program"),
+        ("jewellery", "jewelry"),
+        ("plough", "plow"),
+        ("mould", "mold"),
+        ("towards", "toward"),
+    ];
+
+    let word: String = word.chars().filter(|c| *c != '-').collect();
+    if let Some((_, american)) = WORDS.iter().find(|(british, _)| *british == word) {
+        return american.to_string();
+    }
+    let length = word.chars().count();
+    for (british, american, shortest) in ENDINGS {
+        if length >= *shortest {
+            if let Some(stem) = word.strip_suffix(british) {
+                return format!("{}{}", stem, american);
+            }
+        }
+    }
+    word
+}
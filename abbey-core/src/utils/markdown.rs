@@ -0,0 +1,93 @@
+/// The markdown extensions Abbey understands, shared by the HTML export and the
+/// rendered previews so they always agree
+pub fn parser_options() -> pulldown_cmark::Options {
+    use pulldown_cmark::Options;
+    Options::ENABLE_TABLES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_FOOTNOTES
+}
+
+/// [`parser_options`] with `{#id}` attributes on headings, for documents Abbey
+/// writes heading anchors into, like a project's. Only for those, as anywhere
+/// else it would swallow braces the writer ended a heading with.
+pub fn anchored_parser_options() -> pulldown_cmark::Options {
+    parser_options() | pulldown_cmark::Options::ENABLE_HEADING_ATTRIBUTES
+}
+
+/// Converts byte offsets to char offsets, counting on from the last one asked
+/// for since they mostly come in order
+pub(crate) struct CharOffsets<'a> {
+    text: &'a str,
+    byte: usize,
+    char: usize,
+}
+
+impl<'a> CharOffsets<'a> {
+    pub(crate) fn new(text: &'a str) -> Self {
+        Self { text, byte: 0, char: 0 }
+    }
+
+    pub(crate) fn at(&mut self, byte: usize) -> usize {
+        if byte < self.byte {
+            self.byte = 0;
+            self.char = 0;
+        }
+        self.char += self.text.get(self.byte..byte).map_or(0, |text| text.chars().count());
+        self.byte = byte;
+        self.char
+    }
+}
+
+/// Convert markdown to plain text (for word counting, etc.)
+pub fn markdown_to_plain_text(markdown: &str) -> String {
+    super::text_stats::plain_text(markdown)
+}
+
+/// Count words in markdown content
+pub fn word_count(content: &str) -> usize {
+    super::text_stats::word_count(content)
+}
+
+/// Estimate reading time in minutes
+pub fn reading_time_minutes(content: &str) -> u32 {
+    super::text_stats::TextStats::of(content).reading_minutes().max(1)
+}
+
+/// Generate an excerpt from markdown content
+pub fn excerpt(content: &str, max_chars: usize) -> String {
+    let plain = markdown_to_plain_text(content);
+    
+    let cut = match plain.char_indices().nth(max_chars) {
+        Some((cut, _)) => cut,
+        None => return plain,
+    };
+    
+    // Find word boundary
+    let truncated = &plain[..cut];
+    if let Some(last_space) = truncated.rfind(' ') {
+        format!("{}...", &truncated[..last_space])
+    } else {
+        format!("{}...", truncated)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_word_count() {
+        assert_eq!(word_count("Hello world"), 2);
+        assert_eq!(word_count("# Header\n\nSome **bold** text"), 4);
+        assert_eq!(markdown_to_plain_text("snake_case and __strong__"), "snake_case and strong");
+    }
+
+    #[test]
+    fn test_excerpt() {
+        let content = "This is a longer piece of text that should be truncated.";
+        let result = excerpt(content, 20);
+        assert!(result.ends_with("..."));
+        assert!(result.len() <= 23); // 20 + "..."
+    }
+}
@@ -0,0 +1,15 @@
+pub mod anchors;
+pub mod diff;
+pub mod editing;
+pub mod frequency;
+pub mod lint;
+pub mod markdown;
+pub mod outline;
+pub mod render;
+pub mod spelling;
+pub mod text_stats;
+pub mod typography;
+
+// Markdown utilities available for future use
+#[allow(unused_imports)]
+pub use markdown::*;
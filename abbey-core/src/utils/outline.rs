@@ -0,0 +1,163 @@
+//! The outline of a markdown document: its headings, each heading the start
+//! of a section running to the next heading at the same level or above.
+//!
+//! Only headings at the top level of the document count, so a heading quoted
+//! in a blockquote or written in a list item stays part of the section it's in.
+//! Moving a section ([`move_section`]) takes its subsections with it.
+
+use pulldown_cmark::{Event, HeadingLevel, Parser, Tag, TagEnd};
+use std::ops::Range;
+
+use super::markdown::{parser_options, CharOffsets};
+use super::text_stats;
+
+/// A heading and the text under it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+    /// 1 for `#`, up to 6
+    pub level: u8,
+    pub title: String,
+    /// The heading's own lines, in chars
+    pub heading: Range<usize>,
+    /// From the heading to where the next section at this level or above
+    /// starts, in chars
+    pub range: Range<usize>,
+    /// Words in the section, its subsections and heading included
+    pub words: usize,
+}
+
+/// Where a moved section ended up
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Moved {
+    pub text: String,
+    /// The moved section's first char in `text`
+    pub start: usize,
+}
+
+/// Every section, in document order
+pub fn outline(markdown: &str) -> Vec<Section> {
+    let headings = headings(markdown);
+    let mut offsets = CharOffsets::new(markdown);
+    let mut sections = Vec::with_capacity(headings.len());
+    for (i, heading) in headings.iter().enumerate() {
+        let range = heading.range.start..section_end(&headings, i, markdown.len());
+        sections.push(Section {
+            level: heading.level,
+            title: heading.title.clone(),
+            heading: offsets.at(heading.range.start)..offsets.at(heading.range.end),
+            range: offsets.at(range.start)..offsets.at(range.end),
+            words: text_stats::word_count(&markdown[range]),
+        });
+    }
+    sections
+}
+
+/// Move section `index`, and its subsections, to just before section
+/// `before`, or to the end of the document if `before` is `None`
+///
+/// Returns `None` if the section wouldn't move, or would move into itself.
+pub fn move_section(markdown: &str, index: usize, before: Option<usize>) -> Option<Moved> {
+    let headings = headings(markdown);
+    let section = headings.get(index)?.range.start..section_end(&headings, index, markdown.len());
+    let target = match before {
+        Some(before) => headings.get(before)?.range.start,
+        None => markdown.len(),
+    };
+    if (section.start..=section.end).contains(&target) {
+        return None;
+    }
+
+    let piece = format!("{}\n\n", markdown[section.clone()].trim_end_matches('\n'));
+    let mut rest = String::with_capacity(markdown.len() + 2);
+    rest.push_str(&markdown[..section.start]);
+    rest.push_str(&markdown[section.end..]);
+    let at = if target > section.end { target - section.len() } else { target };
+
+    let (mut text, start) = if before.is_some() {
+        let text = format!("{}{}{}", &rest[..at], piece, &rest[at..]);
+        (text, at)
+    } else {
+        let kept = rest.trim_end_matches('\n');
+        let gap = if kept.is_empty() { "" } else { "\n\n" };
+        (format!("{}{}{}", kept, gap, piece), kept.len() + gap.len())
+    };
+
+    // Keep the document's ending as it was, whichever section now ends it
+    let trimmed = text.trim_end_matches('\n').len();
+    text.truncate(trimmed);
+    if markdown.ends_with('\n') {
+        text.push('\n');
+    }
+    let start = text[..start].chars().count();
+    Some(Moved { text, start })
+}
+
+/// A top-level heading, in bytes
+struct Heading {
+    level: u8,
+    title: String,
+    range: Range<usize>,
+}
+
+fn headings(markdown: &str) -> Vec<Heading> {
+    let mut headings = Vec::new();
+    let mut current: Option<Heading> = None;
+    // Blockquotes, lists and footnotes the parser is inside
+    let mut containers = 0usize;
+
+    for (event, range) in Parser::new_ext(markdown, parser_options()).into_offset_iter() {
+        match event {
+            Event::Start(Tag::BlockQuote(_) | Tag::List(_) | Tag::Item | Tag::FootnoteDefinition(_)) => {
+                containers += 1;
+            }
+            Event::End(TagEnd::BlockQuote(_) | TagEnd::List(_) | TagEnd::Item | TagEnd::FootnoteDefinition) => {
+                containers = containers.saturating_sub(1);
+            }
+            Event::Start(Tag::Heading { level, .. }) if containers == 0 => {
+                current = Some(Heading {
+                    level: heading_level(level),
+                    title: String::new(),
+                    range,
+                });
+            }
+            Event::Text(text) | Event::Code(text) => {
+                if let Some(ref mut heading) = current {
+                    heading.title.push_str(&text);
+                }
+            }
+            Event::SoftBreak | Event::HardBreak => {
+                if let Some(ref mut heading) = current {
+                    heading.title.push(' ');
+                }
+            }
+            Event::End(TagEnd::Heading(_)) => {
+                if let Some(mut heading) = current.take() {
+                    heading.title = heading.title.trim().to_string();
+                    headings.push(heading);
+                }
+            }
+            _ => {}
+        }
+    }
+    headings
+}
+
+/// Where section `index` ends: at the next heading at its level or above
+fn section_end(headings: &[Heading], index: usize, len: usize) -> usize {
+    let level = headings[index].level;
+    headings[index + 1..]
+        .iter()
+        .find(|next| next.level <= level)
+        .map_or(len, |next| next.range.start)
+}
+
+fn heading_level(level: HeadingLevel) -> u8 {
+    match level {
+        HeadingLevel::H1 => 1,
+        HeadingLevel::H2 => 2,
+        HeadingLevel::H3 => 3,
+        HeadingLevel::H4 => 4,
+        HeadingLevel::H5 => 5,
+        HeadingLevel::H6 => 6,
+    }
+}
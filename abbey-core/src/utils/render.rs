@@ -0,0 +1,525 @@
+//! Markdown laid out as plain text with styled spans, so a text widget can show
+//! it rendered. It parses with the HTML export's options, so a preview shows
+//! what an export will.
+//!
+//! The output is split into top-level blocks that remember where in the
+//! markdown they came from. A view can re-render after an edit and only redraw
+//! the blocks that changed, and map positions between source and preview.
+
+use pulldown_cmark::{Alignment, Event, Parser, Tag, TagEnd};
+use std::ops::Range;
+
+use super::markdown::parser_options;
+
+/// How a stretch of rendered text looks
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Style {
+    /// A heading of level 1 to 6
+    Heading(u8),
+    Strong,
+    Emphasis,
+    Strikethrough,
+    Code,
+    CodeBlock,
+    Quote,
+    /// Link text, with where it points
+    Link(String),
+    /// An image's alt text, with its source
+    Image(String),
+    /// A bullet, number or task box
+    ListMarker,
+    /// Lines nested this many lists and quotes deep
+    Indent(u8),
+    Rule,
+    Table,
+    TableHeader,
+    FootnoteReference,
+}
+
+/// A styled range of a block's text, in chars
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub style: Style,
+}
+
+/// One top-level block of the document, rendered
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Block {
+    /// Byte range of the markdown it was rendered from
+    pub source: Range<usize>,
+    /// The text to show, ending in a blank line that separates it from the next
+    pub text: String,
+    pub spans: Vec<Span>,
+}
+
+impl Block {
+    pub fn char_len(&self) -> usize {
+        self.text.chars().count()
+    }
+
+    /// Whether both look the same, wherever in the markdown they came from
+    pub fn same_output(&self, other: &Block) -> bool {
+        self.text == other.text && self.spans == other.spans
+    }
+}
+
+/// Which blocks changed between two renders: `old[start..old_end]` became
+/// `new[start..new_end]`, and everything around them looks the same
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Splice {
+    pub start: usize,
+    pub old_end: usize,
+    pub new_end: usize,
+}
+
+/// Compare two renders of a document, for redrawing only what changed
+pub fn diff(old: &[Block], new: &[Block]) -> Splice {
+    let start = old.iter().zip(new).take_while(|(a, b)| a.same_output(b)).count();
+    let suffix = old[start..]
+        .iter()
+        .rev()
+        .zip(new[start..].iter().rev())
+        .take_while(|(a, b)| a.same_output(b))
+        .count();
+
+    Splice {
+        start,
+        old_end: old.len() - suffix,
+        new_end: new.len() - suffix,
+    }
+}
+
+/// Render markdown into blocks of styled text
+pub fn render(markdown: &str) -> Vec<Block> {
+    let mut renderer = Renderer::default();
+    for (event, range) in Parser::new_ext(markdown, parser_options()).into_offset_iter() {
+        renderer.event(event, range);
+    }
+    renderer.blocks
+}
+
+/// Where a char offset in the markdown shows in the rendered text. Inside a
+/// block the offset is scaled to the block's rendered length, which keeps long
+/// paragraphs roughly level; between blocks it lands on the next one.
+pub fn preview_position(markdown: &str, blocks: &[Block], source: usize) -> usize {
+    let byte = markdown.char_indices().nth(source).map_or(markdown.len(), |(i, _)| i);
+
+    let mut start = 0;
+    for block in blocks {
+        if byte < block.source.start {
+            return start;
+        }
+        if byte < block.source.end {
+            let done = chars_in(markdown, block.source.start..byte);
+            let total = chars_in(markdown, block.source.clone());
+            return start + scale(done, total, shown_len(block));
+        }
+        start += block.char_len();
+    }
+    start
+}
+
+/// Where a char offset in the rendered text came from in the markdown; the
+/// inverse of `preview_position`
+pub fn source_position(markdown: &str, blocks: &[Block], preview: usize) -> usize {
+    let mut start = 0;
+    for block in blocks {
+        let len = block.char_len();
+        if preview < start + len {
+            let shown = shown_len(block);
+            let block_start = chars_in(markdown, 0..block.source.start);
+            let total = chars_in(markdown, block.source.clone());
+            return block_start + scale((preview - start).min(shown), shown, total);
+        }
+        start += len;
+    }
+    markdown.chars().count()
+}
+
+/// A block's length without the blank line after it
+fn shown_len(block: &Block) -> usize {
+    block.text.trim_end_matches('\n').chars().count()
+}
+
+/// Chars in a byte range of the markdown; blocks from an older render may no
+/// longer fit it, and count as empty
+fn chars_in(markdown: &str, range: Range<usize>) -> usize {
+    markdown.get(range).map_or(0, |text| text.chars().count())
+}
+
+fn scale(n: usize, from: usize, to: usize) -> usize {
+    (n * to).checked_div(from).unwrap_or(0)
+}
+
+/// Bullets for each level of bullet list nesting, repeating when lists nest deeper
+const BULLETS: [&str; 3] = ["•", "◦", "▪"];
+
+/// A table being collected, to lay out once every cell's width is known
+struct Table {
+    start: usize,
+    alignments: Vec<Alignment>,
+    /// Byte ranges of each row's cells in the output
+    rows: Vec<Vec<Range<usize>>>,
+    header_rows: usize,
+    cell_start: usize,
+}
+
+#[derive(Default)]
+struct Renderer {
+    blocks: Vec<Block>,
+    /// Where the top-level block being rendered starts in the markdown
+    source_start: usize,
+    /// Nesting of block-level tags; 0 between top-level blocks
+    depth: usize,
+    /// The current block's text, and its finished spans in bytes
+    out: String,
+    spans: Vec<(Range<usize>, Style)>,
+    /// Spans still open, by where they started
+    open: Vec<(usize, Style)>,
+    /// Where each line starts and how deeply it's nested
+    lines: Vec<(usize, u8)>,
+    /// Next number of each open list, `None` for bullets
+    lists: Vec<Option<u64>>,
+    quotes: usize,
+    /// Just wrote a list marker, so the item's first paragraph stays on its line
+    after_marker: bool,
+    table: Option<Table>,
+}
+
+impl Renderer {
+    fn event(&mut self, event: Event, range: Range<usize>) {
+        match event {
+            Event::Start(tag) => {
+                if is_block(&tag) {
+                    if self.depth == 0 {
+                        self.source_start = range.start;
+                    }
+                    self.depth += 1;
+                }
+                self.start(tag);
+            }
+            Event::End(tag) => {
+                self.end(tag);
+                if !is_inline(&tag) {
+                    self.depth = self.depth.saturating_sub(1);
+                    if self.depth == 0 {
+                        self.finish_block(range.end);
+                    }
+                }
+            }
+            Event::Text(text) => self.push(&text),
+            Event::Code(code) => self.push_styled(&code, Style::Code),
+            Event::SoftBreak => self.push(" "),
+            Event::HardBreak => self.push("\n"),
+            Event::Rule => {
+                if self.depth == 0 {
+                    self.source_start = range.start;
+                }
+                self.newline();
+                self.push_styled("* * *", Style::Rule);
+                if self.depth == 0 {
+                    self.finish_block(range.end);
+                }
+            }
+            Event::TaskListMarker(checked) => {
+                self.push_styled(if checked { "☑ " } else { "☐ " }, Style::ListMarker);
+                self.after_marker = true;
+            }
+            Event::FootnoteReference(label) => self.push_styled(&label, Style::FootnoteReference),
+            // Raw HTML shows as whatever it renders to, which a text view can't
+            _ => {}
+        }
+    }
+
+    fn start(&mut self, tag: Tag) {
+        match tag {
+            Tag::Paragraph => self.paragraph_break(),
+            Tag::Heading { level, .. } => {
+                self.paragraph_break();
+                self.open(Style::Heading(level as u8));
+            }
+            Tag::BlockQuote(_) => {
+                self.paragraph_break();
+                self.quotes += 1;
+                self.open(Style::Quote);
+            }
+            Tag::CodeBlock(_) => {
+                self.paragraph_break();
+                self.open(Style::CodeBlock);
+            }
+            Tag::List(first) => {
+                self.newline();
+                self.lists.push(first);
+            }
+            Tag::Item => {
+                self.newline();
+                let marker = match self.lists.last_mut() {
+                    Some(Some(number)) => {
+                        *number += 1;
+                        format!("{}. ", *number - 1)
+                    }
+                    _ => {
+                        let nesting = self.lists.iter().filter(|list| list.is_none()).count();
+                        format!("{} ", BULLETS[nesting.saturating_sub(1) % BULLETS.len()])
+                    }
+                };
+                self.push_styled(&marker, Style::ListMarker);
+                self.after_marker = true;
+            }
+            Tag::FootnoteDefinition(label) => {
+                self.paragraph_break();
+                self.push_styled(&label, Style::FootnoteReference);
+                self.push(" ");
+                self.after_marker = true;
+            }
+            Tag::Table(alignments) => {
+                self.paragraph_break();
+                self.table = Some(Table {
+                    start: self.out.len(),
+                    alignments,
+                    rows: Vec::new(),
+                    header_rows: 0,
+                    cell_start: 0,
+                });
+            }
+            Tag::TableHead | Tag::TableRow => {
+                if let Some(table) = self.table.as_mut() {
+                    table.rows.push(Vec::new());
+                }
+            }
+            Tag::TableCell => {
+                if let Some(table) = self.table.as_mut() {
+                    table.cell_start = self.out.len();
+                }
+            }
+            Tag::Emphasis => self.open(Style::Emphasis),
+            Tag::Strong => self.open(Style::Strong),
+            Tag::Strikethrough => self.open(Style::Strikethrough),
+            Tag::Link { dest_url, .. } => self.open(Style::Link(dest_url.to_string())),
+            Tag::Image { dest_url, .. } => {
+                self.open(Style::Image(dest_url.to_string()));
+                self.push("🖼 ");
+            }
+            _ => {}
+        }
+    }
+
+    fn end(&mut self, tag: TagEnd) {
+        match tag {
+            TagEnd::Heading(_) | TagEnd::CodeBlock | TagEnd::Emphasis | TagEnd::Strong
+            | TagEnd::Strikethrough | TagEnd::Link => self.close(),
+            TagEnd::BlockQuote(_) => {
+                self.close();
+                self.quotes = self.quotes.saturating_sub(1);
+            }
+            TagEnd::List(_) => {
+                self.lists.pop();
+            }
+            TagEnd::Image => {
+                // Without alt text, say which image it is
+                if self.out.ends_with("🖼 ") {
+                    if let Some((_, Style::Image(source))) = self.open.last() {
+                        let source = source.clone();
+                        self.push(&source);
+                    }
+                }
+                self.close();
+            }
+            TagEnd::TableHead => {
+                if let Some(table) = self.table.as_mut() {
+                    table.header_rows = table.rows.len();
+                }
+            }
+            TagEnd::TableCell => {
+                let end = self.out.len();
+                if let Some(table) = self.table.as_mut() {
+                    let start = table.cell_start;
+                    if let Some(row) = table.rows.last_mut() {
+                        row.push(start..end);
+                    }
+                }
+            }
+            TagEnd::Table => self.lay_out_table(),
+            _ => {}
+        }
+    }
+
+    /// Start a new line, unless already at the start of one
+    fn newline(&mut self) {
+        if !self.out.is_empty() && !self.out.ends_with('\n') {
+            self.push("\n");
+        }
+    }
+
+    /// Leave a blank line before a paragraph-like block, except the first in a
+    /// block or list item
+    fn paragraph_break(&mut self) {
+        if self.after_marker {
+            self.after_marker = false;
+            return;
+        }
+        self.newline();
+        if !self.out.is_empty() && !self.out.ends_with("\n\n") {
+            self.push("\n");
+        }
+    }
+
+    fn push(&mut self, text: &str) {
+        let level = (self.lists.len() + self.quotes).min(u8::MAX as usize) as u8;
+        for piece in text.split_inclusive('\n') {
+            if self.out.is_empty() || self.out.ends_with('\n') {
+                self.lines.push((self.out.len(), level));
+            }
+            self.out.push_str(piece);
+        }
+        self.after_marker = false;
+    }
+
+    fn push_styled(&mut self, text: &str, style: Style) {
+        let start = self.out.len();
+        self.push(text);
+        self.spans.push((start..self.out.len(), style));
+    }
+
+    fn open(&mut self, style: Style) {
+        self.open.push((self.out.len(), style));
+    }
+
+    fn close(&mut self) {
+        if let Some((start, style)) = self.open.pop() {
+            if start < self.out.len() {
+                self.spans.push((start..self.out.len(), style));
+            }
+        }
+    }
+
+    /// Replace the collected cells with rows padded into columns
+    fn lay_out_table(&mut self) {
+        let table = match self.table.take() {
+            Some(table) => table,
+            None => return,
+        };
+
+        let columns = table.rows.iter().map(Vec::len).max().unwrap_or(0);
+        let mut widths = vec![0; columns];
+        for row in &table.rows {
+            for (column, cell) in row.iter().enumerate() {
+                widths[column] = widths[column].max(self.out[cell.clone()].chars().count());
+            }
+        }
+
+        // Take the cells and their spans out, then write them back in place
+        let cells: Vec<Vec<String>> = table
+            .rows
+            .iter()
+            .map(|row| row.iter().map(|cell| self.out[cell.clone()].to_string()).collect())
+            .collect();
+        let (cell_spans, kept): (Vec<_>, Vec<_>) =
+            self.spans.drain(..).partition(|(range, _)| range.start >= table.start);
+        self.spans = kept;
+        self.lines.retain(|(start, _)| *start < table.start);
+        self.out.truncate(table.start);
+
+        let mut header_end = table.start;
+        for (index, row) in cells.iter().enumerate() {
+            self.newline();
+            for (column, width) in widths.iter().enumerate() {
+                if column > 0 {
+                    self.push(" │ ");
+                }
+                let text = row.get(column).map(String::as_str).unwrap_or("");
+                let padding = width - text.chars().count();
+                let (before, after) = match table.alignments.get(column) {
+                    Some(Alignment::Right) => (padding, 0),
+                    Some(Alignment::Center) => (padding / 2, padding - padding / 2),
+                    _ => (0, padding),
+                };
+
+                self.push(&" ".repeat(before));
+                let old_start = table.rows[index].get(column).map(|cell| cell.start);
+                let new_start = self.out.len();
+                self.push(text);
+                if let Some(old_start) = old_start {
+                    let old_end = old_start + text.len();
+                    for (range, style) in &cell_spans {
+                        if range.start >= old_start && range.end <= old_end {
+                            let moved = range.start - old_start + new_start..range.end - old_start + new_start;
+                            self.spans.push((moved, style.clone()));
+                        }
+                    }
+                }
+                self.push(&" ".repeat(after));
+            }
+
+            if index + 1 == table.header_rows {
+                header_end = self.out.len();
+                let rule: Vec<String> = widths.iter().map(|width| "─".repeat(*width)).collect();
+                self.push("\n");
+                self.push(&rule.join("─┼─"));
+            }
+        }
+
+        if header_end > table.start {
+            self.spans.push((table.start..header_end, Style::TableHeader));
+        }
+        self.spans.push((table.start..self.out.len(), Style::Table));
+    }
+
+    fn finish_block(&mut self, source_end: usize) {
+        let source = self.source_start..source_end;
+        let mut out = std::mem::take(&mut self.out);
+        let mut spans = std::mem::take(&mut self.spans);
+        let lines = std::mem::take(&mut self.lines);
+        self.open.clear();
+        self.lists.clear();
+        self.quotes = 0;
+        self.after_marker = false;
+
+        out.truncate(out.trim_end_matches('\n').len());
+        if out.is_empty() {
+            return;
+        }
+
+        // Nested lines are indented as a whole, newline included
+        for (index, (start, level)) in lines.iter().enumerate() {
+            if *level > 0 && *start < out.len() {
+                let end = lines.get(index + 1).map_or(out.len(), |(next, _)| *next).min(out.len());
+                spans.push((*start..end, Style::Indent(*level)));
+            }
+        }
+
+        let char_offset = |byte: usize| out[..byte.min(out.len())].chars().count();
+        let spans = spans
+            .into_iter()
+            .map(|(range, style)| Span {
+                start: char_offset(range.start),
+                end: char_offset(range.end),
+                style,
+            })
+            .filter(|span| span.start < span.end)
+            .collect();
+
+        out.push_str("\n\n");
+        self.blocks.push(Block {
+            source,
+            text: out,
+            spans,
+        });
+    }
+}
+
+fn is_block(tag: &Tag) -> bool {
+    !matches!(
+        tag,
+        Tag::Emphasis | Tag::Strong | Tag::Strikethrough | Tag::Link { .. } | Tag::Image { .. }
+    )
+}
+
+fn is_inline(tag: &TagEnd) -> bool {
+    matches!(
+        tag,
+        TagEnd::Emphasis | TagEnd::Strong | TagEnd::Strikethrough | TagEnd::Link | TagEnd::Image
+    )
+}
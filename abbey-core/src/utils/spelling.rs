@@ -0,0 +1,810 @@
+//! Offline spell checking against the Hunspell dictionaries installed on the
+//! system. The `.aff`/`.dic` pair is read directly: prefix and suffix rules
+//! with their conditions, every flag format and flag aliases, `TRY`, `REP`,
+//! `FORBIDDENWORD`, `NEEDAFFIX` and `NOSUGGEST`.
+//!
+//! Not supported, so some words Hunspell accepts are flagged:
+//! - compounding (`COMPOUNDFLAG`, `COMPOUNDRULE` and the rest), which languages
+//!   like German, Dutch and Finnish build long words with
+//! - twofold affixes: the flags after a slash in an affix rule are dropped, so
+//!   a word only ever loses one suffix and one prefix
+//! - `CIRCUMFIX`, `COMPLEXPREFIXES`, `KEEPCASE`, `CHECKSHARPS`, `IGNORE` and
+//!   `ICONV`/`OCONV`
+//!
+//! Only prose is checked: code, URLs, HTML and link targets in the markdown are
+//! skipped.
+
+use pulldown_cmark::{Event, LinkType, Parser, Tag, TagEnd};
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::{env, fs, io};
+
+use super::markdown::{parser_options, CharOffsets};
+
+/// How many suggestions to offer for a misspelling
+const MAX_SUGGESTIONS: usize = 8;
+
+/// Letters to try in suggestions when the dictionary doesn't list its own
+const DEFAULT_TRY: &str = "esianrtolcdugmphbyfvkwzxjq'";
+
+type Flag = u32;
+
+/// How the dictionary writes flags after a word or affix
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FlagFormat {
+    /// One character each, the default and `FLAG UTF-8`
+    Char,
+    /// Two characters each, `FLAG long`
+    Long,
+    /// Comma-separated numbers, `FLAG num`
+    Num,
+}
+
+impl FlagFormat {
+    fn parse(self, flags: &str) -> Vec<Flag> {
+        match self {
+            FlagFormat::Char => flags.chars().map(|c| c as Flag).collect(),
+            FlagFormat::Long => flags
+                .chars()
+                .collect::<Vec<_>>()
+                .chunks(2)
+                .map(|pair| pair.iter().fold(0, |flag, &c| (flag << 16) | c as Flag))
+                .collect(),
+            FlagFormat::Num => flags.split(',').filter_map(|n| n.trim().parse().ok()).collect(),
+        }
+    }
+}
+
+/// One position of an affix condition
+#[derive(Debug, Clone)]
+enum CharClass {
+    Any,
+    Is(char),
+    OneOf(Vec<char>),
+    NoneOf(Vec<char>),
+}
+
+impl CharClass {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            CharClass::Any => true,
+            CharClass::Is(expected) => c == *expected,
+            CharClass::OneOf(chars) => chars.contains(&c),
+            CharClass::NoneOf(chars) => !chars.contains(&c),
+        }
+    }
+}
+
+/// Parse a condition such as `[^aeiou]y`; `.` alone means no condition
+fn parse_condition(condition: &str) -> Vec<CharClass> {
+    if condition == "." {
+        return Vec::new();
+    }
+
+    let mut classes = Vec::new();
+    let mut chars = condition.chars();
+    while let Some(c) = chars.next() {
+        let class = match c {
+            '.' => CharClass::Any,
+            '[' => {
+                let mut set: Vec<char> = chars.by_ref().take_while(|&c| c != ']').collect();
+                if set.first() == Some(&'^') {
+                    set.remove(0);
+                    CharClass::NoneOf(set)
+                } else {
+                    CharClass::OneOf(set)
+                }
+            }
+            c => CharClass::Is(c),
+        };
+        classes.push(class);
+    }
+    classes
+}
+
+/// A prefix or suffix rule: strip `strip` from a stem meeting `condition`, then
+/// add `add`
+#[derive(Debug, Clone)]
+struct Affix {
+    flag: Flag,
+    /// Whether it combines with an affix of the other kind
+    cross_product: bool,
+    strip: String,
+    add: String,
+    condition: Vec<CharClass>,
+}
+
+impl Affix {
+    fn prefix_fits(&self, stem: &str) -> bool {
+        let mut chars = stem.chars();
+        self.condition.iter().all(|class| chars.next().is_some_and(|c| class.matches(c)))
+    }
+
+    fn suffix_fits(&self, stem: &str) -> bool {
+        let mut chars = stem.chars().rev();
+        self.condition.iter().rev().all(|class| chars.next().is_some_and(|c| class.matches(c)))
+    }
+}
+
+/// A Hunspell dictionary: stems with their flags, and the affix rules the flags
+/// allow
+#[derive(Debug, Default)]
+pub struct Dictionary {
+    /// Each stem's flag sets, one per homonym
+    words: HashMap<String, Vec<Vec<Flag>>>,
+    prefixes: Vec<Affix>,
+    suffixes: Vec<Affix>,
+    /// Rules by the text they add, for looking up what a word might end or start with
+    prefix_index: HashMap<String, Vec<usize>>,
+    suffix_index: HashMap<String, Vec<usize>>,
+    try_chars: Vec<char>,
+    replacements: Vec<(String, String)>,
+    forbidden: Option<Flag>,
+    need_affix: Option<Flag>,
+    only_in_compound: Option<Flag>,
+    no_suggest: Option<Flag>,
+}
+
+impl Dictionary {
+    /// Read a dictionary from its `.aff` and `.dic` files, in whatever encoding
+    /// the `.aff` declares with `SET`
+    pub fn load(aff: &Path, dic: &Path) -> io::Result<Self> {
+        let aff_bytes = fs::read(aff)?;
+        let dic_bytes = fs::read(dic)?;
+        let utf8 = declared_encoding(&aff_bytes).is_none_or(|encoding| encoding.eq_ignore_ascii_case("UTF-8"));
+
+        Ok(Self::parse(&decode(&aff_bytes, utf8), &decode(&dic_bytes, utf8)))
+    }
+
+    /// Build a dictionary from the text of its `.aff` and `.dic` files
+    pub fn parse(aff: &str, dic: &str) -> Self {
+        let mut dictionary = Dictionary::default();
+        let mut format = FlagFormat::Char;
+        let mut aliases: Vec<Vec<Flag>> = Vec::new();
+        let mut cross_products: HashMap<(bool, Flag), bool> = HashMap::new();
+        // The AF table starts with a line giving its length
+        let mut alias_count_seen = false;
+
+        for line in aff.lines() {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            let (&keyword, args) = match parts.split_first() {
+                Some(split) => split,
+                None => continue,
+            };
+            let first_flag = |value: &str| format.parse(value).first().copied();
+
+            match (keyword, args) {
+                ("FLAG", [value, ..]) => {
+                    format = match *value {
+                        "long" => FlagFormat::Long,
+                        "num" => FlagFormat::Num,
+                        _ => FlagFormat::Char,
+                    };
+                }
+                ("TRY", [chars, ..]) => dictionary.try_chars = chars.chars().collect(),
+                ("FORBIDDENWORD", [flag, ..]) => dictionary.forbidden = first_flag(flag),
+                ("NEEDAFFIX", [flag, ..]) => dictionary.need_affix = first_flag(flag),
+                ("ONLYINCOMPOUND", [flag, ..]) => dictionary.only_in_compound = first_flag(flag),
+                ("NOSUGGEST", [flag, ..]) => dictionary.no_suggest = first_flag(flag),
+                ("AF", [flags, ..]) => {
+                    if alias_count_seen {
+                        aliases.push(format.parse(flags));
+                    }
+                    alias_count_seen = true;
+                }
+                ("REP", [from, to, ..]) => {
+                    let unanchor = |text: &str| text.trim_start_matches('^').trim_end_matches('$').replace('_', " ");
+                    dictionary.replacements.push((unanchor(from), unanchor(to)));
+                }
+                ("PFX" | "SFX", [flag, cross, count]) if count.parse::<usize>().is_ok() => {
+                    if let Some(flag) = first_flag(flag) {
+                        cross_products.insert((keyword == "PFX", flag), *cross == "Y");
+                    }
+                }
+                ("PFX" | "SFX", [flag, strip, add, rest @ ..]) => {
+                    let is_prefix = keyword == "PFX";
+                    let flag = match first_flag(flag) {
+                        Some(flag) => flag,
+                        None => continue,
+                    };
+                    // Flags after a slash continue the affix; only the text is used
+                    let add = add.split('/').next().unwrap_or_default();
+                    let affix = Affix {
+                        flag,
+                        cross_product: cross_products.get(&(is_prefix, flag)).copied().unwrap_or(false),
+                        strip: if *strip == "0" { String::new() } else { strip.to_string() },
+                        add: if add == "0" { String::new() } else { add.to_string() },
+                        condition: parse_condition(rest.first().copied().unwrap_or(".")),
+                    };
+
+                    let (rules, index) = if is_prefix {
+                        (&mut dictionary.prefixes, &mut dictionary.prefix_index)
+                    } else {
+                        (&mut dictionary.suffixes, &mut dictionary.suffix_index)
+                    };
+                    index.entry(affix.add.clone()).or_default().push(rules.len());
+                    rules.push(affix);
+                }
+                _ => {}
+            }
+        }
+
+        let mut lines = dic.lines();
+        // The first line is the word count, which we don't need
+        if let Some(first) = lines.clone().next() {
+            if first.trim().parse::<usize>().is_ok() {
+                lines.next();
+            }
+        }
+        for line in lines {
+            let entry = match line.split(['\t', ' ']).next() {
+                Some(entry) if !entry.is_empty() => entry,
+                _ => continue,
+            };
+            let (word, flags) = split_entry(entry);
+            let flags = match flags {
+                Some(flags) if !aliases.is_empty() => flags
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|n| aliases.get(n.wrapping_sub(1)))
+                    .cloned()
+                    .unwrap_or_default(),
+                Some(flags) => format.parse(flags),
+                None => Vec::new(),
+            };
+            dictionary.words.entry(word).or_default().push(flags);
+        }
+
+        if dictionary.try_chars.is_empty() {
+            dictionary.try_chars = DEFAULT_TRY.chars().collect();
+        }
+        dictionary
+    }
+
+    /// Whether the dictionary knows `word`, allowing for it being capitalised
+    /// or in capitals
+    pub fn check(&self, word: &str) -> bool {
+        let word = normalize(word);
+        case_variants(&word).iter().any(|variant| self.check_exact(variant))
+    }
+
+    /// Likely corrections for a misspelled word, best first
+    pub fn suggest(&self, word: &str) -> Vec<String> {
+        let word = normalize(word);
+        let chars: Vec<char> = word.chars().collect();
+        let mut suggestions: Vec<String> = Vec::new();
+        let mut consider = |candidate: String| {
+            if suggestions.len() < MAX_SUGGESTIONS
+                && candidate != word
+                && !suggestions.contains(&candidate)
+                && self.suggestable(&candidate)
+            {
+                suggestions.push(candidate);
+            }
+        };
+
+        // Known common mistakes first
+        for (from, to) in &self.replacements {
+            for (at, _) in word.match_indices(from.as_str()) {
+                consider(format!("{}{}{}", &word[..at], to, &word[at + from.len()..]));
+            }
+        }
+
+        // Words the dictionary only knows capitalised, like names
+        consider(capitalize(&word));
+
+        // Swapped neighbours
+        for i in 1..chars.len() {
+            let mut swapped = chars.clone();
+            swapped.swap(i - 1, i);
+            consider(swapped.into_iter().collect());
+        }
+
+        // One wrong letter
+        for i in 0..chars.len() {
+            for &c in &self.try_chars {
+                let c = if chars[i].is_uppercase() { c.to_uppercase().next().unwrap_or(c) } else { c };
+                if c != chars[i] {
+                    let mut replaced = chars.clone();
+                    replaced[i] = c;
+                    consider(replaced.into_iter().collect());
+                }
+            }
+        }
+
+        // One letter too many
+        for i in 0..chars.len() {
+            let mut shorter = chars.clone();
+            shorter.remove(i);
+            consider(shorter.into_iter().collect());
+        }
+
+        // One letter missing
+        for i in 0..=chars.len() {
+            for &c in &self.try_chars {
+                let mut longer = chars.clone();
+                longer.insert(i, c);
+                consider(longer.into_iter().collect());
+            }
+        }
+
+        // Two words run together
+        for i in 1..chars.len() {
+            let left: String = chars[..i].iter().collect();
+            let right: String = chars[i..].iter().collect();
+            if self.check(&left) && self.check(&right) {
+                consider(format!("{} {}", left, right));
+            }
+        }
+
+        if suggestions.is_empty() {
+            suggestions = self.similar_words(&word);
+        }
+        suggestions
+    }
+
+    /// Dictionary words sharing the most letter pairs with `word`, for
+    /// misspellings too far from any word for single edits to find
+    fn similar_words(&self, word: &str) -> Vec<String> {
+        let lower = word.to_lowercase();
+        let length = lower.chars().count();
+        let pairs = letter_pairs(&lower);
+        if pairs.is_empty() {
+            return Vec::new();
+        }
+
+        let mut scored: Vec<(usize, &String)> = self
+            .words
+            .iter()
+            .filter(|(stem, homonyms)| {
+                stem.chars().count().abs_diff(length) <= 2 && homonyms.iter().any(|flags| self.usable_alone(flags))
+            })
+            .filter_map(|(stem, _)| {
+                let shared = letter_pairs(&stem.to_lowercase()).iter().filter(|pair| pairs.contains(pair)).count();
+                // At least half the pairs in common, or it's not worth offering
+                (shared * 2 >= pairs.len()).then_some((shared, stem))
+            })
+            .collect();
+        scored.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(b.1)));
+
+        scored
+            .into_iter()
+            .map(|(_, stem)| stem.clone())
+            .filter(|stem| self.suggestable(stem))
+            .take(MAX_SUGGESTIONS)
+            .collect()
+    }
+
+    fn suggestable(&self, word: &str) -> bool {
+        let no_suggest = self.no_suggest.is_some_and(|flag| {
+            self.words.get(word).is_some_and(|homonyms| homonyms.iter().all(|flags| flags.contains(&flag)))
+        });
+        !no_suggest && word.split(' ').all(|part| self.check(part))
+    }
+
+    fn check_exact(&self, word: &str) -> bool {
+        if let Some(homonyms) = self.words.get(word) {
+            if homonyms.iter().any(|flags| self.is_forbidden(flags)) {
+                return false;
+            }
+            if homonyms.iter().any(|flags| self.usable_alone(flags)) {
+                return true;
+            }
+        }
+        self.check_suffixed(word) || self.check_prefixed(word, None)
+    }
+
+    /// Whether `word` is a stem plus one of its suffixes, and maybe a prefix
+    fn check_suffixed(&self, word: &str) -> bool {
+        let boundaries = word.char_indices().map(|(i, _)| i).chain([word.len()]);
+        for at in boundaries {
+            let rules = match self.suffix_index.get(&word[at..]) {
+                Some(rules) => rules,
+                None => continue,
+            };
+            for rule in rules.iter().map(|&i| &self.suffixes[i]) {
+                let stem = format!("{}{}", &word[..at], rule.strip);
+                if stem.is_empty() || !rule.suffix_fits(&stem) {
+                    continue;
+                }
+                if self.stem_has(&stem, &[rule.flag]) {
+                    return true;
+                }
+                if rule.cross_product && self.check_prefixed(&stem, Some(rule.flag)) {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    /// Whether `word` is a stem plus one of its prefixes; `suffix` is a suffix
+    /// already taken off, which the stem must allow as well
+    fn check_prefixed(&self, word: &str, suffix: Option<Flag>) -> bool {
+        let boundaries = word.char_indices().map(|(i, _)| i).chain([word.len()]);
+        for at in boundaries {
+            let rules = match self.prefix_index.get(&word[..at]) {
+                Some(rules) => rules,
+                None => continue,
+            };
+            for rule in rules.iter().map(|&i| &self.prefixes[i]) {
+                if suffix.is_some() && !rule.cross_product {
+                    continue;
+                }
+                let stem = format!("{}{}", rule.strip, &word[at..]);
+                if stem.is_empty() || !rule.prefix_fits(&stem) {
+                    continue;
+                }
+                let found = match suffix {
+                    Some(suffix) => self.stem_has(&stem, &[rule.flag, suffix]),
+                    None => self.stem_has(&stem, &[rule.flag]),
+                };
+                if found {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    fn stem_has(&self, stem: &str, flags: &[Flag]) -> bool {
+        self.words.get(stem).is_some_and(|homonyms| {
+            homonyms
+                .iter()
+                .any(|own| !self.is_forbidden(own) && flags.iter().all(|flag| own.contains(flag)))
+        })
+    }
+
+    fn is_forbidden(&self, flags: &[Flag]) -> bool {
+        self.forbidden.is_some_and(|flag| flags.contains(&flag))
+    }
+
+    /// Whether a stem is a word by itself, not only a base for affixes or compounds
+    fn usable_alone(&self, flags: &[Flag]) -> bool {
+        let marked = |flag: Option<Flag>| flag.is_some_and(|flag| flags.contains(&flag));
+        !marked(self.forbidden) && !marked(self.need_affix) && !marked(self.only_in_compound)
+    }
+}
+
+/// The encoding named by an `.aff` file's `SET` line
+fn declared_encoding(aff: &[u8]) -> Option<String> {
+    aff.split(|&b| b == b'\n')
+        .map(|line| String::from_utf8_lossy(line).trim().to_string())
+        .find_map(|line| line.strip_prefix("SET ").map(|value| value.trim().to_string()))
+}
+
+/// Dictionary text as UTF-8, or as one char per byte for the 8-bit encodings
+/// older dictionaries use
+fn decode(bytes: &[u8], utf8: bool) -> String {
+    if utf8 {
+        let bytes = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(bytes);
+        String::from_utf8_lossy(bytes).into_owned()
+    } else {
+        bytes.iter().map(|&b| b as char).collect()
+    }
+}
+
+/// Split a `.dic` entry into its word and flags, at the first unescaped slash
+fn split_entry(entry: &str) -> (String, Option<&str>) {
+    let mut escaped = false;
+    for (i, c) in entry.char_indices() {
+        match c {
+            '\\' => escaped = !escaped,
+            '/' if !escaped && i > 0 => return (entry[..i].replace("\\/", "/"), Some(&entry[i + 1..])),
+            _ => escaped = false,
+        }
+    }
+    (entry.replace("\\/", "/"), None)
+}
+
+/// Typographic apostrophes check the same as straight ones
+fn normalize(word: &str) -> String {
+    word.replace('\u{2019}', "'")
+}
+
+/// The spellings to look `word` up as: itself, and lower case forms if it's
+/// capitalised or in capitals
+fn case_variants(word: &str) -> Vec<String> {
+    let mut variants = vec![word.to_string()];
+    let lower = word.to_lowercase();
+    if lower == word {
+        return variants;
+    }
+
+    let all_caps = word.chars().filter(|c| c.is_alphabetic()).count() > 1 && word.to_uppercase() == word;
+    if all_caps {
+        variants.push(capitalize(&lower));
+        variants.push(lower);
+    } else if word.chars().next().is_some_and(char::is_uppercase) {
+        variants.push(lower);
+    }
+    variants
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn letter_pairs(word: &str) -> Vec<(char, char)> {
+    let chars: Vec<char> = word.chars().collect();
+    chars.windows(2).map(|pair| (pair[0], pair[1])).collect()
+}
+
+// ========== Words in Markdown ==========
+
+/// A word of prose in a markdown document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Word {
+    /// Where it is, in chars
+    pub range: Range<usize>,
+    pub text: String,
+}
+
+/// The words of prose in `markdown`, leaving out code, HTML, URLs, link targets
+/// and anything with digits in it
+pub fn words(markdown: &str) -> Vec<Word> {
+    blocks(markdown).concat()
+}
+
+/// The same words, split by the paragraph, heading, list item or table cell
+/// they're in
+pub(crate) fn blocks(markdown: &str) -> Vec<Vec<Word>> {
+    let mut blocks = Vec::new();
+    let mut words = Vec::new();
+    let mut offsets = CharOffsets::new(markdown);
+    // Depth inside code blocks, HTML and similar, whose text isn't prose
+    let mut skip = 0usize;
+    // Whether each open link is an autolink, whose text is its address
+    let mut links: Vec<bool> = Vec::new();
+
+    for (event, range) in Parser::new_ext(markdown, parser_options()).into_offset_iter() {
+        let block_edge = match &event {
+            Event::Start(tag) => is_block(tag),
+            Event::End(tag) => is_block_end(tag),
+            Event::Rule => true,
+            _ => false,
+        };
+        if block_edge && !words.is_empty() {
+            blocks.push(std::mem::take(&mut words));
+        }
+
+        match event {
+            Event::Start(Tag::CodeBlock(_) | Tag::HtmlBlock | Tag::MetadataBlock(_)) => skip += 1,
+            Event::End(TagEnd::CodeBlock | TagEnd::HtmlBlock | TagEnd::MetadataBlock(_)) => {
+                skip = skip.saturating_sub(1);
+            }
+            Event::Start(Tag::Link { link_type, .. }) => {
+                let autolink = matches!(link_type, LinkType::Autolink | LinkType::Email);
+                if autolink {
+                    skip += 1;
+                }
+                links.push(autolink);
+            }
+            Event::End(TagEnd::Link) if links.pop() == Some(true) => {
+                skip = skip.saturating_sub(1);
+            }
+            Event::Text(_) if skip == 0 => {
+                if let Some(text) = markdown.get(range.clone()) {
+                    push_words(text, range.start, &mut offsets, &mut words);
+                }
+            }
+            _ => {}
+        }
+    }
+    if !words.is_empty() {
+        blocks.push(words);
+    }
+    blocks
+}
+
+fn is_block(tag: &Tag) -> bool {
+    !matches!(
+        tag,
+        Tag::Emphasis | Tag::Strong | Tag::Strikethrough | Tag::Link { .. } | Tag::Image { .. }
+    )
+}
+
+fn is_block_end(tag: &TagEnd) -> bool {
+    !matches!(
+        tag,
+        TagEnd::Emphasis | TagEnd::Strong | TagEnd::Strikethrough | TagEnd::Link | TagEnd::Image
+    )
+}
+
+/// Add the words of a stretch of text that starts `start` bytes into the document
+fn push_words(text: &str, start: usize, offsets: &mut CharOffsets, words: &mut Vec<Word>) {
+    let mut chunk_start = 0;
+    for chunk in text.split_inclusive(char::is_whitespace) {
+        let chunk_offset = start + chunk_start;
+        chunk_start += chunk.len();
+        if looks_like_address(chunk) {
+            continue;
+        }
+
+        let mut run: Option<usize> = None;
+        for (i, c) in chunk.char_indices().chain([(chunk.len(), ' ')]) {
+            let in_word = c.is_alphanumeric() || is_apostrophe(c);
+            match (run, in_word) {
+                (None, true) => run = Some(i),
+                (Some(run_start), false) => {
+                    run = None;
+                    let raw = &chunk[run_start..i];
+                    let word = raw.trim_start_matches(is_apostrophe);
+                    let leading = raw.len() - word.len();
+                    let word = word.trim_end_matches(is_apostrophe);
+                    if word.is_empty() || word.chars().any(|c| c.is_numeric()) {
+                        continue;
+                    }
+
+                    let byte_start = chunk_offset + run_start + leading;
+                    let char_start = offsets.at(byte_start);
+                    words.push(Word {
+                        range: char_start..char_start + word.chars().count(),
+                        text: word.to_string(),
+                    });
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+fn is_apostrophe(c: char) -> bool {
+    c == '\'' || c == '\u{2019}'
+}
+
+/// URLs, email addresses and bare domains, which aren't words
+fn looks_like_address(chunk: &str) -> bool {
+    let chunk = chunk.trim();
+    chunk.contains("://") || chunk.starts_with("www.") || (chunk.contains('@') && chunk.contains('.'))
+}
+
+// ========== Checking ==========
+
+/// Words the writer has taught the checker, kept per library
+#[derive(Debug, Clone, Default)]
+pub struct PersonalDictionary {
+    words: HashSet<String>,
+}
+
+impl PersonalDictionary {
+    pub fn new(words: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            words: words.into_iter().map(|word| normalize(&word)).collect(),
+        }
+    }
+
+    /// Whether `word` was added, capitalised or in capitals as well
+    pub fn contains(&self, word: &str) -> bool {
+        case_variants(&normalize(word)).iter().any(|variant| self.words.contains(variant))
+    }
+
+    /// Add a word, returning whether it's new
+    pub fn add(&mut self, word: &str) -> bool {
+        self.words.insert(normalize(word))
+    }
+
+    /// Every word, sorted, for saving
+    pub fn words(&self) -> Vec<String> {
+        let mut words: Vec<String> = self.words.iter().cloned().collect();
+        words.sort();
+        words
+    }
+}
+
+/// The words of `markdown` that neither dictionary knows
+pub fn misspelled(markdown: &str, dictionary: &Dictionary, personal: &PersonalDictionary) -> Vec<Word> {
+    let mut known: HashMap<String, bool> = HashMap::new();
+    words(markdown)
+        .into_iter()
+        .filter(|word| {
+            !*known
+                .entry(word.text.clone())
+                .or_insert_with(|| personal.contains(&word.text) || dictionary.check(&word.text))
+        })
+        .collect()
+}
+
+// ========== Finding Dictionaries ==========
+
+/// An installed Hunspell dictionary
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DictionaryFiles {
+    /// The language code it's named for, like `en_GB`
+    pub language: String,
+    pub aff: PathBuf,
+    pub dic: PathBuf,
+}
+
+impl DictionaryFiles {
+    pub fn load(&self) -> io::Result<Dictionary> {
+        Dictionary::load(&self.aff, &self.dic)
+    }
+}
+
+/// Where Hunspell dictionaries are installed: `$DICPATH`, the user's data
+/// directory, then the system's, including the host's inside a Flatpak
+pub fn dictionary_dirs() -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = env::var_os("DICPATH")
+        .map(|paths| env::split_paths(&paths).collect())
+        .unwrap_or_default();
+    if let Some(base) = directories::BaseDirs::new() {
+        dirs.push(base.data_dir().join("hunspell"));
+    }
+    dirs.extend(
+        [
+            "/usr/share/hunspell",
+            "/usr/share/myspell",
+            "/usr/share/myspell/dicts",
+            "/usr/local/share/hunspell",
+            "/app/share/hunspell",
+            "/run/host/usr/share/hunspell",
+        ]
+        .iter()
+        .map(PathBuf::from),
+    );
+    dirs
+}
+
+/// The dictionaries in `dirs`, by language; where two directories have the
+/// same language the earlier one wins
+pub fn find_dictionaries(dirs: &[PathBuf]) -> Vec<DictionaryFiles> {
+    let mut found: Vec<DictionaryFiles> = Vec::new();
+    for dir in dirs {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        let mut here: Vec<DictionaryFiles> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "dic"))
+            .filter_map(|dic| {
+                let aff = dic.with_extension("aff");
+                let language = dic.file_stem()?.to_str()?.to_string();
+                // Hyphenation patterns share the extension
+                (aff.is_file() && !language.starts_with("hyph_")).then_some(DictionaryFiles { language, aff, dic })
+            })
+            .collect();
+        here.sort_by(|a, b| a.language.cmp(&b.language));
+
+        for files in here {
+            if !found.iter().any(|f| f.language == files.language) {
+                found.push(files);
+            }
+        }
+    }
+    found.sort_by(|a, b| a.language.cmp(&b.language));
+    found
+}
+
+/// Every installed dictionary
+pub fn available_dictionaries() -> Vec<DictionaryFiles> {
+    find_dictionaries(&dictionary_dirs())
+}
+
+/// The best dictionary for a language: that exact one, or else another
+/// variety of the same language, so `en` or `en_CA` can fall back to `en_US`
+pub fn choose_dictionary<'a>(dictionaries: &'a [DictionaryFiles], language: &str) -> Option<&'a DictionaryFiles> {
+    let language = language.replace('-', "_");
+    let base = language.split('_').next().unwrap_or_default();
+    dictionaries.iter().find(|d| d.language == language).or_else(|| {
+        dictionaries
+            .iter()
+            .find(|d| d.language.split('_').next() == Some(base))
+    })
+}
+
+/// The language of the user's locale, like `en_GB`, or `en_US` if unset
+pub fn default_language() -> String {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|name| env::var(name).ok())
+        .map(|locale| locale.split(['.', '@']).next().unwrap_or_default().to_string())
+        .find(|language| !language.is_empty() && language != "C" && language != "POSIX")
+        .unwrap_or_else(|| "en_US".to_string())
+}
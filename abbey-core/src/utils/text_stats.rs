@@ -0,0 +1,459 @@
+//! Counts and readability scores for markdown, worked out over the text it
+//! renders to rather than its source, so markup isn't counted as words.
+//!
+//! Words are segmented along the lines of Unicode's word boundaries (UAX #29):
+//! apostrophes, underscores and hyphens inside a word and separators inside a
+//! number don't split it, and Chinese and Japanese, which aren't written with
+//! spaces, count a word per character. The readability scores are designed
+//! for English.
+
+use pulldown_cmark::{Event, Parser, Tag, TagEnd};
+use std::ops::Range;
+use std::time::Duration;
+
+use super::markdown::{parser_options, CharOffsets};
+
+/// Reading speed for text written with spaces, in words a minute
+const WORDS_PER_MINUTE: f64 = 200.0;
+
+/// Reading speed for Chinese and Japanese, in characters a minute
+const CJK_CHARS_PER_MINUTE: f64 = 450.0;
+
+/// Everything worth counting in a piece of markdown
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TextStats {
+    pub words: usize,
+    /// Of `words`, the Chinese and Japanese characters counted one each
+    pub cjk_words: usize,
+    /// Characters as readers see them, so an accented letter or an emoji
+    /// sequence counts once
+    pub characters: usize,
+    pub characters_without_spaces: usize,
+    /// Letters and digits in words, for the character-based scores
+    pub letters: usize,
+    pub syllables: usize,
+    pub sentences: usize,
+    /// Paragraphs, headings, list items, code blocks and tables with text in
+    pub paragraphs: usize,
+}
+
+impl TextStats {
+    pub fn of(markdown: &str) -> Self {
+        let mut stats = Self::default();
+        for block in blocks(markdown) {
+            let before = stats.words;
+            stats.add_block(&block);
+            if stats.words > before {
+                stats.paragraphs += 1;
+            }
+        }
+        stats
+    }
+
+    fn add_block(&mut self, text: &str) {
+        self.characters += visible_chars(text, |_| true);
+        self.characters_without_spaces += visible_chars(text, |c| !c.is_whitespace());
+        self.sentences += count_sentences(text);
+
+        for word in words(text) {
+            self.words += 1;
+            self.letters += word.chars().filter(|c| c.is_alphanumeric()).count();
+            if word.chars().next().is_some_and(is_cjk) {
+                self.cjk_words += 1;
+                self.syllables += 1;
+            } else {
+                self.syllables += syllables(word);
+            }
+        }
+    }
+
+    /// How long the text takes to read at an average pace
+    pub fn reading_time(&self) -> Duration {
+        let spaced = (self.words - self.cjk_words) as f64 / WORDS_PER_MINUTE;
+        let cjk = self.cjk_words as f64 / CJK_CHARS_PER_MINUTE;
+        Duration::from_secs_f64((spaced + cjk) * 60.0)
+    }
+
+    /// Reading time in whole minutes, rounded up, and at least one for any text
+    pub fn reading_minutes(&self) -> u32 {
+        (self.reading_time().as_secs_f64() / 60.0).ceil() as u32
+    }
+
+    fn words_per_sentence(&self) -> Option<f64> {
+        if self.words == 0 || self.sentences == 0 {
+            return None;
+        }
+        Some(self.words as f64 / self.sentences as f64)
+    }
+
+    /// Flesch reading ease: 100 and above reads very easily, under 30 is dense
+    pub fn flesch_reading_ease(&self) -> Option<f64> {
+        let per_sentence = self.words_per_sentence()?;
+        Some(206.835 - 1.015 * per_sentence - 84.6 * (self.syllables as f64 / self.words as f64))
+    }
+
+    /// Flesch–Kincaid grade level: the US school grade the text suits
+    pub fn flesch_kincaid_grade(&self) -> Option<f64> {
+        let per_sentence = self.words_per_sentence()?;
+        Some(0.39 * per_sentence + 11.8 * (self.syllables as f64 / self.words as f64) - 15.59)
+    }
+
+    /// Automated readability index, a grade level from word and sentence length
+    pub fn automated_readability_index(&self) -> Option<f64> {
+        let per_sentence = self.words_per_sentence()?;
+        Some(4.71 * (self.letters as f64 / self.words as f64) + 0.5 * per_sentence - 21.43)
+    }
+
+    /// Coleman–Liau index, a grade level from letters and sentences per hundred words
+    pub fn coleman_liau_index(&self) -> Option<f64> {
+        self.words_per_sentence()?;
+        let letters = self.letters as f64 * 100.0 / self.words as f64;
+        let sentences = self.sentences as f64 * 100.0 / self.words as f64;
+        Some(0.0588 * letters - 0.296 * sentences - 15.8)
+    }
+}
+
+/// Words in markdown, counted the same way everywhere in Abbey
+pub fn word_count(markdown: &str) -> usize {
+    blocks(markdown).iter().map(|block| words(block).len()).sum()
+}
+
+/// The text markdown renders to, with a blank line between blocks
+pub fn plain_text(markdown: &str) -> String {
+    blocks(markdown).join("\n\n")
+}
+
+/// A word as written in markdown source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceWord {
+    /// Where it is in the source, in chars
+    pub range: Range<usize>,
+    pub text: String,
+}
+
+/// The words of markdown, block by block, with where each is in the source, so
+/// they can be found again in an editor
+pub fn source_words(markdown: &str) -> Vec<Vec<SourceWord>> {
+    let mut blocks = Vec::new();
+    let mut block = Vec::new();
+    let mut offsets = CharOffsets::new(markdown);
+    let mut in_table = false;
+
+    for (event, range) in Parser::new_ext(markdown, parser_options()).into_offset_iter() {
+        let edge = match &event {
+            Event::Start(Tag::Table(_)) | Event::End(TagEnd::Table) => true,
+            Event::Start(tag) => !in_table && !is_inline(tag),
+            Event::End(tag) => !in_table && !is_inline_end(tag),
+            Event::Rule => true,
+            _ => false,
+        };
+        if edge && !block.is_empty() {
+            blocks.push(std::mem::take(&mut block));
+        }
+
+        match event {
+            Event::Start(Tag::Table(_)) => in_table = true,
+            Event::End(TagEnd::Table) => in_table = false,
+            Event::Text(_) | Event::Code(_) => {
+                let source = match markdown.get(range.clone()) {
+                    Some(source) => source,
+                    None => continue,
+                };
+                for span in word_spans(source) {
+                    let start = offsets.at(range.start + span.start);
+                    let text = &source[span];
+                    block.push(SourceWord {
+                        range: start..start + text.chars().count(),
+                        text: text.to_string(),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+    if !block.is_empty() {
+        blocks.push(block);
+    }
+    blocks
+}
+
+/// The rendered text of each block: paragraphs, headings, list items, code
+/// blocks and whole tables. HTML, footnote markers and link targets are left
+/// out.
+fn blocks(markdown: &str) -> Vec<String> {
+    let mut blocks = Vec::new();
+    let mut block = String::new();
+    // Tables count as one block, their cells separated by spaces
+    let mut in_table = false;
+
+    for event in Parser::new_ext(markdown, parser_options()) {
+        let edge = match &event {
+            Event::Start(Tag::Table(_)) | Event::End(TagEnd::Table) => true,
+            Event::Start(tag) => !in_table && !is_inline(tag),
+            Event::End(tag) => !in_table && !is_inline_end(tag),
+            Event::Rule => true,
+            _ => false,
+        };
+        if edge && !block.trim().is_empty() {
+            blocks.push(std::mem::take(&mut block).trim().to_string());
+        }
+        if edge {
+            block.clear();
+        }
+
+        match event {
+            Event::Start(Tag::Table(_)) => in_table = true,
+            Event::End(TagEnd::Table) => in_table = false,
+            Event::End(TagEnd::TableCell) => block.push(' '),
+            Event::Text(text) | Event::Code(text) => block.push_str(&text),
+            Event::SoftBreak => block.push(' '),
+            Event::HardBreak => block.push('\n'),
+            _ => {}
+        }
+    }
+    if !block.trim().is_empty() {
+        blocks.push(block.trim().to_string());
+    }
+    blocks
+}
+
+fn is_inline(tag: &Tag) -> bool {
+    matches!(
+        tag,
+        Tag::Emphasis | Tag::Strong | Tag::Strikethrough | Tag::Link { .. } | Tag::Image { .. }
+    )
+}
+
+fn is_inline_end(tag: &TagEnd) -> bool {
+    matches!(
+        tag,
+        TagEnd::Emphasis | TagEnd::Strong | TagEnd::Strikethrough | TagEnd::Link | TagEnd::Image
+    )
+}
+
+// ========== Words ==========
+
+/// The words in plain text, in order
+pub fn words(text: &str) -> Vec<&str> {
+    word_spans(text).into_iter().map(|span| &text[span]).collect()
+}
+
+/// Where each word in plain text is, in bytes
+fn word_spans(text: &str) -> Vec<Range<usize>> {
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let mut words = Vec::new();
+    let mut start: Option<usize> = None;
+
+    for (i, &(byte, c)) in chars.iter().enumerate() {
+        if is_cjk(c) {
+            if let Some(s) = start.take() {
+                words.push(s..byte);
+            }
+            words.push(byte..byte + c.len_utf8());
+            continue;
+        }
+
+        let in_word = match start {
+            None => is_word_char(c) && !is_mark(c),
+            Some(_) => is_word_char(c) || joins(chars.get(i.wrapping_sub(1)), c, chars.get(i + 1)),
+        };
+        match (start, in_word) {
+            (None, true) => start = Some(byte),
+            (Some(s), false) => {
+                words.push(s..byte);
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        words.push(s..text.len());
+    }
+    words
+}
+
+/// Letters, digits, marks on them, and the underscores that join identifiers
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || is_mark(c)
+}
+
+/// Whether punctuation between two word characters keeps them one word: an
+/// apostrophe or hyphen between letters, a full stop or colon in an
+/// abbreviation, or a separator inside a number
+fn joins(before: Option<&(usize, char)>, c: char, after: Option<&(usize, char)>) -> bool {
+    let (before, after) = match (before, after) {
+        (Some(&(_, before)), Some(&(_, after))) => (before, after),
+        _ => return false,
+    };
+    if !is_word_char(before) || !is_word_char(after) || is_cjk(after) {
+        return false;
+    }
+    match c {
+        '\'' | '\u{2019}' | '-' | '\u{2010}' | '\u{2011}' | '.' | ':' | '\u{00B7}' => true,
+        ',' | ';' => before.is_numeric() && after.is_numeric(),
+        _ => false,
+    }
+}
+
+/// Combining marks, joiners and emoji modifiers, which belong to the character
+/// before them
+fn is_mark(c: char) -> bool {
+    matches!(
+        c,
+        '\u{0300}'..='\u{036F}'
+            | '\u{0483}'..='\u{0489}'
+            | '\u{0591}'..='\u{05BD}'
+            | '\u{064B}'..='\u{065F}'
+            | '\u{0900}'..='\u{0903}'
+            | '\u{093A}'..='\u{094F}'
+            | '\u{1AB0}'..='\u{1AFF}'
+            | '\u{1DC0}'..='\u{1DFF}'
+            | '\u{200C}'..='\u{200D}'
+            | '\u{20D0}'..='\u{20FF}'
+            | '\u{FE00}'..='\u{FE0F}'
+            | '\u{FE20}'..='\u{FE2F}'
+            | '\u{1F3FB}'..='\u{1F3FF}'
+            | '\u{E0020}'..='\u{E007F}'
+    )
+}
+
+/// Chinese characters and Japanese kana, which are written without spaces
+fn is_cjk(c: char) -> bool {
+    matches!(
+        c,
+        '\u{3040}'..='\u{30FF}'
+            | '\u{3400}'..='\u{4DBF}'
+            | '\u{4E00}'..='\u{9FFF}'
+            | '\u{F900}'..='\u{FAFF}'
+            | '\u{FF66}'..='\u{FF9F}'
+            | '\u{20000}'..='\u{2FA1F}'
+    )
+}
+
+/// Characters as a reader counts them: marks and joiners go with the
+/// character before them, as does whatever a zero-width joiner joins on
+fn visible_chars(text: &str, counted: impl Fn(char) -> bool) -> usize {
+    let mut count = 0;
+    let mut joined = false;
+    for c in text.chars() {
+        if is_mark(c) {
+            joined = c == '\u{200D}';
+            continue;
+        }
+        if !joined && counted(c) {
+            count += 1;
+        }
+        joined = false;
+    }
+    count
+}
+
+// ========== Sentences and Syllables ==========
+
+/// Sentences in a block: each run of terminators after some words that ends
+/// the text or comes before a space and a capital, plus any words left over
+/// at the end, as in a heading
+fn count_sentences(text: &str) -> usize {
+    sentence_spans(text).len()
+}
+
+/// Where each sentence [`count_sentences`] finds starts and ends, in chars,
+/// from its first non-space to the end of its closing punctuation
+pub(crate) fn sentence_spans(text: &str) -> Vec<Range<usize>> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut spans = Vec::new();
+    // Where the current sentence starts, once anything but spaces turns up
+    let mut start: Option<usize> = None;
+    // Whether words have turned up since the last sentence ended
+    let mut open = false;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        i += 1;
+        if start.is_none() && !c.is_whitespace() {
+            start = Some(i - 1);
+        }
+        if c.is_alphanumeric() {
+            open = true;
+            continue;
+        }
+        if !open || !is_terminator(c) {
+            continue;
+        }
+
+        let end = i - 1;
+        while i < chars.len() && (is_terminator(chars[i]) || is_closing(chars[i])) {
+            i += 1;
+        }
+        // Chinese and Japanese full stops need no space after them
+        let ends = i == chars.len() || chars[i].is_whitespace() || matches!(c, '。' | '！' | '？');
+        // Nor does a sentence go on in lower case, as after "a.m." or a quoted question
+        let continues = chars[i..]
+            .iter()
+            .find(|c| !c.is_whitespace() && !matches!(c, '"' | '\'' | '“' | '‘' | '«' | '(' | '['))
+            .is_some_and(|c| c.is_lowercase());
+        if ends && !continues && !(c == '.' && follows_abbreviation(&chars[..end])) {
+            spans.push(start.take().unwrap_or(end)..i);
+            open = false;
+        }
+    }
+    if open {
+        let end = chars.iter().rposition(|c| !c.is_whitespace()).map_or(0, |last| last + 1);
+        spans.push(start.unwrap_or(0)..end);
+    }
+    spans
+}
+
+fn is_terminator(c: char) -> bool {
+    matches!(c, '.' | '!' | '?' | '…' | '‼' | '⁇' | '。' | '！' | '？')
+}
+
+fn is_closing(c: char) -> bool {
+    matches!(c, '"' | '\'' | '”' | '’' | '»' | ')' | ']' | '」' | '』')
+}
+
+/// Whether the word before a full stop is a title, a common abbreviation or an
+/// initial, which don't end sentences
+fn follows_abbreviation(before: &[char]) -> bool {
+    const ABBREVIATIONS: &[&str] = &["mr", "mrs", "ms", "dr", "prof", "st", "jr", "sr", "vs", "etc", "cf", "fig", "no", "vol", "approx"];
+
+    let word: String = before
+        .iter()
+        .rev()
+        .take_while(|c| c.is_alphabetic())
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .collect();
+    let letters = word.chars().count();
+    (letters == 1 && word.chars().all(char::is_uppercase))
+        || (letters > 0 && ABBREVIATIONS.contains(&word.to_lowercase().as_str()))
+}
+
+/// An estimate of the syllables in an English word: groups of vowels, less a
+/// silent final "e"
+fn syllables(word: &str) -> usize {
+    let word = word.to_lowercase();
+    let letters: Vec<char> = word.chars().filter(|c| c.is_alphabetic()).collect();
+    if letters.is_empty() {
+        return 1;
+    }
+
+    let is_vowel = |c: char| "aeiouyàáâäæèéêëìíîïòóôöœùúûü".contains(c);
+    let mut count = 0;
+    let mut previous_vowel = false;
+    for &c in &letters {
+        let vowel = is_vowel(c);
+        if vowel && !previous_vowel {
+            count += 1;
+        }
+        previous_vowel = vowel;
+    }
+
+    // "make" has one syllable, "table" two
+    let n = letters.len();
+    if count > 1 && letters[n - 1] == 'e' && !(n > 2 && letters[n - 2] == 'l' && !is_vowel(letters[n - 3])) {
+        count -= 1;
+    }
+    count.max(1)
+}
@@ -0,0 +1,229 @@
+//! Typographic punctuation for markdown prose: curly quotes and apostrophes,
+//! en and em dashes from `--` and `---`, an ellipsis from `...` and, for
+//! French, non-breaking spaces before `; : ! ? »` and after `«`.
+//!
+//! The same substitutions run as the writer types ([`substitute`], checked
+//! with [`in_prose`]) and over a whole document ([`smarten`]). Neither touches
+//! code, HTML, link targets or markdown syntax such as rules and tables.
+
+use pulldown_cmark::{Event, Parser, Tag, TagEnd};
+use std::ops::Range;
+
+use super::markdown::{parser_options, CharOffsets};
+
+/// Before `;`, `!` and `?` in French
+const NARROW_NBSP: char = '\u{202F}';
+/// Before `:` and inside guillemets in French
+const NBSP: char = '\u{00A0}';
+
+/// Words that start with an apostrophe standing for missing letters
+const ELISIONS: &[&str] = &["tis", "twas", "em", "cause", "n", "round", "til"];
+
+/// Which substitutions to make beyond the ones every language gets
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Options {
+    pub french_spacing: bool,
+}
+
+/// Text to put in place of a typed character and the `remove` characters
+/// before it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Replacement {
+    pub remove: usize,
+    pub text: String,
+}
+
+/// A change to a document, in chars
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edit {
+    pub range: Range<usize>,
+    pub text: String,
+}
+
+/// What typing `c` between `before` and `after`, the rest of its line, should
+/// become, if anything. Doesn't know about code; see [`in_prose`].
+pub fn substitute(c: char, before: &str, after: &str, options: &Options) -> Option<Replacement> {
+    let last = before.chars().next_back();
+    // A backslash asks for the character as typed
+    if last == Some('\\') {
+        return None;
+    }
+
+    let replace = |remove: usize, text: String| Some(Replacement { remove, text });
+    match c {
+        '"' => replace(0, if opens(before) { "“" } else { "”" }.to_string()),
+        '\'' => replace(0, if opens(before) && !elides(after) { "‘" } else { "’" }.to_string()),
+        '-' if !is_markup_line(before) => match last {
+            Some('–') => replace(1, "—".to_string()),
+            Some('-') => replace(1, "–".to_string()),
+            _ => None,
+        },
+        '.' if before.ends_with("..") => replace(2, "…".to_string()),
+        ';' | '!' | '?' if options.french_spacing && last == Some(' ') && !after.starts_with('[') => {
+            replace(1, format!("{}{}", NARROW_NBSP, c))
+        }
+        ':' | '»' if options.french_spacing && last == Some(' ') => replace(1, format!("{}{}", NBSP, c)),
+        ' ' if options.french_spacing && last == Some('«') => replace(0, NBSP.to_string()),
+        _ => None,
+    }
+}
+
+/// Whether a quote after `before` opens: at the start of a line, after a space
+/// or an opening bracket or dash, looking past emphasis markers
+fn opens(before: &str) -> bool {
+    before
+        .trim_end_matches(['*', '_', '~'])
+        .chars()
+        .next_back()
+        .is_none_or(|c| c.is_whitespace() || matches!(c, '(' | '[' | '{' | '<' | '“' | '‘' | '«' | '—' | '–' | '-' | '/'))
+}
+
+/// Whether an apostrophe before `after` stands for missing letters, as in
+/// ’90s or ’til
+fn elides(after: &str) -> bool {
+    if after.starts_with(|c: char| c.is_ascii_digit()) {
+        return true;
+    }
+    let word: String = after.chars().take_while(|c| c.is_alphabetic()).collect();
+    ELISIONS.contains(&word.to_lowercase().as_str())
+}
+
+/// Lines where dashes are markdown: rules, list bullets, table delimiters and
+/// HTML comments
+fn is_markup_line(before: &str) -> bool {
+    before.chars().all(|c| c == '-' || c.is_whitespace()) || before.contains('|') || before.contains("<!-")
+}
+
+/// Whether typing at a char offset of a markdown document puts text in prose,
+/// rather than in code, HTML or a link target
+pub fn in_prose(markdown: &str, offset: usize) -> bool {
+    let byte = markdown.char_indices().nth(offset).map_or(markdown.len(), |(i, _)| i);
+    let line_start = markdown[..byte].rfind('\n').map_or(0, |i| i + 1);
+
+    // A code span or fence still being typed isn't closed yet, so the parser
+    // wouldn't see it
+    if markdown[line_start..byte].matches('`').count() % 2 == 1 || in_open_fence(&markdown[..line_start]) {
+        return false;
+    }
+
+    let mut in_code = 0;
+    let mut markup: Vec<Range<usize>> = Vec::new();
+    let mut text: Vec<Range<usize>> = Vec::new();
+    for (event, range) in Parser::new_ext(markdown, parser_options()).into_offset_iter() {
+        match event {
+            Event::Start(Tag::CodeBlock(_)) | Event::Start(Tag::HtmlBlock) => {
+                in_code += 1;
+                markup.push(range);
+            }
+            Event::End(TagEnd::CodeBlock) | Event::End(TagEnd::HtmlBlock) => {
+                in_code -= 1;
+            }
+            Event::Start(Tag::Link { .. }) | Event::Start(Tag::Image { .. }) => markup.push(range),
+            Event::Code(_) | Event::Html(_) | Event::InlineHtml(_) => markup.push(range),
+            Event::Text(_) if in_code == 0 => text.push(range),
+            _ => {}
+        }
+    }
+
+    // A link's label is prose, though the link around it isn't
+    !markup.iter().any(|range| range.start <= byte && byte < range.end)
+        || text.iter().any(|range| range.start <= byte && byte <= range.end)
+}
+
+/// Whether lines before this one leave a fenced code block open
+fn in_open_fence(before: &str) -> bool {
+    let mut fence: Option<String> = None;
+    for line in before.lines() {
+        let trimmed = line.trim_start();
+        if line.len() - trimmed.len() > 3 {
+            continue;
+        }
+        let marker: String = trimmed.chars().take_while(|c| *c == '`' || *c == '~').collect();
+        let is_fence = marker.len() >= 3 && marker.chars().all(|c| c == marker.chars().next().unwrap_or('`'));
+        match fence {
+            None if is_fence => fence = Some(marker),
+            Some(ref open) if is_fence && marker.starts_with(open.as_str()) && trimmed[marker.len()..].trim().is_empty() => {
+                fence = None;
+            }
+            _ => {}
+        }
+    }
+    fence.is_some()
+}
+
+/// The changes [`smarten`] makes, in document order
+pub fn smarten_edits(markdown: &str, options: &Options) -> Vec<Edit> {
+    let chars: Vec<char> = markdown.chars().collect();
+    let mut edits = Vec::new();
+    for run in prose_runs(markdown) {
+        let line_start = chars[..run.start].iter().rposition(|c| *c == '\n').map_or(0, |i| i + 1);
+        let mut line: String = chars[line_start..run.start].iter().collect();
+        let mut text = String::new();
+        // Chars of this run on the current line, which are all a substitution may take back
+        let mut taken = 0;
+
+        for (i, &c) in chars[run.clone()].iter().enumerate() {
+            if c == '\n' {
+                line.clear();
+                text.push(c);
+                taken = 0;
+                continue;
+            }
+            let after: String = chars[run.start + i + 1..].iter().take(8).take_while(|c| **c != '\n').collect();
+            match substitute(c, &line, &after, options) {
+                Some(replacement) if replacement.remove <= taken => {
+                    for _ in 0..replacement.remove {
+                        line.pop();
+                        text.pop();
+                    }
+                    line.push_str(&replacement.text);
+                    text.push_str(&replacement.text);
+                    taken = taken + replacement.text.chars().count() - replacement.remove;
+                }
+                _ => {
+                    line.push(c);
+                    text.push(c);
+                    taken += 1;
+                }
+            }
+        }
+
+        if !chars[run.clone()].iter().copied().eq(text.chars()) {
+            edits.push(Edit { range: run, text });
+        }
+    }
+    edits
+}
+
+/// A markdown document with typographic punctuation throughout its prose
+pub fn smarten(markdown: &str, options: &Options) -> String {
+    let mut chars: Vec<char> = markdown.chars().collect();
+    for edit in smarten_edits(markdown, options).into_iter().rev() {
+        chars.splice(edit.range, edit.text.chars());
+    }
+    chars.into_iter().collect()
+}
+
+/// Where the prose is, in chars: the text of the document outside code and
+/// HTML
+fn prose_runs(markdown: &str) -> Vec<Range<usize>> {
+    let mut offsets = CharOffsets::new(markdown);
+    let mut runs: Vec<Range<usize>> = Vec::new();
+    let mut in_code = 0;
+    for (event, range) in Parser::new_ext(markdown, parser_options()).into_offset_iter() {
+        match event {
+            Event::Start(Tag::CodeBlock(_)) => in_code += 1,
+            Event::End(TagEnd::CodeBlock) => in_code -= 1,
+            Event::Text(_) if in_code == 0 => {
+                let run = offsets.at(range.start)..offsets.at(range.end);
+                // Text the parser split up is one run again, so `--` stays together
+                match runs.last_mut() {
+                    Some(last) if last.end == run.start => last.end = run.end,
+                    _ => runs.push(run),
+                }
+            }
+            _ => {}
+        }
+    }
+    runs
+}
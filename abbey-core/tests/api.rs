@@ -0,0 +1,332 @@
+mod common;
+
+use abbey_core::api::{self, Action, Change, Kind, Request, Response, Server};
+use abbey_core::data::{ApiSettings, Flow, Folder, Project, Settings, Storage};
+use common::{composition, library};
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::sync::Mutex;
+use std::time::Duration;
+
+fn send(storage: &Storage, method: &str, target: &str, body: Option<Value>) -> (Response, Vec<Change>) {
+    let mut request = Request::new(method, target);
+    if let Some(body) = body {
+        request = request.with_body(body.to_string());
+    }
+    api::handle(storage, &request)
+}
+
+fn body(response: &Response) -> Value {
+    serde_json::from_slice(&response.body).unwrap()
+}
+
+#[test]
+fn composition_crud() {
+    let (_dir, storage) = library();
+
+    let (response, changes) = send(
+        &storage,
+        "POST",
+        "/api/compositions",
+        Some(json!({ "title": "Letters", "content": "Dear reader" })),
+    );
+    assert_eq!(response.status, 201);
+    let id = body(&response)["id"].as_str().unwrap().to_string();
+    assert_eq!(changes, vec![Change::new(Kind::Composition, Action::Created, &id)]);
+    assert!(storage.compositions_dir().join("Letters.md").exists());
+
+    let (response, _) = send(&storage, "PATCH", &format!("/api/compositions/{}", id), Some(json!({ "content": "Dear reader, hello" })));
+    assert_eq!(response.status, 200);
+    assert_eq!(body(&response)["word_count"], 3);
+
+    let (response, _) = send(&storage, "GET", "/api/compositions", None);
+    let list = body(&response);
+    assert_eq!(list.as_array().unwrap().len(), 1);
+    assert_eq!(list[0]["title"], "Letters");
+    assert!(list[0].get("content").is_none());
+
+    // Deleting archives unless asked to remove it for good
+    let (response, _) = send(&storage, "DELETE", &format!("/api/compositions/{}", id), None);
+    assert_eq!(response.status, 204);
+    assert!(storage.load_compositions().unwrap()[0].archived);
+    let (response, _) = send(&storage, "GET", "/api/compositions?archived=true", None);
+    assert_eq!(body(&response).as_array().unwrap().len(), 1);
+
+    let (response, changes) = send(&storage, "DELETE", &format!("/api/compositions/{}?permanent=true", id), None);
+    assert_eq!(response.status, 204);
+    assert_eq!(changes[0].action, Action::Deleted);
+    assert!(storage.load_compositions().unwrap().is_empty());
+    assert_eq!(send(&storage, "GET", &format!("/api/compositions/{}", id), None).0.status, 404);
+}
+
+#[test]
+fn bad_requests_are_explained() {
+    let (_dir, storage) = library();
+    let comp = composition("Essay", "");
+    storage.save_composition(&comp).unwrap();
+    let path = format!("/api/compositions/{}", comp.id);
+
+    let (response, changes) = send(&storage, "PATCH", &path, Some(json!({ "titel": "typo" })));
+    assert_eq!(response.status, 400);
+    assert!(body(&response)["error"].as_str().unwrap().contains("titel"));
+    assert!(changes.is_empty());
+
+    assert_eq!(send(&storage, "PATCH", &path, Some(json!({ "title": " " }))).0.status, 400);
+    assert_eq!(send(&storage, "PATCH", &path, Some(json!({ "folder_id": "nope" }))).0.status, 400);
+    assert_eq!(send(&storage, "PUT", &path, None).0.status, 404);
+    assert_eq!(send(&storage, "GET", "/elsewhere", None).0.status, 404);
+}
+
+#[test]
+fn renaming_rewrites_links_to_the_composition() {
+    let (_dir, storage) = library();
+    let target = composition("Draft", "");
+    let linking = composition("Index", "Start at [[Draft]].");
+    storage.save_compositions(&[target.clone(), linking.clone()]).unwrap();
+
+    let path = format!("/api/compositions/{}", target.id);
+    let (response, changes) = send(&storage, "PATCH", &path, Some(json!({ "title": "Opening" })));
+    assert_eq!(response.status, 200);
+    assert_eq!(
+        changes,
+        vec![
+            Change::new(Kind::Composition, Action::Updated, &target.id),
+            Change::new(Kind::Composition, Action::Updated, &linking.id),
+        ]
+    );
+    let compositions = storage.load_compositions().unwrap();
+    let linking = compositions.iter().find(|c| c.id == linking.id).unwrap();
+    assert_eq!(linking.content, "Start at [[Opening]].");
+}
+
+#[test]
+fn read_only_library_refuses_writes() {
+    let (dir, storage) = library();
+    storage.save_composition(&composition("Essay", "text")).unwrap();
+    drop(storage);
+    let storage = Storage::open_read_only(dir.path().to_path_buf());
+
+    assert_eq!(send(&storage, "GET", "/api/compositions", None).0.status, 200);
+    let (response, changes) = send(&storage, "POST", "/api/compositions", Some(json!({ "content": "x" })));
+    assert_eq!(response.status, 409);
+    assert!(changes.is_empty());
+    assert_eq!(body(&send(&storage, "GET", "/api", None).0)["read_only"], true);
+}
+
+#[test]
+fn notes_belong_to_their_composition() {
+    let (_dir, storage) = library();
+    let comp = composition("Essay", "");
+    storage.save_composition(&comp).unwrap();
+    let notes = format!("/api/compositions/{}/notes", comp.id);
+
+    let (response, changes) = send(&storage, "POST", &notes, Some(json!({ "content": "check dates" })));
+    assert_eq!(response.status, 201);
+    let note_id = body(&response)["id"].as_str().unwrap().to_string();
+    assert_eq!(changes[0].composition_id.as_deref(), Some(comp.id.as_str()));
+
+    let note = format!("{}/{}", notes, note_id);
+    assert_eq!(send(&storage, "PATCH", &note, Some(json!({ "content": "dates checked" }))).0.status, 200);
+    assert_eq!(body(&send(&storage, "GET", &notes, None).0)[0]["content"], "dates checked");
+
+    assert_eq!(send(&storage, "DELETE", &note, None).0.status, 204);
+    assert_eq!(send(&storage, "DELETE", &note, None).0.status, 404);
+    assert!(storage.load_compositions().unwrap()[0].notes.is_empty());
+}
+
+#[test]
+fn deleting_a_folder_keeps_its_compositions() {
+    let (_dir, storage) = library();
+    let folder = Folder::new("Drafts".to_string());
+    storage.save_folders(std::slice::from_ref(&folder)).unwrap();
+    let mut comp = composition("Essay", "");
+    comp.folder_id = Some(folder.id.clone());
+    storage.save_composition(&comp).unwrap();
+
+    let (response, _) = send(&storage, "PATCH", &format!("/api/folders/{}", folder.id), Some(json!({ "name": "Old drafts" })));
+    assert_eq!(body(&response)["name"], "Old drafts");
+
+    let (response, changes) = send(&storage, "DELETE", &format!("/api/folders/{}", folder.id), None);
+    assert_eq!(response.status, 204);
+    assert_eq!(
+        changes,
+        vec![
+            Change::new(Kind::Composition, Action::Updated, &comp.id),
+            Change::new(Kind::Folder, Action::Deleted, &folder.id),
+        ]
+    );
+    assert!(storage.load_folders().unwrap().is_empty());
+    assert_eq!(storage.load_compositions().unwrap()[0].folder_id, None);
+}
+
+#[test]
+fn projects_and_exports() {
+    let (_dir, storage) = library();
+    let first = composition("One", "First part.");
+    let second = composition("Two", "Second part.");
+    storage.save_compositions(&[first.clone(), second.clone()]).unwrap();
+
+    let (response, _) = send(
+        &storage,
+        "POST",
+        "/api/projects",
+        Some(json!({ "title": "Book", "composition_ids": [second.id, first.id] })),
+    );
+    assert_eq!(response.status, 201);
+    let id = body(&response)["id"].as_str().unwrap().to_string();
+
+    let (response, _) = send(&storage, "GET", &format!("/api/projects/{}/export", id), None);
+    assert!(response.content_type.starts_with("text/markdown"));
+    let markdown = String::from_utf8(response.body).unwrap();
+    assert!(markdown.find("Second part.").unwrap() < markdown.find("First part.").unwrap());
+
+    let (response, _) = send(&storage, "GET", &format!("/api/compositions/{}/export?format=html", first.id), None);
+    assert!(response.content_type.starts_with("text/html"));
+    assert!(String::from_utf8(response.body).unwrap().contains("<p>First part.</p>"));
+    assert_eq!(send(&storage, "GET", &format!("/api/projects/{}/export?format=pdf", id), None).0.status, 400);
+
+    // Removing a composition for good takes it out of its projects too
+    let (_, changes) = send(&storage, "DELETE", &format!("/api/compositions/{}?permanent=true", second.id), None);
+    assert!(changes.contains(&Change::new(Kind::Project, Action::Updated, &id)));
+    let projects: Vec<Project> = storage.load_projects().unwrap();
+    assert_eq!(projects[0].composition_ids, vec![first.id.clone()]);
+
+    assert_eq!(send(&storage, "DELETE", &format!("/api/projects/{}", id), None).0.status, 204);
+    assert!(storage.load_projects().unwrap().is_empty());
+}
+
+#[test]
+fn flows_and_stats() {
+    let (_dir, storage) = library();
+    let mut old = Flow::new(10);
+    old.created_at = chrono::Utc::now() - chrono::Duration::days(30);
+    old.content = "long ago".to_string();
+    let mut recent = Flow::new(5);
+    recent.content = "just now".to_string();
+    storage.save_flows(&[old, recent.clone()]).unwrap();
+
+    let flows = body(&send(&storage, "GET", "/api/flows?since=7d", None).0);
+    assert_eq!(flows.as_array().unwrap().len(), 1);
+    assert_eq!(flows[0]["id"], recent.id.as_str());
+    assert_eq!(send(&storage, "GET", "/api/flows?since=someday", None).0.status, 400);
+    assert_eq!(send(&storage, "GET", "/api/flows?since=99999999999d", None).0.status, 400);
+
+    let stats = body(&send(&storage, "GET", "/api/stats", None).0);
+    assert_eq!(stats["flows"], 2);
+    assert_eq!(stats["flow_words"], 4);
+}
+
+#[test]
+fn requests_parse_from_the_wire() {
+    let raw = "PATCH /api/compositions/abc?archived=all&q=two+words%21 HTTP/1.1\r\n\
+               Host: localhost\r\n\
+               Authorization: Bearer secret\r\n\
+               Content-Length: 2\r\n\r\n{}";
+    let request = Request::read_from(&mut raw.as_bytes()).unwrap();
+
+    assert_eq!(request.method, "PATCH");
+    assert_eq!(request.segments(), vec!["api", "compositions", "abc"]);
+    assert_eq!(request.query("q"), Some("two words!"));
+    assert_eq!(request.header("AUTHORIZATION"), Some("Bearer secret"));
+    assert_eq!(request.body, b"{}");
+
+    let truncated = "POST /api HTTP/1.1\r\nContent-Length: 10\r\n\r\n{}";
+    assert_eq!(Request::read_from(&mut truncated.as_bytes()).unwrap_err().status, 400);
+    let chunked = "POST /api HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n";
+    assert_eq!(Request::read_from(&mut chunked.as_bytes()).unwrap_err().status, 411);
+}
+
+#[test]
+fn settings_without_api_section_load() {
+    let settings: Settings = serde_json::from_str(
+        r#"{"theme":"dark","font_size":16,"line_height":1.5,
+            "microblog":{"endpoint":"","api_key":"","blog_id":null},
+            "last_opened_composition":null}"#,
+    )
+    .unwrap();
+    assert!(!settings.api.enabled);
+    assert_eq!(settings.api.port, ApiSettings::DEFAULT_PORT);
+    assert_ne!(ApiSettings::generate_token(), ApiSettings::generate_token());
+}
+
+// ========== Over the network ==========
+
+fn start(storage: Storage) -> Server {
+    let storage = Mutex::new(storage);
+    Server::start(0, "secret", move |request| api::handle(&storage.lock().unwrap(), request)).unwrap()
+}
+
+/// Send a raw request and return the status and body
+fn http(server: &Server, method: &str, path: &str, token: Option<&str>, body: &str) -> (u16, String) {
+    let mut stream = TcpStream::connect(server.local_addr()).unwrap();
+    let auth = token.map(|t| format!("Authorization: Bearer {}\r\n", t)).unwrap_or_default();
+    write!(
+        stream,
+        "{} {} HTTP/1.1\r\nHost: localhost\r\n{}Content-Length: {}\r\n\r\n{}",
+        method,
+        path,
+        auth,
+        body.len(),
+        body
+    )
+    .unwrap();
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let status = response[9..12].parse().unwrap();
+    let body = response.split_once("\r\n\r\n").unwrap().1.to_string();
+    (status, body)
+}
+
+#[test]
+fn server_requires_the_token() {
+    let (_dir, storage) = library();
+    let server = start(storage);
+
+    assert!(server.local_addr().ip().is_loopback());
+    assert_eq!(http(&server, "GET", "/api", None, "").0, 401);
+    assert_eq!(http(&server, "GET", "/api", Some("secreT"), "").0, 401);
+
+    let (status, body) = http(&server, "GET", "/api", Some("secret"), "");
+    assert_eq!(status, 200);
+    assert!(body.contains("\"name\": \"abbey\""));
+
+    assert!(Server::start(0, " ", |_| (Response::no_content(), Vec::new())).is_err());
+}
+
+#[test]
+fn event_stream_announces_changes() {
+    let (_dir, storage) = library();
+    let server = start(storage);
+
+    let mut events = TcpStream::connect(server.local_addr()).unwrap();
+    events.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    write!(events, "GET /api/events HTTP/1.1\r\nAuthorization: Bearer secret\r\n\r\n").unwrap();
+    let mut events = BufReader::new(events);
+
+    // Subscribed once the stream has said hello
+    let mut line = String::new();
+    while line != ": connected\n" {
+        line.clear();
+        events.read_line(&mut line).unwrap();
+    }
+
+    let (status, body) = http(&server, "POST", "/api/folders", Some("secret"), r#"{"name":"Ideas"}"#);
+    assert_eq!(status, 201);
+    let folder: Folder = serde_json::from_str(&body).unwrap();
+
+    // Changes made elsewhere, like in the app, go out the same way
+    server.events().publish(Change::new(Kind::Flow, Action::Created, "f1"));
+
+    let mut received = Vec::new();
+    while received.len() < 2 {
+        line.clear();
+        events.read_line(&mut line).unwrap();
+        if let Some(data) = line.strip_prefix("data: ") {
+            received.push(serde_json::from_str::<Value>(data).unwrap());
+        }
+    }
+    assert_eq!(received[0], json!({ "kind": "folder", "action": "created", "id": folder.id }));
+    assert_eq!(received[1], json!({ "kind": "flow", "action": "created", "id": "f1" }));
+}
@@ -0,0 +1,222 @@
+//! Drives the automation interface over a private session bus, as a script
+//! would. Needs `dbus-daemon`; run with `cargo test -p abbey-core --features dbus`.
+#![cfg(feature = "dbus")]
+
+mod common;
+
+use abbey_core::data::{Composition, Note};
+use abbey_core::dbus::{self, AutomationError, AutomationHandler, AUTOMATION_INTERFACE};
+use abbey_core::search;
+use abbey_core::stats::LibraryStats;
+use gio::glib;
+use gio::prelude::*;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+const OBJECT_PATH: &str = "/app/abbey/Abbey/Automation";
+
+/// A library kept in memory, standing in for the app
+#[derive(Default)]
+struct Library {
+    compositions: RefCell<Vec<Composition>>,
+    flows_started: RefCell<Vec<u32>>,
+    captures: RefCell<Vec<u32>>,
+    read_only: bool,
+}
+
+impl Library {
+    fn with_composition(&self, query: &str, f: impl FnOnce(&mut Composition) -> String) -> Result<String, AutomationError> {
+        if self.read_only {
+            return Err(AutomationError::ReadOnly);
+        }
+
+        let mut compositions = self.compositions.borrow_mut();
+        let id = match search::find_by_id_or_title(&compositions, query, |c| &c.id, |c| &c.title) {
+            Ok(Some(comp)) => comp.id.clone(),
+            Ok(None) => return Err(AutomationError::NotFound(format!("no composition matches \"{}\"", query))),
+            Err(matches) => {
+                return Err(AutomationError::Ambiguous {
+                    query: query.to_string(),
+                    matches: matches.iter().map(|c| c.title.clone()).collect(),
+                })
+            }
+        };
+        let comp = compositions.iter_mut().find(|c| c.id == id).unwrap();
+        Ok(f(comp))
+    }
+}
+
+impl AutomationHandler for Library {
+    fn create_composition(&self, title: Option<String>, text: String) -> Result<String, AutomationError> {
+        if self.read_only {
+            return Err(AutomationError::ReadOnly);
+        }
+        let mut comp = Composition::new();
+        if let Some(title) = title {
+            comp.title = title;
+        }
+        comp.content = text;
+        comp.update_word_count();
+        let id = comp.id.clone();
+        self.compositions.borrow_mut().insert(0, comp);
+        Ok(id)
+    }
+
+    fn append_to_composition(&self, composition: &str, text: &str) -> Result<String, AutomationError> {
+        self.with_composition(composition, |comp| {
+            comp.append_paragraph(text);
+            comp.id.clone()
+        })
+    }
+
+    fn add_note(&self, composition: &str, text: &str) -> Result<String, AutomationError> {
+        self.with_composition(composition, |comp| {
+            let note = Note::new(text.to_string());
+            let id = note.id.clone();
+            comp.notes.push(note);
+            id
+        })
+    }
+
+    fn start_flow(&self, minutes: u32) -> Result<(), AutomationError> {
+        if !self.flows_started.borrow().is_empty() {
+            return Err(AutomationError::Busy("a flow session is already running".to_string()));
+        }
+        self.flows_started.borrow_mut().push(minutes);
+        Ok(())
+    }
+
+    fn stats(&self) -> Result<LibraryStats, AutomationError> {
+        Ok(LibraryStats::new(&self.compositions.borrow(), &[], &[]))
+    }
+
+    fn show_quick_capture(&self, timestamp: u32) {
+        self.captures.borrow_mut().push(timestamp);
+    }
+}
+
+fn with_library(library: Library, test: impl FnOnce(&gio::DBusConnection, &str, &Library)) {
+    common::with_bus(|service, client, name| {
+        let library = Rc::new(library);
+        let registration =
+            dbus::register_automation(service, OBJECT_PATH, library.clone()).expect("register automation");
+
+        test(client, name, &library);
+
+        service.unregister_object(registration).unwrap();
+    });
+}
+
+fn call(client: &gio::DBusConnection, name: &str, method: &str, args: glib::Variant) -> Result<glib::Variant, glib::Error> {
+    common::call(client, name, OBJECT_PATH, AUTOMATION_INTERFACE, method, args)
+}
+
+/// The D-Bus error name of a failed call
+fn error_name(result: Result<glib::Variant, glib::Error>) -> String {
+    let error = result.expect_err("call should fail");
+    gio::DBusError::remote_error(&error).expect("a D-Bus error").to_string()
+}
+
+#[test]
+fn create_append_and_note_by_title() {
+    with_library(Library::default(), |client, name, library| {
+        let reply = call(client, name, "CreateComposition", ("Inbox", "first thought").to_variant()).unwrap();
+        let (id,) = reply.get::<(String,)>().unwrap();
+
+        let reply = call(client, name, "AppendToComposition", ("inbox", "second thought\n").to_variant()).unwrap();
+        assert_eq!(reply.get::<(String,)>().unwrap().0, id);
+
+        let reply = call(client, name, "AddNote", (&id[..8], "check this").to_variant()).unwrap();
+        let (note_id,) = reply.get::<(String,)>().unwrap();
+
+        let compositions = library.compositions.borrow();
+        assert_eq!(compositions[0].content, "first thought\n\nsecond thought");
+        assert_eq!(compositions[0].word_count, 4);
+        assert_eq!(compositions[0].notes[0].id, note_id);
+        assert_eq!(compositions[0].notes[0].content, "check this");
+    });
+}
+
+#[test]
+fn blank_title_gets_the_default() {
+    with_library(Library::default(), |client, name, library| {
+        call(client, name, "CreateComposition", ("  ", "text").to_variant()).unwrap();
+        assert!(!library.compositions.borrow()[0].title.trim().is_empty());
+    });
+}
+
+#[test]
+fn lookup_errors_have_their_own_names() {
+    with_library(Library::default(), |client, name, _| {
+        call(client, name, "CreateComposition", ("Draft", "a").to_variant()).unwrap();
+        call(client, name, "CreateComposition", ("draft", "b").to_variant()).unwrap();
+
+        assert_eq!(
+            error_name(call(client, name, "AppendToComposition", ("Draft", "more").to_variant())),
+            "app.abbey.Abbey.Error.Ambiguous"
+        );
+        assert_eq!(
+            error_name(call(client, name, "AddNote", ("Nothing", "note").to_variant())),
+            "app.abbey.Abbey.Error.NotFound"
+        );
+        assert_eq!(
+            error_name(call(client, name, "AppendToComposition", ("Draft", "  \n").to_variant())),
+            "org.freedesktop.DBus.Error.InvalidArgs"
+        );
+    });
+}
+
+#[test]
+fn read_only_library_refuses_changes() {
+    let library = Library {
+        read_only: true,
+        ..Library::default()
+    };
+    with_library(library, |client, name, library| {
+        assert_eq!(
+            error_name(call(client, name, "CreateComposition", ("", "text").to_variant())),
+            "app.abbey.Abbey.Error.ReadOnly"
+        );
+        assert!(library.compositions.borrow().is_empty());
+    });
+}
+
+#[test]
+fn flows_need_a_sensible_duration() {
+    with_library(Library::default(), |client, name, library| {
+        assert_eq!(
+            error_name(call(client, name, "StartFlow", (0u32,).to_variant())),
+            "org.freedesktop.DBus.Error.InvalidArgs"
+        );
+        assert_eq!(
+            error_name(call(client, name, "StartFlow", (dbus::MAX_FLOW_MINUTES + 1,).to_variant())),
+            "org.freedesktop.DBus.Error.InvalidArgs"
+        );
+
+        call(client, name, "StartFlow", (15u32,).to_variant()).unwrap();
+        assert_eq!(
+            error_name(call(client, name, "StartFlow", (5u32,).to_variant())),
+            "app.abbey.Abbey.Error.Busy"
+        );
+        assert_eq!(*library.flows_started.borrow(), vec![15]);
+    });
+}
+
+#[test]
+fn stats_and_quick_capture() {
+    with_library(Library::default(), |client, name, library| {
+        call(client, name, "CreateComposition", ("One", "three words here").to_variant()).unwrap();
+
+        let reply = call(client, name, "GetStats", ().to_variant()).unwrap();
+        let (stats,) = reply.get::<(HashMap<String, glib::Variant>,)>().unwrap();
+        assert_eq!(stats["compositions"].get::<u64>(), Some(1));
+        assert_eq!(stats["composition_words"].get::<u64>(), Some(3));
+        assert_eq!(stats["flow_streak_days"].get::<u64>(), Some(0));
+
+        call(client, name, "ShowQuickCapture", (7u32,).to_variant()).unwrap();
+        assert_eq!(*library.captures.borrow(), vec![7]);
+        assert!(call(client, name, "ShowQuickCapture", ().to_variant()).is_err());
+    });
+}
+
@@ -0,0 +1,399 @@
+mod common;
+
+use abbey_core::cli::{self, Command};
+use abbey_core::data::{Composition, Flow, Project, Storage};
+use common::{composition, library};
+use chrono::{Duration, Local, TimeZone, Utc};
+use serde_json::Value;
+use std::fs;
+use std::io::Read;
+use tempfile::TempDir;
+
+struct Output {
+    code: i32,
+    out: String,
+    err: String,
+}
+
+/// Run `abbey <args> --library <dir>` with optional piped input
+fn abbey(dir: &TempDir, args: &[&str], stdin: Option<&str>) -> Output {
+    let mut args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+    args.push("--library".to_string());
+    args.push(dir.path().display().to_string());
+
+    let mut input = stdin.map(|text| text.as_bytes());
+    let mut out = Vec::new();
+    let mut err = Vec::new();
+    let code = cli::run_with(&args, input.as_mut().map(|i| i as &mut dyn Read), &mut out, &mut err);
+
+    Output {
+        code,
+        out: String::from_utf8(out).unwrap(),
+        err: String::from_utf8(err).unwrap(),
+    }
+}
+
+fn json(output: &Output) -> Value {
+    assert_eq!(output.code, 0, "stderr: {}", output.err);
+    serde_json::from_str(&output.out).unwrap()
+}
+
+/// A populated library, released so the commands can take the lock
+fn sample_library() -> (TempDir, Vec<Composition>) {
+    let (dir, storage) = library();
+    let essay = composition("Essay", "One two three.");
+    let story = composition("Short Story", "Once upon a time");
+    let mut old = composition("Old Draft", "gone");
+    old.archived = true;
+    let compositions = vec![essay, story, old];
+    storage.save_compositions(&compositions).unwrap();
+
+    let mut project = Project::new("Collected".to_string());
+    project.add_composition(compositions[1].id.clone());
+    project.add_composition(compositions[0].id.clone());
+    storage.save_projects(&[project]).unwrap();
+
+    let mut flow = Flow::new(10);
+    flow.content = "free writing here".to_string();
+    flow.actual_duration_seconds = 600;
+    storage.append_flow(&flow).unwrap();
+
+    (dir, compositions)
+}
+
+#[test]
+fn recognises_commands() {
+    assert!(cli::is_command("list"));
+    assert!(cli::is_command("export-project"));
+    assert!(!cli::is_command("draft.md"));
+    assert!(!cli::is_command("--new"));
+}
+
+#[test]
+fn parses_options_anywhere() {
+    let args: Vec<String> = ["flows", "--json", "--since", "7d"].iter().map(|a| a.to_string()).collect();
+    let (command, options) = cli::parse(&args).unwrap();
+    assert!(options.json);
+    assert!(matches!(command, Command::Flows { since: Some(_) }));
+
+    let args: Vec<String> = ["serve", "--port", "8080"].iter().map(|a| a.to_string()).collect();
+    assert_eq!(cli::parse(&args).unwrap().0, Command::Serve { port: Some(8080) });
+}
+
+#[test]
+fn rejects_bad_usage() {
+    let dir = TempDir::new().unwrap();
+    assert_eq!(abbey(&dir, &["list", "extra"], None).code, 2);
+    assert_eq!(abbey(&dir, &["show"], None).code, 2);
+    assert_eq!(abbey(&dir, &["list", "--bogus"], None).code, 2);
+    assert_eq!(abbey(&dir, &["export-project", "x", "--format", "pdf"], None).code, 2);
+    assert_eq!(abbey(&dir, &["serve", "--port", "99999"], None).code, 2);
+
+    let output = abbey(&dir, &["flows", "--since", "someday"], None);
+    assert_eq!(output.code, 2);
+    assert!(output.err.contains("someday"));
+    assert_eq!(abbey(&dir, &["flows", "--since", "3ü"], None).code, 2);
+}
+
+#[test]
+fn help_lists_commands() {
+    let dir = TempDir::new().unwrap();
+    let output = abbey(&dir, &["help"], None);
+    assert_eq!(output.code, 0);
+    for command in ["list", "show", "new", "append", "export-project", "flows", "stats", "check", "serve"] {
+        assert!(output.out.contains(command));
+    }
+}
+
+#[test]
+fn missing_library_is_an_error() {
+    let dir = TempDir::new().unwrap();
+    let mut args = vec!["list".to_string(), "--library".to_string()];
+    args.push(dir.path().join("nowhere").display().to_string());
+    let mut out = Vec::new();
+    let mut err = Vec::new();
+
+    assert_eq!(cli::run_with(&args, None, &mut out, &mut err), 1);
+    assert!(String::from_utf8(err).unwrap().contains("no library found"));
+}
+
+#[test]
+fn list_shows_active_or_archived() {
+    let (dir, compositions) = sample_library();
+
+    let output = abbey(&dir, &["list"], None);
+    assert_eq!(output.code, 0);
+    assert!(output.out.contains("Essay"));
+    assert!(output.out.contains("Short Story"));
+    assert!(!output.out.contains("Old Draft"));
+    assert!(output.out.contains(&compositions[0].id[..8]));
+
+    let archived = json(&abbey(&dir, &["list", "--archived", "--json"], None));
+    assert_eq!(archived.as_array().unwrap().len(), 1);
+    assert_eq!(archived[0]["title"], "Old Draft");
+    assert_eq!(archived[0]["archived"], true);
+}
+
+#[test]
+fn list_json_has_summaries() {
+    let (dir, _) = sample_library();
+    let list = json(&abbey(&dir, &["list", "--json"], None));
+
+    assert_eq!(list[0]["title"], "Essay");
+    assert_eq!(list[0]["words"], 3);
+    assert!(list[0].get("content").is_none());
+}
+
+#[test]
+fn show_finds_by_id_prefix_or_title() {
+    let (dir, compositions) = sample_library();
+
+    let by_title = abbey(&dir, &["show", "short story"], None);
+    assert_eq!(by_title.code, 0);
+    assert_eq!(by_title.out, "# Short Story\n\nOnce upon a time\n");
+
+    let by_prefix = json(&abbey(&dir, &["show", &compositions[0].id[..6], "--json"], None));
+    assert_eq!(by_prefix["id"], compositions[0].id.as_str());
+    assert_eq!(by_prefix["content"], "One two three.");
+
+    let missing = abbey(&dir, &["show", "Nope"], None);
+    assert_eq!(missing.code, 1);
+    assert!(missing.err.contains("no composition matches"));
+}
+
+#[test]
+fn show_reports_ambiguous_titles() {
+    let (dir, storage) = library();
+    storage
+        .save_compositions(&[composition("Twin", "a"), composition("Twin", "b")])
+        .unwrap();
+    drop(storage);
+
+    let output = abbey(&dir, &["show", "Twin"], None);
+    assert_eq!(output.code, 1);
+    assert!(output.err.contains("matches more than one"));
+}
+
+#[test]
+fn new_takes_text_from_arguments_or_stdin() {
+    let (dir, storage) = library();
+    drop(storage);
+
+    let created = json(&abbey(&dir, &["new", "--title", "Ideas", "first", "thought", "--json"], None));
+    assert_eq!(created["title"], "Ideas");
+    assert_eq!(created["content"], "first thought");
+    assert_eq!(created["word_count"], 2);
+
+    let piped = abbey(&dir, &["new", "--title", "Piped"], Some("from a pipe\n"));
+    assert_eq!(piped.code, 0);
+    assert!(piped.out.starts_with("Created \"Piped\""));
+
+    let stored = Storage::open_read_only(dir.path().to_path_buf()).load_compositions().unwrap();
+    assert_eq!(stored.len(), 2);
+    assert_eq!(stored[0].title, "Piped");
+    assert_eq!(stored[0].content, "from a pipe");
+    assert!(dir.path().join("compositions").join("Ideas.md").exists());
+}
+
+#[test]
+fn append_adds_a_paragraph() {
+    let (dir, compositions) = sample_library();
+
+    let output = abbey(&dir, &["append", "Essay"], Some("Four five.\n"));
+    assert_eq!(output.code, 0, "stderr: {}", output.err);
+    assert!(output.out.contains("Appended 2 words"));
+
+    let stored = Storage::open_read_only(dir.path().to_path_buf()).load_compositions().unwrap();
+    let essay = stored.iter().find(|c| c.id == compositions[0].id).unwrap();
+    assert_eq!(essay.content, "One two three.\n\nFour five.");
+    assert_eq!(essay.word_count, 5);
+    assert!(essay.updated_at > compositions[0].updated_at);
+
+    assert_eq!(abbey(&dir, &["append", "Essay"], None).code, 2);
+}
+
+#[test]
+fn writing_commands_respect_the_app_lock() {
+    let (dir, _) = sample_library();
+
+    // A lock from an instance on another machine that is still alive
+    let now = Utc::now();
+    fs::write(
+        dir.path().join(".abbey.lock"),
+        serde_json::json!({
+            "pid": 1, "host": "desk", "acquired_at": now, "heartbeat_at": now, "token": "x",
+        })
+        .to_string(),
+    )
+    .unwrap();
+
+    let output = abbey(&dir, &["append", "Essay", "more"], None);
+    assert_eq!(output.code, 1);
+    assert!(output.err.contains("already open"));
+
+    // Reading still works
+    assert_eq!(abbey(&dir, &["list"], None).code, 0);
+}
+
+#[test]
+fn export_project_to_stdout_or_file() {
+    let (dir, _) = sample_library();
+
+    let md = abbey(&dir, &["export-project", "collected"], None);
+    assert_eq!(md.code, 0);
+    assert!(md.out.starts_with("# Collected\n"));
+    assert!(md.out.find("Once upon a time").unwrap() < md.out.find("One two three.").unwrap());
+
+    let target = dir.path().join("book.html");
+    let html = abbey(
+        &dir,
+        &["export-project", "Collected", "--format", "html", "--output", target.to_str().unwrap()],
+        None,
+    );
+    assert_eq!(html.code, 0);
+    let written = fs::read_to_string(target).unwrap();
+    assert!(written.starts_with("<!DOCTYPE html>"));
+    assert!(written.contains("<h2>Essay</h2>"));
+
+    assert_eq!(abbey(&dir, &["export-project", "Missing"], None).code, 1);
+}
+
+#[test]
+fn export_project_with_smart_punctuation() {
+    let (dir, storage) = library();
+    let quoted = composition("Quoted", "\"Wait---it's `\"code\"`...\"");
+    storage.save_compositions(std::slice::from_ref(&quoted)).unwrap();
+    let mut project = Project::new("Smart".to_string());
+    project.add_composition(quoted.id.clone());
+    storage.save_projects(&[project]).unwrap();
+    drop(storage);
+
+    let plain = abbey(&dir, &["export-project", "Smart"], None);
+    assert!(plain.out.contains("\"Wait---it's"));
+
+    let smart = abbey(&dir, &["export-project", "Smart", "--smart"], None);
+    assert_eq!(smart.code, 0);
+    assert!(smart.out.contains("“Wait—it’s `\"code\"`…”"));
+}
+
+#[test]
+fn flows_filters_by_date() {
+    let (dir, storage) = library();
+    let mut recent = Flow::new(15);
+    recent.content = "today's words".to_string();
+    let mut old = Flow::new(5);
+    old.content = "long ago".to_string();
+    old.created_at = Utc::now() - Duration::days(30);
+    storage.save_flows(&[recent, old]).unwrap();
+    drop(storage);
+
+    let all = json(&abbey(&dir, &["flows", "--json"], None));
+    assert_eq!(all.as_array().unwrap().len(), 2);
+
+    let week = json(&abbey(&dir, &["flows", "--since", "1w", "--json"], None));
+    assert_eq!(week.as_array().unwrap().len(), 1);
+    assert_eq!(week[0]["content"], "today's words");
+
+    let human = abbey(&dir, &["flows", "--since", "today"], None);
+    assert!(human.out.contains("today's words"));
+    assert!(human.out.contains("1 sessions, 2 words"));
+}
+
+#[test]
+fn parse_since_accepts_dates_and_offsets() {
+    let now = Local.with_ymd_and_hms(2026, 5, 20, 15, 30, 0).unwrap();
+    let midnight = |d: u32| {
+        Local
+            .with_ymd_and_hms(2026, 5, d, 0, 0, 0)
+            .unwrap()
+            .with_timezone(&Utc)
+    };
+
+    assert_eq!(cli::parse_since("today", now), Some(midnight(20)));
+    assert_eq!(cli::parse_since("yesterday", now), Some(midnight(19)));
+    assert_eq!(cli::parse_since("3d", now), Some(midnight(17)));
+    assert_eq!(cli::parse_since("2w", now), Some(midnight(6)));
+    assert_eq!(cli::parse_since("2026-05-01", now), Some(midnight(1)));
+    assert_eq!(
+        cli::parse_since("2026-05-01T10:00:00Z", now),
+        Some(Utc.with_ymd_and_hms(2026, 5, 1, 10, 0, 0).unwrap())
+    );
+    assert_eq!(cli::parse_since("soon", now), None);
+    assert_eq!(cli::parse_since("d", now), None);
+    assert_eq!(cli::parse_since("-5d", now), None);
+    // Too far back to be a date rather than panicking
+    assert_eq!(cli::parse_since("99999999999d", now), None);
+    assert_eq!(cli::parse_since("9999999999999999w", now), None);
+    // Ending in a char wider than a byte
+    assert_eq!(cli::parse_since("3ü", now), None);
+    assert_eq!(cli::parse_since("ü", now), None);
+}
+
+#[test]
+fn stats_summarise_the_library() {
+    let (dir, _) = sample_library();
+
+    let stats = json(&abbey(&dir, &["stats", "--json"], None));
+    assert_eq!(stats["compositions"], 2);
+    assert_eq!(stats["archived"], 1);
+    assert_eq!(stats["composition_words"], 7);
+    assert_eq!(stats["flows"], 1);
+    assert_eq!(stats["flow_words"], 3);
+    assert_eq!(stats["flow_streak_days"], 1);
+
+    let human = abbey(&dir, &["stats"], None);
+    assert!(human.out.contains("Compositions  2 (1 archived)"));
+    assert!(human.out.contains("Flows         1 (3 words, 10m)"));
+}
+
+#[test]
+fn check_passes_on_a_healthy_library() {
+    let (dir, _) = sample_library();
+
+    let output = abbey(&dir, &["check"], None);
+    assert_eq!(output.code, 0);
+    assert!(output.out.starts_with("No problems found"));
+}
+
+#[test]
+fn check_reports_problems() {
+    let (dir, storage) = library();
+    let mut comp = composition("Drifted", "three words here");
+    comp.word_count = 99;
+    comp.folder_id = Some("gone".to_string());
+    let mut project = Project::new("Book".to_string());
+    project.add_composition("missing".to_string());
+    storage.save_compositions(&[comp.clone(), comp]).unwrap();
+    storage.save_projects(&[project]).unwrap();
+    drop(storage);
+    fs::write(dir.path().join("flows.json"), "not json").unwrap();
+
+    let report = abbey(&dir, &["check", "--json"], None);
+    assert_eq!(report.code, 1);
+    let report: Value = serde_json::from_str(&report.out).unwrap();
+    let messages: Vec<_> = report["issues"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|i| format!("{}: {}", i["severity"].as_str().unwrap(), i["message"].as_str().unwrap()))
+        .collect();
+
+    assert!(messages.iter().any(|m| m.starts_with("error: flows.json is damaged")));
+    assert!(messages.iter().any(|m| m.starts_with("error: Composition id") && m.contains("more than once")));
+    assert!(messages.iter().any(|m| m.contains("folder that no longer exists")));
+    assert!(messages.iter().any(|m| m.contains("stored word count of 99")));
+    assert!(messages.iter().any(|m| m.contains("1 missing composition")));
+}
+
+#[test]
+fn check_warns_about_leftovers_without_failing() {
+    let (dir, storage) = library();
+    storage.write_recovery(&composition("Unsaved", "text")).unwrap();
+    drop(storage);
+    fs::write(dir.path().join("compositions.json.tmp"), "[").unwrap();
+
+    let output = abbey(&dir, &["check"], None);
+    assert_eq!(output.code, 0);
+    assert!(output.out.contains("warning: 1 composition(s) have unsaved edits"));
+    assert!(output.out.contains("warning: compositions.json.tmp was left behind"));
+}
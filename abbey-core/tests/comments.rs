@@ -0,0 +1,126 @@
+use abbey_core::data::{Comment, CommentReply, Composition, Project};
+use abbey_core::export::{self, ExportFormat};
+use abbey_core::utils::anchors::{follow, nearest};
+
+fn commented(content: &str, quote: &str, text: &str) -> Composition {
+    let mut comp = Composition::new();
+    comp.title = "Essay".to_string();
+    comp.content = content.to_string();
+    let start = content[..content.find(quote).unwrap()].chars().count();
+    let end = start + quote.chars().count();
+    comp.comments.push(Comment::new(start, end, quote.to_string(), text.to_string()));
+    comp
+}
+
+#[test]
+fn anchors_move_with_edits_around_them() {
+    let old = "The quick fox jumps.";
+    // "quick" is chars 4..9
+    assert_eq!(follow(4..9, "quick", old, "So the quick fox jumps."), 7..12);
+    assert_eq!(follow(4..9, "quick", old, "The quick fox leaps."), 4..9);
+    // Typing at either end stays outside the anchor
+    assert_eq!(follow(4..9, "quick", old, "The very quick fox jumps."), 9..14);
+    assert_eq!(follow(4..9, "quick", old, "The quickest fox jumps."), 4..9);
+}
+
+#[test]
+fn anchors_find_their_quote_again_or_end_up_empty() {
+    // Edited inside: the quote's gone, so the anchor keeps to what's there
+    assert_eq!(follow(4..9, "quick", "The quick fox.", "The quiet fox."), 4..4);
+    // Moved wholesale, as when a paragraph is cut and pasted
+    let moved = follow(0..5, "First", "First. Second.", "Second. First.");
+    assert_eq!(moved, 8..13);
+    assert_eq!(nearest("a b a b a", "a", 5), Some(4..5));
+    assert_eq!(nearest("café café", "café", 9), Some(5..9));
+    assert_eq!(nearest("text", "", 0), None);
+}
+
+#[test]
+fn compositions_keep_comments_on_their_text() {
+    let mut comp = commented("Call me Ishmael. Some years ago.", "Ishmael", "Who?");
+    let old = std::mem::replace(&mut comp.content, "Prologue. Call me Ishmael. Some years ago.".to_string());
+    comp.follow_edit(&old);
+    let comment = &comp.comments[0];
+    assert_eq!((comment.start, comment.end), (18, 25));
+    assert_eq!(comment.quote, "Ishmael");
+
+    // Out of step with the text, as after a crash between saves
+    comp.comments[0].start = 0;
+    comp.comments[0].end = 7;
+    let current = comp.content.clone();
+    comp.follow_edit(&current);
+    assert_eq!((comp.comments[0].start, comp.comments[0].end), (18, 25));
+
+    // Deleted: the comment stays, empty, and remembers what it was on
+    let old = std::mem::replace(&mut comp.content, "Prologue. Call me. Some years ago.".to_string());
+    comp.follow_edit(&old);
+    assert!(comp.comments[0].is_detached());
+    assert_eq!(comp.comments[0].quote, "Ishmael");
+}
+
+#[test]
+fn open_comments_export_as_markdown_footnotes() {
+    let mut comp = commented("A *bold* claim.\n\n```\ncode here\n```\n", "claim", "Source?");
+    comp.comments[0].replies.push(CommentReply::new("Added one.\nSee notes.".to_string()));
+    let mut done = Comment::new(0, 1, "A".to_string(), "Fixed".to_string());
+    done.resolved = true;
+    comp.comments.push(done);
+    comp.comments.push(Comment::new(19, 23, "code".to_string(), "Rename".to_string()));
+
+    let md = export::composition_to_markdown(&comp);
+    assert!(md.contains("A *bold* claim[^comment-1]."), "{}", md);
+    // Not inside the code block, but after it
+    assert!(md.contains("```\ncode here\n```\n[^comment-2]"), "{}", md);
+    assert!(md.contains("[^comment-1]: Source?\n\n    Reply: Added one.\n    See notes."), "{}", md);
+    assert!(md.contains("[^comment-2]: Rename"));
+    assert!(!md.contains("Fixed"));
+
+    let html = export::export_composition(&comp, ExportFormat::Html);
+    assert!(html.contains("footnote-definition"));
+}
+
+#[test]
+fn footnotes_number_through_a_project() {
+    let one = commented("First draft.", "draft", "One");
+    let two = commented("Second draft.", "Second", "Two");
+    let mut project = Project::new("Book".to_string());
+    project.add_composition(one.id.clone());
+    project.add_composition(two.id.clone());
+
+    let md = export::project_to_markdown(&project, &[one, two]);
+    assert!(md.contains("First draft[^comment-1]."));
+    assert!(md.contains("Second[^comment-2] draft."));
+    assert!(md.contains("[^comment-2]: Two"));
+}
+
+#[test]
+fn open_comments_export_as_opendocument_comments() {
+    let mut comp = commented("Some *very* fine & good text.\n\n- item one\n- item two\n", "very* fine", "Too much?");
+    comp.comments[0].replies.push(CommentReply::new("Maybe".to_string()));
+    comp.comments.push(Comment::new(33, 33, "one".to_string(), "Was here".to_string()));
+
+    let odt = export::export_composition(&comp, ExportFormat::Odt);
+    assert!(odt.starts_with("<?xml"));
+    assert!(odt.contains("<dc:title>Essay</dc:title>"));
+    assert!(odt.contains(r#"<text:h text:style-name="Heading_20_1" text:outline-level="1">Essay</text:h>"#));
+    // The comment starts inside the emphasis and ends after "fine"
+    assert!(odt.contains(r#"<text:span text:style-name="Emphasis"><office:annotation office:name="comment-1">"#), "{}", odt);
+    assert!(odt.contains("<text:p>Too much?</text:p><text:p>Reply: Maybe</text:p></office:annotation>very</text:span> fine<office:annotation-end office:name=\"comment-1\"/> &amp; good"), "{}", odt);
+    // An empty anchor is a comment at a point
+    assert!(odt.contains("<office:annotation><dc:date>"));
+    assert!(odt.contains("<text:list-item><text:p text:style-name=\"Text_20_body\">item"));
+    assert_eq!(odt.matches("<office:annotation").count(), odt.matches("</office:annotation>").count() + 1);
+}
+
+#[test]
+fn smartened_opendocument_keeps_comments_on_their_text() {
+    let comp = commented("\"Quoted\" and then the end.", "the end", "Ending");
+    let mut project = Project::new("Book".to_string());
+    project.add_composition(comp.id.clone());
+
+    let options = abbey_core::utils::typography::Options::default();
+    let odt = export::export_project_smartened(&project, &[comp], ExportFormat::Odt, &options);
+    assert!(odt.contains("“Quoted” and then <office:annotation office:name=\"comment-1\">"), "{}", odt);
+    assert!(odt.contains("</office:annotation>the end<office:annotation-end"));
+}
+
@@ -0,0 +1,92 @@
+//! Fixtures shared by the integration tests, and a private session bus for the
+//! D-Bus interface tests
+#![allow(dead_code)]
+
+use abbey_core::data::{Composition, Storage};
+#[cfg(feature = "dbus")]
+use gio::glib;
+#[cfg(feature = "dbus")]
+use gio::prelude::*;
+#[cfg(feature = "dbus")]
+use std::sync::Mutex;
+use tempfile::TempDir;
+
+// Every test bus sets DBUS_SESSION_BUS_ADDRESS for the whole process
+#[cfg(feature = "dbus")]
+static BUS: Mutex<()> = Mutex::new(());
+
+/// A composition with this title and text, its words counted
+pub fn composition(title: &str, content: &str) -> Composition {
+    let mut comp = Composition::new();
+    comp.title = title.to_string();
+    comp.content = content.to_string();
+    comp.update_word_count();
+    comp
+}
+
+/// A new library in a temporary directory, removed when the `TempDir` drops
+pub fn library() -> (TempDir, Storage) {
+    let dir = TempDir::new().unwrap();
+    let storage = Storage::open(dir.path().to_path_buf()).unwrap();
+    (dir, storage)
+}
+
+/// Run `test` on a fresh session bus with a connection for the service, one
+/// for the client, and the service's bus name. Method calls are dispatched on
+/// a main context private to the test.
+#[cfg(feature = "dbus")]
+pub fn with_bus(test: impl FnOnce(&gio::DBusConnection, &gio::DBusConnection, &str)) {
+    let _guard = BUS.lock().unwrap_or_else(|e| e.into_inner());
+    let bus = gio::TestDBus::new(gio::TestDBusFlags::NONE);
+    bus.up();
+    let address = bus.bus_address().expect("test bus has an address");
+
+    let context = glib::MainContext::new();
+    context
+        .with_thread_default(|| {
+            let connect = || {
+                gio::DBusConnection::for_address_sync(
+                    &address,
+                    gio::DBusConnectionFlags::AUTHENTICATION_CLIENT
+                        | gio::DBusConnectionFlags::MESSAGE_BUS_CONNECTION,
+                    None,
+                    None::<&gio::Cancellable>,
+                )
+                .expect("connect to test bus")
+            };
+            let service = connect();
+            let client = connect();
+
+            let name = service.unique_name().expect("service has a bus name");
+            test(&service, &client, &name);
+        })
+        .unwrap();
+
+    bus.down();
+}
+
+/// Call a method, running the test's main context until the reply arrives
+#[cfg(feature = "dbus")]
+pub fn call(
+    client: &gio::DBusConnection,
+    name: &str,
+    path: &str,
+    interface: &str,
+    method: &str,
+    args: glib::Variant,
+) -> Result<glib::Variant, glib::Error> {
+    glib::MainContext::ref_thread_default().block_on(client.call_future(
+        Some(name),
+        path,
+        interface,
+        method,
+        Some(&args),
+        None,
+        gio::DBusCallFlags::NONE,
+        5000,
+    ))
+}
+
+pub fn strings(words: &[&str]) -> Vec<String> {
+    words.iter().map(|w| w.to_string()).collect()
+}
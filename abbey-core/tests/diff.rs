@@ -0,0 +1,56 @@
+use abbey_core::utils::diff::{compare, Hunk};
+
+/// Each change as the old and new text it covers
+fn changes<'a>(old: &'a str, new: &'a str) -> Vec<(&'a str, &'a str)> {
+    compare(old, new)
+        .into_iter()
+        .map(|hunk| (&old[hunk.old], &new[hunk.new]))
+        .collect()
+}
+
+#[test]
+fn same_texts_have_no_changes() {
+    assert!(compare("", "").is_empty());
+    assert!(compare("One line.\nTwo lines.\n", "One line.\nTwo lines.\n").is_empty());
+}
+
+#[test]
+fn changes_are_found_word_by_word() {
+    assert_eq!(
+        changes("The quick brown fox jumps.", "The slow brown fox leaps."),
+        vec![("quick", "slow"), ("jumps", "leaps")]
+    );
+    assert_eq!(changes("It don't matter.", "It doesn't matter."), vec![("don't", "doesn't")]);
+    assert_eq!(changes("A café.", "A café au lait."), vec![("", " au lait")]);
+}
+
+#[test]
+fn neighbouring_changes_read_as_one() {
+    assert_eq!(changes("the quick fox", "a slow fox"), vec![("the quick", "a slow")]);
+    // A blank line keeps paragraphs' changes apart
+    assert_eq!(changes("one\n\ntwo", "uno\n\ndos"), vec![("one", "uno"), ("two", "dos")]);
+}
+
+#[test]
+fn only_changed_lines_are_compared() {
+    let old = "# Title\n\nFirst paragraph stays.\n\nSecond one is edited here.\n\nLast stays.\n";
+    let new = "# Title\n\nNew paragraph.\n\nFirst paragraph stays.\n\nSecond one was edited here.\n\nLast stays.\n";
+    assert_eq!(
+        changes(old, new),
+        vec![("", "New paragraph.\n\n"), ("is", "was")]
+    );
+}
+
+#[test]
+fn hunks_copy_across_either_way() {
+    let old = "Call me Ishmael. Some years ago.";
+    let new = "Call me Bob. Some months ago.";
+    let hunks = compare(old, new);
+    assert_eq!(hunks.len(), 2);
+    assert_eq!(hunks[0].copy_to_new(old, new), "Call me Ishmael. Some months ago.");
+    assert_eq!(hunks[1].copy_to_old(old, new), "Call me Ishmael. Some months ago.");
+
+    // Added text copied back is taken out
+    let hunk = Hunk { old: 5..5, new: 5..8 };
+    assert_eq!(hunk.copy_to_new("Café.", "Café au."), "Café.");
+}
@@ -0,0 +1,122 @@
+use abbey_core::utils::editing::{continue_list, deletes_pair, on_typed, paragraph_at, sentence_at, ListContinuation, Typed};
+
+fn continues(before: &str) -> Option<String> {
+    match continue_list(before, "") {
+        Some(ListContinuation::Continue(prefix)) => Some(prefix),
+        _ => None,
+    }
+}
+
+#[test]
+fn bullets_and_numbers_continue() {
+    assert_eq!(continues("- milk").as_deref(), Some("- "));
+    assert_eq!(continues("* eggs").as_deref(), Some("* "));
+    assert_eq!(continues("    + nested").as_deref(), Some("    + "));
+    assert_eq!(continues("3. third").as_deref(), Some("4. "));
+    assert_eq!(continues("9) ninth").as_deref(), Some("10) "));
+}
+
+#[test]
+fn tasks_continue_unchecked() {
+    assert_eq!(continues("- [x] done").as_deref(), Some("- [ ] "));
+    assert_eq!(continues("- [ ] todo").as_deref(), Some("- [ ] "));
+}
+
+#[test]
+fn quotes_keep_their_markers() {
+    assert_eq!(continues("> a thought").as_deref(), Some("> "));
+    assert_eq!(continues("> > deeper").as_deref(), Some("> > "));
+    assert_eq!(continues("> 1. quoted item").as_deref(), Some("> 2. "));
+}
+
+#[test]
+fn ordinary_lines_are_left_alone() {
+    assert_eq!(continue_list("Just a sentence.", ""), None);
+    assert_eq!(continue_list("# Heading", ""), None);
+    assert_eq!(continue_list("**bold** start", ""), None);
+    assert_eq!(continue_list("2024 was a year", ""), None);
+    assert_eq!(continue_list("-5 degrees", ""), None);
+}
+
+#[test]
+fn an_empty_item_ends_the_list() {
+    assert_eq!(continue_list("- ", ""), Some(ListContinuation::End));
+    assert_eq!(continue_list("  2. ", ""), Some(ListContinuation::End));
+    assert_eq!(continue_list("- [ ] ", ""), Some(ListContinuation::End));
+    assert_eq!(continue_list("> ", ""), Some(ListContinuation::End));
+
+    // Splitting an item before its text still continues the list
+    assert_eq!(
+        continue_list("- ", "rest of item"),
+        Some(ListContinuation::Continue("- ".to_string()))
+    );
+}
+
+#[test]
+fn brackets_pair_before_space_and_skip_their_closer() {
+    assert_eq!(on_typed('(', "see ", ""), Typed::Pair(')'));
+    assert_eq!(on_typed('[', "a ", " b"), Typed::Pair(']'));
+    assert_eq!(on_typed('(', "", "word"), Typed::Insert);
+    assert_eq!(on_typed(')', "(text", ")"), Typed::Skip);
+    assert_eq!(on_typed(')', "(text", ""), Typed::Insert);
+}
+
+#[test]
+fn emphasis_pairs_at_word_starts_only() {
+    assert_eq!(on_typed('*', "some ", ""), Typed::Pair('*'));
+    assert_eq!(on_typed('_', "", ""), Typed::Pair('_'));
+    assert_eq!(on_typed('_', "snake", ""), Typed::Insert);
+    assert_eq!(on_typed('*', "2", "3"), Typed::Insert);
+    assert_eq!(on_typed('`', "run ", ""), Typed::Pair('`'));
+}
+
+#[test]
+fn bold_pairs_twice_and_closes_by_skipping() {
+    // `*|*` becomes `**|**`
+    assert_eq!(on_typed('*', "a *", "*"), Typed::Pair('*'));
+    // `**bold|**` steps over each closer
+    assert_eq!(on_typed('*', "**bold", "**"), Typed::Skip);
+    assert_eq!(on_typed('*', "**bold*", "*"), Typed::Skip);
+}
+
+#[test]
+fn line_start_markers_stay_single() {
+    assert_eq!(on_typed('*', "", ""), Typed::Insert);
+    assert_eq!(on_typed('*', "  ", ""), Typed::Insert);
+    assert_eq!(on_typed('`', "``", ""), Typed::Insert);
+}
+
+#[test]
+fn backspace_removes_empty_pairs() {
+    assert!(deletes_pair('(', ')'));
+    assert!(deletes_pair('*', '*'));
+    assert!(!deletes_pair('(', ']'));
+    assert!(!deletes_pair('a', 'a'));
+}
+
+#[test]
+fn focus_finds_the_paragraph_around_the_cursor() {
+    let text = "# Title\n\nFirst line\nsecond line.\n\n\nLast";
+    assert_eq!(paragraph_at(text, 3), 0..7);
+    assert_eq!(paragraph_at(text, 12), 9..32);
+    assert_eq!(paragraph_at(text, 32), 9..32);
+    assert_eq!(paragraph_at(text, 34), 34..34);
+    assert_eq!(paragraph_at(text, 37), 35..39);
+    assert_eq!(paragraph_at("", 0), 0..0);
+}
+
+#[test]
+fn focus_finds_the_sentence_around_the_cursor() {
+    let text = "Intro.\n\nDr. Smith came. “Why?” she asked! Then left\n\nNext";
+    let sentence = |offset| {
+        let range = sentence_at(text, offset);
+        text.chars().skip(range.start).take(range.len()).collect::<String>()
+    };
+    assert_eq!(sentence(10), "Dr. Smith came.");
+    // Between sentences it's the one just finished
+    assert_eq!(sentence(23), "Dr. Smith came.");
+    assert_eq!(sentence(30), "“Why?” she asked!");
+    assert_eq!(sentence(50), "Then left");
+    assert_eq!(sentence(55), "Next");
+    assert_eq!(sentence_at(text, 7), 7..7);
+}
@@ -0,0 +1,161 @@
+mod common;
+
+use abbey_core::data::{Comment, Composition, Note, Project, Suggestion, SuggestionKind};
+use abbey_core::export::{self, ExportFormat};
+use common::composition;
+
+fn book() -> (Project, Vec<Composition>) {
+    let one = composition("One", "First chapter.");
+    let two = composition("", "Second chapter.");
+    let unused = composition("Unused", "Not in the project.");
+
+    let mut project = Project::new("Book".to_string());
+    project.description = "A short book".to_string();
+    project.add_composition(two.id.clone());
+    project.add_composition("missing".to_string());
+    project.add_composition(one.id.clone());
+
+    (project, vec![one, two, unused])
+}
+
+#[test]
+fn project_markdown_follows_project_order() {
+    let (project, compositions) = book();
+    let md = export::project_to_markdown(&project, &compositions);
+
+    assert!(md.starts_with("# Book\n\n*A short book*\n\n---\n\n"));
+    let second = md.find("Second chapter.").unwrap();
+    let first = md.find("First chapter.").unwrap();
+    assert!(second < first);
+    assert!(md.contains("## Untitled\n\nSecond chapter."));
+    assert!(!md.contains("Not in the project."));
+}
+
+#[test]
+fn project_without_description_skips_it() {
+    let project = Project::new("Empty".to_string());
+    assert_eq!(export::project_to_markdown(&project, &[]), "# Empty\n\n---\n\n");
+}
+
+#[test]
+fn html_export_is_a_full_document() {
+    let (mut project, compositions) = book();
+    project.title = "Fish & <Chips>".to_string();
+
+    let html = export::export_project(&project, &compositions, ExportFormat::Html);
+
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("<title>Fish &amp; &lt;Chips&gt;</title>"));
+    assert!(html.contains("<h2>One</h2>"));
+    assert!(html.contains("<p>First chapter.</p>"));
+    assert!(html.contains("<em>A short book</em>"));
+}
+
+#[test]
+fn markdown_export_matches_project_markdown() {
+    let (project, compositions) = book();
+    assert_eq!(
+        export::export_project(&project, &compositions, ExportFormat::Markdown),
+        export::project_to_markdown(&project, &compositions)
+    );
+}
+
+#[test]
+fn composition_markdown_lists_notes() {
+    let mut comp = composition("Essay", "Body");
+    assert_eq!(export::composition_to_markdown(&comp), "# Essay\n\nBody");
+
+    comp.notes.push(Note::new("tighten intro".to_string()));
+    comp.notes.push(Note::new("cite source".to_string()));
+    assert_eq!(
+        export::composition_to_markdown(&comp),
+        "# Essay\n\nBody\n\n---\n\n## Notes\n\n- tighten intro\n- cite source\n"
+    );
+}
+
+#[test]
+fn wiki_links_go_to_sections_within_a_project() {
+    let one = composition("One", "Back to [[Two]], on to [[Elsewhere]].");
+    let two = composition("Two", "");
+    let elsewhere = composition("Elsewhere", "");
+    let mut project = Project::new("Book".to_string());
+    project.add_composition(one.id.clone());
+    project.add_composition(two.id.clone());
+    let library = vec![one, two.clone(), elsewhere];
+
+    let md = export::project_to_markdown(&project, &library);
+    let anchor = format!("composition-{}", two.id);
+    assert!(md.contains(&format!("Back to [Two](#{}), on to Elsewhere.", anchor)));
+    assert!(md.contains(&format!("## Two {{#{}}}", anchor)));
+    assert!(md.contains("## One\n\n"));
+
+    let html = export::export_project(&project, &library, ExportFormat::Html);
+    assert!(html.contains(&format!(r#"<h2 id="{}">Two</h2>"#, anchor)));
+    assert!(html.contains(&format!(r##"<a href="#{}">Two</a>"##, anchor)));
+
+    let odt = export::export_project(&project, &library, ExportFormat::Odt);
+    assert!(odt.contains(&format!(r#"<text:bookmark text:name="{}"/>"#, anchor)));
+}
+
+#[test]
+fn exported_compositions_read_wiki_links_as_text() {
+    let comp = composition("Essay", "As [[Another Essay]] and [[abc|the last one]] said.");
+    let md = export::export_composition(&comp, ExportFormat::Markdown);
+    assert_eq!(md, "# Essay\n\nAs Another Essay and the last one said.");
+    // The composition's own file keeps them
+    assert!(export::composition_to_markdown(&comp).contains("[[Another Essay]]"));
+}
+
+#[test]
+fn pending_suggestions_are_left_out_of_exports() {
+    // "slow" suggested out and "quick" suggested in its place
+    let mut comp = composition("Essay", "The slowquick fox jumps.");
+    comp.suggestions.push(Suggestion::new(SuggestionKind::Deletion, 4, 8, "slow".to_string(), "Ann".to_string()));
+    comp.suggestions.push(Suggestion::new(SuggestionKind::Insertion, 8, 13, "quick".to_string(), "Ann".to_string()));
+    comp.comments.push(Comment::new(18, 23, "jumps".to_string(), "Leaps?".to_string()));
+    comp.update_word_count();
+    assert_eq!(comp.content_without_suggestions(), "The slow fox jumps.");
+    assert_eq!(comp.word_count, 4);
+
+    assert_eq!(
+        export::export_composition(&comp, ExportFormat::Markdown),
+        "# Essay\n\nThe slow fox jumps[^comment-1].\n\n[^comment-1]: Leaps?"
+    );
+    assert!(export::export_composition(&comp, ExportFormat::Html).contains("<p>The slow fox jumps<"));
+    let odt = export::export_composition(&comp, ExportFormat::Odt);
+    assert!(odt.contains("The slow fox ") && !odt.contains("quick"));
+
+    let mut project = Project::new("Book".to_string());
+    project.add_composition(comp.id.clone());
+    let md = export::project_to_markdown(&project, &[comp.clone()]);
+    assert!(md.contains("The slow fox jumps[^comment-1].") && !md.contains("quick"));
+    // The composition itself keeps them for review
+    assert_eq!(comp.content, "The slowquick fox jumps.");
+    assert_eq!(comp.suggestions.len(), 2);
+}
+
+#[test]
+fn markdown_to_html_renders_fragment() {
+    assert_eq!(export::markdown_to_html("**bold**"), "<p><strong>bold</strong></p>\n");
+}
+
+#[test]
+fn headings_ending_in_braces_keep_them() {
+    assert_eq!(export::markdown_to_html("# Notes {draft}"), "<h1>Notes {draft}</h1>\n");
+
+    let comp = composition("Essay", "## Notes {draft}\n\nText.");
+    assert!(export::export_composition(&comp, ExportFormat::Html).contains("<h2>Notes {draft}</h2>"));
+    assert!(export::export_composition(&comp, ExportFormat::Odt).contains("Notes {draft}"));
+}
+
+#[test]
+fn export_format_names() {
+    assert_eq!(ExportFormat::from_name("md"), Some(ExportFormat::Markdown));
+    assert_eq!(ExportFormat::from_name("Markdown"), Some(ExportFormat::Markdown));
+    assert_eq!(ExportFormat::from_name("HTML"), Some(ExportFormat::Html));
+    assert_eq!(ExportFormat::from_name("odt"), Some(ExportFormat::Odt));
+    assert_eq!(ExportFormat::from_name("pdf"), None);
+    assert_eq!(ExportFormat::Html.extension(), "html");
+    assert_eq!(ExportFormat::Markdown.extension(), "md");
+    assert_eq!(ExportFormat::Odt.extension(), "fodt");
+}
@@ -0,0 +1,66 @@
+use abbey_core::utils::frequency::{analyze, is_stopword, occurrences, Baseline};
+
+#[test]
+fn top_words_leave_out_stopwords_and_markup() {
+    let analysis = analyze(&["# The Garden\n\nThe **garden** was quiet. The garden gate was open, and the gate creaked."], &Baseline::default());
+    let top: Vec<(&str, usize)> = analysis.top_words.iter().map(|term| (term.text.as_str(), term.count)).collect();
+    assert_eq!(&top[..2], &[("garden", 3), ("gate", 2)]);
+    assert!(!top.iter().any(|(word, _)| is_stopword(word)));
+    assert!(is_stopword("The") && is_stopword("don\u{2019}t"));
+}
+
+#[test]
+fn phrases_stay_within_sentences() {
+    let analysis = analyze(
+        &["The old mill stood still. The old mill burned. Mill stood on the hill. Old mill."],
+        &Baseline::default(),
+    );
+    let phrases: Vec<(&str, usize)> = analysis.top_phrases.iter().map(|term| (term.text.as_str(), term.count)).collect();
+    assert_eq!(phrases[0], ("old mill", 3));
+    // "mill stood" turns up twice but "mill. mill" never makes a phrase
+    assert!(phrases.contains(&("mill stood", 2)));
+    assert!(!phrases.iter().any(|(phrase, _)| phrase.starts_with("mill mill")));
+}
+
+#[test]
+fn pairs_inside_longer_phrases_are_folded_in() {
+    let analysis = analyze(&["Deep blue sea. Deep blue sea. Deep blue sky."], &Baseline::default());
+    let phrases: Vec<(&str, usize)> = analysis.top_phrases.iter().map(|term| (term.text.as_str(), term.count)).collect();
+    assert_eq!(phrases, vec![("deep blue", 3), ("deep blue sea", 2)]);
+}
+
+#[test]
+fn overused_words_need_a_library_to_compare_with() {
+    let text = "The lantern swung. A lantern glowed. Her lantern dimmed. The lantern went out.";
+    assert!(analyze(&[text], &Baseline::default()).overused.is_empty());
+
+    let library = "The river ran past the town and the hills beyond it. ".repeat(100);
+    let baseline = Baseline::new([library.as_str()]);
+    let overused = analyze(&[text], &baseline).overused;
+    assert_eq!(overused.len(), 1);
+    assert_eq!((overused[0].word.as_str(), overused[0].count), ("lantern", 4));
+    assert!(overused[0].rate > overused[0].library_rate * 3.0);
+}
+
+#[test]
+fn clusters_find_close_repeats() {
+    let filler = "word ".repeat(50);
+    let text = format!("Suddenly it rained. Suddenly it stopped. Suddenly it was dark. {} Suddenly again.", filler);
+    let analysis = analyze(&["Nothing here.", &text], &Baseline::default());
+    let clusters: Vec<_> = analysis.clusters.iter().filter(|cluster| cluster.word == "suddenly").collect();
+    assert_eq!(clusters.len(), 1);
+    assert_eq!(clusters[0].document, 1);
+    assert_eq!(clusters[0].occurrences.len(), 3);
+    assert_eq!(clusters[0].range(), 0..49);
+}
+
+#[test]
+fn occurrences_are_in_chars_and_ignore_case() {
+    let text = "“Café” crowd. The café *crowd* left; café. Crowd";
+    let found = occurrences(text, "café crowd");
+    assert_eq!(found, vec![1..12, 18..29]);
+    let chars: Vec<char> = text.chars().collect();
+    assert_eq!(chars[18..29].iter().collect::<String>(), "café *crowd");
+    assert_eq!(occurrences(text, "CROWD").len(), 3);
+    assert!(occurrences(text, "  ").is_empty());
+}
@@ -0,0 +1,108 @@
+use abbey_core::data::Storage;
+use abbey_core::export;
+use abbey_core::import;
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+#[test]
+fn openable_files() {
+    assert!(import::is_openable(Path::new("draft.md")));
+    assert!(import::is_openable(Path::new("/tmp/Notes.MARKDOWN")));
+    assert!(import::is_openable(Path::new("todo.txt")));
+    assert!(!import::is_openable(Path::new("photo.png")));
+    assert!(!import::is_openable(Path::new("README")));
+}
+
+#[test]
+fn heading_becomes_title() {
+    let comp = import::composition_from_markdown("# My Essay\n\nFirst line.\nSecond line.\n", Path::new("x.md"));
+    assert_eq!(comp.title, "My Essay");
+    assert_eq!(comp.content, "First line.\nSecond line.\n");
+    assert_eq!(comp.word_count, 4);
+    assert!(comp.external_path.is_none());
+}
+
+#[test]
+fn file_name_is_title_without_heading() {
+    let text = "Just some text\n## Not a title\n";
+    let comp = import::composition_from_markdown(text, Path::new("/home/me/draft notes.md"));
+    assert_eq!(comp.title, "draft notes");
+    assert_eq!(comp.content, text);
+}
+
+#[test]
+fn import_round_trips_abbey_exports() {
+    let original = import::composition_from_markdown("# Round Trip\n\nBody text.", Path::new("a.md"));
+    let exported = export::composition_to_markdown(&original);
+    let reimported = import::composition_from_markdown(&exported, Path::new("b.md"));
+
+    assert_eq!(reimported.title, "Round Trip");
+    assert_eq!(reimported.content, "Body text.");
+}
+
+#[test]
+fn linked_composition_keeps_file_exact() {
+    let text = "# Heading stays\n\nbody\n";
+    let path = Path::new("/docs/chapter.md");
+    let comp = import::linked_composition(text, path);
+
+    assert_eq!(comp.title, "chapter");
+    assert_eq!(comp.content, text);
+    assert_eq!(comp.external_path.as_deref(), Some(path));
+
+    let others = vec![import::linked_composition("", Path::new("/docs/other.md")), comp.clone()];
+    assert_eq!(import::find_linked(&others, path).unwrap().id, comp.id);
+    assert!(import::find_linked(&others, Path::new("/docs/none.md")).is_none());
+}
+
+#[test]
+fn linked_compositions_save_to_their_file() {
+    let dir = TempDir::new().unwrap();
+    let storage = Storage::open(dir.path().join("Abbey")).unwrap();
+    let file = dir.path().join("outside.md");
+    fs::write(&file, "before").unwrap();
+
+    let mut comp = import::linked_composition("before", &file);
+    comp.content = "after editing".to_string();
+    storage.save_composition(&comp).unwrap();
+
+    assert_eq!(fs::read_to_string(&file).unwrap(), "after editing");
+    assert!(!storage.compositions_dir().join("outside.md").exists());
+
+    let stored = storage.load_compositions().unwrap();
+    assert_eq!(stored[0].external_path.as_deref(), Some(file.as_path()));
+}
+
+#[test]
+fn read_external_picks_up_outside_edits() {
+    let dir = TempDir::new().unwrap();
+    let storage = Storage::open(dir.path().join("Abbey")).unwrap();
+    let file = dir.path().join("shared.md");
+    fs::write(&file, "one").unwrap();
+
+    let mut comp = import::linked_composition("one", &file);
+    assert!(!storage.read_external(&mut comp).unwrap());
+
+    fs::write(&file, "one two three").unwrap();
+    assert!(storage.read_external(&mut comp).unwrap());
+    assert_eq!(comp.content, "one two three");
+    assert_eq!(comp.word_count, 3);
+
+    fs::remove_file(&file).unwrap();
+    assert!(storage.read_external(&mut comp).is_err());
+}
+
+#[test]
+fn read_only_library_does_not_write_linked_files() {
+    let dir = TempDir::new().unwrap();
+    let file = dir.path().join("kept.md");
+    fs::write(&file, "original").unwrap();
+
+    let storage = Storage::open_read_only(dir.path().join("Abbey"));
+    let mut comp = import::linked_composition("original", &file);
+    comp.content = "changed".to_string();
+
+    assert!(storage.write_external(&comp).unwrap_err().is_read_only());
+    assert_eq!(fs::read_to_string(&file).unwrap(), "original");
+}
@@ -0,0 +1,114 @@
+mod common;
+
+use abbey_core::data::{Comment, Flow, Note};
+use abbey_core::links::{self, Source};
+use common::composition;
+
+#[test]
+fn links_are_found_outside_code() {
+    let text = "See [[Chapter One]] and [[abc-123|the intro]].\n`[[Not a link]]` and [[ ]] or [[Open\n```\n[[Fenced]]\n```\n";
+    let found = links::find(text);
+    assert_eq!(found.len(), 2);
+    assert_eq!(&text[found[0].range.clone()], "[[Chapter One]]");
+    assert_eq!((found[0].target.as_str(), found[0].label.as_deref()), ("Chapter One", None));
+    assert_eq!((found[1].target.as_str(), found[1].label.as_deref()), ("abc-123", Some("the intro")));
+
+    let at = text.find("Chapter").unwrap();
+    assert_eq!(links::link_at(text, at).unwrap().target, "Chapter One");
+    assert!(links::link_at(text, 0).is_none());
+}
+
+#[test]
+fn links_resolve_by_id_then_title() {
+    let mut archived = composition("Draft", "");
+    archived.archived = true;
+    let current = composition("draft", "");
+    let compositions = vec![archived, current.clone()];
+
+    assert_eq!(links::resolve(&current.id, &compositions).unwrap().id, current.id);
+    // Titles match whatever their case, and ones still in use come first
+    assert_eq!(links::resolve(" DRAFT ", &compositions).unwrap().id, current.id);
+    assert!(links::resolve("Nothing", &compositions).is_none());
+}
+
+#[test]
+fn titles_complete_what_is_typed() {
+    let text = "Go to [[cha";
+    let partial = links::partial_at(text, text.len()).unwrap();
+    assert_eq!(&text[partial.clone()], "cha");
+    assert!(links::partial_at("[[Done]] now", 12).is_none());
+
+    let titles = ["Preface", "Chapter Two", "Merchant", "Chapter One"];
+    assert_eq!(
+        links::complete(&text[partial], titles.iter().copied()),
+        vec!["Chapter Two", "Chapter One", "Merchant"]
+    );
+}
+
+#[test]
+fn renaming_rewrites_links_to_the_old_title() {
+    let target = composition("Old Name", "");
+    let mut linking = composition("Index", "See [[old name]], [[Old Name|here]] and [[Other]].");
+    linking.comments.push(Comment::new(42, 47, "Other".to_string(), "Which?".to_string()));
+    linking.notes.push(Note::new("Also [[Old Name]]".to_string()));
+    let by_id = composition("By id", &format!("[[{}|it]]", target.id));
+    let mut compositions = vec![target.clone(), linking, by_id];
+
+    compositions[0].title = "The New Name".to_string();
+    let changed = links::retitle(&mut compositions, &target.id, "Old Name");
+    assert_eq!(changed, vec![compositions[1].id.clone()]);
+    assert_eq!(compositions[1].content, "See [[The New Name]], [[The New Name|here]] and [[Other]].");
+    assert_eq!(compositions[1].notes[0].content, "Also [[The New Name]]");
+    assert_eq!((compositions[1].comments[0].start, compositions[1].comments[0].end), (50, 55));
+    // Links by id need nothing
+    assert_eq!(compositions[2].content, format!("[[{}|it]]", target.id));
+}
+
+#[test]
+fn renaming_leaves_links_that_went_elsewhere() {
+    let renamed = composition("Notes", "");
+    let other = composition("Notes", "");
+    let linking = composition("Index", "[[Notes]]");
+    let mut compositions = vec![other, renamed.clone(), linking];
+
+    // The first "Notes" was the one linked to, and still is
+    compositions[1].title = "Research".to_string();
+    assert!(links::retitle(&mut compositions, &renamed.id, "Notes").is_empty());
+    assert_eq!(compositions[2].content, "[[Notes]]");
+}
+
+#[test]
+fn backlinks_come_from_compositions_notes_and_flows() {
+    let target = composition("Lighthouse", "Nothing links back from here.");
+    let mut essay = composition("Essay", "The keeper of the [[lighthouse]] was alone.");
+    essay.notes.push(Note::new(format!("Check [[{}|it]]", target.id)));
+    let unrelated = composition("Unrelated", "[[Harbour]]");
+    let mut flow = Flow::new(10);
+    flow.content = "Morning pages about the [[Lighthouse]].".to_string();
+    let compositions = vec![target.clone(), essay.clone(), unrelated];
+
+    let backlinks = links::backlinks(&target, &compositions, &[flow.clone()]);
+    let sources: Vec<&Source> = backlinks.iter().map(|backlink| &backlink.source).collect();
+    assert_eq!(
+        sources,
+        vec![
+            &Source::Composition(essay.id.clone()),
+            &Source::Note(essay.id.clone(), essay.notes[0].id.clone()),
+            &Source::Flow(flow.id.clone()),
+        ]
+    );
+    assert_eq!(backlinks[0].name, "Essay");
+    assert_eq!(backlinks[0].context, "The keeper of the [[lighthouse]] was alone.");
+}
+
+#[test]
+fn backlink_context_is_cut_around_the_link() {
+    let target = composition("Sea", "");
+    let long = format!("{} [[Sea]] {}", "word ".repeat(40), "more ".repeat(40));
+    let linking = composition("Long", &long);
+    let backlinks = links::backlinks(&target, &[target.clone(), linking], &[]);
+    let context = &backlinks[0].context;
+    assert!(context.starts_with('…') && context.ends_with('…'));
+    assert!(context.contains("[[Sea]]"));
+    assert!(context.chars().count() < long.chars().count());
+}
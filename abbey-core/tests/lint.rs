@@ -0,0 +1,141 @@
+use abbey_core::utils::lint::{Issue, Linter, Prose, Rule};
+
+fn issues(markdown: &str, rule: &str) -> Vec<Issue> {
+    Linter::builtin()
+        .check(markdown, &[])
+        .into_iter()
+        .filter(|issue| issue.rule == rule)
+        .collect()
+}
+
+fn texts(markdown: &str, rule: &str) -> Vec<String> {
+    issues(markdown, rule).into_iter().map(|issue| issue.text).collect()
+}
+
+#[test]
+fn sentences_split_at_full_stops_but_not_abbreviations() {
+    let prose = Prose::new("Mr. Smith met Dr. Jones at 3.5 miles. They talked! Was it *late?* Yes.\n\nNew paragraph");
+    let sentences: Vec<usize> = prose.paragraphs[0].sentences().map(|s| s.len()).collect();
+    assert_eq!(sentences, vec![7, 2, 3, 1]);
+    assert_eq!(prose.paragraphs.len(), 2);
+}
+
+#[test]
+fn passive_voice_is_flagged_with_its_verb() {
+    assert_eq!(
+        texts("The letter was written by hand. It is quickly forgotten. She needed help.", "passive-voice"),
+        vec!["was written", "is quickly forgotten"]
+    );
+    assert!(texts("He is here. The bed was red.", "passive-voice").is_empty());
+}
+
+#[test]
+fn adverbs_are_flagged_only_in_dense_paragraphs() {
+    let dense = "She ran quickly and quietly to the door.";
+    assert_eq!(texts(dense, "adverbs"), vec!["quickly", "quietly"]);
+
+    let sparse = format!("{} slowly.", "word ".repeat(60));
+    assert!(texts(&sparse, "adverbs").is_empty());
+    assert!(texts("The only early family reply.", "adverbs").is_empty());
+}
+
+#[test]
+fn weasel_words_and_cliches_match_whole_phrases() {
+    assert_eq!(
+        texts("Studies show it is very good. Basically, fine.", "weasel-words"),
+        vec!["Studies show", "very", "Basically"]
+    );
+    assert_eq!(
+        texts("At the end of the day we picked the low-hanging fruit.", "cliches"),
+        vec!["At the end of the day", "low-hanging fruit"]
+    );
+    // A phrase doesn't run across sentences
+    assert!(texts("It was the end. Of the day, nothing.", "cliches").is_empty());
+}
+
+#[test]
+fn doubled_words_are_flagged_unless_punctuated() {
+    assert_eq!(texts("I saw the the cat.\nWell, well. She had had enough.", "repeated-words"), vec!["the the"]);
+    // Words either side of a paragraph break don't count
+    assert!(texts("Ends with cat\n\ncat starts here", "repeated-words").is_empty());
+}
+
+#[test]
+fn long_sentences_cover_the_whole_sentence() {
+    let long = format!("Start {} end. Short one.", "and on ".repeat(20));
+    let found = issues(&long, "long-sentences");
+    assert_eq!(found.len(), 1);
+    assert!(found[0].text.starts_with("Start") && found[0].text.ends_with("end"));
+    assert!(found[0].message.starts_with("42 words"));
+}
+
+#[test]
+fn the_less_common_spelling_is_flagged() {
+    let markdown = "The color was grey. Another color. Her colour, a gray e-mail and an email to organise and organize.";
+    let found = texts(markdown, "inconsistent-spellings");
+    // On a tie, the spelling used first wins
+    assert_eq!(found, vec!["colour", "gray", "email", "organize"]);
+
+    let message = &issues(markdown, "inconsistent-spellings")[0].message;
+    assert_eq!(message, "Spelled “color” 2 times elsewhere");
+}
+
+#[test]
+fn code_and_links_are_not_checked() {
+    let markdown = "```\nthe the\n```\n\nSee <https://example.com/was/written> and `is used`.";
+    assert!(Linter::builtin().check(markdown, &[]).is_empty());
+}
+
+#[test]
+fn issues_come_in_order_with_char_ranges() {
+    let markdown = "Café was closed. Very nice.";
+    let found = Linter::builtin().check(markdown, &[]);
+    let found: Vec<(&str, std::ops::Range<usize>)> = found.iter().map(|i| (i.rule, i.range.clone())).collect();
+    assert_eq!(found, vec![("passive-voice", 5..15), ("weasel-words", 17..21)]);
+}
+
+#[test]
+fn rules_can_be_turned_off_and_findings_ignored() {
+    let markdown = "It was written. It was written again. Very good.";
+    let linter = Linter::builtin().without(&["weasel-words".to_string()]);
+    assert!(linter.rules().all(|rule| rule.id() != "weasel-words"));
+
+    let found = linter.check(markdown, &[]);
+    assert_eq!(found.len(), 2);
+    assert_eq!(found[0].key(), "passive-voice:was written");
+
+    // Ignoring one finding ignores the same text everywhere
+    assert!(linter.check(markdown, &[found[0].key()]).is_empty());
+}
+
+#[test]
+fn custom_rules_plug_in() {
+    struct Exclamations;
+
+    impl Rule for Exclamations {
+        fn id(&self) -> &'static str {
+            "exclamations"
+        }
+
+        fn name(&self) -> &'static str {
+            "Exclamations"
+        }
+
+        fn check(&self, prose: &Prose) -> Vec<Issue> {
+            let mut issues = Vec::new();
+            for paragraph in &prose.paragraphs {
+                for word in &paragraph.words {
+                    if prose.text(word.range.end..word.range.end + 1) == "!" {
+                        issues.push(prose.issue(self.id(), word.range.clone(), "Calm down".to_string()));
+                    }
+                }
+            }
+            issues
+        }
+    }
+
+    let linter = Linter::new(Vec::new()).with_rule(Exclamations);
+    let found = linter.check("Wow! Fine.", &[]);
+    assert_eq!(found.len(), 1);
+    assert_eq!((found[0].text.as_str(), found[0].message.as_str()), ("Wow", "Calm down"));
+}
@@ -0,0 +1,159 @@
+use abbey_core::data::{LibraryLock, Storage, StorageError};
+use chrono::{Duration, Utc};
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+const LOCK_FILE: &str = ".abbey.lock";
+
+/// Write a lock file as another instance would
+fn write_foreign_lock(dir: &Path, pid: u32, host: &str, heartbeat_age: Duration) {
+    fs::create_dir_all(dir).unwrap();
+    let heartbeat = Utc::now() - heartbeat_age;
+    let json = serde_json::json!({
+        "pid": pid,
+        "host": host,
+        "acquired_at": heartbeat,
+        "heartbeat_at": heartbeat,
+        "token": "someone-else",
+    });
+    fs::write(dir.join(LOCK_FILE), json.to_string()).unwrap();
+}
+
+fn this_host() -> String {
+    fs::read_to_string("/proc/sys/kernel/hostname")
+        .or_else(|_| fs::read_to_string("/etc/hostname"))
+        .map(|name| name.trim().to_string())
+        .unwrap_or_default()
+}
+
+#[test]
+fn open_takes_the_lock_and_drop_releases_it() {
+    let dir = TempDir::new().unwrap();
+    let base = dir.path().join("Abbey");
+
+    let storage = Storage::open(base.clone()).unwrap();
+    let contents = fs::read_to_string(base.join(LOCK_FILE)).unwrap();
+    assert!(contents.contains(&format!("\"pid\": {}", std::process::id())));
+
+    drop(storage);
+    assert!(!base.join(LOCK_FILE).exists());
+}
+
+#[test]
+fn read_only_open_does_not_lock() {
+    let dir = TempDir::new().unwrap();
+    let _storage = Storage::open_read_only(dir.path().to_path_buf());
+    assert!(!dir.path().join(LOCK_FILE).exists());
+}
+
+#[test]
+fn live_lock_from_another_machine_blocks_writing() {
+    let dir = TempDir::new().unwrap();
+    write_foreign_lock(dir.path(), 4242, "other-laptop", Duration::seconds(5));
+
+    match Storage::open(dir.path().to_path_buf()) {
+        Err(StorageError::Locked(holder)) => {
+            assert_eq!(holder.host, "other-laptop");
+            assert_eq!(holder.pid, 4242);
+        }
+        other => panic!("expected the library to be locked, got {:?}", other.err()),
+    }
+
+    let err = LibraryLock::acquire(dir.path()).unwrap_err();
+    assert!(err.prevents_writing());
+    assert!(err.to_string().contains("other-laptop"));
+
+    // The other instance's lock is left alone
+    assert!(fs::read_to_string(dir.path().join(LOCK_FILE)).unwrap().contains("someone-else"));
+}
+
+#[test]
+fn stale_lock_is_replaced() {
+    let dir = TempDir::new().unwrap();
+    write_foreign_lock(dir.path(), 4242, "other-laptop", Duration::hours(2));
+
+    let storage = Storage::open(dir.path().to_path_buf()).unwrap();
+    let contents = fs::read_to_string(dir.path().join(LOCK_FILE)).unwrap();
+    assert!(!contents.contains("someone-else"));
+    drop(storage);
+}
+
+#[test]
+fn lock_of_dead_local_process_is_replaced() {
+    if !Path::new("/proc/self").exists() {
+        return;
+    }
+
+    let dir = TempDir::new().unwrap();
+    // Far above any pid_max, so no such process can exist
+    write_foreign_lock(dir.path(), u32::MAX - 1, &this_host(), Duration::seconds(5));
+
+    assert!(LibraryLock::acquire(dir.path()).is_ok());
+}
+
+#[test]
+fn garbled_lock_blocks_until_it_goes_stale() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join(LOCK_FILE), "").unwrap();
+
+    assert!(matches!(LibraryLock::acquire(dir.path()), Err(StorageError::Locked(_))));
+}
+
+#[test]
+fn reopening_in_the_same_process_takes_over() {
+    let dir = TempDir::new().unwrap();
+    let base = dir.path().join("Abbey");
+
+    let first = Storage::open(base.clone()).unwrap();
+    let second = Storage::open(base.clone()).unwrap();
+
+    // Dropping the superseded storage must not release the new lock
+    drop(first);
+    assert!(base.join(LOCK_FILE).exists());
+
+    second.refresh_lock().unwrap();
+    drop(second);
+    assert!(!base.join(LOCK_FILE).exists());
+}
+
+#[test]
+fn refresh_updates_the_heartbeat() {
+    let dir = TempDir::new().unwrap();
+    let mut lock = LibraryLock::acquire(dir.path()).unwrap();
+    let before = fs::read_to_string(dir.path().join(LOCK_FILE)).unwrap();
+
+    std::thread::sleep(std::time::Duration::from_millis(20));
+    lock.refresh().unwrap();
+
+    let after = fs::read_to_string(dir.path().join(LOCK_FILE)).unwrap();
+    assert_ne!(before, after);
+    assert!(!dir.path().join(".abbey.lock.tmp").exists());
+}
+
+#[test]
+fn refresh_recreates_a_deleted_lock() {
+    let dir = TempDir::new().unwrap();
+    let mut lock = LibraryLock::acquire(dir.path()).unwrap();
+    fs::remove_file(dir.path().join(LOCK_FILE)).unwrap();
+
+    lock.refresh().unwrap();
+    assert!(dir.path().join(LOCK_FILE).exists());
+}
+
+#[test]
+fn losing_the_lock_makes_storage_read_only() {
+    let dir = TempDir::new().unwrap();
+    let storage = Storage::open(dir.path().to_path_buf()).unwrap();
+
+    // Another machine decided our lock was stale and took it
+    write_foreign_lock(dir.path(), 4242, "other-laptop", Duration::seconds(0));
+
+    assert!(matches!(storage.refresh_lock(), Err(StorageError::Locked(_))));
+    assert!(storage.is_read_only());
+    assert!(storage.save_folders(&[]).unwrap_err().is_read_only());
+
+    // Their lock survives our storage going away
+    drop(storage);
+    assert!(fs::read_to_string(dir.path().join(LOCK_FILE)).unwrap().contains("someone-else"));
+}
@@ -0,0 +1,74 @@
+use abbey_core::utils::outline::{move_section, outline};
+
+const ESSAY: &str = "\
+Preamble.
+
+# One
+
+First part.
+
+## One A
+
+Detail here.
+
+# Two
+
+Second part, longer than the first.
+";
+
+fn titles(markdown: &str) -> Vec<String> {
+    outline(markdown).into_iter().map(|s| s.title).collect()
+}
+
+#[test]
+fn sections_run_to_the_next_heading_at_their_level() {
+    let sections = outline(ESSAY);
+    let summary: Vec<_> = sections.iter().map(|s| (s.level, s.title.as_str(), s.words)).collect();
+    assert_eq!(summary, vec![(1, "One", 7), (2, "One A", 4), (1, "Two", 7)]);
+
+    let chars: Vec<char> = ESSAY.chars().collect();
+    let text = |range: &std::ops::Range<usize>| chars[range.clone()].iter().collect::<String>();
+    assert_eq!(text(&sections[0].heading), "# One\n");
+    assert!(text(&sections[0].range).ends_with("Detail here.\n\n"));
+    assert!(text(&sections[2].range).starts_with("# Two"));
+    assert_eq!(sections[2].range.end, chars.len());
+}
+
+#[test]
+fn only_top_level_headings_count() {
+    let markdown = "# Real\n\n> # Quoted\n\n- # Listed\n\n```\n# code\n```\n\nSetext\n------\n\n# `Code` *title*\n";
+    assert_eq!(titles(markdown), vec!["Real", "Setext", "Code title"]);
+    assert_eq!(outline(markdown)[1].level, 2);
+}
+
+#[test]
+fn move_a_section_with_its_subsections() {
+    let moved = move_section(ESSAY, 0, None).unwrap();
+    assert_eq!(
+        moved.text,
+        "Preamble.\n\n# Two\n\nSecond part, longer than the first.\n\n# One\n\nFirst part.\n\n## One A\n\nDetail here.\n"
+    );
+    assert!(moved.text.chars().skip(moved.start).collect::<String>().starts_with("# One\n"));
+
+    let back = move_section(&moved.text, 1, Some(0)).unwrap();
+    assert_eq!(back.text, ESSAY);
+    assert_eq!(back.start, "Preamble.\n\n".len());
+}
+
+#[test]
+fn the_last_section_gets_a_blank_line_when_moved_up() {
+    let markdown = "# A\ntext a\n# B\ntext b";
+    let moved = move_section(markdown, 1, Some(0)).unwrap();
+    assert_eq!(moved.text, "# B\ntext b\n\n# A\ntext a");
+    assert_eq!(moved.start, 0);
+}
+
+#[test]
+fn sections_do_not_move_into_themselves() {
+    // Before itself, before its own subsection and before the next section
+    assert!(move_section(ESSAY, 0, Some(0)).is_none());
+    assert!(move_section(ESSAY, 0, Some(1)).is_none());
+    assert!(move_section(ESSAY, 0, Some(2)).is_none());
+    assert!(move_section(ESSAY, 2, None).is_none());
+    assert!(move_section(ESSAY, 5, None).is_none());
+}
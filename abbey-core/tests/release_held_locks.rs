@@ -0,0 +1,21 @@
+//! Kept in its own test binary: releasing every held lock would otherwise
+//! interfere with lock tests running in parallel
+
+use abbey_core::data::{release_held_locks, Storage};
+use tempfile::TempDir;
+
+#[test]
+fn release_held_locks_removes_lock_files_without_drop() {
+    let dir = TempDir::new().unwrap();
+    let storage = Storage::open(dir.path().to_path_buf()).unwrap();
+    let lock_path = dir.path().join(".abbey.lock");
+    assert!(lock_path.exists());
+
+    // As a panic hook would, before the process dies without running destructors
+    release_held_locks();
+    assert!(!lock_path.exists());
+
+    // A later drop finds nothing of its own to remove
+    drop(storage);
+    assert!(!lock_path.exists());
+}
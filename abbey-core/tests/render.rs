@@ -0,0 +1,154 @@
+use abbey_core::export::markdown_to_html;
+use abbey_core::utils::render::{diff, preview_position, render, source_position, Block, Splice, Style};
+
+/// The whole rendered text
+fn text(blocks: &[Block]) -> String {
+    blocks.iter().map(|b| b.text.as_str()).collect()
+}
+
+/// The text each span of `style` covers, across blocks
+fn styled(blocks: &[Block], style: &Style) -> Vec<String> {
+    blocks
+        .iter()
+        .flat_map(|block| {
+            block
+                .spans
+                .iter()
+                .filter(|span| &span.style == style)
+                .map(|span| block.text.chars().skip(span.start).take(span.end - span.start).collect())
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+#[test]
+fn blocks_are_separated_and_remember_their_source() {
+    let markdown = "# Title\n\nFirst paragraph\nwraps here.\n\nSecond.\n";
+    let blocks = render(markdown);
+
+    assert_eq!(blocks.len(), 3);
+    assert_eq!(text(&blocks), "Title\n\nFirst paragraph wraps here.\n\nSecond.\n\n");
+    assert_eq!(&markdown[blocks[1].source.clone()].trim_end(), &"First paragraph\nwraps here.");
+    assert_eq!(styled(&blocks, &Style::Heading(1)), vec!["Title"]);
+}
+
+#[test]
+fn nested_emphasis_parses_like_the_export() {
+    let blocks = render("**a*b**");
+    assert_eq!(text(&blocks), "a*b\n\n");
+    assert_eq!(styled(&blocks, &Style::Strong), vec!["a*b"]);
+    assert_eq!(markdown_to_html("**a*b**"), "<p><strong>a*b</strong></p>\n");
+
+    let blocks = render("**bold *and italic***");
+    assert_eq!(styled(&blocks, &Style::Emphasis), vec!["and italic"]);
+    assert_eq!(styled(&blocks, &Style::Strong), vec!["bold and italic"]);
+}
+
+#[test]
+fn escapes_are_plain_text() {
+    let blocks = render(r"2 \* 3 = 6 and \_not italic\_");
+    assert_eq!(text(&blocks), "2 * 3 = 6 and _not italic_\n\n");
+    assert!(blocks[0].spans.is_empty());
+}
+
+#[test]
+fn ordered_and_nested_lists_get_markers_and_indents() {
+    let blocks = render("3. three\n4. four\n   - inner\n     - deeper\n");
+    assert_eq!(text(&blocks), "3. three\n4. four\n• inner\n◦ deeper\n\n");
+    assert_eq!(styled(&blocks, &Style::ListMarker), vec!["3. ", "4. ", "• ", "◦ "]);
+    assert_eq!(styled(&blocks, &Style::Indent(2)), vec!["• inner\n"]);
+    assert_eq!(styled(&blocks, &Style::Indent(3)), vec!["◦ deeper"]);
+}
+
+#[test]
+fn task_lists_show_boxes() {
+    let blocks = render("- [x] done\n- [ ] todo\n");
+    assert_eq!(text(&blocks), "• ☑ done\n• ☐ todo\n\n");
+}
+
+#[test]
+fn links_and_images_keep_their_targets() {
+    let blocks = render("See [the site](https://example.com) and ![a cat](cat.png) or ![](dog.png).");
+    assert_eq!(styled(&blocks, &Style::Link("https://example.com".to_string())), vec!["the site"]);
+    assert_eq!(styled(&blocks, &Style::Image("cat.png".to_string())), vec!["🖼 a cat"]);
+    assert_eq!(styled(&blocks, &Style::Image("dog.png".to_string())), vec!["🖼 dog.png"]);
+}
+
+#[test]
+fn code_blocks_keep_their_lines() {
+    let blocks = render("```rust\nfn main() {\n    *x = 1;\n}\n```\n\nAfter with `code`.");
+    assert_eq!(styled(&blocks, &Style::CodeBlock), vec!["fn main() {\n    *x = 1;\n}"]);
+    assert_eq!(styled(&blocks, &Style::Code), vec!["code"]);
+}
+
+#[test]
+fn multi_line_quotes_stay_one_quote() {
+    let blocks = render("> first line\n> second line\n>\n> another paragraph\n");
+    assert_eq!(blocks.len(), 1);
+    assert_eq!(text(&blocks), "first line second line\n\nanother paragraph\n\n");
+    assert_eq!(
+        styled(&blocks, &Style::Quote),
+        vec!["first line second line\n\nanother paragraph"]
+    );
+}
+
+#[test]
+fn tables_are_laid_out_in_columns() {
+    let markdown = "| Name | Words |\n|:-----|------:|\n| Essay | 1200 |\n| **Poem** | 80 |\n";
+    let blocks = render(markdown);
+    assert_eq!(
+        text(&blocks),
+        "Name  │ Words\n──────┼──────\nEssay │  1200\nPoem  │    80\n\n"
+    );
+    assert_eq!(styled(&blocks, &Style::Strong), vec!["Poem"]);
+    assert_eq!(styled(&blocks, &Style::TableHeader), vec!["Name  │ Words"]);
+    assert!(markdown_to_html(markdown).contains("<table>"));
+}
+
+#[test]
+fn strikethrough_matches_the_export() {
+    let blocks = render("~~gone~~");
+    assert_eq!(styled(&blocks, &Style::Strikethrough), vec!["gone"]);
+    assert_eq!(markdown_to_html("~~gone~~"), "<p><del>gone</del></p>\n");
+}
+
+#[test]
+fn diff_finds_the_changed_blocks() {
+    let old = render("One.\n\nTwo.\n\nThree.\n");
+    let new = render("One.\n\nTwo, edited.\n\nThree.\n");
+    assert_eq!(diff(&old, &new), Splice { start: 1, old_end: 2, new_end: 2 });
+
+    // A new block at the end leaves the rest alone
+    let appended = render("One.\n\nTwo.\n\nThree.\n\nFour.\n");
+    assert_eq!(diff(&old, &appended), Splice { start: 3, old_end: 3, new_end: 4 });
+
+    // Moving blocks in the source without changing them redraws nothing
+    let moved = render("One.\n\n\n\nTwo.\n\nThree.\n");
+    assert_eq!(diff(&old, &moved), Splice { start: 3, old_end: 3, new_end: 3 });
+}
+
+#[test]
+fn positions_map_between_source_and_preview() {
+    let markdown = "# Title\n\nSome **bold** text.\n\n- one\n- two\n";
+    let blocks = render(markdown);
+    assert_eq!(text(&blocks), "Title\n\nSome bold text.\n\n• one\n• two\n\n");
+
+    // Block starts line up exactly, both ways
+    assert_eq!(preview_position(markdown, &blocks, 0), 0);
+    assert_eq!(preview_position(markdown, &blocks, 9), 7);
+    assert_eq!(source_position(markdown, &blocks, 7), 9);
+    assert_eq!(source_position(markdown, &blocks, 24), 30);
+
+    // The blank line between blocks belongs to the next one
+    assert_eq!(preview_position(markdown, &blocks, 8), 7);
+
+    // Inside a block positions move forward together
+    let middle = preview_position(markdown, &blocks, 20);
+    assert!(middle > 7 && middle < 22);
+    assert!(source_position(markdown, &blocks, middle) <= 20);
+
+    // Past the end, and against a render of older text
+    assert_eq!(preview_position(markdown, &blocks, 1000), text(&blocks).chars().count());
+    assert_eq!(source_position(markdown, &blocks, 1000), markdown.chars().count());
+    assert_eq!(preview_position("# Ti", &blocks, 3), 0);
+}
@@ -0,0 +1,133 @@
+mod common;
+
+use abbey_core::data::{Note, Project};
+use abbey_core::replace::{find_in_library, replace_in_library, Location, Matcher, Query};
+use common::composition;
+
+fn matcher(pattern: &str, configure: impl FnOnce(&mut Query)) -> Matcher {
+    let mut query = Query {
+        pattern: pattern.to_string(),
+        ..Query::default()
+    };
+    configure(&mut query);
+    Matcher::new(&query).unwrap()
+}
+
+#[test]
+fn options_change_what_matches() {
+    let text = "Cat cat concatenate CAT";
+    assert_eq!(matcher("cat", |_| {}).find(text).len(), 4);
+    assert_eq!(matcher("cat", |q| q.case_sensitive = true).find(text), vec![4..7, 11..14]);
+    assert_eq!(matcher("cat", |q| q.whole_word = true).find(text).len(), 3);
+    assert_eq!(matcher("c.t", |_| {}).find(text).len(), 0);
+    assert_eq!(matcher("c.t", |q| q.regex = true).find(text).len(), 4);
+    assert!(matcher("", |_| {}).find(text).is_empty());
+    assert!(Matcher::new(&Query {
+        pattern: "(".to_string(),
+        regex: true,
+        ..Query::default()
+    })
+    .is_err());
+}
+
+#[test]
+fn replacements_expand_groups_only_for_regexes() {
+    let by_regex = matcher(r"(\w+)@(\w+)", |q| q.regex = true);
+    assert_eq!(by_regex.replace("me@home", "$2 at ${1}", None), "home at me");
+
+    let literal = matcher("a.b", |_| {});
+    assert_eq!(literal.replace("a.b axb", "$1", None), "$1 axb");
+}
+
+#[test]
+fn replace_only_the_picked_matches() {
+    let m = matcher("one", |_| {});
+    let picked = [1].into_iter().collect();
+    assert_eq!(m.replace("one, one, one", "two", Some(&picked)), "one, two, one");
+}
+
+#[test]
+fn library_hits_preview_each_change() {
+    let mut comp = composition("Colour Study", "The colour of it.\nNo colour here, colour there.");
+    comp.notes.push(Note::new("Check colour names".to_string()));
+    let mut archived = composition("Old colour", "colour");
+    archived.archived = true;
+    let mut project = Project::new("Colours".to_string());
+    project.description = "About colour".to_string();
+
+    let m = matcher("colour", |q| q.whole_word = true);
+    let hits = find_in_library(&m, "color", &[comp.clone(), archived], std::slice::from_ref(&project));
+    let places: Vec<_> = hits.iter().map(|hit| (&hit.location, hit.index)).collect();
+    assert_eq!(
+        places,
+        vec![
+            (&Location::CompositionTitle(comp.id.clone()), 0),
+            (&Location::CompositionContent(comp.id.clone()), 0),
+            (&Location::CompositionContent(comp.id.clone()), 1),
+            (&Location::CompositionContent(comp.id.clone()), 2),
+            (&Location::Note(comp.id.clone(), comp.notes[0].id.clone()), 0),
+            (&Location::ProjectDescription(project.id.clone()), 0),
+        ]
+    );
+
+    let second = &hits[2];
+    assert_eq!(second.name, "Colour Study");
+    assert_eq!(second.before, "No colour here, colour there.");
+    assert_eq!(second.matched, 3..9);
+    assert_eq!(second.after, "No color here, colour there.");
+    assert_eq!(second.replaced, 3..8);
+}
+
+#[test]
+fn long_lines_are_cut_around_the_match() {
+    let line = format!("{} needle {}", "a ".repeat(100), "b ".repeat(100));
+    let hits = find_in_library(&matcher("needle", |_| {}), "pin", &[composition("Long", &line)], &[]);
+    let hit = &hits[0];
+    assert!(hit.before.starts_with('…') && hit.before.ends_with('…'));
+    assert!(hit.before.chars().count() <= 82);
+    let chars: Vec<char> = hit.before.chars().collect();
+    assert_eq!(chars[hit.matched.clone()].iter().collect::<String>(), "needle");
+}
+
+#[test]
+fn replace_the_picked_hits_in_the_library() {
+    let mut compositions = vec![composition("Draft", "teh cat and teh dog"), composition("Other", "nothing")];
+    compositions[0].notes.push(Note::new("fix teh typos".to_string()));
+    let mut projects = vec![Project::new("teh book".to_string())];
+    let before = compositions[0].updated_at;
+
+    let m = matcher("teh", |q| q.whole_word = true);
+    let hits = find_in_library(&m, "the", &compositions, &projects);
+    assert_eq!(hits.len(), 4);
+
+    // Leave the second match in the text alone
+    let picked: Vec<_> = hits.into_iter().filter(|hit| hit.index == 0).collect();
+    let replaced = replace_in_library(&m, "the", &mut compositions, &mut projects, &picked);
+
+    assert_eq!(replaced.count, 3);
+    assert_eq!(replaced.compositions, vec![compositions[0].id.clone()]);
+    assert_eq!(replaced.projects, vec![projects[0].id.clone()]);
+    assert_eq!(compositions[0].content, "the cat and teh dog");
+    assert_eq!(compositions[0].notes[0].content, "fix the typos");
+    assert_eq!(projects[0].title, "the book");
+    assert_eq!(compositions[1].content, "nothing");
+    assert!(compositions[0].updated_at >= before);
+}
+
+#[test]
+fn replacing_in_a_title_renames_links_to_it() {
+    let mut compositions = vec![composition("Harbour Notes", ""), composition("Index", "See [[Harbour Notes]].")];
+    let mut projects = Vec::new();
+
+    let m = matcher("Harbour", |_| {});
+    let hits = find_in_library(&m, "Harbor", &compositions, &projects);
+    // Only the title is picked; the link follows it all the same
+    let title = Location::CompositionTitle(compositions[0].id.clone());
+    let picked: Vec<_> = hits.into_iter().filter(|hit| hit.location == title).collect();
+    let replaced = replace_in_library(&m, "Harbor", &mut compositions, &mut projects, &picked);
+
+    assert_eq!(replaced.count, 1);
+    assert_eq!(replaced.compositions, vec![compositions[0].id.clone(), compositions[1].id.clone()]);
+    assert_eq!(compositions[0].title, "Harbor Notes");
+    assert_eq!(compositions[1].content, "See [[Harbor Notes]].");
+}
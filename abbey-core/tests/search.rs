@@ -0,0 +1,116 @@
+mod common;
+
+use abbey_core::data::{Flow, Note};
+use abbey_core::search::{self, Target};
+use common::composition;
+use chrono::{Duration, Utc};
+
+fn terms(words: &[&str]) -> Vec<String> {
+    words.iter().map(|w| w.to_string()).collect()
+}
+
+fn flow(content: &str) -> Flow {
+    let mut flow = Flow::new(10);
+    flow.content = content.to_string();
+    flow
+}
+
+#[test]
+fn result_ids_round_trip() {
+    let target = Target::Composition("abc".to_string());
+    assert_eq!(target.result_id(), "composition:abc");
+    assert_eq!(Target::parse("composition:abc"), Some(target));
+    assert_eq!(Target::parse("flow:f1"), Some(Target::Flow("f1".to_string())));
+    assert_eq!(Target::parse("folder:x"), None);
+}
+
+#[test]
+fn every_term_must_match() {
+    let comps = vec![
+        composition("Garden", "Tomatoes and basil"),
+        composition("Kitchen", "Basil pesto"),
+    ];
+    let flows = vec![flow("the basil grew tall in the garden")];
+
+    let results = search::search(&comps, &flows, &terms(&["basil", "GARDEN"]));
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0], Target::Composition(comps[0].id.clone()).result_id());
+    assert_eq!(results[1], Target::Flow(flows[0].id.clone()).result_id());
+
+    assert!(search::search(&comps, &flows, &terms(&["  "])).is_empty());
+}
+
+#[test]
+fn title_and_tag_matches_rank_first() {
+    let mut older = composition("Notes on rivers", "");
+    older.updated_at = Utc::now() - Duration::days(3);
+    let mut tagged = composition("Untitled draft", "");
+    tagged.tags = vec!["Rivers".to_string()];
+    let in_text = composition("Walk", "we crossed two rivers");
+    let mut in_note = composition("Other", "");
+    in_note.notes.push(Note::new("check the rivers chapter".to_string()));
+    let mut archived = composition("Rivers archive", "");
+    archived.archived = true;
+
+    let comps = vec![in_note.clone(), in_text.clone(), tagged.clone(), older.clone(), archived];
+    let ids: Vec<_> = search::search(&comps, &[], &terms(&["river"]))
+        .into_iter()
+        .map(|id| match Target::parse(&id) {
+            Some(Target::Composition(id)) => id,
+            other => panic!("unexpected result {:?}", other),
+        })
+        .collect();
+
+    assert_eq!(ids, vec![older.id, tagged.id, in_text.id, in_note.id]);
+}
+
+#[test]
+fn subsearch_narrows_previous_results() {
+    let comps = vec![composition("Spring", "rain"), composition("Summer", "rain and sun")];
+    let previous = search::search(&comps, &[], &terms(&["rain"]));
+    let mut stale = previous.clone();
+    stale.push("composition:deleted".to_string());
+
+    let narrowed = search::subsearch(&comps, &[], &stale, &terms(&["rain", "sun"]));
+    assert_eq!(narrowed, vec![Target::Composition(comps[1].id.clone()).result_id()]);
+}
+
+#[test]
+fn metas_show_an_excerpt_around_the_match() {
+    let text = "Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod tempor \
+                incididunt ut labore et dolore magna aliqua. The lighthouse keeper slept.";
+    let comps = vec![composition("", text)];
+    let flows = vec![flow("a short flow")];
+    let ids = vec![
+        Target::Composition(comps[0].id.clone()).result_id(),
+        Target::Flow(flows[0].id.clone()).result_id(),
+        "flow:missing".to_string(),
+    ];
+
+    let metas = search::result_metas(&comps, &flows, &ids, &terms(&["lighthouse"]));
+    assert_eq!(metas.len(), 2);
+    assert_eq!(metas[0].name, "Untitled");
+    assert!(metas[0].description.starts_with('…'));
+    assert!(metas[0].description.contains("The lighthouse keeper slept."));
+    assert!(metas[0].description.chars().count() <= search::EXCERPT_CHARS + 1);
+    assert!(metas[1].name.starts_with("Flow on "));
+    assert_eq!(metas[1].description, "a short flow");
+}
+
+#[test]
+fn excerpt_starts_at_the_beginning_without_a_match() {
+    let excerpt = search::excerpt_around("# Title\n\nFirst words of the piece", &terms(&["zebra"]), 16);
+    assert_eq!(excerpt, "Title First…");
+}
+
+#[test]
+fn find_by_id_or_title_prefers_exact_matches() {
+    let comps = vec![composition("Draft", ""), composition("draft", ""), composition("Letter", "")];
+    let find = |query: &str| search::find_by_id_or_title(&comps, query, |c| &c.id, |c| &c.title);
+
+    assert_eq!(find(&comps[2].id).unwrap().unwrap().id, comps[2].id);
+    assert_eq!(find("LETTER").unwrap().unwrap().id, comps[2].id);
+    assert_eq!(find(&comps[2].id[..8]).unwrap().unwrap().id, comps[2].id);
+    assert_eq!(find("Draft").unwrap_err().len(), 2);
+    assert!(find("nothing").unwrap().is_none());
+}
@@ -0,0 +1,124 @@
+//! Talks to the search provider over a private session bus, the way GNOME Shell
+//! does. Needs `dbus-daemon`; run with `cargo test -p abbey-core --features dbus`.
+#![cfg(feature = "dbus")]
+
+mod common;
+
+use abbey_core::data::{Composition, Flow};
+use abbey_core::dbus::{self, Activation, Corpus, SearchProvider, SEARCH_PROVIDER_INTERFACE};
+use abbey_core::search::Target;
+use common::strings;
+use gio::glib;
+use gio::prelude::*;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+const OBJECT_PATH: &str = "/app/abbey/Abbey/SearchProvider";
+
+/// Run `test` with a provider over `corpus` exported on a fresh session bus,
+/// handing it a client connection and the provider's bus name
+fn with_provider(corpus: Corpus, test: impl FnOnce(&gio::DBusConnection, &str, &RefCell<Vec<Activation>>)) {
+    common::with_bus(|service, client, name| {
+        let activations = Rc::new(RefCell::new(Vec::new()));
+        let recorded = activations.clone();
+        let provider = SearchProvider::new(move || corpus.clone(), move |a| recorded.borrow_mut().push(a));
+        let registration = provider.register(service, OBJECT_PATH).expect("register provider");
+
+        test(client, name, &activations);
+
+        service.unregister_object(registration).unwrap();
+    });
+}
+
+fn call(client: &gio::DBusConnection, name: &str, method: &str, args: glib::Variant) -> Result<glib::Variant, glib::Error> {
+    common::call(client, name, OBJECT_PATH, SEARCH_PROVIDER_INTERFACE, method, args)
+}
+
+fn corpus() -> Corpus {
+    let mut essay = Composition::new();
+    essay.title = "Harbour Lights".to_string();
+    essay.content = "The boats came in after dark.".to_string();
+    let mut flow = Flow::new(10);
+    flow.content = "thinking about harbour towns".to_string();
+
+    Corpus {
+        compositions: vec![essay],
+        flows: vec![flow],
+    }
+}
+
+#[test]
+fn initial_and_subsearch_results() {
+    let corpus = corpus();
+    let essay = Target::Composition(corpus.compositions[0].id.clone()).result_id();
+    let flow = Target::Flow(corpus.flows[0].id.clone()).result_id();
+
+    with_provider(corpus, |client, name, _| {
+        let reply = call(client, name, "GetInitialResultSet", (strings(&["harbour"]),).to_variant()).unwrap();
+        let (results,) = reply.get::<(Vec<String>,)>().unwrap();
+        assert_eq!(results, vec![essay.clone(), flow.clone()]);
+
+        let reply = call(
+            client,
+            name,
+            "GetSubsearchResultSet",
+            (results, strings(&["harbour", "towns"])).to_variant(),
+        )
+        .unwrap();
+        assert_eq!(reply.get::<(Vec<String>,)>().unwrap().0, vec![flow.clone()]);
+    });
+}
+
+#[test]
+fn result_metas_carry_name_and_excerpt() {
+    let corpus = corpus();
+    let essay = Target::Composition(corpus.compositions[0].id.clone()).result_id();
+
+    with_provider(corpus, |client, name, _| {
+        call(client, name, "GetInitialResultSet", (strings(&["boats"]),).to_variant()).unwrap();
+        let reply = call(client, name, "GetResultMetas", (vec![essay.clone()],).to_variant()).unwrap();
+        let (metas,) = reply.get::<(Vec<HashMap<String, glib::Variant>>,)>().unwrap();
+
+        assert_eq!(metas.len(), 1);
+        assert_eq!(metas[0]["id"].get::<String>().unwrap(), essay);
+        assert_eq!(metas[0]["name"].get::<String>().unwrap(), "Harbour Lights");
+        assert_eq!(
+            metas[0]["description"].get::<String>().unwrap(),
+            "The boats came in after dark."
+        );
+    });
+}
+
+#[test]
+fn activation_reaches_the_app() {
+    let corpus = corpus();
+    let essay_id = corpus.compositions[0].id.clone();
+
+    with_provider(corpus, |client, name, activations| {
+        let result_id = Target::Composition(essay_id.clone()).result_id();
+        call(client, name, "ActivateResult", (result_id, strings(&["boats"]), 42u32).to_variant()).unwrap();
+        call(client, name, "LaunchSearch", (strings(&["boats"]), 43u32).to_variant()).unwrap();
+        assert!(call(client, name, "ActivateResult", ("bogus", strings(&[]), 0u32).to_variant()).is_err());
+
+        assert_eq!(
+            *activations.borrow(),
+            vec![
+                Activation::Open {
+                    target: Target::Composition(essay_id.clone()),
+                    timestamp: 42
+                },
+                Activation::Launch {
+                    terms: strings(&["boats"]),
+                    timestamp: 43
+                },
+            ]
+        );
+    });
+}
+
+#[test]
+fn object_path_sits_below_the_app() {
+    assert_eq!(dbus::object_path("/app/abbey/Abbey", "SearchProvider"), OBJECT_PATH);
+    assert_eq!(dbus::object_path("/app/abbey/Abbey/", "SearchProvider"), OBJECT_PATH);
+}
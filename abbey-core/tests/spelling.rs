@@ -0,0 +1,183 @@
+use abbey_core::utils::spelling::{
+    choose_dictionary, find_dictionaries, misspelled, words, Dictionary, PersonalDictionary,
+};
+use std::fs;
+use tempfile::TempDir;
+
+const AFF: &str = "SET UTF-8
+TRY esianrtolcdugmphbyfvkwzxjq'
+REP 2
+REP f ph
+REP ph f
+FORBIDDENWORD !
+NEEDAFFIX ?
+
+PFX U Y 1
+PFX U 0 un .
+
+SFX S Y 3
+SFX S y ies [^aeiou]y
+SFX S 0 s [aeiou]y
+SFX S 0 s [^y]
+
+SFX D Y 2
+SFX D 0 ed [^e]
+SFX D 0 d e
+";
+
+const DIC: &str = "9
+happy/U
+try/SD
+play/SD
+like/UD
+telephone/S
+London
+irregardless/!
+sav/?D
+write
+";
+
+fn dictionary() -> Dictionary {
+    Dictionary::parse(AFF, DIC)
+}
+
+fn texts(markdown: &str) -> Vec<String> {
+    words(markdown).into_iter().map(|word| word.text).collect()
+}
+
+#[test]
+fn suffixes_follow_their_conditions() {
+    let dictionary = dictionary();
+    for word in ["try", "tries", "plays", "played", "liked", "telephones", "write"] {
+        assert!(dictionary.check(word), "{} should be known", word);
+    }
+    for word in ["trys", "plaies", "likeed", "writes", "telephoned"] {
+        assert!(!dictionary.check(word), "{} should be unknown", word);
+    }
+}
+
+#[test]
+fn prefixes_combine_with_suffixes_when_both_allow_it() {
+    let dictionary = dictionary();
+    assert!(dictionary.check("unhappy"));
+    assert!(dictionary.check("unliked"));
+    assert!(!dictionary.check("unplays"));
+}
+
+#[test]
+fn forbidden_and_affix_only_stems_are_not_words() {
+    let dictionary = dictionary();
+    assert!(!dictionary.check("irregardless"));
+    assert!(!dictionary.check("sav"));
+    assert!(dictionary.check("saved"));
+}
+
+#[test]
+fn capitalisation_is_allowed_but_not_lost() {
+    let dictionary = dictionary();
+    assert!(dictionary.check("Happy"));
+    assert!(dictionary.check("HAPPY"));
+    assert!(dictionary.check("London"));
+    assert!(dictionary.check("LONDON"));
+    assert!(!dictionary.check("london"));
+    assert!(!dictionary.check("hAppy"));
+}
+
+#[test]
+fn suggestions_cover_common_mistakes() {
+    let dictionary = dictionary();
+    assert_eq!(dictionary.suggest("telefone").first().map(String::as_str), Some("telephone"));
+    assert!(dictionary.suggest("tyr").contains(&"try".to_string()));
+    assert!(dictionary.suggest("hapy").contains(&"happy".to_string()));
+    assert!(dictionary.suggest("london").contains(&"London".to_string()));
+    assert!(dictionary.suggest("playtry").contains(&"play try".to_string()));
+    assert!(dictionary.suggest("Tyr").contains(&"Try".to_string()));
+
+    // Nothing forbidden or affix-only is offered
+    assert!(!dictionary.suggest("irregardles").contains(&"irregardless".to_string()));
+    assert!(!dictionary.suggest("sa").contains(&"sav".to_string()));
+}
+
+#[test]
+fn long_numeric_and_aliased_flags() {
+    let aff = "FLAG long\nAF 1\nAF AaBb\nSFX Aa Y 1\nSFX Aa 0 s .\nSFX Bb Y 1\nSFX Bb 0 ing .\n";
+    let dictionary = Dictionary::parse(aff, "1\nwalk/1\n");
+    assert!(dictionary.check("walks"));
+    assert!(dictionary.check("walking"));
+
+    let aff = "FLAG num\nSFX 101 Y 1\nSFX 101 0 s .\n";
+    let dictionary = Dictionary::parse(aff, "1\ncat/101,7\n");
+    assert!(dictionary.check("cats"));
+    assert!(!dictionary.check("dogs"));
+}
+
+#[test]
+fn eight_bit_dictionaries_are_decoded() {
+    let dir = TempDir::new().unwrap();
+    let aff = dir.path().join("fr.aff");
+    let dic = dir.path().join("fr.dic");
+    fs::write(&aff, b"SET ISO8859-1\n").unwrap();
+    fs::write(&dic, b"1\ncaf\xe9\n").unwrap();
+
+    let dictionary = Dictionary::load(&aff, &dic).unwrap();
+    assert!(dictionary.check("café"));
+}
+
+#[test]
+fn words_skip_code_addresses_and_markup() {
+    let markdown = "Don't check `code` or https://example.com/page, see [the docs](http://x.org) \
+                    <http://y.org> me@example.com <!-- note --> 2nd ‘quoted’ well-known\n\n\
+                    ```\nfn main\n```\n\n<div>\nblock html\n</div>\n";
+    assert_eq!(
+        texts(markdown),
+        vec!["Don't", "check", "or", "see", "the", "docs", "quoted", "well", "known"]
+    );
+}
+
+#[test]
+fn word_ranges_are_in_chars() {
+    let found = words("# Café\n\nnaïve **bold**");
+    let ranges: Vec<_> = found.iter().map(|word| word.range.clone()).collect();
+    assert_eq!(ranges, vec![2..6, 8..13, 16..20]);
+}
+
+#[test]
+fn personal_words_count_as_spelled() {
+    let dictionary = dictionary();
+    let mut personal = PersonalDictionary::new(vec!["Abbey".to_string()]);
+    assert!(personal.add("markdown"));
+    assert!(!personal.add("markdown"));
+
+    assert!(personal.contains("ABBEY"));
+    assert!(personal.contains("Markdown"));
+    assert!(!personal.contains("abbey"));
+    assert_eq!(personal.words(), vec!["Abbey", "markdown"]);
+
+    let wrong: Vec<_> = misspelled("Abbey tries hapy markdown, hapy again", &dictionary, &personal)
+        .into_iter()
+        .map(|word| (word.text, word.range))
+        .collect();
+    assert_eq!(wrong, vec![("hapy".to_string(), 12..16), ("hapy".to_string(), 27..31), ("again".to_string(), 32..37)]);
+}
+
+#[test]
+fn dictionaries_are_found_and_chosen_by_language() {
+    let first = TempDir::new().unwrap();
+    let second = TempDir::new().unwrap();
+    for (dir, name) in [(&first, "en_US"), (&first, "hyph_en_US"), (&second, "en_US"), (&second, "de_DE")] {
+        fs::write(dir.path().join(format!("{}.aff", name)), "").unwrap();
+        fs::write(dir.path().join(format!("{}.dic", name)), "").unwrap();
+    }
+    // A word list without rules isn't a dictionary
+    fs::write(first.path().join("fr_FR.dic"), "").unwrap();
+
+    let found = find_dictionaries(&[first.path().to_path_buf(), second.path().to_path_buf()]);
+    let languages: Vec<_> = found.iter().map(|d| d.language.as_str()).collect();
+    assert_eq!(languages, vec!["de_DE", "en_US"]);
+    assert_eq!(found[1].dic, first.path().join("en_US.dic"));
+
+    assert_eq!(choose_dictionary(&found, "en_US").unwrap().language, "en_US");
+    assert_eq!(choose_dictionary(&found, "en-GB").unwrap().language, "en_US");
+    assert_eq!(choose_dictionary(&found, "de").unwrap().language, "de_DE");
+    assert!(choose_dictionary(&found, "fr_FR").is_none());
+}
@@ -0,0 +1,110 @@
+use abbey_core::data::{Composition, Flow, Note, Project};
+use abbey_core::stats::{self, LibraryStats};
+use chrono::{Duration, Local, NaiveDate, TimeZone, Utc};
+
+/// A flow at noon local time on `day`, so it lands on that local day
+fn flow_on(day: NaiveDate, content: &str, seconds: u64) -> Flow {
+    let mut flow = Flow::new(10);
+    flow.content = content.to_string();
+    flow.actual_duration_seconds = seconds;
+    flow.created_at = Local
+        .from_local_datetime(&day.and_hms_opt(12, 0, 0).unwrap())
+        .unwrap()
+        .with_timezone(&Utc);
+    flow
+}
+
+fn day(y: i32, m: u32, d: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(y, m, d).unwrap()
+}
+
+#[test]
+fn library_totals() {
+    let mut essay = Composition::new();
+    essay.content = "one two three".to_string();
+    essay.notes.push(Note::new("a".to_string()));
+    let mut story = Composition::new();
+    story.content = "four five".to_string();
+    let mut old = Composition::new();
+    old.content = "archived words don't count".to_string();
+    old.archived = true;
+
+    let flows = vec![
+        flow_on(day(2026, 3, 2), "alpha beta", 600),
+        flow_on(day(2026, 3, 1), "gamma", 300),
+    ];
+    let projects = vec![Project::new("Book".to_string())];
+
+    let stats = LibraryStats::on_day(&[essay, story, old], &flows, &projects, day(2026, 3, 2));
+
+    assert_eq!(
+        stats,
+        LibraryStats {
+            compositions: 2,
+            archived: 1,
+            composition_words: 5,
+            notes: 1,
+            projects: 1,
+            flows: 2,
+            flow_words: 3,
+            flow_seconds: 900,
+            flow_streak_days: 2,
+        }
+    );
+}
+
+#[test]
+fn empty_library_has_zero_stats() {
+    assert_eq!(LibraryStats::new(&[], &[], &[]), LibraryStats::default());
+}
+
+#[test]
+fn streak_counts_consecutive_days_up_to_today() {
+    let flows = vec![
+        flow_on(day(2026, 3, 10), "a", 0),
+        flow_on(day(2026, 3, 10), "b", 0),
+        flow_on(day(2026, 3, 9), "c", 0),
+        flow_on(day(2026, 3, 8), "d", 0),
+        flow_on(day(2026, 3, 6), "e", 0),
+    ];
+
+    assert_eq!(stats::flow_streak(&flows, day(2026, 3, 10)), 3);
+    // Today's flow hasn't happened yet
+    assert_eq!(stats::flow_streak(&flows, day(2026, 3, 11)), 3);
+    // A missed day breaks it
+    assert_eq!(stats::flow_streak(&flows, day(2026, 3, 12)), 0);
+    assert_eq!(stats::flow_streak(&flows, day(2026, 3, 7)), 1);
+    assert_eq!(stats::flow_streak(&[], day(2026, 3, 7)), 0);
+}
+
+#[test]
+fn words_are_grouped_by_local_day() {
+    let flows = vec![
+        flow_on(day(2026, 1, 2), "one two", 0),
+        flow_on(day(2026, 1, 2), "three", 0),
+        flow_on(day(2026, 1, 1), "four", 0),
+    ];
+
+    let by_day = stats::flow_words_by_day(&flows);
+    assert_eq!(by_day.len(), 2);
+    assert_eq!(by_day[&day(2026, 1, 1)], 1);
+    assert_eq!(by_day[&day(2026, 1, 2)], 3);
+    assert_eq!(stats::local_day(&flows[2].created_at), day(2026, 1, 1));
+}
+
+#[test]
+fn flows_since_filters_and_sorts_newest_first() {
+    let now = Utc::now();
+    let mut old = Flow::new(5);
+    old.created_at = now - Duration::days(10);
+    let mut recent = Flow::new(5);
+    recent.created_at = now - Duration::days(1);
+    let mut newest = Flow::new(5);
+    newest.created_at = now;
+
+    let flows = vec![recent.clone(), old, newest.clone()];
+    let since = stats::flows_since(&flows, now - Duration::days(2));
+
+    let ids: Vec<_> = since.iter().map(|f| f.id.as_str()).collect();
+    assert_eq!(ids, vec![newest.id.as_str(), recent.id.as_str()]);
+}
@@ -0,0 +1,327 @@
+mod common;
+
+use abbey_core::data::{Flow, FocusScope, Folder, Note, Project, Settings, Storage, StorageError};
+use common::composition;
+use std::fs;
+use tempfile::TempDir;
+
+fn open_library() -> (TempDir, Storage) {
+    let dir = TempDir::new().unwrap();
+    let storage = Storage::open(dir.path().join("Abbey")).unwrap();
+    (dir, storage)
+}
+
+#[test]
+fn open_creates_library_structure() {
+    let dir = TempDir::new().unwrap();
+    let base = dir.path().join("nested").join("Abbey");
+    let storage = Storage::open(base.clone()).unwrap();
+
+    assert_eq!(storage.base_dir(), &base);
+    assert!(storage.compositions_dir().is_dir());
+    assert!(storage.flows_dir().is_dir());
+    assert!(storage.projects_dir().is_dir());
+    assert!(base.join(".recovery").is_dir());
+    assert!(!storage.is_read_only());
+}
+
+#[test]
+fn open_fails_when_base_dir_is_a_file() {
+    let dir = TempDir::new().unwrap();
+    let base = dir.path().join("Abbey");
+    fs::write(&base, "not a directory").unwrap();
+
+    assert!(matches!(Storage::open(base), Err(StorageError::Io { .. })));
+}
+
+#[test]
+fn empty_library_loads_defaults() {
+    let (_dir, storage) = open_library();
+
+    assert!(storage.load_compositions().unwrap().is_empty());
+    assert!(storage.load_flows().unwrap().is_empty());
+    assert!(storage.load_projects().unwrap().is_empty());
+    assert!(storage.load_folders().unwrap().is_empty());
+    assert!(storage.load_recovery().unwrap().is_empty());
+
+    let settings = storage.load_settings().unwrap();
+    assert_eq!(settings.theme, Settings::default().theme);
+    assert_eq!(settings.session.nav_section, "writing");
+}
+
+#[test]
+fn compositions_round_trip() {
+    let (_dir, storage) = open_library();
+    let mut first = composition("First", "one two three");
+    first.notes.push(Note::new("check the ending".to_string()));
+    first.tags.push("essay".to_string());
+    let second = composition("Second", "four");
+
+    storage.save_compositions(&[first.clone(), second.clone()]).unwrap();
+    let loaded = storage.load_compositions().unwrap();
+
+    assert_eq!(loaded.len(), 2);
+    assert_eq!(loaded[0].id, first.id);
+    assert_eq!(loaded[0].content, "one two three");
+    assert_eq!(loaded[0].word_count, 3);
+    assert_eq!(loaded[0].notes[0].content, "check the ending");
+    assert_eq!(loaded[0].tags, vec!["essay".to_string()]);
+    assert_eq!(loaded[1].id, second.id);
+}
+
+#[test]
+fn save_composition_inserts_new_first_and_updates_in_place() {
+    let (_dir, storage) = open_library();
+    let older = composition("Older", "a");
+    let newer = composition("Newer", "b");
+
+    storage.save_composition(&older).unwrap();
+    storage.save_composition(&newer).unwrap();
+
+    let mut edited = older.clone();
+    edited.content = "a revised".to_string();
+    storage.save_composition(&edited).unwrap();
+
+    let loaded = storage.load_compositions().unwrap();
+    assert_eq!(loaded.len(), 2);
+    assert_eq!(loaded[0].id, newer.id);
+    assert_eq!(loaded[1].id, older.id);
+    assert_eq!(loaded[1].content, "a revised");
+}
+
+#[test]
+fn save_composition_writes_markdown_copy() {
+    let (_dir, storage) = open_library();
+    let mut comp = composition("Notes: a/b?", "Body text");
+    comp.notes.push(Note::new("first note".to_string()));
+
+    storage.save_composition(&comp).unwrap();
+
+    let path = storage.compositions_dir().join("Notes_ a_b_.md");
+    let markdown = fs::read_to_string(path).unwrap();
+    assert!(markdown.starts_with("# Notes: a/b?\n\nBody text"));
+    assert!(markdown.contains("## Notes\n\n- first note\n"));
+}
+
+#[test]
+fn folders_and_settings_round_trip() {
+    let (_dir, storage) = open_library();
+    let folder = Folder::new("Drafts".to_string());
+    storage.save_folders(&[folder]).unwrap();
+
+    let mut settings = Settings {
+        theme: "parchment".to_string(),
+        last_opened_composition: Some("abc".to_string()),
+        ..Settings::default()
+    };
+    settings.session.cursor_offset = 42;
+    storage.save_settings(&settings).unwrap();
+
+    let folders = storage.load_folders().unwrap();
+    assert_eq!(folders.len(), 1);
+    assert_eq!(folders[0].name, "Drafts");
+    assert!(folders[0].expanded);
+
+    let loaded = storage.load_settings().unwrap();
+    assert_eq!(loaded.theme, "parchment");
+    assert_eq!(loaded.last_opened_composition.as_deref(), Some("abc"));
+    assert_eq!(loaded.session.cursor_offset, 42);
+}
+
+#[test]
+fn settings_without_session_use_defaults() {
+    let (_dir, storage) = open_library();
+    fs::write(
+        storage.base_dir().join("settings.json"),
+        r#"{"theme":"newspaper","font_size":20,"line_height":1.5,
+            "microblog":{"endpoint":"","api_key":"","blog_id":null},
+            "last_opened_composition":null}"#,
+    )
+    .unwrap();
+
+    let settings = storage.load_settings().unwrap();
+    assert_eq!(settings.theme, "newspaper");
+    assert_eq!(settings.session.window_width, 1200);
+    assert!(settings.session.sidebar_visible);
+    assert!(settings.spelling.enabled);
+    assert!(settings.lint.enabled && settings.lint.disabled_rules.is_empty());
+    assert!(!settings.focus.enabled && settings.focus.typewriter);
+    assert_eq!(settings.focus.scope, FocusScope::Sentence);
+    assert_eq!(settings.focus.column_width, 72);
+    assert!(!settings.typography.smart_punctuation && !settings.typography.french_spacing);
+}
+
+#[test]
+fn personal_dictionary_round_trips() {
+    let (_dir, storage) = open_library();
+    assert!(storage.load_personal_dictionary().unwrap().is_empty());
+
+    storage.save_personal_dictionary(&["Abbey".to_string(), "markdown".to_string()]).unwrap();
+    assert_eq!(storage.load_personal_dictionary().unwrap(), vec!["Abbey", "markdown"]);
+
+    storage.set_read_only(true);
+    assert!(matches!(storage.save_personal_dictionary(&[]), Err(StorageError::ReadOnly)));
+}
+
+#[test]
+fn compositions_without_folder_field_still_load() {
+    let (_dir, storage) = open_library();
+    fs::write(
+        storage.base_dir().join("compositions.json"),
+        r#"[{"id":"1","title":"Old","content":"text","notes":[],
+             "created_at":"2024-01-01T10:00:00Z","updated_at":"2024-01-01T10:00:00Z",
+             "archived":false,"word_count":1,"tags":[]}]"#,
+    )
+    .unwrap();
+
+    let loaded = storage.load_compositions().unwrap();
+    assert_eq!(loaded[0].title, "Old");
+    assert_eq!(loaded[0].folder_id, None);
+    assert_eq!(loaded[0].language, None);
+    assert!(loaded[0].lint_ignores.is_empty());
+}
+
+#[test]
+fn corrupt_file_is_reported_not_replaced() {
+    let (_dir, storage) = open_library();
+    let path = storage.base_dir().join("compositions.json");
+    fs::write(&path, "[{ broken").unwrap();
+
+    match storage.load_compositions() {
+        Err(StorageError::Corrupt { path: reported, .. }) => assert_eq!(reported, path),
+        other => panic!("expected a corrupt file error, got {:?}", other.map(|c| c.len())),
+    }
+    assert_eq!(fs::read_to_string(&path).unwrap(), "[{ broken");
+}
+
+#[test]
+fn writes_leave_no_temporary_files() {
+    let (_dir, storage) = open_library();
+    storage.save_compositions(&[composition("A", "b")]).unwrap();
+    storage.save_compositions(&[composition("C", "d")]).unwrap();
+
+    let leftovers: Vec<_> = fs::read_dir(storage.base_dir())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .filter(|name| name.ends_with(".tmp") || name == ".write-test")
+        .collect();
+    assert!(leftovers.is_empty(), "left behind {:?}", leftovers);
+}
+
+#[test]
+fn read_only_library_loads_but_refuses_writes() {
+    let (dir, storage) = open_library();
+    let comp = composition("Kept", "safe");
+    storage.save_compositions(std::slice::from_ref(&comp)).unwrap();
+    drop(storage);
+
+    let read_only = Storage::open_read_only(dir.path().join("Abbey"));
+    assert!(read_only.is_read_only());
+    assert_eq!(read_only.load_compositions().unwrap()[0].id, comp.id);
+
+    let err = read_only.save_compositions(&[]).unwrap_err();
+    assert!(err.is_read_only());
+    assert!(read_only.append_flow(&Flow::new(5)).unwrap_err().is_read_only());
+    assert!(read_only.clear_recovery().unwrap_err().is_read_only());
+    assert_eq!(read_only.load_compositions().unwrap().len(), 1);
+}
+
+#[test]
+fn read_only_library_never_touches_disk() {
+    let dir = TempDir::new().unwrap();
+    let base = dir.path().join("Missing");
+    let storage = Storage::open_read_only(base.clone());
+
+    assert!(storage.load_compositions().unwrap().is_empty());
+    assert!(storage.save_settings(&Settings::default()).is_err());
+    assert!(!base.exists());
+}
+
+#[test]
+fn set_read_only_toggles_writes() {
+    let (_dir, storage) = open_library();
+    storage.set_read_only(true);
+    assert!(storage.save_folders(&[]).unwrap_err().is_read_only());
+
+    storage.set_read_only(false);
+    storage.save_folders(&[]).unwrap();
+}
+
+#[test]
+fn append_flow_prepends_and_journals() {
+    let (_dir, storage) = open_library();
+    let mut first = Flow::new(10);
+    first.content = "morning pages".to_string();
+    let mut second = Flow::new(15);
+    second.content = "evening pages here".to_string();
+
+    storage.append_flow(&first).unwrap();
+    storage.append_flow(&second).unwrap();
+
+    let flows = storage.load_flows().unwrap();
+    assert_eq!(flows.len(), 2);
+    assert_eq!(flows[0].id, second.id);
+
+    let journal = fs::read_to_string(storage.flows_dir().join("Flow Journal.md")).unwrap();
+    assert!(journal.starts_with("# Flow Journal\n"));
+    assert!(journal.contains("(10 min, 2 words)\n\nmorning pages"));
+    assert!(journal.find("morning pages").unwrap() < journal.find("evening pages").unwrap());
+}
+
+#[test]
+fn save_project_creates_folder_once_it_has_compositions() {
+    let (_dir, storage) = open_library();
+    let mut project = Project::new("Book One".to_string());
+
+    storage.save_project(&project).unwrap();
+    assert!(!storage.projects_dir().join("Book One").exists());
+
+    project.add_composition("c1".to_string());
+    storage.save_project(&project).unwrap();
+    assert!(storage.projects_dir().join("Book One").is_dir());
+
+    let projects = storage.load_projects().unwrap();
+    assert_eq!(projects.len(), 1);
+    assert_eq!(projects[0].composition_ids, vec!["c1".to_string()]);
+}
+
+#[test]
+fn export_project_writes_markdown_into_project_folder() {
+    let (_dir, storage) = open_library();
+    let chapter = composition("Chapter", "It begins.");
+    let mut project = Project::new("Book".to_string());
+    project.add_composition(chapter.id.clone());
+
+    let md = storage.export_project_to_markdown(&project, &[chapter]).unwrap();
+
+    let written = fs::read_to_string(storage.projects_dir().join("Book").join("Book.md")).unwrap();
+    assert_eq!(md, written);
+    assert!(md.contains("## Chapter\n\nIt begins."));
+}
+
+#[test]
+fn recovery_journal_round_trip() {
+    let (_dir, storage) = open_library();
+    let comp = composition("Unsaved", "edits in flight");
+    storage.write_recovery(&comp).unwrap();
+    fs::write(storage.base_dir().join(".recovery").join("junk.json"), "{").unwrap();
+    fs::write(storage.base_dir().join(".recovery").join("notes.txt"), "ignored").unwrap();
+
+    let recovered = storage.load_recovery().unwrap();
+    assert_eq!(recovered.len(), 1);
+    assert_eq!(recovered[0].content, "edits in flight");
+
+    storage.clear_recovery().unwrap();
+    assert!(storage.load_recovery().unwrap().is_empty());
+}
+
+#[test]
+fn libraries_are_independent() {
+    let (_a_dir, a) = open_library();
+    let (_b_dir, b) = open_library();
+
+    a.save_compositions(&[composition("Only in A", "")]).unwrap();
+
+    assert_eq!(a.load_compositions().unwrap().len(), 1);
+    assert!(b.load_compositions().unwrap().is_empty());
+}
@@ -0,0 +1,113 @@
+use abbey_core::data::{Comment, Composition, Suggestion, SuggestionKind};
+
+/// A composition with `text` suggested as `kind` where it first appears
+fn suggested(content: &str, text: &str, kind: SuggestionKind) -> Composition {
+    let mut comp = Composition::new();
+    comp.content = content.to_string();
+    let start = content[..content.find(text).unwrap()].chars().count();
+    let end = start + text.chars().count();
+    comp.suggestions.push(Suggestion::new(kind, start, end, text.to_string(), "Ann".to_string()));
+    comp
+}
+
+#[test]
+fn accepting_keeps_insertions_and_makes_deletions() {
+    let mut comp = suggested("The very quick fox.", "very ", SuggestionKind::Insertion);
+    let id = comp.suggestions[0].id.clone();
+    assert!(comp.resolve_suggestion(&id, true));
+    assert_eq!(comp.content, "The very quick fox.");
+    assert!(comp.suggestions.is_empty());
+    assert!(!comp.resolve_suggestion(&id, true));
+
+    let mut comp = suggested("The very quick fox.", "very ", SuggestionKind::Deletion);
+    let id = comp.suggestions[0].id.clone();
+    comp.resolve_suggestion(&id, true);
+    assert_eq!(comp.content, "The quick fox.");
+    assert_eq!(comp.word_count, 3);
+}
+
+#[test]
+fn rejecting_takes_insertions_out_and_keeps_deletions() {
+    let mut comp = suggested("Café au lait, s'il vous plaît.", "au lait", SuggestionKind::Insertion);
+    let id = comp.suggestions[0].id.clone();
+    comp.resolve_suggestion(&id, false);
+    assert_eq!(comp.content, "Café , s'il vous plaît.");
+
+    let mut comp = suggested("The very quick fox.", "very ", SuggestionKind::Deletion);
+    let id = comp.suggestions[0].id.clone();
+    comp.resolve_suggestion(&id, false);
+    assert_eq!(comp.content, "The very quick fox.");
+    assert!(comp.suggestions.is_empty());
+}
+
+#[test]
+fn changing_the_text_without_suggestions_keeps_them_pending() {
+    // "slow" struck out and "quick" put in its place
+    let mut comp = suggested("The slowquick fox jumps.", "slow", SuggestionKind::Deletion);
+    comp.suggestions.push(Suggestion::new(SuggestionKind::Insertion, 8, 13, "quick".to_string(), "Ann".to_string()));
+    assert_eq!(comp.content_without_suggestions(), "The slow fox jumps.");
+
+    comp.set_content_without_suggestions("The slow fox leaps.");
+    assert_eq!(comp.content, "The slowquick fox leaps.");
+    assert_eq!(comp.content_without_suggestions(), "The slow fox leaps.");
+    assert_eq!(comp.suggestions.len(), 2);
+    assert_eq!((comp.suggestions[1].start, comp.suggestions[1].end), (8, 13));
+
+    comp.set_content_without_suggestions("A slow fox leaps.");
+    assert_eq!(comp.content, "A slowquick fox leaps.");
+    assert_eq!((comp.suggestions[0].start, comp.suggestions[0].end), (2, 6));
+    assert_eq!((comp.suggestions[1].start, comp.suggestions[1].end), (6, 11));
+
+    // A change over a suggested insertion replaces it with the rest
+    comp.set_content_without_suggestions("A fox leaps.");
+    assert_eq!(comp.content, "A fox leaps.");
+    assert!(comp.suggestions.is_empty());
+}
+
+#[test]
+fn resolving_moves_later_suggestions_and_comments_along() {
+    // "slow" struck out and "quick" put in its place, with a comment after
+    let mut comp = suggested("The slowquick fox jumps.", "slow", SuggestionKind::Deletion);
+    comp.suggestions.push(Suggestion::new(SuggestionKind::Insertion, 8, 13, "quick".to_string(), "Ann".to_string()));
+    comp.comments.push(Comment::new(18, 23, "jumps".to_string(), "Leaps?".to_string()));
+
+    let id = comp.suggestions[0].id.clone();
+    comp.resolve_suggestion(&id, true);
+    assert_eq!(comp.content, "The quick fox jumps.");
+    assert_eq!((comp.suggestions[0].start, comp.suggestions[0].end), (4, 9));
+    assert_eq!((comp.comments[0].start, comp.comments[0].end), (14, 19));
+
+    let mut comp = suggested("The slowquick fox jumps.", "slow", SuggestionKind::Deletion);
+    comp.suggestions.push(Suggestion::new(SuggestionKind::Insertion, 8, 13, "quick".to_string(), "Ann".to_string()));
+    comp.resolve_all_suggestions(false);
+    assert_eq!(comp.content, "The slow fox jumps.");
+    assert!(comp.suggestions.is_empty());
+}
+
+#[test]
+fn suggestions_follow_edits_and_go_with_their_text() {
+    let mut comp = suggested("One two three.", "two ", SuggestionKind::Insertion);
+    let old = std::mem::replace(&mut comp.content, "Zero. One two three.".to_string());
+    comp.follow_edit(&old);
+    assert_eq!((comp.suggestions[0].start, comp.suggestions[0].end), (10, 14));
+
+    let old = std::mem::replace(&mut comp.content, "Zero. One three.".to_string());
+    comp.follow_edit(&old);
+    assert!(comp.suggestions.is_empty());
+}
+
+#[test]
+fn compositions_saved_before_suggestions_still_load() {
+    let comp = Composition::new();
+    let mut json = serde_json::to_value(&comp).unwrap();
+    let fields = json.as_object_mut().unwrap();
+    fields.remove("suggestions");
+    fields.remove("suggesting");
+    let loaded: Composition = serde_json::from_value(json).unwrap();
+    assert!(loaded.suggestions.is_empty());
+    assert!(!loaded.suggesting);
+
+    let comp = suggested("A b.", "b", SuggestionKind::Deletion);
+    let json = serde_json::to_string(&comp).unwrap();
+    assert!(json.contains("\"kind\":\"deletion\""));
+}
@@ -0,0 +1,91 @@
+use abbey_core::data::{Composition, Flow};
+use abbey_core::utils::text_stats::{plain_text, word_count, words, TextStats};
+use std::time::Duration;
+
+#[test]
+fn markup_is_not_counted_as_words() {
+    assert_eq!(word_count("# Title\n\nSome **bold** and _em_ text - with [a link](https://example.com)."), 9);
+    assert_eq!(word_count("* one\n* two\n\n---\n\n> quoted"), 3);
+    assert_eq!(word_count("<div>html</div>\n\nText[^1]\n\n[^1]: Note"), 2);
+}
+
+#[test]
+fn words_keep_their_inner_punctuation() {
+    assert_eq!(
+        words("snake_case don't well-known 3.14 1,000 U.S. end."),
+        vec!["snake_case", "don't", "well-known", "3.14", "1,000", "U.S", "end"]
+    );
+    assert_eq!(words("“Quoted” -- dashes — here"), vec!["Quoted", "dashes", "here"]);
+    assert_eq!(plain_text("snake_case and __strong__ `code_name`"), "snake_case and strong code_name");
+}
+
+#[test]
+fn chinese_and_japanese_count_per_character() {
+    assert_eq!(words("我爱你"), vec!["我", "爱", "你"]);
+    assert_eq!(word_count("東京は大きい city"), 7);
+
+    let stats = TextStats::of("我爱你。你好！");
+    assert_eq!((stats.words, stats.cjk_words, stats.sentences), (5, 5, 2));
+}
+
+#[test]
+fn characters_count_what_readers_see() {
+    // An e with a combining accent, and a family emoji joined from three
+    let stats = TextStats::of("Cafe\u{301} \u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}");
+    assert_eq!(stats.characters, 6);
+    assert_eq!(stats.characters_without_spaces, 5);
+    assert_eq!(words("Cafe\u{301}"), vec!["Cafe\u{301}"]);
+}
+
+#[test]
+fn sentences_and_paragraphs() {
+    let stats = TextStats::of(
+        "# A Heading\n\nDr. Smith arrived at 3.30 p.m. on time. \"Really?\" she asked! Then left\n\n- an item\n- another.\n\n```\ncode here\n```",
+    );
+    assert_eq!(stats.paragraphs, 5);
+    // The heading, three in the paragraph, one per item, and the code
+    assert_eq!(stats.sentences, 7);
+}
+
+#[test]
+fn reading_time_allows_for_cjk() {
+    let stats = TextStats::of(&"word ".repeat(400));
+    assert_eq!(stats.reading_time(), Duration::from_secs(120));
+    assert_eq!(stats.reading_minutes(), 2);
+
+    let cjk = TextStats::of(&"字".repeat(450));
+    assert_eq!(cjk.reading_minutes(), 1);
+    assert_eq!(TextStats::of("").reading_minutes(), 0);
+}
+
+#[test]
+fn readability_scores() {
+    let easy = TextStats::of("The cat sat on the mat. The dog ran to the park.");
+    assert_eq!((easy.words, easy.sentences, easy.syllables), (12, 2, 12));
+    let ease = easy.flesch_reading_ease().unwrap();
+    assert!((ease - 116.145).abs() < 0.01, "{}", ease);
+    let grade = easy.flesch_kincaid_grade().unwrap();
+    assert!((grade - (-1.45)).abs() < 0.01, "{}", grade);
+
+    let hard = TextStats::of(
+        "Institutional considerations necessitate comprehensive reevaluation of organizational methodologies.",
+    );
+    assert!(hard.flesch_reading_ease().unwrap() < 0.0);
+    assert!(hard.flesch_kincaid_grade().unwrap() > easy.flesch_kincaid_grade().unwrap());
+    assert!(hard.automated_readability_index().unwrap() > easy.automated_readability_index().unwrap());
+    assert!(hard.coleman_liau_index().unwrap() > easy.coleman_liau_index().unwrap());
+
+    assert_eq!(TextStats::of("").flesch_reading_ease(), None);
+}
+
+#[test]
+fn models_count_words_the_same_way() {
+    let mut composition = Composition::new();
+    composition.content = "## Heading\n\n**Bold** move_fast 好的".to_string();
+    composition.update_word_count();
+    assert_eq!(composition.word_count, 5);
+
+    let mut flow = Flow::new(10);
+    flow.content = "* not a word".to_string();
+    assert_eq!(flow.word_count(), 3);
+}
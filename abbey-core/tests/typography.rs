@@ -0,0 +1,96 @@
+use abbey_core::utils::typography::{in_prose, smarten, smarten_edits, substitute, Options};
+
+fn typed(c: char, before: &str, after: &str) -> Option<(usize, String)> {
+    substitute(c, before, after, &Options::default()).map(|r| (r.remove, r.text))
+}
+
+fn french() -> Options {
+    Options { french_spacing: true }
+}
+
+#[test]
+fn quotes_open_and_close() {
+    assert_eq!(typed('"', "", ""), Some((0, "“".to_string())));
+    assert_eq!(typed('"', "She said ", ""), Some((0, "“".to_string())));
+    assert_eq!(typed('"', "She said “hello", ""), Some((0, "”".to_string())));
+    assert_eq!(typed('"', "(*", ""), Some((0, "“".to_string())));
+    assert_eq!(typed('\'', "", "quoted"), Some((0, "‘".to_string())));
+    assert_eq!(typed('\'', "‘quoted", ""), Some((0, "’".to_string())));
+}
+
+#[test]
+fn apostrophes_are_closing_quotes() {
+    assert_eq!(typed('\'', "don", "t"), Some((0, "’".to_string())));
+    assert_eq!(typed('\'', "back in the ", "90s"), Some((0, "’".to_string())));
+    assert_eq!(typed('\'', "rock ", "n roll"), Some((0, "’".to_string())));
+    assert_eq!(typed('\'', "", "Twas"), Some((0, "’".to_string())));
+}
+
+#[test]
+fn dashes_and_ellipses() {
+    assert_eq!(typed('-', "pages 1-", ""), Some((1, "–".to_string())));
+    assert_eq!(typed('-', "wait–", ""), Some((1, "—".to_string())));
+    assert_eq!(typed('-', "well", ""), None);
+    assert_eq!(typed('.', "and so..", ""), Some((2, "…".to_string())));
+    assert_eq!(typed('.', "end.", ""), None);
+}
+
+#[test]
+fn markup_and_escapes_are_left_alone() {
+    assert_eq!(typed('-', "--", ""), None);
+    assert_eq!(typed('-', "  -", ""), None);
+    assert_eq!(typed('-', "| --", ""), None);
+    assert_eq!(typed('-', "<!-", ""), None);
+    assert_eq!(typed('"', "a \\", ""), None);
+}
+
+#[test]
+fn french_spacing_is_optional() {
+    assert_eq!(typed('?', "Vraiment ", ""), None);
+    let space = |c, before: &str| substitute(c, before, "", &french()).map(|r| (r.remove, r.text));
+    assert_eq!(space('?', "Vraiment "), Some((1, "\u{202F}?".to_string())));
+    assert_eq!(space(':', "Voici "), Some((1, "\u{A0}:".to_string())));
+    assert_eq!(space(' ', "«"), Some((0, "\u{A0}".to_string())));
+    assert_eq!(space('»', "« Oui "), Some((1, "\u{A0}»".to_string())));
+    assert_eq!(space('?', "Vraiment"), None);
+}
+
+#[test]
+fn code_is_not_prose() {
+    let text = "Some `code` and\n\n```\nfenced\n```\n\n[a link](http://x.org/a--b)";
+    assert!(in_prose(text, 2));
+    assert!(!in_prose(text, 7));
+    assert!(in_prose(text, 13));
+    assert!(!in_prose(text, 24));
+    assert!(in_prose(text, 36));
+    assert!(!in_prose(text, 48));
+
+    // Not closed yet
+    assert!(!in_prose("Type `unfinished", 12));
+    assert!(!in_prose("```\nstill open", 8));
+}
+
+#[test]
+fn smarten_a_document() {
+    let text = "\"Well---I don't know...\" she said -- twice.\n\n---\n\n- item\n\n`\"code\"` and 'quoted'\n\n```\n\"raw\" -- ...\n```\n";
+    let expected = "“Well—I don’t know…” she said – twice.\n\n---\n\n- item\n\n`\"code\"` and ‘quoted’\n\n```\n\"raw\" -- ...\n```\n";
+    assert_eq!(smarten(text, &Options::default()), expected);
+    assert_eq!(smarten(expected, &Options::default()), expected);
+}
+
+#[test]
+fn smarten_spans_emphasis_and_french() {
+    assert_eq!(smarten("*\"Emphasised\"*", &Options::default()), "*“Emphasised”*");
+    assert_eq!(smarten("« Bonjour ! » Ça va ?", &french()), "«\u{A0}Bonjour\u{202F}!\u{A0}» Ça va\u{202F}?");
+    assert_eq!(smarten("Look ![alt](a.png)", &french()), "Look ![alt](a.png)");
+}
+
+#[test]
+fn edits_are_in_chars() {
+    let edits = smarten_edits("é \"x\"\n\n`y` 'z'", &Options::default());
+    assert_eq!(edits.len(), 2);
+    assert_eq!(edits[0].range, 0..5);
+    assert_eq!(edits[0].text, "é “x”");
+    assert_eq!(edits[1].range, 10..14);
+    assert_eq!(edits[1].text, " ‘z’");
+}
@@ -28,6 +28,8 @@ rm -rf "$PKG_DIR"
 mkdir -p "$PKG_DIR/usr/bin"
 mkdir -p "$PKG_DIR/usr/share/applications"
 mkdir -p "$PKG_DIR/usr/share/icons/hicolor"
+mkdir -p "$PKG_DIR/usr/share/gnome-shell/search-providers"
+mkdir -p "$PKG_DIR/usr/share/dbus-1/services"
 
 # Copy binary
 cp target/release/abbey "$PKG_DIR/usr/bin/"
@@ -36,6 +38,10 @@ strip "$PKG_DIR/usr/bin/abbey"
 # Copy desktop file
 cp data/app.abbey.Abbey.desktop "$PKG_DIR/usr/share/applications/"
 
+# Copy GNOME Shell search provider and the D-Bus service that starts it
+cp data/app.abbey.Abbey.search-provider.ini "$PKG_DIR/usr/share/gnome-shell/search-providers/"
+cp data/app.abbey.Abbey.service "$PKG_DIR/usr/share/dbus-1/services/"
+
 # Copy icons
 for size in 16 24 32 48 64 128 256 512; do
     mkdir -p "$PKG_DIR/usr/share/icons/hicolor/${size}x${size}/apps"
@@ -173,6 +179,8 @@ update-desktop-database /usr/share/applications 2>/dev/null || true
 %files
 %{_bindir}/abbey
 %{_datadir}/applications/app.abbey.Abbey.desktop
+%{_datadir}/gnome-shell/search-providers/app.abbey.Abbey.search-provider.ini
+%{_datadir}/dbus-1/services/app.abbey.Abbey.service
 %{_datadir}/icons/hicolor/*/apps/app.abbey.Abbey.png
 
 %changelog
@@ -8,11 +8,15 @@ set -e
 PREFIX="${PREFIX:-/usr/local}"
 ICON_DIR="${XDG_DATA_HOME:-$HOME/.local/share}/icons/hicolor"
 DESKTOP_DIR="${XDG_DATA_HOME:-$HOME/.local/share}/applications"
+DBUS_DIR="${XDG_DATA_HOME:-$HOME/.local/share}/dbus-1/services"
+BIN_DIR="$HOME/.cargo/bin"
 
 # Use system directories if running as root
 if [ "$(id -u)" = "0" ]; then
     ICON_DIR="$PREFIX/share/icons/hicolor"
     DESKTOP_DIR="$PREFIX/share/applications"
+    DBUS_DIR="$PREFIX/share/dbus-1/services"
+    BIN_DIR="$PREFIX/bin"
 fi
 
 echo "Installing Abbey icons to $ICON_DIR"
@@ -34,6 +38,16 @@ install -Dm644 "assets/icons/hicolor/scalable/apps/app.abbey.Abbey.png" \
 mkdir -p "$DESKTOP_DIR"
 install -Dm644 "data/app.abbey.Abbey.desktop" "$DESKTOP_DIR/app.abbey.Abbey.desktop"
 
+# Install the D-Bus service so searches can start Abbey, pointing at where the binary lives
+mkdir -p "$DBUS_DIR"
+sed "s|/usr/bin/abbey|$BIN_DIR/abbey|" "data/app.abbey.Abbey.service" > "$DBUS_DIR/app.abbey.Abbey.service"
+
+# GNOME Shell only reads search providers from system directories
+if [ "$(id -u)" = "0" ]; then
+    install -Dm644 "data/app.abbey.Abbey.search-provider.ini" \
+        "$PREFIX/share/gnome-shell/search-providers/app.abbey.Abbey.search-provider.ini"
+fi
+
 # Update icon cache (required for some desktop environments)
 if command -v gtk-update-icon-cache &> /dev/null; then
     echo "Updating icon cache..."
@@ -0,0 +1,115 @@
+//! The app's side of the D-Bus automation interface, acting through the main
+//! window so changes show up immediately and share its saving and error handling
+
+use gtk4::prelude::*;
+use gtk4::glib;
+
+use abbey_core::dbus::{AutomationError, AutomationHandler};
+use abbey_core::stats::LibraryStats;
+
+use super::window::AbbeyWindow;
+use super::AbbeyApp;
+use crate::data::Composition;
+
+pub struct AppAutomation {
+    app: glib::WeakRef<AbbeyApp>,
+}
+
+impl AppAutomation {
+    pub fn new(app: &AbbeyApp) -> Self {
+        Self { app: app.downgrade() }
+    }
+
+    /// Run `f` against the main window. A window created just for this call, as
+    /// when Abbey was started over D-Bus, is closed again unless `f` showed it.
+    fn with_window<T>(
+        &self,
+        writable: bool,
+        f: impl FnOnce(&AbbeyWindow) -> Result<T, AutomationError>,
+    ) -> Result<T, AutomationError> {
+        let app = self
+            .app
+            .upgrade()
+            .ok_or_else(|| AutomationError::Failed("Abbey is shutting down".to_string()))?;
+        let had_window = app.windows().iter().any(|w| w.is::<AbbeyWindow>());
+        let window = app.main_window();
+        
+        let result = if writable && window.is_read_only() {
+            Err(AutomationError::ReadOnly)
+        } else {
+            f(&window)
+        };
+        
+        if !had_window && !window.is_visible() {
+            window.close();
+        }
+        result
+    }
+
+    fn find(&self, window: &AbbeyWindow, query: &str) -> Result<Composition, AutomationError> {
+        match window.find_composition(query) {
+            Ok(Some(composition)) => Ok(composition),
+            Ok(None) => Err(AutomationError::NotFound(format!("no composition matches \"{}\"", query))),
+            Err(matches) => Err(AutomationError::Ambiguous {
+                query: query.to_string(),
+                matches,
+            }),
+        }
+    }
+}
+
+impl AutomationHandler for AppAutomation {
+    fn create_composition(&self, title: Option<String>, text: String) -> Result<String, AutomationError> {
+        self.with_window(true, |window| {
+            let mut composition = Composition::new();
+            if let Some(title) = title {
+                composition.title = title;
+            }
+            composition.content = text;
+            composition.update_word_count();
+            
+            let id = composition.id.clone();
+            window.add_composition(composition)?;
+            Ok(id)
+        })
+    }
+
+    fn append_to_composition(&self, composition: &str, text: &str) -> Result<String, AutomationError> {
+        self.with_window(true, |window| {
+            let composition = self.find(window, composition)?;
+            window.append_paragraph(&composition.id, text)?;
+            Ok(composition.id)
+        })
+    }
+
+    fn add_note(&self, composition: &str, text: &str) -> Result<String, AutomationError> {
+        self.with_window(true, |window| {
+            let composition = self.find(window, composition)?;
+            Ok(window.add_note(&composition.id, text)?)
+        })
+    }
+
+    fn start_flow(&self, minutes: u32) -> Result<(), AutomationError> {
+        self.with_window(true, |window| {
+            if window.is_in_flow() {
+                return Err(AutomationError::Busy("a flow session is already running".to_string()));
+            }
+            
+            window.present();
+            window.start_flow(minutes);
+            Ok(())
+        })
+    }
+
+    fn stats(&self) -> Result<LibraryStats, AutomationError> {
+        self.with_window(false, |window| Ok(window.library_stats()?))
+    }
+
+    fn show_quick_capture(&self, timestamp: u32) {
+        match self.app.upgrade() {
+            // The capture window closes an unseen main window itself when done
+            Some(app) => app.main_window().show_quick_capture(timestamp),
+            None => log::warn!("Can't show quick capture while Abbey is shutting down"),
+        }
+    }
+}
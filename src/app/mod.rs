@@ -1,13 +1,21 @@
+mod automation;
 mod window;
 
 use gtk4::prelude::*;
 use libadwaita as adw;
 use adw::subclass::prelude::*;
 use gtk4::gio;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::time::Duration;
 
 use crate::config::APP_ID;
-use crate::data::Storage;
+use crate::data::{self, ApiSettings, Storage, StorageError};
+use abbey_core::api;
+use abbey_core::dbus;
+use abbey_core::search::Target;
+use automation::AppAutomation;
 use window::AbbeyWindow;
 
 mod imp {
@@ -16,6 +24,13 @@ mod imp {
     #[derive(Default)]
     pub struct AbbeyApp {
         pub storage: RefCell<Option<Storage>>,
+        /// Why the library is read-only, shown in every new window
+        pub read_only_reason: RefCell<Option<String>>,
+        pub lock_heartbeat: RefCell<Option<glib::SourceId>>,
+        /// The search provider and automation objects exported on the session bus
+        pub dbus_objects: RefCell<Vec<gio::RegistrationId>>,
+        /// The local HTTP API and the settings it was started with
+        pub api_server: RefCell<Option<(ApiSettings, api::Server)>>,
     }
 
     #[glib::object_subclass]
@@ -29,14 +44,59 @@ mod imp {
 
     impl ApplicationImpl for AbbeyApp {
         fn activate(&self) {
+            self.obj().main_window().present();
+        }
+
+        /// Handles local and remote invocations alike, so `abbey --new` in a terminal
+        /// reaches the running instance
+        fn command_line(&self, command_line: &gio::ApplicationCommandLine) -> glib::ExitCode {
             let app = self.obj();
+            let window = app.main_window();
+            window.present();
+            
+            let options = command_line.options_dict();
+            let mut status = glib::ExitCode::SUCCESS;
+            
+            if options.contains("new") {
+                window.create_new_composition();
+            }
+            
+            if let Ok(Some(minutes)) = options.lookup::<i32>("flow") {
+                match u32::try_from(minutes) {
+                    Ok(minutes) if minutes > 0 => window.start_flow(minutes),
+                    _ => {
+                        log::warn!("Ignoring --flow {}: expected a number of minutes", minutes);
+                        status = glib::ExitCode::FAILURE;
+                    }
+                }
+            }
             
-            // Initialize storage
-            let storage = Storage::new().expect("Failed to initialize storage");
-            self.storage.replace(Some(storage));
+            if let Ok(Some(id)) = options.lookup::<String>("open") {
+                if !window.open_composition_matching(&id) {
+                    window.show_toast(&format!("No composition matches “{}”", id));
+                    status = glib::ExitCode::FAILURE;
+                }
+            }
             
-            let window = AbbeyWindow::new(&*app);
+            // Whatever is left after the options are files, relative to the caller's directory
+            let files: Vec<gio::File> = command_line
+                .arguments()
+                .iter()
+                .skip(1)
+                .map(|arg| command_line.create_file_for_arg(arg))
+                .collect();
+            if !files.is_empty() {
+                app.open(&files, "");
+            }
+            
+            status
+        }
+
+        /// Files from the command line, a file manager or another app
+        fn open(&self, files: &[gio::File], _hint: &str) {
+            let window = self.obj().main_window();
             window.present();
+            window.open_files(files);
         }
 
         fn startup(&self) {
@@ -45,9 +105,65 @@ mod imp {
             // Set the default icon for all windows
             gtk4::Window::set_default_icon_name(crate::config::APP_ID);
             
+            // Bundled icons, plus the editors' markdown language and theme schemes
+            if let Err(e) = gio::resources_register_include!("abbey.gresource") {
+                log::error!("Failed to register resources: {}", e);
+            }
+            crate::ui::init_markdown_editor();
+            
             let app = self.obj();
             app.setup_actions();
             app.setup_accels();
+            app.setup_exit_handlers();
+        }
+
+        /// Export the GNOME Shell search provider and the automation interface next
+        /// to the application object
+        fn dbus_register(&self, connection: &gio::DBusConnection, object_path: &str) -> Result<(), glib::Error> {
+            self.parent_dbus_register(connection, object_path)?;
+            
+            let corpus_app = self.obj().downgrade();
+            let activate_app = self.obj().downgrade();
+            let provider = dbus::SearchProvider::new(
+                move || corpus_app.upgrade().map(|app| app.search_corpus()).unwrap_or_default(),
+                move |activation| {
+                    if let Some(app) = activate_app.upgrade() {
+                        app.activate_search_result(activation);
+                    }
+                },
+            );
+            
+            let search_path = dbus::object_path(object_path, "SearchProvider");
+            let search_registration = provider.register(connection, &search_path)?;
+            self.dbus_objects.borrow_mut().push(search_registration);
+            
+            let automation = std::rc::Rc::new(AppAutomation::new(&self.obj()));
+            let automation_path = dbus::object_path(object_path, "Automation");
+            let automation_registration = dbus::register_automation(connection, &automation_path, automation)?;
+            self.dbus_objects.borrow_mut().push(automation_registration);
+            Ok(())
+        }
+
+        fn dbus_unregister(&self, connection: &gio::DBusConnection, object_path: &str) {
+            for registration in self.dbus_objects.take() {
+                if let Err(e) = connection.unregister_object(registration) {
+                    log::warn!("Failed to unregister D-Bus object: {}", e);
+                }
+            }
+            self.parent_dbus_unregister(connection, object_path);
+        }
+
+        fn shutdown(&self) {
+            if let Some(source) = self.lock_heartbeat.take() {
+                source.remove();
+            }
+            
+            // No more requests once the library is going away
+            self.api_server.replace(None);
+            
+            // Dropping the storage releases the library lock
+            self.storage.replace(None);
+            self.parent_shutdown();
         }
     }
 
@@ -63,32 +179,321 @@ glib::wrapper! {
 
 impl AbbeyApp {
     pub fn new() -> Self {
-        glib::Object::builder()
+        let app: Self = glib::Object::builder()
             .property("application-id", APP_ID)
-            .property("flags", gio::ApplicationFlags::FLAGS_NONE)
-            .build()
+            .property(
+                "flags",
+                gio::ApplicationFlags::HANDLES_OPEN | gio::ApplicationFlags::HANDLES_COMMAND_LINE,
+            )
+            .build();
+        app.setup_command_line_options();
+        
+        // When started over D-Bus to answer a search, stay around for the
+        // follow-up queries instead of exiting after the first one
+        app.set_inactivity_timeout(10_000);
+        app
+    }
+
+    fn setup_command_line_options(&self) {
+        self.add_main_option(
+            "new",
+            glib::Char::from(b'n'),
+            glib::OptionFlags::NONE,
+            glib::OptionArg::None,
+            "Create a new composition",
+            None,
+        );
+        self.add_main_option(
+            "flow",
+            glib::Char::from(b'f'),
+            glib::OptionFlags::NONE,
+            glib::OptionArg::Int,
+            "Start a flow session of MINUTES",
+            Some("MINUTES"),
+        );
+        self.add_main_option(
+            "open",
+            glib::Char::from(b'o'),
+            glib::OptionFlags::NONE,
+            glib::OptionArg::String,
+            "Open the composition with this id",
+            Some("ID"),
+        );
+    }
+
+    /// The window to show things in, opening the library and creating the window
+    /// on first use
+    fn main_window(&self) -> AbbeyWindow {
+        let imp = self.imp();
+        
+        // Initialize storage once, falling back to a read-only library rather than
+        // refusing to start when it can't be written or another instance has it.
+        // Without anywhere to look for the library, open none and say so.
+        if imp.storage.borrow().is_none() {
+            let storage = match Storage::new() {
+                Ok(storage) => Some(storage),
+                Err(e) => {
+                    log::error!("Failed to open library for writing: {}", e);
+                    match Storage::default_base_dir() {
+                        Ok(base_dir) => {
+                            imp.read_only_reason.replace(Some(format!(
+                                "Abbey couldn't open your library for writing: {}.",
+                                e
+                            )));
+                            Some(Storage::open_read_only(base_dir))
+                        }
+                        Err(e) => {
+                            imp.read_only_reason.replace(Some(format!(
+                                "Abbey couldn't find your library: {}, so none is open.",
+                                e
+                            )));
+                            None
+                        }
+                    }
+                }
+            };
+            if let Some(storage) = storage {
+                imp.storage.replace(Some(storage));
+                self.start_lock_heartbeat();
+            }
+        }
+        
+        if let Some(window) = self.windows().into_iter().find_map(|w| w.downcast::<AbbeyWindow>().ok()) {
+            return window;
+        }
+        
+        let window = AbbeyWindow::new(self);
+        if let Some(ref reason) = *imp.read_only_reason.borrow() {
+            window.enter_read_only(reason);
+        }
+        window
     }
 
     pub fn storage(&self) -> std::cell::Ref<'_, Option<Storage>> {
         self.imp().storage.borrow()
     }
 
+    /// Try to reopen the library for writing after it fell back to read-only
+    pub fn reopen_storage(&self) -> Result<(), StorageError> {
+        let base_dir = match *self.storage() {
+            Some(ref storage) => storage.base_dir().clone(),
+            None => Storage::default_base_dir()?,
+        };
+
+        let storage = Storage::open(base_dir)?;
+        if self.imp().storage.replace(Some(storage)).is_none() {
+            // No library was open at startup, so nothing is keeping the lock fresh yet
+            self.start_lock_heartbeat();
+        }
+        self.imp().read_only_reason.replace(None);
+        Ok(())
+    }
+
+    /// What the search provider looks through: the open window's compositions, or
+    /// the library on disk when Abbey was only started to answer a search
+    fn search_corpus(&self) -> dbus::Corpus {
+        if let Some(window) = self.windows().into_iter().find_map(|w| w.downcast::<AbbeyWindow>().ok()) {
+            return window.search_corpus();
+        }
+        
+        let load = |storage: &Storage| -> Result<dbus::Corpus, StorageError> {
+            Ok(dbus::Corpus {
+                compositions: storage.load_compositions()?,
+                flows: storage.load_flows()?,
+            })
+        };
+        let result = match *self.storage() {
+            Some(ref storage) => load(storage),
+            None => Storage::default_base_dir().and_then(|dir| load(&Storage::open_read_only(dir))),
+        };
+        
+        result.unwrap_or_else(|e| {
+            log::error!("Failed to load library for search: {}", e);
+            dbus::Corpus::default()
+        })
+    }
+
+    fn activate_search_result(&self, activation: dbus::Activation) {
+        let window = self.main_window();
+        
+        match activation {
+            dbus::Activation::Open { target, timestamp } => {
+                window.present_with_time(timestamp);
+                let found = match target {
+                    Target::Composition(ref id) => window.open_composition_matching(id),
+                    Target::Flow(ref id) => window.open_flow(id),
+                };
+                if !found {
+                    window.show_toast("That search result is no longer in your library");
+                }
+            }
+            dbus::Activation::Launch { timestamp, .. } => window.present_with_time(timestamp),
+        }
+    }
+
+    // ========== HTTP API ==========
+
+    /// Start, restart or stop the local API to match `settings`
+    pub fn apply_api_settings(&self, settings: &ApiSettings) -> std::io::Result<()> {
+        let wanted = settings.enabled && !settings.token.is_empty();
+        if let Some((ref running, _)) = *self.imp().api_server.borrow() {
+            if wanted && running.port == settings.port && running.token == settings.token {
+                return Ok(());
+            }
+        }
+        
+        // The old server has to let go of its port first
+        self.imp().api_server.replace(None);
+        if !wanted {
+            return Ok(());
+        }
+        
+        let server = api::Server::start(settings.port, &settings.token, forward_api_request)?;
+        log::info!("Serving the HTTP API at http://{}/api", server.local_addr());
+        self.imp().api_server.replace(Some((settings.clone(), server)));
+        Ok(())
+    }
+
+    /// Send a change made in the app to API event stream listeners
+    pub fn announce(&self, change: api::Change) {
+        if let Some((_, ref server)) = *self.imp().api_server.borrow() {
+            server.events().publish(change);
+        }
+    }
+
+    /// Answer an API request on the main thread. Edits waiting on autosave are
+    /// written first so the request sees them, and the window reloads whatever
+    /// the request changed.
+    fn handle_api_request(&self, request: &api::Request) -> api::Reply {
+        let window = self.windows().into_iter().find_map(|w| w.downcast::<AbbeyWindow>().ok());
+        if let Some(ref window) = window {
+            if let Err(e) = window.flush_pending_save() {
+                let message = format!("Abbey couldn't save the edits in progress: {}", e);
+                return (api::Response::error(503, &message), Vec::new());
+            }
+        }
+        
+        let reply = match *self.storage() {
+            Some(ref storage) => api::handle(storage, request),
+            None => (api::Response::error(503, "the library isn't open"), Vec::new()),
+        };
+        
+        if let Some(window) = window {
+            if !reply.1.is_empty() {
+                window.reload_library(&reply.1);
+            }
+        }
+        reply
+    }
+
+    /// Refresh the library lock periodically so other instances can tell this one
+    /// is still alive. If one of them took the lock over anyway (say after this
+    /// machine slept past the stale timeout), every window goes read-only.
+    fn start_lock_heartbeat(&self) {
+        let app = self.downgrade();
+        let source = glib::timeout_add_seconds_local(data::LOCK_HEARTBEAT_SECONDS, move || {
+            let app = match app.upgrade() {
+                Some(app) => app,
+                None => return glib::ControlFlow::Break,
+            };
+            
+            let result = match *app.storage() {
+                Some(ref storage) => storage.refresh_lock(),
+                None => Ok(()),
+            };
+            
+            match result {
+                Ok(()) => {}
+                Err(e @ StorageError::Locked(_)) => {
+                    log::error!("Lost the library lock: {}", e);
+                    let reason = format!("Abbey stopped writing to your library: {}.", e);
+                    for window in app.windows() {
+                        if let Some(window) = window.downcast_ref::<AbbeyWindow>() {
+                            window.enter_read_only(&reason);
+                        }
+                    }
+                    app.imp().read_only_reason.replace(Some(reason));
+                }
+                Err(e) => log::warn!("Failed to refresh library lock: {}", e),
+            }
+            glib::ControlFlow::Continue
+        });
+        self.imp().lock_heartbeat.replace(Some(source));
+    }
+
+    /// Release the library lock on every way out: termination signals go through
+    /// the normal quit path, and a panic releases it before the process dies
+    fn setup_exit_handlers(&self) {
+        let previous_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            data::release_held_locks();
+            previous_hook(info);
+        }));
+
+        #[cfg(unix)]
+        {
+            const SIGHUP: i32 = 1;
+            const SIGINT: i32 = 2;
+            const SIGTERM: i32 = 15;
+            
+            let quitting = std::rc::Rc::new(Cell::new(false));
+            for signal in [SIGHUP, SIGINT, SIGTERM] {
+                let app = self.downgrade();
+                let quitting = quitting.clone();
+                glib::unix_signal_add_local(signal, move || {
+                    if let Some(app) = app.upgrade() {
+                        // A second signal means the user doesn't want to wait for a
+                        // window kept open by a failed save
+                        if quitting.replace(true) {
+                            app.quit();
+                        } else {
+                            app.quit_after_flush();
+                        }
+                    }
+                    glib::ControlFlow::Continue
+                });
+            }
+        }
+    }
+
     fn setup_actions(&self) {
         // Quit action
         let quit_action = gio::ActionEntry::builder("quit")
             .activate(|app: &Self, _, _| {
-                app.quit();
+                app.quit_after_flush();
             })
             .build();
-
+        
         // About action
         let about_action = gio::ActionEntry::builder("about")
             .activate(|app: &Self, _, _| {
                 app.show_about();
             })
             .build();
+        
+        // Quick capture, also reachable with `gapplication action app.abbey.Abbey quick-capture`
+        let capture_action = gio::ActionEntry::builder("quick-capture")
+            .activate(|app: &Self, _, _| {
+                app.main_window().show_quick_capture(gtk4::gdk::CURRENT_TIME);
+            })
+            .build();
+        
+        self.add_action_entries([quit_action, about_action, capture_action]);
+    }
 
-        self.add_action_entries([quit_action, about_action]);
+    /// Close every window through its close-request handler, which flushes pending
+    /// saves and asks the user what to do if that fails. The application exits once
+    /// the last window is gone; a window kept open by a failed save keeps it running.
+    fn quit_after_flush(&self) {
+        for window in self.windows() {
+            if window.is::<AbbeyWindow>() {
+                window.close();
+            }
+        }
+        
+        if !self.windows().iter().any(|w| w.is::<AbbeyWindow>()) {
+            self.quit();
+        }
     }
 
     fn setup_accels(&self) {
@@ -96,6 +501,14 @@ impl AbbeyApp {
         self.set_accels_for_action("win.new-composition", &["<Control>n"]);
         self.set_accels_for_action("win.save", &["<Control>s"]);
         self.set_accels_for_action("win.flow-mode", &["<Control><Shift>f"]);
+        self.set_accels_for_action("win.check-spelling", &["F7"]);
+        self.set_accels_for_action("win.focus-mode", &["F11"]);
+        self.set_accels_for_action("win.find", &["<Control>f"]);
+        self.set_accels_for_action("win.find-replace", &["<Control>h"]);
+        self.set_accels_for_action("win.find-in-library", &["<Control><Shift>h"]);
+        self.set_accels_for_action("win.compare", &["<Control><Alt>d"]);
+        self.set_accels_for_action("win.add-comment", &["<Control><Alt>m"]);
+        self.set_accels_for_action("win.suggest-changes", &["<Control><Shift>e"]);
     }
 
     fn show_about(&self) {
@@ -111,7 +524,7 @@ impl AbbeyApp {
             .comments("A beautiful writing application for focused creativity")
             .website("https://abbey.app")
             .build();
-
+        
         if let Some(win) = window {
             about.set_transient_for(Some(&win));
             about.present();
@@ -123,6 +536,44 @@ impl AbbeyApp {
     }
 }
 
+/// How long an API request waits for the main loop, which a modal dialog or a
+/// long save can hold up
+const API_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Runs on the API's connection threads: hand the request to the main thread,
+/// where the library lives, and wait for the answer. A request that times out
+/// before the main thread gets to it is dropped there rather than handled late,
+/// so a client retrying it doesn't make the same change twice.
+fn forward_api_request(request: &api::Request) -> api::Reply {
+    let (sender, receiver) = mpsc::channel();
+    // Taken by whichever comes first: the main thread starting on the request,
+    // or this thread giving up on it
+    let claimed = Arc::new(AtomicBool::new(false));
+    let request = request.clone();
+    let handler_claimed = claimed.clone();
+    glib::MainContext::default().invoke(move || {
+        if handler_claimed.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let reply = match gio::Application::default().and_downcast::<AbbeyApp>() {
+            Some(app) => app.handle_api_request(&request),
+            None => (api::Response::error(503, "Abbey is shutting down"), Vec::new()),
+        };
+        let _ = sender.send(reply);
+    });
+
+    match receiver.recv_timeout(API_REQUEST_TIMEOUT) {
+        Ok(reply) => reply,
+        Err(_) if !claimed.swap(true, Ordering::SeqCst) => {
+            (api::Response::error(503, "Abbey is busy; try again"), Vec::new())
+        }
+        // The main thread got to it just in time, so its answer is on the way
+        Err(_) => receiver
+            .recv()
+            .unwrap_or_else(|_| (api::Response::error(503, "Abbey is shutting down"), Vec::new())),
+    }
+}
+
 impl Default for AbbeyApp {
     fn default() -> Self {
         Self::new()
@@ -5,9 +5,62 @@ use gtk4::{gio, glib, CompositeTemplate};
 use libadwaita as adw;
 use std::cell::{Cell, RefCell};
 
+use abbey_core::api;
+use abbey_core::dbus;
+use abbey_core::import;
+use abbey_core::links::{self, Source};
+use abbey_core::replace::{self, Hit, Matcher};
+use abbey_core::search;
+use abbey_core::stats::LibraryStats;
+use abbey_core::utils::frequency::{self, Baseline};
+
 use crate::config::THEMES;
-use crate::data::{Composition, Flow, Folder, Project};
-use crate::ui::{CompositionView, FlowView, FlowHistoryView, ProjectsView, ThemeManager};
+use crate::data::{Composition, Flow, FocusScope, FocusSettings, Folder, Note, Project, Settings, Storage, StorageError, TypographySettings};
+use crate::ui::{ApiDialog, CompareVersions, CompositionView, FlowView, FlowHistoryView, LibraryReplace, ProjectsView, QuickCapture, ThemeManager, Version, WordAnalysis};
+
+// Sidebar navigation rows, in the order they appear in window.ui
+const NAV_SECTIONS: &[&str] = &["writing", "flow-history", "projects", "archive"];
+
+/// How often at most the open composition is journaled while it's edited
+const JOURNAL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Save state shown in the header bar
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SaveState {
+    #[default]
+    Saved,
+    Saving,
+    Failed,
+}
+
+/// Compositions and projects a library-wide replace changed, as they were
+/// before it and right after, so it can be undone while they're unedited
+#[derive(Debug, Clone)]
+struct ReplaceSnapshot {
+    compositions: Vec<(Composition, Composition)>,
+    projects: Vec<(Project, Project)>,
+}
+
+/// A write that failed, kept so the error banner's Retry button can repeat it
+#[derive(Debug, Clone)]
+enum FailedWrite {
+    Compositions,
+    Folders,
+    Projects(Vec<Project>),
+    Flow(Flow),
+}
+
+impl FailedWrite {
+    /// Whether this write makes an earlier failed `other` redundant
+    fn supersedes(&self, other: &FailedWrite) -> bool {
+        matches!(
+            (self, other),
+            (Self::Compositions, Self::Compositions)
+                | (Self::Folders, Self::Folders)
+                | (Self::Projects(_), Self::Projects(_))
+        )
+    }
+}
 
 mod imp {
     use super::*;
@@ -37,14 +90,34 @@ mod imp {
         pub projects_box: TemplateChild<gtk4::Box>,
         #[template_child]
         pub archive_box: TemplateChild<gtk4::Box>,
+        #[template_child]
+        pub content_toolbar: TemplateChild<adw::ToolbarView>,
+        #[template_child]
+        pub save_status_label: TemplateChild<gtk4::Label>,
+        #[template_child]
+        pub read_only_banner: TemplateChild<adw::Banner>,
+        #[template_child]
+        pub error_banner: TemplateChild<adw::Banner>,
         
         pub theme_manager: RefCell<Option<ThemeManager>>,
         pub current_composition: RefCell<Option<Composition>>,
+        pub composition_view: RefCell<Option<CompositionView>>,
         pub compositions: RefCell<Vec<Composition>>,
         pub folders: RefCell<Vec<Folder>>,
         pub in_flow_mode: Cell<bool>,
+        pub in_focus_mode: Cell<bool>,
+        /// Whether the sidebar was collapsed before focus mode hid it
+        pub collapsed_before_focus: Cell<bool>,
         pub current_flow_view: RefCell<Option<FlowView>>,
         pub autosave_source_id: RefCell<Option<glib::SourceId>>,
+        pub journal_source_id: RefCell<Option<glib::SourceId>>,
+        pub settings: RefCell<Settings>,
+        pub save_state: Cell<SaveState>,
+        pub force_close: Cell<bool>,
+        pub failed_writes: RefCell<Vec<FailedWrite>>,
+        pub needs_reload: Cell<bool>,
+        /// What the last library-wide replace changed, for Undo Replace
+        pub replace_snapshot: RefCell<Option<ReplaceSnapshot>>,
     }
 
     #[glib::object_subclass]
@@ -91,7 +164,30 @@ mod imp {
     }
 
     impl WidgetImpl for AbbeyWindow {}
-    impl WindowImpl for AbbeyWindow {}
+
+    impl WindowImpl for AbbeyWindow {
+        fn close_request(&self) -> glib::Propagation {
+            let obj = self.obj();
+            
+            if !self.force_close.get() {
+                // An unfinished flow would otherwise be discarded with the window
+                if self.in_flow_mode.get() {
+                    if let Some(flow_view) = self.current_flow_view.borrow().clone() {
+                        flow_view.end_flow();
+                    }
+                }
+                
+                if let Err(e) = obj.flush_pending_save() {
+                    obj.confirm_close_unsaved(&e);
+                    return glib::Propagation::Stop;
+                }
+            }
+            
+            obj.save_session();
+            self.parent_close_request()
+        }
+    }
+
     impl ApplicationWindowImpl for AbbeyWindow {}
     impl AdwApplicationWindowImpl for AbbeyWindow {}
 }
@@ -113,6 +209,19 @@ impl AbbeyWindow {
         
         window.setup_actions();
         window.load_compositions();
+        window.restore_session();
+        window.setup_spelling();
+        window.setup_style_check();
+        window.setup_focus_mode();
+        window.setup_typography();
+        window.start_api();
+        
+        // Wait until the window is shown so the dialog has a parent on screen
+        let win = window.clone();
+        glib::idle_add_local_once(move || {
+            win.offer_recovery();
+        });
+        
         window
     }
 
@@ -122,31 +231,31 @@ impl AbbeyWindow {
                 win.create_new_composition();
             })
             .build();
-
+        
         let save_action = gio::ActionEntry::builder("save")
             .activate(|win: &Self, _, _| {
                 win.save_current_composition();
             })
             .build();
-
+        
         let flow_action = gio::ActionEntry::builder("flow-mode")
             .activate(|win: &Self, _, _| {
                 win.start_flow_mode();
             })
             .build();
-
+        
         let archive_action = gio::ActionEntry::builder("archive")
             .activate(|win: &Self, _, _| {
                 win.archive_current_composition();
             })
             .build();
-
+        
         let publish_action = gio::ActionEntry::builder("publish")
             .activate(|win: &Self, _, _| {
                 win.publish_to_microblog();
             })
             .build();
-
+        
         let move_to_folder_action = gio::ActionEntry::builder("move-to-folder")
             .parameter_type(Some(&String::static_variant_type()))
             .activate(|win: &Self, _, param| {
@@ -163,8 +272,172 @@ impl AbbeyWindow {
                 }
             })
             .build();
-
-        self.add_action_entries([new_action, save_action, flow_action, archive_action, publish_action, move_to_folder_action]);
+        
+        let retry_save_action = gio::ActionEntry::builder("retry-save")
+            .activate(|win: &Self, _, _| {
+                win.retry_failed_writes();
+            })
+            .build();
+        
+        let reopen_library_action = gio::ActionEntry::builder("reopen-library")
+            .activate(|win: &Self, _, _| {
+                win.reopen_library();
+            })
+            .build();
+        
+        let check_spelling_action = gio::ActionEntry::builder("check-spelling")
+            .activate(|win: &Self, _, _| {
+                win.check_spelling();
+            })
+            .build();
+        
+        let word_analysis_action = gio::ActionEntry::builder("word-analysis")
+            .activate(|win: &Self, _, _| {
+                win.analyze_current_composition();
+            })
+            .build();
+        
+        let focus_mode_action = gio::ActionEntry::builder("focus-mode")
+            .state(false.to_variant())
+            .activate(|win: &Self, _, _| {
+                win.set_focus_mode(!win.imp().in_focus_mode.get());
+            })
+            .build();
+        
+        let focus_scope_action = gio::ActionEntry::builder("focus-scope")
+            .parameter_type(Some(&String::static_variant_type()))
+            .state("sentence".to_variant())
+            .activate(|win: &Self, action, param| {
+                let scope = match param.and_then(|p| p.get::<String>()).as_deref() {
+                    Some("paragraph") => FocusScope::Paragraph,
+                    _ => FocusScope::Sentence,
+                };
+                action.set_state(&focus_scope_name(scope).to_variant());
+                win.update_focus_settings(|focus| focus.scope = scope);
+            })
+            .build();
+        
+        let typewriter_action = gio::ActionEntry::builder("typewriter-scrolling")
+            .state(true.to_variant())
+            .activate(|win: &Self, action, _| {
+                let typewriter = !action.state().and_then(|s| s.get::<bool>()).unwrap_or(true);
+                action.set_state(&typewriter.to_variant());
+                win.update_focus_settings(|focus| focus.typewriter = typewriter);
+            })
+            .build();
+        
+        let column_width_action = gio::ActionEntry::builder("focus-column-width")
+            .parameter_type(Some(&u32::static_variant_type()))
+            .state(FocusSettings::default().column_width.to_variant())
+            .activate(|win: &Self, action, param| {
+                if let Some(width) = param.and_then(|p| p.get::<u32>()) {
+                    action.set_state(&width.to_variant());
+                    win.update_focus_settings(|focus| focus.column_width = width);
+                }
+            })
+            .build();
+        
+        let smart_punctuation_action = gio::ActionEntry::builder("smart-punctuation")
+            .state(false.to_variant())
+            .activate(|win: &Self, action, _| {
+                let on = !action.state().and_then(|s| s.get::<bool>()).unwrap_or(false);
+                action.set_state(&on.to_variant());
+                win.update_typography_settings(|typography| typography.smart_punctuation = on);
+            })
+            .build();
+        
+        let french_spacing_action = gio::ActionEntry::builder("french-spacing")
+            .state(false.to_variant())
+            .activate(|win: &Self, action, _| {
+                let on = !action.state().and_then(|s| s.get::<bool>()).unwrap_or(false);
+                action.set_state(&on.to_variant());
+                win.update_typography_settings(|typography| typography.french_spacing = on);
+            })
+            .build();
+        
+        let smarten_action = gio::ActionEntry::builder("smarten")
+            .activate(|win: &Self, _, _| {
+                win.smarten_current_composition();
+            })
+            .build();
+        
+        let suggest_changes_action = gio::ActionEntry::builder("suggest-changes")
+            .state(false.to_variant())
+            .activate(|win: &Self, _, _| {
+                win.toggle_suggesting();
+            })
+            .build();
+        
+        let add_comment_action = gio::ActionEntry::builder("add-comment")
+            .activate(|win: &Self, _, _| {
+                win.add_comment();
+            })
+            .build();
+        
+        let find_action = gio::ActionEntry::builder("find")
+            .activate(|win: &Self, _, _| {
+                win.show_find(false);
+            })
+            .build();
+        
+        let find_replace_action = gio::ActionEntry::builder("find-replace")
+            .activate(|win: &Self, _, _| {
+                win.show_find(true);
+            })
+            .build();
+        
+        let find_in_library_action = gio::ActionEntry::builder("find-in-library")
+            .activate(|win: &Self, _, _| {
+                win.show_library_replace();
+            })
+            .build();
+        
+        let compare_action = gio::ActionEntry::builder("compare")
+            .activate(|win: &Self, _, _| {
+                win.show_compare();
+            })
+            .build();
+        
+        let undo_replace_action = gio::ActionEntry::builder("undo-replace")
+            .activate(|win: &Self, _, _| {
+                win.undo_library_replace();
+            })
+            .build();
+        
+        let api_settings_action = gio::ActionEntry::builder("api-settings")
+            .activate(|win: &Self, _, _| {
+                win.show_api_settings();
+            })
+            .build();
+        
+        self.add_action_entries([
+            new_action,
+            save_action,
+            flow_action,
+            archive_action,
+            publish_action,
+            move_to_folder_action,
+            retry_save_action,
+            reopen_library_action,
+            api_settings_action,
+            check_spelling_action,
+            word_analysis_action,
+            focus_mode_action,
+            focus_scope_action,
+            typewriter_action,
+            column_width_action,
+            smart_punctuation_action,
+            french_spacing_action,
+            smarten_action,
+            suggest_changes_action,
+            add_comment_action,
+            find_action,
+            find_replace_action,
+            find_in_library_action,
+            undo_replace_action,
+            compare_action,
+        ]);
+        self.update_undo_replace_action();
     }
 
     fn setup_theme_manager(&self) {
@@ -199,174 +472,1017 @@ impl AbbeyWindow {
         
         let window = self.clone();
         nav_list.connect_row_activated(move |_, row| {
-            let index = row.index();
-            match index {
-                0 => window.show_writing(),
-                1 => window.show_flow_history(),
-                2 => window.show_projects(),
-                3 => window.show_archive(),
-                _ => {}
-            }
+            window.show_nav_section(row.index());
         });
     }
 
-    fn show_writing(&self) {
-        // Return to composition view with current composition or empty state
-        self.imp().main_stack.set_visible_child_name("composition");
+    fn show_nav_section(&self, index: i32) {
+        match index {
+            0 => self.show_writing(),
+            1 => self.show_flow_history(),
+            2 => self.show_projects(),
+            3 => self.show_archive(),
+            _ => {}
+        }
     }
 
-    fn setup_composition_list(&self) {
-        let list = &self.imp().composition_list;
-        list.set_selection_mode(gtk4::SelectionMode::Single);
-        
-        // Row activation now handled per-row via connect_activated
-        // to distinguish folder vs composition rows
+    fn select_nav_section(&self, section: &str) {
+        let index = NAV_SECTIONS.iter().position(|s| *s == section).unwrap_or(0) as i32;
+        let nav_list = &self.imp().nav_list;
+        if let Some(row) = nav_list.row_at_index(index) {
+            nav_list.select_row(Some(&row));
+        }
+        self.show_nav_section(index);
     }
 
-    fn load_compositions(&self) {
-        let app = self.application().and_downcast::<crate::app::AbbeyApp>().unwrap();
-        let storage_ref = app.storage();
+    /// Restore window geometry, navigation and the last open composition for this library
+    fn restore_session(&self) {
+        let settings = self.with_storage(|storage| storage.load_settings())
+            .unwrap_or_else(|e| {
+                log::error!("Failed to load settings: {}", e);
+                Settings::default()
+            });
+        let session = settings.session.clone();
+        let last_opened = settings.last_opened_composition.clone();
+        self.imp().settings.replace(settings);
+        
+        self.set_default_size(session.window_width, session.window_height);
+        if session.maximized {
+            self.maximize();
+        }
         
-        if let Some(ref storage) = *storage_ref {
-            // Load folders
-            match storage.load_folders() {
-                Ok(folders) => {
-                    self.imp().folders.replace(folders);
-                }
-                Err(e) => {
-                    log::error!("Failed to load folders: {}", e);
-                }
-            }
-            
-            // Load compositions
-            match storage.load_compositions() {
-                Ok(compositions) => {
-                    self.imp().compositions.replace(compositions.clone());
-                    self.update_composition_list();
-                }
-                Err(e) => {
-                    log::error!("Failed to load compositions: {}", e);
+        if !session.sidebar_visible {
+            self.imp().split_view.set_collapsed(true);
+            self.imp().split_view.set_show_content(true);
+        }
+        
+        if let Some(comp_id) = last_opened {
+            let available = self.imp().compositions.borrow()
+                .iter()
+                .any(|c| c.id == comp_id && !c.archived);
+            if available {
+                self.open_composition_by_id(&comp_id);
+                if let Some(ref view) = *self.imp().composition_view.borrow() {
+                    view.restore_state(&session);
                 }
             }
         }
+        
+        self.select_nav_section(&session.nav_section);
     }
 
-    fn update_composition_list(&self) {
-        let list = &self.imp().composition_list;
-        let compositions = self.imp().compositions.borrow();
-        let folders = self.imp().folders.borrow();
+    /// Persist window geometry, navigation and editor position to the library settings
+    fn save_session(&self) {
+        let mut settings = self.imp().settings.borrow_mut();
+        
+        let (width, height) = self.default_size();
+        let session = &mut settings.session;
+        session.window_width = width;
+        session.window_height = height;
+        session.maximized = self.is_maximized();
+        // Flow and focus modes collapse the sidebar temporarily; don't remember that
+        session.sidebar_visible = if self.imp().in_focus_mode.get() {
+            !self.imp().collapsed_before_focus.get()
+        } else {
+            !self.imp().split_view.is_collapsed() || self.imp().in_flow_mode.get()
+        };
         
-        // Clear existing rows
-        while let Some(child) = list.first_child() {
-            list.remove(&child);
+        if let Some(row) = self.imp().nav_list.selected_row() {
+            if let Some(section) = NAV_SECTIONS.get(row.index() as usize) {
+                session.nav_section = section.to_string();
+            }
         }
         
-        // Add "New Folder" button row
-        let new_folder_row = adw::ActionRow::builder()
-            .title("New Folder")
-            .activatable(true)
-            .build();
-        new_folder_row.add_prefix(&gtk4::Image::from_icon_name("folder-new-symbolic"));
-        new_folder_row.add_css_class("dim-label");
+        if let Some(ref view) = *self.imp().composition_view.borrow() {
+            session.cursor_offset = view.cursor_offset();
+            session.scroll_position = view.scroll_position();
+            session.notes_visible = view.notes_visible();
+            session.preview_active = view.preview_active();
+            session.split_active = view.split_active();
+        }
         
-        let window = self.clone();
-        new_folder_row.connect_activated(move |_| {
-            window.create_new_folder();
-        });
-        list.append(&new_folder_row);
+        settings.last_opened_composition = self.imp().current_composition.borrow()
+            .as_ref()
+            .filter(|c| !c.archived)
+            .map(|c| c.id.clone());
         
-        // Add folders with their compositions
-        for folder in folders.iter() {
-            let folder_row = self.create_folder_row(folder);
-            list.append(&folder_row);
-            
-            // Add compositions in this folder if expanded
-            if folder.expanded {
-                for comp in compositions.iter().filter(|c| !c.archived && c.folder_id.as_ref() == Some(&folder.id)) {
-                    let row = self.create_composition_row(comp);
-                    row.set_margin_start(24);
-                    list.append(&row);
-                }
+        if let Err(e) = self.with_storage(|storage| storage.save_settings(&settings)) {
+            if !e.is_read_only() {
+                log::error!("Failed to save session: {}", e);
             }
         }
-        
-        // Add compositions without a folder
-        for comp in compositions.iter().filter(|c| !c.archived && c.folder_id.is_none()) {
-            let row = self.create_composition_row(comp);
-            list.append(&row);
-        }
     }
 
-    fn create_folder_row(&self, folder: &Folder) -> adw::ActionRow {
-        let row = adw::ActionRow::builder()
-            .title(&folder.name)
-            .activatable(true)
-            .build();
-        
-        let icon_name = if folder.expanded { "folder-open-symbolic" } else { "folder-symbolic" };
-        row.add_prefix(&gtk4::Image::from_icon_name(icon_name));
-        row.add_css_class("folder-row");
-        
-        // Count compositions in folder
-        let compositions = self.imp().compositions.borrow();
-        let count = compositions.iter().filter(|c| !c.archived && c.folder_id.as_ref() == Some(&folder.id)).count();
-        row.set_subtitle(&format!("{} compositions", count));
-        
-        // Toggle expand/collapse on activate
-        let window = self.clone();
-        let folder_id = folder.id.clone();
-        row.connect_activated(move |_| {
-            window.toggle_folder(&folder_id);
-        });
-        
-        // Edit button
-        let edit_btn = gtk4::Button::builder()
-            .icon_name("document-edit-symbolic")
-            .valign(gtk4::Align::Center)
-            .tooltip_text("Rename folder")
-            .build();
-        edit_btn.add_css_class("flat");
+    /// Give the editors the library's spelling settings and personal dictionary,
+    /// saving changes made from their context menus back to the library
+    fn setup_spelling(&self) {
+        let personal = self.with_storage(|storage| storage.load_personal_dictionary())
+            .unwrap_or_else(|e| {
+                log::error!("Failed to load the personal dictionary: {}", e);
+                Vec::new()
+            });
+        let settings = self.imp().settings.borrow().spelling.clone();
         
         let window = self.clone();
-        let folder_id = folder.id.clone();
-        let folder_name = folder.name.clone();
-        edit_btn.connect_clicked(move |_| {
-            window.rename_folder(&folder_id, &folder_name);
-        });
-        row.add_suffix(&edit_btn);
-        
-        // Delete button
-        let delete_btn = gtk4::Button::builder()
-            .icon_name("user-trash-symbolic")
-            .valign(gtk4::Align::Center)
-            .tooltip_text("Delete folder")
-            .build();
-        delete_btn.add_css_class("flat");
+        let save_personal = move |words: Vec<String>| {
+            if let Err(e) = window.with_storage(|storage| storage.save_personal_dictionary(&words)) {
+                log::error!("Failed to save the personal dictionary: {}", e);
+                window.show_toast("The word is known until Abbey closes, but couldn't be saved to your library");
+            }
+        };
         
         let window = self.clone();
-        let folder_id = folder.id.clone();
-        delete_btn.connect_clicked(move |_| {
-            window.delete_folder(&folder_id);
-        });
-        row.add_suffix(&delete_btn);
+        let save_enabled = move |enabled: bool| {
+            window.imp().settings.borrow_mut().spelling.enabled = enabled;
+            let saved = {
+                let settings = window.imp().settings.borrow();
+                window.with_storage(|storage| storage.save_settings(&settings))
+            };
+            if let Err(e) = saved {
+                if !e.is_read_only() {
+                    log::error!("Failed to save spelling settings: {}", e);
+                }
+            }
+        };
         
-        row
+        crate::ui::configure_spelling(&settings, personal, save_personal, save_enabled);
     }
 
-    fn create_composition_row(&self, composition: &Composition) -> adw::ActionRow {
-        let row = adw::ActionRow::builder()
-            .title(&composition.title)
-            .subtitle(&composition.created_at.format("%Y-%m-%d %H:%M").to_string())
-            .activatable(true)
-            .build();
-        
-        row.add_css_class("composition-row");
-        
-        // Open composition on activate
+    /// Give the editors the library's style rule settings, saving changes made
+    /// from the style panel
+    fn setup_style_check(&self) {
+        let settings = self.imp().settings.borrow().lint.clone();
         let window = self.clone();
-        let comp_id = composition.id.clone();
-        row.connect_activated(move |_| {
-            window.open_composition_by_id(&comp_id);
+        crate::ui::configure_style_check(&settings, move |lint| {
+            window.imp().settings.borrow_mut().lint = lint.clone();
+            let saved = {
+                let settings = window.imp().settings.borrow();
+                window.with_storage(|storage| storage.save_settings(&settings))
+            };
+            if let Err(e) = saved {
+                if !e.is_read_only() {
+                    log::error!("Failed to save style settings: {}", e);
+                }
+            }
+        });
+    }
+
+    // ========== Focus Mode ==========
+
+    /// Bring back focus mode and its options as the library left them
+    fn setup_focus_mode(&self) {
+        let focus = self.imp().settings.borrow().focus.clone();
+        self.set_action_state("focus-scope", focus_scope_name(focus.scope).to_variant());
+        self.set_action_state("typewriter-scrolling", focus.typewriter.to_variant());
+        self.set_action_state("focus-column-width", focus.column_width.to_variant());
+        if focus.enabled {
+            self.set_focus_mode(true);
+        }
+    }
+
+    fn set_action_state(&self, name: &str, state: glib::Variant) {
+        if let Some(action) = self.lookup_action(name).and_downcast::<gio::SimpleAction>() {
+            action.set_state(&state);
+        }
+    }
+
+    /// Hide everything but the composition being written, full screen
+    fn set_focus_mode(&self, on: bool) {
+        let imp = self.imp();
+        if imp.in_focus_mode.get() == on {
+            return;
+        }
+        
+        if on {
+            imp.collapsed_before_focus.set(imp.split_view.is_collapsed());
+            imp.split_view.set_collapsed(true);
+            imp.split_view.set_show_content(true);
+            self.select_nav_section("writing");
+            self.fullscreen();
+        } else {
+            imp.split_view.set_collapsed(imp.collapsed_before_focus.get());
+            self.unfullscreen();
+        }
+        imp.content_toolbar.set_reveal_top_bars(!on);
+        imp.in_focus_mode.set(on);
+        self.set_action_state("focus-mode", on.to_variant());
+        self.update_focus_settings(|focus| focus.enabled = on);
+        
+        if on {
+            self.show_toast("Focus mode: press F11 to leave");
+        }
+    }
+
+    /// Change focus mode's settings, showing and saving the change
+    fn update_focus_settings(&self, change: impl FnOnce(&mut FocusSettings)) {
+        change(&mut self.imp().settings.borrow_mut().focus);
+        self.apply_focus_mode();
+        
+        let saved = {
+            let settings = self.imp().settings.borrow();
+            self.with_storage(|storage| storage.save_settings(&settings))
+        };
+        if let Err(e) = saved {
+            if !e.is_read_only() {
+                log::error!("Failed to save focus settings: {}", e);
+            }
+        }
+    }
+
+    fn apply_focus_mode(&self) {
+        let focus = self.imp().settings.borrow().focus.clone();
+        if let Some(ref view) = *self.imp().composition_view.borrow() {
+            view.set_focus_mode(self.imp().in_focus_mode.get().then_some(&focus));
+        }
+    }
+
+    // ========== Typography ==========
+
+    /// Bring back smart punctuation as the library left it
+    fn setup_typography(&self) {
+        let typography = self.imp().settings.borrow().typography.clone();
+        self.set_action_state("smart-punctuation", typography.smart_punctuation.to_variant());
+        self.set_action_state("french-spacing", typography.french_spacing.to_variant());
+        crate::ui::set_smart_typography(typography.smart_punctuation.then(|| typography.options()));
+    }
+
+    /// Change smart punctuation's settings for every editor, and save them
+    fn update_typography_settings(&self, change: impl FnOnce(&mut TypographySettings)) {
+        let typography = {
+            let mut settings = self.imp().settings.borrow_mut();
+            change(&mut settings.typography);
+            settings.typography.clone()
+        };
+        crate::ui::set_smart_typography(typography.smart_punctuation.then(|| typography.options()));
+        
+        let saved = {
+            let settings = self.imp().settings.borrow();
+            self.with_storage(|storage| storage.save_settings(&settings))
+        };
+        if let Err(e) = saved {
+            if !e.is_read_only() {
+                log::error!("Failed to save typography settings: {}", e);
+            }
+        }
+    }
+
+    /// Curly quotes, dashes and ellipses throughout the open composition
+    fn smarten_current_composition(&self) {
+        if self.is_read_only() {
+            self.show_toast("The library is read-only, so compositions can't be changed");
+            return;
+        }
+        let view = match self.imp().composition_view.borrow().clone() {
+            Some(view) => view,
+            None => {
+                self.show_toast("Open a composition to smarten");
+                return;
+            }
+        };
+        
+        let options = self.imp().settings.borrow().typography.options();
+        match view.smarten(&options) {
+            0 => self.show_toast("Punctuation is already typographic"),
+            _ => self.show_toast("Punctuation smartened; undo to take it back"),
+        }
+    }
+
+    // ========== Comments and Suggestions ==========
+
+    /// Turn suggestion mode on or off for the open composition
+    fn toggle_suggesting(&self) {
+        if self.is_read_only() {
+            self.show_toast("The library is read-only");
+            return;
+        }
+        let view = match self.imp().composition_view.borrow().clone() {
+            Some(view) if !self.imp().in_flow_mode.get() => view,
+            _ => {
+                self.show_toast("Open a composition to suggest changes to it");
+                return;
+            }
+        };
+        let suggesting = !view.is_suggesting();
+        view.set_suggesting(suggesting);
+        self.on_composition_suggesting_changed(suggesting);
+    }
+
+    /// Start a comment on the text selected in the open composition
+    fn add_comment(&self) {
+        if self.is_read_only() {
+            self.show_toast("The library is read-only");
+            return;
+        }
+        match self.imp().composition_view.borrow().clone() {
+            Some(view) if !self.imp().in_flow_mode.get() => view.add_comment(),
+            _ => self.show_toast("Open a composition to comment on it"),
+        }
+    }
+
+    // ========== Find and Replace ==========
+
+    /// Find, and replace if `replace`, in the open composition
+    fn show_find(&self, replace: bool) {
+        match self.imp().composition_view.borrow().clone() {
+            Some(view) if !self.imp().in_flow_mode.get() => view.show_find(replace),
+            _ => self.show_toast("Open a composition to search it"),
+        }
+    }
+
+    /// Find and replace across every composition, note and project, previewing
+    /// each change first
+    fn show_library_replace(&self) {
+        if self.is_read_only() {
+            self.show_toast("The library is read-only, so nothing can be replaced");
+            return;
+        }
+        self.sync_current_to_list();
+        let projects = match self.with_storage(|storage| storage.load_projects()) {
+            Ok(projects) => projects,
+            Err(e) => {
+                log::error!("Failed to load projects: {}", e);
+                self.show_toast("Couldn't load projects to search");
+                return;
+            }
+        };
+        
+        let dialog = LibraryReplace::new(self.imp().compositions.borrow().clone(), projects);
+        let window = self.clone();
+        dialog.connect_replace(move |matcher, replacement, hits| {
+            window.replace_in_library(matcher, replacement, hits);
+        });
+        dialog.present(Some(self));
+    }
+
+    /// Make the picked replacements and save them, keeping what they changed
+    /// so the toast can undo them
+    fn replace_in_library(&self, matcher: &Matcher, replacement: &str, hits: &[Hit]) {
+        self.sync_current_to_list();
+        let mut projects = match self.with_storage(|storage| storage.load_projects()) {
+            Ok(projects) => projects,
+            Err(e) => {
+                log::error!("Failed to load projects: {}", e);
+                self.show_toast("Nothing was replaced, as projects couldn't be loaded");
+                return;
+            }
+        };
+        let compositions_before = self.imp().compositions.borrow().clone();
+        let projects_before = projects.clone();
+        
+        let replaced = replace::replace_in_library(
+            matcher,
+            replacement,
+            &mut self.imp().compositions.borrow_mut(),
+            &mut projects,
+            hits,
+        );
+        if replaced.count == 0 {
+            return;
+        }
+        
+        let snapshot = {
+            let compositions = self.imp().compositions.borrow();
+            ReplaceSnapshot {
+                compositions: compositions_before
+                    .into_iter()
+                    .filter(|c| replaced.compositions.contains(&c.id))
+                    .filter_map(|before| {
+                        let after = compositions.iter().find(|c| c.id == before.id)?.clone();
+                        Some((before, after))
+                    })
+                    .collect(),
+                projects: projects_before
+                    .into_iter()
+                    .filter(|p| replaced.projects.contains(&p.id))
+                    .filter_map(|before| {
+                        let after = projects.iter().find(|p| p.id == before.id)?.clone();
+                        Some((before, after))
+                    })
+                    .collect(),
+            }
+        };
+        let touched: Vec<Composition> = snapshot.compositions.iter().map(|(before, _)| before.clone()).collect();
+        self.save_replacement(&touched, &projects);
+        self.imp().replace_snapshot.replace(Some(snapshot));
+        self.update_undo_replace_action();
+        
+        let message = match replaced.count {
+            1 => "Replaced 1 match".to_string(),
+            n => format!("Replaced {} matches", n),
+        };
+        let toast = adw::Toast::builder()
+            .title(message.as_str())
+            .button_label("Undo")
+            .action_name("win.undo-replace")
+            .timeout(10)
+            .build();
+        self.imp().toast_overlay.add_toast(toast);
+    }
+
+    /// Put back the text the last library-wide replace changed, unless any of
+    /// it has been edited since
+    fn undo_library_replace(&self) {
+        if self.is_read_only() {
+            self.show_toast("The library is read-only, so the replacement can't be undone");
+            return;
+        }
+        let snapshot = match self.imp().replace_snapshot.borrow().clone() {
+            Some(snapshot) => snapshot,
+            None => return,
+        };
+        let mut projects = match self.with_storage(|storage| storage.load_projects()) {
+            Ok(projects) => projects,
+            Err(e) => {
+                log::error!("Failed to load projects: {}", e);
+                self.show_toast("The replacement can't be undone, as projects couldn't be loaded");
+                return;
+            }
+        };
+        
+        self.sync_current_to_list();
+        let edited = {
+            let compositions = self.imp().compositions.borrow();
+            let edited_composition = snapshot.compositions.iter().find(|(_, after)| {
+                compositions.iter().find(|c| c.id == after.id).is_some_and(|comp| !same_text(comp, after))
+            });
+            let edited_project = snapshot.projects.iter().find(|(_, after)| {
+                projects
+                    .iter()
+                    .find(|p| p.id == after.id)
+                    .is_some_and(|project| project.title != after.title || project.description != after.description)
+            });
+            edited_composition
+                .map(|(_, after)| after.title.clone())
+                .or(edited_project.map(|(_, after)| after.title.clone()))
+        };
+        if let Some(title) = edited {
+            self.show_toast(&format!("“{}” has changed since, so the replacement can't be undone", title));
+            return;
+        }
+        
+        // Only the replaced text goes back; comments and the like made since stay
+        let now = chrono::Utc::now();
+        let mut touched = Vec::new();
+        {
+            let mut compositions = self.imp().compositions.borrow_mut();
+            for (before, after) in &snapshot.compositions {
+                if let Some(comp) = compositions.iter_mut().find(|c| c.id == before.id) {
+                    touched.push(comp.clone());
+                    comp.title = before.title.clone();
+                    comp.content = before.content.clone();
+                    comp.follow_edit(&after.content);
+                    comp.update_word_count();
+                    for note in comp.notes.iter_mut() {
+                        if let Some(old) = before.notes.iter().find(|n| n.id == note.id) {
+                            note.content = old.content.clone();
+                        }
+                    }
+                    comp.updated_at = now;
+                }
+            }
+        }
+        for (before, _) in &snapshot.projects {
+            if let Some(project) = projects.iter_mut().find(|p| p.id == before.id) {
+                project.title = before.title.clone();
+                project.description = before.description.clone();
+                project.updated_at = now;
+            }
+        }
+        self.imp().replace_snapshot.replace(None);
+        self.update_undo_replace_action();
+        self.save_replacement(&touched, &projects);
+        self.show_toast("Replacement undone");
+    }
+
+    /// Undo Replace is there while the last library-wide replace can be undone
+    fn update_undo_replace_action(&self) {
+        let undoable = self.imp().replace_snapshot.borrow().is_some();
+        if let Some(action) = self.lookup_action("undo-replace").and_downcast::<gio::SimpleAction>() {
+            action.set_enabled(undoable);
+        }
+    }
+
+    /// Save compositions and projects a library-wide replace touched, and show
+    /// them wherever they're open
+    fn save_replacement(&self, touched: &[Composition], projects: &[Project]) {
+        self.save_projects(projects);
+        self.save_touched_compositions(touched);
+        if self.imp().main_stack.visible_child_name().as_deref() == Some("projects") {
+            self.show_projects();
+        }
+    }
+
+    /// Save compositions changed outside the editor, given as they were
+    /// before, and show them wherever they're open
+    fn save_touched_compositions(&self, touched: &[Composition]) {
+        // The open composition is written from the editor's copy, so it has to
+        // have the change too
+        let current_id = self.imp().current_composition.borrow().as_ref().map(|c| c.id.clone());
+        let current_touched = touched.iter().any(|c| Some(&c.id) == current_id.as_ref());
+        if current_touched {
+            let updated = self.imp().compositions.borrow().iter().find(|c| Some(&c.id) == current_id.as_ref()).cloned();
+            self.imp().current_composition.replace(updated);
+        }
+        
+        self.cancel_autosave();
+        let saved = self.write_compositions();
+        for old in touched.iter().filter(|c| c.external_path.is_some() && Some(&c.id) != current_id.as_ref()) {
+            let comp = self.imp().compositions.borrow().iter().find(|c| c.id == old.id).cloned();
+            if let Some(comp) = comp {
+                if let Err(e) = self.with_storage(|storage| storage.write_external(&comp)) {
+                    log::error!("Failed to write {}: {}", comp.title, e);
+                }
+            }
+        }
+        match saved {
+            Ok(()) => {
+                for comp in touched {
+                    self.announce(api::Change::new(api::Kind::Composition, api::Action::Updated, &comp.id));
+                }
+            }
+            // Still in memory, and saved again once the library is writable
+            Err(e) => log::error!("Failed to save compositions: {}", e),
+        }
+        
+        if current_touched {
+            self.reopen_current_composition();
+        }
+        self.update_composition_list();
+    }
+
+    /// Compare two versions of a text, starting with the open composition
+    fn show_compare(&self) {
+        self.sync_current_to_list();
+        let flows = match self.with_storage(|storage| storage.load_flows()) {
+            Ok(flows) => flows,
+            Err(e) => {
+                log::error!("Failed to load flows: {}", e);
+                Vec::new()
+            }
+        };
+        
+        let compositions = self.imp().compositions.borrow().clone();
+        let mut versions: Vec<Version> = compositions
+            .iter()
+            .filter(|c| !c.archived)
+            .chain(compositions.iter().filter(|c| c.archived))
+            .map(|comp| {
+                let title = if comp.title.is_empty() { "Untitled" } else { comp.title.as_str() };
+                Version {
+                    name: if comp.archived { format!("{} (archived)", title) } else { title.to_string() },
+                    text: comp.content_without_suggestions().into_owned(),
+                    composition_id: Some(comp.id.clone()),
+                }
+            })
+            .collect();
+        versions.extend(flows.into_iter().rev().map(|flow| Version {
+            name: format!("Flow, {}", flow.created_at.format("%B %d, %Y %H:%M")),
+            text: flow.content,
+            composition_id: None,
+        }));
+        
+        let current_id = self.imp().current_composition.borrow().as_ref().map(|c| c.id.clone());
+        let left = versions
+            .iter()
+            .position(|v| v.composition_id.is_some() && v.composition_id == current_id)
+            .unwrap_or(0);
+        let right = if left == 0 && versions.len() > 1 { 1 } else { 0 };
+        
+        let dialog = CompareVersions::new(versions, left, right);
+        let window = self.clone();
+        dialog.connect_copy(move |id, text| window.copy_compared_change(id, text));
+        dialog.present(Some(self));
+    }
+
+    /// Give composition `id` the text a change was copied across into, which
+    /// was compared without its pending suggestions
+    fn copy_compared_change(&self, id: &str, text: &str) -> bool {
+        if self.is_read_only() {
+            self.show_toast("The library is read-only, so nothing can be copied into it");
+            return false;
+        }
+        self.sync_current_to_list();
+        let before = {
+            let mut compositions = self.imp().compositions.borrow_mut();
+            let comp = match compositions.iter_mut().find(|c| c.id == id) {
+                Some(comp) => comp,
+                None => return false,
+            };
+            let before = comp.clone();
+            comp.set_content_without_suggestions(text);
+            comp.update_word_count();
+            comp.updated_at = chrono::Utc::now();
+            before
+        };
+        self.save_touched_compositions(&[before]);
+        true
+    }
+
+    /// Review the spelling of whatever is being written
+    fn check_spelling(&self) {
+        if self.imp().in_flow_mode.get() {
+            if let Some(flow_view) = self.imp().current_flow_view.borrow().clone() {
+                flow_view.check_spelling();
+            }
+        } else if let Some(view) = self.imp().composition_view.borrow().clone() {
+            view.check_spelling();
+        }
+    }
+
+    /// Word frequency and repetition in the open composition
+    fn analyze_current_composition(&self) {
+        let id = self.imp().current_composition.borrow().as_ref().map(|c| c.id.clone());
+        match id {
+            Some(id) => self.show_word_analysis(vec![id]),
+            None => self.show_toast("Open a composition to analyze"),
+        }
+    }
+
+    /// Analyze compositions together against the rest of the library, showing
+    /// what's picked from the report in the editor
+    fn show_word_analysis(&self, ids: Vec<String>) {
+        self.sync_current_to_list();
+        let (title, names, ids, analysis) = {
+            let compositions = self.imp().compositions.borrow();
+            let picked: Vec<&Composition> = ids
+                .iter()
+                .filter_map(|id| compositions.iter().find(|c| &c.id == id))
+                .collect();
+            
+            // Suggested insertions aren't written yet, so they're left out
+            let others: Vec<_> = compositions
+                .iter()
+                .filter(|c| !c.archived && !ids.contains(&c.id))
+                .map(|c| c.content_without_suggestions())
+                .collect();
+            let baseline = Baseline::new(others.iter().map(|text| text.as_ref()));
+            let texts: Vec<_> = picked.iter().map(|c| c.content_without_suggestions()).collect();
+            let documents: Vec<&str> = texts.iter().map(|text| text.as_ref()).collect();
+            let names: Vec<String> = picked.iter().map(|c| c.title.clone()).collect();
+            let title = match picked.as_slice() {
+                [only] => format!("Word Analysis — {}", only.title),
+                _ => "Word Analysis".to_string(),
+            };
+            let ids: Vec<String> = picked.iter().map(|c| c.id.clone()).collect();
+            (title, names, ids, frequency::analyze(&documents, &baseline))
+        };
+        if ids.is_empty() {
+            self.show_toast("Nothing to analyze");
+            return;
+        }
+        
+        let dialog = WordAnalysis::new(&title, &names, &analysis);
+        
+        // A word shows in the open composition if it's used there, else in the first that uses it
+        let window = self.clone();
+        let term_ids = ids.clone();
+        dialog.connect_term_activated(move |term| {
+            let open = window.imp().current_composition.borrow().as_ref().map(|c| c.id.clone());
+            let target = {
+                let compositions = window.imp().compositions.borrow();
+                let uses = |id: &String| {
+                    compositions
+                        .iter()
+                        .find(|c| &c.id == id)
+                        .is_some_and(|c| !frequency::occurrences(&c.content_without_suggestions(), term).is_empty())
+                };
+                match open {
+                    Some(open) if term_ids.contains(&open) && uses(&open) => Some(open),
+                    _ => term_ids.iter().find(|id| uses(id)).cloned(),
+                }
+            };
+            if let Some(view) = target.and_then(|id| window.show_composition_view(&id)) {
+                view.highlight_term(term, None);
+            }
+        });
+        
+        let window = self.clone();
+        dialog.connect_cluster_activated(move |cluster| {
+            let view = ids.get(cluster.document).and_then(|id| window.show_composition_view(id));
+            if let Some(view) = view {
+                view.highlight_term(&cluster.word, Some(cluster.range().start));
+            }
+        });
+        
+        dialog.present(Some(self));
+    }
+
+    /// The editor for a composition, opening it if it isn't already
+    fn show_composition_view(&self, comp_id: &str) -> Option<CompositionView> {
+        self.select_nav_section("writing");
+        if self.open_view_for(comp_id).is_none() {
+            self.open_composition_by_id(comp_id);
+        }
+        self.open_view_for(comp_id)
+    }
+
+    fn show_writing(&self) {
+        // Return to composition view with current composition or empty state
+        self.imp().main_stack.set_visible_child_name("composition");
+    }
+
+    fn setup_composition_list(&self) {
+        let list = &self.imp().composition_list;
+        list.set_selection_mode(gtk4::SelectionMode::Single);
+        
+        // Row activation now handled per-row via connect_activated
+        // to distinguish folder vs composition rows
+    }
+
+    fn load_compositions(&self) {
+        self.imp().needs_reload.set(false);
+
+        // Load folders
+        match self.with_storage(|storage| storage.load_folders()) {
+            Ok(folders) => {
+                self.imp().folders.replace(folders);
+            }
+            Err(e) => {
+                log::error!("Failed to load folders: {}", e);
+                self.report_load_failure(&e);
+            }
+        }
+
+        // Load compositions
+        match self.with_storage(|storage| storage.load_compositions()) {
+            Ok(compositions) => {
+                self.imp().compositions.replace(compositions);
+                self.update_composition_list();
+            }
+            Err(e) => {
+                log::error!("Failed to load compositions: {}", e);
+                self.report_load_failure(&e);
+            }
+        }
+    }
+
+    /// Run an operation against the application's library
+    fn with_storage<T>(&self, f: impl FnOnce(&Storage) -> Result<T, StorageError>) -> Result<T, StorageError> {
+        let app = self.application().and_downcast::<crate::app::AbbeyApp>().unwrap();
+        let storage_ref = app.storage();
+        
+        match *storage_ref {
+            Some(ref storage) => f(storage),
+            None => Err(StorageError::ReadOnly),
+        }
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.with_storage(|storage| Ok(storage.is_read_only())).unwrap_or(true)
+    }
+
+    /// A library file that can't be read must not be replaced by a full rewrite of
+    /// whatever partial data made it into memory, so stop writing altogether
+    fn report_load_failure(&self, error: &StorageError) {
+        self.imp().needs_reload.set(true);
+        self.enter_read_only(&format!("Abbey couldn't read your library: {}.", error));
+    }
+
+    /// Switch to read-only mode and explain why in a persistent banner
+    pub fn enter_read_only(&self, reason: &str) {
+        let _ = self.with_storage(|storage| {
+            storage.set_read_only(true);
+            Ok(())
+        });
+
+        let banner = &self.imp().read_only_banner;
+        banner.set_title(&format!("{} Your writing is shown read-only.", reason));
+        banner.set_revealed(true);
+
+        self.update_read_only_state();
+    }
+
+    /// Enable or disable editing to match the library's writability
+    fn update_read_only_state(&self) {
+        let read_only = self.is_read_only();
+
+        for name in ["new-composition", "flow-mode", "archive", "move-to-folder"] {
+            if let Some(action) = self.lookup_action(name).and_downcast::<gio::SimpleAction>() {
+                action.set_enabled(!read_only);
+            }
+        }
+
+        if let Some(ref view) = *self.imp().composition_view.borrow() {
+            view.set_editable(!read_only);
+        }
+
+        if !read_only {
+            self.imp().read_only_banner.set_revealed(false);
+        }
+    }
+
+    /// Retry button on the read-only banner: try to get write access back
+    fn reopen_library(&self) {
+        let app = self.application().and_downcast::<crate::app::AbbeyApp>().unwrap();
+
+        match app.reopen_storage() {
+            Ok(()) => {
+                self.update_read_only_state();
+                if self.imp().needs_reload.get() {
+                    // Nothing trustworthy was loaded before, so read it again
+                    self.load_compositions();
+                } else {
+                    self.retry_failed_writes();
+                }
+
+                if !self.is_read_only() {
+                    self.show_toast("Library is writable again");
+                }
+            }
+            Err(e) => {
+                log::error!("Failed to reopen library: {}", e);
+                self.imp().read_only_banner.set_title(&format!(
+                    "Abbey still can't write to your library: {}. Your writing is shown read-only.",
+                    e
+                ));
+            }
+        }
+    }
+
+    /// Show a failed write in the error banner and keep it for Retry
+    fn report_write_failure(&self, write: FailedWrite, error: &StorageError) {
+        if error.is_read_only() {
+            self.show_toast("The library is read-only, so this change wasn't saved");
+            return;
+        }
+
+        {
+            let mut failed = self.imp().failed_writes.borrow_mut();
+            failed.retain(|w| !write.supersedes(w));
+            failed.push(write);
+        }
+
+        let banner = &self.imp().error_banner;
+        banner.set_title(&format!("Changes could not be saved: {}", error));
+        banner.set_revealed(true);
+
+        if error.prevents_writing() {
+            self.enter_read_only(&format!("Abbey can't write to your library: {}.", error));
+        }
+    }
+
+    /// A later successful write of the same kind makes a failed one obsolete
+    fn resolve_failed_write(&self, write: &FailedWrite) {
+        let remaining = {
+            let mut failed = self.imp().failed_writes.borrow_mut();
+            failed.retain(|w| !write.supersedes(w));
+            failed.len()
+        };
+
+        if remaining == 0 {
+            self.imp().error_banner.set_revealed(false);
+        }
+    }
+
+    /// Retry button on the error banner
+    fn retry_failed_writes(&self) {
+        let pending = self.imp().failed_writes.take();
+        if pending.is_empty() {
+            self.imp().error_banner.set_revealed(false);
+            return;
+        }
+
+        for write in pending {
+            // Failures re-register themselves through report_write_failure
+            match write {
+                FailedWrite::Compositions => {
+                    self.sync_current_to_list();
+                    let _ = self.write_compositions();
+                }
+                FailedWrite::Folders => self.save_folders(),
+                FailedWrite::Projects(projects) => self.save_projects(&projects),
+                FailedWrite::Flow(flow) => self.save_flow(flow),
+            }
+        }
+
+        if self.imp().failed_writes.borrow().is_empty() {
+            self.imp().error_banner.set_revealed(false);
+            self.show_toast("All changes saved");
+        }
+    }
+
+    fn update_composition_list(&self) {
+        let list = &self.imp().composition_list;
+        let compositions = self.imp().compositions.borrow();
+        let folders = self.imp().folders.borrow();
+        
+        // Clear existing rows
+        while let Some(child) = list.first_child() {
+            list.remove(&child);
+        }
+        
+        // Add "New Folder" button row
+        let new_folder_row = adw::ActionRow::builder()
+            .title("New Folder")
+            .activatable(true)
+            .build();
+        new_folder_row.add_prefix(&gtk4::Image::from_icon_name("folder-new-symbolic"));
+        new_folder_row.add_css_class("dim-label");
+        
+        let window = self.clone();
+        new_folder_row.connect_activated(move |_| {
+            window.create_new_folder();
+        });
+        list.append(&new_folder_row);
+        
+        // Add folders with their compositions
+        for folder in folders.iter() {
+            let folder_row = self.create_folder_row(folder);
+            list.append(&folder_row);
+            
+            // Add compositions in this folder if expanded
+            if folder.expanded {
+                for comp in compositions.iter().filter(|c| !c.archived && c.folder_id.as_ref() == Some(&folder.id)) {
+                    let row = self.create_composition_row(comp);
+                    row.set_margin_start(24);
+                    list.append(&row);
+                }
+            }
+        }
+        
+        // Add compositions without a folder
+        for comp in compositions.iter().filter(|c| !c.archived && c.folder_id.is_none()) {
+            let row = self.create_composition_row(comp);
+            list.append(&row);
+        }
+    }
+
+    fn create_folder_row(&self, folder: &Folder) -> adw::ActionRow {
+        let row = adw::ActionRow::builder()
+            .title(&folder.name)
+            .activatable(true)
+            .build();
+        
+        let icon_name = if folder.expanded { "folder-open-symbolic" } else { "folder-symbolic" };
+        row.add_prefix(&gtk4::Image::from_icon_name(icon_name));
+        row.add_css_class("folder-row");
+        
+        // Count compositions in folder
+        let compositions = self.imp().compositions.borrow();
+        let count = compositions.iter().filter(|c| !c.archived && c.folder_id.as_ref() == Some(&folder.id)).count();
+        row.set_subtitle(&format!("{} compositions", count));
+        
+        // Toggle expand/collapse on activate
+        let window = self.clone();
+        let folder_id = folder.id.clone();
+        row.connect_activated(move |_| {
+            window.toggle_folder(&folder_id);
+        });
+        
+        // Edit button
+        let edit_btn = gtk4::Button::builder()
+            .icon_name("document-edit-symbolic")
+            .valign(gtk4::Align::Center)
+            .tooltip_text("Rename folder")
+            .build();
+        edit_btn.add_css_class("flat");
+        
+        let window = self.clone();
+        let folder_id = folder.id.clone();
+        let folder_name = folder.name.clone();
+        edit_btn.connect_clicked(move |_| {
+            window.rename_folder(&folder_id, &folder_name);
+        });
+        row.add_suffix(&edit_btn);
+        
+        // Delete button
+        let delete_btn = gtk4::Button::builder()
+            .icon_name("user-trash-symbolic")
+            .valign(gtk4::Align::Center)
+            .tooltip_text("Delete folder")
+            .build();
+        delete_btn.add_css_class("flat");
+        
+        let window = self.clone();
+        let folder_id = folder.id.clone();
+        delete_btn.connect_clicked(move |_| {
+            window.delete_folder(&folder_id);
+        });
+        row.add_suffix(&delete_btn);
+        
+        row
+    }
+
+    fn create_composition_row(&self, composition: &Composition) -> adw::ActionRow {
+        let row = adw::ActionRow::builder()
+            .title(&composition.title)
+            .subtitle(&composition.created_at.format("%Y-%m-%d %H:%M").to_string())
+            .activatable(true)
+            .build();
+        
+        row.add_css_class("composition-row");
+        
+        if let Some(ref path) = composition.external_path {
+            let icon = gtk4::Image::from_icon_name("document-edit-symbolic");
+            icon.set_tooltip_text(Some(&format!("Linked to {}", path.display())));
+            row.add_prefix(&icon);
+        }
+        
+        // Open composition on activate
+        let window = self.clone();
+        let comp_id = composition.id.clone();
+        row.connect_activated(move |_| {
+            window.open_composition_by_id(&comp_id);
         });
         
         // Move to folder menu button
@@ -410,7 +1526,392 @@ impl AbbeyWindow {
         }
     }
 
+    /// Open a composition by id or unique id prefix, as given on the command line.
+    /// Returns whether one was found.
+    pub fn open_composition_matching(&self, query: &str) -> bool {
+        let id = {
+            let compositions = self.imp().compositions.borrow();
+            let exact = compositions.iter().find(|c| c.id == query);
+            let mut prefixed = compositions.iter().filter(|c| c.id.starts_with(query));
+            match (exact, prefixed.next(), prefixed.next()) {
+                (Some(comp), _, _) | (None, Some(comp), None) => Some(comp.id.clone()),
+                _ => None,
+            }
+        };
+        
+        match id {
+            Some(id) => {
+                self.select_nav_section("writing");
+                self.open_composition_by_id(&id);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Show a flow in the flow journal. Returns whether it was found.
+    pub fn open_flow(&self, flow_id: &str) -> bool {
+        self.select_nav_section("flow-history");
+        
+        self.imp()
+            .flow_history_box
+            .first_child()
+            .and_downcast::<FlowHistoryView>()
+            .is_some_and(|view| view.select_flow(flow_id))
+    }
+
+    /// Everything the desktop search provider looks through, including edits not
+    /// yet written to disk
+    pub fn search_corpus(&self) -> dbus::Corpus {
+        self.sync_current_to_list();
+        let flows = self.with_storage(|storage| storage.load_flows()).unwrap_or_else(|e| {
+            log::error!("Failed to load flows for search: {}", e);
+            Vec::new()
+        });
+        
+        dbus::Corpus {
+            compositions: self.imp().compositions.borrow().clone(),
+            flows,
+        }
+    }
+
+    // ========== Automation ==========
+
+    /// Find a composition by id, title or unique id prefix, as on the command
+    /// line. `Err` holds the titles of every match when more than one did.
+    pub fn find_composition(&self, query: &str) -> Result<Option<Composition>, Vec<String>> {
+        self.sync_current_to_list();
+        let compositions = self.imp().compositions.borrow();
+        
+        search::find_by_id_or_title(&compositions, query, |c| &c.id, |c| &c.title)
+            .map(|found| found.cloned())
+            .map_err(|matches| matches.iter().map(|c| c.title.clone()).collect())
+    }
+
+    /// Add a composition sent in from outside, without opening it
+    pub fn add_composition(&self, composition: Composition) -> Result<(), StorageError> {
+        self.imp().compositions.borrow_mut().insert(0, composition);
+        self.update_composition_list();
+        self.write_compositions()
+    }
+
+    /// Append a paragraph to a composition, through the editor if it's open so
+    /// the cursor and undo history survive
+    pub fn append_paragraph(&self, comp_id: &str, text: &str) -> Result<(), StorageError> {
+        match self.open_view_for(comp_id) {
+            Some(view) => view.append_paragraph(text),
+            None => {
+                let mut compositions = self.imp().compositions.borrow_mut();
+                if let Some(comp) = compositions.iter_mut().find(|c| c.id == comp_id) {
+                    comp.append_paragraph(text);
+                }
+            }
+        }
+        
+        self.cancel_autosave();
+        self.sync_current_to_list();
+        self.update_composition_list();
+        self.write_compositions()
+    }
+
+    /// Attach a note to a composition, returning the note's id
+    pub fn add_note(&self, comp_id: &str, text: &str) -> Result<String, StorageError> {
+        let note_id = match self.open_view_for(comp_id) {
+            Some(view) => view.add_note(text.to_string()),
+            None => {
+                let note = Note::new(text.to_string());
+                let note_id = note.id.clone();
+                let mut compositions = self.imp().compositions.borrow_mut();
+                if let Some(comp) = compositions.iter_mut().find(|c| c.id == comp_id) {
+                    comp.notes.insert(0, note);
+                    comp.updated_at = chrono::Utc::now();
+                }
+                note_id
+            }
+        };
+        
+        self.cancel_autosave();
+        self.sync_current_to_list();
+        self.write_compositions()?;
+        Ok(note_id)
+    }
+
+    pub fn is_in_flow(&self) -> bool {
+        self.imp().in_flow_mode.get()
+    }
+
+    /// Statistics including edits not yet written to disk
+    pub fn library_stats(&self) -> Result<LibraryStats, StorageError> {
+        self.sync_current_to_list();
+        let (flows, projects) = self.with_storage(|storage| Ok((storage.load_flows()?, storage.load_projects()?)))?;
+        
+        Ok(LibraryStats::new(&self.imp().compositions.borrow(), &flows, &projects))
+    }
+
+    /// A small window for jotting something down into a new or recent composition
+    pub fn show_quick_capture(&self, timestamp: u32) {
+        self.sync_current_to_list();
+        let app = self.application().and_downcast::<crate::app::AbbeyApp>().unwrap();
+        let capture = QuickCapture::new(&app, &self.imp().compositions.borrow());
+        
+        let window = self.clone();
+        capture.connect_captured(move |target, text| {
+            if window.is_read_only() {
+                return Err("The library is read-only".to_string());
+            }
+            
+            let result = match target {
+                Some(comp_id) => window.append_paragraph(&comp_id, &text),
+                None => {
+                    let mut composition = Composition::new();
+                    composition.content = text;
+                    composition.update_word_count();
+                    window.add_composition(composition)
+                }
+            };
+            result.map_err(|e| format!("Couldn't save: {}", e))
+        });
+        
+        // Abbey may have been started just to capture something; don't leave an
+        // unseen main window keeping it running afterwards
+        let main_was_visible = self.is_visible();
+        let window = self.clone();
+        capture.connect_close_request(move |_| {
+            if !main_was_visible && !window.is_visible() {
+                window.close();
+            }
+            glib::Propagation::Proceed
+        });
+        
+        capture.present_with_time(timestamp);
+    }
+
+    /// The editor, if it's showing this composition
+    fn open_view_for(&self, comp_id: &str) -> Option<CompositionView> {
+        let is_open = self.imp().current_composition.borrow().as_ref().is_some_and(|c| c.id == comp_id);
+        if is_open {
+            self.imp().composition_view.borrow().clone()
+        } else {
+            None
+        }
+    }
+
+    // ========== HTTP API ==========
+
+    fn app(&self) -> crate::app::AbbeyApp {
+        self.application().and_downcast::<crate::app::AbbeyApp>().unwrap()
+    }
+
+    /// Start the local API if the library's settings turn it on
+    fn start_api(&self) {
+        let api = self.imp().settings.borrow().api.clone();
+        if let Err(e) = self.app().apply_api_settings(&api) {
+            log::error!("Failed to start the HTTP API on port {}: {}", api.port, e);
+            self.show_toast(&format!("The local API couldn't start: {}", e));
+        }
+    }
+
+    fn show_api_settings(&self) {
+        let dialog = ApiDialog::new(&self.imp().settings.borrow().api);
+        
+        let window = self.clone();
+        dialog.connect_changed(move |api| {
+            window.imp().settings.borrow_mut().api = api.clone();
+            let saved = {
+                let settings = window.imp().settings.borrow();
+                window.with_storage(|storage| storage.save_settings(&settings))
+            };
+            if let Err(e) = saved {
+                log::error!("Failed to save API settings: {}", e);
+            }
+            window.app().apply_api_settings(&api).map_err(|e| e.to_string())
+        });
+        dialog.present(Some(self));
+    }
+
+    /// Tell API clients about a change made here in the app
+    fn announce(&self, change: api::Change) {
+        self.app().announce(change);
+    }
+
+    /// Pick up changes made through the API. The open composition is reloaded in
+    /// place if it was among them, keeping the cursor where it was.
+    pub fn reload_library(&self, changes: &[api::Change]) {
+        self.load_compositions();
+        
+        let current_id = match *self.imp().current_composition.borrow() {
+            Some(ref comp) => comp.id.clone(),
+            None => return,
+        };
+        let touched = changes
+            .iter()
+            .any(|c| c.id == current_id || c.composition_id.as_deref() == Some(current_id.as_str()));
+        if !touched {
+            return;
+        }
+        
+        if !self.reopen_current_composition() {
+            self.imp().current_composition.replace(None);
+            self.imp().composition_view.replace(None);
+            let content_box = &self.imp().content_box;
+            while let Some(child) = content_box.first_child() {
+                content_box.remove(&child);
+            }
+            self.show_toast("The open composition was deleted by another program");
+        }
+    }
+
+    /// Show the open composition as it now is in the list, keeping the cursor
+    /// and panes where they were; false if it's no longer there
+    fn reopen_current_composition(&self) -> bool {
+        let current_id = match *self.imp().current_composition.borrow() {
+            Some(ref comp) => comp.id.clone(),
+            None => return false,
+        };
+        let updated = self.imp().compositions.borrow().iter().find(|c| c.id == current_id).cloned();
+        let composition = match updated {
+            Some(composition) => composition,
+            None => return false,
+        };
+        
+        let mut position = self.imp().settings.borrow().session.clone();
+        if let Some(ref view) = *self.imp().composition_view.borrow() {
+            position.cursor_offset = view.cursor_offset();
+            position.scroll_position = view.scroll_position();
+            position.notes_visible = view.notes_visible();
+            position.preview_active = view.preview_active();
+            position.split_active = view.split_active();
+        }
+        
+        self.open_composition(composition);
+        if let Some(ref view) = *self.imp().composition_view.borrow() {
+            view.restore_state(&position);
+        }
+        true
+    }
+
+    // ========== Opening Files ==========
+
+    /// Open markdown files from outside the library. Files already linked are
+    /// opened directly; for the rest, ask whether to import a copy or edit in place.
+    pub fn open_files(&self, files: &[gio::File]) {
+        if self.is_read_only() {
+            self.show_toast("The library is read-only, so files can't be opened");
+            return;
+        }
+        
+        let mut new_files = Vec::new();
+        for file in files {
+            let path = match file.path() {
+                Some(path) => path.canonicalize().unwrap_or(path),
+                None => {
+                    self.show_toast("Abbey can only open files on this computer");
+                    continue;
+                }
+            };
+            let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+            
+            if !import::is_openable(&path) {
+                self.show_toast(&format!("“{}” isn't a Markdown or text file", name));
+                continue;
+            }
+            
+            let linked = import::find_linked(&self.imp().compositions.borrow(), &path).map(|c| c.id.clone());
+            if let Some(id) = linked {
+                self.select_nav_section("writing");
+                self.open_composition_by_id(&id);
+                continue;
+            }
+            
+            match std::fs::read_to_string(&path) {
+                Ok(text) => new_files.push((path, text)),
+                Err(e) => {
+                    log::error!("Failed to read {}: {}", path.display(), e);
+                    self.show_toast(&format!("Couldn't read “{}”", name));
+                }
+            }
+        }
+        
+        if new_files.is_empty() {
+            return;
+        }
+        
+        let heading = match new_files.as_slice() {
+            [(path, _)] => format!("Open “{}”", path.file_name().unwrap_or_default().to_string_lossy()),
+            _ => format!("Open {} Files", new_files.len()),
+        };
+        let dialog = adw::MessageDialog::new(
+            Some(self),
+            Some(&heading),
+            Some("Import a copy into your library, or edit the file where it is? Edits to a linked file are saved back to it."),
+        );
+        
+        dialog.add_response("cancel", "Cancel");
+        dialog.add_response("link", "Edit in Place");
+        dialog.add_response("import", "Import Copy");
+        dialog.set_response_appearance("import", adw::ResponseAppearance::Suggested);
+        dialog.set_default_response(Some("import"));
+        dialog.set_close_response("cancel");
+        
+        let window = self.clone();
+        dialog.connect_response(None, move |dlg, response| {
+            dlg.close();
+            let link = match response {
+                "import" => false,
+                "link" => true,
+                _ => return,
+            };
+            
+            let added: Vec<Composition> = new_files
+                .iter()
+                .map(|(path, text)| {
+                    if link {
+                        import::linked_composition(text, path)
+                    } else {
+                        import::composition_from_markdown(text, path)
+                    }
+                })
+                .collect();
+            window.add_opened_compositions(added, link);
+        });
+        
+        dialog.present();
+    }
+
+    fn add_opened_compositions(&self, added: Vec<Composition>, linked: bool) {
+        let count = added.len();
+        let last = added.last().cloned();
+        
+        {
+            let mut compositions = self.imp().compositions.borrow_mut();
+            for comp in added {
+                compositions.insert(0, comp);
+            }
+        }
+        
+        self.save_compositions();
+        self.update_composition_list();
+        
+        if let Some(comp) = last {
+            self.select_nav_section("writing");
+            self.open_composition(comp);
+        }
+        
+        let message = match (count, linked) {
+            (1, true) => "Editing file in place".to_string(),
+            (1, false) => "File imported".to_string(),
+            (n, true) => format!("Editing {} files in place", n),
+            (n, false) => format!("{} files imported", n),
+        };
+        self.show_toast(&message);
+    }
+
     pub fn create_new_composition(&self) {
+        if self.is_read_only() {
+            self.show_toast("The library is read-only");
+            return;
+        }
+
         let composition = Composition::new();
         
         // Add to list
@@ -429,6 +1930,11 @@ impl AbbeyWindow {
     }
 
     fn create_new_folder(&self) {
+        if self.is_read_only() {
+            self.show_toast("The library is read-only");
+            return;
+        }
+
         let dialog = adw::MessageDialog::new(
             Some(self),
             Some("New Folder"),
@@ -469,6 +1975,7 @@ impl AbbeyWindow {
 
     fn do_create_folder(&self, name: String) {
         let folder = Folder::new(name);
+        let change = api::Change::new(api::Kind::Folder, api::Action::Created, &folder.id);
         
         {
             let mut folders = self.imp().folders.borrow_mut();
@@ -476,6 +1983,7 @@ impl AbbeyWindow {
         }
         
         self.save_folders();
+        self.announce(change);
         self.update_composition_list();
         self.show_toast("Folder created");
     }
@@ -538,6 +2046,7 @@ impl AbbeyWindow {
             }
         }
         self.save_folders();
+        self.announce(api::Change::new(api::Kind::Folder, api::Action::Updated, folder_id));
         self.update_composition_list();
     }
 
@@ -583,31 +2092,84 @@ impl AbbeyWindow {
         
         self.save_folders();
         self.save_compositions();
+        self.announce(api::Change::new(api::Kind::Folder, api::Action::Deleted, folder_id));
         self.update_composition_list();
         self.show_toast("Folder deleted");
     }
 
     fn save_folders(&self) {
-        let app = self.application().and_downcast::<crate::app::AbbeyApp>().unwrap();
-        let storage_ref = app.storage();
-        
-        if let Some(ref storage) = *storage_ref {
+        let result = {
             let folders = self.imp().folders.borrow();
-            if let Err(e) = storage.save_folders(&folders) {
+            self.with_storage(|storage| storage.save_folders(&folders))
+        };
+
+        match result {
+            Ok(()) => self.resolve_failed_write(&FailedWrite::Folders),
+            Err(e) => {
                 log::error!("Failed to save folders: {}", e);
+                self.report_write_failure(FailedWrite::Folders, &e);
             }
         }
     }
 
     fn save_compositions(&self) {
-        let app = self.application().and_downcast::<crate::app::AbbeyApp>().unwrap();
-        let storage_ref = app.storage();
+        if let Err(e) = self.write_compositions() {
+            log::error!("Failed to save compositions: {}", e);
+        }
+    }
+
+    /// Write the composition index, keeping the header bar indicator and error
+    /// banner in sync
+    fn write_compositions(&self) -> Result<(), StorageError> {
+        let result = self.with_storage(|storage| {
+            storage.save_compositions(&self.imp().compositions.borrow())?;
+
+            // A linked composition also lives in its own file
+            if let Some(ref comp) = *self.imp().current_composition.borrow() {
+                storage.write_external(comp)?;
+            }
+            
+            // Everything in memory is on disk now, so the journal is obsolete
+            if let Err(e) = storage.clear_recovery() {
+                log::warn!("Failed to clear recovery journal: {}", e);
+            }
+            Ok(())
+        });
         
-        if let Some(ref storage) = *storage_ref {
-            let compositions = self.imp().compositions.borrow();
-            if let Err(e) = storage.save_compositions(&compositions) {
-                log::error!("Failed to save compositions: {}", e);
+        match result {
+            Ok(()) => {
+                self.set_save_state(SaveState::Saved);
+                self.resolve_failed_write(&FailedWrite::Compositions);
+                if let Some(ref comp) = *self.imp().current_composition.borrow() {
+                    self.announce(api::Change::new(api::Kind::Composition, api::Action::Updated, &comp.id));
+                }
             }
+            Err(ref e) => {
+                self.set_save_state(SaveState::Failed);
+                self.report_write_failure(FailedWrite::Compositions, e);
+            }
+        }
+        result
+    }
+
+    fn set_save_state(&self, state: SaveState) {
+        self.imp().save_state.set(state);
+        
+        let label = &self.imp().save_status_label;
+        let (text, tooltip) = match state {
+            SaveState::Saved => ("Saved", "All changes saved"),
+            SaveState::Saving => ("Saving…", "Changes will be saved shortly"),
+            SaveState::Failed => ("Save failed", "Your latest changes could not be saved"),
+        };
+        label.set_text(text);
+        label.set_tooltip_text(Some(tooltip));
+        
+        if state == SaveState::Failed {
+            label.add_css_class("error");
+            label.remove_css_class("dim-label");
+        } else {
+            label.remove_css_class("error");
+            label.add_css_class("dim-label");
         }
     }
 
@@ -635,6 +2197,7 @@ impl AbbeyWindow {
     }
 
     fn open_composition(&self, composition: Composition) {
+        let composition = self.refresh_linked_file(composition);
         self.imp().current_composition.replace(Some(composition.clone()));
         
         // Clear content box and add composition view
@@ -659,11 +2222,84 @@ impl AbbeyWindow {
             window_clone3.on_composition_notes_changed(notes);
         });
         
+        let window_clone6 = self.clone();
+        view.connect_comments_changed(move |comments| {
+            window_clone6.on_composition_comments_changed(comments);
+        });
+        
+        let window_clone7 = self.clone();
+        view.connect_suggestions_changed(move |suggestions| {
+            window_clone7.on_composition_suggestions_changed(suggestions);
+        });
+        
+        let window_clone8 = self.clone();
+        view.connect_suggesting_changed(move |suggesting| {
+            window_clone8.on_composition_suggesting_changed(suggesting);
+        });
+        self.set_action_state("suggest-changes", composition.suggesting.to_variant());
+        
+        let window_clone4 = self.clone();
+        view.connect_language_changed(move |language| {
+            window_clone4.on_composition_language_changed(language);
+        });
+        
+        let window_clone5 = self.clone();
+        view.connect_lint_ignores_changed(move |ignores| {
+            window_clone5.on_composition_lint_ignores_changed(ignores);
+        });
+        
+        let window_clone9 = self.clone();
+        view.connect_title_committed(move |old_title, _| {
+            window_clone9.on_composition_renamed(&old_title);
+        });
+        
+        let window_clone10 = self.clone();
+        view.connect_link_activated(move |target| {
+            window_clone10.follow_link(target);
+        });
+        
+        let window_clone11 = self.clone();
+        view.connect_backlink_activated(move |source| {
+            window_clone11.open_backlink(source);
+        });
+        
+        view.set_editable(!self.is_read_only());
         content_box.append(&view);
+        self.imp().composition_view.replace(Some(view));
+        self.refresh_links();
+        self.apply_focus_mode();
         
         self.imp().main_stack.set_visible_child_name("composition");
     }
 
+    /// Pick up edits made to a linked file outside Abbey since it was last open
+    fn refresh_linked_file(&self, mut composition: Composition) -> Composition {
+        if composition.external_path.is_none() {
+            return composition;
+        }
+        
+        // Unsaved edits to this very composition must reach the file first
+        if let Err(e) = self.flush_pending_save() {
+            log::error!("Failed to save before reloading linked file: {}", e);
+            return composition;
+        }
+        
+        match self.with_storage(|storage| storage.read_external(&mut composition)) {
+            Ok(true) => {
+                if let Some(comp) = self.imp().compositions.borrow_mut().iter_mut().find(|c| c.id == composition.id) {
+                    *comp = composition.clone();
+                }
+                self.save_compositions();
+            }
+            Ok(false) => {}
+            Err(e) => {
+                log::error!("Failed to read linked file: {}", e);
+                self.show_toast("The linked file couldn't be read; showing the last saved copy");
+            }
+        }
+        composition
+    }
+
     fn on_composition_content_changed(&self, content: String) {
         if let Some(ref mut comp) = *self.imp().current_composition.borrow_mut() {
             comp.content = content;
@@ -684,6 +2320,78 @@ impl AbbeyWindow {
         self.schedule_autosave();
     }
 
+    /// Point links to the open composition's old title at its new one
+    fn on_composition_renamed(&self, old_title: &str) {
+        if self.is_read_only() {
+            return;
+        }
+        let id = match *self.imp().current_composition.borrow() {
+            Some(ref comp) => comp.id.clone(),
+            None => return,
+        };
+        self.sync_current_to_list();
+        let before = self.imp().compositions.borrow().clone();
+        let changed = links::retitle(&mut self.imp().compositions.borrow_mut(), &id, old_title);
+        if changed.is_empty() {
+            return;
+        }
+        let touched: Vec<Composition> = before.into_iter().filter(|c| changed.contains(&c.id)).collect();
+        self.save_touched_compositions(&touched);
+        self.refresh_links();
+        self.show_toast(&match changed.len() {
+            1 => "Updated links in 1 composition".to_string(),
+            n => format!("Updated links in {} compositions", n),
+        });
+    }
+
+    /// Give the open composition's editor the titles it can link to and the
+    /// places that link to it
+    fn refresh_links(&self) {
+        let view = match self.imp().composition_view.borrow().clone() {
+            Some(view) => view,
+            None => return,
+        };
+        let current = match self.imp().current_composition.borrow().clone() {
+            Some(comp) => comp,
+            None => return,
+        };
+        let flows = self.with_storage(|storage| storage.load_flows()).unwrap_or_else(|e| {
+            log::error!("Failed to load flows for backlinks: {}", e);
+            Vec::new()
+        });
+        
+        let compositions = self.imp().compositions.borrow();
+        view.set_link_titles(
+            compositions
+                .iter()
+                .filter(|c| !c.archived && c.id != current.id && !c.title.is_empty())
+                .map(|c| c.title.clone())
+                .collect(),
+        );
+        view.set_backlinks(links::backlinks(&current, &compositions, &flows));
+    }
+
+    /// Open the composition a link Ctrl+clicked in the editor is to
+    fn follow_link(&self, target: &str) {
+        self.sync_current_to_list();
+        let id = links::resolve(target, &self.imp().compositions.borrow()).map(|c| c.id.clone());
+        match id {
+            Some(id) => self.open_composition_by_id(&id),
+            None => self.show_toast(&format!("No composition called “{}”", target)),
+        }
+    }
+
+    fn open_backlink(&self, source: &Source) {
+        match source {
+            Source::Composition(id) | Source::Note(id, _) => self.open_composition_by_id(id),
+            Source::Flow(id) => {
+                if !self.open_flow(id) {
+                    self.show_toast("The flow couldn't be found");
+                }
+            }
+        }
+    }
+
     fn on_composition_notes_changed(&self, notes: Vec<crate::data::Note>) {
         if let Some(ref mut comp) = *self.imp().current_composition.borrow_mut() {
             comp.notes = notes;
@@ -693,6 +2401,49 @@ impl AbbeyWindow {
         self.schedule_autosave();
     }
 
+    fn on_composition_comments_changed(&self, comments: Vec<crate::data::Comment>) {
+        if let Some(ref mut comp) = *self.imp().current_composition.borrow_mut() {
+            comp.comments = comments;
+            comp.updated_at = chrono::Utc::now();
+        }
+        self.sync_current_to_list();
+        self.schedule_autosave();
+    }
+
+    fn on_composition_suggestions_changed(&self, suggestions: Vec<crate::data::Suggestion>) {
+        if let Some(ref mut comp) = *self.imp().current_composition.borrow_mut() {
+            comp.suggestions = suggestions;
+            comp.updated_at = chrono::Utc::now();
+        }
+        self.sync_current_to_list();
+        self.schedule_autosave();
+    }
+
+    fn on_composition_suggesting_changed(&self, suggesting: bool) {
+        self.set_action_state("suggest-changes", suggesting.to_variant());
+        if let Some(ref mut comp) = *self.imp().current_composition.borrow_mut() {
+            comp.suggesting = suggesting;
+        }
+        self.sync_current_to_list();
+        self.schedule_autosave();
+    }
+
+    fn on_composition_language_changed(&self, language: String) {
+        if let Some(ref mut comp) = *self.imp().current_composition.borrow_mut() {
+            comp.language = Some(language);
+        }
+        self.sync_current_to_list();
+        self.schedule_autosave();
+    }
+
+    fn on_composition_lint_ignores_changed(&self, ignores: Vec<String>) {
+        if let Some(ref mut comp) = *self.imp().current_composition.borrow_mut() {
+            comp.lint_ignores = ignores;
+        }
+        self.sync_current_to_list();
+        self.schedule_autosave();
+    }
+
     fn sync_current_to_list(&self) {
         if let Some(ref comp) = *self.imp().current_composition.borrow() {
             let mut compositions = self.imp().compositions.borrow_mut();
@@ -702,12 +2453,22 @@ impl AbbeyWindow {
         }
     }
 
-    fn schedule_autosave(&self) {
-        // Cancel any pending autosave (use try-remove pattern)
+    /// Cancel the pending autosave timer, returning whether one was scheduled
+    fn cancel_autosave(&self) -> bool {
         if let Some(source_id) = self.imp().autosave_source_id.take() {
             // Only try to remove if the main context still has this source
             let _ = glib::MainContext::default().find_source_by_id(&source_id).map(|s| s.destroy());
+            true
+        } else {
+            false
         }
+    }
+
+    fn schedule_autosave(&self) {
+        // Cancel any pending autosave (use try-remove pattern)
+        self.cancel_autosave();
+        self.set_save_state(SaveState::Saving);
+        self.schedule_recovery_journal();
         
         // Schedule new autosave in 2 seconds
         let window = self.clone();
@@ -724,13 +2485,201 @@ impl AbbeyWindow {
     }
 
     fn autosave(&self) {
-        let app = self.application().and_downcast::<crate::app::AbbeyApp>().unwrap();
-        let storage_ref = app.storage();
+        if let Err(e) = self.write_compositions() {
+            log::error!("Autosave failed: {}", e);
+        }
+    }
+
+    /// Journal the open composition soon, unless that's already due; unlike
+    /// autosave it isn't put off by more typing, only kept from happening on
+    /// every keystroke
+    fn schedule_recovery_journal(&self) {
+        if self.imp().journal_source_id.borrow().is_some() {
+            return;
+        }
+        let window = self.downgrade();
+        let source_id = glib::timeout_add_local_once(JOURNAL_INTERVAL, move || {
+            if let Some(window) = window.upgrade() {
+                window.imp().journal_source_id.replace(None);
+                window.write_recovery_journal();
+            }
+        });
+        self.imp().journal_source_id.replace(Some(source_id));
+    }
+
+    /// Cancel the pending journal write, returning whether one was scheduled
+    fn cancel_recovery_journal(&self) -> bool {
+        match self.imp().journal_source_id.take() {
+            Some(source_id) => {
+                let _ = glib::MainContext::default().find_source_by_id(&source_id).map(|s| s.destroy());
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Journal the open composition so edits survive a crash before autosave runs
+    fn write_recovery_journal(&self) {
+        if let Some(ref comp) = *self.imp().current_composition.borrow() {
+            if let Err(e) = self.with_storage(|storage| storage.write_recovery(comp)) {
+                log::warn!("Failed to journal unsaved changes: {}", e);
+            }
+        }
+    }
+
+    /// Write edits still waiting on the autosave timer (or left over from a failed save)
+    pub fn flush_pending_save(&self) -> Result<(), StorageError> {
+        let pending = self.cancel_autosave();
+        let journal_pending = self.cancel_recovery_journal();
+        if !pending && self.imp().save_state.get() == SaveState::Saved {
+            return Ok(());
+        }
         
-        if let Some(ref storage) = *storage_ref {
+        self.sync_current_to_list();
+        let result = self.write_compositions();
+        // The journal was to have the latest edits, and has to if they're
+        // still not saved
+        if result.is_err() && journal_pending {
+            self.write_recovery_journal();
+        }
+        result
+    }
+
+    /// Ask what to do when pending edits could not be written on close
+    fn confirm_close_unsaved(&self, error: &StorageError) {
+        let dialog = adw::MessageDialog::new(
+            Some(self),
+            Some("Changes Not Saved"),
+            Some(&format!(
+                "Abbey couldn't save your latest changes: {}.\n\nIf you close now, unsaved text is kept in the recovery journal where possible and offered again next time Abbey starts.",
+                error
+            )),
+        );
+        
+        dialog.add_response("cancel", "Keep Open");
+        dialog.add_response("retry", "Try Again");
+        dialog.add_response("close", "Close Anyway");
+        dialog.set_response_appearance("retry", adw::ResponseAppearance::Suggested);
+        dialog.set_response_appearance("close", adw::ResponseAppearance::Destructive);
+        dialog.set_default_response(Some("retry"));
+        dialog.set_close_response("cancel");
+        
+        let window = self.clone();
+        dialog.connect_response(None, move |dlg, response| {
+            dlg.close();
+            match response {
+                "retry" => window.close(),
+                "close" => {
+                    window.imp().force_close.set(true);
+                    window.close();
+                }
+                _ => {}
+            }
+        });
+        
+        dialog.present();
+    }
+
+    /// Offer to restore compositions journaled before an unclean exit
+    fn offer_recovery(&self) {
+        let recovered = self.with_storage(|storage| storage.load_recovery())
+            .unwrap_or_else(|e| {
+                log::error!("Failed to read recovery journal: {}", e);
+                Vec::new()
+            });
+        
+        // Only journal entries newer than what made it to disk are worth offering
+        let recovered: Vec<Composition> = {
             let compositions = self.imp().compositions.borrow();
-            if let Err(e) = storage.save_compositions(&compositions) {
-                log::error!("Autosave failed: {}", e);
+            recovered.into_iter()
+                .filter(|r| {
+                    compositions.iter()
+                        .find(|c| c.id == r.id)
+                        .map(|c| r.updated_at > c.updated_at)
+                        .unwrap_or(true)
+                })
+                .collect()
+        };
+        
+        if recovered.is_empty() {
+            self.discard_recovery();
+            return;
+        }
+        
+        // Recovering means saving, which has to wait until the library is writable
+        if self.is_read_only() {
+            return;
+        }
+
+        let titles: Vec<String> = recovered.iter()
+            .map(|c| format!("• {}", if c.title.is_empty() { "Untitled" } else { &c.title }))
+            .collect();
+        
+        let dialog = adw::MessageDialog::new(
+            Some(self),
+            Some("Recover Unsaved Changes?"),
+            Some(&format!(
+                "Abbey closed before these compositions were saved:\n\n{}",
+                titles.join("\n")
+            )),
+        );
+        
+        dialog.add_response("discard", "Discard");
+        dialog.add_response("recover", "Recover");
+        dialog.set_response_appearance("discard", adw::ResponseAppearance::Destructive);
+        dialog.set_response_appearance("recover", adw::ResponseAppearance::Suggested);
+        dialog.set_default_response(Some("recover"));
+        
+        let window = self.clone();
+        let recovered = RefCell::new(Some(recovered));
+        dialog.connect_response(None, move |dlg, response| {
+            dlg.close();
+            match (response, recovered.take()) {
+                ("recover", Some(recovered)) => window.apply_recovered(recovered),
+                ("discard", _) => window.discard_recovery(),
+                // Closing the dialog leaves the journal for next time
+                _ => {}
+            }
+        });
+        
+        dialog.present();
+    }
+
+    fn apply_recovered(&self, recovered: Vec<Composition>) {
+        let current_id = self.imp().current_composition.borrow()
+            .as_ref()
+            .map(|c| c.id.clone());
+        let mut reopen = None;
+        
+        {
+            let mut compositions = self.imp().compositions.borrow_mut();
+            for comp in recovered {
+                if current_id.as_deref() == Some(comp.id.as_str()) {
+                    reopen = Some(comp.clone());
+                }
+                if let Some(pos) = compositions.iter().position(|c| c.id == comp.id) {
+                    compositions[pos] = comp;
+                } else {
+                    compositions.insert(0, comp);
+                }
+            }
+        }
+        
+        match self.write_compositions() {
+            Ok(()) => self.show_toast("Unsaved changes recovered"),
+            Err(e) => log::error!("Failed to save recovered compositions: {}", e),
+        }
+        self.update_composition_list();
+        
+        if let Some(comp) = reopen {
+            self.open_composition(comp);
+        }
+    }
+
+    fn discard_recovery(&self) {
+        if let Err(e) = self.with_storage(|storage| storage.clear_recovery()) {
+            if !e.is_read_only() {
+                log::warn!("Failed to clear recovery journal: {}", e);
             }
         }
     }
@@ -740,14 +2689,9 @@ impl AbbeyWindow {
         self.sync_current_to_list();
         
         // Save immediately
-        let app = self.application().and_downcast::<crate::app::AbbeyApp>().unwrap();
-        let storage_ref = app.storage();
-        
-        if let Some(ref storage) = *storage_ref {
-            let compositions = self.imp().compositions.borrow();
-            if let Err(e) = storage.save_compositions(&compositions) {
-                log::error!("Failed to save composition: {}", e);
-            }
+        self.cancel_autosave();
+        if let Err(e) = self.write_compositions() {
+            log::error!("Failed to save composition: {}", e);
         }
     }
 
@@ -761,6 +2705,11 @@ impl AbbeyWindow {
     }
 
     pub fn start_flow_mode(&self) {
+        if self.is_read_only() {
+            self.show_toast("The library is read-only");
+            return;
+        }
+
         println!("START_FLOW_MODE CALLED!");
         // Show duration selection dialog
         let dialog = adw::MessageDialog::new(
@@ -802,9 +2751,25 @@ impl AbbeyWindow {
         dialog.present();
         println!("Dialog presented");
     }
-    
+
+    /// Start a flow session of a given length without asking, e.g. from `abbey --flow 15`
+    pub fn start_flow(&self, duration_minutes: u32) {
+        if self.is_read_only() {
+            self.show_toast("The library is read-only");
+            return;
+        }
+        if self.imp().in_flow_mode.get() {
+            self.show_toast("A flow session is already running");
+            return;
+        }
+        
+        self.begin_flow(duration_minutes);
+    }
+
     fn begin_flow(&self, duration_minutes: u32) {
         println!("BEGIN_FLOW called with {} minutes", duration_minutes);
+        // Flow mode has its own full-screen treatment
+        self.set_focus_mode(false);
         self.imp().in_flow_mode.set(true);
         
         let window = self.clone();
@@ -842,21 +2807,24 @@ impl AbbeyWindow {
         self.imp().in_flow_mode.set(false);
         self.imp().split_view.set_collapsed(false);
         
-        // Save flow
-        let app = self.application().and_downcast::<crate::app::AbbeyApp>().unwrap();
-        let storage_ref = app.storage();
+        self.save_flow(flow);
         
-        if let Some(ref storage) = *storage_ref {
-            if let Err(e) = storage.append_flow(&flow) {
+        // Return to composition mode
+        self.imp().main_stack.set_visible_child_name("composition");
+    }
+
+    fn save_flow(&self, flow: Flow) {
+        match self.with_storage(|storage| storage.append_flow(&flow)) {
+            Ok(()) => {
+                self.announce(api::Change::new(api::Kind::Flow, api::Action::Created, &flow.id));
+                self.show_toast(&format!("Flow saved! {} words written", flow.word_count()));
+            }
+            Err(e) => {
                 log::error!("Failed to save flow: {}", e);
                 self.show_toast("Failed to save flow");
-            } else {
-                self.show_toast(&format!("Flow saved! {} words written", flow.word_count()));
+                self.report_write_failure(FailedWrite::Flow(flow), &e);
             }
         }
-        
-        // Return to composition mode
-        self.imp().main_stack.set_visible_child_name("composition");
     }
 
     pub fn show_flow_history(&self) {
@@ -889,6 +2857,11 @@ impl AbbeyWindow {
     }
 
     fn use_flow_text_in_composition(&self, text: &str) {
+        if self.is_read_only() {
+            self.show_toast("The library is read-only");
+            return;
+        }
+
         // Create a dialog with options
         let dialog = adw::MessageDialog::new(
             Some(self),
@@ -1040,6 +3013,8 @@ impl AbbeyWindow {
             match storage.load_projects() {
                 Ok(projects) => {
                     let projects_view = ProjectsView::new(&projects, &compositions);
+                    let typography = self.imp().settings.borrow().typography.clone();
+                    projects_view.set_typography(typography.options(), typography.smart_punctuation);
                     
                     // Connect save callback using window reference
                     let window = self.clone();
@@ -1047,6 +3022,11 @@ impl AbbeyWindow {
                         window.save_projects(&updated_projects);
                     });
                     
+                    let window = self.clone();
+                    projects_view.connect_analyze(move |ids| {
+                        window.show_word_analysis(ids);
+                    });
+                    
                     projects_box.append(&projects_view);
                     self.imp().main_stack.set_visible_child_name("projects");
                 }
@@ -1059,12 +3039,11 @@ impl AbbeyWindow {
     }
 
     fn save_projects(&self, projects: &[Project]) {
-        let app = self.application().and_downcast::<crate::app::AbbeyApp>().unwrap();
-        let storage_ref = app.storage();
-        
-        if let Some(ref storage) = *storage_ref {
-            if let Err(e) = storage.save_projects(projects) {
+        match self.with_storage(|storage| storage.save_projects(projects)) {
+            Ok(()) => self.resolve_failed_write(&FailedWrite::Projects(Vec::new())),
+            Err(e) => {
                 log::error!("Failed to save projects: {}", e);
+                self.report_write_failure(FailedWrite::Projects(projects.to_vec()), &e);
             }
         }
     }
@@ -1122,3 +3101,20 @@ impl AbbeyWindow {
         self.imp().toast_overlay.add_toast(toast);
     }
 }
+
+/// The focus-scope action's name for a scope
+fn focus_scope_name(scope: FocusScope) -> &'static str {
+    match scope {
+        FocusScope::Sentence => "sentence",
+        FocusScope::Paragraph => "paragraph",
+    }
+}
+
+/// Whether two copies of a composition have the same text a replace reaches:
+/// title, content and notes
+fn same_text(a: &Composition, b: &Composition) -> bool {
+    a.title == b.title
+        && a.content == b.content
+        && a.notes.len() == b.notes.len()
+        && a.notes.iter().zip(&b.notes).all(|(a, b)| a.id == b.id && a.content == b.content)
+}
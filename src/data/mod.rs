@@ -1,5 +0,0 @@
-mod models;
-mod storage;
-
-pub use models::*;
-pub use storage::Storage;
@@ -1,14 +1,19 @@
 mod app;
 mod config;
-mod data;
 mod ui;
-mod utils;
 
+use abbey_core::data;
 use app::AbbeyApp;
 
 fn main() {
     env_logger::init();
     
+    // Subcommands like `abbey list` run headless, without touching GTK
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().is_some_and(|arg| abbey_core::cli::is_command(arg)) {
+        std::process::exit(abbey_core::cli::run(&args));
+    }
+    
     let app = AbbeyApp::new();
     std::process::exit(app.run());
 }
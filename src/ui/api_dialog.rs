@@ -0,0 +1,173 @@
+use adw::subclass::prelude::*;
+use adw::prelude::*;
+use gtk4::{glib, CompositeTemplate};
+use libadwaita as adw;
+use std::cell::{Cell, RefCell};
+
+use crate::data::ApiSettings;
+
+type ChangedCallback = Box<dyn Fn(ApiSettings) -> Result<(), String> + 'static>;
+
+mod imp {
+    use super::*;
+
+    #[derive(Default, CompositeTemplate)]
+    #[template(file = "api_dialog.ui")]
+    pub struct ApiDialog {
+        #[template_child]
+        pub enabled_row: TemplateChild<adw::SwitchRow>,
+        #[template_child]
+        pub port_row: TemplateChild<adw::SpinRow>,
+        #[template_child]
+        pub token_row: TemplateChild<adw::ActionRow>,
+        #[template_child]
+        pub status_label: TemplateChild<gtk4::Label>,
+        
+        pub settings: RefCell<ApiSettings>,
+        pub changed_callback: RefCell<Option<ChangedCallback>>,
+        /// Set while the rows are filled in, so that doesn't count as a change
+        pub loading: Cell<bool>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for ApiDialog {
+        const NAME: &'static str = "ApiDialog";
+        type Type = super::ApiDialog;
+        type ParentType = adw::Window;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+            klass.bind_template_callbacks();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    #[gtk4::template_callbacks]
+    impl ApiDialog {
+        #[template_callback]
+        fn on_copy_token(&self) {
+            let token = self.settings.borrow().token.clone();
+            self.obj().clipboard().set_text(&token);
+        }
+
+        #[template_callback]
+        fn on_new_token(&self) {
+            self.settings.borrow_mut().token = ApiSettings::generate_token();
+            self.obj().apply();
+        }
+    }
+
+    impl ObjectImpl for ApiDialog {
+        fn constructed(&self) {
+            self.parent_constructed();
+            
+            let obj = self.obj();
+            let dialog = obj.clone();
+            self.enabled_row.connect_active_notify(move |row| {
+                if !dialog.imp().loading.get() {
+                    dialog.imp().settings.borrow_mut().enabled = row.is_active();
+                    dialog.apply();
+                }
+            });
+            
+            let dialog = obj.clone();
+            self.port_row.connect_value_notify(move |row| {
+                if !dialog.imp().loading.get() {
+                    dialog.imp().settings.borrow_mut().port = row.value() as u16;
+                    dialog.apply();
+                }
+            });
+        }
+    }
+
+    impl WidgetImpl for ApiDialog {}
+    impl WindowImpl for ApiDialog {}
+    impl AdwWindowImpl for ApiDialog {}
+}
+
+glib::wrapper! {
+    pub struct ApiDialog(ObjectSubclass<imp::ApiDialog>)
+        @extends adw::Window, gtk4::Window, gtk4::Widget,
+        @implements gtk4::Accessible, gtk4::Buildable;
+}
+
+impl ApiDialog {
+    pub fn new(settings: &ApiSettings) -> Self {
+        let dialog: Self = glib::Object::builder()
+            .property("modal", true)
+            .build();
+        
+        let imp = dialog.imp();
+        imp.settings.replace(settings.clone());
+        imp.loading.set(true);
+        imp.enabled_row.set_active(settings.enabled);
+        imp.port_row.set_value(settings.port.into());
+        imp.loading.set(false);
+        dialog.update_rows();
+        dialog
+    }
+
+    pub fn present(&self, parent: Option<&impl IsA<gtk4::Window>>) {
+        if let Some(parent) = parent {
+            self.set_transient_for(Some(parent));
+        }
+        gtk4::prelude::GtkWindowExt::present(self);
+    }
+
+    /// Called with the new settings on every change; an error is shown in the
+    /// dialog, e.g. when the port is already taken
+    pub fn connect_changed<F: Fn(ApiSettings) -> Result<(), String> + 'static>(&self, callback: F) {
+        self.imp().changed_callback.replace(Some(Box::new(callback)));
+    }
+
+    fn apply(&self) {
+        // Turning the API on for the first time needs a token to go with it
+        {
+            let mut settings = self.imp().settings.borrow_mut();
+            if settings.enabled && settings.token.is_empty() {
+                settings.token = ApiSettings::generate_token();
+            }
+        }
+        
+        let settings = self.imp().settings.borrow().clone();
+        let result = match *self.imp().changed_callback.borrow() {
+            Some(ref callback) => callback(settings),
+            None => Ok(()),
+        };
+        
+        self.update_rows();
+        if let Err(message) = result {
+            self.imp().status_label.set_text(&format!("The API couldn't start: {}", message));
+            self.imp().status_label.add_css_class("error");
+        }
+    }
+
+    fn update_rows(&self) {
+        let imp = self.imp();
+        let settings = imp.settings.borrow();
+        
+        imp.port_row.set_sensitive(settings.enabled);
+        imp.token_row.set_sensitive(settings.enabled);
+        imp.token_row.set_subtitle(if settings.token.is_empty() { "None yet" } else { &settings.token });
+        
+        let status = if settings.enabled {
+            format!(
+                "Serving at http://127.0.0.1:{}/api. Send the token as “Authorization: Bearer <token>”.",
+                settings.port
+            )
+        } else {
+            "The API is off.".to_string()
+        };
+        imp.status_label.set_text(&status);
+        imp.status_label.remove_css_class("error");
+    }
+}
+
+impl Default for ApiDialog {
+    fn default() -> Self {
+        glib::Object::builder().build()
+    }
+}
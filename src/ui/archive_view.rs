@@ -2,8 +2,9 @@ use adw::subclass::prelude::*;
 use gtk4::prelude::*;
 use gtk4::{glib, CompositeTemplate};
 use libadwaita as adw;
-use std::cell::RefCell;
+use std::cell::{OnceCell, RefCell};
 
+use super::MarkdownView;
 use crate::data::Composition;
 
 mod imp {
@@ -26,6 +27,7 @@ mod imp {
         pub compositions: RefCell<Vec<Composition>>,
         pub selected_id: RefCell<Option<String>>,
         pub restore_callback: RefCell<Option<Box<dyn Fn(String) + 'static>>>,
+        pub content: OnceCell<MarkdownView>,
     }
 
     #[glib::object_subclass]
@@ -81,11 +83,6 @@ impl ArchiveView {
     }
 
     fn setup_views(&self) {
-        let content_view = &self.imp().content_view;
-        content_view.set_editable(false);
-        content_view.add_css_class("markdown-view");
-        content_view.set_wrap_mode(gtk4::WrapMode::Word);
-        
         // Setup list selection
         let view = self.clone();
         self.imp().archive_list.connect_row_selected(move |_, row| {
@@ -147,11 +144,16 @@ impl ArchiveView {
         let compositions = self.imp().compositions.borrow();
         if let Some(comp) = compositions.get(index) {
             self.imp().selected_id.replace(Some(comp.id.clone()));
-            self.imp().content_view.buffer().set_text(&comp.content);
+            self.content().set_markdown(&comp.content_without_suggestions());
             self.imp().restore_btn.set_sensitive(true);
         }
     }
 
+    /// The rendered composition, set up on first use
+    fn content(&self) -> &MarkdownView {
+        self.imp().content.get_or_init(|| MarkdownView::new(&self.imp().content_view))
+    }
+
     pub fn connect_restore<F: Fn(String) + 'static>(&self, callback: F) {
         self.imp().restore_callback.replace(Some(Box::new(callback)));
     }
@@ -0,0 +1,638 @@
+use adw::subclass::prelude::*;
+use gtk4::prelude::*;
+use gtk4::{gdk, gio, glib, CompositeTemplate};
+use libadwaita as adw;
+use sourceview5::prelude::*;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::data::{Comment, CommentReply};
+use abbey_core::utils::anchors;
+
+/// Marks in the editor's margin where comments start
+const MARK_CATEGORY: &str = "comment";
+const TAG_NAME: &str = "comment";
+
+/// How long typing pauses before comments catch up with the text
+const SYNC_DELAY: Duration = Duration::from_millis(300);
+
+type ChangedCallback = Box<dyn Fn(Vec<Comment>) + 'static>;
+
+mod imp {
+    use super::*;
+
+    #[derive(Default, CompositeTemplate)]
+    #[template(file = "comments_panel.ui")]
+    pub struct CommentsPanel {
+        #[template_child]
+        pub show_resolved_btn: TemplateChild<gtk4::ToggleButton>,
+        #[template_child]
+        pub add_btn: TemplateChild<gtk4::Button>,
+        #[template_child]
+        pub summary_label: TemplateChild<gtk4::Label>,
+        #[template_child]
+        pub draft_box: TemplateChild<gtk4::Box>,
+        #[template_child]
+        pub draft_quote_label: TemplateChild<gtk4::Label>,
+        #[template_child]
+        pub draft_entry: TemplateChild<gtk4::Entry>,
+        #[template_child]
+        pub comments_list: TemplateChild<gtk4::ListBox>,
+
+        pub view: RefCell<Option<sourceview5::View>>,
+        pub comments: RefCell<Vec<Comment>>,
+        /// Each comment's start and end in the text, by id; they move as the
+        /// text is edited
+        pub anchors: RefCell<HashMap<String, (gtk4::TextMark, gtk4::TextMark)>>,
+        /// The ids of the comments the list shows, row by row
+        pub shown: RefCell<Vec<String>>,
+        /// The selection a comment is being written on
+        pub draft: RefCell<Option<(gtk4::TextMark, gtk4::TextMark)>>,
+        pub pending: RefCell<Option<glib::SourceId>>,
+        pub changed_callback: RefCell<Option<ChangedCallback>>,
+        pub reveal_callback: RefCell<Option<Box<dyn Fn() + 'static>>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for CommentsPanel {
+        const NAME: &'static str = "CommentsPanel";
+        type Type = super::CommentsPanel;
+        type ParentType = gtk4::Box;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+            klass.bind_template_callbacks();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    #[gtk4::template_callbacks]
+    impl CommentsPanel {
+        #[template_callback]
+        fn on_show_resolved_toggled(&self) {
+            self.obj().refresh();
+        }
+
+        #[template_callback]
+        fn on_add_clicked(&self) {
+            self.obj().begin_comment();
+        }
+
+        #[template_callback]
+        fn on_draft_activate(&self) {
+            self.obj().finish_comment();
+        }
+
+        #[template_callback]
+        fn on_draft_cancel(&self) {
+            self.obj().cancel_comment();
+        }
+
+        #[template_callback]
+        fn on_row_activated(&self, row: &gtk4::ListBoxRow) {
+            let id = usize::try_from(row.index())
+                .ok()
+                .and_then(|index| self.shown.borrow().get(index).cloned());
+            if let Some(id) = id {
+                self.obj().select(&id);
+            }
+        }
+    }
+
+    impl ObjectImpl for CommentsPanel {
+        fn constructed(&self) {
+            self.parent_constructed();
+
+            // Escape gives up on a comment being written
+            let keys = gtk4::EventControllerKey::new();
+            let panel = self.obj().downgrade();
+            keys.connect_key_pressed(move |_, key, _, _| match (key, panel.upgrade()) {
+                (gdk::Key::Escape, Some(panel)) => {
+                    panel.cancel_comment();
+                    glib::Propagation::Stop
+                }
+                _ => glib::Propagation::Proceed,
+            });
+            self.draft_entry.add_controller(keys);
+        }
+    }
+
+    impl WidgetImpl for CommentsPanel {}
+    impl BoxImpl for CommentsPanel {}
+}
+
+glib::wrapper! {
+    /// Comments on stretches of a markdown editor's text, marked in its margin
+    /// and listed with their replies
+    pub struct CommentsPanel(ObjectSubclass<imp::CommentsPanel>)
+        @extends gtk4::Box, gtk4::Widget,
+        @implements gtk4::Accessible, gtk4::Buildable;
+}
+
+impl CommentsPanel {
+    /// Anchor comments in `view`'s text from now on
+    pub fn attach(&self, view: &sourceview5::View) {
+        let buffer = view.buffer();
+        let tag = gtk4::TextTag::builder()
+            .name(TAG_NAME)
+            .background_rgba(&gdk::RGBA::new(0.99, 0.75, 0.25, 0.22))
+            .build();
+        buffer.tag_table().add(&tag);
+
+        // An icon in the margin at each comment, saying what it says
+        let attributes = sourceview5::MarkAttributes::new();
+        attributes.set_icon_name("chat-message-new-symbolic");
+        let panel = self.downgrade();
+        attributes.connect_query_tooltip_text(move |_, mark| {
+            let panel = match panel.upgrade() {
+                Some(panel) => panel,
+                None => return String::new(),
+            };
+            let line = match mark.buffer() {
+                Some(buffer) => buffer.iter_at_mark(mark).line(),
+                None => return String::new(),
+            };
+            panel
+                .comments_at_line(line)
+                .iter()
+                .map(|comment| comment.content.clone())
+                .collect::<Vec<_>>()
+                .join("\n\n")
+        });
+        view.set_mark_attributes(MARK_CATEGORY, &attributes, 0);
+        view.set_show_line_marks(true);
+
+        let panel = self.downgrade();
+        view.connect_line_mark_activated(move |_, iter, _, _, _| {
+            let panel = match panel.upgrade() {
+                Some(panel) => panel,
+                None => return,
+            };
+            if let Some(comment) = panel.comments_at_line(iter.line()).first() {
+                panel.reveal();
+                panel.select(&comment.id);
+            }
+        });
+
+        let panel = self.downgrade();
+        buffer.connect_changed(move |_| {
+            if let Some(panel) = panel.upgrade() {
+                panel.schedule_sync();
+            }
+        });
+
+        // Add Comment in the editor's context menu, after what's there already
+        let actions = gio::SimpleActionGroup::new();
+        let add = gio::SimpleAction::new("add", None);
+        let panel = self.downgrade();
+        add.connect_activate(move |_, _| {
+            if let Some(panel) = panel.upgrade() {
+                panel.begin_comment();
+            }
+        });
+        actions.add_action(&add);
+        view.insert_action_group("comments", Some(&actions));
+
+        let section = gio::Menu::new();
+        section.append(Some("Add Comment…"), Some("comments.add"));
+        let menu = gio::Menu::new();
+        menu.append_section(None, &view.extra_menu());
+        menu.append_section(None, &section);
+        view.set_extra_menu(Some(&menu));
+
+        self.imp().view.replace(Some(view.clone()));
+    }
+
+    /// Show these comments, anchoring each where it says it is in the text
+    /// the view has now
+    pub fn set_comments(&self, comments: &[Comment]) {
+        let imp = self.imp();
+        if let Some(source_id) = imp.pending.take() {
+            source_id.remove();
+        }
+        self.cancel_comment();
+        let buffer = match imp.view.borrow().as_ref() {
+            Some(view) => view.buffer(),
+            None => return,
+        };
+
+        for (_, (start, end)) in imp.anchors.borrow_mut().drain() {
+            buffer.delete_mark(&start);
+            buffer.delete_mark(&end);
+        }
+        for comment in comments {
+            let anchor = anchor(&buffer, comment.start, comment.end);
+            imp.anchors.borrow_mut().insert(comment.id.clone(), anchor);
+        }
+        imp.comments.replace(comments.to_vec());
+        self.refresh();
+    }
+
+    /// Called with every comment when one is added, answered, resolved or
+    /// deleted, or moves with the text
+    pub fn connect_changed<F: Fn(Vec<Comment>) + 'static>(&self, callback: F) {
+        self.imp().changed_callback.replace(Some(Box::new(callback)));
+    }
+
+    /// Called when the panel has something to show, like a comment picked in
+    /// the margin
+    pub fn connect_reveal<F: Fn() + 'static>(&self, callback: F) {
+        self.imp().reveal_callback.replace(Some(Box::new(callback)));
+    }
+
+    /// Start writing a comment on the selected text
+    pub fn begin_comment(&self) {
+        let imp = self.imp();
+        let view = match imp.view.borrow().clone() {
+            Some(view) if view.is_editable() => view,
+            _ => return,
+        };
+        let buffer = view.buffer();
+        let (start, end) = match buffer.selection_bounds() {
+            Some(bounds) => bounds,
+            None => {
+                imp.summary_label.set_text("Select some text to comment on it");
+                self.reveal();
+                return;
+            }
+        };
+
+        self.cancel_comment();
+        let quote = buffer.text(&start, &end, false);
+        imp.draft_quote_label.set_text(&format!("On “{}”", quote.lines().next().unwrap_or_default()));
+        imp.draft.replace(Some(anchor(&buffer, start.offset() as usize, end.offset() as usize)));
+        imp.draft_box.set_visible(true);
+        self.reveal();
+        imp.draft_entry.grab_focus();
+    }
+
+    fn finish_comment(&self) {
+        let imp = self.imp();
+        let text = imp.draft_entry.text().trim().to_string();
+        if text.is_empty() {
+            return;
+        }
+        // The draft's marks become the comment's anchor
+        let (start, end) = match imp.draft.take() {
+            Some(draft) => draft,
+            None => return,
+        };
+        self.cancel_comment();
+
+        let buffer = match start.buffer() {
+            Some(buffer) => buffer,
+            None => return,
+        };
+        let (from, to) = (buffer.iter_at_mark(&start), buffer.iter_at_mark(&end));
+        let comment = Comment::new(
+            from.offset() as usize,
+            to.offset() as usize,
+            buffer.text(&from, &to, false).to_string(),
+            text,
+        );
+        imp.anchors.borrow_mut().insert(comment.id.clone(), (start, end));
+        imp.comments.borrow_mut().push(comment);
+        self.changed();
+        if let Some(ref view) = *imp.view.borrow() {
+            view.grab_focus();
+        }
+    }
+
+    fn cancel_comment(&self) {
+        let imp = self.imp();
+        if let Some((start, end)) = imp.draft.take() {
+            if let Some(buffer) = start.buffer() {
+                buffer.delete_mark(&start);
+                buffer.delete_mark(&end);
+            }
+        }
+        imp.draft_entry.set_text("");
+        imp.draft_box.set_visible(false);
+    }
+
+    fn reveal(&self) {
+        if let Some(ref callback) = *self.imp().reveal_callback.borrow() {
+            callback();
+        }
+    }
+
+    fn editable(&self) -> bool {
+        self.imp().view.borrow().as_ref().is_some_and(|view| view.is_editable())
+    }
+
+    /// Tell the composition, and show the comments as they are now
+    fn changed(&self) {
+        self.refresh();
+        if let Some(ref callback) = *self.imp().changed_callback.borrow() {
+            callback(self.imp().comments.borrow().clone());
+        }
+    }
+
+    fn schedule_sync(&self) {
+        if let Some(source_id) = self.imp().pending.take() {
+            source_id.remove();
+        }
+        if self.imp().comments.borrow().is_empty() {
+            return;
+        }
+
+        let panel = self.downgrade();
+        let source_id = glib::timeout_add_local_once(SYNC_DELAY, move || {
+            if let Some(panel) = panel.upgrade() {
+                panel.imp().pending.replace(None);
+                panel.sync();
+            }
+        });
+        self.imp().pending.replace(Some(source_id));
+    }
+
+    /// Bring comments up to date with where their anchors moved to
+    fn sync(&self) {
+        let imp = self.imp();
+        let buffer = match imp.view.borrow().as_ref() {
+            Some(view) => view.buffer(),
+            None => return,
+        };
+        let text = buffer.text(&buffer.start_iter(), &buffer.end_iter(), false);
+
+        let mut moved = false;
+        for comment in imp.comments.borrow_mut().iter_mut() {
+            let anchors_by_id = imp.anchors.borrow();
+            let (start, end) = match anchors_by_id.get(&comment.id) {
+                Some(anchor) => anchor,
+                None => continue,
+            };
+            let mut range = buffer.iter_at_mark(start).offset() as usize..buffer.iter_at_mark(end).offset() as usize;
+
+            // Text cut away and put back, as when a section moves or an edit
+            // is undone, takes its comment along if it's still close by
+            if range.is_empty() && !comment.quote.is_empty() {
+                let near = if comment.is_detached() { comment.start } else { range.start };
+                if let Some(found) = anchors::nearest(&text, &comment.quote, near) {
+                    let back = comment.is_detached() && found.start == comment.start;
+                    if !comment.is_detached() || back {
+                        buffer.move_mark(start, &buffer.iter_at_offset(found.start as i32));
+                        buffer.move_mark(end, &buffer.iter_at_offset(found.end as i32));
+                        range = found;
+                    }
+                }
+            }
+
+            if range.start != comment.start || range.end != comment.end {
+                if !range.is_empty() {
+                    comment.quote = buffer
+                        .text(&buffer.iter_at_offset(range.start as i32), &buffer.iter_at_offset(range.end as i32), false)
+                        .to_string();
+                }
+                comment.start = range.start;
+                comment.end = range.end;
+                moved = true;
+            }
+        }
+
+        if moved {
+            self.changed();
+        } else {
+            self.highlight();
+        }
+    }
+
+    /// Unresolved comments starting on a line, in the order they start
+    fn comments_at_line(&self, line: i32) -> Vec<Comment> {
+        let buffer = match self.imp().view.borrow().as_ref() {
+            Some(view) => view.buffer(),
+            None => return Vec::new(),
+        };
+        let anchors_by_id = self.imp().anchors.borrow();
+        self.imp()
+            .comments
+            .borrow()
+            .iter()
+            .filter(|comment| !comment.resolved)
+            .filter(|comment| {
+                anchors_by_id
+                    .get(&comment.id)
+                    .is_some_and(|(start, _)| buffer.iter_at_mark(start).line() == line)
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Mark open comments' text and margin lines
+    fn highlight(&self) {
+        let imp = self.imp();
+        let buffer = match imp.view.borrow().as_ref().and_then(|view| view.buffer().downcast::<sourceview5::Buffer>().ok()) {
+            Some(buffer) => buffer,
+            None => return,
+        };
+        let (start, end) = buffer.bounds();
+        buffer.remove_tag_by_name(TAG_NAME, &start, &end);
+        buffer.remove_source_marks(&start, &end, Some(MARK_CATEGORY));
+
+        let anchors_by_id = imp.anchors.borrow();
+        for comment in imp.comments.borrow().iter().filter(|comment| !comment.resolved) {
+            if let Some((start, end)) = anchors_by_id.get(&comment.id) {
+                let (start, end) = (buffer.iter_at_mark(start), buffer.iter_at_mark(end));
+                buffer.apply_tag_by_name(TAG_NAME, &start, &end);
+                buffer.create_source_mark(None, MARK_CATEGORY, &start);
+            }
+        }
+    }
+
+    /// Rebuild the list, in document order
+    fn refresh(&self) {
+        let imp = self.imp();
+        self.highlight();
+        imp.add_btn.set_sensitive(self.editable());
+
+        let mut comments = imp.comments.borrow().clone();
+        comments.sort_by_key(|comment| (comment.start, comment.end));
+        let open = comments.iter().filter(|comment| !comment.resolved).count();
+        let resolved = comments.len() - open;
+        imp.summary_label.set_text(&match (open, resolved) {
+            (0, 0) => "Select text and add a comment to it".to_string(),
+            (1, 0) => "1 comment".to_string(),
+            (n, 0) => format!("{} comments", n),
+            (n, r) => format!("{} open, {} resolved", n, r),
+        });
+
+        let show_resolved = imp.show_resolved_btn.is_active();
+        imp.comments_list.remove_all();
+        let mut shown = Vec::new();
+        for comment in comments.iter().filter(|comment| show_resolved || !comment.resolved) {
+            imp.comments_list.append(&self.comment_row(comment));
+            shown.push(comment.id.clone());
+        }
+        imp.comments_list.set_visible(!shown.is_empty());
+        imp.shown.replace(shown);
+    }
+
+    fn comment_row(&self, comment: &Comment) -> gtk4::ListBoxRow {
+        let editable = self.editable();
+        let vbox = gtk4::Box::builder()
+            .orientation(gtk4::Orientation::Vertical)
+            .spacing(4)
+            .margin_start(12)
+            .margin_end(12)
+            .margin_top(10)
+            .margin_bottom(10)
+            .build();
+
+        let quote = comment.quote.lines().next().unwrap_or_default();
+        let quote = gtk4::Label::builder()
+            .label(if comment.is_detached() {
+                format!("On deleted text “{}”", quote)
+            } else {
+                format!("“{}”", quote)
+            })
+            .xalign(0.0)
+            .ellipsize(gtk4::pango::EllipsizeMode::End)
+            .css_classes(["caption", "dim-label"])
+            .build();
+        vbox.append(&quote);
+        vbox.append(&text_label(&comment.content, &comment.created_at));
+
+        for reply in &comment.replies {
+            let label = text_label(&reply.content, &reply.created_at);
+            label.set_margin_start(12);
+            vbox.append(&label);
+        }
+
+        // Reply, resolve or reopen, and delete
+        let reply_entry = gtk4::Entry::builder()
+            .placeholder_text("Reply…")
+            .visible(false)
+            .build();
+        let actions = gtk4::Box::builder().spacing(4).build();
+        let reply_btn = gtk4::Button::builder()
+            .label("Reply")
+            .css_classes(["flat"])
+            .sensitive(editable)
+            .build();
+        let resolve_btn = gtk4::Button::builder()
+            .label(if comment.resolved { "Reopen" } else { "Resolve" })
+            .css_classes(["flat"])
+            .sensitive(editable)
+            .build();
+        let spacer = gtk4::Box::builder().hexpand(true).build();
+        let delete_btn = gtk4::Button::builder()
+            .icon_name("edit-delete-symbolic")
+            .tooltip_text("Delete Comment")
+            .css_classes(["flat", "circular"])
+            .sensitive(editable)
+            .build();
+        actions.append(&reply_btn);
+        actions.append(&resolve_btn);
+        actions.append(&spacer);
+        actions.append(&delete_btn);
+        vbox.append(&reply_entry);
+        vbox.append(&actions);
+
+        let entry = reply_entry.clone();
+        reply_btn.connect_clicked(move |_| {
+            entry.set_visible(true);
+            entry.grab_focus();
+        });
+        let panel = self.downgrade();
+        let id = comment.id.clone();
+        reply_entry.connect_activate(move |entry| {
+            let text = entry.text().trim().to_string();
+            if let (Some(panel), false) = (panel.upgrade(), text.is_empty()) {
+                panel.update(&id, |comment| comment.replies.push(CommentReply::new(text)));
+            }
+        });
+        let panel = self.downgrade();
+        let id = comment.id.clone();
+        resolve_btn.connect_clicked(move |_| {
+            if let Some(panel) = panel.upgrade() {
+                panel.update(&id, |comment| comment.resolved = !comment.resolved);
+            }
+        });
+        let panel = self.downgrade();
+        let id = comment.id.clone();
+        delete_btn.connect_clicked(move |_| {
+            if let Some(panel) = panel.upgrade() {
+                panel.delete(&id);
+            }
+        });
+
+        let row = gtk4::ListBoxRow::builder().child(&vbox).build();
+        if comment.resolved {
+            row.add_css_class("dim-label");
+        }
+        row
+    }
+
+    fn update(&self, id: &str, change: impl FnOnce(&mut Comment)) {
+        if let Some(comment) = self.imp().comments.borrow_mut().iter_mut().find(|comment| comment.id == id) {
+            change(comment);
+        }
+        self.changed();
+    }
+
+    fn delete(&self, id: &str) {
+        let imp = self.imp();
+        imp.comments.borrow_mut().retain(|comment| comment.id != id);
+        if let Some((start, end)) = imp.anchors.borrow_mut().remove(id) {
+            if let Some(buffer) = start.buffer() {
+                buffer.delete_mark(&start);
+                buffer.delete_mark(&end);
+            }
+        }
+        self.changed();
+    }
+
+    /// Select the text a comment is on and scroll to it
+    fn select(&self, id: &str) {
+        let imp = self.imp();
+        let view = match imp.view.borrow().clone() {
+            Some(view) => view,
+            None => return,
+        };
+        let (start, end) = match imp.anchors.borrow().get(id) {
+            Some(anchor) => anchor.clone(),
+            None => return,
+        };
+        let buffer = view.buffer();
+        let mut from = buffer.iter_at_mark(&start);
+        buffer.select_range(&from, &buffer.iter_at_mark(&end));
+        view.scroll_to_iter(&mut from, 0.1, false, 0.0, 0.0);
+
+        if let Some(index) = imp.shown.borrow().iter().position(|shown| shown == id) {
+            if let Some(row) = imp.comments_list.row_at_index(index as i32) {
+                row.grab_focus();
+            }
+        }
+    }
+}
+
+/// Marks around chars `start..end` that keep text typed at either end out
+fn anchor(buffer: &gtk4::TextBuffer, start: usize, end: usize) -> (gtk4::TextMark, gtk4::TextMark) {
+    let start = buffer.create_mark(None, &buffer.iter_at_offset(start as i32), false);
+    let end = buffer.create_mark(None, &buffer.iter_at_offset(end as i32), true);
+    (start, end)
+}
+
+/// A comment or reply, with when it was written
+fn text_label(text: &str, created_at: &chrono::DateTime<chrono::Utc>) -> gtk4::Label {
+    gtk4::Label::builder()
+        .label(format!(
+            "{} <span size=\"small\" alpha=\"60%\">{}</span>",
+            glib::markup_escape_text(text),
+            created_at.with_timezone(&chrono::Local).format("%b %d, %H:%M")
+        ))
+        .use_markup(true)
+        .wrap(true)
+        .xalign(0.0)
+        .build()
+}
+
+impl Default for CommentsPanel {
+    fn default() -> Self {
+        glib::Object::builder().build()
+    }
+}
@@ -0,0 +1,491 @@
+use adw::subclass::prelude::*;
+use adw::prelude::*;
+use gtk4::{gdk, gio, glib, pango, CompositeTemplate};
+use libadwaita as adw;
+use std::cell::{Cell, RefCell};
+use std::ops::Range;
+
+use abbey_core::utils::diff::{self, Hunk};
+
+/// Called with the composition a change was copied into and its new text;
+/// false if it couldn't be changed
+type CopyCallback = Box<dyn Fn(&str, &str) -> bool + 'static>;
+
+/// A text to compare, and the composition it is, which changes can be
+/// copied into
+#[derive(Debug, Clone)]
+pub struct Version {
+    pub name: String,
+    pub text: String,
+    pub composition_id: Option<String>,
+}
+
+/// Where a change is, in chars, in each of the three views
+#[derive(Debug, Clone)]
+pub struct Place {
+    left: Range<i32>,
+    right: Range<i32>,
+    inline: Range<i32>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Side {
+    Left,
+    Right,
+    Inline,
+}
+
+impl Place {
+    fn on(&self, side: Side) -> &Range<i32> {
+        match side {
+            Side::Left => &self.left,
+            Side::Right => &self.right,
+            Side::Inline => &self.inline,
+        }
+    }
+}
+
+mod imp {
+    use super::*;
+
+    #[derive(Default, CompositeTemplate)]
+    #[template(file = "compare_versions.ui")]
+    pub struct CompareVersions {
+        #[template_child]
+        pub left_dropdown: TemplateChild<gtk4::DropDown>,
+        #[template_child]
+        pub right_dropdown: TemplateChild<gtk4::DropDown>,
+        #[template_child]
+        pub stack: TemplateChild<adw::ViewStack>,
+        #[template_child]
+        pub left_view: TemplateChild<gtk4::TextView>,
+        #[template_child]
+        pub right_view: TemplateChild<gtk4::TextView>,
+        #[template_child]
+        pub inline_view: TemplateChild<gtk4::TextView>,
+        #[template_child]
+        pub previous_btn: TemplateChild<gtk4::Button>,
+        #[template_child]
+        pub next_btn: TemplateChild<gtk4::Button>,
+        #[template_child]
+        pub summary_label: TemplateChild<gtk4::Label>,
+        #[template_child]
+        pub copy_left_btn: TemplateChild<gtk4::Button>,
+        #[template_child]
+        pub copy_right_btn: TemplateChild<gtk4::Button>,
+
+        pub versions: RefCell<Vec<Version>>,
+        pub hunks: RefCell<Vec<Hunk>>,
+        pub places: RefCell<Vec<Place>>,
+        /// The change being looked at
+        pub current: Cell<Option<usize>>,
+        /// Set while the views are being filled, when the cursor moving
+        /// doesn't pick a change
+        pub filling: Cell<bool>,
+        pub copy_callback: RefCell<Option<CopyCallback>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for CompareVersions {
+        const NAME: &'static str = "CompareVersions";
+        type Type = super::CompareVersions;
+        type ParentType = adw::Window;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+            klass.bind_template_callbacks();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    #[gtk4::template_callbacks]
+    impl CompareVersions {
+        #[template_callback]
+        fn on_source_changed(&self) {
+            self.obj().refresh(None);
+        }
+
+        #[template_callback]
+        fn on_open_left(&self) {
+            self.obj().open_file(Side::Left);
+        }
+
+        #[template_callback]
+        fn on_open_right(&self) {
+            self.obj().open_file(Side::Right);
+        }
+
+        #[template_callback]
+        fn on_mode_changed(&self) {
+            self.obj().show_current();
+        }
+
+        #[template_callback]
+        fn on_previous(&self) {
+            self.obj().step(-1);
+        }
+
+        #[template_callback]
+        fn on_next(&self) {
+            self.obj().step(1);
+        }
+
+        #[template_callback]
+        fn on_copy_left(&self) {
+            self.obj().copy_across(Side::Left);
+        }
+
+        #[template_callback]
+        fn on_copy_right(&self) {
+            self.obj().copy_across(Side::Right);
+        }
+    }
+
+    impl ObjectImpl for CompareVersions {
+        fn constructed(&self) {
+            self.parent_constructed();
+            let obj = self.obj();
+            for (view, side) in [
+                (&*self.left_view, Side::Left),
+                (&*self.right_view, Side::Right),
+                (&*self.inline_view, Side::Inline),
+            ] {
+                let buffer = view.buffer();
+                add_tags(&buffer);
+
+                // Clicking into a change makes it the one looked at
+                let dialog = obj.downgrade();
+                buffer.connect_mark_set(move |buffer, iter, mark| {
+                    if mark != &buffer.get_insert() {
+                        return;
+                    }
+                    if let Some(dialog) = dialog.upgrade() {
+                        dialog.pick_at(side, iter.offset());
+                    }
+                });
+            }
+        }
+    }
+
+    impl WidgetImpl for CompareVersions {}
+    impl WindowImpl for CompareVersions {}
+    impl AdwWindowImpl for CompareVersions {}
+}
+
+glib::wrapper! {
+    pub struct CompareVersions(ObjectSubclass<imp::CompareVersions>)
+        @extends adw::Window, gtk4::Window, gtk4::Widget,
+        @implements gtk4::Accessible, gtk4::Buildable;
+}
+
+impl CompareVersions {
+    /// Compare `versions[left]` with `versions[right]`; files can be added
+    /// to the versions from the dialog
+    pub fn new(versions: Vec<Version>, left: usize, right: usize) -> Self {
+        let dialog: Self = glib::Object::builder().build();
+        dialog.imp().versions.replace(versions);
+        dialog.update_sources(left, right);
+        dialog
+    }
+
+    pub fn present(&self, parent: Option<&impl IsA<gtk4::Window>>) {
+        if let Some(parent) = parent {
+            self.set_transient_for(Some(parent));
+        }
+        gtk4::prelude::GtkWindowExt::present(self);
+    }
+
+    /// Called with the composition a change was copied into and its text
+    /// with the change; returns false if the composition couldn't be changed
+    pub fn connect_copy<F: Fn(&str, &str) -> bool + 'static>(&self, callback: F) {
+        self.imp().copy_callback.replace(Some(Box::new(callback)));
+    }
+
+    /// List the versions to pick from, with these picked
+    fn update_sources(&self, left: usize, right: usize) {
+        let imp = self.imp();
+        let names: Vec<String> = imp.versions.borrow().iter().map(|v| v.name.clone()).collect();
+        let names: Vec<&str> = names.iter().map(String::as_str).collect();
+        let model = gtk4::StringList::new(&names);
+
+        imp.filling.set(true);
+        imp.left_dropdown.set_model(Some(&model));
+        imp.right_dropdown.set_model(Some(&model));
+        imp.left_dropdown.set_selected(left as u32);
+        imp.right_dropdown.set_selected(right as u32);
+        imp.filling.set(false);
+        self.refresh(None);
+    }
+
+    fn version(&self, side: Side) -> Option<Version> {
+        let dropdown = match side {
+            Side::Left => &self.imp().left_dropdown,
+            _ => &self.imp().right_dropdown,
+        };
+        self.imp().versions.borrow().get(dropdown.selected() as usize).cloned()
+    }
+
+    /// Compare the picked versions again, looking at change `current` after
+    fn refresh(&self, current: Option<usize>) {
+        let imp = self.imp();
+        if imp.filling.get() {
+            return;
+        }
+        let (old, new) = match (self.version(Side::Left), self.version(Side::Right)) {
+            (Some(old), Some(new)) => (old.text, new.text),
+            _ => (String::new(), String::new()),
+        };
+        let hunks = diff::compare(&old, &new);
+
+        // The inline view has the old text with the new put in after it
+        let mut inline = String::new();
+        let mut inline_chars = 0;
+        let mut push = |text: &str| {
+            inline.push_str(text);
+            inline_chars += text.chars().count() as i32;
+            inline_chars
+        };
+        let mut places = Vec::new();
+        let mut removed = Vec::new();
+        let mut added = Vec::new();
+        let (mut old_chars, mut new_chars) = (Chars::new(&old), Chars::new(&new));
+        let mut same_from = 0;
+        for hunk in &hunks {
+            let start = push(&old[same_from..hunk.old.start]);
+            let middle = push(&old[hunk.old.clone()]);
+            let end = push(&new[hunk.new.clone()]);
+            removed.push(start..middle);
+            added.push(middle..end);
+            places.push(Place {
+                left: old_chars.at(hunk.old.start)..old_chars.at(hunk.old.end),
+                right: new_chars.at(hunk.new.start)..new_chars.at(hunk.new.end),
+                inline: start..end,
+            });
+            same_from = hunk.old.end;
+        }
+        push(&old[same_from..]);
+
+        imp.filling.set(true);
+        fill(&imp.left_view.buffer(), &old, places.iter().map(|p| (&p.left, "removed")));
+        fill(&imp.right_view.buffer(), &new, places.iter().map(|p| (&p.right, "added")));
+        let spans = removed.iter().map(|r| (r, "removed")).chain(added.iter().map(|r| (r, "added")));
+        fill(&imp.inline_view.buffer(), &inline, spans);
+        imp.filling.set(false);
+
+        let count = hunks.len();
+        imp.hunks.replace(hunks);
+        imp.places.replace(places);
+        imp.current.set(match count {
+            0 => None,
+            _ => Some(current.unwrap_or(0).min(count - 1)),
+        });
+        self.show_current();
+    }
+
+    /// Mark the current change, scroll to it and say where it is
+    fn show_current(&self) {
+        let imp = self.imp();
+        let current = imp.current.get();
+        let count = imp.hunks.borrow().len();
+        let place = current.and_then(|index| imp.places.borrow().get(index).cloned());
+
+        imp.filling.set(true);
+        for (view, side) in [
+            (&*imp.left_view, Side::Left),
+            (&*imp.right_view, Side::Right),
+            (&*imp.inline_view, Side::Inline),
+        ] {
+            let buffer = view.buffer();
+            buffer.remove_tag_by_name("current", &buffer.start_iter(), &buffer.end_iter());
+            if let Some(ref place) = place {
+                let range = place.on(side);
+                let start = buffer.iter_at_offset(range.start);
+                buffer.apply_tag_by_name("current", &start, &buffer.iter_at_offset(range.end));
+                let mark = match buffer.mark("current") {
+                    Some(mark) => {
+                        buffer.move_mark(&mark, &start);
+                        mark
+                    }
+                    None => buffer.create_mark(Some("current"), &start, true),
+                };
+                view.scroll_to_mark(&mark, 0.1, true, 0.0, 0.3);
+            }
+        }
+        imp.filling.set(false);
+
+        imp.summary_label.set_text(&match (current, count) {
+            (_, 0) => "No differences".to_string(),
+            (Some(index), _) => format!("Change {} of {}", index + 1, count),
+            (None, 1) => "1 change".to_string(),
+            (None, n) => format!("{} changes", n),
+        });
+        imp.previous_btn.set_sensitive(current.is_some_and(|index| index > 0));
+        imp.next_btn.set_sensitive(current.is_some_and(|index| index + 1 < count));
+
+        let into = |side: Side| current.is_some() && self.version(side).is_some_and(|v| v.composition_id.is_some());
+        imp.copy_left_btn.set_sensitive(into(Side::Left));
+        imp.copy_right_btn.set_sensitive(into(Side::Right));
+    }
+
+    fn step(&self, by: isize) {
+        let imp = self.imp();
+        let count = imp.hunks.borrow().len() as isize;
+        if let Some(index) = imp.current.get() {
+            let index = index as isize + by;
+            if (0..count).contains(&index) {
+                imp.current.set(Some(index as usize));
+                self.show_current();
+            }
+        }
+    }
+
+    /// Look at the change at `offset` in the view on `side`, if there is one
+    fn pick_at(&self, side: Side, offset: i32) {
+        let imp = self.imp();
+        if imp.filling.get() {
+            return;
+        }
+        let index = imp
+            .places
+            .borrow()
+            .iter()
+            .position(|place| place.on(side).start <= offset && offset <= place.on(side).end);
+        if index.is_some() && index != imp.current.get() {
+            imp.current.set(index);
+            self.show_current();
+        }
+    }
+
+    /// Make the current change on `side` as it is on the other side
+    fn copy_across(&self, side: Side) {
+        let imp = self.imp();
+        let (old, new) = match (self.version(Side::Left), self.version(Side::Right)) {
+            (Some(old), Some(new)) => (old, new),
+            _ => return,
+        };
+        let current = imp.current.get();
+        let hunk = match current.and_then(|index| imp.hunks.borrow().get(index).cloned()) {
+            Some(hunk) => hunk,
+            None => return,
+        };
+        let (target, text) = match side {
+            Side::Left => (old.composition_id, hunk.copy_to_old(&old.text, &new.text)),
+            _ => (new.composition_id, hunk.copy_to_new(&old.text, &new.text)),
+        };
+        let target = match target {
+            Some(target) => target,
+            None => return,
+        };
+
+        let copied = match *imp.copy_callback.borrow() {
+            Some(ref callback) => callback(&target, &text),
+            None => false,
+        };
+        if !copied {
+            return;
+        }
+        for version in imp.versions.borrow_mut().iter_mut() {
+            if version.composition_id.as_deref() == Some(target.as_str()) {
+                version.text = text.clone();
+            }
+        }
+        self.refresh(current);
+    }
+
+    /// Pick a file to compare on `side`
+    fn open_file(&self, side: Side) {
+        let dialog = gtk4::FileDialog::builder().title("Open a File to Compare").build();
+        let window = self.clone();
+        dialog.open(Some(self), None::<&gio::Cancellable>, move |result| {
+            let path = match result.ok().and_then(|file| file.path()) {
+                Some(path) => path,
+                None => return,
+            };
+            let text = match std::fs::read_to_string(&path) {
+                Ok(text) => text,
+                Err(e) => {
+                    log::error!("Failed to read {}: {}", path.display(), e);
+                    window.imp().summary_label.set_text("Only text files can be compared");
+                    return;
+                }
+            };
+            let name = path.file_name().map_or_else(|| path.display().to_string(), |name| name.to_string_lossy().to_string());
+
+            let imp = window.imp();
+            let index = {
+                let mut versions = imp.versions.borrow_mut();
+                versions.push(Version { name, text, composition_id: None });
+                versions.len() - 1
+            };
+            let (left, right) = match side {
+                Side::Left => (index, imp.right_dropdown.selected() as usize),
+                _ => (imp.left_dropdown.selected() as usize, index),
+            };
+            window.update_sources(left, right);
+        });
+    }
+}
+
+/// Walks a text from start to end turning byte offsets into char offsets
+struct Chars<'a> {
+    text: &'a str,
+    byte: usize,
+    char: i32,
+}
+
+impl<'a> Chars<'a> {
+    fn new(text: &'a str) -> Self {
+        Self { text, byte: 0, char: 0 }
+    }
+
+    /// The char offset of `byte`, which mustn't be before the last one asked for
+    fn at(&mut self, byte: usize) -> i32 {
+        self.char += self.text[self.byte..byte].chars().count() as i32;
+        self.byte = byte;
+        self.char
+    }
+}
+
+/// Removed text struck out in red, added text underlined in green, and the
+/// change being looked at outlined over both
+fn add_tags(buffer: &gtk4::TextBuffer) {
+    let tags = buffer.tag_table();
+    tags.add(
+        &gtk4::TextTag::builder()
+            .name("removed")
+            .strikethrough(true)
+            .background_rgba(&gdk::RGBA::new(0.88, 0.11, 0.14, 0.15))
+            .build(),
+    );
+    tags.add(
+        &gtk4::TextTag::builder()
+            .name("added")
+            .underline(pango::Underline::Single)
+            .background_rgba(&gdk::RGBA::new(0.18, 0.76, 0.49, 0.18))
+            .build(),
+    );
+    tags.add(
+        &gtk4::TextTag::builder()
+            .name("current")
+            .weight(700)
+            .paragraph_background_rgba(&gdk::RGBA::new(0.21, 0.52, 0.89, 0.08))
+            .build(),
+    );
+}
+
+/// Put `text` in `buffer` with each span's tag on its chars
+fn fill<'a>(buffer: &gtk4::TextBuffer, text: &str, spans: impl Iterator<Item = (&'a Range<i32>, &'a str)>) {
+    buffer.set_text(text);
+    for (range, tag) in spans {
+        buffer.apply_tag_by_name(tag, &buffer.iter_at_offset(range.start), &buffer.iter_at_offset(range.end));
+    }
+}
+
+impl Default for CompareVersions {
+    fn default() -> Self {
+        glib::Object::builder().build()
+    }
+}
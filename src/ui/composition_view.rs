@@ -1,10 +1,23 @@
 use adw::subclass::prelude::*;
 use gtk4::prelude::*;
-use gtk4::{glib, CompositeTemplate};
+use gtk4::{gdk, glib, pango, CompositeTemplate};
 use libadwaita as adw;
-use std::cell::RefCell;
+use std::cell::{Cell, OnceCell, RefCell};
 
-use crate::data::{Composition, Note};
+use super::style_check::{self, StyleChecker};
+use super::{CommentsPanel, FindBar, LinksPanel, MarkdownView, OutlinePanel, SpellChecker, SuggestionBar};
+use crate::data::{Comment, Composition, FocusScope, FocusSettings, Note, SessionState, Suggestion};
+use abbey_core::links::{Backlink, Source};
+use abbey_core::utils::{editing, frequency, typography};
+use abbey_core::utils::lint::Issue;
+use abbey_core::utils::text_stats::{self, TextStats};
+
+const HIGHLIGHT_TAG: &str = "term-highlight";
+const FOCUS_DIM_TAG: &str = "focus-dim";
+
+/// Margins around the text outside focus mode, as in the template
+const EDITOR_MARGIN: i32 = 48;
+const EDITOR_TOP_MARGIN: i32 = 24;
 
 mod imp {
     use super::*;
@@ -15,28 +28,85 @@ mod imp {
         #[template_child]
         pub split_view: TemplateChild<adw::OverlaySplitView>,
         #[template_child]
+        pub title_bar: TemplateChild<gtk4::CenterBox>,
+        #[template_child]
+        pub title_separator: TemplateChild<gtk4::Separator>,
+        #[template_child]
         pub title_entry: TemplateChild<gtk4::Entry>,
         #[template_child]
-        pub editor: TemplateChild<gtk4::TextView>,
+        pub editor: TemplateChild<sourceview5::View>,
+        #[template_child]
+        pub find_bar: TemplateChild<FindBar>,
+        #[template_child]
+        pub suggestion_bar: TemplateChild<SuggestionBar>,
         #[template_child]
-        pub editor_stack: TemplateChild<gtk4::Stack>,
+        pub editor_panes: TemplateChild<gtk4::Box>,
+        #[template_child]
+        pub editor_scroll: TemplateChild<gtk4::ScrolledWindow>,
+        #[template_child]
+        pub preview_scroll: TemplateChild<gtk4::ScrolledWindow>,
         #[template_child]
         pub preview_view: TemplateChild<gtk4::TextView>,
         #[template_child]
         pub toggle_preview_btn: TemplateChild<gtk4::ToggleButton>,
         #[template_child]
+        pub toggle_split_btn: TemplateChild<gtk4::ToggleButton>,
+        #[template_child]
         pub word_count_label: TemplateChild<gtk4::Label>,
         #[template_child]
+        pub stats_btn: TemplateChild<gtk4::MenuButton>,
+        #[template_child]
         pub notes_list: TemplateChild<gtk4::ListBox>,
         #[template_child]
         pub note_entry: TemplateChild<gtk4::Entry>,
         #[template_child]
         pub toggle_notes_btn: TemplateChild<gtk4::ToggleButton>,
+        #[template_child]
+        pub toggle_style_btn: TemplateChild<gtk4::ToggleButton>,
+        #[template_child]
+        pub toggle_outline_btn: TemplateChild<gtk4::ToggleButton>,
+        #[template_child]
+        pub toggle_comments_btn: TemplateChild<gtk4::ToggleButton>,
+        #[template_child]
+        pub toggle_links_btn: TemplateChild<gtk4::ToggleButton>,
+        #[template_child]
+        pub sidebar_stack: TemplateChild<gtk4::Stack>,
+        #[template_child]
+        pub style_rules_btn: TemplateChild<gtk4::MenuButton>,
+        #[template_child]
+        pub style_summary_label: TemplateChild<gtk4::Label>,
+        #[template_child]
+        pub style_list: TemplateChild<gtk4::ListBox>,
+        #[template_child]
+        pub outline_panel: TemplateChild<OutlinePanel>,
+        #[template_child]
+        pub comments_panel: TemplateChild<CommentsPanel>,
+        #[template_child]
+        pub links_panel: TemplateChild<LinksPanel>,
         
         pub composition: RefCell<Option<Composition>>,
         pub content_changed_callback: RefCell<Option<Box<dyn Fn(String) + 'static>>>,
         pub title_changed_callback: RefCell<Option<Box<dyn Fn(String) + 'static>>>,
+        pub title_committed_callback: RefCell<Option<Box<dyn Fn(String, String) + 'static>>>,
+        /// The title as it was when the composition was opened or the title
+        /// was last committed, to tell a rename from typing
+        pub committed_title: RefCell<String>,
         pub notes_changed_callback: RefCell<Option<Box<dyn Fn(Vec<Note>) + 'static>>>,
+        pub comments_changed_callback: RefCell<Option<Box<dyn Fn(Vec<Comment>) + 'static>>>,
+        pub suggestions_changed_callback: RefCell<Option<Box<dyn Fn(Vec<Suggestion>) + 'static>>>,
+        pub suggesting_changed_callback: RefCell<Option<Box<dyn Fn(bool) + 'static>>>,
+        pub language_changed_callback: RefCell<Option<Box<dyn Fn(String) + 'static>>>,
+        pub lint_ignores_changed_callback: RefCell<Option<Box<dyn Fn(Vec<String>) + 'static>>>,
+        pub pending_scroll: Cell<Option<f64>>,
+        pub preview: OnceCell<MarkdownView>,
+        pub spell_checker: OnceCell<SpellChecker>,
+        pub style_checker: OnceCell<StyleChecker>,
+        /// What the style list shows, row by row
+        pub style_issues: RefCell<Vec<Issue>>,
+        /// Set while one pane scrolls the other, so it doesn't scroll back
+        pub syncing_scroll: Cell<bool>,
+        /// Focus mode's settings while it's on
+        pub focus: RefCell<Option<FocusSettings>>,
     }
 
     #[glib::object_subclass]
@@ -46,6 +116,12 @@ mod imp {
         type ParentType = gtk4::Box;
 
         fn class_init(klass: &mut Self::Class) {
+            sourceview5::View::ensure_type();
+            FindBar::ensure_type();
+            SuggestionBar::ensure_type();
+            OutlinePanel::ensure_type();
+            CommentsPanel::ensure_type();
+            LinksPanel::ensure_type();
             klass.bind_template();
             klass.bind_template_callbacks();
         }
@@ -59,18 +135,91 @@ mod imp {
     impl CompositionView {
         #[template_callback]
         fn on_toggle_notes(&self, btn: &gtk4::ToggleButton) {
-            self.split_view.set_show_sidebar(btn.is_active());
+            // The sidebar shows one of notes, the style report, the outline,
+            // comments and backlinks
+            if btn.is_active() {
+                self.toggle_style_btn.set_active(false);
+                self.toggle_outline_btn.set_active(false);
+                self.toggle_comments_btn.set_active(false);
+                self.toggle_links_btn.set_active(false);
+                self.sidebar_stack.set_visible_child_name("notes");
+            }
+            self.obj().update_sidebar();
+        }
+
+        #[template_callback]
+        fn on_toggle_style(&self, btn: &gtk4::ToggleButton) {
+            if btn.is_active() {
+                self.toggle_notes_btn.set_active(false);
+                self.toggle_outline_btn.set_active(false);
+                self.toggle_comments_btn.set_active(false);
+                self.toggle_links_btn.set_active(false);
+                self.sidebar_stack.set_visible_child_name("style");
+            }
+            self.obj().update_sidebar();
+        }
+
+        #[template_callback]
+        fn on_toggle_outline(&self, btn: &gtk4::ToggleButton) {
+            if btn.is_active() {
+                self.toggle_notes_btn.set_active(false);
+                self.toggle_style_btn.set_active(false);
+                self.toggle_comments_btn.set_active(false);
+                self.toggle_links_btn.set_active(false);
+                self.sidebar_stack.set_visible_child_name("outline");
+            }
+            self.obj().update_sidebar();
+        }
+
+        #[template_callback]
+        fn on_toggle_comments(&self, btn: &gtk4::ToggleButton) {
+            if btn.is_active() {
+                self.toggle_notes_btn.set_active(false);
+                self.toggle_style_btn.set_active(false);
+                self.toggle_outline_btn.set_active(false);
+                self.toggle_links_btn.set_active(false);
+                self.sidebar_stack.set_visible_child_name("comments");
+            }
+            self.obj().update_sidebar();
+        }
+
+        #[template_callback]
+        fn on_toggle_links(&self, btn: &gtk4::ToggleButton) {
+            if btn.is_active() {
+                self.toggle_notes_btn.set_active(false);
+                self.toggle_style_btn.set_active(false);
+                self.toggle_outline_btn.set_active(false);
+                self.toggle_comments_btn.set_active(false);
+                self.sidebar_stack.set_visible_child_name("links");
+            }
+            self.obj().update_sidebar();
+        }
+
+        #[template_callback]
+        fn on_style_row_activated(&self, row: &gtk4::ListBoxRow) {
+            let issue = usize::try_from(row.index())
+                .ok()
+                .and_then(|index| self.style_issues.borrow().get(index).cloned());
+            if let Some(issue) = issue {
+                self.obj().style_checker().select(&issue);
+            }
         }
 
         #[template_callback]
         fn on_toggle_preview(&self, btn: &gtk4::ToggleButton) {
+            // The preview shows alone or beside the editor, not both
             if btn.is_active() {
-                // Render markdown and show preview
-                self.obj().render_preview();
-                self.editor_stack.set_visible_child_name("preview");
-            } else {
-                self.editor_stack.set_visible_child_name("edit");
+                self.toggle_split_btn.set_active(false);
+            }
+            self.obj().update_panes();
+        }
+
+        #[template_callback]
+        fn on_toggle_split(&self, btn: &gtk4::ToggleButton) {
+            if btn.is_active() {
+                self.toggle_preview_btn.set_active(false);
             }
+            self.obj().update_panes();
         }
 
         #[template_callback]
@@ -92,6 +241,9 @@ mod imp {
         fn constructed(&self) {
             self.parent_constructed();
             self.obj().setup_editor();
+            self.obj().setup_split_sync();
+            self.obj().setup_style_check();
+            self.obj().setup_focus();
         }
     }
 
@@ -118,26 +270,121 @@ impl CompositionView {
         // Add styling classes
         editor.add_css_class("editor-view");
         
+        // Markdown highlighting, auto-pairing and list continuation
+        super::editor::setup_markdown_editor(editor);
+        
+        self.imp().find_bar.attach(editor);
+        self.imp().outline_panel.attach(editor);
+        
+        // Suggestion mode, recording edits for review instead of making them
+        let suggestions = &self.imp().suggestion_bar;
+        suggestions.attach(editor);
+        let view = self.clone();
+        suggestions.connect_changed(move |suggestions| {
+            if let Some(ref mut comp) = *view.imp().composition.borrow_mut() {
+                comp.suggestions = suggestions.clone();
+            }
+            if let Some(ref callback) = *view.imp().suggestions_changed_callback.borrow() {
+                callback(suggestions);
+            }
+        });
+        let view = self.clone();
+        suggestions.connect_mode_changed(move |suggesting| {
+            if let Some(ref mut comp) = *view.imp().composition.borrow_mut() {
+                comp.suggesting = suggesting;
+            }
+            if let Some(ref callback) = *view.imp().suggesting_changed_callback.borrow() {
+                callback(suggesting);
+            }
+        });
+        
+        // Spell checking, in the composition's own language if it picked one
+        let view = self.clone();
+        self.spell_checker().connect_language_changed(move |language| {
+            if let Some(ref mut comp) = *view.imp().composition.borrow_mut() {
+                comp.language = Some(language.clone());
+            }
+            if let Some(ref callback) = *view.imp().language_changed_callback.borrow() {
+                callback(language);
+            }
+        });
+        
+        // Comments, after spelling so Add Comment follows its suggestions in
+        // the context menu
+        let comments = &self.imp().comments_panel;
+        comments.attach(editor);
+        let view = self.clone();
+        comments.connect_changed(move |comments| {
+            if let Some(ref mut comp) = *view.imp().composition.borrow_mut() {
+                comp.comments = comments.clone();
+            }
+            if let Some(ref callback) = *view.imp().comments_changed_callback.borrow() {
+                callback(comments);
+            }
+        });
+        let view = self.clone();
+        comments.connect_reveal(move || {
+            view.imp().toggle_comments_btn.set_active(true);
+        });
+        
+        // Wiki links, completed from titles and opened with Ctrl+click
+        self.imp().links_panel.attach(editor);
+        
+        // Uses of a word picked in the word analysis, until the next edit
+        let highlight = gtk4::TextTag::builder()
+            .name(HIGHLIGHT_TAG)
+            .background_rgba(&gdk::RGBA::new(0.96, 0.83, 0.18, 0.45))
+            .build();
+        editor.buffer().tag_table().add(&highlight);
+        
         // Configure for comfortable writing
         editor.set_wrap_mode(gtk4::WrapMode::Word);
-        editor.set_left_margin(48);
-        editor.set_right_margin(48);
-        editor.set_top_margin(24);
-        editor.set_bottom_margin(24);
+        editor.set_left_margin(EDITOR_MARGIN);
+        editor.set_right_margin(EDITOR_MARGIN);
+        editor.set_top_margin(EDITOR_TOP_MARGIN);
+        editor.set_bottom_margin(EDITOR_TOP_MARGIN);
         
         // Track changes
         let view = self.clone();
         editor.buffer().connect_changed(move |buffer| {
             let text = buffer.text(&buffer.start_iter(), &buffer.end_iter(), false);
-            let word_count = text.split_whitespace().count();
-            view.imp().word_count_label.set_text(&format!("{} words", word_count));
+            buffer.remove_tag_by_name(HIGHLIGHT_TAG, &buffer.start_iter(), &buffer.end_iter());
+            
+            // Counted and previewed as it reads without pending suggestions,
+            // once text typed as one has been marked
+            let idle_view = view.clone();
+            glib::idle_add_local_once(move || idle_view.update_reading());
             
             // Notify of content change
             if let Some(ref callback) = *view.imp().content_changed_callback.borrow() {
                 callback(text.to_string());
             }
         });
-
+        
+        // Apply a restored scroll position once layout has grown the adjustment enough
+        if let Some(adjustment) = editor.vadjustment() {
+            let view = self.clone();
+            adjustment.connect_changed(move |adj| {
+                if let Some(target) = view.imp().pending_scroll.get() {
+                    if adj.upper() - adj.page_size() >= target {
+                        view.imp().pending_scroll.set(None);
+                        adj.set_value(target);
+                    }
+                }
+            });
+        }
+        
+        // Statistics for the selection if there is one, else the whole text
+        let view = self.clone();
+        self.imp().stats_btn.set_create_popup_func(move |btn| {
+            let buffer = view.imp().editor.buffer();
+            let (title, text) = match buffer.selection_bounds() {
+                Some((start, end)) => ("Selection", buffer.text(&start, &end, false)),
+                None => ("Document", buffer.text(&buffer.start_iter(), &buffer.end_iter(), false)),
+            };
+            btn.set_popover(Some(&super::stats_popover::stats_popover(title, &TextStats::of(&text))));
+        });
+        
         // Title entry styling
         self.imp().title_entry.add_css_class("title-entry");
         
@@ -153,201 +400,618 @@ impl CompositionView {
                 callback(title);
             }
         });
+        
+        // A rename is done when the title is entered or left
+        let view = self.clone();
+        self.imp().title_entry.connect_activate(move |_| view.commit_title());
+        let focus = gtk4::EventControllerFocus::new();
+        let view = self.clone();
+        focus.connect_leave(move |_| view.commit_title());
+        self.imp().title_entry.add_controller(focus);
+    }
+
+    fn commit_title(&self) {
+        let title = self.get_title();
+        let old_title = self.imp().committed_title.replace(title.clone());
+        if old_title == title {
+            return;
+        }
+        if let Some(ref callback) = *self.imp().title_committed_callback.borrow() {
+            callback(old_title, title);
+        }
     }
 
     pub fn set_composition(&self, composition: &Composition) {
         // Set title
         self.imp().title_entry.set_text(&composition.title);
+        self.imp().committed_title.replace(composition.title.clone());
         
-        // Set content
-        self.imp().editor.buffer().set_text(&composition.content);
+        // Set content, without letting undo reach back into the previous
+        // composition, and without suggesting it
+        let buffer = self.imp().editor.buffer();
+        let suggestions = &self.imp().suggestion_bar;
+        suggestions.without_recording(|| {
+            buffer.begin_irreversible_action();
+            buffer.set_text(&composition.content);
+            buffer.end_irreversible_action();
+        });
         
         // Load notes
         self.load_notes(&composition.notes);
         
+        // Comments and suggestions, back on their text if a save caught them
+        // before they'd caught up with it
+        let mut anchored = composition.clone();
+        anchored.follow_edit(&composition.content);
+        self.imp().comments_panel.set_comments(&anchored.comments);
+        suggestions.set_suggestions(&anchored.suggestions);
+        suggestions.set_suggesting(composition.suggesting);
+        
+        self.spell_checker().set_language(composition.language.as_deref());
+        self.style_checker().set_ignored(composition.lint_ignores.clone());
+        
         // Update word count
-        let word_count = composition.content.split_whitespace().count();
+        let word_count = text_stats::word_count(&composition.content_without_suggestions());
         self.imp().word_count_label.set_text(&format!("{} words", word_count));
         
         // Store composition
         self.imp().composition.replace(Some(composition.clone()));
         
-        // Reset to edit mode
+        // Reset to edit mode; a side-by-side preview stays
+        self.imp().pending_scroll.set(None);
         self.imp().toggle_preview_btn.set_active(false);
-        self.imp().editor_stack.set_visible_child_name("edit");
     }
 
-    fn render_preview(&self) {
-        let buffer = self.imp().editor.buffer();
-        let markdown = buffer.text(&buffer.start_iter(), &buffer.end_iter(), false);
-        
-        // Convert markdown to styled text
-        let preview_buffer = self.imp().preview_view.buffer();
-        preview_buffer.set_text("");
-        
-        // Create tags for styling
-        let tag_table = preview_buffer.tag_table();
-        
-        // Heading tags
-        if tag_table.lookup("h1").is_none() {
-            let h1_tag = gtk4::TextTag::builder()
-                .name("h1")
-                .weight(700)
-                .scale(1.8)
-                .pixels_below_lines(12)
-                .build();
-            tag_table.add(&h1_tag);
-        }
+    /// Restore cursor, scroll position and pane toggles from a saved session
+    pub fn restore_state(&self, session: &SessionState) {
+        self.imp().toggle_notes_btn.set_active(session.notes_visible);
+        self.imp().toggle_split_btn.set_active(session.split_active);
+        self.imp().toggle_preview_btn.set_active(session.preview_active);
         
-        if tag_table.lookup("h2").is_none() {
-            let h2_tag = gtk4::TextTag::builder()
-                .name("h2")
-                .weight(600)
-                .scale(1.4)
-                .pixels_above_lines(16)
-                .pixels_below_lines(8)
-                .build();
-            tag_table.add(&h2_tag);
-        }
+        let editor = &self.imp().editor;
+        let buffer = editor.buffer();
+        let offset = session.cursor_offset.clamp(0, buffer.char_count());
+        buffer.place_cursor(&buffer.iter_at_offset(offset));
         
-        if tag_table.lookup("h3").is_none() {
-            let h3_tag = gtk4::TextTag::builder()
-                .name("h3")
-                .weight(600)
-                .scale(1.2)
-                .pixels_above_lines(12)
-                .pixels_below_lines(6)
-                .build();
-            tag_table.add(&h3_tag);
+        if let Some(adjustment) = editor.vadjustment() {
+            if adjustment.upper() - adjustment.page_size() >= session.scroll_position {
+                adjustment.set_value(session.scroll_position);
+            } else {
+                // Text not laid out yet - the adjustment handler applies it later
+                self.imp().pending_scroll.set(Some(session.scroll_position));
+            }
         }
         
-        if tag_table.lookup("bold").is_none() {
-            let bold_tag = gtk4::TextTag::builder()
-                .name("bold")
-                .weight(700)
-                .build();
-            tag_table.add(&bold_tag);
+        if !session.preview_active {
+            editor.grab_focus();
         }
-        
-        if tag_table.lookup("italic").is_none() {
-            let italic_tag = gtk4::TextTag::builder()
-                .name("italic")
-                .style(gtk4::pango::Style::Italic)
-                .build();
-            tag_table.add(&italic_tag);
+    }
+
+    /// Allow or prevent editing, e.g. while the library is read-only
+    pub fn set_editable(&self, editable: bool) {
+        self.imp().editor.set_editable(editable);
+        self.imp().title_entry.set_editable(editable);
+        self.imp().note_entry.set_sensitive(editable);
+        self.imp().notes_list.set_sensitive(editable);
+        self.imp().comments_panel.set_sensitive(editable);
+        self.imp().links_panel.set_sensitive(editable);
+        self.imp().suggestion_bar.set_sensitive(editable);
+    }
+
+    pub fn cursor_offset(&self) -> i32 {
+        self.imp().editor.buffer().cursor_position()
+    }
+
+    pub fn scroll_position(&self) -> f64 {
+        self.imp().editor.vadjustment()
+            .map(|adj| adj.value())
+            .unwrap_or(0.0)
+    }
+
+    pub fn notes_visible(&self) -> bool {
+        self.imp().toggle_notes_btn.is_active()
+    }
+
+    pub fn preview_active(&self) -> bool {
+        self.imp().toggle_preview_btn.is_active()
+    }
+
+    pub fn split_active(&self) -> bool {
+        self.imp().toggle_split_btn.is_active()
+    }
+
+    /// Record edits as suggestions, or make them
+    pub fn set_suggesting(&self, suggesting: bool) {
+        self.imp().suggestion_bar.set_suggesting(suggesting);
+        if let Some(ref mut comp) = *self.imp().composition.borrow_mut() {
+            comp.suggesting = suggesting;
         }
+    }
+
+    pub fn is_suggesting(&self) -> bool {
+        self.imp().suggestion_bar.is_suggesting()
+    }
+
+    /// Start a comment on the selected text
+    pub fn add_comment(&self) {
+        self.imp().comments_panel.begin_comment();
+    }
+
+    /// Go through the composition's misspellings one by one
+    pub fn check_spelling(&self) {
+        self.spell_checker().review();
+    }
+
+    /// Highlight every use of a word or phrase and scroll to the one at `at`,
+    /// in chars, or else the first; returns how many there are
+    pub fn highlight_term(&self, term: &str, at: Option<usize>) -> usize {
+        let editor = &self.imp().editor;
+        let buffer = editor.buffer();
+        buffer.remove_tag_by_name(HIGHLIGHT_TAG, &buffer.start_iter(), &buffer.end_iter());
         
-        if tag_table.lookup("code").is_none() {
-            let code_tag = gtk4::TextTag::builder()
-                .name("code")
-                .family("monospace")
-                .background("rgba(128, 128, 128, 0.15)")
-                .build();
-            tag_table.add(&code_tag);
+        let text = buffer.text(&buffer.start_iter(), &buffer.end_iter(), false);
+        let found = frequency::occurrences(&text, term);
+        for range in &found {
+            buffer.apply_tag_by_name(
+                HIGHLIGHT_TAG,
+                &buffer.iter_at_offset(range.start as i32),
+                &buffer.iter_at_offset(range.end as i32),
+            );
         }
         
-        if tag_table.lookup("blockquote").is_none() {
-            let quote_tag = gtk4::TextTag::builder()
-                .name("blockquote")
-                .style(gtk4::pango::Style::Italic)
-                .left_margin(24)
-                .foreground("gray")
-                .build();
-            tag_table.add(&quote_tag);
+        let target = at.or_else(|| found.first().map(|range| range.start));
+        if let Some(offset) = target {
+            let mut iter = buffer.iter_at_offset(offset as i32);
+            buffer.place_cursor(&iter);
+            editor.scroll_to_iter(&mut iter, 0.1, true, 0.0, 0.3);
         }
+        self.imp().toggle_preview_btn.set_active(false);
+        editor.grab_focus();
+        found.len()
+    }
+
+    /// Open the find bar, with replacing if `replace`
+    pub fn show_find(&self, replace: bool) {
+        self.imp().toggle_preview_btn.set_active(false);
+        self.imp().find_bar.show(replace);
+    }
+
+    /// Put typographic punctuation throughout the composition's prose, as one
+    /// step to undo; returns how many passages changed
+    pub fn smarten(&self, options: &typography::Options) -> usize {
+        let buffer = self.imp().editor.buffer();
+        let text = buffer.text(&buffer.start_iter(), &buffer.end_iter(), false);
+        let edits = typography::smarten_edits(&text, options);
         
-        if tag_table.lookup("bullet").is_none() {
-            let bullet_tag = gtk4::TextTag::builder()
-                .name("bullet")
-                .left_margin(24)
-                .build();
-            tag_table.add(&bullet_tag);
+        buffer.begin_user_action();
+        for edit in edits.iter().rev() {
+            let mut start = buffer.iter_at_offset(edit.range.start as i32);
+            let mut end = buffer.iter_at_offset(edit.range.end as i32);
+            buffer.delete(&mut start, &mut end);
+            buffer.insert(&mut start, &edit.text);
         }
+        buffer.end_user_action();
+        edits.len()
+    }
+
+    fn spell_checker(&self) -> &SpellChecker {
+        self.imp().spell_checker.get_or_init(|| SpellChecker::new(&*self.imp().editor))
+    }
+
+    fn style_checker(&self) -> &StyleChecker {
+        self.imp().style_checker.get_or_init(|| StyleChecker::new(&*self.imp().editor))
+    }
+
+    fn update_sidebar(&self) {
+        let imp = self.imp();
+        let wanted = imp.toggle_notes_btn.is_active()
+            || imp.toggle_style_btn.is_active()
+            || imp.toggle_outline_btn.is_active()
+            || imp.toggle_comments_btn.is_active()
+            || imp.toggle_links_btn.is_active();
+        imp.split_view.set_show_sidebar(wanted && imp.focus.borrow().is_none());
+    }
+
+    fn setup_style_check(&self) {
+        let view = self.clone();
+        self.style_checker().connect_issues_changed(move |issues| {
+            view.show_style_issues(issues);
+        });
         
-        // Parse and render markdown line by line
-        for line in markdown.lines() {
-            let mut iter = preview_buffer.end_iter();
-            
-            if line.starts_with("# ") {
-                preview_buffer.insert(&mut iter, &line[2..]);
-                let start = preview_buffer.iter_at_offset(iter.offset() - (line.len() as i32 - 2));
-                preview_buffer.apply_tag_by_name("h1", &start, &iter);
-            } else if line.starts_with("## ") {
-                preview_buffer.insert(&mut iter, &line[3..]);
-                let start = preview_buffer.iter_at_offset(iter.offset() - (line.len() as i32 - 3));
-                preview_buffer.apply_tag_by_name("h2", &start, &iter);
-            } else if line.starts_with("### ") {
-                preview_buffer.insert(&mut iter, &line[4..]);
-                let start = preview_buffer.iter_at_offset(iter.offset() - (line.len() as i32 - 4));
-                preview_buffer.apply_tag_by_name("h3", &start, &iter);
-            } else if line.starts_with("> ") {
-                preview_buffer.insert(&mut iter, &line[2..]);
-                let start = preview_buffer.iter_at_offset(iter.offset() - (line.len() as i32 - 2));
-                preview_buffer.apply_tag_by_name("blockquote", &start, &iter);
-            } else if line.starts_with("- ") || line.starts_with("* ") {
-                preview_buffer.insert(&mut iter, &format!("• {}", &line[2..]));
-                let start = preview_buffer.iter_at_offset(iter.offset() - (line.len() as i32));
-                preview_buffer.apply_tag_by_name("bullet", &start, &iter);
-            } else {
-                // Inline formatting
-                self.render_inline_markdown(&preview_buffer, line);
+        let view = self.clone();
+        self.style_checker().connect_ignores_changed(move |ignores| {
+            if let Some(ref mut comp) = *view.imp().composition.borrow_mut() {
+                comp.lint_ignores = ignores.clone();
             }
-            
-            preview_buffer.insert(&mut preview_buffer.end_iter(), "\n");
-        }
-    }
-
-    fn render_inline_markdown(&self, buffer: &gtk4::TextBuffer, text: &str) {
-        let mut iter = buffer.end_iter();
-        let mut chars: Vec<char> = text.chars().collect();
-        let mut i = 0;
-        
-        while i < chars.len() {
-            // Bold **text**
-            if i + 1 < chars.len() && chars[i] == '*' && chars[i + 1] == '*' {
-                if let Some(end) = chars[i + 2..].iter().position(|&c| c == '*')
-                    .and_then(|p| if p + i + 3 < chars.len() && chars[p + i + 3] == '*' { Some(p) } else { None })
-                {
-                    let content: String = chars[i + 2..i + 2 + end].iter().collect();
-                    let start_offset = iter.offset();
-                    buffer.insert(&mut iter, &content);
-                    let start = buffer.iter_at_offset(start_offset);
-                    buffer.apply_tag_by_name("bold", &start, &iter);
-                    i += 4 + end;
-                    continue;
-                }
+            if let Some(ref callback) = *view.imp().lint_ignores_changed_callback.borrow() {
+                callback(ignores);
             }
+        });
+        
+        // Rule toggles, built as the menu opens so they show the current settings
+        self.imp().style_rules_btn.set_create_popup_func(|btn| {
+            let vbox = gtk4::Box::builder()
+                .orientation(gtk4::Orientation::Vertical)
+                .spacing(4)
+                .margin_start(6)
+                .margin_end(6)
+                .margin_top(6)
+                .margin_bottom(6)
+                .build();
             
-            // Italic *text* or _text_
-            if (chars[i] == '*' || chars[i] == '_') && (i == 0 || chars[i - 1] != chars[i]) {
-                let marker = chars[i];
-                if let Some(end) = chars[i + 1..].iter().position(|&c| c == marker) {
-                    let content: String = chars[i + 1..i + 1 + end].iter().collect();
-                    let start_offset = iter.offset();
-                    buffer.insert(&mut iter, &content);
-                    let start = buffer.iter_at_offset(start_offset);
-                    buffer.apply_tag_by_name("italic", &start, &iter);
-                    i += 2 + end;
-                    continue;
-                }
-            }
+            let underline = gtk4::CheckButton::builder()
+                .label("Underline in Editor")
+                .active(style_check::style_underlines())
+                .build();
+            underline.connect_toggled(|check| style_check::set_style_underlines(check.is_active()));
+            vbox.append(&underline);
+            vbox.append(&gtk4::Separator::new(gtk4::Orientation::Horizontal));
             
-            // Inline code `text`
-            if chars[i] == '`' {
-                if let Some(end) = chars[i + 1..].iter().position(|&c| c == '`') {
-                    let content: String = chars[i + 1..i + 1 + end].iter().collect();
-                    let start_offset = iter.offset();
-                    buffer.insert(&mut iter, &content);
-                    let start = buffer.iter_at_offset(start_offset);
-                    buffer.apply_tag_by_name("code", &start, &iter);
-                    i += 2 + end;
-                    continue;
-                }
+            for (id, name, enabled) in style_check::style_rules() {
+                let check = gtk4::CheckButton::builder()
+                    .label(name)
+                    .active(enabled)
+                    .build();
+                check.connect_toggled(move |check| style_check::set_style_rule_enabled(id, check.is_active()));
+                vbox.append(&check);
             }
             
-            // Regular character
-            buffer.insert(&mut iter, &chars[i].to_string());
-            i += 1;
+            let popover = gtk4::Popover::builder().child(&vbox).build();
+            btn.set_popover(Some(&popover));
+        });
+    }
+
+    /// List what the style rules found, in document order
+    fn show_style_issues(&self, issues: &[Issue]) {
+        let imp = self.imp();
+        let list = &imp.style_list;
+        while let Some(child) = list.first_child() {
+            list.remove(&child);
+        }
+        
+        let summary = match issues.len() {
+            0 => "No suggestions".to_string(),
+            1 => "1 suggestion".to_string(),
+            n => format!("{} suggestions", n),
+        };
+        imp.style_summary_label.set_text(&summary);
+        
+        let names: Vec<(&str, &str)> = style_check::style_rules()
+            .into_iter()
+            .map(|(id, name, _)| (id, name))
+            .collect();
+        for issue in issues {
+            let name = names
+                .iter()
+                .find(|(id, _)| *id == issue.rule)
+                .map_or(issue.rule, |(_, name)| *name);
+            list.append(&self.create_style_row(issue, name));
+        }
+        imp.style_issues.replace(issues.to_vec());
+    }
+
+    fn create_style_row(&self, issue: &Issue, rule_name: &str) -> gtk4::ListBoxRow {
+        let row = gtk4::ListBoxRow::builder()
+            .activatable(true)
+            .css_classes(["note-card"])
+            .build();
+        
+        let hbox = gtk4::Box::builder()
+            .orientation(gtk4::Orientation::Horizontal)
+            .spacing(8)
+            .build();
+        
+        let vbox = gtk4::Box::builder()
+            .orientation(gtk4::Orientation::Vertical)
+            .spacing(4)
+            .hexpand(true)
+            .build();
+        
+        let rule = gtk4::Label::builder()
+            .label(rule_name)
+            .xalign(0.0)
+            .css_classes(["dim-label", "caption"])
+            .build();
+        
+        let text = gtk4::Label::builder()
+            .label(issue.text.split_whitespace().collect::<Vec<_>>().join(" "))
+            .xalign(0.0)
+            .ellipsize(gtk4::pango::EllipsizeMode::End)
+            .css_classes(["heading"])
+            .build();
+        
+        let message = gtk4::Label::builder()
+            .label(&issue.message)
+            .wrap(true)
+            .xalign(0.0)
+            .css_classes(["caption"])
+            .build();
+        
+        vbox.append(&rule);
+        vbox.append(&text);
+        vbox.append(&message);
+        hbox.append(&vbox);
+        
+        let ignore_btn = gtk4::Button::builder()
+            .icon_name("window-close-symbolic")
+            .valign(gtk4::Align::Center)
+            .tooltip_text("Ignore in this composition")
+            .css_classes(["flat", "circular"])
+            .build();
+        
+        let view = self.clone();
+        let issue = issue.clone();
+        ignore_btn.connect_clicked(move |_| {
+            view.style_checker().ignore(&issue);
+        });
+        hbox.append(&ignore_btn);
+        
+        row.set_child(Some(&hbox));
+        
+        row
+    }
+
+    /// The rendered preview, set up on first use
+    fn preview(&self) -> &MarkdownView {
+        self.imp().preview.get_or_init(|| MarkdownView::new(&self.imp().preview_view))
+    }
+
+    /// The word count, and the preview while it's showing, for the text as
+    /// it reads without pending suggestions
+    fn update_reading(&self) {
+        let text = self.imp().suggestion_bar.text_without_suggestions();
+        let word_count = text_stats::word_count(&text);
+        self.imp().word_count_label.set_text(&format!("{} words", word_count));
+        if self.preview_active() || self.split_active() {
+            self.preview().schedule(&text);
+        }
+    }
+
+    fn render_preview(&self) {
+        self.preview().set_markdown(&self.imp().suggestion_bar.text_without_suggestions());
+    }
+
+    /// Show the editor, the preview or both, as the toggles ask
+    fn update_panes(&self) {
+        let imp = self.imp();
+        let preview = self.preview_active();
+        let split = self.split_active();
+        
+        imp.editor_scroll.set_visible(!preview);
+        imp.preview_scroll.set_visible(preview || split);
+        if split {
+            imp.editor_panes.add_css_class("split");
+        } else {
+            imp.editor_panes.remove_css_class("split");
+        }
+        
+        if preview || split {
+            self.render_preview();
+        }
+        if split {
+            // Line the panes up once the preview has been laid out
+            let view = self.clone();
+            glib::idle_add_local_once(move || view.sync_preview_to_editor());
+        }
+    }
+
+    // ========== Side-by-Side Sync ==========
+
+    /// Keep a side-by-side preview level with the editor: scrolling either pane
+    /// scrolls the other, the preview follows the cursor, and clicking the
+    /// preview puts the cursor on the line it came from
+    fn setup_split_sync(&self) {
+        let imp = self.imp();
+        
+        if let Some(adjustment) = imp.editor.vadjustment() {
+            let view = self.clone();
+            adjustment.connect_value_changed(move |_| view.sync_preview_to_editor());
+        }
+        if let Some(adjustment) = imp.preview_view.vadjustment() {
+            let view = self.clone();
+            adjustment.connect_value_changed(move |_| view.sync_editor_to_preview());
+        }
+        
+        let view = self.clone();
+        imp.editor.buffer().connect_cursor_position_notify(move |_| view.follow_cursor());
+        
+        let click = gtk4::GestureClick::new();
+        let view = self.clone();
+        click.connect_released(move |_, _, x, y| view.jump_to_preview_point(x, y));
+        imp.preview_view.add_controller(click);
+    }
+
+    fn sync_preview_to_editor(&self) {
+        if !self.split_active() || self.imp().syncing_scroll.get() {
+            return;
+        }
+        
+        let (offset, y) = top_line(self.imp().editor.upcast_ref());
+        let target = self.preview().preview_position(&self.get_content(), offset);
+        self.scroll_offset_to(&self.imp().preview_view, target, y);
+    }
+
+    fn sync_editor_to_preview(&self) {
+        if !self.split_active() || self.imp().syncing_scroll.get() {
+            return;
+        }
+        
+        let (offset, y) = top_line(&self.imp().preview_view);
+        let target = self.preview().source_position(&self.get_content(), offset);
+        self.scroll_offset_to(self.imp().editor.upcast_ref(), target, y);
+    }
+
+    /// Bring the cursor's place in the preview level with the cursor
+    fn follow_cursor(&self) {
+        if !self.split_active() {
+            return;
+        }
+        
+        let editor: &gtk4::TextView = self.imp().editor.upcast_ref();
+        let buffer = editor.buffer();
+        let cursor = buffer.iter_at_mark(&buffer.get_insert());
+        let y = viewport_y(editor, &cursor);
+        let target = self.preview().preview_position(&self.get_content(), cursor.offset() as usize);
+        self.scroll_offset_to(&self.imp().preview_view, target, y);
+    }
+
+    /// Put the cursor at the start of the source line behind a clicked point
+    /// of the preview, level with where the click was
+    fn jump_to_preview_point(&self, x: f64, y: f64) {
+        let preview_view = &self.imp().preview_view;
+        if !self.split_active() || preview_view.buffer().has_selection() {
+            return;
+        }
+        
+        let (buffer_x, buffer_y) = preview_view.window_to_buffer_coords(
+            gtk4::TextWindowType::Widget,
+            x as i32,
+            y as i32,
+        );
+        let clicked = match preview_view.iter_at_location(buffer_x, buffer_y) {
+            Some(iter) => iter,
+            None => return,
+        };
+        
+        let source = self.preview().source_position(&self.get_content(), clicked.offset() as usize);
+        let editor = &self.imp().editor;
+        let buffer = editor.buffer();
+        let mut line = buffer.iter_at_offset(source as i32);
+        line.set_line_offset(0);
+        
+        self.scroll_offset_to(editor.upcast_ref(), line.offset() as usize, y);
+        buffer.place_cursor(&line);
+        editor.grab_focus();
+    }
+
+    /// Scroll a pane so the char at `offset` shows `y` pixels below its top,
+    /// without the other pane following
+    fn scroll_offset_to(&self, text_view: &gtk4::TextView, offset: usize, y: f64) {
+        let adjustment = match text_view.vadjustment() {
+            Some(adjustment) => adjustment,
+            None => return,
+        };
+        let iter = text_view.buffer().iter_at_offset(offset as i32);
+        let current = viewport_y(text_view, &iter);
+        
+        self.imp().syncing_scroll.set(true);
+        adjustment.set_value(adjustment.value() + current - y);
+        self.imp().syncing_scroll.set(false);
+    }
+
+    // ========== Focus Mode ==========
+
+    /// Turn focus mode on with these settings, or off with `None`. The title
+    /// bar and sidebar hide, everything but the sentence or paragraph being
+    /// written dims, and the text narrows to a column.
+    pub fn set_focus_mode(&self, focus: Option<&FocusSettings>) {
+        let imp = self.imp();
+        let on = focus.is_some();
+        imp.focus.replace(focus.cloned());
+        
+        imp.title_bar.set_visible(!on);
+        imp.title_separator.set_visible(!on);
+        if on {
+            imp.toggle_preview_btn.set_active(false);
+            imp.toggle_split_btn.set_active(false);
+            imp.split_view.set_show_sidebar(false);
+        } else {
+            self.update_sidebar();
+        }
+        
+        self.update_focus_layout();
+        self.update_focus();
+    }
+
+    fn setup_focus(&self) {
+        let imp = self.imp();
+        let tag = gtk4::TextTag::builder().name(FOCUS_DIM_TAG).build();
+        imp.editor.buffer().tag_table().add(&tag);
+        
+        let view = self.clone();
+        imp.editor.buffer().connect_notify_local(Some("cursor-position"), move |_, _| {
+            view.update_focus();
+        });
+        
+        // Re-centre the column and the cursor line as the window changes size
+        let view = self.clone();
+        imp.editor_scroll.hadjustment().connect_changed(move |_| {
+            view.update_focus_layout();
+        });
+        let view = self.clone();
+        imp.editor_scroll.vadjustment().connect_notify_local(Some("page-size"), move |_, _| {
+            view.update_focus_layout();
+        });
+    }
+
+    /// Margins that hold the text to the column width and, with typewriter
+    /// scrolling, let the first and last lines reach the middle of the window
+    fn update_focus_layout(&self) {
+        let imp = self.imp();
+        let editor = &imp.editor;
+        let (side, vertical) = match *imp.focus.borrow() {
+            Some(ref focus) => {
+                let metrics = editor.pango_context().metrics(None, None);
+                let char_width = metrics.approximate_char_width() / pango::SCALE;
+                let column = char_width * focus.column_width as i32;
+                let side = ((imp.editor_scroll.width() - column) / 2).max(EDITOR_MARGIN);
+                let vertical = if focus.typewriter {
+                    (imp.editor_scroll.height() / 2).max(EDITOR_TOP_MARGIN)
+                } else {
+                    EDITOR_TOP_MARGIN
+                };
+                (side, vertical)
+            }
+            None => (EDITOR_MARGIN, EDITOR_TOP_MARGIN),
+        };
+        
+        // Only on a change, as new margins resize the adjustments that call this
+        if editor.left_margin() != side {
+            editor.set_left_margin(side);
+            editor.set_right_margin(side);
+        }
+        if editor.top_margin() != vertical {
+            editor.set_top_margin(vertical);
+            editor.set_bottom_margin(vertical);
+        }
+    }
+
+    /// Dim all but the sentence or paragraph at the cursor, and keep the
+    /// cursor line in the middle with typewriter scrolling
+    fn update_focus(&self) {
+        let imp = self.imp();
+        let editor = &imp.editor;
+        let buffer = editor.buffer();
+        buffer.remove_tag_by_name(FOCUS_DIM_TAG, &buffer.start_iter(), &buffer.end_iter());
+        
+        let (scope, typewriter) = match *imp.focus.borrow() {
+            Some(ref focus) => (focus.scope, focus.typewriter),
+            None => return,
+        };
+        
+        let text = buffer.text(&buffer.start_iter(), &buffer.end_iter(), false);
+        let offset = buffer.cursor_position() as usize;
+        let lit = match scope {
+            FocusScope::Sentence => editing::sentence_at(&text, offset),
+            FocusScope::Paragraph => editing::paragraph_at(&text, offset),
+        };
+        
+        // Faded text colour, over the markdown highlighting, in whatever theme is showing
+        if let Some(tag) = buffer.tag_table().lookup(FOCUS_DIM_TAG) {
+            let color = editor.color();
+            tag.set_foreground_rgba(Some(&gdk::RGBA::new(color.red(), color.green(), color.blue(), 0.3)));
+            tag.set_priority(buffer.tag_table().size() - 1);
+        }
+        buffer.apply_tag_by_name(FOCUS_DIM_TAG, &buffer.start_iter(), &buffer.iter_at_offset(lit.start as i32));
+        buffer.apply_tag_by_name(FOCUS_DIM_TAG, &buffer.iter_at_offset(lit.end as i32), &buffer.end_iter());
+        
+        if typewriter {
+            // Once the edit that moved the cursor has been laid out
+            let view = self.clone();
+            glib::idle_add_local_once(move || {
+                let editor = &view.imp().editor;
+                editor.scroll_to_mark(&editor.buffer().get_insert(), 0.0, true, 0.0, 0.5);
+            });
         }
     }
 
@@ -440,7 +1104,8 @@ impl CompositionView {
         }
     }
 
-    pub fn add_note(&self, content: String) {
+    /// Add a note, returning its id
+    pub fn add_note(&self, content: String) -> String {
         let note = Note::new(content);
         
         // Add to composition
@@ -462,6 +1127,27 @@ impl CompositionView {
         if let Some(ref callback) = *self.imp().notes_changed_callback.borrow() {
             callback(notes);
         }
+        
+        note.id
+    }
+
+    /// Add a paragraph at the end of the text, leaving the cursor where it is
+    pub fn append_paragraph(&self, text: &str) {
+        let buffer = self.imp().editor.buffer();
+        let content = buffer.text(&buffer.start_iter(), &buffer.end_iter(), false);
+        let kept = content.trim_end();
+        
+        // Replace trailing whitespace with exactly one blank line
+        let mut start = buffer.iter_at_offset(kept.chars().count() as i32);
+        let mut end = buffer.end_iter();
+        buffer.delete(&mut start, &mut end);
+        
+        let mut end = buffer.end_iter();
+        if kept.is_empty() {
+            buffer.insert(&mut end, text);
+        } else {
+            buffer.insert(&mut end, &format!("\n\n{}", text));
+        }
     }
 
     pub fn connect_content_changed<F: Fn(String) + 'static>(&self, callback: F) {
@@ -472,10 +1158,59 @@ impl CompositionView {
         self.imp().title_changed_callback.replace(Some(Box::new(callback)));
     }
 
+    /// Called with the old and new title once a rename is done, rather than
+    /// as it's typed
+    pub fn connect_title_committed<F: Fn(String, String) + 'static>(&self, callback: F) {
+        self.imp().title_committed_callback.replace(Some(Box::new(callback)));
+    }
+
     pub fn connect_notes_changed<F: Fn(Vec<Note>) + 'static>(&self, callback: F) {
         self.imp().notes_changed_callback.replace(Some(Box::new(callback)));
     }
 
+    pub fn connect_comments_changed<F: Fn(Vec<Comment>) + 'static>(&self, callback: F) {
+        self.imp().comments_changed_callback.replace(Some(Box::new(callback)));
+    }
+
+    pub fn connect_suggestions_changed<F: Fn(Vec<Suggestion>) + 'static>(&self, callback: F) {
+        self.imp().suggestions_changed_callback.replace(Some(Box::new(callback)));
+    }
+
+    /// Called when suggestion mode is turned on or off from the editor
+    pub fn connect_suggesting_changed<F: Fn(bool) + 'static>(&self, callback: F) {
+        self.imp().suggesting_changed_callback.replace(Some(Box::new(callback)));
+    }
+
+    /// Called with the spelling language picked for the composition
+    /// Called when a style finding is dismissed, with the composition's ignore list
+    pub fn connect_lint_ignores_changed<F: Fn(Vec<String>) + 'static>(&self, callback: F) {
+        self.imp().lint_ignores_changed_callback.replace(Some(Box::new(callback)));
+    }
+
+    pub fn connect_language_changed<F: Fn(String) + 'static>(&self, callback: F) {
+        self.imp().language_changed_callback.replace(Some(Box::new(callback)));
+    }
+
+    /// The titles of the compositions links can be completed with
+    pub fn set_link_titles(&self, titles: Vec<String>) {
+        self.imp().links_panel.set_titles(titles);
+    }
+
+    /// List the places that link to the composition
+    pub fn set_backlinks(&self, backlinks: Vec<Backlink>) {
+        self.imp().links_panel.set_backlinks(backlinks);
+    }
+
+    /// Called with the target of a link Ctrl+clicked in the text
+    pub fn connect_link_activated<F: Fn(&str) + 'static>(&self, callback: F) {
+        self.imp().links_panel.connect_open(callback);
+    }
+
+    /// Called with where a backlink picked in the sidebar is from
+    pub fn connect_backlink_activated<F: Fn(&Source) + 'static>(&self, callback: F) {
+        self.imp().links_panel.connect_backlink_activated(callback);
+    }
+
     pub fn get_content(&self) -> String {
         let buffer = self.imp().editor.buffer();
         buffer.text(&buffer.start_iter(), &buffer.end_iter(), false).to_string()
@@ -491,3 +1226,18 @@ impl Default for CompositionView {
         glib::Object::builder().build()
     }
 }
+
+/// The char offset of the line at the top of a text view, and how far below
+/// the top that line starts
+fn top_line(text_view: &gtk4::TextView) -> (usize, f64) {
+    let (_, top) = text_view.window_to_buffer_coords(gtk4::TextWindowType::Widget, 0, 0);
+    let (line, line_top) = text_view.line_at_y(top);
+    (line.offset() as usize, f64::from(line_top - top))
+}
+
+/// How far below the top of a text view an iter's line shows
+fn viewport_y(text_view: &gtk4::TextView, iter: &gtk4::TextIter) -> f64 {
+    let location = text_view.iter_location(iter);
+    let (_, y) = text_view.buffer_to_window_coords(gtk4::TextWindowType::Widget, 0, location.y());
+    f64::from(y)
+}
@@ -1,51 +1,342 @@
 use gtk4::prelude::*;
+use gtk4::{gdk, glib};
+use sourceview5::prelude::*;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
 
-/// A simple markdown-capable editor
-pub struct Editor {
-    text_view: gtk4::TextView,
+use abbey_core::utils::editing::{self, ListContinuation, Typed};
+use abbey_core::utils::typography;
+
+/// Our markdown definition, which the theme schemes style
+const LANGUAGE_ID: &str = "abbey-markdown";
+
+/// Where the language definition and style schemes are bundled
+const RESOURCE_PATH: &str = "resource:///app/abbey/Abbey";
+
+thread_local! {
+    /// The current theme's style scheme, for editors created later
+    static SCHEME: RefCell<Option<sourceview5::StyleScheme>> = const { RefCell::new(None) };
+    /// Every markdown editor's buffer, to restyle when the theme changes
+    static BUFFERS: RefCell<Vec<glib::WeakRef<sourceview5::Buffer>>> = const { RefCell::new(Vec::new()) };
+    /// Smart punctuation as you type, when it's on
+    static TYPOGRAPHY: Cell<Option<typography::Options>> = const { Cell::new(None) };
+}
+
+/// Make the bundled language definition and style schemes available; call once
+/// before any editor is built
+pub fn init_markdown_editor() {
+    sourceview5::init();
+
+    // Ours comes first, so it wins over any installed definition with the same id
+    let languages = sourceview5::LanguageManager::default();
+    let bundled = format!("{}/language-specs", RESOURCE_PATH);
+    let installed = languages.search_path();
+    let mut search_path: Vec<&str> = vec![bundled.as_str()];
+    search_path.extend(installed.iter().map(|path| path.as_str()));
+    languages.set_search_path(&search_path);
+
+    sourceview5::StyleSchemeManager::default().prepend_search_path(&format!("{}/styles", RESOURCE_PATH));
+}
+
+/// Switch every markdown editor to the style scheme matching a theme from
+/// `config::THEMES`; unknown themes get the toolkit's default scheme
+pub fn apply_style_scheme(theme_id: &str) {
+    let scheme = sourceview5::StyleSchemeManager::default().scheme(&format!("abbey-{}", theme_id));
+
+    BUFFERS.with(|buffers| {
+        let mut buffers = buffers.borrow_mut();
+        buffers.retain(|buffer| buffer.upgrade().is_some());
+        for buffer in buffers.iter().filter_map(|buffer| buffer.upgrade()) {
+            buffer.set_style_scheme(scheme.as_ref());
+        }
+    });
+    SCHEME.with(|current| *current.borrow_mut() = scheme);
+}
+
+/// Turn smart punctuation as you type on, with these options, or off, for
+/// every markdown editor
+pub fn set_smart_typography(options: Option<typography::Options>) {
+    TYPOGRAPHY.with(|current| current.set(options));
 }
 
-impl Editor {
-    pub fn new() -> Self {
-        let text_view = gtk4::TextView::builder()
-            .wrap_mode(gtk4::WrapMode::Word)
-            .left_margin(48)
-            .right_margin(48)
-            .top_margin(24)
-            .bottom_margin(24)
-            .build();
-        
-        text_view.add_css_class("editor-view");
-        
-        Self { text_view }
+/// Turn a source view into a markdown editor: highlighting in the current
+/// theme's scheme, auto-paired brackets and emphasis, and lists continued on Enter
+pub fn setup_markdown_editor(view: &sourceview5::View) {
+    let buffer = match view.buffer().downcast::<sourceview5::Buffer>() {
+        Ok(buffer) => buffer,
+        Err(_) => return,
+    };
+
+    let language = sourceview5::LanguageManager::default().language(LANGUAGE_ID);
+    if language.is_none() {
+        log::warn!("The {} language definition is missing; editing without highlighting", LANGUAGE_ID);
     }
+    buffer.set_language(language.as_ref());
+    buffer.set_highlight_syntax(true);
+    buffer.set_highlight_matching_brackets(true);
+    SCHEME.with(|scheme| buffer.set_style_scheme(scheme.borrow().as_ref()));
+    BUFFERS.with(|buffers| buffers.borrow_mut().push(buffer.downgrade()));
+
+    view.set_auto_indent(true);
+    view.set_tab_width(4);
+    view.set_indent_width(4);
+    view.set_insert_spaces_instead_of_tabs(true);
+
+    let typing = Rc::new(Typing::default());
+
+    // Capture phase, so Enter is seen before the view breaks the line, except
+    // while an input method is composing and takes Enter to commit
+    let composing = typing.clone();
+    view.connect_preedit_changed(move |_, preedit| composing.composing.set(!preedit.is_empty()));
+    let keys = gtk4::EventControllerKey::new();
+    keys.set_propagation_phase(gtk4::PropagationPhase::Capture);
+    let weak_view = view.downgrade();
+    let composing = typing.clone();
+    keys.connect_key_pressed(move |_, key, _, modifiers| {
+        match weak_view.upgrade() {
+            Some(view) if !composing.composing.get() => handle_key(&view, key, modifiers),
+            _ => glib::Propagation::Proceed,
+        }
+    });
+    view.add_controller(keys);
+
+    // Backspace arrives as the view's own signal once the input method passes it on
+    view.connect_backspace(|view| {
+        if view.is_editable() && delete_pair(&view.buffer()) == glib::Propagation::Stop {
+            view.stop_signal_emission_by_name("backspace");
+        }
+    });
+
+    // Typed characters are handled as they reach the text, so ones composed with
+    // dead keys or committed by an input method are paired like any other
+    let state = typing.clone();
+    buffer.connect_begin_user_action(move |_| state.in_action.set(true));
+    let state = typing.clone();
+    buffer.connect_end_user_action(move |buffer| {
+        state.in_action.set(false);
+        state.replaced.take();
+        if let Some((offset, c, replacement)) = state.substitution.take() {
+            substitute(buffer.upcast_ref(), offset, c, &replacement, &state);
+        }
+    });
+    let state = typing.clone();
+    buffer.connect_delete_range(move |buffer, start, end| {
+        // Typing over a selection deletes it first
+        if state.in_action.get() && !state.editing.get() && buffer.selection_bounds() == Some((*start, *end)) {
+            state.replaced.replace(Some(buffer.text(start, end, false).to_string()));
+        }
+    });
+    let weak_view = view.downgrade();
+    let state = typing;
+    buffer.connect_insert_text(move |buffer, location, text| {
+        let view = match weak_view.upgrade() {
+            Some(view) => view,
+            None => return,
+        };
+        let mut chars = text.chars();
+        let c = match (chars.next(), chars.next()) {
+            (Some(c), None) => c,
+            _ => return,
+        };
+        // Only what's typed: one character at the cursor, as part of a user action
+        if !state.in_action.get() || state.editing.get() || !view.is_editable() || !view.has_focus() {
+            return;
+        }
+        if *location != buffer.iter_at_mark(&buffer.get_insert()) {
+            return;
+        }
 
-    pub fn set_content(&self, content: &str) {
-        self.text_view.buffer().set_text(content);
+        if type_char(buffer.upcast_ref(), location, c, &state) == glib::Propagation::Stop {
+            buffer.stop_signal_emission_by_name("insert-text");
+        }
+    });
+}
+
+/// What the editor knows of the edit in progress, as typed text reaches the
+/// buffer through the view's input method rather than as key presses
+#[derive(Default)]
+struct Typing {
+    /// Inside a user action, which text typed into the view always is
+    in_action: Cell<bool>,
+    /// Making our own changes, which aren't typing
+    editing: Cell<bool>,
+    /// An input method is composing text in the view
+    composing: Cell<bool>,
+    /// The selected text the typing replaced, for a typed opener to wrap
+    replaced: RefCell<Option<String>>,
+    /// Smart punctuation for the character typed before this offset, to put in
+    /// once the character is in
+    substitution: RefCell<Option<(i32, char, typography::Replacement)>>,
+}
+
+impl Typing {
+    /// Make our own changes without taking them for typing
+    fn edit(&self, change: impl FnOnce()) {
+        let was = self.editing.replace(true);
+        change();
+        self.editing.set(was);
     }
+}
 
-    pub fn get_content(&self) -> String {
-        let buffer = self.text_view.buffer();
-        buffer.text(&buffer.start_iter(), &buffer.end_iter(), false).to_string()
+fn handle_key(view: &sourceview5::View, key: gdk::Key, modifiers: gdk::ModifierType) -> glib::Propagation {
+    let shortcut = gdk::ModifierType::CONTROL_MASK | gdk::ModifierType::ALT_MASK | gdk::ModifierType::SUPER_MASK;
+    if !view.is_editable() || modifiers.intersects(shortcut) {
+        return glib::Propagation::Proceed;
     }
 
-    pub fn widget(&self) -> &gtk4::TextView {
-        &self.text_view
+    match key {
+        // Shift+Enter still breaks the line without a new item
+        gdk::Key::Return | gdk::Key::KP_Enter if !modifiers.contains(gdk::ModifierType::SHIFT_MASK) => {
+            continue_list(&view.buffer())
+        }
+        _ => glib::Propagation::Proceed,
     }
+}
+/// The current line's text before and after the cursor
+fn line_around_cursor(buffer: &gtk4::TextBuffer) -> (String, String) {
+    let cursor = buffer.iter_at_mark(&buffer.get_insert());
+
+    let mut line_start = cursor;
+    line_start.set_line_offset(0);
+    let mut line_end = cursor;
+    if !line_end.ends_line() {
+        line_end.forward_to_line_end();
+    }
+
+    (
+        buffer.text(&line_start, &cursor, false).to_string(),
+        buffer.text(&cursor, &line_end, false).to_string(),
+    )
+}
+
+fn continue_list(buffer: &gtk4::TextBuffer) -> glib::Propagation {
+    if buffer.has_selection() {
+        return glib::Propagation::Proceed;
+    }
+
+    let (before, after) = line_around_cursor(buffer);
+    match editing::continue_list(&before, &after) {
+        Some(ListContinuation::Continue(prefix)) => {
+            buffer.begin_user_action();
+            buffer.insert_at_cursor(&format!("\n{}", prefix));
+            buffer.end_user_action();
+            glib::Propagation::Stop
+        }
+        Some(ListContinuation::End) => {
+            // Pressing Enter on an empty item leaves the list instead of adding another
+            let mut cursor = buffer.iter_at_mark(&buffer.get_insert());
+            let mut line_start = cursor;
+            line_start.set_line_offset(0);
+            buffer.begin_user_action();
+            buffer.delete(&mut line_start, &mut cursor);
+            buffer.end_user_action();
+            glib::Propagation::Stop
+        }
+        None => glib::Propagation::Proceed,
+    }
+}
+
+/// Act on `c` as it's typed at `location`, the cursor. `Stop` means it was
+/// dealt with here and mustn't be inserted as well.
+fn type_char(buffer: &gtk4::TextBuffer, location: &mut gtk4::TextIter, c: char, typing: &Typing) -> glib::Propagation {
+    // Typing an opener over a selection wraps it, e.g. to embolden a word
+    if let Some(text) = typing.replaced.take() {
+        let close = match editing::closing_pair(c) {
+            Some(close) => close,
+            None => return glib::Propagation::Proceed,
+        };
+        let length = text.chars().count() as i32;
+        typing.edit(|| buffer.insert(location, &format!("{}{}{}", c, text, close)));
 
-    pub fn word_count(&self) -> usize {
-        self.get_content().split_whitespace().count()
+        // Keep the wrapped text selected, so `*` twice makes it bold
+        let inner_end = location.offset() - 1;
+        buffer.select_range(
+            &buffer.iter_at_offset(inner_end - length),
+            &buffer.iter_at_offset(inner_end),
+        );
+        return glib::Propagation::Stop;
     }
 
-    pub fn connect_changed<F: Fn() + 'static>(&self, callback: F) {
-        self.text_view.buffer().connect_changed(move |_| {
-            callback();
-        });
+    let (before, after) = line_around_cursor(buffer);
+    match editing::on_typed(c, &before, &after) {
+        Typed::Insert => smarten_typed(buffer, location, c, &before, &after, typing),
+        Typed::Pair(close) => {
+            typing.edit(|| buffer.insert(location, &format!("{}{}", c, close)));
+            location.backward_char();
+            buffer.place_cursor(location);
+            glib::Propagation::Stop
+        }
+        Typed::Skip => {
+            location.forward_char();
+            buffer.place_cursor(location);
+            glib::Propagation::Stop
+        }
     }
 }
 
-impl Default for Editor {
-    fn default() -> Self {
-        Self::new()
+/// With smart punctuation on, note the typographic form of `c` to put in once
+/// it's typed, so that undo brings back the character as typed
+fn smarten_typed(
+    buffer: &gtk4::TextBuffer,
+    location: &gtk4::TextIter,
+    c: char,
+    before: &str,
+    after: &str,
+    typing: &Typing,
+) -> glib::Propagation {
+    let options = match TYPOGRAPHY.with(Cell::get) {
+        Some(options) => options,
+        None => return glib::Propagation::Proceed,
+    };
+    let replacement = match typography::substitute(c, before, after, &options) {
+        Some(replacement) => replacement,
+        None => return glib::Propagation::Proceed,
+    };
+    let offset = location.offset();
+    let text = buffer.text(&buffer.start_iter(), &buffer.end_iter(), false);
+    if typography::in_prose(&text, offset as usize) {
+        typing.substitution.replace(Some((offset + 1, c, replacement)));
     }
+    glib::Propagation::Proceed
+}
+
+/// Put smart punctuation in place of `c`, typed just before `offset`, as a
+/// step of its own, so one undo takes back the substitution alone
+fn substitute(buffer: &gtk4::TextBuffer, offset: i32, c: char, replacement: &typography::Replacement, typing: &Typing) {
+    let mut end = buffer.iter_at_offset(offset);
+    let mut start = end;
+    start.backward_char();
+    if start.char() != c {
+        return;
+    }
+    start.backward_chars(replacement.remove as i32);
+
+    typing.edit(|| {
+        buffer.begin_user_action();
+        buffer.delete(&mut start, &mut end);
+        buffer.insert(&mut start, &replacement.text);
+        buffer.end_user_action();
+    });
+}
+
+/// Backspace inside an empty pair removes both halves
+fn delete_pair(buffer: &gtk4::TextBuffer) -> glib::Propagation {
+    if buffer.has_selection() {
+        return glib::Propagation::Proceed;
+    }
+
+    let cursor = buffer.iter_at_mark(&buffer.get_insert());
+    let mut start = cursor;
+    if !start.backward_char() {
+        return glib::Propagation::Proceed;
+    }
+    let mut end = cursor;
+    if !end.forward_char() || !editing::deletes_pair(start.char(), cursor.char()) {
+        return glib::Propagation::Proceed;
+    }
+
+    buffer.begin_user_action();
+    buffer.delete(&mut start, &mut end);
+    buffer.end_user_action();
+    glib::Propagation::Stop
 }
@@ -0,0 +1,326 @@
+use adw::subclass::prelude::*;
+use gtk4::prelude::*;
+use gtk4::{gdk, glib, CompositeTemplate};
+use libadwaita as adw;
+use sourceview5::prelude::*;
+use std::cell::RefCell;
+
+mod imp {
+    use super::*;
+
+    #[derive(Default, CompositeTemplate)]
+    #[template(file = "find_bar.ui")]
+    pub struct FindBar {
+        #[template_child]
+        pub revealer: TemplateChild<gtk4::Revealer>,
+        #[template_child]
+        pub find_entry: TemplateChild<gtk4::SearchEntry>,
+        #[template_child]
+        pub matches_label: TemplateChild<gtk4::Label>,
+        #[template_child]
+        pub case_btn: TemplateChild<gtk4::ToggleButton>,
+        #[template_child]
+        pub word_btn: TemplateChild<gtk4::ToggleButton>,
+        #[template_child]
+        pub regex_btn: TemplateChild<gtk4::ToggleButton>,
+        #[template_child]
+        pub replace_btn: TemplateChild<gtk4::ToggleButton>,
+        #[template_child]
+        pub replace_row: TemplateChild<gtk4::Box>,
+        #[template_child]
+        pub replace_entry: TemplateChild<gtk4::Entry>,
+
+        pub view: RefCell<Option<sourceview5::View>>,
+        pub context: RefCell<Option<sourceview5::SearchContext>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for FindBar {
+        const NAME: &'static str = "FindBar";
+        type Type = super::FindBar;
+        type ParentType = gtk4::Box;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+            klass.bind_template_callbacks();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    #[gtk4::template_callbacks]
+    impl FindBar {
+        #[template_callback]
+        fn on_search_changed(&self) {
+            let text = self.find_entry.text();
+            if let Some(ref context) = *self.context.borrow() {
+                context.settings().set_search_text((!text.is_empty()).then_some(text.as_str()));
+            }
+        }
+
+        #[template_callback]
+        fn on_find_next(&self) {
+            self.obj().find(true);
+        }
+
+        #[template_callback]
+        fn on_find_previous(&self) {
+            self.obj().find(false);
+        }
+
+        #[template_callback]
+        fn on_replace_toggled(&self, btn: &gtk4::ToggleButton) {
+            self.replace_row.set_visible(btn.is_active());
+        }
+
+        #[template_callback]
+        fn on_replace(&self) {
+            self.obj().replace();
+        }
+
+        #[template_callback]
+        fn on_replace_all(&self) {
+            self.obj().replace_all();
+        }
+
+        #[template_callback]
+        fn on_close(&self) {
+            self.obj().hide();
+        }
+    }
+
+    impl ObjectImpl for FindBar {
+        fn constructed(&self) {
+            self.parent_constructed();
+            self.obj().setup_keys();
+        }
+    }
+
+    impl WidgetImpl for FindBar {}
+    impl BoxImpl for FindBar {}
+}
+
+glib::wrapper! {
+    /// Find and replace in a markdown editor, with every match highlighted
+    pub struct FindBar(ObjectSubclass<imp::FindBar>)
+        @extends gtk4::Box, gtk4::Widget,
+        @implements gtk4::Accessible, gtk4::Buildable;
+}
+
+impl FindBar {
+    /// Search `view`'s text from now on
+    pub fn attach(&self, view: &sourceview5::View) {
+        let buffer = match view.buffer().downcast::<sourceview5::Buffer>() {
+            Ok(buffer) => buffer,
+            Err(_) => return,
+        };
+        let imp = self.imp();
+
+        let settings = sourceview5::SearchSettings::new();
+        settings.set_wrap_around(true);
+        imp.case_btn.bind_property("active", &settings, "case-sensitive").sync_create().build();
+        imp.word_btn.bind_property("active", &settings, "at-word-boundaries").sync_create().build();
+        imp.regex_btn.bind_property("active", &settings, "regex-enabled").sync_create().build();
+
+        let context = sourceview5::SearchContext::new(&buffer, Some(&settings));
+        context.set_highlight(false);
+        let bar = self.downgrade();
+        context.connect_occurrences_count_notify(move |_| {
+            if let Some(bar) = bar.upgrade() {
+                bar.update_matches();
+            }
+        });
+        let bar = self.downgrade();
+        context.connect_regex_error_notify(move |_| {
+            if let Some(bar) = bar.upgrade() {
+                bar.update_matches();
+            }
+        });
+        let bar = self.downgrade();
+        buffer.connect_mark_set(move |_, _, mark| {
+            if mark.name().as_deref() == Some("insert") {
+                if let Some(bar) = bar.upgrade() {
+                    bar.update_matches();
+                }
+            }
+        });
+
+        imp.view.replace(Some(view.clone()));
+        imp.context.replace(Some(context));
+    }
+
+    /// Open the bar, with the replace row if `replace`, looking for the
+    /// selected text if there is some
+    pub fn show(&self, replace: bool) {
+        let imp = self.imp();
+        let view = match imp.view.borrow().clone() {
+            Some(view) => view,
+            None => return,
+        };
+
+        let buffer = view.buffer();
+        if let Some((start, end)) = buffer.selection_bounds() {
+            if start.line() == end.line() {
+                imp.find_entry.set_text(&buffer.text(&start, &end, false));
+            }
+        }
+
+        // Nothing to replace in a read-only library
+        imp.replace_btn.set_sensitive(view.is_editable());
+        imp.replace_btn.set_active(replace && view.is_editable());
+
+        imp.revealer.set_reveal_child(true);
+        if let Some(ref context) = *imp.context.borrow() {
+            context.set_highlight(true);
+        }
+        imp.find_entry.grab_focus();
+        imp.find_entry.select_region(0, -1);
+        self.update_matches();
+    }
+
+    /// Close the bar and go back to writing
+    pub fn hide(&self) {
+        let imp = self.imp();
+        imp.revealer.set_reveal_child(false);
+        if let Some(ref context) = *imp.context.borrow() {
+            context.set_highlight(false);
+        }
+        if let Some(ref view) = *imp.view.borrow() {
+            view.grab_focus();
+        }
+    }
+
+    pub fn is_shown(&self) -> bool {
+        self.imp().revealer.reveals_child()
+    }
+
+    fn setup_keys(&self) {
+        // Escape closes the bar from either entry, and Shift+Enter goes back
+        let keys = gtk4::EventControllerKey::new();
+        keys.set_propagation_phase(gtk4::PropagationPhase::Capture);
+        let bar = self.downgrade();
+        keys.connect_key_pressed(move |_, key, _, modifiers| {
+            let bar = match bar.upgrade() {
+                Some(bar) => bar,
+                None => return glib::Propagation::Proceed,
+            };
+            match key {
+                gdk::Key::Escape => {
+                    bar.hide();
+                    glib::Propagation::Stop
+                }
+                gdk::Key::Return | gdk::Key::KP_Enter
+                    if modifiers.contains(gdk::ModifierType::SHIFT_MASK) && bar.imp().find_entry.has_focus() =>
+                {
+                    bar.find(false);
+                    glib::Propagation::Stop
+                }
+                _ => glib::Propagation::Proceed,
+            }
+        });
+        self.add_controller(keys);
+    }
+
+    /// Select the next match after the selection, or the one before it
+    fn find(&self, forward: bool) {
+        let imp = self.imp();
+        let (view, context) = match (imp.view.borrow().clone(), imp.context.borrow().clone()) {
+            (Some(view), Some(context)) => (view, context),
+            _ => return,
+        };
+
+        let buffer = view.buffer();
+        let (start, end) = buffer
+            .selection_bounds()
+            .unwrap_or_else(|| {
+                let cursor = buffer.iter_at_mark(&buffer.get_insert());
+                (cursor, cursor)
+            });
+        let found = if forward { context.forward(&end) } else { context.backward(&start) };
+        if let Some((start, end, _)) = found {
+            buffer.select_range(&start, &end);
+            view.scroll_to_mark(&buffer.get_insert(), 0.1, false, 0.0, 0.0);
+        }
+    }
+
+    /// Replace the selected match and move on to the next
+    fn replace(&self) {
+        let imp = self.imp();
+        let (view, context) = match (imp.view.borrow().clone(), imp.context.borrow().clone()) {
+            (Some(view), Some(context)) => (view, context),
+            _ => return,
+        };
+        if !view.is_editable() {
+            return;
+        }
+
+        if let Some((mut start, mut end)) = view.buffer().selection_bounds() {
+            if context.occurrence_position(&start, &end) > 0 {
+                if let Err(e) = context.replace(&mut start, &mut end, &imp.replace_entry.text()) {
+                    imp.matches_label.set_text(e.message());
+                    return;
+                }
+            }
+        }
+        self.find(true);
+    }
+
+    /// Replace every match, as one step to undo
+    fn replace_all(&self) {
+        let imp = self.imp();
+        let (view, context) = match (imp.view.borrow().clone(), imp.context.borrow().clone()) {
+            (Some(view), Some(context)) => (view, context),
+            _ => return,
+        };
+        if !view.is_editable() {
+            return;
+        }
+
+        let count = context.occurrences_count();
+        match context.replace_all(&imp.replace_entry.text()) {
+            Ok(()) if count > 0 => imp.matches_label.set_text(&format!("Replaced {}", count)),
+            Ok(()) => {}
+            Err(e) => imp.matches_label.set_text(e.message()),
+        }
+    }
+
+    /// Show how many matches there are, and which one is selected
+    fn update_matches(&self) {
+        let imp = self.imp();
+        let (view, context) = match (imp.view.borrow().clone(), imp.context.borrow().clone()) {
+            (Some(view), Some(context)) => (view, context),
+            _ => return,
+        };
+
+        let label = &imp.matches_label;
+        if context.regex_error().is_some() {
+            label.set_text("Invalid pattern");
+            label.add_css_class("error");
+            return;
+        }
+        label.remove_css_class("error");
+
+        let count = context.occurrences_count();
+        let position = match view.buffer().selection_bounds() {
+            Some((start, end)) => context.occurrence_position(&start, &end),
+            None => 0,
+        };
+        let text = match count {
+            // Still counting, or nothing to look for
+            _ if imp.find_entry.text().is_empty() || count < 0 => String::new(),
+            0 => "No matches".to_string(),
+            _ if position > 0 => format!("{} of {}", position, count),
+            1 => "1 match".to_string(),
+            _ => format!("{} matches", count),
+        };
+        label.set_text(&text);
+    }
+}
+
+impl Default for FindBar {
+    fn default() -> Self {
+        glib::Object::builder().build()
+    }
+}
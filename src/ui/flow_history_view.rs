@@ -2,8 +2,9 @@ use adw::subclass::prelude::*;
 use gtk4::prelude::*;
 use gtk4::{glib, CompositeTemplate};
 use libadwaita as adw;
-use std::cell::RefCell;
+use std::cell::{OnceCell, RefCell};
 
+use super::MarkdownView;
 use crate::data::{Flow, FlowDocument};
 
 mod imp {
@@ -24,6 +25,7 @@ mod imp {
         pub flows: RefCell<Vec<Flow>>,
         pub selected_text: RefCell<String>,
         pub use_callback: RefCell<Option<Box<dyn Fn(String) + 'static>>>,
+        pub content: OnceCell<MarkdownView>,
     }
 
     #[glib::object_subclass]
@@ -90,11 +92,8 @@ impl FlowHistoryView {
     }
 
     fn setup_views(&self) {
-        // Setup content view as read-only
+        // Room around the rendered flow
         let content_view = &self.imp().content_view;
-        content_view.set_editable(false);
-        content_view.add_css_class("markdown-view");
-        content_view.set_wrap_mode(gtk4::WrapMode::Word);
         content_view.set_left_margin(24);
         content_view.set_right_margin(24);
         content_view.set_top_margin(16);
@@ -160,7 +159,24 @@ impl FlowHistoryView {
         let flows = self.imp().flows.borrow();
         if let Some(flow) = flows.get(index) {
             self.imp().selected_text.replace(flow.content.clone());
-            self.imp().content_view.buffer().set_text(&flow.content);
+            self.content().set_markdown(&flow.content);
+        }
+    }
+
+    /// The rendered flow, set up on first use
+    fn content(&self) -> &MarkdownView {
+        self.imp().content.get_or_init(|| MarkdownView::new(&self.imp().content_view))
+    }
+
+    /// Select the flow with this id, returning whether it is in the list
+    pub fn select_flow(&self, flow_id: &str) -> bool {
+        let index = self.imp().flows.borrow().iter().position(|f| f.id == flow_id);
+        match index.and_then(|i| self.imp().flow_list.row_at_index(i as i32)) {
+            Some(row) => {
+                self.imp().flow_list.select_row(Some(&row));
+                true
+            }
+            None => false,
         }
     }
 
@@ -172,7 +188,7 @@ impl FlowHistoryView {
 fn format_duration(seconds: u64) -> String {
     let hours = seconds / 3600;
     let minutes = (seconds % 3600) / 60;
-    
+
     if hours > 0 {
         format!("{}h {}m", hours, minutes)
     } else {
@@ -2,9 +2,11 @@ use adw::subclass::prelude::*;
 use gtk4::prelude::*;
 use gtk4::{glib, CompositeTemplate};
 use libadwaita as adw;
-use std::cell::{Cell, RefCell};
+use std::cell::{Cell, OnceCell, RefCell};
 
+use super::SpellChecker;
 use crate::data::Flow;
+use abbey_core::utils::text_stats;
 
 mod imp {
     use super::*;
@@ -15,7 +17,7 @@ mod imp {
         #[template_child]
         pub timer_label: TemplateChild<gtk4::Label>,
         #[template_child]
-        pub editor: TemplateChild<gtk4::TextView>,
+        pub editor: TemplateChild<sourceview5::View>,
         #[template_child]
         pub word_count_label: TemplateChild<gtk4::Label>,
         #[template_child]
@@ -29,6 +31,7 @@ mod imp {
         pub elapsed_seconds: Cell<u64>,
         pub is_paused: Cell<bool>,
         pub flow_ended_callback: RefCell<Option<Box<dyn Fn(Flow) + 'static>>>,
+        pub spell_checker: OnceCell<SpellChecker>,
     }
 
     #[glib::object_subclass]
@@ -38,6 +41,7 @@ mod imp {
         type ParentType = gtk4::Box;
 
         fn class_init(klass: &mut Self::Class) {
+            sourceview5::View::ensure_type();
             klass.bind_template();
             klass.bind_template_callbacks();
         }
@@ -98,6 +102,10 @@ impl FlowView {
         editor.add_css_class("flow-editor");
         editor.add_css_class("editor-view");
         
+        // Markdown highlighting, auto-pairing and list continuation
+        super::editor::setup_markdown_editor(editor);
+        self.spell_checker();
+        
         // Configure editor for focused writing
         editor.set_wrap_mode(gtk4::WrapMode::Word);
         
@@ -105,7 +113,7 @@ impl FlowView {
         let view = self.clone();
         editor.buffer().connect_changed(move |buffer| {
             let text = buffer.text(&buffer.start_iter(), &buffer.end_iter(), false);
-            let word_count = text.split_whitespace().count();
+            let word_count = text_stats::word_count(&text);
             view.imp().word_count_label.set_text(&format!("{} words", word_count));
         });
     }
@@ -207,6 +215,15 @@ impl FlowView {
         }
     }
 
+    /// Go through the flow's misspellings one by one
+    pub fn check_spelling(&self) {
+        self.spell_checker().review();
+    }
+
+    fn spell_checker(&self) -> &SpellChecker {
+        self.imp().spell_checker.get_or_init(|| SpellChecker::new(&*self.imp().editor))
+    }
+
     pub fn connect_flow_ended<F: Fn(Flow) + 'static>(&self, callback: F) {
         self.imp().flow_ended_callback.replace(Some(Box::new(callback)));
     }
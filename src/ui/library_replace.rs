@@ -0,0 +1,277 @@
+use adw::subclass::prelude::*;
+use adw::prelude::*;
+use gtk4::{glib, CompositeTemplate};
+use libadwaita as adw;
+use std::cell::RefCell;
+
+use crate::data::{Composition, Project};
+use abbey_core::replace::{self, Hit, Location, Matcher, Query};
+
+type ReplaceCallback = Box<dyn Fn(&Matcher, &str, &[Hit]) + 'static>;
+
+mod imp {
+    use super::*;
+
+    #[derive(Default, CompositeTemplate)]
+    #[template(file = "library_replace.ui")]
+    pub struct LibraryReplace {
+        #[template_child]
+        pub find_entry: TemplateChild<gtk4::SearchEntry>,
+        #[template_child]
+        pub replace_entry: TemplateChild<gtk4::Entry>,
+        #[template_child]
+        pub case_btn: TemplateChild<gtk4::ToggleButton>,
+        #[template_child]
+        pub word_btn: TemplateChild<gtk4::ToggleButton>,
+        #[template_child]
+        pub regex_btn: TemplateChild<gtk4::ToggleButton>,
+        #[template_child]
+        pub summary_label: TemplateChild<gtk4::Label>,
+        #[template_child]
+        pub hits_list: TemplateChild<gtk4::ListBox>,
+        #[template_child]
+        pub apply_btn: TemplateChild<gtk4::Button>,
+
+        pub compositions: RefCell<Vec<Composition>>,
+        pub projects: RefCell<Vec<Project>>,
+        pub matcher: RefCell<Option<Matcher>>,
+        /// Every match, with the check button that picks it
+        pub hits: RefCell<Vec<(Hit, gtk4::CheckButton)>>,
+        pub replace_callback: RefCell<Option<ReplaceCallback>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for LibraryReplace {
+        const NAME: &'static str = "LibraryReplace";
+        type Type = super::LibraryReplace;
+        type ParentType = adw::Window;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+            klass.bind_template_callbacks();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    #[gtk4::template_callbacks]
+    impl LibraryReplace {
+        #[template_callback]
+        fn on_changed(&self) {
+            self.obj().refresh();
+        }
+
+        #[template_callback]
+        fn on_apply(&self) {
+            self.obj().apply();
+        }
+    }
+
+    impl ObjectImpl for LibraryReplace {}
+    impl WidgetImpl for LibraryReplace {}
+    impl WindowImpl for LibraryReplace {}
+    impl AdwWindowImpl for LibraryReplace {}
+}
+
+glib::wrapper! {
+    pub struct LibraryReplace(ObjectSubclass<imp::LibraryReplace>)
+        @extends adw::Window, gtk4::Window, gtk4::Widget,
+        @implements gtk4::Accessible, gtk4::Buildable;
+}
+
+impl LibraryReplace {
+    /// Look through these compositions and projects, which nothing else
+    /// changes while the dialog is open
+    pub fn new(compositions: Vec<Composition>, projects: Vec<Project>) -> Self {
+        let dialog: Self = glib::Object::builder().build();
+        dialog.imp().compositions.replace(compositions);
+        dialog.imp().projects.replace(projects);
+        dialog.refresh();
+        dialog
+    }
+
+    pub fn present(&self, parent: Option<&impl IsA<gtk4::Window>>) {
+        if let Some(parent) = parent {
+            self.set_transient_for(Some(parent));
+        }
+        gtk4::prelude::GtkWindowExt::present(self);
+        self.imp().find_entry.grab_focus();
+    }
+
+    /// Called with the query, the replacement and the matches picked to replace
+    pub fn connect_replace<F: Fn(&Matcher, &str, &[Hit]) + 'static>(&self, callback: F) {
+        self.imp().replace_callback.replace(Some(Box::new(callback)));
+    }
+
+    /// Find every match again and list what replacing it would do
+    fn refresh(&self) {
+        let imp = self.imp();
+        imp.hits_list.remove_all();
+        imp.hits.borrow_mut().clear();
+        imp.matcher.replace(None);
+        imp.summary_label.remove_css_class("error");
+
+        let query = Query {
+            pattern: imp.find_entry.text().to_string(),
+            case_sensitive: imp.case_btn.is_active(),
+            whole_word: imp.word_btn.is_active(),
+            regex: imp.regex_btn.is_active(),
+        };
+        if query.pattern.is_empty() {
+            imp.summary_label.set_text("Archived compositions are left alone");
+            self.update_apply();
+            return;
+        }
+        let matcher = match Matcher::new(&query) {
+            Ok(matcher) => matcher,
+            Err(e) => {
+                imp.summary_label.set_text(&format!("Invalid pattern: {}", e));
+                imp.summary_label.add_css_class("error");
+                self.update_apply();
+                return;
+            }
+        };
+
+        let replacement = imp.replace_entry.text();
+        let hits = replace::find_in_library(
+            &matcher,
+            &replacement,
+            &imp.compositions.borrow(),
+            &imp.projects.borrow(),
+        );
+        let places = hits
+            .iter()
+            .map(|hit| owner(&hit.location))
+            .collect::<std::collections::HashSet<_>>()
+            .len();
+        imp.summary_label.set_text(&match hits.len() {
+            0 => "No matches".to_string(),
+            1 => "1 match".to_string(),
+            n if places == 1 => format!("{} matches in one place", n),
+            n => format!("{} matches in {} places", n, places),
+        });
+
+        for hit in hits {
+            let check = gtk4::CheckButton::builder().active(true).valign(gtk4::Align::Center).build();
+            let dialog = self.clone();
+            check.connect_toggled(move |_| dialog.update_apply());
+            imp.hits_list.append(&hit_row(&hit, &check));
+            imp.hits.borrow_mut().push((hit, check));
+        }
+        imp.matcher.replace(Some(matcher));
+        self.update_apply();
+    }
+
+    fn update_apply(&self) {
+        let imp = self.imp();
+        let picked = imp.hits.borrow().iter().filter(|(_, check)| check.is_active()).count();
+        imp.apply_btn.set_sensitive(picked > 0);
+        imp.apply_btn.set_label(&match picked {
+            0 | 1 => "Replace".to_string(),
+            n => format!("Replace {}", n),
+        });
+    }
+
+    fn apply(&self) {
+        let imp = self.imp();
+        let matcher = match imp.matcher.borrow().clone() {
+            Some(matcher) => matcher,
+            None => return,
+        };
+        let picked: Vec<Hit> = imp
+            .hits
+            .borrow()
+            .iter()
+            .filter(|(_, check)| check.is_active())
+            .map(|(hit, _)| hit.clone())
+            .collect();
+        if picked.is_empty() {
+            return;
+        }
+
+        self.close();
+        if let Some(ref callback) = *imp.replace_callback.borrow() {
+            callback(&matcher, &imp.replace_entry.text(), &picked);
+        }
+    }
+}
+
+/// The composition or project a match is in
+fn owner(location: &Location) -> &str {
+    match location {
+        Location::CompositionTitle(id)
+        | Location::CompositionContent(id)
+        | Location::Note(id, _)
+        | Location::ProjectTitle(id)
+        | Location::ProjectDescription(id) => id,
+    }
+}
+
+/// A match, its line struck through where it is and its replacement in bold
+fn hit_row(hit: &Hit, check: &gtk4::CheckButton) -> gtk4::ListBoxRow {
+    let place = match hit.location {
+        Location::CompositionTitle(_) => "title",
+        Location::CompositionContent(_) => "text",
+        Location::Note(..) => "note",
+        Location::ProjectTitle(_) => "project title",
+        Location::ProjectDescription(_) => "project description",
+    };
+    let heading = gtk4::Label::builder()
+        .label(format!("{} · {}", hit.name, place))
+        .xalign(0.0)
+        .ellipsize(gtk4::pango::EllipsizeMode::End)
+        .css_classes(["caption-heading", "dim-label"])
+        .build();
+    let before = gtk4::Label::builder()
+        .label(marked(&hit.before, &hit.matched, "s"))
+        .use_markup(true)
+        .xalign(0.0)
+        .wrap(true)
+        .build();
+    let after = gtk4::Label::builder()
+        .label(marked(&hit.after, &hit.replaced, "b"))
+        .use_markup(true)
+        .xalign(0.0)
+        .wrap(true)
+        .build();
+
+    let text = gtk4::Box::new(gtk4::Orientation::Vertical, 2);
+    text.append(&heading);
+    text.append(&before);
+    text.append(&after);
+
+    let content = gtk4::Box::builder()
+        .spacing(12)
+        .margin_start(12)
+        .margin_end(12)
+        .margin_top(8)
+        .margin_bottom(8)
+        .build();
+    content.append(check);
+    content.append(&text);
+
+    gtk4::ListBoxRow::builder().child(&content).activatable(false).build()
+}
+
+/// Markup for `text` with the chars in `range` inside `tag`
+fn marked(text: &str, range: &std::ops::Range<usize>, tag: &str) -> String {
+    let part = |skip: usize, take: usize| -> String {
+        let part: String = text.chars().skip(skip).take(take).collect();
+        glib::markup_escape_text(&part).to_string()
+    };
+    format!(
+        "{}<{tag}>{}</{tag}>{}",
+        part(0, range.start),
+        part(range.start, range.len()),
+        part(range.end, usize::MAX),
+        tag = tag
+    )
+}
+
+impl Default for LibraryReplace {
+    fn default() -> Self {
+        glib::Object::builder().build()
+    }
+}
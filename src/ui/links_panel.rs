@@ -0,0 +1,430 @@
+use adw::subclass::prelude::*;
+use gtk4::prelude::*;
+use gtk4::{gdk, glib, pango, CompositeTemplate};
+use libadwaita as adw;
+use std::cell::RefCell;
+use std::time::Duration;
+
+use abbey_core::links::{self, Backlink, Source};
+
+const TAG_NAME: &str = "wiki-link";
+
+/// How long typing pauses before links are underlined again
+const HIGHLIGHT_DELAY: Duration = Duration::from_millis(300);
+
+/// How many titles are offered while a link is typed
+const MAX_COMPLETIONS: usize = 8;
+
+type OpenCallback = Box<dyn Fn(&str) + 'static>;
+type BacklinkCallback = Box<dyn Fn(&Source) + 'static>;
+
+mod imp {
+    use super::*;
+
+    #[derive(Default, CompositeTemplate)]
+    #[template(file = "links_panel.ui")]
+    pub struct LinksPanel {
+        #[template_child]
+        pub summary_label: TemplateChild<gtk4::Label>,
+        #[template_child]
+        pub backlinks_list: TemplateChild<gtk4::ListBox>,
+
+        pub view: RefCell<Option<sourceview5::View>>,
+        /// Titles of the compositions that can be linked to
+        pub titles: RefCell<Vec<String>>,
+        pub backlinks: RefCell<Vec<Backlink>>,
+        /// Titles completing the link being typed, under the cursor
+        pub popover: RefCell<Option<(gtk4::Popover, gtk4::ListBox)>>,
+        pub completions: RefCell<Vec<String>>,
+        pub pending: RefCell<Option<glib::SourceId>>,
+        pub open_callback: RefCell<Option<OpenCallback>>,
+        pub backlink_callback: RefCell<Option<BacklinkCallback>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for LinksPanel {
+        const NAME: &'static str = "LinksPanel";
+        type Type = super::LinksPanel;
+        type ParentType = gtk4::Box;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+            klass.bind_template_callbacks();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    #[gtk4::template_callbacks]
+    impl LinksPanel {
+        #[template_callback]
+        fn on_row_activated(&self, row: &gtk4::ListBoxRow) {
+            let source = usize::try_from(row.index())
+                .ok()
+                .and_then(|index| self.backlinks.borrow().get(index).map(|backlink| backlink.source.clone()));
+            if let Some(source) = source {
+                if let Some(ref callback) = *self.backlink_callback.borrow() {
+                    callback(&source);
+                }
+            }
+        }
+    }
+
+    impl ObjectImpl for LinksPanel {
+        fn constructed(&self) {
+            self.parent_constructed();
+            self.obj().show_backlinks();
+        }
+    }
+
+    impl WidgetImpl for LinksPanel {}
+    impl BoxImpl for LinksPanel {}
+}
+
+glib::wrapper! {
+    /// Wiki links in a markdown editor: underlined, opened with Ctrl+click and
+    /// completed from composition titles as they're typed, with the places
+    /// that link to the composition listed
+    pub struct LinksPanel(ObjectSubclass<imp::LinksPanel>)
+        @extends gtk4::Box, gtk4::Widget,
+        @implements gtk4::Accessible, gtk4::Buildable;
+}
+
+impl LinksPanel {
+    /// Follow links in `view`'s text from now on
+    pub fn attach(&self, view: &sourceview5::View) {
+        let buffer = view.buffer();
+        let tag = gtk4::TextTag::builder()
+            .name(TAG_NAME)
+            .underline(pango::Underline::Single)
+            .foreground_rgba(&gdk::RGBA::new(0.21, 0.52, 0.89, 1.0))
+            .build();
+        buffer.tag_table().add(&tag);
+
+        let panel = self.downgrade();
+        buffer.connect_changed(move |_| {
+            if let Some(panel) = panel.upgrade() {
+                panel.schedule_highlight();
+                panel.update_completions(true);
+            }
+        });
+        let panel = self.downgrade();
+        buffer.connect_cursor_position_notify(move |_| {
+            if let Some(panel) = panel.upgrade() {
+                panel.update_completions(false);
+            }
+        });
+
+        // Ctrl+click opens the link under the pointer
+        let click = gtk4::GestureClick::new();
+        click.set_button(gdk::BUTTON_PRIMARY);
+        click.set_propagation_phase(gtk4::PropagationPhase::Capture);
+        let panel = self.downgrade();
+        click.connect_pressed(move |gesture, _, x, y| {
+            if !gesture.current_event_state().contains(gdk::ModifierType::CONTROL_MASK) {
+                return;
+            }
+            if let Some(panel) = panel.upgrade() {
+                if panel.open_link_at(x, y) {
+                    gesture.set_state(gtk4::EventSequenceState::Claimed);
+                }
+            }
+        });
+        view.add_controller(click);
+
+        // Up, Down, Enter, Tab and Escape go to the titles while they show
+        let keys = gtk4::EventControllerKey::new();
+        keys.set_propagation_phase(gtk4::PropagationPhase::Capture);
+        let panel = self.downgrade();
+        keys.connect_key_pressed(move |_, key, _, _| match panel.upgrade() {
+            Some(panel) if panel.completing() => panel.completion_key(key),
+            _ => glib::Propagation::Proceed,
+        });
+        view.add_controller(keys);
+
+        let list = gtk4::ListBox::builder().selection_mode(gtk4::SelectionMode::Browse).build();
+        let panel = self.downgrade();
+        list.connect_row_activated(move |_, row| {
+            if let Some(panel) = panel.upgrade() {
+                panel.complete(row.index());
+            }
+        });
+        let popover = gtk4::Popover::builder()
+            .child(&list)
+            .autohide(false)
+            .has_arrow(false)
+            .can_focus(false)
+            .position(gtk4::PositionType::Bottom)
+            .build();
+        popover.set_parent(view);
+
+        self.imp().popover.replace(Some((popover, list)));
+        self.imp().view.replace(Some(view.clone()));
+        self.schedule_highlight();
+    }
+
+    /// The titles links can be completed with
+    pub fn set_titles(&self, titles: Vec<String>) {
+        self.imp().titles.replace(titles);
+    }
+
+    /// List the places that link to the composition
+    pub fn set_backlinks(&self, backlinks: Vec<Backlink>) {
+        self.imp().backlinks.replace(backlinks);
+        self.show_backlinks();
+    }
+
+    /// Called with the target of a link Ctrl+clicked in the editor
+    pub fn connect_open<F: Fn(&str) + 'static>(&self, callback: F) {
+        self.imp().open_callback.replace(Some(Box::new(callback)));
+    }
+
+    /// Called with where a backlink picked in the list is from
+    pub fn connect_backlink_activated<F: Fn(&Source) + 'static>(&self, callback: F) {
+        self.imp().backlink_callback.replace(Some(Box::new(callback)));
+    }
+
+    fn show_backlinks(&self) {
+        let imp = self.imp();
+        imp.backlinks_list.remove_all();
+        let backlinks = imp.backlinks.borrow();
+        imp.summary_label.set_text(&match backlinks.len() {
+            0 => "Nothing links here yet. Type [[ in another composition to link to this one".to_string(),
+            1 => "Linked from 1 place".to_string(),
+            n => format!("Linked from {} places", n),
+        });
+        for backlink in backlinks.iter() {
+            imp.backlinks_list.append(&backlink_row(backlink));
+        }
+    }
+
+    fn schedule_highlight(&self) {
+        if let Some(source_id) = self.imp().pending.take() {
+            source_id.remove();
+        }
+        let panel = self.downgrade();
+        let source_id = glib::timeout_add_local_once(HIGHLIGHT_DELAY, move || {
+            if let Some(panel) = panel.upgrade() {
+                panel.imp().pending.replace(None);
+                panel.highlight();
+            }
+        });
+        self.imp().pending.replace(Some(source_id));
+    }
+
+    /// Underline every link in the text
+    fn highlight(&self) {
+        let buffer = match self.imp().view.borrow().as_ref() {
+            Some(view) => view.buffer(),
+            None => return,
+        };
+        let (start, end) = buffer.bounds();
+        buffer.remove_tag_by_name(TAG_NAME, &start, &end);
+
+        let text = buffer.text(&start, &end, false);
+        let (mut byte, mut offset) = (0, 0);
+        let mut char_offset = |to: usize| {
+            offset += text[byte..to].chars().count() as i32;
+            byte = to;
+            offset
+        };
+        for link in links::find(&text) {
+            let from = buffer.iter_at_offset(char_offset(link.range.start));
+            let to = buffer.iter_at_offset(char_offset(link.range.end));
+            buffer.apply_tag_by_name(TAG_NAME, &from, &to);
+        }
+    }
+
+    /// Open the link at a point of the view; false if there's none there
+    fn open_link_at(&self, x: f64, y: f64) -> bool {
+        let view = match self.imp().view.borrow().clone() {
+            Some(view) => view,
+            None => return false,
+        };
+        let (x, y) = view.window_to_buffer_coords(gtk4::TextWindowType::Widget, x as i32, y as i32);
+        let iter = match view.iter_at_location(x, y) {
+            Some(iter) => iter,
+            None => return false,
+        };
+        let buffer = view.buffer();
+        let at = buffer.text(&buffer.start_iter(), &iter, false).len();
+        let link = match links::link_at(&buffer.text(&buffer.start_iter(), &buffer.end_iter(), false), at) {
+            Some(link) => link,
+            None => return false,
+        };
+        if let Some(ref callback) = *self.imp().open_callback.borrow() {
+            callback(&link.target);
+        }
+        true
+    }
+
+    /// The title typed so far into a link left open at the cursor, as char
+    /// offsets
+    fn partial(&self) -> Option<(i32, String)> {
+        let view = self.imp().view.borrow().clone()?;
+        let buffer = view.buffer();
+        let cursor = buffer.iter_at_mark(&buffer.get_insert());
+        let mut line_start = cursor;
+        line_start.set_line_offset(0);
+        let before = buffer.text(&line_start, &cursor, false);
+        let typed = &before[links::partial_at(&before, before.len())?];
+        Some((cursor.offset() - typed.chars().count() as i32, typed.to_string()))
+    }
+
+    /// Offer titles for the link being typed, or stop offering them once the
+    /// cursor leaves it; `typed` when the text changed, as only typing starts
+    /// offering them
+    fn update_completions(&self, typed: bool) {
+        let imp = self.imp();
+        let (popover, list) = match imp.popover.borrow().clone() {
+            Some(popover) => popover,
+            None => return,
+        };
+        let view = match imp.view.borrow().clone() {
+            Some(view) if view.is_editable() && view.has_focus() => view,
+            _ => {
+                popover.popdown();
+                return;
+            }
+        };
+        let partial = match self.partial() {
+            Some((_, partial)) if typed || popover.is_visible() => partial,
+            _ => {
+                popover.popdown();
+                return;
+            }
+        };
+
+        let titles = imp.titles.borrow();
+        let completions: Vec<String> = links::complete(&partial, titles.iter().map(String::as_str))
+            .into_iter()
+            .filter(|title| !title.contains([']', '|', '[']))
+            .take(MAX_COMPLETIONS)
+            .map(str::to_string)
+            .collect();
+        if completions.is_empty() {
+            popover.popdown();
+            return;
+        }
+
+        list.remove_all();
+        for title in &completions {
+            let label = gtk4::Label::builder()
+                .label(title.as_str())
+                .xalign(0.0)
+                .ellipsize(pango::EllipsizeMode::End)
+                .max_width_chars(40)
+                .margin_start(6)
+                .margin_end(6)
+                .margin_top(4)
+                .margin_bottom(4)
+                .build();
+            list.append(&label);
+        }
+        list.select_row(list.row_at_index(0).as_ref());
+        imp.completions.replace(completions);
+
+        let buffer = view.buffer();
+        let location = view.iter_location(&buffer.iter_at_mark(&buffer.get_insert()));
+        let (x, y) = view.buffer_to_window_coords(gtk4::TextWindowType::Widget, location.x(), location.y());
+        popover.set_pointing_to(Some(&gdk::Rectangle::new(x, y, 1, location.height())));
+        popover.popup();
+    }
+
+    fn completing(&self) -> bool {
+        self.imp().popover.borrow().as_ref().is_some_and(|(popover, _)| popover.is_visible())
+    }
+
+    fn completion_key(&self, key: gdk::Key) -> glib::Propagation {
+        let (popover, list) = match self.imp().popover.borrow().clone() {
+            Some(popover) => popover,
+            None => return glib::Propagation::Proceed,
+        };
+        let selected = list.selected_row().map_or(0, |row| row.index());
+        let count = self.imp().completions.borrow().len() as i32;
+        match key {
+            gdk::Key::Down => list.select_row(list.row_at_index((selected + 1).min(count - 1)).as_ref()),
+            gdk::Key::Up => list.select_row(list.row_at_index((selected - 1).max(0)).as_ref()),
+            gdk::Key::Return | gdk::Key::KP_Enter | gdk::Key::Tab => self.complete(selected),
+            gdk::Key::Escape => popover.popdown(),
+            _ => return glib::Propagation::Proceed,
+        }
+        glib::Propagation::Stop
+    }
+
+    /// Finish the link being typed with the `index`th title offered
+    fn complete(&self, index: i32) {
+        let imp = self.imp();
+        let title = match usize::try_from(index).ok().and_then(|index| imp.completions.borrow().get(index).cloned()) {
+            Some(title) => title,
+            None => return,
+        };
+        if let Some((ref popover, _)) = *imp.popover.borrow() {
+            popover.popdown();
+        }
+        let (start, _) = match self.partial() {
+            Some(partial) => partial,
+            None => return,
+        };
+        let view = match imp.view.borrow().clone() {
+            Some(view) => view,
+            None => return,
+        };
+
+        let buffer = view.buffer();
+        buffer.begin_user_action();
+        let mut from = buffer.iter_at_offset(start);
+        let mut cursor = buffer.iter_at_mark(&buffer.get_insert());
+        buffer.delete(&mut from, &mut cursor);
+        buffer.insert(&mut from, &title);
+        // The closing brackets may have been put in already as the opening
+        // ones were typed
+        let mut after = from;
+        after.forward_chars(2);
+        if buffer.text(&from, &after, false) != "]]" {
+            buffer.insert(&mut from, "]]");
+        } else {
+            from = after;
+        }
+        buffer.place_cursor(&from);
+        buffer.end_user_action();
+    }
+}
+
+/// What links here and the line it's on
+fn backlink_row(backlink: &Backlink) -> gtk4::ListBoxRow {
+    let vbox = gtk4::Box::builder()
+        .orientation(gtk4::Orientation::Vertical)
+        .spacing(4)
+        .margin_start(12)
+        .margin_end(12)
+        .margin_top(10)
+        .margin_bottom(10)
+        .build();
+    let name = gtk4::Label::builder()
+        .label(backlink.name.as_str())
+        .xalign(0.0)
+        .ellipsize(pango::EllipsizeMode::End)
+        .css_classes(["caption-heading"])
+        .build();
+    let context = gtk4::Label::builder()
+        .label(backlink.context.as_str())
+        .xalign(0.0)
+        .wrap(true)
+        .wrap_mode(pango::WrapMode::WordChar)
+        .lines(3)
+        .ellipsize(pango::EllipsizeMode::End)
+        .css_classes(["dim-label"])
+        .build();
+    vbox.append(&name);
+    vbox.append(&context);
+    gtk4::ListBoxRow::builder().child(&vbox).build()
+}
+
+impl Default for LinksPanel {
+    fn default() -> Self {
+        glib::Object::builder().build()
+    }
+}
@@ -1,154 +1,224 @@
 use gtk4::prelude::*;
-use pulldown_cmark::{Parser, Event, Tag, TagEnd, HeadingLevel};
+use gtk4::{glib, pango};
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Duration;
 
-/// Renders markdown content to a GtkTextView
+use abbey_core::utils::render::{self, Block, Style};
+
+/// How long typing has to pause before a scheduled render runs
+const RENDER_DELAY: Duration = Duration::from_millis(250);
+
+/// Indentation per level of list or quote nesting
+const INDENT_STEP: i32 = 24;
+
+/// Renders markdown into a read-only text view through tags. Re-rendering only
+/// redraws the blocks that changed, so the view keeps its place while you type.
+#[derive(Clone)]
 pub struct MarkdownView {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
     text_view: gtk4::TextView,
+    /// What the buffer currently shows
+    blocks: RefCell<Vec<Block>>,
+    pending: RefCell<Option<glib::SourceId>>,
 }
 
 impl MarkdownView {
-    pub fn new() -> Self {
-        let text_view = gtk4::TextView::builder()
-            .editable(false)
-            .wrap_mode(gtk4::WrapMode::Word)
-            .left_margin(24)
-            .right_margin(24)
-            .top_margin(16)
-            .bottom_margin(16)
-            .build();
-        
+    /// Render into an existing text view, such as a template child
+    pub fn new(text_view: &gtk4::TextView) -> Self {
+        text_view.set_editable(false);
+        text_view.set_wrap_mode(gtk4::WrapMode::Word);
         text_view.add_css_class("markdown-view");
+        create_tags(text_view);
         
-        Self { text_view }
+        Self {
+            inner: Rc::new(Inner {
+                text_view: text_view.clone(),
+                blocks: RefCell::new(Vec::new()),
+                pending: RefCell::new(None),
+            }),
+        }
     }
 
+    /// Render right away, replacing any render still waiting
     pub fn set_markdown(&self, markdown: &str) {
-        let buffer = self.text_view.buffer();
-        buffer.set_text("");
-        
-        let mut iter = buffer.end_iter();
-        
-        // Create tags for styling
-        let tag_table = buffer.tag_table();
-        
-        let h1_tag = gtk4::TextTag::builder()
-            .name("h1")
-            .scale(2.0)
-            .weight(700)
-            .pixels_below_lines(16)
-            .build();
-        tag_table.add(&h1_tag);
-        
-        let h2_tag = gtk4::TextTag::builder()
-            .name("h2")
-            .scale(1.5)
-            .weight(600)
-            .pixels_above_lines(24)
-            .pixels_below_lines(12)
-            .build();
-        tag_table.add(&h2_tag);
-        
-        let h3_tag = gtk4::TextTag::builder()
-            .name("h3")
-            .scale(1.25)
-            .weight(600)
-            .pixels_above_lines(16)
-            .pixels_below_lines(8)
-            .build();
-        tag_table.add(&h3_tag);
-        
-        let bold_tag = gtk4::TextTag::builder()
-            .name("bold")
-            .weight(700)
-            .build();
-        tag_table.add(&bold_tag);
+        self.cancel_pending();
+        self.show(render::render(markdown));
+    }
+
+    /// Render once typing pauses; each call restarts the wait
+    pub fn schedule(&self, markdown: &str) {
+        self.cancel_pending();
         
-        let italic_tag = gtk4::TextTag::builder()
-            .name("italic")
-            .style(pango::Style::Italic)
-            .build();
-        tag_table.add(&italic_tag);
+        let view = self.clone();
+        let markdown = markdown.to_string();
+        let source_id = glib::timeout_add_local_once(RENDER_DELAY, move || {
+            view.inner.pending.replace(None);
+            view.show(render::render(&markdown));
+        });
+        self.inner.pending.replace(Some(source_id));
+    }
+
+    /// Where a char offset in `markdown` shows in the view
+    pub fn preview_position(&self, markdown: &str, source: usize) -> usize {
+        render::preview_position(markdown, &self.inner.blocks.borrow(), source)
+    }
+
+    /// Where a char offset in the view came from in `markdown`
+    pub fn source_position(&self, markdown: &str, preview: usize) -> usize {
+        render::source_position(markdown, &self.inner.blocks.borrow(), preview)
+    }
+
+    fn cancel_pending(&self) {
+        if let Some(source_id) = self.inner.pending.take() {
+            source_id.remove();
+        }
+    }
+
+    /// Swap the blocks that changed into the buffer
+    fn show(&self, blocks: Vec<Block>) {
+        let buffer = self.inner.text_view.buffer();
+        let mut shown = self.inner.blocks.borrow_mut();
         
-        let code_tag = gtk4::TextTag::builder()
-            .name("code")
-            .family("JetBrains Mono")
-            .background("rgba(0,0,0,0.05)")
-            .build();
-        tag_table.add(&code_tag);
+        // Start over if something else wrote to the buffer
+        let length = |blocks: &[Block]| blocks.iter().map(Block::char_len).sum::<usize>() as i32;
+        if length(&shown) != buffer.char_count() {
+            buffer.set_text("");
+            shown.clear();
+        }
         
-        let quote_tag = gtk4::TextTag::builder()
-            .name("quote")
-            .style(pango::Style::Italic)
-            .left_margin(32)
-            .build();
-        tag_table.add(&quote_tag);
+        let splice = render::diff(&shown, &blocks);
+        let start = length(&shown[..splice.start]);
+        let old_end = start + length(&shown[splice.start..splice.old_end]);
         
-        // Parse and render markdown
-        let parser = Parser::new(markdown);
-        let mut current_tags: Vec<String> = Vec::new();
+        let mut from = buffer.iter_at_offset(start);
+        let mut to = buffer.iter_at_offset(old_end);
+        buffer.delete(&mut from, &mut to);
         
-        for event in parser {
-            match event {
-                Event::Start(tag) => {
-                    match tag {
-                        Tag::Heading { level, .. } => {
-                            let tag_name = match level {
-                                HeadingLevel::H1 => "h1",
-                                HeadingLevel::H2 => "h2",
-                                _ => "h3",
-                            };
-                            current_tags.push(tag_name.to_string());
-                        }
-                        Tag::Strong => current_tags.push("bold".to_string()),
-                        Tag::Emphasis => current_tags.push("italic".to_string()),
-                        Tag::CodeBlock(_) => current_tags.push("code".to_string()),
-                        Tag::BlockQuote(_) => current_tags.push("quote".to_string()),
-                        _ => {}
-                    }
-                }
-                Event::End(tag) => {
-                    match tag {
-                        TagEnd::Heading(_) | TagEnd::Strong | TagEnd::Emphasis | 
-                        TagEnd::CodeBlock | TagEnd::BlockQuote(_) => {
-                            current_tags.pop();
-                        }
-                        TagEnd::Paragraph => {
-                            buffer.insert(&mut iter, "\n\n");
-                        }
-                        _ => {}
-                    }
-                }
-                Event::Text(text) => {
-                    let start_offset = iter.offset();
-                    buffer.insert(&mut iter, &text);
-                    
-                    // Apply tags
-                    for tag_name in &current_tags {
-                        if let Some(tag) = tag_table.lookup(tag_name) {
-                            let start = buffer.iter_at_offset(start_offset);
-                            let end = buffer.end_iter();
-                            buffer.apply_tag(&tag, &start, &end);
-                        }
-                    }
-                }
-                Event::SoftBreak | Event::HardBreak => {
-                    buffer.insert(&mut iter, "\n");
-                }
-                Event::Rule => {
-                    buffer.insert(&mut iter, "\n―――\n");
-                }
-                _ => {}
+        let mut iter = buffer.iter_at_offset(start);
+        for block in &blocks[splice.start..splice.new_end] {
+            let block_start = iter.offset();
+            buffer.insert(&mut iter, &block.text);
+            
+            for span in &block.spans {
+                let tag = match self.tag(&span.style) {
+                    Some(tag) => tag,
+                    None => continue,
+                };
+                let span_start = buffer.iter_at_offset(block_start + span.start as i32);
+                let span_end = buffer.iter_at_offset(block_start + span.end as i32);
+                buffer.apply_tag(&tag, &span_start, &span_end);
             }
+            iter = buffer.iter_at_offset(block_start + block.char_len() as i32);
         }
+        
+        *shown = blocks;
     }
 
-    pub fn widget(&self) -> &gtk4::TextView {
-        &self.text_view
+    fn tag(&self, style: &Style) -> Option<gtk4::TextTag> {
+        let tag_table = self.inner.text_view.buffer().tag_table();
+        let name = match style {
+            Style::Heading(level) => format!("h{}", level),
+            Style::Strong => "bold".to_string(),
+            Style::Emphasis => "italic".to_string(),
+            Style::Strikethrough => "strikethrough".to_string(),
+            Style::Code => "code".to_string(),
+            Style::CodeBlock => "code-block".to_string(),
+            Style::Quote => "blockquote".to_string(),
+            Style::Link(_) => "link".to_string(),
+            Style::Image(_) => "image".to_string(),
+            Style::ListMarker => "list-marker".to_string(),
+            Style::Rule => "rule".to_string(),
+            Style::Table => "table".to_string(),
+            Style::TableHeader => "table-header".to_string(),
+            Style::FootnoteReference => "footnote".to_string(),
+            Style::Indent(level) => {
+                // One tag per depth, made as deeper nesting turns up
+                let name = format!("indent-{}", level);
+                if tag_table.lookup(&name).is_none() {
+                    let margin = self.inner.text_view.left_margin() + INDENT_STEP * i32::from(*level);
+                    let tag = gtk4::TextTag::builder()
+                        .name(name.as_str())
+                        .left_margin(margin)
+                        .build();
+                    tag_table.add(&tag);
+                }
+                name
+            }
+        };
+        tag_table.lookup(&name)
     }
 }
 
-impl Default for MarkdownView {
-    fn default() -> Self {
-        Self::new()
+fn create_tags(text_view: &gtk4::TextView) {
+    let tag_table = text_view.buffer().tag_table();
+    let mono = format!("{}, monospace", crate::config::FONT_MONO);
+
+    let headings = [(1.8, 700, 12, 12), (1.4, 600, 16, 8), (1.2, 600, 12, 6), (1.1, 600, 8, 4), (1.0, 600, 8, 4), (1.0, 600, 8, 4)];
+    for (index, (scale, weight, above, below)) in headings.into_iter().enumerate() {
+        tag_table.add(
+            &gtk4::TextTag::builder()
+                .name(format!("h{}", index + 1))
+                .scale(scale)
+                .weight(weight)
+                .pixels_above_lines(above)
+                .pixels_below_lines(below)
+                .build(),
+        );
+    }
+
+    let tags = [
+        gtk4::TextTag::builder().name("bold").weight(700).build(),
+        gtk4::TextTag::builder().name("italic").style(pango::Style::Italic).build(),
+        gtk4::TextTag::builder().name("strikethrough").strikethrough(true).build(),
+        gtk4::TextTag::builder()
+            .name("code")
+            .family(mono.as_str())
+            .background("rgba(128, 128, 128, 0.15)")
+            .build(),
+        gtk4::TextTag::builder()
+            .name("code-block")
+            .family(mono.as_str())
+            .paragraph_background("rgba(128, 128, 128, 0.1)")
+            .wrap_mode(gtk4::WrapMode::None)
+            .build(),
+        gtk4::TextTag::builder()
+            .name("blockquote")
+            .style(pango::Style::Italic)
+            .foreground("gray")
+            .build(),
+        gtk4::TextTag::builder()
+            .name("link")
+            .underline(pango::Underline::Single)
+            .foreground("#3584e4")
+            .build(),
+        gtk4::TextTag::builder()
+            .name("image")
+            .style(pango::Style::Italic)
+            .foreground("gray")
+            .build(),
+        gtk4::TextTag::builder().name("list-marker").weight(600).build(),
+        gtk4::TextTag::builder()
+            .name("rule")
+            .justification(gtk4::Justification::Center)
+            .foreground("gray")
+            .build(),
+        gtk4::TextTag::builder()
+            .name("table")
+            .family(mono.as_str())
+            .wrap_mode(gtk4::WrapMode::None)
+            .build(),
+        gtk4::TextTag::builder().name("table-header").weight(700).build(),
+        gtk4::TextTag::builder()
+            .name("footnote")
+            .scale(0.75)
+            .rise(6 * pango::SCALE)
+            .build(),
+    ];
+    for tag in &tags {
+        tag_table.add(tag);
     }
 }
@@ -1,23 +1,46 @@
 mod theme;
+mod api_dialog;
+mod comments_panel;
+mod compare_versions;
 mod composition_view;
 mod flow_view;
 mod flow_history_view;
 mod projects_view;
 mod publish_dialog;
+mod quick_capture;
 mod markdown_view;
 mod editor;
+mod find_bar;
+mod library_replace;
+mod links_panel;
+mod outline_panel;
 mod archive_view;
+mod spelling;
+mod spelling_review;
+mod stats_popover;
+mod style_check;
+mod suggestion_bar;
+mod word_analysis;
 
 pub use theme::ThemeManager;
+pub use editor::{init_markdown_editor, set_smart_typography};
+pub use api_dialog::ApiDialog;
+pub use comments_panel::CommentsPanel;
+pub use compare_versions::{CompareVersions, Version};
 pub use composition_view::CompositionView;
+pub use find_bar::FindBar;
 pub use flow_view::FlowView;
 pub use flow_history_view::FlowHistoryView;
+pub use library_replace::LibraryReplace;
+pub use links_panel::LinksPanel;
+pub use outline_panel::OutlinePanel;
 pub use projects_view::ProjectsView;
 pub use publish_dialog::PublishDialog;
+pub use quick_capture::QuickCapture;
 pub use archive_view::ArchiveView;
-
-// These are available for future use
-#[allow(unused_imports)]
 pub use markdown_view::MarkdownView;
-#[allow(unused_imports)]
-pub use editor::Editor;
+pub use spelling::{configure_spelling, SpellChecker};
+pub use spelling_review::SpellingReview;
+pub use style_check::{configure_style_check, StyleChecker};
+pub use suggestion_bar::SuggestionBar;
+pub use word_analysis::WordAnalysis;
@@ -0,0 +1,342 @@
+use adw::subclass::prelude::*;
+use gtk4::prelude::*;
+use gtk4::{gdk, glib, CompositeTemplate};
+use libadwaita as adw;
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::time::Duration;
+
+use abbey_core::utils::outline::{self, Section};
+
+/// How long typing pauses before the outline catches up
+const REFRESH_DELAY: Duration = Duration::from_millis(300);
+
+mod imp {
+    use super::*;
+
+    #[derive(Default, CompositeTemplate)]
+    #[template(file = "outline_panel.ui")]
+    pub struct OutlinePanel {
+        #[template_child]
+        pub summary_label: TemplateChild<gtk4::Label>,
+        #[template_child]
+        pub sections_list: TemplateChild<gtk4::ListBox>,
+
+        pub view: RefCell<Option<sourceview5::View>>,
+        /// What the list shows, row by row
+        pub sections: RefCell<Vec<Section>>,
+        /// Headings folded away, by level and title so they stay folded as
+        /// the text around them changes
+        pub collapsed: RefCell<HashSet<(u8, String)>>,
+        pub pending: RefCell<Option<glib::SourceId>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for OutlinePanel {
+        const NAME: &'static str = "OutlinePanel";
+        type Type = super::OutlinePanel;
+        type ParentType = gtk4::Box;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+            klass.bind_template_callbacks();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    #[gtk4::template_callbacks]
+    impl OutlinePanel {
+        #[template_callback]
+        fn on_row_activated(&self, row: &gtk4::ListBoxRow) {
+            if let Ok(index) = usize::try_from(row.index()) {
+                self.obj().go_to(index);
+            }
+        }
+    }
+
+    impl ObjectImpl for OutlinePanel {
+        fn constructed(&self) {
+            self.parent_constructed();
+            self.obj().setup_drop_at_end();
+
+            // Catch up with edits made while the panel was hidden
+            self.obj().connect_map(|panel| panel.refresh());
+        }
+    }
+
+    impl WidgetImpl for OutlinePanel {}
+    impl BoxImpl for OutlinePanel {}
+}
+
+glib::wrapper! {
+    /// The headings of a markdown editor's text, to jump to, fold and drag
+    /// whole sections around by
+    pub struct OutlinePanel(ObjectSubclass<imp::OutlinePanel>)
+        @extends gtk4::Box, gtk4::Widget,
+        @implements gtk4::Accessible, gtk4::Buildable;
+}
+
+impl OutlinePanel {
+    /// Outline `view`'s text from now on
+    pub fn attach(&self, view: &sourceview5::View) {
+        let panel = self.downgrade();
+        view.buffer().connect_changed(move |_| {
+            if let Some(panel) = panel.upgrade() {
+                panel.schedule();
+            }
+        });
+        self.imp().view.replace(Some(view.clone()));
+        self.refresh();
+    }
+
+    fn schedule(&self) {
+        if let Some(source_id) = self.imp().pending.take() {
+            source_id.remove();
+        }
+        // Nothing to keep current while the panel is hidden
+        if !self.is_mapped() {
+            return;
+        }
+
+        let panel = self.downgrade();
+        let source_id = glib::timeout_add_local_once(REFRESH_DELAY, move || {
+            if let Some(panel) = panel.upgrade() {
+                panel.imp().pending.replace(None);
+                panel.refresh();
+            }
+        });
+        self.imp().pending.replace(Some(source_id));
+    }
+
+    fn text(&self) -> Option<String> {
+        let view = self.imp().view.borrow().clone()?;
+        let buffer = view.buffer();
+        Some(buffer.text(&buffer.start_iter(), &buffer.end_iter(), false).to_string())
+    }
+
+    /// Outline the text again and rebuild the list
+    fn refresh(&self) {
+        let imp = self.imp();
+        let sections = match self.text() {
+            Some(text) => outline::outline(&text),
+            None => Vec::new(),
+        };
+
+        imp.summary_label.set_text(&match sections.len() {
+            0 => "Headings you add show here".to_string(),
+            1 => "1 section".to_string(),
+            n => format!("{} sections", n),
+        });
+
+        imp.sections_list.remove_all();
+        let collapsed = imp.collapsed.borrow().clone();
+        // Levels of the collapsed headings the current one is under
+        let mut folds: Vec<u8> = Vec::new();
+        for (index, section) in sections.iter().enumerate() {
+            while folds.last().is_some_and(|&level| level >= section.level) {
+                folds.pop();
+            }
+            let has_subsections = sections
+                .get(index + 1)
+                .is_some_and(|next| next.level > section.level);
+            let is_collapsed = collapsed.contains(&(section.level, section.title.clone()));
+
+            let row = self.section_row(index, section, has_subsections, is_collapsed);
+            row.set_visible(folds.is_empty());
+            imp.sections_list.append(&row);
+
+            if is_collapsed {
+                folds.push(section.level);
+            }
+        }
+        imp.sections.replace(sections);
+    }
+
+    fn section_row(&self, index: usize, section: &Section, has_subsections: bool, is_collapsed: bool) -> gtk4::ListBoxRow {
+        let fold = gtk4::Button::builder()
+            .icon_name(if is_collapsed { "pan-end-symbolic" } else { "pan-down-symbolic" })
+            .tooltip_text(if is_collapsed { "Expand Section" } else { "Collapse Section" })
+            .valign(gtk4::Align::Center)
+            .css_classes(["flat"])
+            .build();
+        if has_subsections {
+            let panel = self.downgrade();
+            let key = (section.level, section.title.clone());
+            fold.connect_clicked(move |_| {
+                if let Some(panel) = panel.upgrade() {
+                    panel.toggle_fold(&key);
+                }
+            });
+        } else {
+            // Kept, unseen, so titles at one level line up
+            fold.set_opacity(0.0);
+            fold.set_can_target(false);
+            fold.set_can_focus(false);
+        }
+
+        let title = gtk4::Label::builder()
+            .label(if section.title.is_empty() { "Untitled" } else { section.title.as_str() })
+            .xalign(0.0)
+            .hexpand(true)
+            .ellipsize(gtk4::pango::EllipsizeMode::End)
+            .tooltip_text(&section.title)
+            .build();
+        if section.level == 1 {
+            title.add_css_class("heading");
+        }
+
+        let words = gtk4::Label::builder()
+            .label(match section.words {
+                1 => "1 word".to_string(),
+                n => format!("{} words", n),
+            })
+            .css_classes(["caption", "dim-label", "numeric"])
+            .build();
+
+        let content = gtk4::Box::builder()
+            .spacing(4)
+            .margin_start(12 * (i32::from(section.level) - 1))
+            .build();
+        content.append(&fold);
+        content.append(&title);
+        content.append(&words);
+
+        let row = gtk4::ListBoxRow::builder().child(&content).build();
+        self.setup_drag(&row, index);
+        row
+    }
+
+    fn toggle_fold(&self, key: &(u8, String)) {
+        {
+            let mut collapsed = self.imp().collapsed.borrow_mut();
+            if !collapsed.remove(key) {
+                collapsed.insert(key.clone());
+            }
+        }
+        self.refresh();
+    }
+
+    /// Put the cursor at a section's heading and bring it to the top
+    fn go_to(&self, index: usize) {
+        let view = match self.imp().view.borrow().clone() {
+            Some(view) => view,
+            None => return,
+        };
+        let start = match self.imp().sections.borrow().get(index) {
+            Some(section) => section.heading.start,
+            None => return,
+        };
+        let buffer = view.buffer();
+        let iter = buffer.iter_at_offset(start as i32);
+        buffer.place_cursor(&iter);
+        view.scroll_to_mark(&buffer.get_insert(), 0.0, true, 0.0, 0.0);
+        view.grab_focus();
+    }
+
+    /// Drag a row by its section number and drop another on it to move that
+    /// section before this one
+    fn setup_drag(&self, row: &gtk4::ListBoxRow, index: usize) {
+        let source = gtk4::DragSource::new();
+        source.set_actions(gdk::DragAction::MOVE);
+        let panel = self.downgrade();
+        source.connect_prepare(move |_, _, _| {
+            let panel = panel.upgrade()?;
+            if !panel.editable() {
+                return None;
+            }
+            Some(gdk::ContentProvider::for_value(&(index as u32).to_value()))
+        });
+        let dragged = row.downgrade();
+        source.connect_drag_begin(move |source, _| {
+            if let Some(row) = dragged.upgrade() {
+                source.set_icon(Some(&gtk4::WidgetPaintable::new(Some(&row))), 0, 0);
+            }
+        });
+        row.add_controller(source);
+
+        let target = gtk4::DropTarget::new(u32::static_type(), gdk::DragAction::MOVE);
+        let panel = self.downgrade();
+        target.connect_drop(move |_, value, _, _| {
+            match (panel.upgrade(), value.get::<u32>()) {
+                (Some(panel), Ok(from)) => panel.move_section(from as usize, Some(index)),
+                _ => false,
+            }
+        });
+        row.add_controller(target);
+    }
+
+    /// Dropping below the last heading moves a section to the end
+    fn setup_drop_at_end(&self) {
+        let target = gtk4::DropTarget::new(u32::static_type(), gdk::DragAction::MOVE);
+        let panel = self.downgrade();
+        target.connect_drop(move |_, value, _, _| {
+            match (panel.upgrade(), value.get::<u32>()) {
+                (Some(panel), Ok(from)) => panel.move_section(from as usize, None),
+                _ => false,
+            }
+        });
+        self.imp().sections_list.add_controller(target);
+    }
+
+    fn editable(&self) -> bool {
+        self.imp().view.borrow().as_ref().is_some_and(|view| view.is_editable())
+    }
+
+    /// Move a section and its subsections, as one step to undo
+    fn move_section(&self, from: usize, before: Option<usize>) -> bool {
+        let view = match self.imp().view.borrow().clone() {
+            Some(view) if view.is_editable() => view,
+            _ => return false,
+        };
+        let text = match self.text() {
+            Some(text) => text,
+            None => return false,
+        };
+        let moved = match outline::move_section(&text, from, before) {
+            Some(moved) => moved,
+            None => return false,
+        };
+
+        // Only replace what changed, so the rest of the text keeps its place
+        let old: Vec<char> = text.chars().collect();
+        let new: Vec<char> = moved.text.chars().collect();
+        let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+        let suffix = old[prefix..]
+            .iter()
+            .rev()
+            .zip(new[prefix..].iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+        let middle: String = new[prefix..new.len() - suffix].iter().collect();
+
+        let buffer = view.buffer();
+        buffer.begin_user_action();
+        let mut start = buffer.iter_at_offset(prefix as i32);
+        let mut end = buffer.iter_at_offset((old.len() - suffix) as i32);
+        buffer.delete(&mut start, &mut end);
+        buffer.insert(&mut start, &middle);
+        buffer.end_user_action();
+
+        let iter = buffer.iter_at_offset(moved.start as i32);
+        buffer.place_cursor(&iter);
+        view.scroll_to_mark(&buffer.get_insert(), 0.0, true, 0.0, 0.0);
+
+        // Not while the row dropped on is still handling the drop
+        let panel = self.downgrade();
+        glib::idle_add_local_once(move || {
+            if let Some(panel) = panel.upgrade() {
+                panel.refresh();
+            }
+        });
+        true
+    }
+}
+
+impl Default for OutlinePanel {
+    fn default() -> Self {
+        glib::Object::builder().build()
+    }
+}
@@ -5,6 +5,9 @@ use gtk4::{glib, CompositeTemplate};
 use libadwaita as adw;
 use std::cell::RefCell;
 
+use abbey_core::export;
+use abbey_core::utils::typography;
+
 use crate::data::{Composition, Project};
 
 mod imp {
@@ -33,8 +36,11 @@ mod imp {
         pub current_project: RefCell<Option<Project>>,
         pub current_project_index: RefCell<Option<usize>>,
         pub save_callback: RefCell<Option<Box<dyn Fn(Vec<Project>) + 'static>>>,
+        pub analyze_callback: RefCell<Option<Box<dyn Fn(Vec<String>) + 'static>>>,
         pub updating: std::cell::Cell<bool>,
         pub selected_composition_index: RefCell<Option<usize>>,
+        pub typography: std::cell::Cell<typography::Options>,
+        pub smart_export: std::cell::Cell<bool>,
     }
 
     #[glib::object_subclass]
@@ -64,6 +70,11 @@ mod imp {
         fn on_export_project(&self) {
             self.obj().export_current_project();
         }
+
+        #[template_callback]
+        fn on_analyze_project(&self) {
+            self.obj().analyze_current_project();
+        }
     }
 
     impl ObjectImpl for ProjectsView {
@@ -507,27 +518,32 @@ impl ProjectsView {
         self.imp().save_callback.replace(Some(Box::new(callback)));
     }
 
+    /// Called with the ids of the project's compositions, in order, to analyze
+    pub fn connect_analyze<F: Fn(Vec<String>) + 'static>(&self, callback: F) {
+        self.imp().analyze_callback.replace(Some(Box::new(callback)));
+    }
+
+    fn analyze_current_project(&self) {
+        let ids = match *self.imp().current_project.borrow() {
+            Some(ref project) => project.composition_ids.clone(),
+            None => return,
+        };
+        if let Some(ref callback) = *self.imp().analyze_callback.borrow() {
+            callback(ids);
+        }
+    }
+
+    /// How exports smarten punctuation, and whether they do unless told otherwise
+    pub fn set_typography(&self, options: typography::Options, smart_by_default: bool) {
+        self.imp().typography.set(options);
+        self.imp().smart_export.set(smart_by_default);
+    }
+
     pub fn export_current_project(&self) {
         if let Some(ref project) = *self.imp().current_project.borrow() {
             let compositions = self.imp().compositions.borrow();
             
-            // Build the combined content
-            let mut md = format!("# {}\n\n", project.title);
-            
-            if !project.description.is_empty() {
-                md.push_str(&format!("*{}*\n\n", project.description));
-            }
-            
-            md.push_str("---\n\n");
-            
-            for comp_id in &project.composition_ids {
-                if let Some(comp) = compositions.iter().find(|c| c.id == *comp_id) {
-                    let title = if comp.title.is_empty() { "Untitled" } else { &comp.title };
-                    md.push_str(&format!("## {}\n\n", title));
-                    md.push_str(&comp.content);
-                    md.push_str("\n\n---\n\n");
-                }
-            }
+            let md = export::project_to_markdown(project, &compositions);
             
             // Show preview dialog
             self.show_export_preview(&project.title, &md);
@@ -535,6 +551,9 @@ impl ProjectsView {
     }
 
     fn show_export_preview(&self, project_title: &str, content: &str) {
+        let options = self.imp().typography.get();
+        let smart_title = typography::smarten(project_title, &options);
+        let smart_content = typography::smarten(content, &options);
         let parent_window = self.root().and_then(|r| r.downcast::<gtk4::Window>().ok());
         
         let dialog = adw::Window::builder()
@@ -563,9 +582,23 @@ impl ProjectsView {
             .css_classes(["suggested-action"])
             .build();
         
+        // An OpenDocument file keeps comments as comments rather than footnotes
+        let export_odt_btn = gtk4::Button::builder()
+            .label("Export ODT")
+            .css_classes(["suggested-action"])
+            .build();
+        
+        header.pack_end(&export_odt_btn);
         header.pack_end(&export_html_btn);
         header.pack_end(&export_md_btn);
         
+        let smart_check = gtk4::CheckButton::builder()
+            .label("Smart Punctuation")
+            .tooltip_text("Curly quotes, dashes and ellipses in the exported document")
+            .active(self.imp().smart_export.get())
+            .build();
+        header.pack_start(&smart_check);
+        
         toolbar_view.add_top_bar(&header);
         
         // Preview content in scrolled window
@@ -584,29 +617,54 @@ impl ProjectsView {
             .cursor_visible(false)
             .build();
         
-        text_view.buffer().set_text(content);
+        let preview = if smart_check.is_active() { smart_content.as_str() } else { content };
+        text_view.buffer().set_text(preview);
         scrolled.set_child(Some(&text_view));
         
+        let buffer = text_view.buffer();
+        let plain = content.to_string();
+        let smart = smart_content.clone();
+        smart_check.connect_toggled(move |check| {
+            buffer.set_text(if check.is_active() { &smart } else { &plain });
+        });
+        
         toolbar_view.set_content(Some(&scrolled));
         dialog.set_content(Some(&toolbar_view));
         
         // Connect button handlers
         let dlg = dialog.clone();
         let view = self.clone();
+        let check = smart_check.clone();
         let title = project_title.to_string();
         let md_content = content.to_string();
+        let smart = smart_content.clone();
         export_md_btn.connect_clicked(move |_| {
             dlg.close();
-            view.save_export(&title, &md_content, "md");
+            let md = if check.is_active() { &smart } else { &md_content };
+            view.save_export(&title, md, "md");
         });
         
         let dlg = dialog.clone();
         let view = self.clone();
+        let check = smart_check.clone();
         let title = project_title.to_string();
         let md_content = content.to_string();
         export_html_btn.connect_clicked(move |_| {
             dlg.close();
-            view.save_export_html(&title, &md_content);
+            if check.is_active() {
+                view.save_export_html(&title, &smart_title, &smart_content);
+            } else {
+                view.save_export_html(&title, &title, &md_content);
+            }
+        });
+        
+        let dlg = dialog.clone();
+        let view = self.clone();
+        let check = smart_check.clone();
+        let title = project_title.to_string();
+        export_odt_btn.connect_clicked(move |_| {
+            dlg.close();
+            view.save_export_odt(&title, check.is_active());
         });
         
         dialog.present();
@@ -632,41 +690,28 @@ impl ProjectsView {
         }
     }
 
-    fn save_export_html(&self, title: &str, markdown: &str) {
-        // Convert markdown to HTML
-        let parser = pulldown_cmark::Parser::new(markdown);
-        let mut html = String::new();
-        pulldown_cmark::html::push_html(&mut html, parser);
-        
-        // Wrap in full HTML document with styling
-        let full_html = format!(r#"<!DOCTYPE html>
-<html lang="en">
-<head>
-    <meta charset="UTF-8">
-    <meta name="viewport" content="width=device-width, initial-scale=1.0">
-    <title>{}</title>
-    <style>
-        body {{
-            font-family: 'Georgia', serif;
-            max-width: 800px;
-            margin: 40px auto;
-            padding: 20px;
-            line-height: 1.8;
-            color: #333;
-        }}
-        h1 {{ font-size: 2.5em; margin-bottom: 0.5em; }}
-        h2 {{ font-size: 1.8em; margin-top: 2em; border-bottom: 1px solid #ddd; padding-bottom: 0.3em; }}
-        hr {{ border: none; border-top: 1px solid #ddd; margin: 2em 0; }}
-        p {{ margin: 1em 0; }}
-    </style>
-</head>
-<body>
-{}
-</body>
-</html>"#, title, html);
-        
+    /// Save an HTML page titled `heading`, named after `title`
+    fn save_export_html(&self, title: &str, heading: &str, markdown: &str) {
+        let full_html = export::project_html_document(heading, markdown);
         self.save_export(title, &full_html, "html");
     }
+
+    /// Save the current project as a flat OpenDocument text file
+    fn save_export_odt(&self, title: &str, smart: bool) {
+        let document = match *self.imp().current_project.borrow() {
+            Some(ref project) => {
+                let compositions = self.imp().compositions.borrow();
+                if smart {
+                    let options = self.imp().typography.get();
+                    export::export_project_smartened(project, &compositions, export::ExportFormat::Odt, &options)
+                } else {
+                    export::export_project(project, &compositions, export::ExportFormat::Odt)
+                }
+            }
+            None => return,
+        };
+        self.save_export(title, &document, export::ExportFormat::Odt.extension());
+    }
 }
 
 impl Default for ProjectsView {
@@ -4,6 +4,7 @@ use gtk4::{glib, CompositeTemplate};
 use libadwaita as adw;
 use std::cell::RefCell;
 
+use super::MarkdownView;
 use crate::data::Composition;
 
 mod imp {
@@ -90,8 +91,8 @@ impl PublishDialog {
     fn set_composition(&self, composition: &Composition) {
         self.imp().composition.replace(Some(composition.clone()));
         
-        // Show preview
-        self.imp().preview_view.buffer().set_text(&composition.content);
+        // Show preview, without pending suggestions as it will be published
+        MarkdownView::new(&self.imp().preview_view).set_markdown(&composition.content_without_suggestions());
     }
 
     pub fn present(&self, parent: Option<&impl IsA<gtk4::Window>>) {
@@ -132,7 +133,7 @@ impl PublishDialog {
                 &api_key,
                 &blog_id,
                 &composition.title,
-                &composition.content,
+                &composition.content_without_suggestions(),
             ).await;
             
             match result {
@@ -159,12 +160,12 @@ async fn publish_to_microblog(
     content: &str,
 ) -> Result<(), String> {
     let client = reqwest::Client::new();
-    
+
     let mut url = endpoint.to_string();
     if !blog_id.is_empty() {
         url = format!("{}/micropub", endpoint);
     }
-    
+
     // Build micropub-style request
     let body = serde_json::json!({
         "type": ["h-entry"],
@@ -174,7 +175,7 @@ async fn publish_to_microblog(
             "published": [chrono::Utc::now().to_rfc3339()]
         }
     });
-    
+
     let response = client
         .post(&url)
         .header("Authorization", format!("Bearer {}", api_key))
@@ -183,7 +184,7 @@ async fn publish_to_microblog(
         .send()
         .await
         .map_err(|e| e.to_string())?;
-    
+
     if response.status().is_success() {
         Ok(())
     } else {
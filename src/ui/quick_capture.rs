@@ -0,0 +1,169 @@
+use adw::subclass::prelude::*;
+use gtk4::prelude::*;
+use gtk4::{gdk, glib, CompositeTemplate};
+use libadwaita as adw;
+use std::cell::RefCell;
+
+use crate::data::Composition;
+
+/// How many recent compositions are offered to append to
+const RECENT_TARGETS: usize = 8;
+
+type CaptureCallback = Box<dyn Fn(Option<String>, String) -> Result<(), String> + 'static>;
+
+mod imp {
+    use super::*;
+
+    #[derive(Default, CompositeTemplate)]
+    #[template(file = "quick_capture.ui")]
+    pub struct QuickCapture {
+        #[template_child]
+        pub target_dropdown: TemplateChild<gtk4::DropDown>,
+        #[template_child]
+        pub text_view: TemplateChild<gtk4::TextView>,
+        #[template_child]
+        pub capture_btn: TemplateChild<gtk4::Button>,
+        #[template_child]
+        pub status_label: TemplateChild<gtk4::Label>,
+        
+        /// Composition ids matching the dropdown entries after "New Composition"
+        pub target_ids: RefCell<Vec<String>>,
+        pub captured_callback: RefCell<Option<CaptureCallback>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for QuickCapture {
+        const NAME: &'static str = "QuickCapture";
+        type Type = super::QuickCapture;
+        type ParentType = adw::Window;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+            klass.bind_template_callbacks();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    #[gtk4::template_callbacks]
+    impl QuickCapture {
+        #[template_callback]
+        fn on_capture(&self) {
+            self.obj().capture();
+        }
+
+        #[template_callback]
+        fn on_cancel(&self) {
+            self.obj().close();
+        }
+    }
+
+    impl ObjectImpl for QuickCapture {
+        fn constructed(&self) {
+            self.parent_constructed();
+            self.obj().setup_input();
+        }
+    }
+
+    impl WidgetImpl for QuickCapture {}
+    impl WindowImpl for QuickCapture {}
+    impl AdwWindowImpl for QuickCapture {}
+}
+
+glib::wrapper! {
+    pub struct QuickCapture(ObjectSubclass<imp::QuickCapture>)
+        @extends adw::Window, gtk4::Window, gtk4::Widget,
+        @implements gtk4::Accessible, gtk4::Buildable;
+}
+
+impl QuickCapture {
+    /// A capture window offering a new composition or any of the most recently
+    /// edited ones
+    pub fn new(app: &impl IsA<gtk4::Application>, compositions: &[Composition]) -> Self {
+        let capture: Self = glib::Object::builder()
+            .property("application", app)
+            .build();
+        
+        capture.set_targets(compositions);
+        capture
+    }
+
+    fn setup_input(&self) {
+        let buffer = self.imp().text_view.buffer();
+        let capture_btn = self.imp().capture_btn.clone();
+        buffer.connect_changed(move |buffer| {
+            let text = buffer.text(&buffer.start_iter(), &buffer.end_iter(), false);
+            capture_btn.set_sensitive(!text.trim().is_empty());
+        });
+        
+        // Ctrl+Enter captures and Escape cancels, so it works without the mouse
+        let key_controller = gtk4::EventControllerKey::new();
+        let capture = self.clone();
+        key_controller.connect_key_pressed(move |_, key, _, modifier| {
+            let ctrl = modifier.contains(gdk::ModifierType::CONTROL_MASK);
+            match key {
+                gdk::Key::Escape => {
+                    capture.close();
+                    glib::Propagation::Stop
+                }
+                gdk::Key::Return | gdk::Key::KP_Enter if ctrl => {
+                    capture.capture();
+                    glib::Propagation::Stop
+                }
+                _ => glib::Propagation::Proceed,
+            }
+        });
+        self.add_controller(key_controller);
+    }
+
+    fn set_targets(&self, compositions: &[Composition]) {
+        let mut recent: Vec<_> = compositions.iter().filter(|c| !c.archived).collect();
+        recent.sort_by_key(|c| std::cmp::Reverse(c.updated_at));
+        recent.truncate(RECENT_TARGETS);
+        
+        let mut labels = vec!["New Composition".to_string()];
+        labels.extend(recent.iter().map(|c| {
+            let title = if c.title.is_empty() { "Untitled" } else { c.title.as_str() };
+            format!("Append to “{}”", title)
+        }));
+        let labels: Vec<&str> = labels.iter().map(|l| l.as_str()).collect();
+        self.imp().target_dropdown.set_model(Some(&gtk4::StringList::new(&labels)));
+        
+        self.imp().target_ids.replace(recent.iter().map(|c| c.id.clone()).collect());
+    }
+
+    fn capture(&self) {
+        let buffer = self.imp().text_view.buffer();
+        let text = buffer.text(&buffer.start_iter(), &buffer.end_iter(), false).trim_end().to_string();
+        if text.trim().is_empty() {
+            return;
+        }
+        
+        // The first entry is a new composition; the rest follow target_ids
+        let selected = self.imp().target_dropdown.selected() as usize;
+        let target = selected
+            .checked_sub(1)
+            .and_then(|i| self.imp().target_ids.borrow().get(i).cloned());
+        
+        let result = match *self.imp().captured_callback.borrow() {
+            Some(ref callback) => callback(target, text),
+            None => Ok(()),
+        };
+        
+        match result {
+            Ok(()) => self.close(),
+            Err(message) => {
+                self.imp().status_label.set_text(&message);
+                self.imp().status_label.set_visible(true);
+            }
+        }
+    }
+
+    /// Called with the chosen composition id (`None` for a new one) and the text.
+    /// The window stays open showing the error if the callback fails.
+    pub fn connect_captured<F: Fn(Option<String>, String) -> Result<(), String> + 'static>(&self, callback: F) {
+        self.imp().captured_callback.replace(Some(Box::new(callback)));
+    }
+}
@@ -0,0 +1,558 @@
+use gtk4::prelude::*;
+use gtk4::{gio, glib, pango};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::{Rc, Weak};
+use std::time::Duration;
+
+use abbey_core::utils::spelling::{self, Dictionary, DictionaryFiles, PersonalDictionary, Word};
+use crate::data::SpellingSettings;
+
+/// How long typing has to pause before the text is checked again
+const CHECK_DELAY: Duration = Duration::from_millis(500);
+
+/// Suggestions shown in the context menu; the review dialog shows them all
+const MENU_SUGGESTIONS: usize = 5;
+
+const TAG_NAME: &str = "misspelled";
+
+type LanguageCallback = Box<dyn Fn(String) + 'static>;
+
+/// Spelling state shared by every checker: the library's settings and
+/// personal dictionary, and dictionaries loaded so far
+#[derive(Default)]
+struct Shared {
+    enabled: bool,
+    default_language: String,
+    personal: PersonalDictionary,
+    /// Installed dictionaries, looked up once
+    installed: Option<Vec<DictionaryFiles>>,
+    /// Loaded dictionaries by language; `None` if loading failed
+    loaded: HashMap<String, Option<Rc<Dictionary>>>,
+    /// Languages whose dictionary is being loaded
+    loading: HashSet<String>,
+    checkers: Vec<Weak<Inner>>,
+    save_personal: Option<Box<dyn Fn(Vec<String>)>>,
+    save_enabled: Option<Box<dyn Fn(bool)>>,
+}
+
+thread_local! {
+    static SHARED: RefCell<Shared> = RefCell::new(Shared {
+        enabled: true,
+        default_language: spelling::default_language(),
+        ..Shared::default()
+    });
+}
+
+/// Apply a library's spelling settings and personal dictionary to every
+/// checker; the callbacks save changes made from a context menu
+pub fn configure_spelling(
+    settings: &SpellingSettings,
+    personal: Vec<String>,
+    save_personal: impl Fn(Vec<String>) + 'static,
+    save_enabled: impl Fn(bool) + 'static,
+) {
+    SHARED.with(|shared| {
+        let mut shared = shared.borrow_mut();
+        shared.enabled = settings.enabled;
+        shared.default_language = settings.language.clone().unwrap_or_else(spelling::default_language);
+        shared.personal = PersonalDictionary::new(personal);
+        shared.save_personal = Some(Box::new(save_personal));
+        shared.save_enabled = Some(Box::new(save_enabled));
+    });
+    recheck_all();
+}
+
+fn installed() -> Vec<DictionaryFiles> {
+    SHARED.with(|shared| {
+        shared
+            .borrow_mut()
+            .installed
+            .get_or_insert_with(spelling::available_dictionaries)
+            .clone()
+    })
+}
+
+/// The dictionary for a language, once it's loaded. The first call starts
+/// loading it on a worker thread, as a big one takes a while to parse, and the
+/// checkers in that language are rechecked when it's in.
+fn dictionary(language: &str) -> Option<Rc<Dictionary>> {
+    if let Some(loaded) = SHARED.with(|shared| shared.borrow().loaded.get(language).cloned()) {
+        return loaded;
+    }
+    if SHARED.with(|shared| shared.borrow().loading.contains(language)) {
+        return None;
+    }
+
+    let files = match spelling::choose_dictionary(&installed(), language) {
+        Some(files) => files.clone(),
+        None => {
+            log::warn!("No {} dictionary is installed; spelling isn't checked", language);
+            SHARED.with(|shared| shared.borrow_mut().loaded.insert(language.to_string(), None));
+            return None;
+        }
+    };
+    SHARED.with(|shared| shared.borrow_mut().loading.insert(language.to_string()));
+
+    let language = language.to_string();
+    let loading = gio::spawn_blocking(move || {
+        let result = files.load();
+        (files, result)
+    });
+    glib::spawn_future_local(async move {
+        let loaded = match loading.await {
+            Ok((_, Ok(dictionary))) => Some(Rc::new(dictionary)),
+            Ok((files, Err(e))) => {
+                log::error!("Failed to load the {} dictionary from {}: {}", files.language, files.dic.display(), e);
+                None
+            }
+            Err(_) => {
+                log::error!("Loading the {} dictionary panicked", language);
+                None
+            }
+        };
+        SHARED.with(|shared| {
+            let mut shared = shared.borrow_mut();
+            shared.loading.remove(&language);
+            shared.loaded.insert(language.clone(), loaded);
+        });
+
+        for checker in checkers().into_iter().filter(|checker| checker.language() == language) {
+            checker.recheck();
+            if let Some(ref callback) = *checker.inner.dictionary_loaded.borrow() {
+                callback();
+            }
+        }
+    });
+    None
+}
+
+fn is_enabled() -> bool {
+    SHARED.with(|shared| shared.borrow().enabled)
+}
+
+fn set_enabled(enabled: bool) {
+    SHARED.with(|shared| {
+        let mut shared = shared.borrow_mut();
+        shared.enabled = enabled;
+        if let Some(ref save) = shared.save_enabled {
+            save(enabled);
+        }
+    });
+    recheck_all();
+}
+
+fn checkers() -> Vec<SpellChecker> {
+    SHARED.with(|shared| {
+        let mut shared = shared.borrow_mut();
+        shared.checkers.retain(|inner| inner.strong_count() > 0);
+        shared.checkers.iter().filter_map(upgrade).collect()
+    })
+}
+
+fn recheck_all() {
+    for checker in checkers() {
+        checker.recheck();
+    }
+}
+
+/// Signal handlers hold checkers weakly, as the checker holds the view they're on
+fn upgrade(weak: &Weak<Inner>) -> Option<SpellChecker> {
+    weak.upgrade().map(|inner| SpellChecker { inner })
+}
+
+/// Underlines misspelled words in a text view holding markdown and offers
+/// corrections in its context menu
+#[derive(Clone)]
+pub struct SpellChecker {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    text_view: gtk4::TextView,
+    /// The language picked for this text; `None` follows the library's default
+    language: RefCell<Option<String>>,
+    /// Words skipped with Ignore All, until the view closes
+    ignored: RefCell<HashSet<String>>,
+    /// What the underlines show
+    misspelled: RefCell<Vec<Word>>,
+    /// The misspelling the context menu is about
+    target: RefCell<Option<Word>>,
+    menu: gio::Menu,
+    actions: gio::SimpleActionGroup,
+    pending: RefCell<Option<glib::SourceId>>,
+    language_changed: RefCell<Option<LanguageCallback>>,
+    dictionary_loaded: RefCell<Option<Box<dyn Fn() + 'static>>>,
+}
+
+impl SpellChecker {
+    /// Check a text view in the library's default language
+    pub fn new(text_view: &impl IsA<gtk4::TextView>) -> Self {
+        let text_view = text_view.as_ref();
+        let tag = gtk4::TextTag::builder()
+            .name(TAG_NAME)
+            .underline(pango::Underline::Error)
+            .build();
+        text_view.buffer().tag_table().add(&tag);
+
+        let menu = gio::Menu::new();
+        text_view.set_extra_menu(Some(&menu));
+
+        let checker = Self {
+            inner: Rc::new(Inner {
+                text_view: text_view.clone(),
+                language: RefCell::new(None),
+                ignored: RefCell::new(HashSet::new()),
+                misspelled: RefCell::new(Vec::new()),
+                target: RefCell::new(None),
+                menu,
+                actions: gio::SimpleActionGroup::new(),
+                pending: RefCell::new(None),
+                language_changed: RefCell::new(None),
+                dictionary_loaded: RefCell::new(None),
+            }),
+        };
+        SHARED.with(|shared| shared.borrow_mut().checkers.push(Rc::downgrade(&checker.inner)));
+
+        checker.setup_actions();
+        checker.setup_signals();
+        checker.update_menu();
+        checker
+    }
+
+    /// Check in `language`, like `en_GB`, or the library's default for `None`
+    pub fn set_language(&self, language: Option<&str>) {
+        self.inner.language.replace(language.map(String::from));
+        self.recheck();
+    }
+
+    pub fn language(&self) -> String {
+        match *self.inner.language.borrow() {
+            Some(ref language) => language.clone(),
+            None => SHARED.with(|shared| shared.borrow().default_language.clone()),
+        }
+    }
+
+    /// Called when the language is changed from the context menu
+    pub fn connect_language_changed<F: Fn(String) + 'static>(&self, callback: F) {
+        self.inner.language_changed.replace(Some(Box::new(callback)));
+    }
+
+    pub fn text_view(&self) -> &gtk4::TextView {
+        &self.inner.text_view
+    }
+
+    /// Called when the dictionary for the language has loaded, and the text
+    /// been checked against it
+    pub fn connect_dictionary_loaded<F: Fn() + 'static>(&self, callback: F) {
+        self.inner.dictionary_loaded.replace(Some(Box::new(callback)));
+    }
+
+    /// Whether a dictionary for the language is installed
+    pub fn has_dictionary(&self) -> bool {
+        spelling::choose_dictionary(&installed(), &self.language()).is_some()
+    }
+
+    /// Whether the dictionary for the language is still loading, so there are
+    /// no misspellings to show yet
+    pub fn is_loading(&self) -> bool {
+        let language = self.language();
+        dictionary(&language).is_none() && SHARED.with(|shared| shared.borrow().loading.contains(&language))
+    }
+
+    /// Check the whole text now and redraw the underlines
+    pub fn recheck(&self) {
+        self.cancel_pending();
+
+        let buffer = self.inner.text_view.buffer();
+        buffer.remove_tag_by_name(TAG_NAME, &buffer.start_iter(), &buffer.end_iter());
+
+        let misspelled = if is_enabled() { self.misspellings() } else { Vec::new() };
+        for word in &misspelled {
+            buffer.apply_tag_by_name(
+                TAG_NAME,
+                &buffer.iter_at_offset(word.range.start as i32),
+                &buffer.iter_at_offset(word.range.end as i32),
+            );
+        }
+        self.inner.misspelled.replace(misspelled);
+
+        let target = self.misspelling_at(buffer.cursor_position() as usize);
+        self.inner.target.replace(target);
+        self.update_menu();
+    }
+
+    /// Every misspelling in the text as it is now, in order, whether or not
+    /// spelling is checked while typing
+    pub fn misspellings(&self) -> Vec<Word> {
+        let dictionary = match dictionary(&self.language()) {
+            Some(dictionary) => dictionary,
+            None => return Vec::new(),
+        };
+
+        let buffer = self.inner.text_view.buffer();
+        let text = buffer.text(&buffer.start_iter(), &buffer.end_iter(), false);
+        let ignored = self.inner.ignored.borrow();
+        SHARED.with(|shared| spelling::misspelled(&text, &dictionary, &shared.borrow().personal))
+            .into_iter()
+            .filter(|word| !ignored.contains(&word.text))
+            .collect()
+    }
+
+    pub fn suggest(&self, word: &str) -> Vec<String> {
+        dictionary(&self.language())
+            .map(|dictionary| dictionary.suggest(word))
+            .unwrap_or_default()
+    }
+
+    /// Stop flagging a word until the view closes
+    pub fn ignore(&self, word: &str) {
+        self.inner.ignored.borrow_mut().insert(word.to_string());
+        self.recheck();
+    }
+
+    /// Add a word to the library's personal dictionary
+    pub fn add_to_dictionary(&self, word: &str) {
+        SHARED.with(|shared| {
+            let mut shared = shared.borrow_mut();
+            if shared.personal.add(word) {
+                if let Some(ref save) = shared.save_personal {
+                    save(shared.personal.words());
+                }
+            }
+        });
+        recheck_all();
+    }
+
+    /// Replace a misspelling, if the text hasn't changed since it was found
+    pub fn replace(&self, word: &Word, replacement: &str) -> bool {
+        if !self.inner.text_view.is_editable() {
+            return false;
+        }
+
+        let buffer = self.inner.text_view.buffer();
+        let mut start = buffer.iter_at_offset(word.range.start as i32);
+        let mut end = buffer.iter_at_offset(word.range.end as i32);
+        if buffer.text(&start, &end, false) != word.text {
+            return false;
+        }
+
+        buffer.begin_user_action();
+        buffer.delete(&mut start, &mut end);
+        buffer.insert(&mut start, replacement);
+        buffer.end_user_action();
+        true
+    }
+
+    fn downgrade(&self) -> Weak<Inner> {
+        Rc::downgrade(&self.inner)
+    }
+
+    fn schedule(&self) {
+        self.cancel_pending();
+
+        let checker = self.clone();
+        let source_id = glib::timeout_add_local_once(CHECK_DELAY, move || {
+            checker.inner.pending.replace(None);
+            checker.recheck();
+        });
+        self.inner.pending.replace(Some(source_id));
+    }
+
+    fn cancel_pending(&self) {
+        if let Some(source_id) = self.inner.pending.take() {
+            source_id.remove();
+        }
+    }
+
+    fn setup_signals(&self) {
+        let buffer = self.inner.text_view.buffer();
+
+        let weak = self.downgrade();
+        buffer.connect_changed(move |_| {
+            if let Some(checker) = upgrade(&weak) {
+                checker.schedule();
+            }
+        });
+
+        // The menu is about the misspelling under the cursor, or under a right click
+        let weak = self.downgrade();
+        buffer.connect_cursor_position_notify(move |buffer| {
+            if let Some(checker) = upgrade(&weak) {
+                checker.set_target_at(buffer.cursor_position() as usize);
+            }
+        });
+
+        let click = gtk4::GestureClick::new();
+        click.set_button(gtk4::gdk::BUTTON_SECONDARY);
+        click.set_propagation_phase(gtk4::PropagationPhase::Capture);
+        let weak = self.downgrade();
+        click.connect_pressed(move |_, _, x, y| {
+            let checker = match upgrade(&weak) {
+                Some(checker) => checker,
+                None => return,
+            };
+            let text_view = &checker.inner.text_view;
+            let (x, y) = text_view.window_to_buffer_coords(gtk4::TextWindowType::Widget, x as i32, y as i32);
+            if let Some(iter) = text_view.iter_at_location(x, y) {
+                checker.set_target_at(iter.offset() as usize);
+            }
+        });
+        self.inner.text_view.add_controller(click);
+    }
+
+    fn misspelling_at(&self, offset: usize) -> Option<Word> {
+        self.inner
+            .misspelled
+            .borrow()
+            .iter()
+            .find(|word| word.range.start <= offset && offset <= word.range.end)
+            .cloned()
+    }
+
+    fn set_target_at(&self, offset: usize) {
+        let target = self.misspelling_at(offset);
+        if *self.inner.target.borrow() != target {
+            self.inner.target.replace(target);
+            self.update_menu();
+        }
+    }
+
+    fn setup_actions(&self) {
+        let actions = &self.inner.actions;
+
+        let replace = gio::SimpleAction::new("replace", Some(glib::VariantTy::STRING));
+        let weak = self.downgrade();
+        replace.connect_activate(move |_, param| {
+            let checker = match upgrade(&weak) {
+                Some(checker) => checker,
+                None => return,
+            };
+            let replacement = param.and_then(|param| param.get::<String>());
+            let target = checker.inner.target.borrow().clone();
+            if let (Some(word), Some(replacement)) = (target, replacement) {
+                checker.replace(&word, &replacement);
+            }
+        });
+        actions.add_action(&replace);
+
+        let add = gio::SimpleAction::new("add", None);
+        let weak = self.downgrade();
+        add.connect_activate(move |_, _| {
+            let checker = match upgrade(&weak) {
+                Some(checker) => checker,
+                None => return,
+            };
+            let target = checker.inner.target.borrow().clone();
+            if let Some(word) = target {
+                checker.add_to_dictionary(&word.text);
+            }
+        });
+        actions.add_action(&add);
+
+        let ignore = gio::SimpleAction::new("ignore", None);
+        let weak = self.downgrade();
+        ignore.connect_activate(move |_, _| {
+            let checker = match upgrade(&weak) {
+                Some(checker) => checker,
+                None => return,
+            };
+            let target = checker.inner.target.borrow().clone();
+            if let Some(word) = target {
+                checker.ignore(&word.text);
+            }
+        });
+        actions.add_action(&ignore);
+
+        let review = gio::SimpleAction::new("review", None);
+        let weak = self.downgrade();
+        review.connect_activate(move |_, _| {
+            if let Some(checker) = upgrade(&weak) {
+                checker.review();
+            }
+        });
+        actions.add_action(&review);
+
+        let enabled = gio::SimpleAction::new_stateful("enabled", None, &is_enabled().to_variant());
+        enabled.connect_activate(|action, _| {
+            let enabled = !action.state().and_then(|state| state.get::<bool>()).unwrap_or(true);
+            set_enabled(enabled);
+        });
+        actions.add_action(&enabled);
+
+        let language = gio::SimpleAction::new_stateful(
+            "language",
+            Some(glib::VariantTy::STRING),
+            &self.language().to_variant(),
+        );
+        let weak = self.downgrade();
+        language.connect_activate(move |_, param| {
+            let checker = match upgrade(&weak) {
+                Some(checker) => checker,
+                None => return,
+            };
+            if let Some(language) = param.and_then(|param| param.get::<String>()) {
+                checker.set_language(Some(&language));
+                if let Some(ref callback) = *checker.inner.language_changed.borrow() {
+                    callback(language);
+                }
+            }
+        });
+        actions.add_action(&language);
+
+        self.inner.text_view.insert_action_group("spell", Some(actions));
+    }
+
+    /// Open the review dialog to go through every misspelling in turn
+    pub fn review(&self) {
+        let dialog = super::SpellingReview::new(self);
+        let parent = self.inner.text_view.root().and_downcast::<gtk4::Window>();
+        dialog.present(parent.as_ref());
+    }
+
+    /// Rebuild the context menu for the current target, and bring the
+    /// stateful actions up to date
+    fn update_menu(&self) {
+        let menu = &self.inner.menu;
+        menu.remove_all();
+
+        let set_state = |name: &str, state: glib::Variant| {
+            if let Some(action) = self.inner.actions.lookup_action(name).and_downcast::<gio::SimpleAction>() {
+                action.set_state(&state);
+            }
+        };
+        set_state("enabled", is_enabled().to_variant());
+        set_state("language", self.language().to_variant());
+
+        if let Some(word) = self.inner.target.borrow().clone() {
+            let section = gio::Menu::new();
+            let suggestions = self.suggest(&word.text);
+            if suggestions.is_empty() {
+                section.append(Some("No Suggestions"), None);
+            }
+            for suggestion in suggestions.iter().take(MENU_SUGGESTIONS) {
+                let item = gio::MenuItem::new(Some(suggestion), None);
+                item.set_action_and_target_value(Some("spell.replace"), Some(&suggestion.to_variant()));
+                section.append_item(&item);
+            }
+            section.append(Some(&format!("Add “{}” to Dictionary", word.text)), Some("spell.add"));
+            section.append(Some("Ignore All"), Some("spell.ignore"));
+            menu.append_section(None, &section);
+        }
+
+        let languages = gio::Menu::new();
+        for files in installed() {
+            let item = gio::MenuItem::new(Some(&files.language), None);
+            item.set_action_and_target_value(Some("spell.language"), Some(&files.language.to_variant()));
+            languages.append_item(&item);
+        }
+
+        let options = gio::Menu::new();
+        options.append(Some("Check Spelling…"), Some("spell.review"));
+        options.append(Some("Check Spelling While Typing"), Some("spell.enabled"));
+        if languages.n_items() > 0 {
+            options.append_submenu(Some("Spelling Language"), &languages);
+        }
+        menu.append_section(None, &options);
+    }
+}
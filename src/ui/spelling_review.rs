@@ -0,0 +1,280 @@
+use adw::subclass::prelude::*;
+use adw::prelude::*;
+use gtk4::{glib, CompositeTemplate};
+use libadwaita as adw;
+use std::cell::{Cell, RefCell};
+
+use abbey_core::utils::spelling::Word;
+use super::SpellChecker;
+
+/// How much of the line to show either side of the word
+const CONTEXT_CHARS: usize = 60;
+
+mod imp {
+    use super::*;
+
+    #[derive(Default, CompositeTemplate)]
+    #[template(file = "spelling_review.ui")]
+    pub struct SpellingReview {
+        #[template_child]
+        pub word_label: TemplateChild<gtk4::Label>,
+        #[template_child]
+        pub context_label: TemplateChild<gtk4::Label>,
+        #[template_child]
+        pub replacement_entry: TemplateChild<gtk4::Entry>,
+        #[template_child]
+        pub suggestions_list: TemplateChild<gtk4::ListBox>,
+        #[template_child]
+        pub actions_box: TemplateChild<gtk4::Box>,
+        #[template_child]
+        pub change_box: TemplateChild<gtk4::Box>,
+
+        pub checker: RefCell<Option<SpellChecker>>,
+        pub current: RefCell<Option<Word>>,
+        /// Where to look for the next misspelling, in chars
+        pub position: Cell<usize>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for SpellingReview {
+        const NAME: &'static str = "SpellingReview";
+        type Type = super::SpellingReview;
+        type ParentType = adw::Window;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+            klass.bind_template_callbacks();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    #[gtk4::template_callbacks]
+    impl SpellingReview {
+        #[template_callback]
+        fn on_ignore(&self) {
+            if let Some(word) = self.current.borrow().clone() {
+                self.position.set(word.range.end);
+            }
+            self.obj().show_next();
+        }
+
+        #[template_callback]
+        fn on_ignore_all(&self) {
+            if let Some(word) = self.current.borrow().clone() {
+                self.obj().checker().ignore(&word.text);
+            }
+            self.obj().show_next();
+        }
+
+        #[template_callback]
+        fn on_add(&self) {
+            if let Some(word) = self.current.borrow().clone() {
+                self.obj().checker().add_to_dictionary(&word.text);
+            }
+            self.obj().show_next();
+        }
+
+        #[template_callback]
+        fn on_change(&self) {
+            self.obj().change(false);
+        }
+
+        #[template_callback]
+        fn on_change_all(&self) {
+            self.obj().change(true);
+        }
+
+        #[template_callback]
+        fn on_suggestion_activated(&self, row: &gtk4::ListBoxRow) {
+            if let Some(label) = row.child().and_downcast::<gtk4::Label>() {
+                self.replacement_entry.set_text(&label.text());
+                self.replacement_entry.grab_focus();
+            }
+        }
+    }
+
+    impl ObjectImpl for SpellingReview {}
+    impl WidgetImpl for SpellingReview {}
+    impl WindowImpl for SpellingReview {}
+    impl AdwWindowImpl for SpellingReview {}
+}
+
+glib::wrapper! {
+    pub struct SpellingReview(ObjectSubclass<imp::SpellingReview>)
+        @extends adw::Window, gtk4::Window, gtk4::Widget,
+        @implements gtk4::Accessible, gtk4::Buildable;
+}
+
+impl SpellingReview {
+    /// Go through the misspellings of a checker's text from the top
+    pub fn new(checker: &SpellChecker) -> Self {
+        let dialog: Self = glib::Object::builder()
+            .property("modal", true)
+            .build();
+
+        dialog.imp().checker.replace(Some(checker.clone()));
+        dialog.imp().change_box.set_sensitive(checker.text_view().is_editable());
+        let weak = dialog.downgrade();
+        checker.connect_dictionary_loaded(move || {
+            if let Some(dialog) = weak.upgrade() {
+                dialog.show_next();
+            }
+        });
+        dialog.show_next();
+        dialog
+    }
+
+    pub fn present(&self, parent: Option<&impl IsA<gtk4::Window>>) {
+        if let Some(parent) = parent {
+            self.set_transient_for(Some(parent));
+        }
+        gtk4::prelude::GtkWindowExt::present(self);
+    }
+
+    fn checker(&self) -> SpellChecker {
+        self.imp().checker.borrow().clone().expect("review opened without a checker")
+    }
+
+    /// Show the first misspelling from the current position on, and select it
+    /// in the editor
+    fn show_next(&self) {
+        let imp = self.imp();
+        let checker = self.checker();
+        let next = checker
+            .misspellings()
+            .into_iter()
+            .find(|word| word.range.start >= imp.position.get());
+        imp.current.replace(next.clone());
+
+        while let Some(row) = imp.suggestions_list.row_at_index(0) {
+            imp.suggestions_list.remove(&row);
+        }
+
+        let word = match next {
+            Some(word) => word,
+            None => {
+                let message = if checker.is_loading() {
+                    format!("Loading the {} dictionary...", checker.language())
+                } else if checker.has_dictionary() {
+                    "No more misspellings".to_string()
+                } else {
+                    format!("No {} dictionary is installed", checker.language())
+                };
+                imp.word_label.set_text(&message);
+                imp.context_label.set_visible(false);
+                imp.replacement_entry.set_text("");
+                imp.actions_box.set_sensitive(false);
+                imp.replacement_entry.set_sensitive(false);
+                return;
+            }
+        };
+
+        imp.word_label.set_text(&word.text);
+        imp.context_label.set_markup(&self.context(&word));
+        imp.context_label.set_visible(true);
+        imp.actions_box.set_sensitive(true);
+        imp.replacement_entry.set_sensitive(true);
+
+        let suggestions = checker.suggest(&word.text);
+        for suggestion in &suggestions {
+            let label = gtk4::Label::builder()
+                .label(suggestion.as_str())
+                .xalign(0.0)
+                .margin_start(12)
+                .margin_end(12)
+                .margin_top(8)
+                .margin_bottom(8)
+                .build();
+            imp.suggestions_list.append(&label);
+        }
+        imp.replacement_entry.set_text(suggestions.first().unwrap_or(&word.text));
+
+        // Show it in place, so the writer can judge it in context
+        let text_view = checker.text_view();
+        let buffer = text_view.buffer();
+        let mut start = buffer.iter_at_offset(word.range.start as i32);
+        let end = buffer.iter_at_offset(word.range.end as i32);
+        buffer.select_range(&start, &end);
+        text_view.scroll_to_iter(&mut start, 0.1, false, 0.0, 0.0);
+    }
+
+    /// The line around a word, shortened, with the word in bold
+    fn context(&self, word: &Word) -> String {
+        let buffer = self.checker().text_view().buffer();
+        let start = buffer.iter_at_offset(word.range.start as i32);
+        let end = buffer.iter_at_offset(word.range.end as i32);
+
+        let mut line_start = start;
+        line_start.set_line_offset(0);
+        let mut line_end = end;
+        if !line_end.ends_line() {
+            line_end.forward_to_line_end();
+        }
+
+        let before: String = buffer.text(&line_start, &start, false).to_string();
+        let after: String = buffer.text(&end, &line_end, false).to_string();
+        let before_count = before.chars().count();
+        let before: String = if before_count > CONTEXT_CHARS {
+            format!("…{}", before.chars().skip(before_count - CONTEXT_CHARS).collect::<String>())
+        } else {
+            before
+        };
+        let after: String = if after.chars().count() > CONTEXT_CHARS {
+            format!("{}…", after.chars().take(CONTEXT_CHARS).collect::<String>())
+        } else {
+            after
+        };
+
+        format!(
+            "{}<b>{}</b>{}",
+            glib::markup_escape_text(&before),
+            glib::markup_escape_text(&word.text),
+            glib::markup_escape_text(&after)
+        )
+    }
+
+    /// Change the current misspelling, or every occurrence of it, to the entry's text
+    fn change(&self, all: bool) {
+        let imp = self.imp();
+        let word = match imp.current.borrow().clone() {
+            Some(word) => word,
+            None => return,
+        };
+        let replacement = imp.replacement_entry.text().to_string();
+        if replacement.is_empty() {
+            return;
+        }
+
+        let checker = self.checker();
+        let targets: Vec<Word> = if all {
+            checker.misspellings().into_iter().filter(|w| w.text == word.text).collect()
+        } else {
+            vec![word.clone()]
+        };
+
+        // Last first, so earlier offsets stay valid; one undo step for all of them
+        let buffer = checker.text_view().buffer();
+        let growth = replacement.chars().count() as isize - word.text.chars().count() as isize;
+        let mut shift = 0isize;
+        buffer.begin_user_action();
+        for target in targets.iter().rev() {
+            if checker.replace(target, &replacement) && target.range.start < word.range.start {
+                shift += growth;
+            }
+        }
+        buffer.end_user_action();
+
+        let position = (word.range.start as isize + shift).max(0) as usize + replacement.chars().count();
+        imp.position.set(position);
+        self.show_next();
+    }
+}
+
+impl Default for SpellingReview {
+    fn default() -> Self {
+        glib::Object::builder().build()
+    }
+}
@@ -0,0 +1,64 @@
+use gtk4::prelude::*;
+
+use abbey_core::utils::text_stats::TextStats;
+
+/// A popover listing counts, reading time and readability scores for a
+/// document or a selection, under `title`
+pub fn stats_popover(title: &str, stats: &TextStats) -> gtk4::Popover {
+    let grid = gtk4::Grid::builder()
+        .row_spacing(6)
+        .column_spacing(24)
+        .margin_start(12)
+        .margin_end(12)
+        .margin_top(12)
+        .margin_bottom(12)
+        .build();
+
+    let heading = gtk4::Label::builder()
+        .label(title)
+        .xalign(0.0)
+        .css_classes(["heading"])
+        .build();
+    grid.attach(&heading, 0, 0, 2, 1);
+
+    let score = |score: Option<f64>| score.map_or("—".to_string(), |score| format!("{:.1}", score));
+    let reading = match stats.words {
+        0 => "—".to_string(),
+        _ => format!("{} min", stats.reading_minutes()),
+    };
+    let rows = [
+        ("Words", stats.words.to_string()),
+        ("Characters", stats.characters.to_string()),
+        ("Characters without spaces", stats.characters_without_spaces.to_string()),
+        ("Sentences", stats.sentences.to_string()),
+        ("Paragraphs", stats.paragraphs.to_string()),
+        ("Reading time", reading),
+        ("Reading ease", score(stats.flesch_reading_ease())),
+        ("Grade level", score(stats.flesch_kincaid_grade())),
+        ("Automated readability", score(stats.automated_readability_index())),
+        ("Coleman–Liau", score(stats.coleman_liau_index())),
+    ];
+
+    for (row, (name, value)) in rows.into_iter().enumerate() {
+        let name = gtk4::Label::builder()
+            .label(name)
+            .xalign(0.0)
+            .css_classes(["dim-label"])
+            .build();
+        let value = gtk4::Label::builder()
+            .label(value)
+            .xalign(1.0)
+            .hexpand(true)
+            .css_classes(["numeric"])
+            .build();
+        grid.attach(&name, 0, row as i32 + 1, 1, 1);
+        grid.attach(&value, 1, row as i32 + 1, 1, 1);
+    }
+
+    // What the scores mean
+    grid.set_tooltip_text(Some(
+        "Reading ease runs from 0 (hard) to 100 (easy); the other scores are US school grades",
+    ));
+
+    gtk4::Popover::builder().child(&grid).build()
+}
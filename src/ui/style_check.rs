@@ -0,0 +1,274 @@
+use gtk4::prelude::*;
+use gtk4::{gdk, glib, pango};
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+use std::time::Duration;
+
+use abbey_core::utils::lint::{Issue, Linter};
+use crate::data::LintSettings;
+
+/// How long typing has to pause before the text is checked again
+const CHECK_DELAY: Duration = Duration::from_millis(750);
+
+const TAG_NAME: &str = "style-issue";
+
+type IssuesCallback = Box<dyn Fn(&[Issue]) + 'static>;
+type IgnoresCallback = Box<dyn Fn(Vec<String>) + 'static>;
+
+/// Style settings shared by every checker
+#[derive(Default)]
+struct Shared {
+    settings: LintSettings,
+    checkers: Vec<Weak<Inner>>,
+    save: Option<Box<dyn Fn(&LintSettings)>>,
+}
+
+thread_local! {
+    static SHARED: RefCell<Shared> = RefCell::new(Shared::default());
+}
+
+/// Apply a library's style settings to every checker; `save` keeps changes
+/// made from the sidebar
+pub fn configure_style_check(settings: &LintSettings, save: impl Fn(&LintSettings) + 'static) {
+    SHARED.with(|shared| {
+        let mut shared = shared.borrow_mut();
+        shared.settings = settings.clone();
+        shared.save = Some(Box::new(save));
+    });
+    recheck_all();
+}
+
+/// The rules that are turned on
+fn linter() -> Linter {
+    SHARED.with(|shared| Linter::builtin().without(&shared.borrow().settings.disabled_rules))
+}
+
+fn update_settings(change: impl FnOnce(&mut LintSettings)) {
+    SHARED.with(|shared| {
+        let mut shared = shared.borrow_mut();
+        change(&mut shared.settings);
+        if let Some(ref save) = shared.save {
+            save(&shared.settings);
+        }
+    });
+    recheck_all();
+}
+
+fn recheck_all() {
+    let checkers: Vec<StyleChecker> = SHARED.with(|shared| {
+        let mut shared = shared.borrow_mut();
+        shared.checkers.retain(|inner| inner.strong_count() > 0);
+        shared.checkers.iter().filter_map(upgrade).collect()
+    });
+    for checker in checkers {
+        checker.recheck();
+    }
+}
+
+fn upgrade(weak: &Weak<Inner>) -> Option<StyleChecker> {
+    weak.upgrade().map(|inner| StyleChecker { inner })
+}
+
+/// Every built-in rule as id, name and whether it's turned on
+pub fn style_rules() -> Vec<(&'static str, &'static str, bool)> {
+    let disabled = SHARED.with(|shared| shared.borrow().settings.disabled_rules.clone());
+    Linter::builtin()
+        .rules()
+        .map(|rule| (rule.id(), rule.name(), !disabled.iter().any(|id| id == rule.id())))
+        .collect()
+}
+
+pub fn set_style_rule_enabled(id: &str, enabled: bool) {
+    update_settings(|settings| {
+        settings.disabled_rules.retain(|rule| rule != id);
+        if !enabled {
+            settings.disabled_rules.push(id.to_string());
+        }
+    });
+}
+
+/// Whether findings are underlined in the editor
+pub fn style_underlines() -> bool {
+    SHARED.with(|shared| shared.borrow().settings.enabled)
+}
+
+pub fn set_style_underlines(enabled: bool) {
+    update_settings(|settings| settings.enabled = enabled);
+}
+
+/// Checks the prose in a text view holding markdown against the style rules,
+/// underlining what it finds and explaining it in a tooltip
+#[derive(Clone)]
+pub struct StyleChecker {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    text_view: gtk4::TextView,
+    /// Keys of findings dismissed for this text
+    ignored: RefCell<Vec<String>>,
+    issues: RefCell<Vec<Issue>>,
+    pending: RefCell<Option<glib::SourceId>>,
+    issues_changed: RefCell<Option<IssuesCallback>>,
+    ignores_changed: RefCell<Option<IgnoresCallback>>,
+}
+
+impl StyleChecker {
+    pub fn new(text_view: &impl IsA<gtk4::TextView>) -> Self {
+        let text_view = text_view.as_ref();
+        let tag = gtk4::TextTag::builder()
+            .name(TAG_NAME)
+            .underline(pango::Underline::Single)
+            .underline_rgba(&gdk::RGBA::new(0.9, 0.65, 0.04, 1.0))
+            .build();
+        text_view.buffer().tag_table().add(&tag);
+
+        let checker = Self {
+            inner: Rc::new(Inner {
+                text_view: text_view.clone(),
+                ignored: RefCell::new(Vec::new()),
+                issues: RefCell::new(Vec::new()),
+                pending: RefCell::new(None),
+                issues_changed: RefCell::new(None),
+                ignores_changed: RefCell::new(None),
+            }),
+        };
+        SHARED.with(|shared| shared.borrow_mut().checkers.push(Rc::downgrade(&checker.inner)));
+
+        checker.setup_signals();
+        checker
+    }
+
+    /// Findings dismissed for the text now in the view
+    pub fn set_ignored(&self, ignored: Vec<String>) {
+        self.inner.ignored.replace(ignored);
+        self.recheck();
+    }
+
+    /// Called with every finding after each check
+    pub fn connect_issues_changed<F: Fn(&[Issue]) + 'static>(&self, callback: F) {
+        self.inner.issues_changed.replace(Some(Box::new(callback)));
+    }
+
+    /// Called when a finding is dismissed, with the new ignore list
+    pub fn connect_ignores_changed<F: Fn(Vec<String>) + 'static>(&self, callback: F) {
+        self.inner.ignores_changed.replace(Some(Box::new(callback)));
+    }
+
+    /// Check the whole text now, redrawing the underlines
+    pub fn recheck(&self) {
+        self.cancel_pending();
+
+        let buffer = self.inner.text_view.buffer();
+        let text = buffer.text(&buffer.start_iter(), &buffer.end_iter(), false);
+        let issues = linter().check(&text, &self.inner.ignored.borrow());
+
+        buffer.remove_tag_by_name(TAG_NAME, &buffer.start_iter(), &buffer.end_iter());
+        if style_underlines() {
+            for issue in &issues {
+                buffer.apply_tag_by_name(
+                    TAG_NAME,
+                    &buffer.iter_at_offset(issue.range.start as i32),
+                    &buffer.iter_at_offset(issue.range.end as i32),
+                );
+            }
+        }
+        self.inner.issues.replace(issues);
+
+        if let Some(ref callback) = *self.inner.issues_changed.borrow() {
+            callback(&self.inner.issues.borrow());
+        }
+    }
+
+    /// Stop flagging this text for this rule, here and anywhere else it turns up
+    pub fn ignore(&self, issue: &Issue) {
+        let ignored = {
+            let mut ignored = self.inner.ignored.borrow_mut();
+            let key = issue.key();
+            if !ignored.contains(&key) {
+                ignored.push(key);
+            }
+            ignored.clone()
+        };
+        self.recheck();
+
+        if let Some(ref callback) = *self.inner.ignores_changed.borrow() {
+            callback(ignored);
+        }
+    }
+
+    /// Select a finding in the view and scroll to it
+    pub fn select(&self, issue: &Issue) {
+        let text_view = &self.inner.text_view;
+        let buffer = text_view.buffer();
+        let mut start = buffer.iter_at_offset(issue.range.start as i32);
+        let end = buffer.iter_at_offset(issue.range.end as i32);
+        buffer.select_range(&start, &end);
+        text_view.scroll_to_iter(&mut start, 0.1, false, 0.0, 0.0);
+        text_view.grab_focus();
+    }
+
+    fn downgrade(&self) -> Weak<Inner> {
+        Rc::downgrade(&self.inner)
+    }
+
+    fn schedule(&self) {
+        self.cancel_pending();
+
+        let weak = self.downgrade();
+        let source_id = glib::timeout_add_local_once(CHECK_DELAY, move || {
+            if let Some(checker) = upgrade(&weak) {
+                checker.inner.pending.replace(None);
+                checker.recheck();
+            }
+        });
+        self.inner.pending.replace(Some(source_id));
+    }
+
+    fn cancel_pending(&self) {
+        if let Some(source_id) = self.inner.pending.take() {
+            source_id.remove();
+        }
+    }
+
+    fn setup_signals(&self) {
+        let weak = self.downgrade();
+        self.inner.text_view.buffer().connect_changed(move |_| {
+            if let Some(checker) = upgrade(&weak) {
+                checker.schedule();
+            }
+        });
+
+        // Hovering an underline says what's wrong with it
+        self.inner.text_view.set_has_tooltip(true);
+        let weak = self.downgrade();
+        self.inner.text_view.connect_query_tooltip(move |text_view, x, y, keyboard, tooltip| {
+            let checker = match upgrade(&weak) {
+                Some(checker) if style_underlines() => checker,
+                _ => return false,
+            };
+            let offset = if keyboard {
+                text_view.buffer().cursor_position()
+            } else {
+                let (x, y) = text_view.window_to_buffer_coords(gtk4::TextWindowType::Widget, x, y);
+                match text_view.iter_at_location(x, y) {
+                    Some(iter) => iter.offset(),
+                    None => return false,
+                }
+            };
+            let offset = offset as usize;
+
+            let issues = checker.inner.issues.borrow();
+            let messages: Vec<&str> = issues
+                .iter()
+                .filter(|issue| issue.range.start <= offset && offset < issue.range.end)
+                .map(|issue| issue.message.as_str())
+                .collect();
+            if messages.is_empty() {
+                return false;
+            }
+            tooltip.set_text(Some(&messages.join("\n")));
+            true
+        });
+    }
+}
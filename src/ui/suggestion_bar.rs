@@ -0,0 +1,695 @@
+use adw::subclass::prelude::*;
+use gtk4::prelude::*;
+use gtk4::{gdk, glib, pango, CompositeTemplate};
+use libadwaita as adw;
+use std::cell::{Cell, RefCell};
+use std::time::Duration;
+
+use crate::data::{Suggestion, SuggestionKind};
+
+/// How long typing pauses before suggestions catch up with the text
+const SYNC_DELAY: Duration = Duration::from_millis(300);
+
+type ChangedCallback = Box<dyn Fn(Vec<Suggestion>) + 'static>;
+
+mod imp {
+    use super::*;
+
+    #[derive(Default, CompositeTemplate)]
+    #[template(file = "suggestion_bar.ui")]
+    pub struct SuggestionBar {
+        #[template_child]
+        pub revealer: TemplateChild<gtk4::Revealer>,
+        #[template_child]
+        pub mode_btn: TemplateChild<gtk4::ToggleButton>,
+        #[template_child]
+        pub change_label: TemplateChild<gtk4::Label>,
+        #[template_child]
+        pub previous_btn: TemplateChild<gtk4::Button>,
+        #[template_child]
+        pub next_btn: TemplateChild<gtk4::Button>,
+        #[template_child]
+        pub accept_btn: TemplateChild<gtk4::Button>,
+        #[template_child]
+        pub reject_btn: TemplateChild<gtk4::Button>,
+        #[template_child]
+        pub accept_all_btn: TemplateChild<gtk4::Button>,
+        #[template_child]
+        pub reject_all_btn: TemplateChild<gtk4::Button>,
+
+        pub view: RefCell<Option<sourceview5::View>>,
+        /// Each suggestion with the tag marking its text, in document order
+        pub suggestions: RefCell<Vec<(Suggestion, gtk4::TextTag)>>,
+        /// Whether edits are recorded rather than made
+        pub suggesting: Cell<bool>,
+        /// Set while the text changes without being recorded
+        pub applying: Cell<bool>,
+        /// Set when suggestions were added or dropped since they were last
+        /// passed on
+        pub unsaved: Cell<bool>,
+        /// Who suggestions made here are from
+        pub author: RefCell<String>,
+        pub pending: RefCell<Option<glib::SourceId>>,
+        pub changed_callback: RefCell<Option<ChangedCallback>>,
+        pub mode_callback: RefCell<Option<Box<dyn Fn(bool) + 'static>>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for SuggestionBar {
+        const NAME: &'static str = "SuggestionBar";
+        type Type = super::SuggestionBar;
+        type ParentType = gtk4::Box;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+            klass.bind_template_callbacks();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    #[gtk4::template_callbacks]
+    impl SuggestionBar {
+        #[template_callback]
+        fn on_mode_toggled(&self, btn: &gtk4::ToggleButton) {
+            self.obj().mode_changed(btn.is_active());
+        }
+
+        #[template_callback]
+        fn on_previous(&self) {
+            self.obj().go(false);
+        }
+
+        #[template_callback]
+        fn on_next(&self) {
+            self.obj().go(true);
+        }
+
+        #[template_callback]
+        fn on_accept(&self) {
+            self.obj().resolve(true);
+        }
+
+        #[template_callback]
+        fn on_reject(&self) {
+            self.obj().resolve(false);
+        }
+
+        #[template_callback]
+        fn on_accept_all(&self) {
+            self.obj().resolve_all(true);
+        }
+
+        #[template_callback]
+        fn on_reject_all(&self) {
+            self.obj().resolve_all(false);
+        }
+    }
+
+    impl ObjectImpl for SuggestionBar {}
+    impl WidgetImpl for SuggestionBar {}
+    impl BoxImpl for SuggestionBar {}
+}
+
+glib::wrapper! {
+    /// Suggestion mode for a markdown editor: insertions and deletions are
+    /// marked in the text rather than made, to be accepted or rejected later
+    pub struct SuggestionBar(ObjectSubclass<imp::SuggestionBar>)
+        @extends gtk4::Box, gtk4::Widget,
+        @implements gtk4::Accessible, gtk4::Buildable;
+}
+
+impl SuggestionBar {
+    /// Record suggestions in `view`'s text from now on
+    pub fn attach(&self, view: &sourceview5::View) {
+        let buffer = view.buffer();
+        self.imp().author.replace(author());
+
+        // Inserted text is marked once it's in, when it's known where it went
+        let bar = self.downgrade();
+        buffer.connect_closure(
+            "insert-text",
+            true,
+            glib::closure_local!(move |buffer: gtk4::TextBuffer, end: gtk4::TextIter, text: String, _len: i32| {
+                if let Some(bar) = bar.upgrade() {
+                    bar.inserted(&buffer, &end, &text);
+                }
+            }),
+        );
+
+        // Deleted text is struck out instead, before it's gone
+        let bar = self.downgrade();
+        buffer.connect_delete_range(move |buffer, start, end| {
+            if let Some(bar) = bar.upgrade() {
+                bar.deleting(buffer, start, end);
+            }
+        });
+
+        let bar = self.downgrade();
+        buffer.connect_changed(move |_| {
+            if let Some(bar) = bar.upgrade() {
+                bar.schedule_sync();
+            }
+        });
+        let bar = self.downgrade();
+        buffer.connect_mark_set(move |_, _, mark| {
+            if mark.name().as_deref() == Some("insert") {
+                if let Some(bar) = bar.upgrade() {
+                    bar.update();
+                }
+            }
+        });
+
+        self.imp().view.replace(Some(view.clone()));
+        self.update();
+    }
+
+    /// Mark these suggestions in the text the view has now
+    pub fn set_suggestions(&self, suggestions: &[Suggestion]) {
+        let imp = self.imp();
+        if let Some(source_id) = imp.pending.take() {
+            source_id.remove();
+        }
+        let buffer = match imp.view.borrow().as_ref() {
+            Some(view) => view.buffer(),
+            None => return,
+        };
+
+        for (_, tag) in imp.suggestions.take() {
+            buffer.tag_table().remove(&tag);
+        }
+        let mut marked = Vec::new();
+        for suggestion in suggestions {
+            let tag = new_tag(&buffer, suggestion.kind);
+            let start = buffer.iter_at_offset(suggestion.start as i32);
+            let end = buffer.iter_at_offset(suggestion.end as i32);
+            buffer.apply_tag(&tag, &start, &end);
+            marked.push((suggestion.clone(), tag));
+        }
+        marked.sort_by_key(|(suggestion, _)| (suggestion.start, suggestion.end));
+        imp.suggestions.replace(marked);
+        imp.unsaved.set(false);
+        self.update();
+    }
+
+    /// Record edits as suggestions, or make them
+    pub fn set_suggesting(&self, suggesting: bool) {
+        self.imp().suggesting.set(suggesting);
+        self.imp().mode_btn.set_active(suggesting);
+        self.update();
+    }
+
+    pub fn is_suggesting(&self) -> bool {
+        self.imp().suggesting.get()
+    }
+
+    /// Change the text without recording it, as when loading it
+    pub fn without_recording(&self, change: impl FnOnce()) {
+        let was = self.imp().applying.replace(true);
+        change();
+        self.imp().applying.set(was);
+    }
+
+    /// The view's text as it reads before the suggested changes, with
+    /// suggested insertions left out
+    pub fn text_without_suggestions(&self) -> String {
+        let buffer = match self.imp().view.borrow().as_ref() {
+            Some(view) => view.buffer(),
+            None => return String::new(),
+        };
+        let text = buffer.text(&buffer.start_iter(), &buffer.end_iter(), false);
+        let inserted: Vec<(usize, usize)> = self
+            .imp()
+            .suggestions
+            .borrow()
+            .iter()
+            .filter(|(suggestion, _)| suggestion.kind == SuggestionKind::Insertion)
+            .flat_map(|(_, tag)| runs(&buffer, tag))
+            .map(|(start, end)| (start.offset() as usize, end.offset() as usize))
+            .collect();
+        if inserted.is_empty() {
+            return text.to_string();
+        }
+        text.chars()
+            .enumerate()
+            .filter(|(i, _)| !inserted.iter().any(|&(start, end)| start <= *i && *i < end))
+            .map(|(_, c)| c)
+            .collect()
+    }
+
+    /// Called with every suggestion when one is made, accepted or rejected,
+    /// or moves with the text
+    pub fn connect_changed<F: Fn(Vec<Suggestion>) + 'static>(&self, callback: F) {
+        self.imp().changed_callback.replace(Some(Box::new(callback)));
+    }
+
+    /// Called when suggestion mode is turned on or off from the bar
+    pub fn connect_mode_changed<F: Fn(bool) + 'static>(&self, callback: F) {
+        self.imp().mode_callback.replace(Some(Box::new(callback)));
+    }
+
+    fn mode_changed(&self, suggesting: bool) {
+        if self.imp().suggesting.replace(suggesting) == suggesting {
+            return;
+        }
+        self.update();
+        if let Some(ref callback) = *self.imp().mode_callback.borrow() {
+            callback(suggesting);
+        }
+    }
+
+    fn recording(&self) -> bool {
+        self.imp().suggesting.get() && !self.imp().applying.get()
+    }
+
+    /// Tags of the suggestions of `kind` made here
+    fn own_tags(&self, kind: SuggestionKind) -> Vec<gtk4::TextTag> {
+        let author = self.imp().author.borrow();
+        self.imp()
+            .suggestions
+            .borrow()
+            .iter()
+            .filter(|(suggestion, _)| suggestion.kind == kind && suggestion.author == *author)
+            .map(|(_, tag)| tag.clone())
+            .collect()
+    }
+
+    /// The tag for a change of `kind` to chars `start..end`: that of one made
+    /// here it runs on from, or else a new one's
+    fn tag_for(&self, buffer: &gtk4::TextBuffer, kind: SuggestionKind, start: i32, end: i32) -> gtk4::TextTag {
+        let mut before = buffer.iter_at_offset(start);
+        let after = buffer.iter_at_offset(end);
+        let has_before = before.backward_char();
+        let adjoining = self
+            .own_tags(kind)
+            .into_iter()
+            .find(|tag| (has_before && before.has_tag(tag)) || after.has_tag(tag));
+        if let Some(tag) = adjoining {
+            return tag;
+        }
+
+        let text = buffer.text(&buffer.iter_at_offset(start), &after, false).to_string();
+        let author = self.imp().author.borrow().clone();
+        let suggestion = Suggestion::new(kind, start as usize, end as usize, text, author);
+        let tag = new_tag(buffer, kind);
+        self.imp().suggestions.borrow_mut().push((suggestion, tag.clone()));
+        self.imp().unsaved.set(true);
+        tag
+    }
+
+    fn inserted(&self, buffer: &gtk4::TextBuffer, end: &gtk4::TextIter, text: &str) {
+        if !self.recording() {
+            return;
+        }
+        let end = end.offset();
+        let start = end - text.chars().count() as i32;
+        let (from, to) = (buffer.iter_at_offset(start), buffer.iter_at_offset(end));
+        // Text typed into a change isn't part of it, whatever it takes on
+        for (_, tag) in self.imp().suggestions.borrow().iter() {
+            buffer.remove_tag(tag, &from, &to);
+        }
+        let tag = self.tag_for(buffer, SuggestionKind::Insertion, start, end);
+        buffer.apply_tag(&tag, &buffer.iter_at_offset(start), &buffer.iter_at_offset(end));
+        self.schedule_sync();
+    }
+
+    fn deleting(&self, buffer: &gtk4::TextBuffer, start: &gtk4::TextIter, end: &gtk4::TextIter) {
+        if !self.recording() {
+            return;
+        }
+        let own = self.own_tags(SuggestionKind::Insertion);
+        let struck: Vec<gtk4::TextTag> = self
+            .imp()
+            .suggestions
+            .borrow()
+            .iter()
+            .filter(|(suggestion, _)| suggestion.kind == SuggestionKind::Deletion)
+            .map(|(_, tag)| tag.clone())
+            .collect();
+
+        // One's own suggested text goes for good, text already struck out
+        // stays so, and the rest is struck out
+        let (from, to) = (start.offset(), end.offset());
+        let mut runs: Vec<(i32, i32, Deleted)> = Vec::new();
+        for offset in from..to {
+            let iter = buffer.iter_at_offset(offset);
+            let deleted = if own.iter().any(|tag| iter.has_tag(tag)) {
+                Deleted::Removed
+            } else if struck.iter().any(|tag| iter.has_tag(tag)) {
+                Deleted::AlreadyStruck
+            } else {
+                Deleted::Struck
+            };
+            match runs.last_mut() {
+                Some(run) if run.2 == deleted => run.1 = offset + 1,
+                _ => runs.push((offset, offset + 1, deleted)),
+            }
+        }
+        if runs.iter().all(|run| run.2 == Deleted::Removed) {
+            return;
+        }
+
+        buffer.stop_signal_emission_by_name("delete-range");
+        let backspace = !buffer.has_selection() && buffer.cursor_position() == to;
+        for &(start, end, _) in runs.iter().filter(|run| run.2 == Deleted::Struck) {
+            let tag = self.tag_for(buffer, SuggestionKind::Deletion, start, end);
+            buffer.apply_tag(&tag, &buffer.iter_at_offset(start), &buffer.iter_at_offset(end));
+        }
+
+        // Backspace carries on before the struck text, anything else after it
+        let landing = buffer.create_mark(None, &buffer.iter_at_offset(if backspace { from } else { to }), false);
+        buffer.place_cursor(&buffer.iter_at_mark(&landing));
+
+        // One's own suggested text is taken out once this deletion is over,
+        // as a deletion of its own that the buffer's other listeners hear of
+        let removed: Vec<(gtk4::TextMark, gtk4::TextMark)> = runs
+            .iter()
+            .filter(|run| run.2 == Deleted::Removed)
+            .map(|&(start, end, _)| {
+                (
+                    buffer.create_mark(None, &buffer.iter_at_offset(start), true),
+                    buffer.create_mark(None, &buffer.iter_at_offset(end), false),
+                )
+            })
+            .collect();
+        if removed.is_empty() {
+            buffer.delete_mark(&landing);
+            self.schedule_sync();
+            return;
+        }
+        let bar = self.downgrade();
+        let buffer = buffer.clone();
+        glib::idle_add_local_once(move || {
+            let remove = || {
+                buffer.begin_user_action();
+                for (start, end) in &removed {
+                    buffer.delete(&mut buffer.iter_at_mark(start), &mut buffer.iter_at_mark(end));
+                    buffer.delete_mark(start);
+                    buffer.delete_mark(end);
+                }
+                buffer.end_user_action();
+            };
+            match bar.upgrade() {
+                Some(ref bar) => bar.without_recording(remove),
+                None => remove(),
+            }
+            buffer.place_cursor(&buffer.iter_at_mark(&landing));
+            buffer.delete_mark(&landing);
+            if let Some(bar) = bar.upgrade() {
+                bar.schedule_sync();
+            }
+        });
+    }
+
+    fn schedule_sync(&self) {
+        if let Some(source_id) = self.imp().pending.take() {
+            source_id.remove();
+        }
+        if self.imp().suggestions.borrow().is_empty() && !self.imp().unsaved.get() {
+            return;
+        }
+
+        let bar = self.downgrade();
+        let source_id = glib::timeout_add_local_once(SYNC_DELAY, move || {
+            if let Some(bar) = bar.upgrade() {
+                bar.imp().pending.replace(None);
+                bar.sync();
+            }
+        });
+        self.imp().pending.replace(Some(source_id));
+    }
+
+    /// Bring suggestions up to date with where their tags are now
+    fn sync(&self) {
+        let imp = self.imp();
+        if let Some(source_id) = imp.pending.take() {
+            source_id.remove();
+        }
+        let buffer = match imp.view.borrow().as_ref() {
+            Some(view) => view.buffer(),
+            None => return,
+        };
+
+        let mut changed = imp.unsaved.replace(false);
+        let mut kept = Vec::new();
+        for (mut suggestion, tag) in imp.suggestions.take() {
+            let runs = runs(&buffer, &tag);
+            if runs.is_empty() {
+                buffer.tag_table().remove(&tag);
+                changed = true;
+                continue;
+            }
+            for (index, (start, end)) in runs.into_iter().enumerate() {
+                let text = buffer.text(&start, &end, false).to_string();
+                let range = (start.offset() as usize, end.offset() as usize);
+                if index == 0 {
+                    if (suggestion.start, suggestion.end) != range || suggestion.text != text {
+                        (suggestion.start, suggestion.end) = range;
+                        suggestion.text = text;
+                        changed = true;
+                    }
+                    continue;
+                }
+                // Split by an edit in its middle, the rest is a change of its own
+                let mut part = Suggestion::new(suggestion.kind, range.0, range.1, text, suggestion.author.clone());
+                part.created_at = suggestion.created_at;
+                let part_tag = new_tag(&buffer, part.kind);
+                buffer.remove_tag(&tag, &start, &end);
+                buffer.apply_tag(&part_tag, &start, &end);
+                kept.push((part, part_tag));
+                changed = true;
+            }
+            kept.push((suggestion, tag));
+        }
+        kept.sort_by_key(|(suggestion, _)| (suggestion.start, suggestion.end));
+        imp.suggestions.replace(kept);
+
+        if changed {
+            if let Some(ref callback) = *imp.changed_callback.borrow() {
+                let suggestions = imp.suggestions.borrow().iter().map(|(suggestion, _)| suggestion.clone()).collect();
+                callback(suggestions);
+            }
+        }
+        self.update();
+    }
+
+    /// The change the cursor is in or at the end of
+    fn at_cursor(&self) -> Option<usize> {
+        let buffer = self.imp().view.borrow().as_ref()?.buffer();
+        let cursor = buffer.iter_at_mark(&buffer.get_insert());
+        let mut before = cursor;
+        let has_before = before.backward_char();
+        self.imp()
+            .suggestions
+            .borrow()
+            .iter()
+            .position(|(_, tag)| cursor.has_tag(tag) || (has_before && before.has_tag(tag)))
+    }
+
+    /// The change the cursor is at, else the next one
+    fn target(&self) -> Option<usize> {
+        self.at_cursor().or_else(|| {
+            let buffer = self.imp().view.borrow().as_ref()?.buffer();
+            let cursor = buffer.cursor_position() as usize;
+            let suggestions = self.imp().suggestions.borrow();
+            let next = suggestions.iter().position(|(suggestion, _)| suggestion.start >= cursor);
+            next.or((!suggestions.is_empty()).then_some(0))
+        })
+    }
+
+    fn update(&self) {
+        let imp = self.imp();
+        let count = imp.suggestions.borrow().len();
+        imp.revealer.set_reveal_child(imp.suggesting.get() || count > 0);
+
+        let label = match self.at_cursor() {
+            Some(index) => {
+                let (suggestion, _) = imp.suggestions.borrow()[index].clone();
+                format!(
+                    "{} suggested {} “{}” · {}",
+                    suggestion.author,
+                    match suggestion.kind {
+                        SuggestionKind::Insertion => "adding",
+                        SuggestionKind::Deletion => "deleting",
+                    },
+                    suggestion.text.lines().next().unwrap_or_default(),
+                    suggestion.created_at.with_timezone(&chrono::Local).format("%b %d, %H:%M")
+                )
+            }
+            None => match count {
+                0 => "Edits are marked for review instead of being made".to_string(),
+                1 => "1 suggested change".to_string(),
+                n => format!("{} suggested changes", n),
+            },
+        };
+        imp.change_label.set_text(&label);
+        imp.change_label.set_tooltip_text(Some(&label));
+
+        let editable = imp.view.borrow().as_ref().is_some_and(|view| view.is_editable());
+        imp.mode_btn.set_sensitive(editable);
+        imp.previous_btn.set_sensitive(count > 0);
+        imp.next_btn.set_sensitive(count > 0);
+        for btn in [&imp.accept_btn, &imp.reject_btn, &imp.accept_all_btn, &imp.reject_all_btn] {
+            btn.set_sensitive(editable && count > 0);
+        }
+    }
+
+    /// Select the next change after the cursor, or the one before it
+    fn go(&self, forward: bool) {
+        self.sync();
+        let buffer = match self.imp().view.borrow().as_ref() {
+            Some(view) => view.buffer(),
+            None => return,
+        };
+        let cursor = buffer.cursor_position() as usize;
+        let index = {
+            let suggestions = self.imp().suggestions.borrow();
+            let starts: Vec<usize> = suggestions.iter().map(|(suggestion, _)| suggestion.start).collect();
+            if forward {
+                starts.iter().position(|&start| start > cursor).or((!starts.is_empty()).then_some(0))
+            } else {
+                starts.iter().rposition(|&start| start < cursor).or(starts.len().checked_sub(1))
+            }
+        };
+        if let Some(index) = index {
+            self.select(index);
+        }
+    }
+
+    fn select(&self, index: usize) {
+        let view = match self.imp().view.borrow().clone() {
+            Some(view) => view,
+            None => return,
+        };
+        let (start, end) = match self.imp().suggestions.borrow().get(index) {
+            Some((suggestion, _)) => (suggestion.start, suggestion.end),
+            None => return,
+        };
+        let buffer = view.buffer();
+        let mut from = buffer.iter_at_offset(start as i32);
+        buffer.select_range(&from, &buffer.iter_at_offset(end as i32));
+        view.scroll_to_iter(&mut from, 0.1, false, 0.0, 0.0);
+    }
+
+    /// Make a suggestion part of the text, or take it out
+    fn apply(&self, buffer: &gtk4::TextBuffer, suggestion: &Suggestion, tag: &gtk4::TextTag, accept: bool) {
+        // Accepting a deletion and rejecting an insertion both remove the text
+        let remove = accept == (suggestion.kind == SuggestionKind::Deletion);
+        for (start, end) in runs(buffer, tag).into_iter().rev() {
+            if remove {
+                let (start, end) = (start.offset(), end.offset());
+                self.without_recording(|| {
+                    buffer.delete(&mut buffer.iter_at_offset(start), &mut buffer.iter_at_offset(end));
+                });
+            } else {
+                buffer.remove_tag(tag, &start, &end);
+            }
+        }
+        buffer.tag_table().remove(tag);
+    }
+
+    /// Accept or reject the change at the cursor and select the next
+    fn resolve(&self, accept: bool) {
+        self.sync();
+        let imp = self.imp();
+        let buffer = match imp.view.borrow().as_ref() {
+            Some(view) if view.is_editable() => view.buffer(),
+            _ => return,
+        };
+        let index = match self.target() {
+            Some(index) => index,
+            None => return,
+        };
+
+        let (suggestion, tag) = imp.suggestions.borrow_mut().remove(index);
+        buffer.begin_user_action();
+        self.apply(&buffer, &suggestion, &tag, accept);
+        buffer.end_user_action();
+        imp.unsaved.set(true);
+        self.sync();
+
+        let next = imp.suggestions.borrow().iter().position(|(next, _)| next.start >= suggestion.start);
+        if let Some(next) = next {
+            self.select(next);
+        }
+    }
+
+    /// Accept or reject every change, as one step to undo
+    fn resolve_all(&self, accept: bool) {
+        self.sync();
+        let imp = self.imp();
+        let buffer = match imp.view.borrow().as_ref() {
+            Some(view) if view.is_editable() => view.buffer(),
+            _ => return,
+        };
+
+        let suggestions = imp.suggestions.take();
+        buffer.begin_user_action();
+        for (suggestion, tag) in suggestions.iter().rev() {
+            self.apply(&buffer, suggestion, tag, accept);
+        }
+        buffer.end_user_action();
+        imp.unsaved.set(!suggestions.is_empty());
+        self.sync();
+    }
+}
+
+/// What becomes of a stretch of text deleted while suggesting
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Deleted {
+    Removed,
+    AlreadyStruck,
+    Struck,
+}
+
+/// Underlined for an insertion, struck out for a deletion
+fn new_tag(buffer: &gtk4::TextBuffer, kind: SuggestionKind) -> gtk4::TextTag {
+    let tag = match kind {
+        SuggestionKind::Insertion => gtk4::TextTag::builder()
+            .underline(pango::Underline::Single)
+            .foreground_rgba(&gdk::RGBA::new(0.15, 0.52, 0.3, 1.0))
+            .build(),
+        SuggestionKind::Deletion => gtk4::TextTag::builder()
+            .strikethrough(true)
+            .foreground_rgba(&gdk::RGBA::new(0.78, 0.16, 0.16, 1.0))
+            .build(),
+    };
+    buffer.tag_table().add(&tag);
+    tag
+}
+
+/// The stretches of text `tag` is on
+fn runs(buffer: &gtk4::TextBuffer, tag: &gtk4::TextTag) -> Vec<(gtk4::TextIter, gtk4::TextIter)> {
+    let mut runs = Vec::new();
+    let mut iter = buffer.start_iter();
+    loop {
+        if !iter.has_tag(tag) && !iter.forward_to_tag_toggle(Some(tag)) {
+            break;
+        }
+        let start = iter;
+        iter.forward_to_tag_toggle(Some(tag));
+        if iter.offset() == start.offset() {
+            break;
+        }
+        runs.push((start, iter));
+    }
+    runs
+}
+
+/// The user's name, for the suggestions they make
+fn author() -> String {
+    let name = glib::real_name().to_string_lossy().into_owned();
+    if name.is_empty() || name == "Unknown" {
+        glib::user_name().to_string_lossy().into_owned()
+    } else {
+        name
+    }
+}
+
+impl Default for SuggestionBar {
+    fn default() -> Self {
+        glib::Object::builder().build()
+    }
+}
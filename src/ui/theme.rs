@@ -36,6 +36,9 @@ impl ThemeManager {
                 self.style_manager.set_color_scheme(adw::ColorScheme::Default);
             }
         }
+        
+        // The editors' highlighting follows the theme too
+        super::editor::apply_style_scheme(theme_id);
     }
 
     fn apply_custom_css(&self, css: &str) {
@@ -193,6 +196,11 @@ const BASE_CSS: &str = r#"
 .editor-scroll undershoot.bottom {
     background: none;
 }
+
+/* Side-by-side editor and preview */
+.split > .preview-scroll {
+    border-left: 1px solid alpha(currentColor, 0.1);
+}
 "#;
 
 const SYSTEM_LIGHT_CSS: &str = r#"
@@ -0,0 +1,182 @@
+use adw::subclass::prelude::*;
+use adw::prelude::*;
+use gtk4::{glib, CompositeTemplate};
+use libadwaita as adw;
+use std::cell::RefCell;
+
+use abbey_core::utils::frequency::{Analysis, Cluster, Term};
+
+type TermCallback = Box<dyn Fn(&str) + 'static>;
+type ClusterCallback = Box<dyn Fn(&Cluster) + 'static>;
+
+mod imp {
+    use super::*;
+
+    #[derive(Default, CompositeTemplate)]
+    #[template(file = "word_analysis.ui")]
+    pub struct WordAnalysis {
+        #[template_child]
+        pub summary_label: TemplateChild<gtk4::Label>,
+        #[template_child]
+        pub words_list: TemplateChild<gtk4::ListBox>,
+        #[template_child]
+        pub phrases_list: TemplateChild<gtk4::ListBox>,
+        #[template_child]
+        pub overused_list: TemplateChild<gtk4::ListBox>,
+        #[template_child]
+        pub clusters_list: TemplateChild<gtk4::ListBox>,
+
+        pub term_activated: RefCell<Option<TermCallback>>,
+        pub cluster_activated: RefCell<Option<ClusterCallback>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for WordAnalysis {
+        const NAME: &'static str = "WordAnalysis";
+        type Type = super::WordAnalysis;
+        type ParentType = adw::Window;
+
+        fn class_init(klass: &mut Self::Class) {
+            klass.bind_template();
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for WordAnalysis {}
+    impl WidgetImpl for WordAnalysis {}
+    impl WindowImpl for WordAnalysis {}
+    impl AdwWindowImpl for WordAnalysis {}
+}
+
+glib::wrapper! {
+    pub struct WordAnalysis(ObjectSubclass<imp::WordAnalysis>)
+        @extends adw::Window, gtk4::Window, gtk4::Widget,
+        @implements gtk4::Accessible, gtk4::Buildable;
+}
+
+impl WordAnalysis {
+    /// Show what [`frequency::analyze`](abbey_core::utils::frequency::analyze)
+    /// found in `names`, the analyzed documents in order
+    pub fn new(title: &str, names: &[String], analysis: &Analysis) -> Self {
+        let dialog: Self = glib::Object::builder().property("title", title).build();
+        let imp = dialog.imp();
+
+        let documents = match names.len() {
+            1 => String::new(),
+            n => format!(" in {} compositions", n),
+        };
+        imp.summary_label.set_text(&format!("{} words{}", analysis.words, documents));
+
+        dialog.fill_terms(&imp.words_list, &analysis.top_words, "No words yet");
+        dialog.fill_terms(&imp.phrases_list, &analysis.top_phrases, "No phrases turn up more than once");
+
+        set_placeholder(
+            &imp.overused_list,
+            "Nothing stands out against the rest of the library, or there's too little of it to compare with",
+        );
+        for overused in &analysis.overused {
+            let subtitle = format!(
+                "{:.1} per thousand words, against {:.1} in the rest of the library",
+                overused.rate, overused.library_rate
+            );
+            let row = dialog.term_row(&overused.word, &subtitle, overused.count);
+            imp.overused_list.append(&row);
+        }
+
+        set_placeholder(&imp.clusters_list, "No word keeps coming back close together");
+        for cluster in &analysis.clusters {
+            let subtitle = match names.get(cluster.document) {
+                Some(name) if names.len() > 1 => format!("{} uses close together in {}", cluster.occurrences.len(), name),
+                _ => format!("{} uses close together", cluster.occurrences.len()),
+            };
+            let row = adw::ActionRow::builder()
+                .title(cluster.word.as_str())
+                .subtitle(subtitle.as_str())
+                .use_markup(false)
+                .activatable(true)
+                .build();
+            row.add_suffix(&gtk4::Image::from_icon_name("go-next-symbolic"));
+
+            let dialog_clone = dialog.clone();
+            let cluster = cluster.clone();
+            row.connect_activated(move |_| {
+                if let Some(ref callback) = *dialog_clone.imp().cluster_activated.borrow() {
+                    callback(&cluster);
+                }
+            });
+            imp.clusters_list.append(&row);
+        }
+
+        dialog
+    }
+
+    pub fn present(&self, parent: Option<&impl IsA<gtk4::Window>>) {
+        if let Some(parent) = parent {
+            self.set_transient_for(Some(parent));
+        }
+        gtk4::prelude::GtkWindowExt::present(self);
+    }
+
+    /// Called with a word or phrase picked from the lists
+    pub fn connect_term_activated<F: Fn(&str) + 'static>(&self, callback: F) {
+        self.imp().term_activated.replace(Some(Box::new(callback)));
+    }
+
+    /// Called with a repetition picked from the list
+    pub fn connect_cluster_activated<F: Fn(&Cluster) + 'static>(&self, callback: F) {
+        self.imp().cluster_activated.replace(Some(Box::new(callback)));
+    }
+
+    fn fill_terms(&self, list: &gtk4::ListBox, terms: &[Term], empty: &str) {
+        set_placeholder(list, empty);
+        for term in terms {
+            list.append(&self.term_row(&term.text, "", term.count));
+        }
+    }
+
+    fn term_row(&self, term: &str, subtitle: &str, count: usize) -> adw::ActionRow {
+        let row = adw::ActionRow::builder()
+            .title(term)
+            .subtitle(subtitle)
+            .use_markup(false)
+            .activatable(true)
+            .build();
+        let count = gtk4::Label::builder()
+            .label(count.to_string())
+            .css_classes(["numeric", "dim-label"])
+            .build();
+        row.add_suffix(&count);
+
+        let dialog = self.clone();
+        let term = term.to_string();
+        row.connect_activated(move |_| {
+            if let Some(ref callback) = *dialog.imp().term_activated.borrow() {
+                callback(&term);
+            }
+        });
+        row
+    }
+}
+
+fn set_placeholder(list: &gtk4::ListBox, text: &str) {
+    let label = gtk4::Label::builder()
+        .label(text)
+        .wrap(true)
+        .justify(gtk4::Justification::Center)
+        .margin_start(12)
+        .margin_end(12)
+        .margin_top(24)
+        .margin_bottom(24)
+        .css_classes(["dim-label"])
+        .build();
+    list.set_placeholder(Some(&label));
+}
+
+impl Default for WordAnalysis {
+    fn default() -> Self {
+        glib::Object::builder().build()
+    }
+}
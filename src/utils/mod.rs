@@ -1,5 +0,0 @@
-pub mod markdown;
-
-// Markdown utilities available for future use
-#[allow(unused_imports)]
-pub use markdown::*;